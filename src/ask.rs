@@ -0,0 +1,233 @@
+use anyhow::{Context, Result};
+use clap::Parser;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use tokio::fs;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tracing::{Instrument, info, info_span, warn};
+
+use transcript_tool::cli::{self, CommonArgs, init_logging};
+use transcript_tool::transcript_qa::DEFAULT_CONTEXT_TTL_SECS;
+use transcript_tool::{
+    Answer, Clients, TextClientConfig, TextError, TextModel, TranscriptQa, TranscriptResponse,
+};
+
+#[derive(Parser, Debug)]
+#[command(name = "ask")]
+#[command(version)]
+#[command(about = "Answer questions about a transcript, citing the segments the answer comes from")]
+#[command(after_help = "EXAMPLES:
+    ask meeting.json \"What did the CTO commit to?\"
+    ask meeting.json \"Who owns the launch?\" \"When is the deadline?\"
+    ask meeting.json --context-cache            (questions from stdin, one per line)
+    ask meeting.json \"Any risks?\" --json")]
+pub struct Args {
+    /// Transcript JSON written by `convert` or `batch_convert`
+    #[arg(value_name = "TRANSCRIPT")]
+    input: PathBuf,
+
+    /// Questions to answer; without any, questions are read from stdin, one per line
+    #[arg(value_name = "QUESTION")]
+    questions: Vec<String>,
+
+    /// Store the transcript with Gemini context caching, so each question
+    /// only sends itself; reused by later runs on the same file
+    #[arg(long)]
+    context_cache: bool,
+
+    /// Minutes the API keeps a cached transcript
+    #[arg(long, value_name = "MINUTES", default_value_t = DEFAULT_CONTEXT_TTL_SECS / 60)]
+    context_ttl: u64,
+
+    /// Print one JSON object per answer
+    #[arg(long)]
+    json: bool,
+
+    /// Text model: flash (default), flash-lite, pro
+    #[arg(
+        short = 'm',
+        long,
+        env = "FLASHECHO_TEXT_MODEL",
+        default_value = "flash"
+    )]
+    model: String,
+
+    /// Timeout of each API request in seconds
+    #[arg(short, long, default_value = "120")]
+    timeout: u64,
+
+    #[command(flatten)]
+    common: CommonArgs,
+}
+
+/// Answer followed by one indented `[MM:SS] Speaker: "quote"` line per citation
+fn format_answer(answer: &Answer) -> String {
+    let mut text = answer.answer.trim().to_string();
+    for citation in &answer.citations {
+        text.push_str(&format!(
+            "\n  [{}] {}: \"{}\"",
+            citation.timestamp,
+            citation.speaker,
+            citation.quote.trim()
+        ));
+    }
+    text
+}
+
+/// Run with parsed arguments; shared by this binary and its `flashecho` subcommand
+pub async fn run(args: Args) -> Result<()> {
+    init_logging(args.common.verbose, args.common.log_format);
+    args.common.install_usage_ledger("ask");
+
+    let span = info_span!("file", file = %args.input.display());
+    ask(args).instrument(span).await
+}
+
+async fn ask(args: Args) -> Result<()> {
+    let model: TextModel = args.model.parse().map_err(|e: String| anyhow::anyhow!(e))?;
+    let content = fs::read_to_string(&args.input)
+        .await
+        .with_context(|| format!("Failed to read transcript {:?}", args.input))?;
+    let transcript = TranscriptResponse::from_json_any_version(&content)
+        .context("Failed to parse transcript JSON")?;
+    if transcript.segments.is_empty() {
+        anyhow::bail!("Transcript has no segments");
+    }
+
+    let profile = args.common.load_profile()?;
+    let clients =
+        Clients::new(cli::gemini_api_key(&profile)?).context("Failed to create HTTP client")?;
+    let budget = args
+        .common
+        .budget()
+        .context("Failed to read usage ledger for --budget-since")?;
+    let client = clients.text(TextClientConfig {
+        timeout_secs: args.timeout,
+        max_retries: args.common.max_retries,
+        model,
+        cache: args.common.response_cache(),
+        budget,
+        ..Default::default()
+    });
+    let mut qa = TranscriptQa::new(client, &transcript);
+
+    if args.context_cache {
+        match qa
+            .cache_context(args.context_ttl * 60)
+            .instrument(info_span!("stage", stage = "context_cache"))
+            .await
+        {
+            Ok(cached) => info!(
+                "Transcript cached as {} ({} tokens)",
+                cached.name,
+                cached.token_count.unwrap_or_default()
+            ),
+            Err(e @ TextError::BudgetExceeded(_)) => return Err(e.into()),
+            // Usually a transcript below the model's minimum cache size
+            Err(e) => {
+                warn!("Context caching unavailable: {}", e);
+                if !args.common.quiet {
+                    eprintln!(
+                        "Context caching unavailable ({}); sending the transcript with each question",
+                        e
+                    );
+                }
+            }
+        }
+    }
+
+    if !args.questions.is_empty() {
+        let show_question = args.questions.len() > 1;
+        for question in &args.questions {
+            let answer = answer_question(&qa, question, &args)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to answer {:?}: {}", question, e))?;
+            print_answer(question, &answer, show_question, args.json)?;
+        }
+        return Ok(());
+    }
+
+    // Questions from stdin until EOF; a failed question doesn't end the session
+    let interactive = std::io::stdin().is_terminal();
+    if interactive && !args.common.quiet {
+        eprintln!("Ask about {} (Ctrl-D to quit)", args.input.display());
+    }
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    loop {
+        if interactive {
+            eprint!("> ");
+        }
+        let Some(line) = lines.next_line().await.context("Failed to read stdin")? else {
+            break;
+        };
+        let question = line.trim();
+        if question.is_empty() {
+            continue;
+        }
+        match answer_question(&qa, question, &args).await {
+            Ok(answer) => print_answer(question, &answer, !interactive, args.json)?,
+            Err(e @ TextError::BudgetExceeded(_)) => return Err(e.into()),
+            Err(e) => eprintln!("Failed to answer {:?}: {}", question, e),
+        }
+    }
+    Ok(())
+}
+
+async fn answer_question(
+    qa: &TranscriptQa,
+    question: &str,
+    args: &Args,
+) -> std::result::Result<Answer, TextError> {
+    let pb = cli::spinner(
+        args.common.quiet || args.json,
+        "Reading the transcript...".into(),
+    );
+    let result = qa
+        .ask(question)
+        .instrument(info_span!("stage", stage = "ask"))
+        .await;
+    if let Some(pb) = pb {
+        pb.finish_and_clear();
+    }
+    result
+}
+
+fn print_answer(question: &str, answer: &Answer, show_question: bool, json: bool) -> Result<()> {
+    if json {
+        let line = serde_json::json!({
+            "question": question,
+            "answer": answer.answer,
+            "citations": answer.citations,
+        });
+        println!("{}", serde_json::to_string(&line)?);
+        return Ok(());
+    }
+    if show_question {
+        println!("Q: {}", question);
+    }
+    println!("{}\n", format_answer(answer));
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    run(Args::parse()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_answer() {
+        let answer: Answer = serde_json::from_value(serde_json::json!({
+            "answer": "To hire two engineers. ",
+            "citations": [{"timestamp": "01:05", "speaker": "CTO", "quote": "I'll hire two engineers."}]
+        }))
+        .unwrap();
+        assert_eq!(
+            format_answer(&answer),
+            "To hire two engineers.\n  [01:05] CTO: \"I'll hire two engineers.\""
+        );
+    }
+}
@@ -0,0 +1,74 @@
+//! Host and authentication shared by every client of the Gemini API host
+//! (transcription, File API, image generation and editing).
+
+#[cfg(feature = "__client")]
+use reqwest::header::HeaderValue;
+#[cfg(feature = "__client")]
+use reqwest::{Client, Method, RequestBuilder};
+
+#[cfg(feature = "__client")]
+use crate::redact::ApiKey;
+
+pub const GEMINI_BASE_URL: &str = "https://generativelanguage.googleapis.com";
+
+/// How the API key is sent to the Gemini API
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AuthMode {
+    /// `x-goog-api-key` header, which keeps the key out of URLs and proxy logs
+    #[default]
+    Header,
+    /// Legacy `?key=` query parameter, for gateways that drop custom headers
+    QueryString,
+}
+
+#[cfg(feature = "__client")]
+impl AuthMode {
+    /// Start a request to `url` authenticated with `api_key`
+    pub(crate) fn request(
+        self,
+        client: &Client,
+        method: Method,
+        url: &str,
+        api_key: &ApiKey,
+    ) -> RequestBuilder {
+        match self {
+            AuthMode::Header => {
+                let request = client.request(method, url);
+                match HeaderValue::from_str(api_key.expose()) {
+                    // Sensitive values are left out of `Debug` output
+                    Ok(mut value) => {
+                        value.set_sensitive(true);
+                        request.header("x-goog-api-key", value)
+                    }
+                    // Let reqwest fail the request over the invalid header
+                    Err(_) => request.header("x-goog-api-key", api_key.expose()),
+                }
+            }
+            AuthMode::QueryString => {
+                let separator = if url.contains('?') { '&' } else { '?' };
+                client.request(
+                    method,
+                    format!("{}{}key={}", url, separator, api_key.expose()),
+                )
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "__client"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_key_is_sensitive() {
+        let key = ApiKey::from("secret-key".to_string());
+        let request = AuthMode::Header
+            .request(&Client::new(), Method::GET, "https://example.com", &key)
+            .build()
+            .unwrap();
+        let header = &request.headers()["x-goog-api-key"];
+        assert!(header.is_sensitive());
+        assert_eq!(header, "secret-key");
+        assert!(!format!("{:?}", request).contains("secret-key"));
+    }
+}
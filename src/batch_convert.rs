@@ -1,41 +1,59 @@
 use anyhow::{Context, Result};
-use clap::{Parser, ValueEnum};
+use bytes::Bytes;
+use clap::Parser;
+use futures::StreamExt;
 use indicatif::{ProgressBar, ProgressStyle};
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
 use tokio::fs;
-use tokio::process::Command;
+use tokio::io::AsyncReadExt;
 use tokio::sync::Semaphore;
-use tracing::{Level, debug, info, warn};
-use tracing_subscriber::FmtSubscriber;
+use tracing::{Instrument, debug, info, info_span, warn};
 use walkdir::WalkDir;
 
+use transcript_tool::cli::{
+    self, BatchLimitArgs, CommonArgs, FfmpegArgs, GenerationArgs, PromptArgs, SubtitleArgs,
+    init_logging,
+};
+use transcript_tool::config::{self, Profile};
+use transcript_tool::storage::{LocalStorage, OutputStorage, Storage};
 use transcript_tool::{
-    FileApiClient, GeminiClient, GeminiClientConfig, MAX_INLINE_FILE_SIZE, TranscriptResponse,
+    CircuitBreaker, FileApiClient, GeminiClient, GeminiClientConfig, MAX_INLINE_FILE_SIZE,
+    OpenAiClient, OpenAiClientConfig, OutputFormat, Provider, SubtitleOptions, TranscriptResponse,
+    TranscriptionBackend, format_transcript_with, scaled_timeout_secs, transcript_to_html_with,
 };
 
-#[derive(Debug, Clone, Copy, ValueEnum, Default)]
-enum OutputFormat {
-    #[default]
-    Json,
-    Srt,
-    Vtt,
-    Txt,
-}
-
 #[derive(Parser, Debug)]
 #[command(name = "batch_convert")]
 #[command(version)]
-#[command(about = "Batch convert video/audio files to transcripts using Gemini API")]
-struct Args {
-    /// Folder paths to process (recursive)
-    #[arg(required = true)]
-    folders: Vec<PathBuf>,
-
-    /// Output format
-    #[arg(short, long, value_enum, default_value = "json")]
-    format: OutputFormat,
+#[command(about = "Batch convert video/audio files to transcripts using Gemini or OpenAI")]
+#[command(after_help = "EXAMPLES:
+    batch_convert ./videos -j 4
+    batch_convert ./talks keynote.mp4 panel.mkv
+    find . -name '*.mp4' -newer last-run | batch_convert -
+    fzf -m | batch_convert - -f srt")]
+pub struct Args {
+    /// Folders to process (recursive) or media files; `-` reads more paths
+    /// from stdin, one per line
+    #[arg(value_name = "PATH", required = true)]
+    paths: Vec<PathBuf>,
+
+    /// Output format [default: json]
+    #[arg(short, long, value_enum, env = "FLASHECHO_FORMAT")]
+    format: Option<OutputFormat>,
+
+    /// Also write each transcript in these formats, next to the main one
+    /// under each format's extension (comma-separated or repeated)
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "FORMAT")]
+    also_format: Vec<OutputFormat>,
+
+    /// Write transcripts under this directory or s3:// / gs:// URI, mirroring
+    /// each input folder's layout [default: next to each input file]
+    #[arg(long, value_name = "DIR|URI")]
+    output_dir: Option<String>,
 
     /// Number of parallel jobs
     #[arg(short, long, default_value = "2")]
@@ -49,42 +67,78 @@ struct Args {
     #[arg(short, long, default_value = "false")]
     keep_audio: bool,
 
-    /// Gemini model to use
-    #[arg(long, default_value = "gemini-2.5-flash")]
-    model: String,
+    /// Also write a `<transcript>.blake3` checksum sidecar, checked by `verify`
+    #[arg(long)]
+    checksums: bool,
 
-    /// API timeout in seconds
-    #[arg(long, default_value = "600")]
-    timeout: u64,
+    /// List the files with their durations and the estimated cost, then stop
+    /// without extracting, uploading or transcribing anything
+    #[arg(long)]
+    dry_run: bool,
 
-    /// Max retry attempts for API calls
-    #[arg(long, default_value = "3")]
-    max_retries: u32,
+    /// Transcription provider [default: gemini]
+    #[arg(long, value_enum, env = "FLASHECHO_PROVIDER")]
+    provider: Option<Provider>,
 
-    /// Verbosity level (-v, -vv, -vvv)
-    #[arg(short, long, action = clap::ArgAction::Count)]
-    verbose: u8,
+    /// Model to use (defaults to gemini-2.5-flash for Gemini, whisper-1 for OpenAI)
+    #[arg(long, env = "FLASHECHO_MODEL")]
+    model: Option<String>,
 
-    /// Quiet mode (no progress output)
-    #[arg(short, long)]
-    quiet: bool,
+    /// API timeout in seconds [default: scaled with each file's length, from
+    /// 2 minutes to an hour]
+    #[arg(long, value_name = "SECONDS")]
+    timeout: Option<u64>,
 
-    /// Force use of File API even for small files
+    /// Force use of File API even for small files (Gemini only)
     #[arg(long)]
     force_file_api: bool,
 
     /// Keep uploaded file on server (don't delete after transcription)
     #[arg(long)]
     keep_remote_file: bool,
+
+    /// Also list topics and keywords with the timestamp of their first
+    /// mention (Gemini only)
+    #[arg(long)]
+    topics: bool,
+
+    /// Number of people who speak; the model labels exactly this many
+    /// speakers instead of guessing (Gemini only)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    num_speakers: Option<u32>,
+
+    /// Also translate every segment into these languages, e.g. `es,fr,de`;
+    /// subtitle and text formats get a file per language next to the main
+    /// output, JSON keeps all of them per segment (Gemini only)
+    #[arg(long, value_delimiter = ',', value_name = "LANGUAGES")]
+    translate_to: Vec<String>,
+
+    #[command(flatten)]
+    prompt: PromptArgs,
+
+    #[command(flatten)]
+    generation: GenerationArgs,
+
+    #[command(flatten)]
+    subtitles: SubtitleArgs,
+
+    #[command(flatten)]
+    ffmpeg: FfmpegArgs,
+
+    #[command(flatten)]
+    common: CommonArgs,
+
+    #[command(flatten)]
+    limits: BatchLimitArgs,
 }
 
 const VIDEO_EXTENSIONS: &[&str] = &["mp4", "mkv", "avi", "mov", "webm", "flv", "wmv", "m4v"];
 const AUDIO_EXTENSIONS: &[&str] = &["mp3", "wav", "ogg", "flac", "m4a", "aac", "wma"];
 
-fn get_api_key() -> Result<String> {
-    std::env::var("GEMINI_API_KEY")
-        .or_else(|_| std::env::var("GOOGLE_AI_KEY"))
-        .context("GEMINI_API_KEY or GOOGLE_AI_KEY environment variable is not set")
+fn get_api_key(provider: Provider, profile: &Profile) -> Result<String> {
+    let vars = provider.api_key_vars();
+    cli::api_key(vars, profile.api_key(provider), provider.keyring_account())
+        .with_context(|| cli::missing_api_key(vars))
 }
 
 fn is_media_file(path: &Path) -> bool {
@@ -105,31 +159,84 @@ fn is_audio_file(path: &Path) -> bool {
         .unwrap_or(false)
 }
 
-fn find_media_files(folders: &[PathBuf]) -> Vec<PathBuf> {
+/// Paths listed one per line, as `find` or `fzf -m` print them; spaces are
+/// part of a path, blank lines are skipped
+fn parse_path_list(content: &str) -> Vec<PathBuf> {
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// `paths` with each `-` replaced by the paths listed on stdin
+async fn expand_stdin(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    if !paths.iter().any(|path| path == Path::new("-")) {
+        return Ok(paths.to_vec());
+    }
+    let mut content = String::new();
+    tokio::io::stdin()
+        .read_to_string(&mut content)
+        .await
+        .context("Failed to read paths from stdin")?;
+    let listed = parse_path_list(&content);
+    debug!("Read {} paths from stdin", listed.len());
+
+    let mut expanded = Vec::new();
+    let mut stdin_used = false;
+    for path in paths {
+        if path == Path::new("-") {
+            if !stdin_used {
+                expanded.extend(listed.iter().cloned());
+                stdin_used = true;
+            }
+        } else {
+            expanded.push(path.clone());
+        }
+    }
+    Ok(expanded)
+}
+
+/// Media files under the folders in `paths` and the media files among them,
+/// each with the folder its output key is relative to: the folder it was
+/// found in, or the parent of a file named directly. A file reached twice is
+/// listed once.
+fn find_media_files(paths: &[PathBuf]) -> Vec<(PathBuf, PathBuf)> {
     let mut files = Vec::new();
-    for folder in folders {
-        if !folder.exists() {
-            warn!("Folder does not exist: {:?}", folder);
+    let mut seen = HashSet::new();
+    for path in paths {
+        if !path.exists() {
+            warn!("Path does not exist: {:?}", path);
             continue;
         }
-        for entry in WalkDir::new(folder)
+        if path.is_file() {
+            if !is_media_file(path) {
+                warn!("Skipping {:?}: not a supported media file", path);
+            } else if seen.insert(path.clone()) {
+                let folder = path.parent().unwrap_or(Path::new("")).to_path_buf();
+                files.push((path.clone(), folder));
+            }
+            continue;
+        }
+        for entry in WalkDir::new(path)
             .follow_links(true)
             .into_iter()
             .filter_map(|e| e.ok())
         {
-            let path = entry.path();
-            if path.is_file() && is_media_file(path) {
-                files.push(path.to_path_buf());
+            let file = entry.path();
+            if file.is_file() && is_media_file(file) && seen.insert(file.to_path_buf()) {
+                files.push((file.to_path_buf(), path.clone()));
             }
         }
     }
     files
 }
 
-async fn extract_audio_with_ffmpeg(input: &Path, output: &Path) -> Result<()> {
+async fn extract_audio_with_ffmpeg(ffmpeg: &FfmpegArgs, input: &Path, output: &Path) -> Result<()> {
     debug!("Extracting audio from {:?} to {:?}", input, output);
 
-    let output_result = Command::new("ffmpeg")
+    let output_result = ffmpeg
+        .command()
         .args([
             "-i",
             input.to_str().context("Invalid input path")?,
@@ -143,7 +250,7 @@ async fn extract_audio_with_ffmpeg(input: &Path, output: &Path) -> Result<()> {
         ])
         .output()
         .await
-        .context("Failed to execute ffmpeg. Is ffmpeg installed?")?;
+        .with_context(|| ffmpeg.missing())?;
 
     if !output_result.status.success() {
         let stderr = String::from_utf8_lossy(&output_result.stderr);
@@ -153,130 +260,124 @@ async fn extract_audio_with_ffmpeg(input: &Path, output: &Path) -> Result<()> {
     Ok(())
 }
 
-fn format_timestamp_srt(timestamp: &str) -> String {
-    let parts: Vec<&str> = timestamp.split(':').collect();
-    if parts.len() == 2 {
-        format!("00:{}:{},000", parts[0], parts[1])
-    } else {
-        format!("00:{},000", timestamp)
-    }
+/// Where one input's transcript goes
+struct TranscriptOutput {
+    storage: OutputStorage,
+    key: String,
+    /// `--also-format` outputs written with the main one
+    also: Vec<(OutputFormat, String)>,
+    /// `--translate-to` languages, each written to its own file
+    languages: Vec<String>,
+    input: PathBuf,
 }
 
-fn format_timestamp_vtt(timestamp: &str) -> String {
-    let parts: Vec<&str> = timestamp.split(':').collect();
-    if parts.len() == 2 {
-        format!("00:{}:{}.000", parts[0], parts[1])
-    } else {
-        format!("00:{}.000", timestamp)
+impl TranscriptOutput {
+    /// Under `output_dir` at the input's path relative to `folder`, or next
+    /// to the input without one
+    fn new(
+        input: &Path,
+        folder: &Path,
+        format: OutputFormat,
+        output_dir: Option<&OutputStorage>,
+    ) -> Self {
+        match output_dir {
+            Some(storage) => Self {
+                storage: storage.clone(),
+                key: transcript_key(input.strip_prefix(folder).unwrap_or(input), format),
+                also: Vec::new(),
+                languages: Vec::new(),
+                input: input.to_path_buf(),
+            },
+            None => Self {
+                storage: OutputStorage::Local(LocalStorage::new(
+                    input.parent().unwrap_or(Path::new("")),
+                )),
+                key: transcript_key(Path::new(input.file_name().unwrap_or_default()), format),
+                also: Vec::new(),
+                languages: Vec::new(),
+                input: input.to_path_buf(),
+            },
+        }
     }
-}
 
-fn transcript_to_srt(transcript: &TranscriptResponse) -> String {
-    let mut output = String::new();
-    for (i, segment) in transcript.segments.iter().enumerate() {
-        let start = format_timestamp_srt(&segment.timestamp);
-        let end = if i + 1 < transcript.segments.len() {
-            format_timestamp_srt(&transcript.segments[i + 1].timestamp)
-        } else {
-            let parts: Vec<&str> = segment.timestamp.split(':').collect();
-            if parts.len() == 2 {
-                let mins: u32 = parts[0].parse().unwrap_or(0);
-                let secs: u32 = parts[1].parse().unwrap_or(0) + 5;
-                let new_mins = mins + secs / 60;
-                let new_secs = secs % 60;
-                format!("00:{:02}:{:02},000", new_mins, new_secs)
-            } else {
-                "00:00:05,000".to_string()
-            }
-        };
-        output.push_str(&format!("{}\n", i + 1));
-        output.push_str(&format!("{} --> {}\n", start, end));
-        output.push_str(&format!("[{}] {}\n\n", segment.speaker, segment.content));
+    fn with_also_formats(mut self, format: OutputFormat, also: &[OutputFormat]) -> Self {
+        self.also = cli::also_format_keys(&self.key, format, also);
+        self
     }
-    output
-}
 
-fn transcript_to_vtt(transcript: &TranscriptResponse) -> String {
-    let mut output = String::from("WEBVTT\n\n");
-    for (i, segment) in transcript.segments.iter().enumerate() {
-        let start = format_timestamp_vtt(&segment.timestamp);
-        let end = if i + 1 < transcript.segments.len() {
-            format_timestamp_vtt(&transcript.segments[i + 1].timestamp)
-        } else {
-            let parts: Vec<&str> = segment.timestamp.split(':').collect();
-            if parts.len() == 2 {
-                let mins: u32 = parts[0].parse().unwrap_or(0);
-                let secs: u32 = parts[1].parse().unwrap_or(0) + 5;
-                let new_mins = mins + secs / 60;
-                let new_secs = secs % 60;
-                format!("00:{:02}:{:02}.000", new_mins, new_secs)
-            } else {
-                "00:00:05.000".to_string()
-            }
-        };
-        output.push_str(&format!("{} --> {}\n", start, end));
-        output.push_str(&format!("<v {}>{}\n\n", segment.speaker, segment.content));
+    fn with_translations(mut self, languages: &[String]) -> Self {
+        self.languages = languages.to_vec();
+        self
     }
-    output
 }
 
-fn transcript_to_txt(transcript: &TranscriptResponse) -> String {
-    let mut output = String::new();
-    output.push_str(&format!("Summary:\n{}\n\n", transcript.summary));
-    output.push_str("---\n\n");
-    for segment in &transcript.segments {
-        output.push_str(&format!(
-            "[{}] {} ({})\n{}\n",
-            segment.timestamp, segment.speaker, segment.emotion, segment.content
-        ));
-        if let Some(ref translation) = segment.translation
-            && !translation.is_empty()
-        {
-            output.push_str(&format!("  Translation: {}\n", translation));
-        }
-        output.push('\n');
-    }
-    output
+/// ffprobe runs at once while probing durations
+const PROBE_JOBS: usize = 8;
+
+/// Duration of each file in seconds, in order, `None` where ffprobe can't tell
+async fn probe_durations(ffmpeg: &FfmpegArgs, files: &[(PathBuf, PathBuf)]) -> Vec<Option<f64>> {
+    futures::stream::iter(files)
+        .map(|(file, _)| ffmpeg.probe_duration(file))
+        .buffered(PROBE_JOBS)
+        .collect()
+        .await
 }
 
-fn format_output(transcript: &TranscriptResponse, format: OutputFormat) -> Result<String> {
-    match format {
-        OutputFormat::Json => {
-            serde_json::to_string_pretty(transcript).context("Failed to serialize to JSON")
-        }
-        OutputFormat::Srt => Ok(transcript_to_srt(transcript)),
-        OutputFormat::Vtt => Ok(transcript_to_vtt(transcript)),
-        OutputFormat::Txt => Ok(transcript_to_txt(transcript)),
+/// Progress bar weight of each file: its whole seconds, or the average of
+/// the known durations when unknown. All ones, so the bar counts files, when
+/// no duration is known.
+fn progress_weights(durations: &[Option<f64>]) -> Vec<u64> {
+    let known: Vec<f64> = durations.iter().flatten().copied().collect();
+    if known.is_empty() {
+        return vec![1; durations.len()];
     }
+    let average = known.iter().sum::<f64>() / known.len() as f64;
+    durations
+        .iter()
+        .map(|duration| (duration.unwrap_or(average).round() as u64).max(1))
+        .collect()
 }
 
-fn get_output_extension(format: OutputFormat) -> &'static str {
-    match format {
-        OutputFormat::Json => "json",
-        OutputFormat::Srt => "srt",
-        OutputFormat::Vtt => "vtt",
-        OutputFormat::Txt => "txt",
+/// `--dry-run`: list the files with their durations and estimate the cost of
+/// the ones without a transcript
+async fn dry_run(
+    args: &Args,
+    files: &[(PathBuf, PathBuf)],
+    format: OutputFormat,
+    output_dir: Option<&OutputStorage>,
+    model: &str,
+) -> Result<()> {
+    let mut pending = Vec::new();
+    for (file, folder) in files {
+        let output = TranscriptOutput::new(file, folder, format, output_dir);
+        if output
+            .storage
+            .exists(&output.key)
+            .await
+            .context("Failed to check for an existing transcript")?
+        {
+            println!("{}  skipped: transcript exists", file.display());
+        } else {
+            pending.push((file.clone(), folder.clone()));
+        }
+    }
+    let durations = probe_durations(&args.ffmpeg, &pending).await;
+    for ((file, _), duration) in pending.iter().zip(&durations) {
+        let shown = duration.map_or_else(|| "unknown length".to_string(), cli::format_duration);
+        println!("{}  {}", file.display(), shown);
     }
+    println!("\n{}", cli::dry_run_summary(model, &durations));
+    Ok(())
 }
 
-fn init_logging(verbose: u8) {
-    let level = match verbose {
-        0 => Level::WARN,
-        1 => Level::INFO,
-        2 => Level::DEBUG,
-        _ => Level::TRACE,
-    };
-
-    let subscriber = FmtSubscriber::builder()
-        .with_max_level(level)
-        .with_target(false)
-        .with_thread_ids(false)
-        .with_file(false)
-        .with_line_number(false)
-        .compact()
-        .finish();
-
-    tracing::subscriber::set_global_default(subscriber).ok();
+/// `/`-separated storage key of the transcript of `relative`
+fn transcript_key(relative: &Path, format: OutputFormat) -> String {
+    relative
+        .with_extension(format.extension())
+        .iter()
+        .map(|part| part.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
 }
 
 #[derive(Debug)]
@@ -288,15 +389,61 @@ struct ProcessResult {
     segments: usize,
 }
 
+async fn write_transcript(
+    output: &TranscriptOutput,
+    transcript: &TranscriptResponse,
+    format: OutputFormat,
+    options: &SubtitleOptions,
+    checksums: bool,
+) -> Result<()> {
+    // The main transcript goes last: its existence is what marks the file done
+    let mut outputs = Vec::new();
+    let render = |transcript: &TranscriptResponse, format, key: &str| -> Result<String> {
+        Ok(match format {
+            OutputFormat::Html => transcript_to_html_with(
+                transcript,
+                Some(&cli::html_media_src(&output.input, &output.storage, key)),
+            ),
+            _ => format_transcript_with(transcript, format, options)
+                .context("Failed to serialize to JSON")?,
+        })
+    };
+    for (other, key) in output.also.iter().chain([&(format, output.key.clone())]) {
+        for (language, language_key) in cli::translation_keys(key, *other, &output.languages) {
+            let formatted = render(&transcript.in_translation(&language), *other, &language_key)?;
+            outputs.push((language_key, formatted));
+        }
+        outputs.push((key.clone(), render(transcript, *other, key)?));
+    }
+    output
+        .storage
+        .put_all(&outputs, checksums)
+        .await
+        .context("Failed to write output files")?;
+
+    info!(
+        "Transcript saved to: {:?}",
+        output.storage.location(&output.key)
+    );
+    Ok(())
+}
+
 #[allow(clippy::too_many_arguments)]
 async fn process_file(
     input: PathBuf,
+    output: TranscriptOutput,
+    provider: Provider,
     api_key: String,
     config: GeminiClientConfig,
     format: OutputFormat,
+    options: SubtitleOptions,
+    ffmpeg: FfmpegArgs,
+    timeout: Option<u64>,
+    duration: Option<f64>,
     keep_audio: bool,
     force_file_api: bool,
     keep_remote_file: bool,
+    checksums: bool,
     overall_pb: ProgressBar,
 ) -> ProcessResult {
     let file_name = input
@@ -305,13 +452,21 @@ async fn process_file(
         .unwrap_or_else(|| "unknown".to_string());
 
     // Check if output file already exists
-    let output_path = {
-        let mut p = input.clone();
-        p.set_extension(get_output_extension(format));
-        p
+    let exists = match output.storage.exists(&output.key).await {
+        Ok(exists) => exists,
+        Err(e) => {
+            overall_pb.println(format!("  Failed: {}", file_name));
+            return ProcessResult {
+                path: input,
+                success: false,
+                skipped: false,
+                error: Some(format!("Failed to check for an existing transcript: {}", e)),
+                segments: 0,
+            };
+        }
     };
 
-    if output_path.exists() {
+    if exists {
         overall_pb.println(format!(
             "  Skipped: {} (transcript already exists)",
             file_name
@@ -325,21 +480,52 @@ async fn process_file(
         };
     }
 
+    // Once the API is known to be down or the budget is spent, fail before
+    // extracting audio
+    let stopped = config
+        .circuit_breaker
+        .as_ref()
+        .and_then(|breaker| breaker.check().err().map(|e| e.to_string()))
+        .or_else(|| {
+            config
+                .budget
+                .as_ref()
+                .and_then(|budget| budget.check().err().map(|e| e.to_string()))
+        });
+    if let Some(error) = stopped {
+        overall_pb.println(format!("  Failed: {}", file_name));
+        return ProcessResult {
+            path: input,
+            success: false,
+            skipped: false,
+            error: Some(error),
+            segments: 0,
+        };
+    }
+
     overall_pb.println(format!("  Starting: {}", file_name));
 
     let result = process_file_inner(
         &input,
+        &output,
+        provider,
         &api_key,
         &config,
         format,
+        &options,
+        &ffmpeg,
+        timeout,
+        duration,
         keep_audio,
         force_file_api,
         keep_remote_file,
+        checksums,
     )
     .await;
 
     match result {
         Ok(segments) => {
+            info!(segments, "Transcribed");
             overall_pb.println(format!("  Done: {} ({} segments)", file_name, segments));
             ProcessResult {
                 path: input,
@@ -350,6 +536,7 @@ async fn process_file(
             }
         }
         Err(e) => {
+            warn!(error = %e, "Transcription failed");
             overall_pb.println(format!("  Failed: {}", file_name));
             ProcessResult {
                 path: input,
@@ -362,29 +549,75 @@ async fn process_file(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn process_file_inner(
     input: &Path,
+    output: &TranscriptOutput,
+    provider: Provider,
     api_key: &str,
     config: &GeminiClientConfig,
     format: OutputFormat,
+    options: &SubtitleOptions,
+    ffmpeg: &FfmpegArgs,
+    timeout: Option<u64>,
+    duration: Option<f64>,
     keep_audio: bool,
     force_file_api: bool,
     keep_remote_file: bool,
+    checksums: bool,
 ) -> Result<usize> {
     let (audio_path, should_cleanup) = if is_audio_file(input) {
         (input.to_path_buf(), false)
     } else {
         let mp3_path = input.with_extension("mp3");
-        extract_audio_with_ffmpeg(input, &mp3_path).await?;
+        extract_audio_with_ffmpeg(ffmpeg, input, &mp3_path)
+            .instrument(info_span!("stage", stage = "extract_audio"))
+            .await?;
         (mp3_path, !keep_audio)
     };
 
-    let audio_data = fs::read(&audio_path)
-        .await
-        .context("Failed to read audio file")?;
+    // `Bytes`, so the File API upload can share the buffer instead of copying it
+    let audio_data = Bytes::from(
+        fs::read(&audio_path)
+            .await
+            .context("Failed to read audio file")?,
+    );
 
     let file_size = audio_data.len() as u64;
     let mime_type = GeminiClient::get_mime_type(&audio_path);
+    let mut config = config.clone();
+    config.timeout_secs = timeout.unwrap_or_else(|| scaled_timeout_secs(duration, file_size));
+    debug!("Request timeout: {}s", config.timeout_secs);
+
+    if provider == Provider::OpenAi {
+        let openai_config = OpenAiClientConfig {
+            timeout_secs: config.timeout_secs,
+            max_retries: config.max_retries,
+            model: config.model.clone(),
+            cache: config.cache.clone(),
+            circuit_breaker: config.circuit_breaker.clone(),
+            budget: config.budget.clone(),
+            ..Default::default()
+        };
+        let client = OpenAiClient::with_config(api_key.to_string(), openai_config)
+            .map_err(|e| anyhow::anyhow!("Failed to create OpenAI client: {}", e))?;
+        let transcript = client
+            .transcribe(&audio_data, mime_type)
+            .instrument(info_span!("stage", stage = "transcribe"))
+            .await
+            .map_err(|e| anyhow::anyhow!("Transcription failed: {}", e))?;
+
+        let segment_count = transcript.segments.len();
+        write_transcript(output, &transcript, format, options, checksums)
+            .instrument(info_span!("stage", stage = "write"))
+            .await?;
+
+        if should_cleanup {
+            fs::remove_file(&audio_path).await.ok();
+        }
+
+        return Ok(segment_count);
+    }
 
     let client = GeminiClient::with_config(api_key.to_string(), config.clone())
         .map_err(|e| anyhow::anyhow!("Failed to create Gemini client: {}", e))?;
@@ -392,7 +625,9 @@ async fn process_file_inner(
     let use_file_api = force_file_api || file_size > MAX_INLINE_FILE_SIZE;
 
     let (transcript, uploaded_file_name) = if use_file_api {
-        let file_api = FileApiClient::new(client.http_client().clone(), api_key.to_string());
+        let file_api = FileApiClient::new(client.http_client().clone(), api_key.to_string())
+            .with_base_url(client.base_url())
+            .with_auth_mode(client.auth_mode());
 
         let display_name = audio_path
             .file_name()
@@ -400,38 +635,31 @@ async fn process_file_inner(
             .unwrap_or("audio");
 
         let file_info = file_api
-            .upload_file(&audio_data, mime_type, display_name)
+            .upload_file(audio_data.clone(), mime_type, display_name)
+            .instrument(info_span!("stage", stage = "upload"))
             .await
             .map_err(|e| anyhow::anyhow!("File upload failed: {}", e))?;
 
         let transcript = client
             .transcribe_file_uri(&file_info.uri, mime_type)
+            .instrument(info_span!("stage", stage = "transcribe"))
             .await
             .map_err(|e| anyhow::anyhow!("Transcription failed: {}", e))?;
 
         (transcript, Some((file_api, file_info.name)))
     } else {
         let transcript = client
-            .transcribe_audio(&audio_data, mime_type)
+            .transcribe(&audio_data, mime_type)
+            .instrument(info_span!("stage", stage = "transcribe"))
             .await
             .map_err(|e| anyhow::anyhow!("Transcription failed: {}", e))?;
         (transcript, None)
     };
 
     let segment_count = transcript.segments.len();
-
-    let output_path = {
-        let mut p = input.to_path_buf();
-        p.set_extension(get_output_extension(format));
-        p
-    };
-
-    let formatted_output = format_output(&transcript, format)?;
-    fs::write(&output_path, &formatted_output)
-        .await
-        .context("Failed to write output file")?;
-
-    info!("Transcript saved to: {:?}", output_path);
+    write_transcript(output, &transcript, format, options, checksums)
+        .instrument(info_span!("stage", stage = "write"))
+        .await?;
 
     // Cleanup remote file if uploaded
     if let Some((file_api, file_name)) = uploaded_file_name
@@ -449,89 +677,188 @@ async fn process_file_inner(
     Ok(segment_count)
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let args = Args::parse();
-
-    init_logging(args.verbose);
-
-    let api_key = get_api_key()?;
-
-    // Validate input folders
-    for folder in &args.folders {
-        if !folder.exists() {
-            anyhow::bail!("Folder does not exist: {:?}", folder);
-        }
-        if !folder.is_dir() {
-            anyhow::bail!(
-                "Path is not a directory: {:?}\nUse 'convert' command for single files.",
-                folder
-            );
+/// Run with parsed arguments; shared by this binary and its `flashecho` subcommand
+pub async fn run(args: Args) -> Result<()> {
+    init_logging(args.common.verbose, args.common.log_format);
+    args.common.install_usage_ledger("batch_convert");
+    let budget = args
+        .common
+        .tracking_budget()
+        .context("Failed to read usage ledger for --budget-since")?;
+    let spent_before = (budget.spent_usd(), budget.spent_tokens());
+
+    let profile = args.common.load_profile()?;
+    let provider = config::resolve(args.provider, "provider", profile.provider.as_deref())?;
+    let format = config::resolve(args.format, "format", profile.format.as_deref())?;
+    let model = args
+        .model
+        .clone()
+        .or_else(|| profile.model_for(provider).map(str::to_string))
+        .unwrap_or_else(|| provider.default_model().to_string());
+
+    if provider == Provider::OpenAi && args.force_file_api {
+        anyhow::bail!("--force-file-api is only supported with the Gemini provider");
+    }
+    if provider == Provider::OpenAi && args.topics {
+        anyhow::bail!("--topics is only supported with the Gemini provider");
+    }
+    if provider == Provider::OpenAi && args.num_speakers.is_some() {
+        anyhow::bail!("--num-speakers is only supported with the Gemini provider");
+    }
+    if provider == Provider::OpenAi && !args.translate_to.is_empty() {
+        anyhow::bail!("--translate-to is only supported with the Gemini provider");
+    }
+    if provider == Provider::OpenAi && args.generation.is_set() {
+        anyhow::bail!(
+            "--temperature, --top-p, --top-k, --max-output-tokens and --thinking-budget are \
+             only supported with the Gemini provider"
+        );
+    }
+    if provider == Provider::OpenAi && args.prompt.is_set() {
+        anyhow::bail!("--prompt and --prompt-file are only supported with the Gemini provider");
+    }
+    let prompt = args
+        .prompt
+        .load()
+        .await
+        .context("Failed to read --prompt-file")?;
+
+    let output_dir = args
+        .output_dir
+        .as_deref()
+        .map(OutputStorage::open)
+        .transpose()
+        .context("Invalid --output-dir")?;
+
+    // Validate input folders and files
+    let paths = expand_stdin(&args.paths).await?;
+    for path in &paths {
+        if !path.exists() {
+            anyhow::bail!("Path does not exist: {:?}", path);
         }
     }
 
     // Find all media files
-    let files = find_media_files(&args.folders);
+    let files = find_media_files(&paths);
 
     if files.is_empty() {
-        println!("No video or audio files found in the specified folders.");
+        println!("No video or audio files found in the specified paths.");
         return Ok(());
     }
+    if args.dry_run {
+        return dry_run(&args, &files, format, output_dir.as_ref(), &model).await;
+    }
+    if !files.iter().all(|(file, _)| is_audio_file(file)) {
+        args.ffmpeg
+            .check()
+            .await
+            .with_context(|| args.ffmpeg.missing())?;
+    }
+    let api_key = get_api_key(provider, &profile)?;
 
     let files_count = files.len();
-    println!("Found {} files to process", files_count);
+    let durations = probe_durations(&args.ffmpeg, &files).await;
+    let total_secs: f64 = durations.iter().flatten().sum();
+    if total_secs > 0.0 {
+        println!(
+            "Found {} files to process ({} of media)",
+            files_count,
+            cli::format_duration(total_secs)
+        );
+    } else {
+        println!("Found {} files to process", files_count);
+    }
+    let weights = progress_weights(&durations);
 
+    // `timeout_secs` is set per file, from --timeout or the file's length
     let config = GeminiClientConfig {
-        timeout_secs: args.timeout,
-        max_retries: args.max_retries,
-        model: args.model.clone(),
+        max_retries: args.common.max_retries,
+        model,
+        cache: args.common.response_cache(),
+        circuit_breaker: args.limits.circuit_breaker(),
+        budget: Some(budget.clone()),
+        topics: args.topics,
+        prompt,
+        num_speakers: args.num_speakers,
+        translate_to: args.translate_to.clone(),
+        generation: args.generation.params(),
+        ..Default::default()
     };
 
     let semaphore = Arc::new(Semaphore::new(args.jobs));
 
-    let overall_pb = ProgressBar::new(files_count as u64);
+    // Advances by media seconds rather than files, so the ETA holds up when
+    // lengths differ
+    let overall_pb = ProgressBar::new(weights.iter().sum());
     overall_pb.set_style(
         ProgressStyle::default_bar()
-            .template("[{bar:40.cyan/blue}] {pos}/{len} files ({eta})")
+            .template("[{bar:40.cyan/blue}] {msg} ({eta})")
             .unwrap()
             .progress_chars("#>-"),
     );
+    overall_pb.set_message(format!("0/{} files", files_count));
+    let done = Arc::new(AtomicUsize::new(0));
 
     let mut handles = Vec::new();
     let delay = Duration::from_secs(args.delay);
-
-    for (i, file) in files.into_iter().enumerate() {
+    let breaker = config.circuit_breaker.clone();
+    let options = args.subtitles.options();
+
+    let jobs = files.into_iter().zip(durations).zip(weights);
+    for (i, (((file, folder), duration), weight)) in jobs.enumerate() {
+        let output = TranscriptOutput::new(&file, &folder, format, output_dir.as_ref())
+            .with_also_formats(format, &args.also_format)
+            .with_translations(&args.translate_to);
         let permit = semaphore.clone().acquire_owned().await.unwrap();
         let api_key = api_key.clone();
         let config = config.clone();
-        let format = args.format;
+        let options = options.clone();
+        let ffmpeg = args.ffmpeg.clone();
+        let timeout = args.timeout;
         let keep_audio = args.keep_audio;
         let force_file_api = args.force_file_api;
         let keep_remote_file = args.keep_remote_file;
+        let checksums = args.checksums;
         let overall_pb = overall_pb.clone();
-
-        let handle = tokio::spawn(async move {
-            let result = process_file(
-                file,
-                api_key,
-                config,
-                format,
-                keep_audio,
-                force_file_api,
-                keep_remote_file,
-                overall_pb.clone(),
-            )
-            .await;
-            overall_pb.inc(1);
-            drop(permit);
-            result
-        });
+        let done = done.clone();
+        let span = info_span!("file", file = %file.display());
+
+        let handle = tokio::spawn(
+            async move {
+                let result = process_file(
+                    file,
+                    output,
+                    provider,
+                    api_key,
+                    config,
+                    format,
+                    options,
+                    ffmpeg,
+                    timeout,
+                    duration,
+                    keep_audio,
+                    force_file_api,
+                    keep_remote_file,
+                    checksums,
+                    overall_pb.clone(),
+                )
+                .await;
+                let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+                overall_pb.set_message(format!("{}/{} files", done, files_count));
+                overall_pb.inc(weight);
+                drop(permit);
+                result
+            }
+            .instrument(span),
+        );
 
         handles.push(handle);
 
         // Add delay between starting tasks to avoid rate limiting
         // Skip delay after the last file
-        if i < files_count - 1 && delay.as_secs() > 0 {
+        // and once the circuit breaker is open, since remaining files fail fast
+        let breaker_open = breaker.as_ref().is_some_and(CircuitBreaker::is_open);
+        if i < files_count - 1 && delay.as_secs() > 0 && !breaker_open {
             tokio::time::sleep(delay).await;
         }
     }
@@ -563,6 +890,11 @@ async fn main() -> Result<()> {
             skipped.len()
         );
     }
+    if !args.common.quiet
+        && let Some(spend) = cli::spend_summary(&budget, spent_before)
+    {
+        println!("{}", spend);
+    }
 
     if !failed.is_empty() {
         println!("\nFailed: {} files", failed.len());
@@ -578,6 +910,11 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+#[tokio::main]
+async fn main() -> Result<()> {
+    run(Args::parse()).await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -593,6 +930,26 @@ mod tests {
         assert!(!is_media_file(Path::new("test.json")));
     }
 
+    #[test]
+    fn test_transcript_output_keys() {
+        let input = Path::new("talks/2024/keynote.mp4");
+        let next_to_input =
+            TranscriptOutput::new(input, Path::new("talks"), OutputFormat::Srt, None);
+        assert_eq!(next_to_input.key, "keynote.srt");
+        assert_eq!(
+            next_to_input.storage.location(&next_to_input.key),
+            Path::new("talks/2024")
+                .join("keynote.srt")
+                .display()
+                .to_string()
+        );
+
+        let out = OutputStorage::open("out").unwrap();
+        let mirrored =
+            TranscriptOutput::new(input, Path::new("talks"), OutputFormat::Json, Some(&out));
+        assert_eq!(mirrored.key, "2024/keynote.json");
+    }
+
     #[test]
     fn test_is_audio_file() {
         assert!(is_audio_file(Path::new("test.mp3")));
@@ -602,10 +959,43 @@ mod tests {
     }
 
     #[test]
-    fn test_get_output_extension() {
-        assert_eq!(get_output_extension(OutputFormat::Json), "json");
-        assert_eq!(get_output_extension(OutputFormat::Srt), "srt");
-        assert_eq!(get_output_extension(OutputFormat::Vtt), "vtt");
-        assert_eq!(get_output_extension(OutputFormat::Txt), "txt");
+    fn test_find_media_files_in_folders_and_files() {
+        let root =
+            std::env::temp_dir().join(format!("flashecho_batch_paths_{}", std::process::id()));
+        let talks = root.join("talks");
+        std::fs::create_dir_all(talks.join("2024")).unwrap();
+        for name in ["2024/keynote.mp4", "notes.txt"] {
+            std::fs::write(talks.join(name), b"").unwrap();
+        }
+        let single = root.join("panel.mp3");
+        std::fs::write(&single, b"").unwrap();
+
+        let keynote = talks.join("2024/keynote.mp4");
+        let files = find_media_files(&[
+            talks.clone(),
+            single.clone(),
+            keynote.clone(),
+            talks.join("notes.txt"),
+        ]);
+        assert_eq!(files, vec![(keynote, talks), (single, root.clone())]);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_progress_weights() {
+        assert_eq!(
+            progress_weights(&[Some(600.4), None, Some(1200.0), Some(0.2)]),
+            vec![600, 600, 1200, 1]
+        );
+        assert_eq!(progress_weights(&[None, None]), vec![1, 1]);
+    }
+
+    #[test]
+    fn test_parse_path_list() {
+        assert_eq!(
+            parse_path_list("./a.mp4\n \nb dir/c.mkv\r\n"),
+            vec![PathBuf::from("./a.mp4"), PathBuf::from("b dir/c.mkv")]
+        );
     }
 }
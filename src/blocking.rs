@@ -0,0 +1,531 @@
+//! Synchronous wrappers around the async API clients, for callers without a
+//! tokio runtime (build scripts, non-async applications). Enabled with the
+//! `blocking` feature.
+//!
+//! Like `reqwest::blocking`, these must not be called from inside an async
+//! runtime: blocking on a future there panics.
+
+#[cfg(feature = "__client")]
+use std::future::Future;
+#[cfg(feature = "__client")]
+use std::sync::OnceLock;
+#[cfg(feature = "__client")]
+use tokio::runtime::Runtime;
+
+#[cfg(feature = "embeddings")]
+use crate::embeddings_api::{
+    self, EmbedConfig, Embedding, EmbeddingsClient, EmbeddingsClientConfig,
+};
+#[cfg(feature = "file-api")]
+use crate::file_api::{self, FileApiClient, FileInfo};
+#[cfg(feature = "transcription")]
+use crate::gemini_api::{self, AudioSource, GeminiClient, GeminiClientConfig, TranscriptResponse};
+#[cfg(feature = "imagen")]
+use crate::imagen_api::{self, GeneratedImage, ImageGenConfig, ImagenClient, ImagenClientConfig};
+#[cfg(feature = "imagen-edit")]
+use crate::imagen_edit_api::{
+    self, ImageEditClient, ImageEditClientConfig, ImageEditConfig, InputImage,
+};
+#[cfg(feature = "music")]
+use crate::music_api::{self, GeneratedMusic, MusicClient, MusicClientConfig, MusicGenConfig};
+#[cfg(feature = "transcription")]
+use crate::openai_api::{self, OpenAiClient, OpenAiClientConfig};
+#[cfg(feature = "text")]
+use crate::text_api::{self, CachedContext, TextClient, TextClientConfig, TextGenConfig};
+#[cfg(feature = "tts")]
+use crate::tts_api::{self, DialogueLine, SpeechAudio, SpeechConfig, TtsClient, TtsClientConfig};
+#[cfg(feature = "veo")]
+use crate::veo_api::{self, GeneratedVideo, VeoClient, VeoClientConfig, VideoGenConfig};
+
+/// Runtime shared by every blocking client, started on first use
+#[cfg(feature = "__client")]
+fn block_on<F: Future>(future: F) -> F::Output {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME
+        .get_or_init(|| {
+            tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(1)
+                .thread_name("transcript-tool-blocking")
+                .enable_all()
+                .build()
+                .expect("failed to start blocking runtime")
+        })
+        .block_on(future)
+}
+
+/// Blocking counterpart of [`GeminiClient`]
+#[cfg(feature = "transcription")]
+#[derive(Debug, Clone)]
+pub struct GeminiClientBlocking {
+    inner: GeminiClient,
+}
+
+#[cfg(feature = "transcription")]
+impl GeminiClientBlocking {
+    pub fn new(api_key: String) -> gemini_api::Result<Self> {
+        GeminiClient::new(api_key).map(Self::from)
+    }
+
+    pub fn with_config(api_key: String, config: GeminiClientConfig) -> gemini_api::Result<Self> {
+        GeminiClient::with_config(api_key, config).map(Self::from)
+    }
+
+    pub fn inner(&self) -> &GeminiClient {
+        &self.inner
+    }
+
+    /// File API client sharing this client's connection pool and host
+    #[cfg(feature = "file-api")]
+    pub fn file_api(&self) -> FileApiClientBlocking {
+        FileApiClient::new(
+            self.inner.http_client().clone(),
+            self.inner.api_key().to_string(),
+        )
+        .with_base_url(self.inner.base_url())
+        .with_auth_mode(self.inner.auth_mode())
+        .into()
+    }
+
+    pub fn transcribe_audio(
+        &self,
+        audio_data: &[u8],
+        mime_type: &str,
+    ) -> gemini_api::Result<TranscriptResponse> {
+        block_on(self.inner.transcribe_audio(audio_data, mime_type))
+    }
+
+    pub fn transcribe_file_uri(
+        &self,
+        file_uri: &str,
+        mime_type: &str,
+    ) -> gemini_api::Result<TranscriptResponse> {
+        block_on(self.inner.transcribe_file_uri(file_uri, mime_type))
+    }
+
+    pub fn transcribe_source(
+        &self,
+        source: &AudioSource,
+    ) -> gemini_api::Result<TranscriptResponse> {
+        block_on(self.inner.transcribe_source(source))
+    }
+}
+
+#[cfg(feature = "transcription")]
+impl From<GeminiClient> for GeminiClientBlocking {
+    fn from(inner: GeminiClient) -> Self {
+        Self { inner }
+    }
+}
+
+/// Blocking counterpart of [`FileApiClient`]
+#[cfg(feature = "file-api")]
+pub struct FileApiClientBlocking {
+    inner: FileApiClient,
+}
+
+#[cfg(feature = "file-api")]
+impl FileApiClientBlocking {
+    pub fn inner(&self) -> &FileApiClient {
+        &self.inner
+    }
+
+    pub fn upload_file(
+        &self,
+        data: impl Into<bytes::Bytes>,
+        mime_type: &str,
+        display_name: &str,
+    ) -> file_api::Result<FileInfo> {
+        block_on(self.inner.upload_file(data, mime_type, display_name))
+    }
+
+    pub fn get_file_info(&self, file_name: &str) -> file_api::Result<FileInfo> {
+        block_on(self.inner.get_file_info(file_name))
+    }
+
+    pub fn delete_file(&self, file_name: &str) -> file_api::Result<()> {
+        block_on(self.inner.delete_file(file_name))
+    }
+}
+
+#[cfg(feature = "file-api")]
+impl From<FileApiClient> for FileApiClientBlocking {
+    fn from(inner: FileApiClient) -> Self {
+        Self { inner }
+    }
+}
+
+/// Blocking counterpart of [`ImagenClient`]
+#[cfg(feature = "imagen")]
+#[derive(Debug, Clone)]
+pub struct ImagenClientBlocking {
+    inner: ImagenClient,
+}
+
+#[cfg(feature = "imagen")]
+impl ImagenClientBlocking {
+    pub fn new(api_key: String) -> imagen_api::Result<Self> {
+        ImagenClient::new(api_key).map(Self::from)
+    }
+
+    pub fn with_config(api_key: String, config: ImagenClientConfig) -> imagen_api::Result<Self> {
+        ImagenClient::with_config(api_key, config).map(Self::from)
+    }
+
+    pub fn inner(&self) -> &ImagenClient {
+        &self.inner
+    }
+
+    pub fn generate_image(&self, prompt: &str) -> imagen_api::Result<GeneratedImage> {
+        block_on(self.inner.generate_image(prompt))
+    }
+
+    pub fn generate_image_with_config(
+        &self,
+        prompt: &str,
+        gen_config: Option<&ImageGenConfig>,
+    ) -> imagen_api::Result<GeneratedImage> {
+        block_on(self.inner.generate_image_with_config(prompt, gen_config))
+    }
+}
+
+#[cfg(feature = "imagen")]
+impl From<ImagenClient> for ImagenClientBlocking {
+    fn from(inner: ImagenClient) -> Self {
+        Self { inner }
+    }
+}
+
+/// Blocking counterpart of [`ImageEditClient`]
+#[cfg(feature = "imagen-edit")]
+#[derive(Debug, Clone)]
+pub struct ImageEditClientBlocking {
+    inner: ImageEditClient,
+}
+
+#[cfg(feature = "imagen-edit")]
+impl ImageEditClientBlocking {
+    pub fn new(api_key: String) -> imagen_edit_api::Result<Self> {
+        ImageEditClient::new(api_key).map(Self::from)
+    }
+
+    pub fn with_config(
+        api_key: String,
+        config: ImageEditClientConfig,
+    ) -> imagen_edit_api::Result<Self> {
+        ImageEditClient::with_config(api_key, config).map(Self::from)
+    }
+
+    pub fn inner(&self) -> &ImageEditClient {
+        &self.inner
+    }
+
+    pub fn edit_images(
+        &self,
+        prompt: &str,
+        images: &[InputImage],
+    ) -> imagen_edit_api::Result<GeneratedImage> {
+        block_on(self.inner.edit_images(prompt, images))
+    }
+
+    pub fn edit_images_with_config(
+        &self,
+        prompt: &str,
+        images: &[InputImage],
+        edit_config: Option<&ImageEditConfig>,
+    ) -> imagen_edit_api::Result<GeneratedImage> {
+        block_on(
+            self.inner
+                .edit_images_with_config(prompt, images, edit_config),
+        )
+    }
+
+    pub fn inpaint_with_config(
+        &self,
+        prompt: &str,
+        images: &[InputImage],
+        mask: &InputImage,
+        edit_config: Option<&ImageEditConfig>,
+    ) -> imagen_edit_api::Result<GeneratedImage> {
+        block_on(
+            self.inner
+                .inpaint_with_config(prompt, images, mask, edit_config),
+        )
+    }
+}
+
+#[cfg(feature = "imagen-edit")]
+impl From<ImageEditClient> for ImageEditClientBlocking {
+    fn from(inner: ImageEditClient) -> Self {
+        Self { inner }
+    }
+}
+
+/// Blocking counterpart of [`OpenAiClient`]
+#[cfg(feature = "transcription")]
+#[derive(Debug, Clone)]
+pub struct OpenAiClientBlocking {
+    inner: OpenAiClient,
+}
+
+#[cfg(feature = "transcription")]
+impl OpenAiClientBlocking {
+    pub fn new(api_key: String) -> openai_api::Result<Self> {
+        OpenAiClient::new(api_key).map(Self::from)
+    }
+
+    pub fn with_config(api_key: String, config: OpenAiClientConfig) -> openai_api::Result<Self> {
+        OpenAiClient::with_config(api_key, config).map(Self::from)
+    }
+
+    pub fn inner(&self) -> &OpenAiClient {
+        &self.inner
+    }
+
+    pub fn transcribe_audio(
+        &self,
+        audio_data: &[u8],
+        mime_type: &str,
+    ) -> openai_api::Result<TranscriptResponse> {
+        block_on(self.inner.transcribe_audio(audio_data, mime_type))
+    }
+}
+
+#[cfg(feature = "transcription")]
+impl From<OpenAiClient> for OpenAiClientBlocking {
+    fn from(inner: OpenAiClient) -> Self {
+        Self { inner }
+    }
+}
+
+/// Blocking counterpart of [`VeoClient`]; each call blocks until the video
+/// is rendered and downloaded
+#[cfg(feature = "veo")]
+#[derive(Debug, Clone)]
+pub struct VeoClientBlocking {
+    inner: VeoClient,
+}
+
+#[cfg(feature = "veo")]
+impl VeoClientBlocking {
+    pub fn new(api_key: String) -> veo_api::Result<Self> {
+        VeoClient::new(api_key).map(Self::from)
+    }
+
+    pub fn with_config(api_key: String, config: VeoClientConfig) -> veo_api::Result<Self> {
+        VeoClient::with_config(api_key, config).map(Self::from)
+    }
+
+    pub fn inner(&self) -> &VeoClient {
+        &self.inner
+    }
+
+    pub fn generate_video(&self, prompt: &str) -> veo_api::Result<GeneratedVideo> {
+        block_on(self.inner.generate_video(prompt))
+    }
+
+    pub fn generate_video_with_config(
+        &self,
+        prompt: &str,
+        gen_config: Option<&VideoGenConfig>,
+    ) -> veo_api::Result<GeneratedVideo> {
+        block_on(self.inner.generate_video_with_config(prompt, gen_config))
+    }
+}
+
+#[cfg(feature = "veo")]
+impl From<VeoClient> for VeoClientBlocking {
+    fn from(inner: VeoClient) -> Self {
+        Self { inner }
+    }
+}
+
+/// Blocking counterpart of [`TtsClient`]
+#[cfg(feature = "tts")]
+#[derive(Debug, Clone)]
+pub struct TtsClientBlocking {
+    inner: TtsClient,
+}
+
+#[cfg(feature = "tts")]
+impl TtsClientBlocking {
+    pub fn new(api_key: String) -> tts_api::Result<Self> {
+        TtsClient::new(api_key).map(Self::from)
+    }
+
+    pub fn with_config(api_key: String, config: TtsClientConfig) -> tts_api::Result<Self> {
+        TtsClient::with_config(api_key, config).map(Self::from)
+    }
+
+    pub fn inner(&self) -> &TtsClient {
+        &self.inner
+    }
+
+    pub fn synthesize(&self, text: &str) -> tts_api::Result<SpeechAudio> {
+        block_on(self.inner.synthesize(text))
+    }
+
+    pub fn synthesize_with_config(
+        &self,
+        text: &str,
+        speech: &SpeechConfig,
+    ) -> tts_api::Result<SpeechAudio> {
+        block_on(self.inner.synthesize_with_config(text, speech))
+    }
+
+    pub fn synthesize_dialogue(
+        &self,
+        lines: &[DialogueLine],
+        speech: &SpeechConfig,
+    ) -> tts_api::Result<SpeechAudio> {
+        block_on(self.inner.synthesize_dialogue(lines, speech))
+    }
+}
+
+#[cfg(feature = "tts")]
+impl From<TtsClient> for TtsClientBlocking {
+    fn from(inner: TtsClient) -> Self {
+        Self { inner }
+    }
+}
+
+/// Blocking counterpart of [`MusicClient`]
+#[cfg(feature = "music")]
+#[derive(Debug, Clone)]
+pub struct MusicClientBlocking {
+    inner: MusicClient,
+}
+
+#[cfg(feature = "music")]
+impl MusicClientBlocking {
+    pub fn new(api_key: String) -> music_api::Result<Self> {
+        MusicClient::new(api_key).map(Self::from)
+    }
+
+    pub fn with_config(api_key: String, config: MusicClientConfig) -> music_api::Result<Self> {
+        MusicClient::with_config(api_key, config).map(Self::from)
+    }
+
+    pub fn inner(&self) -> &MusicClient {
+        &self.inner
+    }
+
+    pub fn generate_music(&self, prompt: &str) -> music_api::Result<GeneratedMusic> {
+        block_on(self.inner.generate_music(prompt))
+    }
+
+    pub fn generate_music_with_config(
+        &self,
+        prompt: &str,
+        gen_config: Option<&MusicGenConfig>,
+    ) -> music_api::Result<GeneratedMusic> {
+        block_on(self.inner.generate_music_with_config(prompt, gen_config))
+    }
+}
+
+#[cfg(feature = "music")]
+impl From<MusicClient> for MusicClientBlocking {
+    fn from(inner: MusicClient) -> Self {
+        Self { inner }
+    }
+}
+
+/// Blocking counterpart of [`EmbeddingsClient`]
+#[cfg(feature = "embeddings")]
+#[derive(Debug, Clone)]
+pub struct EmbeddingsClientBlocking {
+    inner: EmbeddingsClient,
+}
+
+#[cfg(feature = "embeddings")]
+impl EmbeddingsClientBlocking {
+    pub fn new(api_key: String) -> embeddings_api::Result<Self> {
+        EmbeddingsClient::new(api_key).map(Self::from)
+    }
+
+    pub fn with_config(
+        api_key: String,
+        config: EmbeddingsClientConfig,
+    ) -> embeddings_api::Result<Self> {
+        EmbeddingsClient::with_config(api_key, config).map(Self::from)
+    }
+
+    pub fn inner(&self) -> &EmbeddingsClient {
+        &self.inner
+    }
+
+    pub fn embed(
+        &self,
+        text: &str,
+        embed_config: Option<&EmbedConfig>,
+    ) -> embeddings_api::Result<Embedding> {
+        block_on(self.inner.embed(text, embed_config))
+    }
+
+    pub fn embed_batch(
+        &self,
+        texts: &[&str],
+        embed_config: Option<&EmbedConfig>,
+    ) -> embeddings_api::Result<Vec<Embedding>> {
+        block_on(self.inner.embed_batch(texts, embed_config))
+    }
+}
+
+#[cfg(feature = "embeddings")]
+impl From<EmbeddingsClient> for EmbeddingsClientBlocking {
+    fn from(inner: EmbeddingsClient) -> Self {
+        Self { inner }
+    }
+}
+
+/// Blocking counterpart of [`TextClient`]
+#[cfg(feature = "text")]
+#[derive(Debug, Clone)]
+pub struct TextClientBlocking {
+    inner: TextClient,
+}
+
+#[cfg(feature = "text")]
+impl TextClientBlocking {
+    pub fn new(api_key: String) -> text_api::Result<Self> {
+        TextClient::new(api_key).map(Self::from)
+    }
+
+    pub fn with_config(api_key: String, config: TextClientConfig) -> text_api::Result<Self> {
+        TextClient::with_config(api_key, config).map(Self::from)
+    }
+
+    pub fn inner(&self) -> &TextClient {
+        &self.inner
+    }
+
+    pub fn generate(&self, prompt: &str) -> text_api::Result<String> {
+        block_on(self.inner.generate(prompt))
+    }
+
+    pub fn generate_with_config(
+        &self,
+        prompt: &str,
+        gen_config: Option<&TextGenConfig>,
+    ) -> text_api::Result<String> {
+        block_on(self.inner.generate_with_config(prompt, gen_config))
+    }
+
+    pub fn cached_context(
+        &self,
+        context: &str,
+        system_instruction: Option<&str>,
+        ttl_secs: u64,
+    ) -> text_api::Result<CachedContext> {
+        block_on(
+            self.inner
+                .cached_context(context, system_instruction, ttl_secs),
+        )
+    }
+}
+
+#[cfg(feature = "text")]
+impl From<TextClient> for TextClientBlocking {
+    fn from(inner: TextClient) -> Self {
+        Self { inner }
+    }
+}
@@ -0,0 +1,178 @@
+//! Spend limits shared by every client of a run, so automation can't quietly
+//! run up a bill. Once the estimated cost or token count of successful calls
+//! reaches a limit, further requests fail with [`BudgetExceeded`] without
+//! being sent.
+//!
+//! Clone one [`Budget`] into every client config. Costs are the estimates of
+//! [`UsageRecord::estimate_cost`](crate::usage::UsageRecord::estimate_cost);
+//! calls to models without a known price only count towards the token limit.
+//! Seed a budget with [`Budget::with_prior_usage`] to make it cover a ledger
+//! period (e.g. this month) instead of a single run.
+
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+use crate::usage::UsageRecord;
+
+/// Returned instead of sending a request once a limit is reached
+#[derive(Debug, Clone, Error)]
+pub enum BudgetExceeded {
+    #[error("Spend budget exceeded: ${spent:.4} of ${limit} spent, not sending more requests")]
+    Cost { spent: f64, limit: f64 },
+
+    #[error("Token budget exceeded: {spent} of {limit} tokens used, not sending more requests")]
+    Tokens { spent: u64, limit: u64 },
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Budget {
+    max_cost_usd: Option<f64>,
+    max_tokens: Option<u64>,
+    /// Shared by every clone
+    spent: Arc<Mutex<Spent>>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Spent {
+    cost_usd: f64,
+    tokens: u64,
+}
+
+impl Budget {
+    /// A budget without limits; add them with [`Budget::with_max_cost_usd`]
+    /// and [`Budget::with_max_tokens`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stop once the estimated cost reaches `usd`
+    pub fn with_max_cost_usd(mut self, usd: f64) -> Self {
+        self.max_cost_usd = Some(usd);
+        self
+    }
+
+    /// Stop once input plus output tokens reach `tokens`
+    pub fn with_max_tokens(mut self, tokens: u64) -> Self {
+        self.max_tokens = Some(tokens);
+        self
+    }
+
+    /// Count ledger `records` from `since` (unix seconds) on as already spent
+    pub fn with_prior_usage(self, records: &[UsageRecord], since: u64) -> Self {
+        for record in records.iter().filter(|r| r.time >= since) {
+            self.add(record.cost_usd.unwrap_or_default(), record);
+        }
+        self
+    }
+
+    /// Estimated USD spent so far
+    pub fn spent_usd(&self) -> f64 {
+        self.spent.lock().unwrap().cost_usd
+    }
+
+    /// Tokens spent so far
+    pub fn spent_tokens(&self) -> u64 {
+        self.spent.lock().unwrap().tokens
+    }
+
+    /// `Err` once a limit is reached; clients call this before every attempt
+    pub fn check(&self) -> Result<(), BudgetExceeded> {
+        let spent = *self.spent.lock().unwrap();
+        if let Some(limit) = self.max_cost_usd
+            && spent.cost_usd >= limit
+        {
+            return Err(BudgetExceeded::Cost {
+                spent: spent.cost_usd,
+                limit,
+            });
+        }
+        if let Some(limit) = self.max_tokens
+            && spent.tokens >= limit
+        {
+            return Err(BudgetExceeded::Tokens {
+                spent: spent.tokens,
+                limit,
+            });
+        }
+        Ok(())
+    }
+
+    /// Count one successful call
+    #[cfg(any(feature = "__retry", test))]
+    pub(crate) fn charge(&self, record: &UsageRecord) {
+        self.add(record.estimate_cost().unwrap_or_default(), record);
+    }
+
+    fn add(&self, cost_usd: f64, record: &UsageRecord) {
+        let mut spent = self.spent.lock().unwrap();
+        spent.cost_usd += cost_usd;
+        spent.tokens += record.input_tokens + record.output_tokens;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(model: &str, input_tokens: u64, output_tokens: u64) -> UsageRecord {
+        UsageRecord {
+            model: model.to_string(),
+            input_tokens,
+            output_tokens,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_cost_limit_is_shared() {
+        let budget = Budget::new().with_max_cost_usd(0.05);
+        let shared = budget.clone();
+
+        // 1290 output tokens of gemini-2.5-flash-image ~ $0.039
+        budget.charge(&call("gemini-2.5-flash-image", 0, 1290));
+        assert!(shared.check().is_ok());
+        shared.charge(&call("gemini-2.5-flash-image", 0, 1290));
+
+        let err = budget.check().unwrap_err();
+        assert!(matches!(err, BudgetExceeded::Cost { limit: 0.05, .. }));
+        assert!(err.to_string().contains("$0.0774 of $0.05"));
+        assert!((budget.spent_usd() - 0.0774).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_token_limit_counts_unpriced_models() {
+        let budget = Budget::new().with_max_tokens(100);
+        budget.charge(&call("some-future-model", 60, 30));
+        assert!(budget.check().is_ok());
+        budget.charge(&call("some-future-model", 5, 5));
+        assert!(matches!(
+            budget.check(),
+            Err(BudgetExceeded::Tokens {
+                spent: 100,
+                limit: 100
+            })
+        ));
+        assert_eq!(budget.spent_usd(), 0.0);
+    }
+
+    #[test]
+    fn test_prior_usage_from_ledger_period() {
+        let old = UsageRecord {
+            time: 10,
+            cost_usd: Some(5.0),
+            ..Default::default()
+        };
+        let recent = UsageRecord {
+            time: 100,
+            cost_usd: Some(0.75),
+            ..Default::default()
+        };
+
+        let budget = Budget::new()
+            .with_max_cost_usd(1.0)
+            .with_prior_usage(&[old, recent], 50);
+        assert!((budget.spent_usd() - 0.75).abs() < 1e-9);
+        assert!(budget.check().is_ok());
+        assert!(Budget::new().check().is_ok());
+    }
+}
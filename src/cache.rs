@@ -0,0 +1,289 @@
+//! Disk-backed cache of API responses, keyed by a hash of the model and the
+//! request payload, so repeating an identical transcription, image, speech or music request
+//! (e.g. to try another output format) doesn't bill it again.
+//!
+//! Set `cache` on a client config to enable it. Cache failures are logged and
+//! otherwise ignored: a broken cache only costs a live request. On wasm32
+//! there is no filesystem, so every lookup misses.
+
+#[cfg(any(feature = "imagen", feature = "tts", feature = "music"))]
+use base64::Engine;
+#[cfg(any(feature = "imagen", feature = "tts", feature = "music"))]
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[cfg(feature = "imagen")]
+use crate::imagen_api::{GeneratedImage, ImageProvenance};
+#[cfg(feature = "music")]
+use crate::music_api::GeneratedMusic;
+#[cfg(feature = "tts")]
+use crate::tts_api::SpeechAudio;
+
+/// How long entries are served before being refetched
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+/// On-disk form of a cached image or music clip
+#[cfg(any(feature = "imagen", feature = "music"))]
+#[derive(Serialize, Deserialize)]
+struct CachedBlob {
+    mime_type: String,
+    data: String,
+}
+
+/// On-disk form of a cached image; entries written before provenance was
+/// recorded still load
+#[cfg(feature = "imagen")]
+#[derive(Serialize, Deserialize)]
+struct CachedImage {
+    #[serde(flatten)]
+    blob: CachedBlob,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    provenance: Option<ImageProvenance>,
+}
+
+/// On-disk form of cached speech
+#[cfg(feature = "tts")]
+#[derive(Serialize, Deserialize)]
+struct CachedSpeech {
+    sample_rate: u32,
+    channels: u16,
+    pcm: String,
+}
+
+impl ResponseCache {
+    pub fn new(dir: impl Into<PathBuf>, ttl: Duration) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl,
+        }
+    }
+
+    /// Cache in [`ResponseCache::default_dir`]
+    pub fn with_ttl(ttl: Duration) -> Self {
+        Self::new(Self::default_dir(), ttl)
+    }
+
+    /// `$FLASHECHO_CACHE_DIR`, else `$XDG_CACHE_HOME/flashecho`, else
+    /// `~/.cache/flashecho`
+    pub fn default_dir() -> PathBuf {
+        if let Some(dir) = std::env::var_os("FLASHECHO_CACHE_DIR") {
+            return PathBuf::from(dir);
+        }
+        let base = std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+            .unwrap_or_else(std::env::temp_dir);
+        base.join("flashecho")
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Cache key for a request: hash of its parts (model, payload, ...)
+    pub fn key(parts: &[&[u8]]) -> String {
+        let mut hasher = blake3::Hasher::new();
+        for part in parts {
+            // Length prefix so ("ab", "c") and ("a", "bc") differ
+            hasher.update(&(part.len() as u64).to_le_bytes());
+            hasher.update(part);
+        }
+        hasher.finalize().to_hex().to_string()
+    }
+
+    #[cfg(feature = "imagen")]
+    pub(crate) async fn get_image(&self, key: &str) -> Option<GeneratedImage> {
+        let cached: CachedImage = self.get_json(key).await?;
+        let data = base64::engine::general_purpose::STANDARD
+            .decode(cached.blob.data)
+            .ok()?;
+        Some(GeneratedImage {
+            data,
+            mime_type: cached.blob.mime_type,
+            provenance: cached.provenance,
+        })
+    }
+
+    #[cfg(feature = "imagen")]
+    pub(crate) async fn put_image(&self, key: &str, image: &GeneratedImage) {
+        let cached = CachedImage {
+            blob: CachedBlob {
+                mime_type: image.mime_type.clone(),
+                data: base64::engine::general_purpose::STANDARD.encode(&image.data),
+            },
+            provenance: image.provenance.clone(),
+        };
+        self.put_json(key, &cached).await;
+    }
+
+    #[cfg(feature = "music")]
+    pub(crate) async fn get_music(&self, key: &str) -> Option<GeneratedMusic> {
+        let cached: CachedBlob = self.get_json(key).await?;
+        let data = base64::engine::general_purpose::STANDARD
+            .decode(cached.data)
+            .ok()?;
+        Some(GeneratedMusic {
+            data,
+            mime_type: cached.mime_type,
+        })
+    }
+
+    #[cfg(feature = "music")]
+    pub(crate) async fn put_music(&self, key: &str, music: &GeneratedMusic) {
+        let cached = CachedBlob {
+            mime_type: music.mime_type.clone(),
+            data: base64::engine::general_purpose::STANDARD.encode(&music.data),
+        };
+        self.put_json(key, &cached).await;
+    }
+
+    #[cfg(feature = "tts")]
+    pub(crate) async fn get_speech(&self, key: &str) -> Option<SpeechAudio> {
+        let cached: CachedSpeech = self.get_json(key).await?;
+        let pcm = base64::engine::general_purpose::STANDARD
+            .decode(cached.pcm)
+            .ok()?;
+        Some(SpeechAudio {
+            pcm,
+            sample_rate: cached.sample_rate,
+            channels: cached.channels,
+        })
+    }
+
+    #[cfg(feature = "tts")]
+    pub(crate) async fn put_speech(&self, key: &str, audio: &SpeechAudio) {
+        let cached = CachedSpeech {
+            sample_rate: audio.sample_rate,
+            channels: audio.channels,
+            pcm: base64::engine::general_purpose::STANDARD.encode(&audio.pcm),
+        };
+        self.put_json(key, &cached).await;
+    }
+}
+
+/// Raw entry storage behind the typed getters and setters above
+#[cfg(any(
+    feature = "transcription",
+    feature = "imagen",
+    feature = "tts",
+    feature = "music",
+    feature = "embeddings",
+    feature = "text"
+))]
+impl ResponseCache {
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    pub(crate) async fn get_json<T: serde::de::DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let bytes = self.read(key).await?;
+        match serde_json::from_slice(&bytes) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                tracing::warn!("Ignoring unreadable cache entry {}: {}", key, e);
+                None
+            }
+        }
+    }
+
+    pub(crate) async fn put_json<T: serde::Serialize>(&self, key: &str, value: &T) {
+        match serde_json::to_vec(value) {
+            Ok(bytes) => self.write(key, &bytes).await,
+            Err(e) => tracing::warn!("Failed to serialize cache entry {}: {}", key, e),
+        }
+    }
+
+    /// Entry bytes, unless missing or older than the TTL
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn read(&self, key: &str) -> Option<Vec<u8>> {
+        let path = self.path(key);
+        let modified = tokio::fs::metadata(&path).await.ok()?.modified().ok()?;
+        let age = modified.elapsed().unwrap_or_default();
+        if age > self.ttl {
+            tracing::debug!("Cache entry {} expired", key);
+            let _ = tokio::fs::remove_file(&path).await;
+            return None;
+        }
+        tokio::fs::read(&path).await.ok()
+    }
+
+    /// Write via a temp file so concurrent readers never see a partial entry
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn write(&self, key: &str, bytes: &[u8]) {
+        let path = self.path(key);
+        let result = async {
+            tokio::fs::create_dir_all(&self.dir).await?;
+            crate::storage::write_atomic(&path, bytes).await
+        }
+        .await;
+        if let Err(e) = result {
+            tracing::warn!("Failed to write cache entry {:?}: {}", path, e);
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    async fn read(&self, _key: &str) -> Option<Vec<u8>> {
+        None
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    async fn write(&self, _key: &str, _bytes: &[u8]) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_separates_parts() {
+        assert_eq!(
+            ResponseCache::key(&[b"model", b"payload"]),
+            ResponseCache::key(&[b"model", b"payload"])
+        );
+        assert_ne!(
+            ResponseCache::key(&[b"ab", b"c"]),
+            ResponseCache::key(&[b"a", b"bc"])
+        );
+    }
+
+    #[cfg(feature = "imagen")]
+    #[tokio::test]
+    async fn test_round_trip_and_expiry() {
+        let dir = std::env::temp_dir().join(format!("flashecho_cache_{}", std::process::id()));
+        let cache = ResponseCache::new(&dir, DEFAULT_CACHE_TTL);
+        let image = GeneratedImage {
+            data: b"PNGDATA".to_vec(),
+            mime_type: "image/png".to_string(),
+            provenance: Some(ImageProvenance {
+                model: "gemini-2.5-flash-image".to_string(),
+                model_version: Some("gemini-2.5-flash-image".to_string()),
+                ..Default::default()
+            }),
+        };
+
+        assert!(cache.get_image("k").await.is_none());
+        cache.put_image("k", &image).await;
+        let cached = cache.get_image("k").await.unwrap();
+        assert_eq!(cached.data, image.data);
+        assert_eq!(cached.mime_type, "image/png");
+        assert_eq!(cached.provenance, image.provenance);
+
+        let expired = ResponseCache::new(&dir, Duration::ZERO);
+        std::thread::sleep(Duration::from_millis(10));
+        assert!(expired.get_image("k").await.is_none());
+        assert!(!dir.join("k.json").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,182 @@
+//! Circuit breaker and retry budget shared by concurrent tasks. When the API
+//! fails hard (quota exhausted, outage), a batch stops sending requests and
+//! fails fast instead of every task retrying on its own for minutes.
+//!
+//! Clone one [`CircuitBreaker`] into every client config of a batch. Only
+//! transient failures (rate limits, network errors, 5xx) count; a success
+//! resets the count. Once tripped the breaker stays open until
+//! [`CircuitBreaker::reset`].
+
+#[cfg(feature = "__retry")]
+use std::fmt;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+/// Returned instead of sending a request while the breaker is open
+#[derive(Debug, Clone, Error)]
+#[error(
+    "Circuit breaker open after {failures} consecutive failures (last: {last_error}); \
+     the API looks down or out of quota, not sending more requests"
+)]
+pub struct CircuitOpen {
+    pub failures: u32,
+    pub last_error: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    #[cfg_attr(not(feature = "__retry"), allow(dead_code))]
+    failure_threshold: u32,
+    consecutive_failures: AtomicU32,
+    /// Retries left across all tasks; `u32::MAX` means unlimited
+    retries_left: AtomicU32,
+    /// Set when tripped
+    open: Mutex<Option<CircuitOpen>>,
+}
+
+impl CircuitBreaker {
+    /// Open after `failure_threshold` consecutive transient failures, with an
+    /// unlimited retry budget
+    pub fn new(failure_threshold: u32) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                failure_threshold: failure_threshold.max(1),
+                consecutive_failures: AtomicU32::new(0),
+                retries_left: AtomicU32::new(u32::MAX),
+                open: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// Allow at most `retries` retries in total across every task sharing
+    /// this breaker; once spent, failures are returned without retrying
+    pub fn with_retry_budget(self, retries: u32) -> Self {
+        self.inner.retries_left.store(retries, Ordering::Relaxed);
+        self
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.inner.open.lock().unwrap().is_some()
+    }
+
+    /// Close the breaker and clear the failure count. The retry budget is
+    /// not refilled.
+    pub fn reset(&self) {
+        self.inner.consecutive_failures.store(0, Ordering::Relaxed);
+        *self.inner.open.lock().unwrap() = None;
+    }
+
+    /// `Err` while open; clients call this before every attempt
+    pub fn check(&self) -> Result<(), CircuitOpen> {
+        match &*self.inner.open.lock().unwrap() {
+            Some(open) => Err(open.clone()),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(feature = "__retry")]
+impl CircuitBreaker {
+    /// Count `result` of one attempt; errors that `is_transient` rejects
+    /// (bad input, auth, ...) don't say anything about API health
+    pub(crate) fn record<T, E: fmt::Display>(
+        &self,
+        result: &Result<T, E>,
+        is_transient: fn(&E) -> bool,
+    ) {
+        match result {
+            Ok(_) => self.record_success(),
+            Err(e) if is_transient(e) => self.record_failure(e),
+            Err(_) => {}
+        }
+    }
+
+    fn record_success(&self) {
+        self.inner.consecutive_failures.store(0, Ordering::Relaxed);
+    }
+
+    /// Count a transient failure, tripping the breaker at the threshold
+    fn record_failure(&self, err: &dyn fmt::Display) {
+        let failures = self
+            .inner
+            .consecutive_failures
+            .fetch_add(1, Ordering::Relaxed)
+            + 1;
+        if failures < self.inner.failure_threshold {
+            return;
+        }
+        let mut open = self.inner.open.lock().unwrap();
+        if open.is_none() {
+            tracing::error!(
+                "Circuit breaker open after {} consecutive failures: {}",
+                failures,
+                err
+            );
+            *open = Some(CircuitOpen {
+                failures,
+                last_error: err.to_string(),
+            });
+        }
+    }
+
+    /// Whether another retry may be sent; spends one unit of the budget
+    pub(crate) fn try_retry(&self) -> bool {
+        if self.is_open() {
+            return false;
+        }
+        let spent =
+            self.inner
+                .retries_left
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |left| match left {
+                    u32::MAX => Some(u32::MAX),
+                    0 => None,
+                    left => Some(left - 1),
+                });
+        if spent.is_err() {
+            tracing::warn!("Retry budget exhausted, not retrying");
+        }
+        spent.is_ok()
+    }
+}
+
+#[cfg(all(test, feature = "__retry"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trips_after_consecutive_failures() {
+        let breaker = CircuitBreaker::new(2);
+        let shared = breaker.clone();
+
+        breaker.record_failure(&"503");
+        breaker.record_success();
+        breaker.record_failure(&"503");
+        assert!(shared.check().is_ok());
+
+        shared.record_failure(&"quota exceeded");
+        let open = breaker.check().unwrap_err();
+        assert_eq!(open.failures, 2);
+        assert!(open.to_string().contains("quota exceeded"));
+        assert!(!breaker.try_retry());
+
+        breaker.reset();
+        assert!(!shared.is_open());
+    }
+
+    #[test]
+    fn test_retry_budget_is_shared() {
+        let breaker = CircuitBreaker::new(10).with_retry_budget(2);
+        let shared = breaker.clone();
+
+        assert!(breaker.try_retry());
+        assert!(shared.try_retry());
+        assert!(!breaker.try_retry());
+        assert!(CircuitBreaker::new(10).try_retry());
+    }
+}
@@ -0,0 +1,962 @@
+//! Flags shared by the command-line tools. Binaries `#[command(flatten)]`
+//! these into their `Args`, so `flashecho` subcommands and the standalone
+//! binaries accept the same options with the same defaults.
+
+use indicatif::{ProgressBar, ProgressStyle};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::process::Command;
+use tracing::Level;
+use tracing_subscriber::FmtSubscriber;
+
+use crate::budget::Budget;
+use crate::cache::ResponseCache;
+use crate::circuit_breaker::CircuitBreaker;
+use crate::config::{self, Config, Profile};
+#[cfg(feature = "transcription")]
+use crate::gemini_api::GenerationParams;
+use crate::json_log::{JsonFields, JsonFormat};
+#[cfg(feature = "keyring")]
+use crate::keyring;
+#[cfg(feature = "transcription")]
+use crate::storage::{OutputStorage, Storage};
+#[cfg(feature = "transcription")]
+use crate::transcript_format::{
+    CueLimits, OutputFormat, SubtitleOptions, VttAlign, VttCueSettings, media_src,
+    segment_timestamp_secs,
+};
+use crate::usage::{self, UsageLedger};
+
+/// Profile, logging, retry and cache flags accepted by every tool
+#[derive(clap::Args, Debug, Clone)]
+pub struct CommonArgs {
+    /// Config file profile to use (API keys, models, output defaults)
+    #[arg(long, value_name = "NAME", env = "FLASHECHO_PROFILE")]
+    pub profile: Option<String>,
+
+    /// Tag recorded with each API call in the usage ledger (`flashecho usage`)
+    #[arg(long, value_name = "TAG", env = "FLASHECHO_PROJECT")]
+    pub project: Option<String>,
+
+    /// Max retry attempts for API calls
+    #[arg(long, default_value = "3")]
+    pub max_retries: u32,
+
+    /// Verbosity level (-v, -vv, -vvv)
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Log output format: human-readable text, or one JSON object per line
+    #[arg(long, value_enum, default_value = "text", env = "FLASHECHO_LOG_FORMAT")]
+    pub log_format: LogFormat,
+
+    /// Quiet mode (no progress output)
+    #[arg(short, long)]
+    pub quiet: bool,
+
+    /// Always call the API instead of reusing a cached response
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// How long cached responses are reused, in hours
+    #[arg(long, value_name = "HOURS", default_value = "168")]
+    pub cache_ttl: u64,
+
+    /// Stop sending requests once the estimated spend reaches this many USD
+    #[arg(long, value_name = "USD", env = "FLASHECHO_MAX_COST")]
+    pub max_cost: Option<f64>,
+
+    /// Stop sending requests once this many input + output tokens are used
+    #[arg(long, value_name = "N", env = "FLASHECHO_MAX_TOKENS")]
+    pub max_tokens: Option<u64>,
+
+    /// Count usage-ledger spend since this date (YYYY-MM-DD) towards the limits
+    #[arg(long, value_name = "DATE", value_parser = parse_date, env = "FLASHECHO_BUDGET_SINCE")]
+    pub budget_since: Option<u64>,
+}
+
+/// How `init_logging` writes log lines
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Compact human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per line with the fields of enclosing spans (`file`,
+    /// `stage`, `request_id`, ...) at the top level, for log shippers
+    Json,
+}
+
+fn parse_date(date: &str) -> Result<u64, String> {
+    usage::parse_date(date).ok_or_else(|| format!("expected YYYY-MM-DD, got '{}'", date))
+}
+
+impl CommonArgs {
+    /// The `--profile` profile (or the config's default profile) from the
+    /// config file at [`Config::default_path`]
+    pub fn load_profile(&self) -> config::Result<Profile> {
+        Config::load_default()?.profile(self.profile.as_deref())
+    }
+
+    /// Record this process's API calls in the usage ledger as `binary`
+    pub fn install_usage_ledger(&self, binary: &str) {
+        usage::install(
+            UsageLedger::new(UsageLedger::default_path(), binary)
+                .with_project(self.project.clone()),
+        );
+    }
+
+    /// Budget shared by every client of the run, if `--max-cost` or
+    /// `--max-tokens` was given; `--budget-since` seeds it from the ledger
+    pub fn budget(&self) -> std::io::Result<Option<Budget>> {
+        if self.max_cost.is_none() && self.max_tokens.is_none() {
+            return Ok(None);
+        }
+        let mut budget = Budget::new();
+        if let Some(usd) = self.max_cost {
+            budget = budget.with_max_cost_usd(usd);
+        }
+        if let Some(tokens) = self.max_tokens {
+            budget = budget.with_max_tokens(tokens);
+        }
+        if let Some(since) = self.budget_since {
+            let records = UsageLedger::read(&UsageLedger::default_path())?;
+            budget = budget.with_prior_usage(&records, since);
+        }
+        Ok(Some(budget))
+    }
+
+    /// [`budget`](Self::budget), or one without limits that only counts
+    /// what the run spends, for the cost in its summary
+    pub fn tracking_budget(&self) -> std::io::Result<Budget> {
+        Ok(self.budget()?.unwrap_or_default())
+    }
+
+    /// Response cache for API clients, unless `--no-cache` was given
+    pub fn response_cache(&self) -> Option<ResponseCache> {
+        (!self.no_cache)
+            .then(|| ResponseCache::with_ttl(Duration::from_secs(self.cache_ttl * 3600)))
+    }
+}
+
+/// Flags that stop a batch early when the API keeps failing
+#[derive(clap::Args, Debug, Clone)]
+pub struct BatchLimitArgs {
+    /// Stop the batch after N consecutive API failures across all tasks (0 disables)
+    #[arg(long, value_name = "N", default_value = "5")]
+    pub max_consecutive_failures: u32,
+
+    /// Max retries across the whole batch (default: unlimited)
+    #[arg(long, value_name = "N")]
+    pub retry_budget: Option<u32>,
+}
+
+impl BatchLimitArgs {
+    /// Breaker shared by every task of the batch, unless disabled
+    pub fn circuit_breaker(&self) -> Option<CircuitBreaker> {
+        if self.max_consecutive_failures == 0 && self.retry_budget.is_none() {
+            return None;
+        }
+        let threshold = match self.max_consecutive_failures {
+            0 => u32::MAX,
+            n => n,
+        };
+        let breaker = CircuitBreaker::new(threshold);
+        Some(match self.retry_budget {
+            Some(retries) => breaker.with_retry_budget(retries),
+            None => breaker,
+        })
+    }
+}
+
+/// ffmpeg binary and decoding flags for the tools that extract audio from video
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct FfmpegArgs {
+    /// ffmpeg binary to run [default: ffmpeg on PATH]
+    #[arg(long, value_name = "PATH", env = "FLASHECHO_FFMPEG")]
+    pub ffmpeg_path: Option<PathBuf>,
+
+    /// Hardware-accelerated decoding, passed to ffmpeg as -hwaccel (auto,
+    /// cuda, vaapi, qsv, videotoolbox, ...)
+    #[arg(long, value_name = "METHOD", env = "FLASHECHO_HWACCEL")]
+    pub hwaccel: Option<String>,
+
+    /// Threads ffmpeg may use, passed as -threads [default: ffmpeg's choice]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub ffmpeg_threads: Option<u32>,
+}
+
+impl FfmpegArgs {
+    /// The ffmpeg binary to run
+    pub fn program(&self) -> &Path {
+        self.ffmpeg_path.as_deref().unwrap_or(Path::new("ffmpeg"))
+    }
+
+    /// An ffmpeg command with the acceleration and thread flags already
+    /// added; append `-i` and the rest
+    pub fn command(&self) -> Command {
+        let mut command = Command::new(self.program());
+        if let Some(hwaccel) = &self.hwaccel {
+            command.args(["-hwaccel", hwaccel]);
+        }
+        if let Some(threads) = self.ffmpeg_threads {
+            command.args(["-threads", &threads.to_string()]);
+        }
+        command
+    }
+
+    /// Run `ffmpeg -version`, so a missing binary fails before any upload or
+    /// API request rather than halfway through
+    pub async fn check(&self) -> std::io::Result<()> {
+        let output = Command::new(self.program())
+            .arg("-version")
+            .output()
+            .await?;
+        if !output.status.success() {
+            return Err(std::io::Error::other(format!(
+                "`-version` exited with {}",
+                output.status
+            )));
+        }
+        Ok(())
+    }
+
+    /// The ffprobe binary next to [`FfmpegArgs::program`]: `/opt/bin/ffmpeg`
+    /// gives `/opt/bin/ffprobe`, plain `ffmpeg` gives `ffprobe` on PATH
+    pub fn probe_program(&self) -> PathBuf {
+        let program = self.program();
+        let name = program
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        let probe = if name.contains("ffmpeg") {
+            name.replacen("ffmpeg", "ffprobe", 1)
+        } else {
+            "ffprobe".to_string()
+        };
+        program.with_file_name(probe)
+    }
+
+    /// Duration of `media` in seconds from ffprobe, `None` when ffprobe is
+    /// missing or can't tell. Only used for estimates, so failures are logged
+    /// rather than returned.
+    pub async fn probe_duration(&self, media: &Path) -> Option<f64> {
+        let output = Command::new(self.probe_program())
+            .args([
+                "-v",
+                "error",
+                "-show_entries",
+                "format=duration",
+                "-of",
+                "default=noprint_wrappers=1:nokey=1",
+            ])
+            .arg(media)
+            .output()
+            .await;
+        match output {
+            Ok(output) if output.status.success() => {
+                parse_probe_duration(&String::from_utf8_lossy(&output.stdout))
+            }
+            Ok(output) => {
+                tracing::debug!(
+                    "ffprobe failed for {:?}: {}",
+                    media,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+                None
+            }
+            Err(e) => {
+                tracing::debug!("Cannot run ffprobe for {:?}: {}", media, e);
+                None
+            }
+        }
+    }
+
+    /// Error message when [`FfmpegArgs::check`] fails
+    pub fn missing(&self) -> String {
+        match &self.ffmpeg_path {
+            Some(path) => format!(
+                "Cannot run ffmpeg at {:?} (--ffmpeg-path or FLASHECHO_FFMPEG)",
+                path
+            ),
+            None => "Cannot run ffmpeg: install it, or point --ffmpeg-path or FLASHECHO_FFMPEG \
+                     at the binary"
+                .to_string(),
+        }
+    }
+}
+
+/// `H:MM:SS`, or `M:SS` under an hour
+pub fn format_duration(secs: f64) -> String {
+    let secs = secs.round() as u64;
+    let (hours, minutes, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, secs)
+    } else {
+        format!("{}:{:02}", minutes, secs)
+    }
+}
+
+/// Spend of a run for its summary: what `budget` counted since it stood at
+/// `start` (its `spent_usd` and `spent_tokens` then). `None` when nothing
+/// was spent, e.g. when every answer came from the cache.
+pub fn spend_summary(budget: &Budget, start: (f64, u64)) -> Option<String> {
+    let usd = (budget.spent_usd() - start.0).max(0.0);
+    let tokens = budget.spent_tokens().saturating_sub(start.1);
+    if usd == 0.0 && tokens == 0 {
+        return None;
+    }
+    Some(match tokens {
+        0 => format!("Estimated cost: ${:.4}", usd),
+        tokens => format!("Estimated cost: ${:.4} ({} tokens)", usd, tokens),
+    })
+}
+
+/// Summary printed by `--dry-run`: how much media would be transcribed and
+/// roughly what it costs with `model`. `durations` are the probed lengths of
+/// the files that would be sent, `None` where ffprobe couldn't tell.
+pub fn dry_run_summary(model: &str, durations: &[Option<f64>]) -> String {
+    let total: f64 = durations.iter().flatten().sum();
+    let unknown = durations.iter().filter(|d| d.is_none()).count();
+    let mut summary = format!(
+        "{} files to transcribe, {} of media",
+        durations.len(),
+        format_duration(total)
+    );
+    if unknown > 0 {
+        summary.push_str(&format!(" ({} of unknown length)", unknown));
+    }
+    if unknown > 0 && unknown == durations.len() {
+        summary.push_str("\nNo cost estimate: ffprobe could not read any duration");
+        return summary;
+    }
+    match usage::estimate_transcription_cost(model, total) {
+        Some(cost) if unknown > 0 => summary.push_str(&format!(
+            "\nEstimated cost: at least ${:.2} with {}",
+            cost, model
+        )),
+        Some(cost) => summary.push_str(&format!("\nEstimated cost: ${:.2} with {}", cost, model)),
+        None => summary.push_str(&format!("\nNo price known for {}", model)),
+    }
+    summary
+}
+
+/// Seconds from `ffprobe -show_entries format=duration` output (`83.450000`,
+/// or `N/A` for streams without one)
+fn parse_probe_duration(stdout: &str) -> Option<f64> {
+    stdout
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|secs| secs.is_finite() && *secs > 0.0)
+}
+
+/// Seconds of `HH:MM:SS`, `MM:SS` or a plain number of seconds, for options
+/// such as `--offset` and `--from`
+#[cfg(feature = "transcription")]
+pub fn parse_time(value: &str) -> Result<u64, String> {
+    value
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .or_else(|| segment_timestamp_secs(value))
+        .ok_or_else(|| {
+            format!(
+                "Invalid time '{}': expected HH:MM:SS, MM:SS or seconds",
+                value
+            )
+        })
+}
+
+/// Player `src` of the HTML transcript of `input` written to `key` in
+/// `storage`: the path from the page to `input` for local output, and the
+/// input's file name in an object store, where the page plays the media once
+/// it is uploaded next to it
+#[cfg(feature = "transcription")]
+pub fn html_media_src(input: &Path, storage: &OutputStorage, key: &str) -> String {
+    if matches!(storage, OutputStorage::Local(_)) {
+        media_src(input, Path::new(&storage.location(key)))
+    } else {
+        media_src(
+            Path::new(input.file_name().unwrap_or_default()),
+            Path::new("transcript.html"),
+        )
+    }
+}
+
+/// The `--also-format` outputs of the transcript at `key`: each format with
+/// the key under that format's extension, skipping `format` itself and
+/// repeated extensions
+#[cfg(feature = "transcription")]
+pub fn also_format_keys(
+    key: &str,
+    format: OutputFormat,
+    also: &[OutputFormat],
+) -> Vec<(OutputFormat, String)> {
+    let mut extensions = vec![format.extension()];
+    let mut outputs = Vec::new();
+    for &other in also {
+        if extensions.contains(&other.extension()) {
+            continue;
+        }
+        extensions.push(other.extension());
+        let other_key = Path::new(key).with_extension(other.extension());
+        outputs.push((other, other_key.to_string_lossy().into_owned()));
+    }
+    outputs
+}
+
+/// The per-language outputs of the transcript at `key` for `--translate-to`:
+/// each language with the key under `<stem>.<language>.<extension>`, a
+/// language with spaces or slashes written with dashes. JSON and JSONL carry
+/// every translation in the main output and get none.
+#[cfg(feature = "transcription")]
+pub fn translation_keys(
+    key: &str,
+    format: OutputFormat,
+    languages: &[String],
+) -> Vec<(String, String)> {
+    if matches!(format, OutputFormat::Json | OutputFormat::Jsonl) {
+        return Vec::new();
+    }
+    let suffix = format!(".{}", format.extension());
+    let stem = key.strip_suffix(&suffix).unwrap_or(key);
+    let mut outputs: Vec<(String, String)> = Vec::new();
+    for language in languages.iter().map(|l| l.trim()) {
+        if language.is_empty() || outputs.iter().any(|(seen, _)| seen == language) {
+            continue;
+        }
+        let tag: String = language
+            .chars()
+            .map(|c| {
+                if c.is_whitespace() || c == '/' || c == '\\' {
+                    '-'
+                } else {
+                    c
+                }
+            })
+            .collect();
+        outputs.push((language.to_string(), format!("{}.{}{}", stem, tag, suffix)));
+    }
+    outputs
+}
+
+/// Readability limits for SRT and VTT output, VTT cue placement and the
+/// frame rate of marker timecodes
+#[cfg(feature = "transcription")]
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct SubtitleArgs {
+    /// Wrap subtitle lines at N characters, speaker label included
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_line_chars: Option<u32>,
+
+    /// Split subtitle cues with more than N lines (needs --max-line-chars)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_lines: Option<u32>,
+
+    /// Split subtitle cues longer than this many seconds
+    #[arg(long, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_cue_duration: Option<u64>,
+
+    /// VTT cue line: a line number (negative counts from the bottom) or a percentage
+    #[arg(long, value_name = "LINE", allow_hyphen_values = true, value_parser = VttCueSettings::parse_line)]
+    pub vtt_line: Option<String>,
+
+    /// VTT cue position: a percentage of the video width
+    #[arg(long, value_name = "PERCENT", value_parser = VttCueSettings::parse_position)]
+    pub vtt_position: Option<String>,
+
+    /// VTT cue text alignment
+    #[arg(long, value_enum, value_name = "ALIGN")]
+    pub vtt_align: Option<VttAlign>,
+
+    /// Frame rate of `markers` timecodes, e.g. 24, 25 or 60 to match the
+    /// editor's sequence [default: 30]
+    #[arg(long, value_name = "FPS", value_parser = clap::value_parser!(u32).range(1..=1000))]
+    pub marker_fps: Option<u32>,
+}
+
+#[cfg(feature = "transcription")]
+impl SubtitleArgs {
+    pub fn options(&self) -> SubtitleOptions {
+        SubtitleOptions {
+            limits: CueLimits {
+                max_line_chars: self.max_line_chars.map(|n| n as usize),
+                max_lines: self.max_lines.map(|n| n as usize),
+                max_duration_secs: self.max_cue_duration,
+            },
+            vtt_settings: VttCueSettings {
+                line: self.vtt_line.clone(),
+                position: self.vtt_position.clone(),
+                align: self.vtt_align,
+            },
+            marker_fps: self.marker_fps,
+        }
+    }
+}
+
+/// Sampling, answer length and thinking settings for transcription requests
+#[cfg(feature = "transcription")]
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct GenerationArgs {
+    /// Sampling temperature, 0.0 to 2.0; lower is more deterministic (Gemini only)
+    #[arg(long, value_name = "T", value_parser = parse_temperature)]
+    pub temperature: Option<f32>,
+
+    /// Sample only from tokens within this cumulative probability, 0.0 to
+    /// 1.0 (Gemini only)
+    #[arg(long, value_name = "P", value_parser = parse_top_p)]
+    pub top_p: Option<f32>,
+
+    /// Sample only from the K most likely tokens (Gemini only)
+    #[arg(long, value_name = "K", value_parser = clap::value_parser!(u32).range(1..))]
+    pub top_k: Option<u32>,
+
+    /// Longest answer in tokens; a cut-off transcript keeps its complete
+    /// segments (Gemini only)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_output_tokens: Option<u32>,
+
+    /// Tokens the model may spend thinking; 0 turns thinking off, -1 lets
+    /// the model decide (Gemini only)
+    #[arg(long, value_name = "N", allow_hyphen_values = true, value_parser = clap::value_parser!(i32).range(-1..))]
+    pub thinking_budget: Option<i32>,
+}
+
+#[cfg(feature = "transcription")]
+impl GenerationArgs {
+    pub fn params(&self) -> GenerationParams {
+        GenerationParams {
+            temperature: self.temperature,
+            top_p: self.top_p,
+            top_k: self.top_k,
+            max_output_tokens: self.max_output_tokens,
+            thinking_budget: self.thinking_budget,
+        }
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.params() != GenerationParams::default()
+    }
+}
+
+#[cfg(feature = "transcription")]
+fn parse_unit_interval(value: &str, max: f32) -> Result<f32, String> {
+    value
+        .trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|n| (0.0..=max).contains(n))
+        .ok_or_else(|| format!("Invalid value '{}': expected 0.0 to {:.1}", value, max))
+}
+
+#[cfg(feature = "transcription")]
+fn parse_temperature(value: &str) -> Result<f32, String> {
+    parse_unit_interval(value, 2.0)
+}
+
+#[cfg(feature = "transcription")]
+fn parse_top_p(value: &str) -> Result<f32, String> {
+    parse_unit_interval(value, 1.0)
+}
+
+/// Instructions that replace the built-in transcription prompt (Gemini only)
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct PromptArgs {
+    /// Transcription instructions to send instead of the built-in prompt,
+    /// e.g. terminology or formatting rules (Gemini only)
+    #[arg(long, value_name = "TEXT", conflicts_with = "prompt_file")]
+    pub prompt: Option<String>,
+
+    /// Read the transcription instructions from a file (Gemini only)
+    #[arg(long, value_name = "FILE")]
+    pub prompt_file: Option<PathBuf>,
+}
+
+impl PromptArgs {
+    /// The custom prompt, if one was given; a blank one counts as none
+    pub async fn load(&self) -> std::io::Result<Option<String>> {
+        let prompt = match (&self.prompt_file, &self.prompt) {
+            (Some(path), _) => tokio::fs::read_to_string(path).await?,
+            (None, Some(prompt)) => prompt.clone(),
+            (None, None) => return Ok(None),
+        };
+        Ok(Some(prompt.trim().to_string()).filter(|prompt| !prompt.is_empty()))
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.prompt.is_some() || self.prompt_file.is_some()
+    }
+}
+
+/// Environment variables checked (in order) for the Gemini API key
+pub const GEMINI_API_KEY_VARS: &[&str] = &["GEMINI_API_KEY", "GOOGLE_AI_KEY"];
+
+/// OS keyring account holding the Gemini API key
+pub const GEMINI_KEYRING_ACCOUNT: &str = "gemini";
+
+/// API key from the first of `env_vars` that is set, else the profile's key,
+/// else the OS keyring entry for `keyring_account` (with the `keyring` feature)
+pub fn api_key(
+    env_vars: &[&str],
+    profile_key: Option<&str>,
+    keyring_account: &str,
+) -> Option<String> {
+    env_vars
+        .iter()
+        .find_map(|var| std::env::var(var).ok())
+        .or_else(|| profile_key.map(str::to_string))
+        .or_else(|| stored_api_key(keyring_account))
+}
+
+#[cfg(feature = "keyring")]
+fn stored_api_key(keyring_account: &str) -> Option<String> {
+    keyring::lookup(keyring_account)
+}
+
+#[cfg(not(feature = "keyring"))]
+fn stored_api_key(_keyring_account: &str) -> Option<String> {
+    None
+}
+
+/// Error message when [`api_key`] finds nothing
+pub fn missing_api_key(env_vars: &[&str]) -> String {
+    let vars = env_vars.join(" or ");
+    if cfg!(feature = "keyring") {
+        format!(
+            "{} environment variable is not set, the config profile has no key and the OS \
+             keyring has none (store one with `flashecho auth login`)",
+            vars
+        )
+    } else {
+        format!(
+            "{} environment variable is not set and the config profile has no key",
+            vars
+        )
+    }
+}
+
+/// Gemini API key from the environment, `profile` or the OS keyring
+pub fn gemini_api_key(profile: &Profile) -> anyhow::Result<String> {
+    let profile_key = profile.gemini_api_key.as_deref();
+    api_key(GEMINI_API_KEY_VARS, profile_key, GEMINI_KEYRING_ACCOUNT)
+        .ok_or_else(|| anyhow::anyhow!(missing_api_key(GEMINI_API_KEY_VARS)))
+}
+
+/// Elapsed-time spinner showing `message`, or `None` when `quiet`
+pub fn spinner(quiet: bool, message: String) -> Option<ProgressBar> {
+    if quiet {
+        return None;
+    }
+    let pb = ProgressBar::new_spinner();
+    pb.set_style(
+        ProgressStyle::default_spinner()
+            .template("{spinner:.green} {msg} ({elapsed})")
+            .unwrap(),
+    );
+    pb.enable_steady_tick(Duration::from_millis(100));
+    pb.set_message(message);
+    Some(pb)
+}
+
+/// Log at WARN, INFO, DEBUG or TRACE for `verbose` 0, 1, 2 and 3+
+pub fn init_logging(verbose: u8, format: LogFormat) {
+    let level = match verbose {
+        0 => Level::WARN,
+        1 => Level::INFO,
+        2 => Level::DEBUG,
+        _ => Level::TRACE,
+    };
+
+    match format {
+        LogFormat::Text => {
+            let subscriber = FmtSubscriber::builder()
+                .with_max_level(level)
+                .with_target(false)
+                .with_thread_ids(false)
+                .with_file(false)
+                .with_line_number(false)
+                .compact()
+                .finish();
+            tracing::subscriber::set_global_default(subscriber).ok();
+        }
+        LogFormat::Json => {
+            let subscriber = FmtSubscriber::builder()
+                .with_max_level(level)
+                .with_ansi(false)
+                .fmt_fields(JsonFields)
+                .event_format(JsonFormat)
+                .finish();
+            tracing::subscriber::set_global_default(subscriber).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct Args {
+        #[command(flatten)]
+        common: CommonArgs,
+        #[command(flatten)]
+        limits: BatchLimitArgs,
+    }
+
+    #[cfg(feature = "transcription")]
+    #[test]
+    fn test_translation_keys() {
+        let languages = ["es".to_string(), " zh-TW".to_string(), "es".to_string()];
+        assert_eq!(
+            translation_keys("out/talk.srt", OutputFormat::Srt, &languages),
+            [
+                ("es".to_string(), "out/talk.es.srt".to_string()),
+                ("zh-TW".to_string(), "out/talk.zh-TW.srt".to_string())
+            ]
+        );
+        assert_eq!(
+            translation_keys(
+                "talk.labels.txt",
+                OutputFormat::Audacity,
+                &["Brazilian Portuguese".to_string()]
+            ),
+            [(
+                "Brazilian Portuguese".to_string(),
+                "talk.Brazilian-Portuguese.labels.txt".to_string()
+            )]
+        );
+        assert!(translation_keys("talk.json", OutputFormat::Json, &languages).is_empty());
+    }
+
+    #[cfg(feature = "transcription")]
+    #[test]
+    fn test_also_format_keys() {
+        let keys = also_format_keys(
+            "out/talk.json",
+            OutputFormat::Json,
+            &[
+                OutputFormat::Srt,
+                OutputFormat::Json,
+                OutputFormat::Txt,
+                OutputFormat::Srt,
+            ],
+        );
+        assert_eq!(
+            keys,
+            [
+                (OutputFormat::Srt, "out/talk.srt".to_string()),
+                (OutputFormat::Txt, "out/talk.txt".to_string())
+            ]
+        );
+    }
+
+    #[cfg(feature = "transcription")]
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(parse_time("00:30:00"), Ok(1800));
+        assert_eq!(parse_time("45:00"), Ok(2700));
+        assert_eq!(parse_time("90"), Ok(90));
+        assert!(parse_time("half an hour").is_err());
+    }
+
+    #[test]
+    fn test_defaults() {
+        let args = Args::parse_from(["tool"]);
+        assert_eq!(args.common.max_retries, 3);
+        assert_eq!(args.common.log_format, LogFormat::Text);
+        assert!(args.common.response_cache().is_some());
+        assert!(args.limits.circuit_breaker().is_some());
+        assert!(args.common.budget().unwrap().is_none());
+    }
+
+    #[cfg(feature = "transcription")]
+    #[test]
+    fn test_generation_args() {
+        #[derive(Parser)]
+        struct Args {
+            #[command(flatten)]
+            generation: GenerationArgs,
+        }
+        let args = Args::parse_from(["tool"]);
+        assert!(!args.generation.is_set());
+        let args = Args::parse_from([
+            "tool",
+            "--temperature",
+            "0.2",
+            "--top-k",
+            "40",
+            "--thinking-budget",
+            "-1",
+        ]);
+        assert!(args.generation.is_set());
+        let params = args.generation.params();
+        assert_eq!(params.temperature, Some(0.2));
+        assert_eq!(params.top_k, Some(40));
+        assert_eq!(params.thinking_budget, Some(-1));
+        for bad in [
+            ["--temperature", "2.5"],
+            ["--top-p", "1.1"],
+            ["--top-p", "-0.1"],
+            ["--thinking-budget", "-2"],
+            ["--max-output-tokens", "0"],
+        ] {
+            assert!(Args::try_parse_from(["tool", bad[0], bad[1]]).is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_prompt_args() {
+        #[derive(Parser)]
+        struct Args {
+            #[command(flatten)]
+            prompt: PromptArgs,
+        }
+        let args = Args::parse_from(["tool", "--prompt", " Use ICD-10 terms. "]);
+        assert_eq!(
+            args.prompt.load().await.unwrap().as_deref(),
+            Some("Use ICD-10 terms.")
+        );
+        let args = Args::parse_from(["tool", "--prompt", "  "]);
+        assert!(args.prompt.is_set());
+        assert_eq!(args.prompt.load().await.unwrap(), None);
+        assert_eq!(
+            Args::parse_from(["tool"]).prompt.load().await.unwrap(),
+            None
+        );
+        assert!(Args::try_parse_from(["tool", "--prompt", "a", "--prompt-file", "b"]).is_err());
+    }
+
+    #[test]
+    fn test_budget_limits() {
+        let args = Args::parse_from(["tool", "--max-cost", "2.5", "--max-tokens", "1000"]);
+        assert_eq!(args.common.max_cost, Some(2.5));
+        assert!(args.common.budget().unwrap().unwrap().check().is_ok());
+        assert!(Args::try_parse_from(["tool", "--budget-since", "June"]).is_err());
+    }
+
+    #[test]
+    fn test_disabled() {
+        let args = Args::parse_from(["tool", "--no-cache", "--max-consecutive-failures", "0"]);
+        assert!(args.common.response_cache().is_none());
+        assert!(args.limits.circuit_breaker().is_none());
+    }
+
+    #[test]
+    fn test_log_format() {
+        let args = Args::parse_from(["tool", "--log-format", "json"]);
+        assert_eq!(args.common.log_format, LogFormat::Json);
+        assert!(Args::try_parse_from(["tool", "--log-format", "xml"]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ffmpeg_command() {
+        let ffmpeg = FfmpegArgs {
+            ffmpeg_path: Some(PathBuf::from("/opt/media/bin/ffmpeg-missing")),
+            hwaccel: Some("cuda".to_string()),
+            ffmpeg_threads: Some(4),
+        };
+        let command = ffmpeg.command();
+        let command = command.as_std();
+        assert_eq!(command.get_program(), "/opt/media/bin/ffmpeg-missing");
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(args, ["-hwaccel", "cuda", "-threads", "4"]);
+
+        assert!(ffmpeg.check().await.is_err());
+        assert!(ffmpeg.missing().contains("--ffmpeg-path"));
+        assert_eq!(FfmpegArgs::default().program(), Path::new("ffmpeg"));
+        assert_eq!(
+            ffmpeg.probe_program(),
+            Path::new("/opt/media/bin/ffprobe-missing")
+        );
+        assert_eq!(FfmpegArgs::default().probe_program(), Path::new("ffprobe"));
+        assert_eq!(ffmpeg.probe_duration(Path::new("talk.mp4")).await, None);
+    }
+
+    #[test]
+    fn test_dry_run_summary() {
+        assert_eq!(format_duration(83.4), "1:23");
+        assert_eq!(format_duration(3723.0), "1:02:03");
+        assert_eq!(
+            dry_run_summary("whisper-1", &[Some(600.0), Some(1200.0)]),
+            "2 files to transcribe, 30:00 of media\nEstimated cost: $0.18 with whisper-1"
+        );
+        assert_eq!(
+            dry_run_summary("whisper-1", &[Some(600.0), None]),
+            "2 files to transcribe, 10:00 of media (1 of unknown length)\n\
+             Estimated cost: at least $0.06 with whisper-1"
+        );
+        assert!(dry_run_summary("my-model", &[]).ends_with("No price known for my-model"));
+        assert!(dry_run_summary("whisper-1", &[None]).ends_with("could not read any duration"));
+    }
+
+    #[test]
+    fn test_spend_summary() {
+        let call = |input_tokens, output_tokens| usage::UsageRecord {
+            model: "gemini-2.5-flash-image".to_string(),
+            input_tokens,
+            output_tokens,
+            ..Default::default()
+        };
+        let budget = Budget::new();
+        budget.charge(&call(0, 1290));
+        let start = (budget.spent_usd(), budget.spent_tokens());
+        assert_eq!(spend_summary(&budget, start), None);
+
+        budget.clone().charge(&call(10, 1290));
+        assert_eq!(
+            spend_summary(&budget, start).unwrap(),
+            "Estimated cost: $0.0387 (1300 tokens)"
+        );
+    }
+
+    #[test]
+    fn test_parse_probe_duration() {
+        assert_eq!(parse_probe_duration("83.450000\n"), Some(83.45));
+        assert_eq!(parse_probe_duration("N/A\n"), None);
+        assert_eq!(parse_probe_duration(""), None);
+    }
+
+    #[cfg(feature = "transcription")]
+    #[test]
+    fn test_subtitle_limits() {
+        #[derive(Parser)]
+        struct SubtitleTool {
+            #[command(flatten)]
+            subtitles: SubtitleArgs,
+        }
+
+        let args = SubtitleTool::parse_from(["tool"]);
+        assert_eq!(args.subtitles.options(), SubtitleOptions::default());
+        let args = SubtitleTool::parse_from([
+            "tool",
+            "--max-line-chars",
+            "42",
+            "--max-lines",
+            "2",
+            "--max-cue-duration",
+            "6",
+            "--vtt-line",
+            "-2",
+            "--vtt-align",
+            "center",
+            "--marker-fps",
+            "25",
+        ]);
+        let options = args.subtitles.options();
+        assert_eq!(
+            options.limits,
+            CueLimits {
+                max_line_chars: Some(42),
+                max_lines: Some(2),
+                max_duration_secs: Some(6),
+            }
+        );
+        assert_eq!(options.vtt_settings.line.as_deref(), Some("-2"));
+        assert_eq!(options.vtt_settings.align, Some(VttAlign::Center));
+        assert_eq!(options.marker_fps, Some(25));
+        assert!(SubtitleTool::try_parse_from(["tool", "--marker-fps", "0"]).is_err());
+        assert!(SubtitleTool::try_parse_from(["tool", "--max-lines", "0"]).is_err());
+        assert!(SubtitleTool::try_parse_from(["tool", "--vtt-position", "50"]).is_err());
+    }
+}
@@ -0,0 +1,167 @@
+use reqwest::Client;
+use std::time::Duration;
+
+#[cfg(feature = "embeddings")]
+use crate::embeddings_api::{EmbeddingsClient, EmbeddingsClientConfig};
+#[cfg(feature = "file-api")]
+use crate::file_api::FileApiClient;
+#[cfg(feature = "transcription")]
+use crate::gemini_api::{GeminiClient, GeminiClientConfig};
+#[cfg(feature = "imagen")]
+use crate::imagen_api::{ImagenClient, ImagenClientConfig};
+#[cfg(feature = "imagen-edit")]
+use crate::imagen_edit_api::{ImageEditClient, ImageEditClientConfig};
+#[cfg(feature = "music")]
+use crate::music_api::{MusicClient, MusicClientConfig};
+use crate::proxy::ProxyConfig;
+use crate::redact::ApiKey;
+#[cfg(feature = "text")]
+use crate::text_api::{TextClient, TextClientConfig};
+use crate::tls::TlsConfig;
+use crate::transport::TransportConfig;
+#[cfg(feature = "tts")]
+use crate::tts_api::{TtsClient, TtsClientConfig};
+#[cfg(feature = "veo")]
+use crate::veo_api::{VeoClient, VeoClientConfig};
+
+const DEFAULT_TIMEOUT_SECS: u64 = 600;
+const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 30;
+
+/// Builder for [`Clients`]
+#[derive(Debug, Clone)]
+pub struct ClientsBuilder {
+    api_key: ApiKey,
+    timeout_secs: u64,
+    connect_timeout_secs: u64,
+    proxy: ProxyConfig,
+    tls: TlsConfig,
+    transport: TransportConfig,
+}
+
+impl ClientsBuilder {
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self {
+            api_key: ApiKey::from(api_key.into()),
+            timeout_secs: DEFAULT_TIMEOUT_SECS,
+            connect_timeout_secs: DEFAULT_CONNECT_TIMEOUT_SECS,
+            proxy: ProxyConfig::default(),
+            tls: TlsConfig::default(),
+            transport: TransportConfig::default(),
+        }
+    }
+
+    /// Timeout for requests without their own (File API uploads). Gemini,
+    /// Imagen, ImageEdit, Veo and TTS requests use the timeout from their config.
+    pub fn timeout_secs(mut self, secs: u64) -> Self {
+        self.timeout_secs = secs;
+        self
+    }
+
+    pub fn connect_timeout_secs(mut self, secs: u64) -> Self {
+        self.connect_timeout_secs = secs;
+        self
+    }
+
+    /// Proxy for every client sharing the pool
+    pub fn proxy(mut self, proxy: ProxyConfig) -> Self {
+        self.proxy = proxy;
+        self
+    }
+
+    /// Extra root certificates and builder hook for every client sharing the pool
+    pub fn tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = tls;
+        self
+    }
+
+    /// Pool, keep-alive and HTTP/2 settings of the shared pool
+    pub fn transport(mut self, transport: TransportConfig) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    pub fn build(self) -> reqwest::Result<Clients> {
+        let builder =
+            crate::proxy::apply(crate::rt::client_builder(self.timeout_secs), &self.proxy)?;
+        #[cfg(not(target_arch = "wasm32"))]
+        let builder = builder.connect_timeout(Duration::from_secs(self.connect_timeout_secs));
+        let builder = crate::transport::apply(builder, &self.transport);
+        let http = crate::tls::apply(builder, &self.tls)?.build()?;
+
+        Ok(Clients {
+            http,
+            api_key: self.api_key,
+        })
+    }
+}
+
+/// One API key and one connection pool shared by every Gemini API client.
+/// Cheap to clone; clones share the pool.
+#[derive(Debug, Clone)]
+pub struct Clients {
+    http: Client,
+    api_key: ApiKey,
+}
+
+impl Clients {
+    pub fn builder(api_key: impl Into<String>) -> ClientsBuilder {
+        ClientsBuilder::new(api_key)
+    }
+
+    pub fn new(api_key: impl Into<String>) -> reqwest::Result<Self> {
+        ClientsBuilder::new(api_key).build()
+    }
+
+    pub fn http_client(&self) -> &Client {
+        &self.http
+    }
+
+    pub fn api_key(&self) -> &str {
+        self.api_key.expose()
+    }
+
+    #[cfg(feature = "transcription")]
+    pub fn gemini(&self, config: GeminiClientConfig) -> GeminiClient {
+        GeminiClient::with_http_client(self.http.clone(), self.api_key().to_string(), config)
+    }
+
+    #[cfg(feature = "file-api")]
+    pub fn file_api(&self) -> FileApiClient {
+        FileApiClient::new(self.http.clone(), self.api_key().to_string())
+    }
+
+    #[cfg(feature = "imagen")]
+    pub fn imagen(&self, config: ImagenClientConfig) -> ImagenClient {
+        ImagenClient::with_http_client(self.http.clone(), self.api_key().to_string(), config)
+    }
+
+    #[cfg(feature = "imagen-edit")]
+    pub fn image_edit(&self, config: ImageEditClientConfig) -> ImageEditClient {
+        ImageEditClient::with_http_client(self.http.clone(), self.api_key().to_string(), config)
+    }
+
+    #[cfg(feature = "veo")]
+    pub fn veo(&self, config: VeoClientConfig) -> VeoClient {
+        VeoClient::with_http_client(self.http.clone(), self.api_key().to_string(), config)
+    }
+
+    #[cfg(feature = "tts")]
+    pub fn tts(&self, config: TtsClientConfig) -> TtsClient {
+        TtsClient::with_http_client(self.http.clone(), self.api_key().to_string(), config)
+    }
+
+    #[cfg(feature = "music")]
+    pub fn music(&self, config: MusicClientConfig) -> MusicClient {
+        MusicClient::with_http_client(self.http.clone(), self.api_key().to_string(), config)
+    }
+
+    #[cfg(feature = "embeddings")]
+    pub fn embeddings(&self, config: EmbeddingsClientConfig) -> EmbeddingsClient {
+        EmbeddingsClient::with_http_client(self.http.clone(), self.api_key().to_string(), config)
+    }
+
+    #[cfg(feature = "text")]
+    pub fn text(&self, config: TextClientConfig) -> TextClient {
+        TextClient::with_http_client(self.http.clone(), self.api_key().to_string(), config)
+    }
+}
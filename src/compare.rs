@@ -0,0 +1,90 @@
+use anyhow::{Context, Result};
+use clap::Parser;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tracing::{Instrument, debug, info_span};
+
+use transcript_tool::cli::{LogFormat, init_logging};
+use transcript_tool::transcript_diff::DEFAULT_MAX_DRIFT_SECS;
+use transcript_tool::{TranscriptDiff, TranscriptResponse};
+
+#[derive(Parser, Debug)]
+#[command(name = "compare")]
+#[command(version)]
+#[command(about = "Compare two transcripts of the same audio segment by segment")]
+#[command(after_help = "EXAMPLES:
+    compare talk.flash.json talk.pro.json
+    compare talk.flash.json talk.pro.json --all --max-drift 5
+    compare a.json b.json --json | jq '.text_similarity'")]
+pub struct Args {
+    /// Transcript JSON written by `convert` or `batch_convert`
+    #[arg(value_name = "A")]
+    a: PathBuf,
+
+    /// Transcript of the same audio to compare against
+    #[arg(value_name = "B")]
+    b: PathBuf,
+
+    /// Largest timestamp difference in seconds of two segments that line up
+    #[arg(long, value_name = "SECONDS", default_value_t = DEFAULT_MAX_DRIFT_SECS)]
+    max_drift: u64,
+
+    /// List every segment, not only those that differ
+    #[arg(long)]
+    all: bool,
+
+    /// Print the comparison as JSON
+    #[arg(long)]
+    json: bool,
+
+    /// Verbosity level (-v, -vv, -vvv)
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Log output format: human-readable text, or one JSON object per line
+    #[arg(long, value_enum, default_value = "text", env = "FLASHECHO_LOG_FORMAT")]
+    log_format: LogFormat,
+}
+
+async fn read_transcript(path: &Path) -> Result<TranscriptResponse> {
+    let content = fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Failed to read transcript {:?}", path))?;
+    TranscriptResponse::from_json_any_version(&content)
+        .with_context(|| format!("Failed to parse transcript JSON {:?}", path))
+}
+
+/// Run with parsed arguments; shared by this binary and its `flashecho` subcommand
+pub async fn run(args: Args) -> Result<()> {
+    init_logging(args.verbose, args.log_format);
+
+    let span = info_span!("file", file = %args.a.display());
+    compare(args).instrument(span).await
+}
+
+async fn compare(args: Args) -> Result<()> {
+    let a = read_transcript(&args.a).await?;
+    let b = read_transcript(&args.b).await?;
+
+    let diff = TranscriptDiff::compare(&a, &b, args.max_drift);
+    debug!(
+        "{} of {} segments matched, {} speaker disagreements",
+        diff.matched, diff.segments_a, diff.speaker_disagreements
+    );
+    if args.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&diff).context("Failed to serialize comparison")?
+        );
+    } else {
+        println!("A: {}", args.a.display());
+        println!("B: {}", args.b.display());
+        print!("{}", diff.to_text(args.all));
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    run(Args::parse()).await
+}
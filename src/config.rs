@@ -0,0 +1,306 @@
+//! Config file with named profiles, e.g. separate API keys and models for a
+//! work and a personal billing account.
+//!
+//! Settings resolve as: command-line flag > environment variable > profile >
+//! built-in default. The file is YAML:
+//!
+//! ```yaml
+//! default_profile: personal
+//! profiles:
+//!   work:
+//!     gemini_api_key: "..."
+//!     model: gemini-2.5-pro
+//!     format: srt
+//!   personal:
+//!     gemini_api_key: "..."
+//!     image_model: 3pro
+//! ```
+
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[cfg(feature = "transcription")]
+use crate::transcription::Provider;
+
+/// Overrides the config file location
+pub const CONFIG_ENV: &str = "FLASHECHO_CONFIG";
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Failed to read config file {path:?}: {source}")]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Invalid config file {path:?}: {source}")]
+    Parse {
+        path: PathBuf,
+        #[source]
+        source: serde_yaml::Error,
+    },
+
+    #[error("Profile '{name}' not found in config file {path:?}")]
+    UnknownProfile { name: String, path: PathBuf },
+
+    #[error("Invalid {key} '{value}' in config profile")]
+    InvalidValue { key: &'static str, value: String },
+}
+
+pub type Result<T> = std::result::Result<T, ConfigError>;
+
+/// Settings of one profile; every field is optional
+#[derive(Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// Used when GEMINI_API_KEY / GOOGLE_AI_KEY are not set
+    pub gemini_api_key: Option<String>,
+    /// Used when OPENAI_API_KEY is not set
+    pub openai_api_key: Option<String>,
+    /// Transcription provider: gemini or openai
+    pub provider: Option<String>,
+    /// Transcription model, used with the profile's provider only
+    pub model: Option<String>,
+    /// Transcript output format: json, jsonl, srt, vtt, txt, anki, audacity,
+    /// markers or html
+    pub format: Option<String>,
+    /// Model for image generation and editing: 2.5-flash or 3pro
+    pub image_model: Option<String>,
+    /// Format generated images are converted to: png, jpg or webp
+    pub image_format: Option<String>,
+    /// Model for video generation: 3, 3-fast or 2
+    pub video_model: Option<String>,
+    /// Model for speech generation: flash or pro
+    pub speech_model: Option<String>,
+}
+
+impl fmt::Debug for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let redacted = |key: &Option<String>| key.as_ref().map(|_| "REDACTED");
+        f.debug_struct("Profile")
+            .field("gemini_api_key", &redacted(&self.gemini_api_key))
+            .field("openai_api_key", &redacted(&self.openai_api_key))
+            .field("provider", &self.provider)
+            .field("model", &self.model)
+            .field("format", &self.format)
+            .field("image_model", &self.image_model)
+            .field("image_format", &self.image_format)
+            .field("video_model", &self.video_model)
+            .field("speech_model", &self.speech_model)
+            .finish()
+    }
+}
+
+#[cfg(feature = "transcription")]
+impl Profile {
+    /// The profile's API key for `provider`
+    pub fn api_key(&self, provider: Provider) -> Option<&str> {
+        match provider {
+            Provider::Gemini => self.gemini_api_key.as_deref(),
+            Provider::OpenAi => self.openai_api_key.as_deref(),
+        }
+    }
+
+    /// The profile's model, unless `provider` (e.g. from `--provider`) isn't
+    /// the profile's provider
+    pub fn model_for(&self, provider: Provider) -> Option<&str> {
+        let own = resolve(None, "provider", self.provider.as_deref()).ok();
+        self.model.as_deref().filter(|_| own == Some(provider))
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Profile used when none is selected
+    pub default_profile: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Config {
+    /// `$FLASHECHO_CONFIG`, else `$XDG_CONFIG_HOME/flashecho/config.yaml`,
+    /// else `~/.config/flashecho/config.yaml`
+    pub fn default_path() -> PathBuf {
+        if let Some(path) = std::env::var_os(CONFIG_ENV) {
+            return PathBuf::from(path);
+        }
+        let base = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+            .unwrap_or_default();
+        base.join("flashecho").join("config.yaml")
+    }
+
+    /// Read `path`; a missing file is an empty config
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Self {
+                    path,
+                    ..Default::default()
+                });
+            }
+            Err(source) => return Err(ConfigError::Read { path, source }),
+        };
+        Self::parse(&text, path)
+    }
+
+    /// [`Config::load`] from [`Config::default_path`]
+    pub fn load_default() -> Result<Self> {
+        Self::load(Self::default_path())
+    }
+
+    fn parse(text: &str, path: PathBuf) -> Result<Self> {
+        // An empty file parses as YAML null
+        if text.trim().is_empty() {
+            return Ok(Self {
+                path,
+                ..Default::default()
+            });
+        }
+        let mut config: Config =
+            serde_yaml::from_str(text).map_err(|source| ConfigError::Parse {
+                path: path.clone(),
+                source,
+            })?;
+        config.path = path;
+        Ok(config)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Profile `name`, else `default_profile`, else an empty profile. A name
+    /// that isn't in the file is an error so typos don't silently fall back.
+    pub fn profile(&self, name: Option<&str>) -> Result<Profile> {
+        let Some(name) = name.or(self.default_profile.as_deref()) else {
+            return Ok(Profile::default());
+        };
+        self.profiles
+            .get(name)
+            .cloned()
+            .ok_or_else(|| ConfigError::UnknownProfile {
+                name: name.to_string(),
+                path: self.path.clone(),
+            })
+    }
+}
+
+/// `flag` if given on the command line or in the environment, else the
+/// profile's `setting` (named `key` in errors), else `T::default()`
+pub fn resolve<T: ValueEnum + Default>(
+    flag: Option<T>,
+    key: &'static str,
+    setting: Option<&str>,
+) -> Result<T> {
+    match (flag, setting) {
+        (Some(value), _) => Ok(value),
+        (None, Some(value)) => T::from_str(value, true).map_err(|_| ConfigError::InvalidValue {
+            key,
+            value: value.to_string(),
+        }),
+        (None, None) => Ok(T::default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+default_profile: personal
+profiles:
+  work:
+    gemini_api_key: work-key
+    model: gemini-2.5-pro
+    format: srt
+  personal:
+    gemini_api_key: personal-key
+"#;
+
+    #[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+    enum Format {
+        #[default]
+        Json,
+        Srt,
+    }
+
+    #[test]
+    fn test_profile_selection() {
+        let config = Config::parse(CONFIG, PathBuf::from("config.yaml")).unwrap();
+
+        let work = config.profile(Some("work")).unwrap();
+        assert_eq!(work.gemini_api_key.as_deref(), Some("work-key"));
+        assert_eq!(work.model.as_deref(), Some("gemini-2.5-pro"));
+
+        let default = config.profile(None).unwrap();
+        assert_eq!(default.gemini_api_key.as_deref(), Some("personal-key"));
+
+        let err = config.profile(Some("wrok")).unwrap_err();
+        assert!(matches!(err, ConfigError::UnknownProfile { .. }));
+    }
+
+    #[test]
+    fn test_missing_and_empty_config() {
+        let config = Config::load("/nonexistent/flashecho/config.yaml").unwrap();
+        assert!(config.profile(None).unwrap().model.is_none());
+        assert!(config.profile(Some("work")).is_err());
+
+        let config = Config::parse("\n", PathBuf::new()).unwrap();
+        assert!(config.profiles.is_empty());
+    }
+
+    #[test]
+    fn test_unknown_setting_rejected() {
+        let err = Config::parse("profiles:\n  work:\n    modle: x\n", PathBuf::new()).unwrap_err();
+        assert!(matches!(err, ConfigError::Parse { .. }));
+    }
+
+    #[test]
+    fn test_resolve_precedence() {
+        assert_eq!(
+            resolve(Some(Format::Json), "format", Some("srt")).unwrap(),
+            Format::Json
+        );
+        assert_eq!(
+            resolve::<Format>(None, "format", Some("SRT")).unwrap(),
+            Format::Srt
+        );
+        assert_eq!(
+            resolve::<Format>(None, "format", None).unwrap(),
+            Format::Json
+        );
+        assert!(resolve::<Format>(None, "format", Some("docx")).is_err());
+    }
+
+    #[cfg(feature = "transcription")]
+    #[test]
+    fn test_model_belongs_to_profile_provider() {
+        let config = Config::parse(CONFIG, PathBuf::new()).unwrap();
+        let work = config.profile(Some("work")).unwrap();
+        assert_eq!(work.model_for(Provider::Gemini), Some("gemini-2.5-pro"));
+        assert_eq!(work.model_for(Provider::OpenAi), None);
+        assert_eq!(work.api_key(Provider::Gemini), Some("work-key"));
+        assert_eq!(work.api_key(Provider::OpenAi), None);
+    }
+
+    #[test]
+    fn test_profile_debug_redacts_keys() {
+        let profile = Profile {
+            gemini_api_key: Some("secret".to_string()),
+            ..Default::default()
+        };
+        assert!(!format!("{:?}", profile).contains("secret"));
+    }
+}
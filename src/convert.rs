@@ -1,80 +1,233 @@
 use anyhow::{Context, Result};
-use clap::{Parser, ValueEnum};
+use bytes::Bytes;
+use clap::Parser;
+use futures::StreamExt;
 use indicatif::{ProgressBar, ProgressStyle};
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 use tokio::fs;
-use tokio::process::Command;
-use tracing::{Level, debug, info, warn};
-use tracing_subscriber::FmtSubscriber;
+use tracing::{Instrument, debug, info, info_span, warn};
 
+use transcript_tool::cli::{
+    self, CommonArgs, FfmpegArgs, GenerationArgs, PromptArgs, SubtitleArgs, init_logging,
+};
+use transcript_tool::config::{self, Profile};
+use transcript_tool::gemini_api::AudioSource;
+use transcript_tool::storage::{OutputStorage, Storage, write_atomic};
+use transcript_tool::usage::UsageRecord;
 use transcript_tool::{
-    FileApiClient, GeminiClient, GeminiClientConfig, MAX_INLINE_FILE_SIZE, TranscriptResponse,
+    Budget, FileApiClient, GeminiClient, GeminiClientConfig, MAX_INLINE_FILE_SIZE, MediaRange,
+    OpenAiClient, OpenAiClientConfig, OutputFormat, Provider, TokenCount, TranscriptResponse,
+    TranscriptionBackend, format_transcript_with, scaled_timeout_secs, segment_clips,
+    transcript_to_anki_with, transcript_to_html_with,
+};
+#[cfg(feature = "text")]
+use transcript_tool::{
+    TextClient, TextClientConfig, TextModel, TranscriptCleanup, TranscriptNormalizer,
 };
-
-#[derive(Debug, Clone, Copy, ValueEnum, Default)]
-enum OutputFormat {
-    #[default]
-    Json,
-    Srt,
-    Vtt,
-    Txt,
-}
 
 #[derive(Parser, Debug)]
 #[command(name = "convert")]
 #[command(version)]
-#[command(about = "Extract audio from video and transcribe using Gemini API")]
-struct Args {
-    /// Input video or audio file path
+#[command(about = "Extract audio from video and transcribe using Gemini or OpenAI")]
+#[command(after_help = "EXAMPLES:
+    convert -i talk.mp4 -f srt
+    convert -i a.mp4 -i b.mp3
+    convert a.mp4 b.mp4 c.mp4 -j 3 -o transcripts/
+    convert -i lecture.mp4 --from 45:00 --to 1:10:00")]
+pub struct Args {
+    /// Input video or audio file path; repeat for several files
+    #[arg(short, long, value_name = "FILE", required_unless_present = "files")]
+    input: Vec<PathBuf>,
+
+    /// More input files, as an alternative to repeating -i
+    #[arg(value_name = "FILE")]
+    files: Vec<PathBuf>,
+
+    /// Output file path, or an s3:// / gs:// URI (a trailing `/` keeps the
+    /// default file name; required with several inputs) [default: <input>.<format>]
     #[arg(short, long)]
-    input: PathBuf,
+    output: Option<String>,
 
-    /// Output file path (defaults to <input>.<format>)
-    #[arg(short, long)]
-    output: Option<PathBuf>,
+    /// Number of files to convert at once when given several
+    #[arg(short, long, default_value = "1", value_parser = clap::value_parser!(u16).range(1..))]
+    jobs: u16,
 
-    /// Output format
-    #[arg(short, long, value_enum, default_value = "json")]
-    format: OutputFormat,
+    /// Output format [default: json]
+    #[arg(short, long, value_enum, env = "FLASHECHO_FORMAT")]
+    format: Option<OutputFormat>,
+
+    /// Also write the transcript in these formats, next to the main output
+    /// under each format's extension (comma-separated or repeated)
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "FORMAT")]
+    also_format: Vec<OutputFormat>,
 
     /// Keep the intermediate MP3 file
     #[arg(short, long, default_value = "false")]
     keep_audio: bool,
 
-    /// Gemini model to use
-    #[arg(long, default_value = "gemini-2.5-flash")]
-    model: String,
-
-    /// API timeout in seconds
-    #[arg(long, default_value = "600")]
-    timeout: u64,
-
-    /// Max retry attempts for API calls
-    #[arg(long, default_value = "3")]
-    max_retries: u32,
+    /// Transcription provider [default: gemini]
+    #[arg(long, value_enum, env = "FLASHECHO_PROVIDER")]
+    provider: Option<Provider>,
 
-    /// Verbosity level (-v, -vv, -vvv)
-    #[arg(short, long, action = clap::ArgAction::Count)]
-    verbose: u8,
+    /// Model to use (defaults to gemini-2.5-flash for Gemini, whisper-1 for OpenAI)
+    #[arg(long, env = "FLASHECHO_MODEL")]
+    model: Option<String>,
 
-    /// Quiet mode (no progress output)
-    #[arg(short, long)]
-    quiet: bool,
+    /// API timeout in seconds [default: scaled with the media length, from 2
+    /// minutes to an hour]
+    #[arg(long, value_name = "SECONDS")]
+    timeout: Option<u64>,
 
-    /// Force use of File API even for small files
+    /// Force use of File API even for small files (Gemini only)
     #[arg(long)]
     force_file_api: bool,
 
+    /// Transcribe from this time on (HH:MM:SS, MM:SS or seconds); the file
+    /// is still uploaded whole, through the File API (Gemini only)
+    #[arg(long, value_name = "TIME", value_parser = cli::parse_time)]
+    from: Option<u64>,
+
+    /// Transcribe up to this time (HH:MM:SS, MM:SS or seconds; Gemini only)
+    #[arg(long, value_name = "TIME", value_parser = cli::parse_time)]
+    to: Option<u64>,
+
     /// Keep uploaded file on server (don't delete after transcription)
     #[arg(long)]
     keep_remote_file: bool,
+
+    /// Also list topics and keywords with the timestamp of their first
+    /// mention (Gemini only)
+    #[arg(long)]
+    topics: bool,
+
+    /// Number of people who speak; the model labels exactly this many
+    /// speakers instead of guessing (Gemini only)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    num_speakers: Option<u32>,
+
+    /// Also translate every segment into these languages, e.g. `es,fr,de`;
+    /// subtitle and text formats get a file per language next to the main
+    /// output, JSON keeps all of them per segment (Gemini only)
+    #[arg(long, value_delimiter = ',', value_name = "LANGUAGES")]
+    translate_to: Vec<String>,
+
+    #[command(flatten)]
+    prompt: PromptArgs,
+
+    #[command(flatten)]
+    generation: GenerationArgs,
+
+    /// Proofread the finished transcript with a Gemini text model: punctuation,
+    /// capitalization, filler words and obvious misrecognitions. Speakers and
+    /// timestamps are kept
+    #[cfg(feature = "text")]
+    #[arg(long)]
+    cleanup: bool,
+
+    /// Write spoken numbers, dates, times and amounts in written form with a
+    /// Gemini text model, following the conventions of these locales
+    /// (e.g. `en-US,de-DE`) for segments in their languages
+    #[cfg(feature = "text")]
+    #[arg(long, value_name = "LOCALE", value_delimiter = ',')]
+    normalize: Vec<String>,
+
+    /// Text model for --cleanup and --normalize: flash-lite (default), flash, pro
+    #[cfg(feature = "text")]
+    #[arg(long, value_name = "MODEL", default_value = "flash-lite")]
+    cleanup_model: String,
+
+    /// Also cut every segment into its own audio clip in DIR, named by number,
+    /// start time and speaker, listed in DIR/clips.jsonl (a folder per input
+    /// when given several)
+    #[arg(long, value_name = "DIR")]
+    export_clips: Option<PathBuf>,
+
+    /// Also write a `<output>.blake3` checksum sidecar, checked by `verify`
+    #[arg(long)]
+    checksums: bool,
+
+    /// Print each input's duration and the estimated cost, then stop without
+    /// extracting, uploading or transcribing anything
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Print the input tokens each transcription would use, counted by the
+    /// free countTokens endpoint, and the estimated input cost, then stop
+    /// (Gemini only)
+    #[arg(long, conflicts_with_all = ["dry_run", "from", "to"])]
+    count_tokens_only: bool,
+
+    #[command(flatten)]
+    subtitles: SubtitleArgs,
+
+    #[command(flatten)]
+    ffmpeg: FfmpegArgs,
+
+    #[command(flatten)]
+    common: CommonArgs,
 }
 
-fn get_api_key() -> Result<String> {
-    std::env::var("GEMINI_API_KEY")
-        .or_else(|_| std::env::var("GOOGLE_AI_KEY"))
-        .context("GEMINI_API_KEY or GOOGLE_AI_KEY environment variable is not set")
+impl Args {
+    fn media_range(&self) -> MediaRange {
+        MediaRange {
+            start_secs: self.from,
+            end_secs: self.to,
+        }
+    }
+}
+
+fn get_api_key(provider: Provider, profile: &Profile) -> Result<String> {
+    let vars = provider.api_key_vars();
+    cli::api_key(vars, profile.api_key(provider), provider.keyring_account())
+        .with_context(|| cli::missing_api_key(vars))
+}
+
+/// Spinner text while `provider` transcribes audio of `duration` seconds
+fn transcribing_message(provider: Provider, duration: Option<f64>) -> String {
+    match duration {
+        Some(secs) => format!(
+            "Transcribing {} of audio with {} API...",
+            cli::format_duration(secs),
+            provider
+        ),
+        None => format!("Transcribing audio with {} API...", provider),
+    }
+}
+
+/// Transcribe audio sent inline with the request, showing a spinner unless quiet
+async fn transcribe_inline<B: TranscriptionBackend>(
+    backend: &B,
+    provider: Provider,
+    audio_data: &[u8],
+    mime_type: &str,
+    duration: Option<f64>,
+    quiet: bool,
+) -> Result<TranscriptResponse> {
+    let pb = if !quiet {
+        let pb = ProgressBar::new_spinner();
+        pb.set_style(
+            ProgressStyle::default_spinner()
+                .template("{spinner:.cyan} {msg} ({elapsed})")
+                .unwrap(),
+        );
+        pb.set_message(transcribing_message(provider, duration));
+        pb.enable_steady_tick(Duration::from_millis(100));
+        Some(pb)
+    } else {
+        None
+    };
+
+    let transcript = backend
+        .transcribe(audio_data, mime_type)
+        .await
+        .map_err(|e| anyhow::anyhow!("Transcription failed: {}", e))?;
+
+    if let Some(pb) = pb {
+        pb.finish_with_message("Transcription complete!");
+    }
+
+    Ok(transcript)
 }
 
 const VIDEO_EXTENSIONS: &[&str] = &["mp4", "mkv", "avi", "mov", "webm", "flv", "wmv", "m4v"];
@@ -98,7 +251,12 @@ fn is_media_file(path: &Path) -> bool {
         .unwrap_or(false)
 }
 
-async fn extract_audio_with_ffmpeg(input: &Path, output: &Path, quiet: bool) -> Result<()> {
+async fn extract_audio_with_ffmpeg(
+    ffmpeg: &FfmpegArgs,
+    input: &Path,
+    output: &Path,
+    quiet: bool,
+) -> Result<()> {
     info!("Extracting audio from {:?} to {:?}", input, output);
 
     let pb = if !quiet {
@@ -115,7 +273,8 @@ async fn extract_audio_with_ffmpeg(input: &Path, output: &Path, quiet: bool) ->
         None
     };
 
-    let output_result = Command::new("ffmpeg")
+    let output_result = ffmpeg
+        .command()
         .args([
             "-i",
             input.to_str().context("Invalid input path")?,
@@ -129,7 +288,7 @@ async fn extract_audio_with_ffmpeg(input: &Path, output: &Path, quiet: bool) ->
         ])
         .output()
         .await
-        .context("Failed to execute ffmpeg. Is ffmpeg installed?")?;
+        .with_context(|| ffmpeg.missing())?;
 
     if let Some(pb) = pb {
         pb.finish_and_clear();
@@ -144,333 +303,817 @@ async fn extract_audio_with_ffmpeg(input: &Path, output: &Path, quiet: bool) ->
     Ok(())
 }
 
-fn format_timestamp_srt(timestamp: &str) -> String {
-    // Convert MM:SS to SRT format 00:MM:SS,000
-    let parts: Vec<&str> = timestamp.split(':').collect();
-    if parts.len() == 2 {
-        format!("00:{}:{},000", parts[0], parts[1])
-    } else {
-        format!("00:{},000", timestamp)
-    }
+/// Run with parsed arguments; shared by this binary and its `flashecho` subcommand
+pub async fn run(args: Args) -> Result<()> {
+    init_logging(args.common.verbose, args.common.log_format);
+    args.common.install_usage_ledger("convert");
+    convert(args).await
 }
 
-fn format_timestamp_vtt(timestamp: &str) -> String {
-    // Convert MM:SS to VTT format 00:MM:SS.000
-    let parts: Vec<&str> = timestamp.split(':').collect();
-    if parts.len() == 2 {
-        format!("00:{}:{}.000", parts[0], parts[1])
-    } else {
-        format!("00:{}.000", timestamp)
-    }
+/// What every input of one run shares
+struct Settings {
+    #[cfg(feature = "text")]
+    profile: Profile,
+    provider: Provider,
+    format: OutputFormat,
+    model: String,
+    api_key: String,
+    budget: Budget,
+    /// Custom transcription prompt from --prompt or --prompt-file
+    prompt: Option<String>,
+    #[cfg(feature = "text")]
+    cleanup_model: Option<TextModel>,
+    /// Hide progress and per-stage messages: with -q, and while several
+    /// files are converted at once
+    quiet: bool,
+    several_inputs: bool,
 }
 
-fn transcript_to_srt(transcript: &TranscriptResponse) -> String {
-    let mut output = String::new();
+/// How one input ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Saved,
+    /// A transcript already existed
+    Skipped,
+}
 
-    for (i, segment) in transcript.segments.iter().enumerate() {
-        let start = format_timestamp_srt(&segment.timestamp);
-        // Estimate end time as 5 seconds after start (or use next segment's start)
-        let end = if i + 1 < transcript.segments.len() {
-            format_timestamp_srt(&transcript.segments[i + 1].timestamp)
-        } else {
-            // Add 5 seconds to last timestamp
-            let parts: Vec<&str> = segment.timestamp.split(':').collect();
-            if parts.len() == 2 {
-                let mins: u32 = parts[0].parse().unwrap_or(0);
-                let secs: u32 = parts[1].parse().unwrap_or(0) + 5;
-                let new_mins = mins + secs / 60;
-                let new_secs = secs % 60;
-                format!("00:{:02}:{:02},000", new_mins, new_secs)
-            } else {
-                "00:00:05,000".to_string()
-            }
-        };
+/// Run `future` under a spinner showing `message`, unless `quiet`
+#[cfg(feature = "text")]
+async fn with_spinner<T>(quiet: bool, message: String, future: impl Future<Output = T>) -> T {
+    let pb = cli::spinner(quiet, message);
+    let result = future.await;
+    if let Some(pb) = pb {
+        pb.finish_and_clear();
+    }
+    result
+}
 
-        output.push_str(&format!("{}\n", i + 1));
-        output.push_str(&format!("{} --> {}\n", start, end));
-        output.push_str(&format!("[{}] {}\n\n", segment.speaker, segment.content));
+/// Proofread (`--cleanup`) and normalize numbers (`--normalize`) in
+/// `transcript` with a Gemini text model
+#[cfg(feature = "text")]
+async fn rewrite_text(
+    args: &Args,
+    settings: &Settings,
+    model: TextModel,
+    timeout: u64,
+    mut transcript: TranscriptResponse,
+) -> Result<TranscriptResponse> {
+    let config = TextClientConfig {
+        timeout_secs: timeout,
+        max_retries: args.common.max_retries,
+        model,
+        cache: args.common.response_cache(),
+        budget: Some(settings.budget.clone()),
+        ..Default::default()
+    };
+    let client = TextClient::with_config(get_api_key(Provider::Gemini, &settings.profile)?, config)
+        .map_err(|e| anyhow::anyhow!("Failed to create text client: {}", e))?;
+    let segments = transcript.segments.len();
+
+    if args.cleanup {
+        let stats = with_spinner(
+            settings.quiet,
+            format!("Cleaning up {} segments with {}...", segments, model),
+            TranscriptCleanup::new(client.clone()).clean(&mut transcript),
+        )
+        .instrument(info_span!("stage", stage = "cleanup"))
+        .await
+        .map_err(|e| anyhow::anyhow!("Cleanup failed: {}", e))?;
+        if !settings.quiet {
+            println!(
+                "Cleaned up {} of {} segments.",
+                stats.changed, stats.segments
+            );
+        }
+        info!(
+            "Cleanup rewrote {} of {} segments",
+            stats.changed, stats.segments
+        );
     }
 
-    output
+    if !args.normalize.is_empty() {
+        let stats = with_spinner(
+            settings.quiet,
+            format!(
+                "Normalizing numbers in {} segments with {}...",
+                segments, model
+            ),
+            TranscriptNormalizer::new(client, &args.normalize).normalize(&mut transcript),
+        )
+        .instrument(info_span!("stage", stage = "normalize"))
+        .await
+        .map_err(|e| anyhow::anyhow!("Normalization failed: {}", e))?;
+        if !settings.quiet {
+            println!(
+                "Normalized numbers in {} of {} segments.",
+                stats.changed, stats.segments
+            );
+        }
+        info!(
+            "Normalization rewrote {} of {} segments",
+            stats.changed, stats.segments
+        );
+    }
+    Ok(transcript)
 }
 
-fn transcript_to_vtt(transcript: &TranscriptResponse) -> String {
-    let mut output = String::from("WEBVTT\n\n");
+/// Check that `input` is an existing media file
+fn check_input(input: &Path) -> Result<()> {
+    if !input.exists() {
+        anyhow::bail!("Input file does not exist: {:?}", input);
+    }
 
-    for (i, segment) in transcript.segments.iter().enumerate() {
-        let start = format_timestamp_vtt(&segment.timestamp);
-        let end = if i + 1 < transcript.segments.len() {
-            format_timestamp_vtt(&transcript.segments[i + 1].timestamp)
-        } else {
-            let parts: Vec<&str> = segment.timestamp.split(':').collect();
-            if parts.len() == 2 {
-                let mins: u32 = parts[0].parse().unwrap_or(0);
-                let secs: u32 = parts[1].parse().unwrap_or(0) + 5;
-                let new_mins = mins + secs / 60;
-                let new_secs = secs % 60;
-                format!("00:{:02}:{:02}.000", new_mins, new_secs)
-            } else {
-                "00:00:05.000".to_string()
-            }
-        };
+    if !is_media_file(input) {
+        anyhow::bail!(
+            "Input file is not a supported media format: {:?}\nSupported formats: video ({}) or audio ({})",
+            input,
+            VIDEO_EXTENSIONS.join(", "),
+            AUDIO_EXTENSIONS.join(", ")
+        );
+    }
+    Ok(())
+}
 
-        output.push_str(&format!("{} --> {}\n", start, end));
-        output.push_str(&format!("<v {}>{}\n\n", segment.speaker, segment.content));
+async fn convert(args: Args) -> Result<()> {
+    let inputs: Vec<PathBuf> = args.input.iter().chain(&args.files).cloned().collect();
+    if inputs.len() > 1
+        && let Some(output) = &args.output
+        && !output.ends_with('/')
+    {
+        anyhow::bail!(
+            "With several inputs, --output must be a directory or s3:// / gs:// prefix ending in `/`, not {}",
+            output
+        );
+    }
+    for input in &inputs {
+        check_input(input)?;
+    }
+    let needs_ffmpeg =
+        args.export_clips.is_some() || !inputs.iter().all(|input| is_audio_file(input));
+    if !args.dry_run && needs_ffmpeg {
+        args.ffmpeg
+            .check()
+            .await
+            .with_context(|| args.ffmpeg.missing())?;
     }
 
-    output
-}
+    let budget = args
+        .common
+        .tracking_budget()
+        .context("Failed to read usage ledger for --budget-since")?;
+    let spent_before = (budget.spent_usd(), budget.spent_tokens());
+
+    let profile = args.common.load_profile()?;
+    let provider = config::resolve(args.provider, "provider", profile.provider.as_deref())?;
+    let format = config::resolve(args.format, "format", profile.format.as_deref())?;
+    let model = args
+        .model
+        .clone()
+        .or_else(|| profile.model_for(provider).map(str::to_string))
+        .unwrap_or_else(|| provider.default_model().to_string());
+
+    if provider == Provider::OpenAi && args.force_file_api {
+        anyhow::bail!("--force-file-api is only supported with the Gemini provider");
+    }
+    if provider == Provider::OpenAi && args.media_range() != MediaRange::default() {
+        anyhow::bail!("--from and --to are only supported with the Gemini provider");
+    }
+    if let (Some(from), Some(to)) = (args.from, args.to)
+        && from >= to
+    {
+        anyhow::bail!("--from must be earlier than --to");
+    }
+    if provider == Provider::OpenAi && args.topics {
+        anyhow::bail!("--topics is only supported with the Gemini provider");
+    }
+    if provider == Provider::OpenAi && args.num_speakers.is_some() {
+        anyhow::bail!("--num-speakers is only supported with the Gemini provider");
+    }
+    if provider == Provider::OpenAi && !args.translate_to.is_empty() {
+        anyhow::bail!("--translate-to is only supported with the Gemini provider");
+    }
+    if provider == Provider::OpenAi && args.generation.is_set() {
+        anyhow::bail!(
+            "--temperature, --top-p, --top-k, --max-output-tokens and --thinking-budget are \
+             only supported with the Gemini provider"
+        );
+    }
+    if provider == Provider::OpenAi && args.count_tokens_only {
+        anyhow::bail!("--count-tokens-only is only supported with the Gemini provider");
+    }
+    if provider == Provider::OpenAi && args.prompt.is_set() {
+        anyhow::bail!("--prompt and --prompt-file are only supported with the Gemini provider");
+    }
+    let prompt = args
+        .prompt
+        .load()
+        .await
+        .context("Failed to read --prompt-file")?;
+    #[cfg(feature = "text")]
+    let cleanup_model = if args.cleanup || !args.normalize.is_empty() {
+        let model: TextModel = args
+            .cleanup_model
+            .parse()
+            .map_err(|e: String| anyhow::anyhow!(e))?;
+        Some(model)
+    } else {
+        None
+    };
 
-fn transcript_to_txt(transcript: &TranscriptResponse) -> String {
-    let mut output = String::new();
+    if args.dry_run {
+        return dry_run(&args, &inputs, format, &model).await;
+    }
 
-    output.push_str(&format!("Summary:\n{}\n\n", transcript.summary));
-    output.push_str("---\n\n");
+    let api_key = get_api_key(provider, &profile)?;
+
+    let concurrent = inputs.len() > 1 && args.jobs > 1;
+    let settings = Settings {
+        #[cfg(feature = "text")]
+        profile,
+        provider,
+        format,
+        model,
+        api_key,
+        budget,
+        prompt,
+        #[cfg(feature = "text")]
+        cleanup_model,
+        quiet: args.common.quiet || concurrent,
+        several_inputs: inputs.len() > 1,
+    };
+    if args.count_tokens_only {
+        return count_tokens_only(&args, &settings, &inputs).await;
+    }
 
-    for segment in &transcript.segments {
-        output.push_str(&format!(
-            "[{}] {} ({})\n{}\n",
-            segment.timestamp, segment.speaker, segment.emotion, segment.content
-        ));
-        if let Some(ref translation) = segment.translation
-            && !translation.is_empty()
+    if let [input] = inputs.as_slice() {
+        let span = info_span!("file", file = %input.display());
+        convert_file(&args, &settings, input)
+            .instrument(span)
+            .await?;
+        if !args.common.quiet
+            && let Some(spend) = cli::spend_summary(&settings.budget, spent_before)
         {
-            output.push_str(&format!("  Translation: {}\n", translation));
+            println!("{}", spend);
         }
-        output.push('\n');
+        return Ok(());
     }
 
-    output
-}
-
-fn format_output(transcript: &TranscriptResponse, format: OutputFormat) -> Result<String> {
-    match format {
-        OutputFormat::Json => {
-            serde_json::to_string_pretty(transcript).context("Failed to serialize to JSON")
+    let total = inputs.len();
+    let settings = &settings;
+    let args = &args;
+    let mut results = futures::stream::iter(inputs.into_iter().enumerate())
+        .map(|(i, input)| async move {
+            if !settings.quiet {
+                println!("\n[{}/{}] {}", i + 1, total, input.display());
+            }
+            let span = info_span!("file", file = %input.display());
+            let result = convert_file(args, settings, &input).instrument(span).await;
+            if concurrent && !args.common.quiet {
+                match &result {
+                    Ok(Outcome::Saved) => println!("Converted: {}", input.display()),
+                    Ok(Outcome::Skipped) => {
+                        println!("Skipped: {} (transcript exists)", input.display())
+                    }
+                    Err(e) => println!("Failed: {}: {:#}", input.display(), e),
+                }
+            }
+            (input, result)
+        })
+        .buffer_unordered(usize::from(args.jobs));
+
+    let mut converted = 0;
+    let mut skipped = 0;
+    let mut failed = Vec::new();
+    while let Some((input, result)) = results.next().await {
+        match result {
+            Ok(Outcome::Saved) => converted += 1,
+            Ok(Outcome::Skipped) => skipped += 1,
+            Err(e) => {
+                warn!("Failed to convert {:?}: {:#}", input, e);
+                failed.push((input, e));
+            }
         }
-        OutputFormat::Srt => Ok(transcript_to_srt(transcript)),
-        OutputFormat::Vtt => Ok(transcript_to_vtt(transcript)),
-        OutputFormat::Txt => Ok(transcript_to_txt(transcript)),
     }
-}
 
-fn get_output_extension(format: OutputFormat) -> &'static str {
-    match format {
-        OutputFormat::Json => "json",
-        OutputFormat::Srt => "srt",
-        OutputFormat::Vtt => "vtt",
-        OutputFormat::Txt => "txt",
+    if !args.common.quiet {
+        println!("\n--- Conversion Complete ---");
+        println!("Converted: {} of {} files", converted, total);
+        if skipped > 0 {
+            println!("Skipped: {} files (already have transcripts)", skipped);
+        }
+        if let Some(spend) = cli::spend_summary(&settings.budget, spent_before) {
+            println!("{}", spend);
+        }
     }
+    if !failed.is_empty() {
+        for (input, e) in &failed {
+            eprintln!("  - {:?}: {:#}", input, e);
+        }
+        anyhow::bail!("{} of {} files failed", failed.len(), total);
+    }
+    Ok(())
 }
 
-fn init_logging(verbose: u8) {
-    let level = match verbose {
-        0 => Level::WARN,
-        1 => Level::INFO,
-        2 => Level::DEBUG,
-        _ => Level::TRACE,
-    };
-
-    let subscriber = FmtSubscriber::builder()
-        .with_max_level(level)
-        .with_target(false)
-        .with_thread_ids(false)
-        .with_file(false)
-        .with_line_number(false)
-        .compact()
-        .finish();
-
-    tracing::subscriber::set_global_default(subscriber).ok();
+/// Extension of the clips cut from `audio`, which keep its codec
+fn clip_extension(audio: &Path) -> String {
+    audio
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("mp3")
+        .to_lowercase()
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let args = Args::parse();
+/// Cut each segment of `transcript` out of `audio` into `dir`
+/// (`--export-clips`), stream-copied so nothing is re-encoded, and list the
+/// clips in `dir/clips.jsonl`
+async fn export_clips(
+    ffmpeg: &FfmpegArgs,
+    audio: &Path,
+    transcript: &TranscriptResponse,
+    media_secs: Option<f64>,
+    dir: &Path,
+    quiet: bool,
+) -> Result<()> {
+    let clips = segment_clips(transcript, media_secs);
+    fs::create_dir_all(dir)
+        .await
+        .with_context(|| format!("Failed to create clip directory {:?}", dir))?;
+    let extension = clip_extension(audio);
 
-    init_logging(args.verbose);
+    let pb = if !quiet {
+        let pb = ProgressBar::new(clips.len() as u64);
+        pb.set_style(
+            ProgressStyle::default_bar()
+                .template("{spinner:.green} Exporting clips [{bar:30.cyan/blue}] {pos}/{len}")
+                .unwrap()
+                .progress_chars("#>-"),
+        );
+        Some(pb)
+    } else {
+        None
+    };
 
-    let api_key = get_api_key()?;
+    let mut manifest = String::new();
+    for clip in &clips {
+        let file_name = format!("{}.{}", clip.file_stem, extension);
+        let output = ffmpeg
+            .command()
+            .args(["-hide_banner", "-loglevel", "error", "-ss"])
+            .arg(format!("{:.3}", clip.start_secs))
+            .arg("-i")
+            .arg(audio)
+            .arg("-t")
+            .arg(format!("{:.3}", clip.duration_secs()))
+            .args(["-c", "copy", "-y"])
+            .arg(dir.join(&file_name))
+            .output()
+            .await
+            .with_context(|| ffmpeg.missing())?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("ffmpeg failed to cut {}: {}", file_name, stderr.trim());
+        }
 
-    if !args.input.exists() {
-        anyhow::bail!("Input file does not exist: {:?}", args.input);
+        let mut line = serde_json::to_value(clip)?;
+        line["file"] = file_name.into();
+        manifest.push_str(&line.to_string());
+        manifest.push('\n');
+        if let Some(pb) = &pb {
+            pb.inc(1);
+        }
+    }
+    if let Some(pb) = pb {
+        pb.finish_and_clear();
     }
 
-    if !is_media_file(&args.input) {
-        anyhow::bail!(
-            "Input file is not a supported media format: {:?}\nSupported formats: video ({}) or audio ({})",
-            args.input,
-            VIDEO_EXTENSIONS.join(", "),
-            AUDIO_EXTENSIONS.join(", ")
-        );
+    write_atomic(&dir.join("clips.jsonl"), manifest.as_bytes())
+        .await
+        .context("Failed to write clips.jsonl")?;
+    if !quiet {
+        println!("Exported {} clips to {:?}", clips.len(), dir);
     }
+    info!("Exported {} clips to {:?}", clips.len(), dir);
+    Ok(())
+}
 
-    // Check if output file already exists
-    let output_path = args.output.clone().unwrap_or_else(|| {
-        let mut p = args.input.clone();
-        p.set_extension(get_output_extension(args.format));
-        p
-    });
-
-    if output_path.exists() {
-        if !args.quiet {
-            println!("Skipped: transcript already exists at {:?}", output_path);
+/// Where the transcript of `input` goes: `--output`, or next to the input
+fn output_target(
+    args: &Args,
+    input: &Path,
+    format: OutputFormat,
+) -> Result<(OutputStorage, String)> {
+    let default_output = input.with_extension(format.extension());
+    let default_name = default_output
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let target = args
+        .output
+        .clone()
+        .unwrap_or_else(|| default_output.to_string_lossy().to_string());
+    OutputStorage::open_file(&target, &default_name)
+        .with_context(|| format!("Invalid output target: {}", target))
+}
+
+/// `--dry-run`: list the inputs with their durations and estimate the cost
+/// of the ones without a transcript
+async fn dry_run(args: &Args, inputs: &[PathBuf], format: OutputFormat, model: &str) -> Result<()> {
+    let mut durations = Vec::new();
+    for input in inputs {
+        let (storage, key) = output_target(args, input, format)?;
+        if storage
+            .exists(&key)
+            .await
+            .context("Failed to check for an existing transcript")?
+        {
+            println!("{}  skipped: transcript exists", input.display());
+            continue;
         }
-        info!("Skipped: transcript already exists at {:?}", output_path);
-        return Ok(());
+        let duration = args.ffmpeg.probe_duration(input).await;
+        let shown = duration.map_or_else(|| "unknown length".to_string(), cli::format_duration);
+        println!("{}  {}", input.display(), shown);
+        durations.push(duration);
     }
+    println!("\n{}", cli::dry_run_summary(model, &durations));
+    Ok(())
+}
 
-    let (audio_path, should_cleanup) = if is_audio_file(&args.input) {
+/// The audio to send for `input` and whether it is a temporary file: audio
+/// inputs as they are, the audio track of videos extracted to MP3
+async fn audio_input(args: &Args, input: &Path, quiet: bool) -> Result<(PathBuf, bool)> {
+    if is_audio_file(input) {
         info!("Input is already an audio file, skipping ffmpeg extraction");
-        if !args.quiet {
+        if !quiet {
             println!("Input is already an audio file, skipping extraction.");
         }
-        (args.input.clone(), false)
-    } else {
-        let mp3_path = args.input.with_extension("mp3");
-        extract_audio_with_ffmpeg(&args.input, &mp3_path, args.quiet).await?;
-        if !args.quiet {
-            println!("Audio extracted successfully.");
+        return Ok((input.to_path_buf(), false));
+    }
+    let mp3_path = input.with_extension("mp3");
+    extract_audio_with_ffmpeg(&args.ffmpeg, input, &mp3_path, quiet)
+        .instrument(info_span!("stage", stage = "extract_audio"))
+        .await?;
+    if !quiet {
+        println!("Audio extracted successfully.");
+    }
+    Ok((mp3_path, !args.keep_audio))
+}
+
+fn gemini_config(args: &Args, settings: &Settings, timeout_secs: u64) -> GeminiClientConfig {
+    GeminiClientConfig {
+        timeout_secs,
+        max_retries: args.common.max_retries,
+        model: settings.model.clone(),
+        cache: args.common.response_cache(),
+        budget: Some(settings.budget.clone()),
+        topics: args.topics,
+        prompt: settings.prompt.clone(),
+        num_speakers: args.num_speakers,
+        translate_to: args.translate_to.clone(),
+        generation: args.generation.params(),
+        ..Default::default()
+    }
+}
+
+/// `--count-tokens-only`: count the input tokens of each input without a
+/// transcript, uploading large ones to the File API (which is free) just as
+/// the transcription would, and estimate the input cost
+async fn count_tokens_only(args: &Args, settings: &Settings, inputs: &[PathBuf]) -> Result<()> {
+    let mut total = TokenCount::default();
+    let mut counted = 0;
+    for input in inputs {
+        let (storage, key) = output_target(args, input, settings.format)?;
+        if storage
+            .exists(&key)
+            .await
+            .context("Failed to check for an existing transcript")?
+        {
+            println!("{}  skipped: transcript exists", input.display());
+            continue;
         }
-        (mp3_path, !args.keep_audio)
+
+        let (audio_path, should_cleanup) = audio_input(args, input, true).await?;
+        let result = count_input_tokens(args, settings, &audio_path).await;
+        if should_cleanup {
+            fs::remove_file(&audio_path).await.ok();
+        }
+        let count = result.with_context(|| format!("Failed to count tokens of {:?}", input))?;
+        println!(
+            "{}  {} tokens ({} audio)",
+            input.display(),
+            count.total_tokens,
+            count.modality("AUDIO")
+        );
+        total.total_tokens += count.total_tokens;
+        total.by_modality.extend(count.by_modality);
+        counted += 1;
+    }
+
+    println!("\n{} files, {} input tokens", counted, total.total_tokens);
+    let audio_tokens = total.modality("AUDIO");
+    let record = UsageRecord {
+        model: settings.model.clone(),
+        input_tokens: total.total_tokens,
+        audio_tokens,
+        ..Default::default()
     };
+    match record.estimate_cost() {
+        Some(cost) => println!(
+            "Estimated input cost: ${:.4} with {} (the transcript itself is billed as output)",
+            cost, settings.model
+        ),
+        None => println!("No price known for {}", settings.model),
+    }
+    Ok(())
+}
 
-    debug!("Reading audio file: {:?}", audio_path);
-    let audio_data = fs::read(&audio_path)
+async fn count_input_tokens(
+    args: &Args,
+    settings: &Settings,
+    audio_path: &Path,
+) -> Result<TokenCount> {
+    let data = fs::read(audio_path)
         .await
         .context("Failed to read audio file")?;
+    let mime_type = GeminiClient::get_mime_type(audio_path);
+    let timeout = args
+        .timeout
+        .unwrap_or_else(|| scaled_timeout_secs(None, data.len() as u64));
+    let client = GeminiClient::with_config(
+        settings.api_key.clone(),
+        gemini_config(args, settings, timeout),
+    )
+    .map_err(|e| anyhow::anyhow!("Failed to create Gemini client: {}", e))?;
+
+    if !args.force_file_api && data.len() as u64 <= MAX_INLINE_FILE_SIZE {
+        let source = AudioSource::Inline {
+            mime_type: mime_type.to_string(),
+            data,
+        };
+        return client
+            .count_tokens(&source)
+            .instrument(info_span!("stage", stage = "count_tokens"))
+            .await
+            .map_err(|e| anyhow::anyhow!("Token counting failed: {}", e));
+    }
 
-    let file_size = audio_data.len() as u64;
-    debug!("Audio file size: {} bytes", file_size);
-
-    // Get correct MIME type
-    let mime_type = GeminiClient::get_mime_type(&audio_path);
-    debug!("Detected MIME type: {}", mime_type);
-
-    let config = GeminiClientConfig {
-        timeout_secs: args.timeout,
-        max_retries: args.max_retries,
-        model: args.model.clone(),
+    let file_api = FileApiClient::new(client.http_client().clone(), settings.api_key.clone())
+        .with_base_url(client.base_url())
+        .with_auth_mode(client.auth_mode());
+    let display_name = audio_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("audio");
+    let file_info = file_api
+        .upload_file(Bytes::from(data), mime_type, display_name)
+        .instrument(info_span!("stage", stage = "upload"))
+        .await
+        .map_err(|e| anyhow::anyhow!("File upload failed: {}", e))?;
+    let source = AudioSource::FileUri {
+        mime_type: mime_type.to_string(),
+        uri: file_info.uri.clone(),
     };
+    let result = client
+        .count_tokens(&source)
+        .instrument(info_span!("stage", stage = "count_tokens"))
+        .await;
+    match file_api.delete_file(&file_info.name).await {
+        Ok(()) => debug!("Remote file deleted: {}", file_info.name),
+        Err(e) => warn!("Failed to delete remote file {}: {}", file_info.name, e),
+    }
+    result.map_err(|e| anyhow::anyhow!("Token counting failed: {}", e))
+}
 
-    let client = GeminiClient::with_config(api_key.clone(), config)
-        .map_err(|e| anyhow::anyhow!("Failed to create Gemini client: {}", e))?;
+/// Transcribe one input and write its transcript
+async fn convert_file(args: &Args, settings: &Settings, input: &Path) -> Result<Outcome> {
+    let Settings {
+        provider, format, ..
+    } = *settings;
+    let model = settings.model.clone();
+    let api_key = settings.api_key.clone();
 
-    // Determine if we need to use the File API
-    let use_file_api = args.force_file_api || file_size > MAX_INLINE_FILE_SIZE;
+    // Check if output file already exists
+    let (storage, key) = output_target(args, input, format)?;
+    let output_path = storage.location(&key);
 
-    let (transcript, uploaded_file_name) = if use_file_api {
-        // Use File API for large files
-        let size_mb = file_size as f64 / (1024.0 * 1024.0);
-        if !args.quiet {
-            if args.force_file_api && file_size <= MAX_INLINE_FILE_SIZE {
-                println!("Using File API (forced) for {:.1}MB file...", size_mb);
-            } else {
-                println!(
-                    "File size {:.1}MB exceeds 20MB limit, using File API for upload...",
-                    size_mb
-                );
-            }
+    if storage
+        .exists(&key)
+        .await
+        .context("Failed to check for an existing transcript")?
+    {
+        if !settings.quiet {
+            println!("Skipped: transcript already exists at {:?}", output_path);
         }
-        info!(
-            "Using File API for file size {} bytes ({:.1}MB)",
-            file_size, size_mb
-        );
-
-        let file_api = FileApiClient::new(client.http_client().clone(), api_key);
+        info!("Skipped: transcript already exists at {:?}", output_path);
+        return Ok(Outcome::Skipped);
+    }
 
-        // Upload progress
-        let upload_pb = if !args.quiet {
-            let pb = ProgressBar::new_spinner();
-            pb.set_style(
-                ProgressStyle::default_spinner()
-                    .template("{spinner:.green} {msg}")
-                    .unwrap(),
-            );
-            pb.set_message("Uploading audio to Gemini File API...");
-            pb.enable_steady_tick(Duration::from_millis(100));
-            Some(pb)
-        } else {
-            None
-        };
+    let duration = args.ffmpeg.probe_duration(input).await;
+    if let Some(secs) = duration {
+        info!("Media duration: {:.1}s", secs);
+        if !settings.quiet {
+            println!("Duration: {}", cli::format_duration(secs));
+        }
+    }
 
-        let display_name = audio_path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("audio");
+    let (audio_path, should_cleanup) = audio_input(args, input, settings.quiet).await?;
 
-        let file_info = file_api
-            .upload_file(&audio_data, mime_type, display_name)
+    debug!("Reading audio file: {:?}", audio_path);
+    // `Bytes`, so the File API upload can share the buffer instead of copying it
+    let audio_data = Bytes::from(
+        fs::read(&audio_path)
             .await
-            .map_err(|e| anyhow::anyhow!("File upload failed: {}", e))?;
+            .context("Failed to read audio file")?,
+    );
 
-        if let Some(pb) = upload_pb {
-            pb.finish_with_message("Upload complete!");
-        }
-        if !args.quiet {
-            println!("File uploaded successfully.");
-        }
-        info!("File uploaded: {} -> {}", file_info.name, file_info.uri);
-
-        // Transcription progress
-        let transcribe_pb = if !args.quiet {
-            let pb = ProgressBar::new_spinner();
-            pb.set_style(
-                ProgressStyle::default_spinner()
-                    .template("{spinner:.cyan} {msg}")
-                    .unwrap(),
-            );
-            pb.set_message("Transcribing audio with Gemini API...");
-            pb.enable_steady_tick(Duration::from_millis(100));
-            Some(pb)
-        } else {
-            None
-        };
+    let file_size = audio_data.len() as u64;
+    debug!("Audio file size: {} bytes", file_size);
+    let timeout = args
+        .timeout
+        .unwrap_or_else(|| scaled_timeout_secs(duration, file_size));
+    debug!("Request timeout: {}s", timeout);
 
-        let transcript = client
-            .transcribe_file_uri(&file_info.uri, mime_type)
-            .await
-            .map_err(|e| anyhow::anyhow!("Transcription failed: {}", e))?;
+    // Get correct MIME type
+    let mime_type = GeminiClient::get_mime_type(&audio_path);
+    debug!("Detected MIME type: {}", mime_type);
 
-        if let Some(pb) = transcribe_pb {
-            pb.finish_with_message("Transcription complete!");
+    let (transcript, uploaded_file_name) = match provider {
+        Provider::OpenAi => {
+            let config = OpenAiClientConfig {
+                timeout_secs: timeout,
+                max_retries: args.common.max_retries,
+                model,
+                cache: args.common.response_cache(),
+                budget: Some(settings.budget.clone()),
+                ..Default::default()
+            };
+            let client = OpenAiClient::with_config(api_key, config)
+                .map_err(|e| anyhow::anyhow!("Failed to create OpenAI client: {}", e))?;
+
+            let transcript = transcribe_inline(
+                &client,
+                provider,
+                &audio_data,
+                mime_type,
+                duration,
+                settings.quiet,
+            )
+            .instrument(info_span!("stage", stage = "transcribe"))
+            .await?;
+            (transcript, None)
         }
+        Provider::Gemini => {
+            let config = gemini_config(args, settings, timeout);
+            let client = GeminiClient::with_config(api_key.clone(), config)
+                .map_err(|e| anyhow::anyhow!("Failed to create Gemini client: {}", e))?;
+
+            // Determine if we need to use the File API; a time range can only
+            // be sent along with an uploaded file
+            let range = args.media_range();
+            let use_file_api = args.force_file_api
+                || range != MediaRange::default()
+                || file_size > MAX_INLINE_FILE_SIZE;
+
+            if use_file_api {
+                // Use File API for large files
+                let size_mb = file_size as f64 / (1024.0 * 1024.0);
+                if !settings.quiet {
+                    if file_size <= MAX_INLINE_FILE_SIZE {
+                        println!("Using File API (forced) for {:.1}MB file...", size_mb);
+                    } else {
+                        println!(
+                            "File size {:.1}MB exceeds 20MB limit, using File API for upload...",
+                            size_mb
+                        );
+                    }
+                }
+                info!(
+                    "Using File API for file size {} bytes ({:.1}MB)",
+                    file_size, size_mb
+                );
 
-        (transcript, Some((file_api, file_info.name)))
-    } else {
-        // Use inline data for small files
-        let pb = if !args.quiet {
-            let pb = ProgressBar::new_spinner();
-            pb.set_style(
-                ProgressStyle::default_spinner()
-                    .template("{spinner:.cyan} {msg}")
-                    .unwrap(),
-            );
-            pb.set_message("Transcribing audio with Gemini API...");
-            pb.enable_steady_tick(Duration::from_millis(100));
-            Some(pb)
-        } else {
-            None
-        };
-
-        let transcript = client
-            .transcribe_audio(&audio_data, mime_type)
-            .await
-            .map_err(|e| anyhow::anyhow!("Transcription failed: {}", e))?;
+                let file_api = FileApiClient::new(client.http_client().clone(), api_key)
+                    .with_base_url(client.base_url())
+                    .with_auth_mode(client.auth_mode());
+
+                // Upload progress
+                let upload_pb = if !settings.quiet {
+                    let pb = ProgressBar::new_spinner();
+                    pb.set_style(
+                        ProgressStyle::default_spinner()
+                            .template("{spinner:.green} {msg}")
+                            .unwrap(),
+                    );
+                    pb.set_message("Uploading audio to Gemini File API...");
+                    pb.enable_steady_tick(Duration::from_millis(100));
+                    Some(pb)
+                } else {
+                    None
+                };
+
+                let display_name = audio_path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or("audio");
+
+                let file_info = file_api
+                    .upload_file(audio_data.clone(), mime_type, display_name)
+                    .instrument(info_span!("stage", stage = "upload"))
+                    .await
+                    .map_err(|e| anyhow::anyhow!("File upload failed: {}", e))?;
+
+                if let Some(pb) = upload_pb {
+                    pb.finish_with_message("Upload complete!");
+                }
+                if !settings.quiet {
+                    println!("File uploaded successfully.");
+                }
+                info!("File uploaded: {} -> {}", file_info.name, file_info.uri);
+
+                // Transcription progress
+                let transcribe_pb = if !settings.quiet {
+                    let pb = ProgressBar::new_spinner();
+                    pb.set_style(
+                        ProgressStyle::default_spinner()
+                            .template("{spinner:.cyan} {msg} ({elapsed})")
+                            .unwrap(),
+                    );
+                    pb.set_message(transcribing_message(provider, duration));
+                    pb.enable_steady_tick(Duration::from_millis(100));
+                    Some(pb)
+                } else {
+                    None
+                };
+
+                let transcript = client
+                    .transcribe_file_uri_range(&file_info.uri, mime_type, range)
+                    .instrument(info_span!("stage", stage = "transcribe"))
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Transcription failed: {}", e))?;
+
+                if let Some(pb) = transcribe_pb {
+                    pb.finish_with_message("Transcription complete!");
+                }
 
-        if let Some(pb) = pb {
-            pb.finish_with_message("Transcription complete!");
+                (transcript, Some((file_api, file_info.name)))
+            } else {
+                // Use inline data for small files
+                let transcript = transcribe_inline(
+                    &client,
+                    provider,
+                    &audio_data,
+                    mime_type,
+                    duration,
+                    settings.quiet,
+                )
+                .instrument(info_span!("stage", stage = "transcribe"))
+                .await?;
+                (transcript, None)
+            }
         }
+    };
 
-        (transcript, None)
+    #[cfg(feature = "text")]
+    let transcript = match settings.cleanup_model {
+        Some(model) => rewrite_text(args, settings, model, timeout, transcript).await?,
+        None => transcript,
     };
 
-    let formatted_output = format_output(&transcript, args.format)?;
+    let options = args.subtitles.options();
+    let render = |transcript: &TranscriptResponse, format, key: &str| -> Result<String> {
+        Ok(match format {
+            // Cards play the clips cut below
+            OutputFormat::Anki if args.export_clips.is_some() => {
+                transcript_to_anki_with(transcript, Some(&clip_extension(&audio_path)))
+            }
+            // The page plays the input itself, which outlives extracted audio
+            OutputFormat::Html => transcript_to_html_with(
+                transcript,
+                Some(&cli::html_media_src(input, &storage, key)),
+            ),
+            _ => format_transcript_with(transcript, format, &options)
+                .context("Failed to serialize to JSON")?,
+        })
+    };
+    // The main output goes last: its existence is what marks the input done
+    let mut targets = cli::also_format_keys(&key, format, &args.also_format);
+    targets.push((format, key.clone()));
+    let mut outputs = Vec::new();
+    for (format, key) in targets {
+        for (language, language_key) in cli::translation_keys(&key, format, &args.translate_to) {
+            let rendered = render(&transcript.in_translation(&language), format, &language_key)?;
+            outputs.push((language_key, rendered));
+        }
+        let rendered = render(&transcript, format, &key)?;
+        outputs.push((key, rendered));
+    }
 
-    fs::write(&output_path, &formatted_output)
+    storage
+        .put_all(&outputs, args.checksums)
+        .instrument(info_span!("stage", stage = "write"))
         .await
-        .context("Failed to write output file")?;
+        .context("Failed to write output files")?;
 
-    if !args.quiet {
+    if !settings.quiet {
         println!("Transcript saved to: {:?}", output_path);
     }
     info!("Transcript saved to: {:?}", output_path);
@@ -478,7 +1121,7 @@ async fn main() -> Result<()> {
     // Cleanup remote file if uploaded (unless --keep-remote-file was specified)
     if let Some((file_api, file_name)) = uploaded_file_name {
         if args.keep_remote_file {
-            if !args.quiet {
+            if !settings.quiet {
                 println!("Keeping remote file: {}", file_name);
             }
             info!("Keeping remote file: {}", file_name);
@@ -486,14 +1129,14 @@ async fn main() -> Result<()> {
             debug!("Cleaning up remote file: {}", file_name);
             match file_api.delete_file(&file_name).await {
                 Ok(()) => {
-                    if !args.quiet {
+                    if !settings.quiet {
                         println!("Remote file deleted.");
                     }
                     info!("Remote file deleted: {}", file_name);
                 }
                 Err(e) => {
                     warn!("Failed to delete remote file {}: {}", file_name, e);
-                    if !args.quiet {
+                    if !settings.quiet {
                         println!("Warning: Failed to delete remote file: {}", e);
                     }
                 }
@@ -501,49 +1144,48 @@ async fn main() -> Result<()> {
         }
     }
 
+    let exported = match &args.export_clips {
+        Some(dir) => {
+            let dir = match input.file_stem() {
+                Some(stem) if settings.several_inputs => dir.join(stem),
+                _ => dir.clone(),
+            };
+            export_clips(
+                &args.ffmpeg,
+                &audio_path,
+                &transcript,
+                duration,
+                &dir,
+                settings.quiet,
+            )
+            .instrument(info_span!("stage", stage = "export_clips"))
+            .await
+        }
+        None => Ok(()),
+    };
+
     if should_cleanup {
         debug!("Cleaning up temporary audio file: {:?}", audio_path);
         fs::remove_file(&audio_path).await.ok();
     }
+    exported?;
 
-    if !args.quiet {
+    if !settings.quiet {
         println!("\nSummary: {}", transcript.summary);
         println!("Total segments: {}", transcript.segments.len());
     }
 
-    Ok(())
+    Ok(Outcome::Saved)
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    run(Args::parse()).await
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use transcript_tool::TranscriptSegment;
-
-    fn create_test_transcript() -> TranscriptResponse {
-        TranscriptResponse {
-            summary: "Test summary".to_string(),
-            segments: vec![
-                TranscriptSegment {
-                    speaker: "Speaker 1".to_string(),
-                    timestamp: "00:05".to_string(),
-                    content: "Hello world".to_string(),
-                    language: "English".to_string(),
-                    language_code: "en".to_string(),
-                    translation: None,
-                    emotion: "neutral".to_string(),
-                },
-                TranscriptSegment {
-                    speaker: "Speaker 2".to_string(),
-                    timestamp: "00:10".to_string(),
-                    content: "Hi there".to_string(),
-                    language: "English".to_string(),
-                    language_code: "en".to_string(),
-                    translation: None,
-                    emotion: "happy".to_string(),
-                },
-            ],
-        }
-    }
 
     #[test]
     fn test_is_audio_file() {
@@ -572,53 +1214,43 @@ mod tests {
     }
 
     #[test]
-    fn test_format_timestamp_srt() {
-        assert_eq!(format_timestamp_srt("05:30"), "00:05:30,000");
-        assert_eq!(format_timestamp_srt("00:05"), "00:00:05,000");
+    fn test_several_inputs() {
+        let args = Args::parse_from(["convert", "-i", "a.mp4", "b.mp3", "-i", "c.mkv", "-j", "2"]);
+        assert_eq!(args.input, [PathBuf::from("a.mp4"), PathBuf::from("c.mkv")]);
+        assert_eq!(args.files, [PathBuf::from("b.mp3")]);
+        assert_eq!(args.jobs, 2);
+
+        assert!(Args::try_parse_from(["convert", "a.mp4"]).is_ok());
+        assert!(Args::try_parse_from(["convert"]).is_err());
+        assert!(Args::try_parse_from(["convert", "a.mp4", "-j", "0"]).is_err());
     }
 
     #[test]
-    fn test_format_timestamp_vtt() {
-        assert_eq!(format_timestamp_vtt("05:30"), "00:05:30.000");
-        assert_eq!(format_timestamp_vtt("00:05"), "00:00:05.000");
+    fn test_num_speakers() {
+        let args = Args::parse_from(["convert", "a.mp4", "--num-speakers", "2"]);
+        assert_eq!(args.num_speakers, Some(2));
+        assert!(Args::try_parse_from(["convert", "a.mp4", "--num-speakers", "0"]).is_err());
     }
 
     #[test]
-    fn test_transcript_to_srt() {
-        let transcript = create_test_transcript();
-        let srt = transcript_to_srt(&transcript);
-
-        assert!(srt.contains("1\n"));
-        assert!(srt.contains("00:00:05,000 --> 00:00:10,000"));
-        assert!(srt.contains("[Speaker 1] Hello world"));
-    }
-
-    #[test]
-    fn test_transcript_to_vtt() {
-        let transcript = create_test_transcript();
-        let vtt = transcript_to_vtt(&transcript);
-
-        assert!(vtt.starts_with("WEBVTT"));
-        assert!(vtt.contains("00:00:05.000 --> 00:00:10.000"));
-        assert!(vtt.contains("<v Speaker 1>Hello world"));
-    }
-
-    #[test]
-    fn test_transcript_to_txt() {
-        let transcript = create_test_transcript();
-        let txt = transcript_to_txt(&transcript);
-
-        assert!(txt.contains("Summary:"));
-        assert!(txt.contains("Test summary"));
-        assert!(txt.contains("[00:05] Speaker 1 (neutral)"));
-        assert!(txt.contains("Hello world"));
+    fn test_translate_to() {
+        let args = Args::parse_from(["convert", "a.mp4", "--translate-to", "es,fr,de"]);
+        assert_eq!(args.translate_to, ["es", "fr", "de"]);
+        assert!(
+            Args::parse_from(["convert", "a.mp4"])
+                .translate_to
+                .is_empty()
+        );
     }
 
     #[test]
-    fn test_get_output_extension() {
-        assert_eq!(get_output_extension(OutputFormat::Json), "json");
-        assert_eq!(get_output_extension(OutputFormat::Srt), "srt");
-        assert_eq!(get_output_extension(OutputFormat::Vtt), "vtt");
-        assert_eq!(get_output_extension(OutputFormat::Txt), "txt");
+    fn test_count_tokens_only() {
+        let args = Args::parse_from(["convert", "a.mp4", "--count-tokens-only"]);
+        assert!(args.count_tokens_only);
+        for other in [&["--dry-run"][..], &["--from", "10:00"], &["--to", "20:00"]] {
+            let mut argv = vec!["convert", "a.mp4", "--count-tokens-only"];
+            argv.extend_from_slice(other);
+            assert!(Args::try_parse_from(argv).is_err());
+        }
     }
 }
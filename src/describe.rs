@@ -0,0 +1,201 @@
+use anyhow::{Context, Result};
+use clap::Parser;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tracing::{Instrument, info_span};
+
+use transcript_tool::cli::{self, CommonArgs, init_logging};
+use transcript_tool::vision_api::{MAX_INLINE_IMAGE_SIZE, image_mime_type};
+use transcript_tool::{
+    Clients, ImageDescription, MediaPart, TextClientConfig, TextModel, VisionClient,
+};
+
+#[derive(Parser, Debug)]
+#[command(name = "describe")]
+#[command(version)]
+#[command(about = "Describe images: caption, objects, dominant colors and visible text")]
+#[command(after_help = "EXAMPLES:
+    describe photo.jpg
+    describe shots/*.png --json > captions.jsonl
+    describe poster.webp --hint \"Alt text for a conference website\"")]
+pub struct Args {
+    /// Image files (PNG, JPEG, WebP, GIF, HEIC, HEIF)
+    #[arg(value_name = "IMAGE", required = true)]
+    images: Vec<PathBuf>,
+
+    /// Print one JSON object per image
+    #[arg(long)]
+    json: bool,
+
+    /// Context for the model, e.g. what the description is for
+    #[arg(long)]
+    hint: Option<String>,
+
+    /// Model: flash (default), flash-lite, pro
+    #[arg(
+        short = 'm',
+        long,
+        env = "FLASHECHO_TEXT_MODEL",
+        default_value = "flash"
+    )]
+    model: String,
+
+    /// Timeout of each API request in seconds
+    #[arg(short, long, default_value = "120")]
+    timeout: u64,
+
+    #[command(flatten)]
+    common: CommonArgs,
+}
+
+/// Caption followed by `Objects:`, `Colors:` and `Text:` lines for the parts
+/// that aren't empty
+fn format_description(description: &ImageDescription) -> String {
+    let mut text = description.caption.clone();
+    if !description.objects.is_empty() {
+        text.push_str(&format!("\nObjects: {}", description.objects.join(", ")));
+    }
+    if !description.dominant_colors.is_empty() {
+        let colors: Vec<String> = description
+            .dominant_colors
+            .iter()
+            .map(|color| {
+                if color.hex.is_empty() {
+                    color.name.clone()
+                } else {
+                    format!("{} ({})", color.name, color.hex)
+                }
+            })
+            .collect();
+        text.push_str(&format!("\nColors: {}", colors.join(", ")));
+    }
+    if !description.text.is_empty() {
+        let lines: Vec<String> = description
+            .text
+            .iter()
+            .map(|line| format!("\"{}\"", line))
+            .collect();
+        text.push_str(&format!("\nText: {}", lines.join(", ")));
+    }
+    text
+}
+
+async fn read_image(path: &Path) -> Result<MediaPart> {
+    let mime_type = image_mime_type(path).with_context(|| {
+        format!(
+            "Unsupported image format: {:?} (use PNG, JPEG, WebP, GIF, HEIC or HEIF)",
+            path
+        )
+    })?;
+    let data = fs::read(path)
+        .await
+        .with_context(|| format!("Failed to read image {:?}", path))?;
+    if data.len() as u64 > MAX_INLINE_IMAGE_SIZE {
+        anyhow::bail!(
+            "Image {:?} is {:.1}MB; the limit is 20MB",
+            path,
+            data.len() as f64 / (1024.0 * 1024.0)
+        );
+    }
+    Ok(MediaPart::Inline {
+        mime_type: mime_type.to_string(),
+        data,
+    })
+}
+
+/// Run with parsed arguments; shared by this binary and its `flashecho` subcommand
+pub async fn run(args: Args) -> Result<()> {
+    init_logging(args.common.verbose, args.common.log_format);
+    args.common.install_usage_ledger("describe");
+
+    let model: TextModel = args.model.parse().map_err(|e: String| anyhow::anyhow!(e))?;
+    let profile = args.common.load_profile()?;
+    let clients =
+        Clients::new(cli::gemini_api_key(&profile)?).context("Failed to create HTTP client")?;
+    let budget = args
+        .common
+        .budget()
+        .context("Failed to read usage ledger for --budget-since")?;
+    let mut vision = VisionClient::new(clients.text(TextClientConfig {
+        timeout_secs: args.timeout,
+        max_retries: args.common.max_retries,
+        model,
+        cache: args.common.response_cache(),
+        budget,
+        ..Default::default()
+    }));
+    if let Some(hint) = &args.hint {
+        vision = vision.with_hint(hint);
+    }
+
+    let show_name = args.images.len() > 1;
+    for path in &args.images {
+        let span = info_span!("file", file = %path.display());
+        describe(&vision, path, &args, show_name)
+            .instrument(span)
+            .await?;
+    }
+    Ok(())
+}
+
+async fn describe(vision: &VisionClient, path: &Path, args: &Args, show_name: bool) -> Result<()> {
+    let image = read_image(path).await?;
+
+    let pb = cli::spinner(
+        args.common.quiet || args.json,
+        format!("Describing {}...", path.display()),
+    );
+    let result = vision
+        .describe(&image)
+        .instrument(info_span!("stage", stage = "describe"))
+        .await;
+    if let Some(pb) = pb {
+        pb.finish_and_clear();
+    }
+    let description =
+        result.map_err(|e| anyhow::anyhow!("Failed to describe {:?}: {}", path, e))?;
+
+    if args.json {
+        let mut line = serde_json::to_value(&description)?;
+        line["image"] = serde_json::json!(path.display().to_string());
+        println!("{}", serde_json::to_string(&line)?);
+    } else {
+        if show_name {
+            println!("{}", path.display());
+        }
+        println!("{}\n", format_description(&description));
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    run(Args::parse()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_description() {
+        let description: ImageDescription = serde_json::from_value(serde_json::json!({
+            "caption": "A shop front at night.",
+            "objects": ["door", "neon sign"],
+            "dominant_colors": [{"name": "navy", "hex": "#1A2B3C"}, {"name": "pink", "hex": ""}],
+            "text": ["OPEN"]
+        }))
+        .unwrap();
+        assert_eq!(
+            format_description(&description),
+            "A shop front at night.\nObjects: door, neon sign\n\
+             Colors: navy (#1A2B3C), pink\nText: \"OPEN\""
+        );
+
+        let bare = ImageDescription {
+            caption: "Fog.".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(format_description(&bare), "Fog.");
+    }
+}
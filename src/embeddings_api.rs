@@ -0,0 +1,687 @@
+//! Gemini text embeddings. [`EmbeddingsClient::embed`] calls `embedContent`
+//! for one text; [`EmbeddingsClient::embed_batch`] sends up to
+//! [`MAX_BATCH_SIZE`] texts per `batchEmbedContents` request and returns the
+//! vectors in input order.
+
+use reqwest::{Client, Method};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{Instrument, debug, field, info, info_span, warn};
+
+use crate::auth::{AuthMode, GEMINI_BASE_URL};
+use crate::budget::{Budget, BudgetExceeded};
+use crate::cache::ResponseCache;
+use crate::circuit_breaker::{CircuitBreaker, CircuitOpen};
+use crate::error::ErrorKind;
+use crate::proxy::ProxyConfig;
+use crate::redact::ApiKey;
+use crate::rt::Stopwatch;
+use crate::tls::TlsConfig;
+use crate::transport::TransportConfig;
+
+const DEFAULT_TIMEOUT_SECS: u64 = 60;
+const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// Most texts `batchEmbedContents` accepts in one request
+pub const MAX_BATCH_SIZE: usize = 100;
+
+/// Supported embedding models
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EmbeddingModel {
+    /// Gemini Embedding - 3072 dimensions, reducible with `output_dimensionality`
+    #[default]
+    Gemini,
+    /// text-embedding-004 - 768 dimensions
+    Text004,
+}
+
+impl EmbeddingModel {
+    pub fn api_model_name(&self) -> &'static str {
+        match self {
+            EmbeddingModel::Gemini => "gemini-embedding-001",
+            EmbeddingModel::Text004 => "text-embedding-004",
+        }
+    }
+}
+
+impl fmt::Display for EmbeddingModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmbeddingModel::Gemini => write!(f, "gemini"),
+            EmbeddingModel::Text004 => write!(f, "text-004"),
+        }
+    }
+}
+
+impl FromStr for EmbeddingModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "gemini" | "gemini-embedding-001" => Ok(EmbeddingModel::Gemini),
+            "text-004" | "text-embedding-004" => Ok(EmbeddingModel::Text004),
+            _ => Err(format!(
+                "Unknown embedding model: {}. Use 'gemini' or 'text-004'",
+                s
+            )),
+        }
+    }
+}
+
+/// What the vectors will be used for; the model tunes them accordingly
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskType {
+    /// A search query, compared against `RetrievalDocument` vectors
+    RetrievalQuery,
+    /// A document to be searched
+    RetrievalDocument,
+    SemanticSimilarity,
+    Classification,
+    Clustering,
+}
+
+impl TaskType {
+    fn api_name(&self) -> &'static str {
+        match self {
+            TaskType::RetrievalQuery => "RETRIEVAL_QUERY",
+            TaskType::RetrievalDocument => "RETRIEVAL_DOCUMENT",
+            TaskType::SemanticSimilarity => "SEMANTIC_SIMILARITY",
+            TaskType::Classification => "CLASSIFICATION",
+            TaskType::Clustering => "CLUSTERING",
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum EmbeddingError {
+    #[error("API key not found in environment (set GEMINI_API_KEY or GOOGLE_AI_KEY)")]
+    MissingApiKey,
+
+    #[error("Nothing to embed")]
+    EmptyInput,
+
+    #[error("Gemini API error ({status}, request {request_id}): {message}")]
+    ApiError {
+        status: u16,
+        message: String,
+        request_id: String,
+    },
+
+    #[error("Rate limited by API (request {request_id}). Retry after some time.")]
+    RateLimited { request_id: String },
+
+    #[error("Invalid response from Gemini API: {0}")]
+    InvalidResponse(String),
+
+    #[error("Network error: {0}")]
+    NetworkError(#[source] reqwest::Error),
+
+    #[error("JSON parsing error: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("Max retries ({0}) exceeded")]
+    MaxRetriesExceeded(u32),
+
+    #[error(transparent)]
+    CircuitOpen(#[from] CircuitOpen),
+
+    #[error(transparent)]
+    BudgetExceeded(#[from] BudgetExceeded),
+}
+
+pub type Result<T> = std::result::Result<T, EmbeddingError>;
+
+impl From<reqwest::Error> for EmbeddingError {
+    fn from(err: reqwest::Error) -> Self {
+        EmbeddingError::NetworkError(crate::redact::reqwest_error(err))
+    }
+}
+
+impl EmbeddingError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            EmbeddingError::MissingApiKey => ErrorKind::Config,
+            EmbeddingError::EmptyInput => ErrorKind::InvalidInput,
+            EmbeddingError::ApiError { .. } => ErrorKind::Api,
+            EmbeddingError::RateLimited { .. } => ErrorKind::RateLimited,
+            EmbeddingError::InvalidResponse(_) | EmbeddingError::JsonError(_) => {
+                ErrorKind::InvalidResponse
+            }
+            EmbeddingError::NetworkError(_) => ErrorKind::Network,
+            EmbeddingError::MaxRetriesExceeded(_) => ErrorKind::RetriesExhausted,
+            EmbeddingError::CircuitOpen(_) => ErrorKind::CircuitOpen,
+            EmbeddingError::BudgetExceeded(_) => ErrorKind::BudgetExceeded,
+        }
+    }
+
+    /// HTTP status returned by the API, if any
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            EmbeddingError::ApiError { status, .. } => Some(*status),
+            EmbeddingError::RateLimited { .. } => Some(429),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EmbeddingsClientConfig {
+    pub timeout_secs: u64,
+    pub max_retries: u32,
+    pub model: EmbeddingModel,
+    /// API host, e.g. a local mock server in tests
+    pub base_url: String,
+    /// Used by `with_config`; `with_http_client` keeps the given client's proxy
+    pub proxy: ProxyConfig,
+    /// Extra root certificates and builder hook, applied like `proxy`
+    pub tls: TlsConfig,
+    /// Connection pool, keep-alive and HTTP/2 settings, applied like `proxy`
+    pub transport: TransportConfig,
+    pub auth_mode: AuthMode,
+    /// Serve identical embedding requests from disk; `None` disables
+    pub cache: Option<ResponseCache>,
+    /// Shared with other clients of a batch to stop retrying when the API is down
+    pub circuit_breaker: Option<CircuitBreaker>,
+    /// Spend limit shared with other clients; requests fail once it is reached
+    pub budget: Option<Budget>,
+}
+
+impl Default for EmbeddingsClientConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: DEFAULT_TIMEOUT_SECS,
+            max_retries: DEFAULT_MAX_RETRIES,
+            model: EmbeddingModel::default(),
+            base_url: GEMINI_BASE_URL.to_string(),
+            proxy: ProxyConfig::default(),
+            tls: TlsConfig::default(),
+            transport: TransportConfig::default(),
+            auth_mode: AuthMode::default(),
+            cache: None,
+            circuit_breaker: None,
+            budget: None,
+        }
+    }
+}
+
+/// Per-request embedding options
+#[derive(Debug, Clone, Default)]
+pub struct EmbedConfig {
+    pub task_type: Option<TaskType>,
+    /// Truncate vectors to this many dimensions (Gemini model only)
+    pub output_dimensionality: Option<u32>,
+    /// Document title, used with `TaskType::RetrievalDocument`
+    pub title: Option<String>,
+}
+
+impl EmbedConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_task_type(mut self, task_type: TaskType) -> Self {
+        self.task_type = Some(task_type);
+        self
+    }
+
+    pub fn with_output_dimensionality(mut self, dimensions: u32) -> Self {
+        self.output_dimensionality = Some(dimensions);
+        self
+    }
+
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+}
+
+/// One embedding vector
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Embedding {
+    pub values: Vec<f32>,
+}
+
+impl Embedding {
+    pub fn dimensions(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Cosine similarity in [-1, 1]; 0 for vectors of different lengths or zero vectors
+    pub fn cosine_similarity(&self, other: &Embedding) -> f32 {
+        if self.values.len() != other.values.len() {
+            return 0.0;
+        }
+        let mut dot = 0.0;
+        let mut norm_a = 0.0;
+        let mut norm_b = 0.0;
+        for (a, b) in self.values.iter().zip(&other.values) {
+            dot += a * b;
+            norm_a += a * a;
+            norm_b += b * b;
+        }
+        if norm_a == 0.0 || norm_b == 0.0 {
+            return 0.0;
+        }
+        dot / (norm_a.sqrt() * norm_b.sqrt())
+    }
+
+    /// Scaled to unit length, so dot products equal cosine similarity
+    pub fn normalized(&self) -> Embedding {
+        let norm = self.values.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if norm == 0.0 {
+            return self.clone();
+        }
+        Embedding {
+            values: self.values.iter().map(|v| v / norm).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EmbeddingsClient {
+    client: Client,
+    api_key: ApiKey,
+    config: EmbeddingsClientConfig,
+}
+
+impl EmbeddingsClient {
+    pub fn new(api_key: String) -> Result<Self> {
+        Self::with_config(api_key, EmbeddingsClientConfig::default())
+    }
+
+    pub fn with_config(api_key: String, config: EmbeddingsClientConfig) -> Result<Self> {
+        let builder = crate::proxy::apply(
+            crate::rt::client_builder(config.timeout_secs),
+            &config.proxy,
+        )?;
+        let builder = crate::transport::apply(builder, &config.transport);
+        let client = crate::tls::apply(builder, &config.tls)?.build()?;
+
+        Ok(Self::with_http_client(client, api_key, config))
+    }
+
+    /// Build on an existing HTTP client, sharing its connection pool
+    pub fn with_http_client(
+        client: Client,
+        api_key: String,
+        config: EmbeddingsClientConfig,
+    ) -> Self {
+        Self {
+            client,
+            api_key: api_key.into(),
+            config,
+        }
+    }
+
+    pub fn model(&self) -> EmbeddingModel {
+        self.config.model
+    }
+
+    /// One `embedContent` request body
+    fn build_request(model: &str, text: &str, embed_config: Option<&EmbedConfig>) -> Value {
+        let mut request = json!({
+            "model": format!("models/{}", model),
+            "content": { "parts": [{ "text": text }] },
+        });
+        if let Some(cfg) = embed_config {
+            if let Some(task_type) = cfg.task_type {
+                request["taskType"] = json!(task_type.api_name());
+            }
+            if let Some(dimensions) = cfg.output_dimensionality {
+                request["outputDimensionality"] = json!(dimensions);
+            }
+            if let Some(title) = &cfg.title {
+                request["title"] = json!(title);
+            }
+        }
+        request
+    }
+
+    fn build_batch_payload(
+        model: &str,
+        texts: &[&str],
+        embed_config: Option<&EmbedConfig>,
+    ) -> Value {
+        let requests: Vec<Value> = texts
+            .iter()
+            .map(|text| Self::build_request(model, text, embed_config))
+            .collect();
+        json!({ "requests": requests })
+    }
+
+    /// Characters of text in a single or batch payload
+    fn input_chars(payload: &Value) -> usize {
+        let requests = match payload["requests"].as_array() {
+            Some(requests) => requests.iter().collect(),
+            None => vec![payload],
+        };
+        requests
+            .iter()
+            .filter_map(|request| request["content"]["parts"][0]["text"].as_str())
+            .map(|text| text.chars().count())
+            .sum()
+    }
+
+    /// Vectors of an `embedContent` (`embedding`) or `batchEmbedContents`
+    /// (`embeddings`) response
+    fn parse_embeddings(data: &Value, expected: usize) -> Result<Vec<Embedding>> {
+        let items: Vec<&Value> = match data.get("embeddings") {
+            Some(list) => list
+                .as_array()
+                .ok_or_else(|| {
+                    EmbeddingError::InvalidResponse("embeddings is not a list".to_string())
+                })?
+                .iter()
+                .collect(),
+            None => vec![&data["embedding"]],
+        };
+        if items.len() != expected {
+            return Err(EmbeddingError::InvalidResponse(format!(
+                "expected {} embeddings, got {}",
+                expected,
+                items.len()
+            )));
+        }
+        items
+            .into_iter()
+            .map(|item| {
+                serde_json::from_value(item.clone())
+                    .map_err(|e| EmbeddingError::InvalidResponse(format!("bad embedding: {}", e)))
+            })
+            .collect()
+    }
+
+    async fn send_request(
+        &self,
+        method: &str,
+        payload: &Value,
+        expected: usize,
+        request_id: &str,
+    ) -> Result<Vec<Embedding>> {
+        let model = self.config.model.api_model_name();
+        let url = format!(
+            "{}/v1beta/models/{}:{}",
+            self.config.base_url, model, method
+        );
+
+        debug!(
+            "Sending {} request with {} texts to Gemini API (model: {})",
+            method, expected, self.config.model
+        );
+
+        let response = self
+            .config
+            .auth_mode
+            .request(&self.client, Method::POST, &url, &self.api_key)
+            .timeout(Duration::from_secs(self.config.timeout_secs))
+            .header("Content-Type", "application/json")
+            .json(payload)
+            .send()
+            .await?;
+
+        let status = response.status();
+        debug!("Received response with status: {}", status);
+
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            crate::metrics::record_rate_limited("embeddings");
+            return Err(EmbeddingError::RateLimited {
+                request_id: request_id.to_string(),
+            });
+        }
+
+        if !status.is_success() {
+            let error_text = self
+                .api_key
+                .scrub(response.text().await.unwrap_or_default());
+            return Err(EmbeddingError::ApiError {
+                status: status.as_u16(),
+                message: error_text,
+                request_id: request_id.to_string(),
+            });
+        }
+
+        let data: Value = response.json().await?;
+        let embeddings = Self::parse_embeddings(&data, expected)?;
+
+        crate::usage::record_embeddings(
+            model,
+            Self::input_chars(payload),
+            self.config.budget.as_ref(),
+        );
+
+        Ok(embeddings)
+    }
+
+    fn is_retryable_error(err: &EmbeddingError) -> bool {
+        crate::error::is_retryable(err.kind(), err.status())
+    }
+
+    /// Embed one text
+    pub async fn embed(&self, text: &str, embed_config: Option<&EmbedConfig>) -> Result<Embedding> {
+        if text.trim().is_empty() {
+            return Err(EmbeddingError::EmptyInput);
+        }
+        let payload = Self::build_request(self.config.model.api_model_name(), text, embed_config);
+        let mut embeddings = self.embed_cached("embedContent", &payload, 1).await?;
+        Ok(embeddings.remove(0))
+    }
+
+    /// Embed many texts, [`MAX_BATCH_SIZE`] per request, in input order
+    pub async fn embed_batch(
+        &self,
+        texts: &[&str],
+        embed_config: Option<&EmbedConfig>,
+    ) -> Result<Vec<Embedding>> {
+        if texts.is_empty() || texts.iter().any(|text| text.trim().is_empty()) {
+            return Err(EmbeddingError::EmptyInput);
+        }
+        let model = self.config.model.api_model_name();
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for chunk in texts.chunks(MAX_BATCH_SIZE) {
+            let payload = Self::build_batch_payload(model, chunk, embed_config);
+            embeddings.extend(
+                self.embed_cached("batchEmbedContents", &payload, chunk.len())
+                    .await?,
+            );
+        }
+        Ok(embeddings)
+    }
+
+    async fn embed_cached(
+        &self,
+        method: &str,
+        payload: &Value,
+        expected: usize,
+    ) -> Result<Vec<Embedding>> {
+        let Some(cache) = &self.config.cache else {
+            return self.embed_with_retry(method, payload, expected).await;
+        };
+
+        let key = ResponseCache::key(&[
+            self.config.model.api_model_name().as_bytes(),
+            payload.to_string().as_bytes(),
+        ]);
+        if let Some(embeddings) = cache.get_json::<Vec<Embedding>>(&key).await
+            && embeddings.len() == expected
+        {
+            info!("Using cached embeddings");
+            return Ok(embeddings);
+        }
+
+        let embeddings = self.embed_with_retry(method, payload, expected).await?;
+        cache.put_json(&key, &embeddings).await;
+        Ok(embeddings)
+    }
+
+    async fn embed_with_retry(
+        &self,
+        method: &str,
+        payload: &Value,
+        expected: usize,
+    ) -> Result<Vec<Embedding>> {
+        let request_id = crate::request_id::next();
+        let span = info_span!(
+            "embeddings_request",
+            request_id = %request_id,
+            model = %self.config.model,
+            payload_bytes = payload.to_string().len(),
+            attempt = field::Empty,
+            latency_ms = field::Empty,
+        );
+
+        async {
+            let mut last_error = None;
+            let mut retry_count = 0;
+
+            while retry_count < self.config.max_retries {
+                if let Some(breaker) = &self.config.circuit_breaker {
+                    breaker.check()?;
+                }
+                if let Some(budget) = &self.config.budget {
+                    budget.check()?;
+                }
+                span.record("attempt", retry_count + 1);
+                let stopwatch = Stopwatch::start();
+                let result = self
+                    .send_request(method, payload, expected, &request_id)
+                    .await;
+                span.record("latency_ms", stopwatch.elapsed_ms());
+                if let Some(breaker) = &self.config.circuit_breaker {
+                    breaker.record(&result, Self::is_retryable_error);
+                }
+                crate::metrics::record_request(
+                    "embeddings",
+                    result.is_ok(),
+                    stopwatch.elapsed_ms(),
+                );
+                debug!(
+                    "Attempt {} finished in {}ms",
+                    retry_count + 1,
+                    stopwatch.elapsed_ms()
+                );
+
+                match result {
+                    Ok(embeddings) => {
+                        info!("Embedded {} texts", embeddings.len());
+                        return Ok(embeddings);
+                    }
+                    Err(e) => {
+                        if Self::is_retryable_error(&e)
+                            && retry_count + 1 < self.config.max_retries
+                            && self
+                                .config
+                                .circuit_breaker
+                                .as_ref()
+                                .is_none_or(CircuitBreaker::try_retry)
+                        {
+                            let delay = if matches!(e, EmbeddingError::RateLimited { .. }) {
+                                Duration::from_secs(30 * (retry_count as u64 + 1))
+                            } else {
+                                Duration::from_secs(2u64.pow(retry_count))
+                            };
+                            warn!(
+                                "Request failed (attempt {}/{}): {}. Retrying in {:?}...",
+                                retry_count + 1,
+                                self.config.max_retries,
+                                e,
+                                delay
+                            );
+                            crate::metrics::record_retry("embeddings");
+                            crate::rt::sleep(delay).await;
+                            retry_count += 1;
+                            last_error = Some(e);
+                        } else {
+                            return Err(e);
+                        }
+                    }
+                }
+            }
+
+            Err(last_error.unwrap_or(EmbeddingError::MaxRetriesExceeded(self.config.max_retries)))
+        }
+        .instrument(span.clone())
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedding_model_from_str() {
+        assert_eq!(
+            EmbeddingModel::from_str("gemini").unwrap(),
+            EmbeddingModel::Gemini
+        );
+        assert_eq!(
+            EmbeddingModel::from_str("text-embedding-004").unwrap(),
+            EmbeddingModel::Text004
+        );
+        assert!(EmbeddingModel::from_str("ada-002").is_err());
+    }
+
+    #[test]
+    fn test_build_request() {
+        let cfg = EmbedConfig::new()
+            .with_task_type(TaskType::RetrievalDocument)
+            .with_output_dimensionality(768)
+            .with_title("Episode 12");
+        let request = EmbeddingsClient::build_request("gemini-embedding-001", "Hello", Some(&cfg));
+        assert_eq!(request["model"], "models/gemini-embedding-001");
+        assert_eq!(request["content"]["parts"][0]["text"], "Hello");
+        assert_eq!(request["taskType"], "RETRIEVAL_DOCUMENT");
+        assert_eq!(request["outputDimensionality"], 768);
+        assert_eq!(request["title"], "Episode 12");
+
+        let plain = EmbeddingsClient::build_request("text-embedding-004", "Hi", None);
+        assert!(plain.get("taskType").is_none());
+
+        let batch = EmbeddingsClient::build_batch_payload("text-embedding-004", &["a", "b"], None);
+        assert_eq!(batch["requests"].as_array().unwrap().len(), 2);
+        assert_eq!(batch["requests"][1]["content"]["parts"][0]["text"], "b");
+        assert_eq!(EmbeddingsClient::input_chars(&batch), 2);
+        assert_eq!(EmbeddingsClient::input_chars(&request), 5);
+    }
+
+    #[test]
+    fn test_parse_embeddings() {
+        let single = json!({ "embedding": { "values": [0.5, -0.5] } });
+        let parsed = EmbeddingsClient::parse_embeddings(&single, 1).unwrap();
+        assert_eq!(parsed[0].values, [0.5, -0.5]);
+
+        let batch = json!({ "embeddings": [{ "values": [1.0] }, { "values": [2.0] }] });
+        let parsed = EmbeddingsClient::parse_embeddings(&batch, 2).unwrap();
+        assert_eq!(parsed[1].values, [2.0]);
+
+        assert!(EmbeddingsClient::parse_embeddings(&batch, 3).is_err());
+        assert!(EmbeddingsClient::parse_embeddings(&json!({}), 1).is_err());
+    }
+
+    #[test]
+    fn test_cosine_similarity() {
+        let a = Embedding {
+            values: vec![1.0, 0.0],
+        };
+        let b = Embedding {
+            values: vec![0.0, 2.0],
+        };
+        let c = Embedding {
+            values: vec![3.0, 0.0],
+        };
+        assert_eq!(a.cosine_similarity(&b), 0.0);
+        assert!((a.cosine_similarity(&c) - 1.0).abs() < 1e-6);
+        assert_eq!(c.normalized().values, [1.0, 0.0]);
+
+        let zero = Embedding {
+            values: vec![0.0, 0.0],
+        };
+        assert_eq!(a.cosine_similarity(&zero), 0.0);
+        let short = Embedding { values: vec![1.0] };
+        assert_eq!(a.cosine_similarity(&short), 0.0);
+    }
+}
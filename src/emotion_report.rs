@@ -0,0 +1,405 @@
+//! Emotion timeline of a transcript: how often each speaker sounded happy,
+//! sad, angry or neutral, and how the mood moves over time buckets. Rendered
+//! as JSON, CSV or a standalone HTML page with SVG charts.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::gemini_api::TranscriptResponse;
+
+/// Emotions the transcription schema allows, in report column order
+pub const EMOTIONS: [&str; 4] = ["happy", "sad", "angry", "neutral"];
+
+/// Default length of a timeline bucket
+pub const DEFAULT_BUCKET_SECS: u64 = 60;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EmotionReport {
+    pub bucket_secs: u64,
+    /// Column order: [`EMOTIONS`], then any other labels found
+    pub emotions: Vec<String>,
+    pub segments: usize,
+    pub counts: BTreeMap<String, usize>,
+    /// In order of first appearance
+    pub speakers: Vec<SpeakerEmotions>,
+    pub timeline: Vec<EmotionBucket>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SpeakerEmotions {
+    pub speaker: String,
+    pub segments: usize,
+    pub counts: BTreeMap<String, usize>,
+    pub dominant: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EmotionBucket {
+    pub start: String,
+    pub end: String,
+    pub segments: usize,
+    pub counts: BTreeMap<String, usize>,
+    pub dominant: Option<String>,
+}
+
+/// Lowercased emotion; blank labels count as `unknown`
+fn emotion_label(emotion: &str) -> String {
+    let emotion = emotion.trim().to_lowercase();
+    if emotion.is_empty() {
+        "unknown".to_string()
+    } else {
+        emotion
+    }
+}
+
+/// `MM:SS`, or `HH:MM:SS` from the first hour on
+fn format_clock(secs: u64) -> String {
+    if secs >= 3600 {
+        format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+impl EmotionReport {
+    /// Count segment emotions per speaker and per `bucket_secs` window
+    pub fn from_transcript(transcript: &TranscriptResponse, bucket_secs: u64) -> Self {
+        let bucket_secs = bucket_secs.max(1);
+        let mut emotions: Vec<String> = EMOTIONS.iter().map(|e| e.to_string()).collect();
+        let mut counts = BTreeMap::new();
+        let mut speakers: Vec<SpeakerEmotions> = Vec::new();
+        let mut timed = Vec::new();
+
+        for segment in &transcript.segments {
+            let emotion = emotion_label(&segment.emotion);
+            if !emotions.contains(&emotion) {
+                emotions.push(emotion.clone());
+            }
+            *counts.entry(emotion.clone()).or_insert(0) += 1;
+
+            let index = match speakers.iter().position(|s| s.speaker == segment.speaker) {
+                Some(index) => index,
+                None => {
+                    speakers.push(SpeakerEmotions {
+                        speaker: segment.speaker.clone(),
+                        segments: 0,
+                        counts: BTreeMap::new(),
+                        dominant: None,
+                    });
+                    speakers.len() - 1
+                }
+            };
+            speakers[index].segments += 1;
+            *speakers[index].counts.entry(emotion.clone()).or_insert(0) += 1;
+
+            timed.push((segment.timestamp.secs() / bucket_secs, emotion));
+        }
+
+        let buckets = timed
+            .iter()
+            .map(|(bucket, _)| bucket + 1)
+            .max()
+            .unwrap_or(0);
+        let mut timeline: Vec<EmotionBucket> = (0..buckets)
+            .map(|bucket| EmotionBucket {
+                start: format_clock(bucket * bucket_secs),
+                end: format_clock((bucket + 1) * bucket_secs),
+                segments: 0,
+                counts: BTreeMap::new(),
+                dominant: None,
+            })
+            .collect();
+        for (bucket, emotion) in timed {
+            let bucket = &mut timeline[bucket as usize];
+            bucket.segments += 1;
+            *bucket.counts.entry(emotion).or_insert(0) += 1;
+        }
+
+        for speaker in &mut speakers {
+            speaker.dominant = dominant(&emotions, &speaker.counts);
+        }
+        for bucket in &mut timeline {
+            bucket.dominant = dominant(&emotions, &bucket.counts);
+        }
+
+        Self {
+            bucket_secs,
+            emotions,
+            segments: transcript.segments.len(),
+            counts,
+            speakers,
+            timeline,
+        }
+    }
+
+    /// One row per speaker (`scope` = `speaker`) followed by one per time
+    /// bucket (`scope` = `time`, label `start-end`), with a count column per
+    /// emotion
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("scope,label,segments");
+        for emotion in &self.emotions {
+            csv.push(',');
+            csv.push_str(&csv_field(emotion));
+        }
+        csv.push_str(",dominant\n");
+
+        let mut row = |scope: &str,
+                       label: &str,
+                       segments: usize,
+                       counts: &BTreeMap<String, usize>,
+                       dominant: &Option<String>| {
+            csv.push_str(&format!("{},{},{}", scope, csv_field(label), segments));
+            for emotion in &self.emotions {
+                csv.push_str(&format!(",{}", counts.get(emotion).copied().unwrap_or(0)));
+            }
+            csv.push_str(&format!(
+                ",{}\n",
+                csv_field(dominant.as_deref().unwrap_or(""))
+            ));
+        };
+        for speaker in &self.speakers {
+            row(
+                "speaker",
+                &speaker.speaker,
+                speaker.segments,
+                &speaker.counts,
+                &speaker.dominant,
+            );
+        }
+        for bucket in &self.timeline {
+            row(
+                "time",
+                &format!("{}-{}", bucket.start, bucket.end),
+                bucket.segments,
+                &bucket.counts,
+                &bucket.dominant,
+            );
+        }
+        csv
+    }
+
+    /// Standalone page: a stacked bar per time bucket and a 100% bar per
+    /// speaker, drawn as inline SVG so it opens offline
+    pub fn to_html(&self, title: &str) -> String {
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>Emotion timeline: {title}</title>\n<style>\n\
+             body {{ font-family: sans-serif; margin: 2em; color: #222; }}\n\
+             .legend span {{ display: inline-block; margin-right: 1.2em; }}\n\
+             .legend i {{ display: inline-block; width: 0.9em; height: 0.9em; margin-right: 0.3em; vertical-align: middle; }}\n\
+             svg text {{ font-size: 11px; fill: #444; }}\n\
+             </style>\n</head>\n<body>\n<h1>Emotion timeline: {title}</h1>\n",
+            title = html_escape(title)
+        );
+        html.push_str(&format!(
+            "<p>{} segments, {} speakers, {}-second buckets</p>\n<p class=\"legend\">",
+            self.segments,
+            self.speakers.len(),
+            self.bucket_secs
+        ));
+        for emotion in &self.emotions {
+            html.push_str(&format!(
+                "<span><i style=\"background:{}\"></i>{}</span>",
+                emotion_color(emotion),
+                html_escape(emotion)
+            ));
+        }
+        html.push_str("</p>\n<h2>Over time</h2>\n");
+        html.push_str(&self.timeline_svg());
+        html.push_str("<h2>By speaker</h2>\n");
+        html.push_str(&self.speakers_svg());
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    fn timeline_svg(&self) -> String {
+        const HEIGHT: f64 = 200.0;
+        const BAR: f64 = 24.0;
+        const GAP: f64 = 6.0;
+        let max = self
+            .timeline
+            .iter()
+            .map(|b| b.segments)
+            .max()
+            .unwrap_or(0)
+            .max(1) as f64;
+        let width = (self.timeline.len() as f64 * (BAR + GAP)).max(BAR + GAP);
+        let mut svg = format!(
+            "<svg width=\"{}\" height=\"{}\" role=\"img\">\n",
+            width,
+            HEIGHT + 20.0
+        );
+        for (i, bucket) in self.timeline.iter().enumerate() {
+            let x = i as f64 * (BAR + GAP);
+            let mut y = HEIGHT;
+            for emotion in &self.emotions {
+                let count = bucket.counts.get(emotion).copied().unwrap_or(0);
+                if count == 0 {
+                    continue;
+                }
+                let h = count as f64 / max * HEIGHT;
+                y -= h;
+                svg.push_str(&format!(
+                    "<rect x=\"{x:.1}\" y=\"{y:.1}\" width=\"{BAR}\" height=\"{h:.1}\" fill=\"{}\">\
+                     <title>{} {}: {} {}</title></rect>\n",
+                    emotion_color(emotion),
+                    bucket.start,
+                    html_escape(emotion),
+                    count,
+                    if count == 1 { "segment" } else { "segments" }
+                ));
+            }
+            // Label every fifth bucket so long recordings stay readable
+            if i % 5 == 0 {
+                svg.push_str(&format!(
+                    "<text x=\"{:.1}\" y=\"{}\">{}</text>\n",
+                    x,
+                    HEIGHT + 15.0,
+                    bucket.start
+                ));
+            }
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    fn speakers_svg(&self) -> String {
+        const LABEL: f64 = 140.0;
+        const WIDTH: f64 = 400.0;
+        const ROW: f64 = 26.0;
+        let mut svg = format!(
+            "<svg width=\"{}\" height=\"{}\" role=\"img\">\n",
+            LABEL + WIDTH,
+            (self.speakers.len() as f64 * ROW).max(ROW)
+        );
+        for (i, speaker) in self.speakers.iter().enumerate() {
+            let y = i as f64 * ROW;
+            svg.push_str(&format!(
+                "<text x=\"0\" y=\"{:.1}\">{}</text>\n",
+                y + 15.0,
+                html_escape(&speaker.speaker)
+            ));
+            let mut x = LABEL;
+            for emotion in &self.emotions {
+                let count = speaker.counts.get(emotion).copied().unwrap_or(0);
+                if count == 0 {
+                    continue;
+                }
+                let share = count as f64 / speaker.segments.max(1) as f64;
+                let w = share * WIDTH;
+                svg.push_str(&format!(
+                    "<rect x=\"{x:.1}\" y=\"{y:.1}\" width=\"{w:.1}\" height=\"20\" fill=\"{}\">\
+                     <title>{}: {:.0}% ({})</title></rect>\n",
+                    emotion_color(emotion),
+                    html_escape(emotion),
+                    share * 100.0,
+                    count
+                ));
+                x += w;
+            }
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+/// Most frequent emotion; ties go to the earlier column
+fn dominant(emotions: &[String], counts: &BTreeMap<String, usize>) -> Option<String> {
+    let mut best: Option<(&String, usize)> = None;
+    for emotion in emotions {
+        let count = counts.get(emotion).copied().unwrap_or(0);
+        if count > 0 && best.is_none_or(|(_, n)| count > n) {
+            best = Some((emotion, count));
+        }
+    }
+    best.map(|(emotion, _)| emotion.clone())
+}
+
+fn emotion_color(emotion: &str) -> &'static str {
+    match emotion {
+        "happy" => "#f2c14e",
+        "sad" => "#4e79a7",
+        "angry" => "#e15759",
+        "neutral" => "#bab0ac",
+        _ => "#76b7b2",
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn html_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn transcript() -> TranscriptResponse {
+        let segment = |speaker: &str, timestamp: &str, emotion: &str| {
+            json!({"speaker": speaker, "timestamp": timestamp, "content": "...",
+                   "language": "English", "language_code": "en", "emotion": emotion})
+        };
+        serde_json::from_value(json!({
+            "summary": "",
+            "segments": [
+                segment("Ann", "00:05", "happy"),
+                segment("Bo, PM", "00:40", "Angry"),
+                segment("Ann", "00:50", "happy"),
+                segment("Ann", "02:10", "neutral"),
+                segment("Bo, PM", "02:20", ""),
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_from_transcript() {
+        let report = EmotionReport::from_transcript(&transcript(), 60);
+        assert_eq!(report.emotions.last().map(String::as_str), Some("unknown"));
+        assert_eq!(report.speakers[0].speaker, "Ann");
+        assert_eq!(report.speakers[0].dominant.as_deref(), Some("happy"));
+        assert_eq!(report.speakers[1].segments, 2);
+        // Tie between angry and unknown goes to the schema emotion
+        assert_eq!(report.speakers[1].dominant.as_deref(), Some("angry"));
+
+        assert_eq!(report.timeline.len(), 3);
+        assert_eq!(report.timeline[0].segments, 3);
+        assert_eq!(report.timeline[1].segments, 0);
+        assert_eq!(report.timeline[1].dominant, None);
+        assert_eq!(report.timeline[2].start, "02:00");
+        assert_eq!(report.timeline[2].dominant.as_deref(), Some("neutral"));
+    }
+
+    #[test]
+    fn test_to_csv() {
+        let csv = EmotionReport::from_transcript(&transcript(), 60).to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "scope,label,segments,happy,sad,angry,neutral,unknown,dominant"
+        );
+        assert_eq!(lines[2], "speaker,\"Bo, PM\",2,0,0,1,0,1,angry");
+        assert_eq!(lines[3], "time,00:00-01:00,3,2,0,1,0,0,happy");
+        assert_eq!(lines[4], "time,01:00-02:00,0,0,0,0,0,0,");
+    }
+
+    #[test]
+    fn test_to_html_escapes_labels() {
+        let html = EmotionReport::from_transcript(&transcript(), 60).to_html("a <b>");
+        assert!(html.contains("<title>Emotion timeline: a &lt;b&gt;</title>"));
+        assert!(html.contains("<svg"));
+        assert_eq!(format_clock(3725), "01:02:05");
+    }
+}
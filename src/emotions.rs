@@ -0,0 +1,184 @@
+use anyhow::{Context, Result};
+use clap::{Parser, ValueEnum};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tracing::{Instrument, debug, info, info_span};
+
+use transcript_tool::cli::{LogFormat, init_logging};
+use transcript_tool::emotion_report::DEFAULT_BUCKET_SECS;
+use transcript_tool::storage::{OutputStorage, Storage};
+use transcript_tool::{EmotionReport, TranscriptResponse};
+
+#[derive(Parser, Debug)]
+#[command(name = "emotions")]
+#[command(version)]
+#[command(about = "Summarize the emotions of a transcript per speaker and over time")]
+#[command(after_help = "EXAMPLES:
+    emotions interview.json
+    emotions interview.json -f csv --bucket 30
+    emotions interview.json -f html -o report.html
+    emotions interview.json -o - | jq '.speakers'")]
+pub struct Args {
+    /// Transcript JSON written by `convert` or `batch_convert` (`-` for stdin)
+    #[arg(value_name = "TRANSCRIPT")]
+    input: PathBuf,
+
+    /// Output file path (`-` for stdout), or an s3:// / gs:// URI
+    /// [default: <input>.emotions.<format>]
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Report format
+    #[arg(short, long, value_enum, default_value = "json")]
+    format: ReportFormat,
+
+    /// Length of a timeline bucket in seconds
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = DEFAULT_BUCKET_SECS,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    bucket: u64,
+
+    /// Verbosity level (-v, -vv, -vvv)
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Log output format: human-readable text, or one JSON object per line
+    #[arg(long, value_enum, default_value = "text", env = "FLASHECHO_LOG_FORMAT")]
+    log_format: LogFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ReportFormat {
+    Json,
+    Csv,
+    /// Standalone page with charts
+    Html,
+}
+
+impl ReportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ReportFormat::Json => "json",
+            ReportFormat::Csv => "csv",
+            ReportFormat::Html => "html",
+        }
+    }
+}
+
+/// `talk.json` -> `talk.emotions.csv`
+fn default_output(input: &Path, format: ReportFormat) -> PathBuf {
+    let extension = format!("emotions.{}", format.extension());
+    if input == Path::new("-") {
+        return PathBuf::from(format!("transcript.{}", extension));
+    }
+    input.with_extension(extension)
+}
+
+fn default_title(input: &Path) -> String {
+    match input.file_stem() {
+        Some(stem) if input != Path::new("-") => stem.to_string_lossy().to_string(),
+        _ => "transcript".to_string(),
+    }
+}
+
+async fn read_input(path: &Path) -> Result<String> {
+    if path == Path::new("-") {
+        let mut content = String::new();
+        tokio::io::stdin()
+            .read_to_string(&mut content)
+            .await
+            .context("Failed to read stdin")?;
+        return Ok(content);
+    }
+    fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Failed to read transcript {:?}", path))
+}
+
+/// Run with parsed arguments; shared by this binary and its `flashecho` subcommand
+pub async fn run(args: Args) -> Result<()> {
+    init_logging(args.verbose, args.log_format);
+
+    let span = info_span!("file", file = %args.input.display());
+    report(args).instrument(span).await
+}
+
+async fn report(args: Args) -> Result<()> {
+    let content = read_input(&args.input).await?;
+    let transcript = TranscriptResponse::from_json_any_version(&content)
+        .context("Failed to parse transcript JSON")?;
+    if transcript.segments.is_empty() {
+        anyhow::bail!("Transcript has no segments");
+    }
+
+    let report = EmotionReport::from_transcript(&transcript, args.bucket);
+    debug!(
+        "{} speakers, {} time buckets",
+        report.speakers.len(),
+        report.timeline.len()
+    );
+    let output = match args.format {
+        ReportFormat::Json => {
+            serde_json::to_string_pretty(&report).context("Failed to serialize report")?
+        }
+        ReportFormat::Csv => report.to_csv(),
+        ReportFormat::Html => report.to_html(&default_title(&args.input)),
+    };
+
+    if args.output.as_deref() == Some("-") {
+        let mut stdout = tokio::io::stdout();
+        stdout
+            .write_all(output.as_bytes())
+            .await
+            .context("Failed to write to stdout")?;
+        stdout.flush().await.context("Failed to flush stdout")?;
+        return Ok(());
+    }
+
+    let default_path = default_output(&args.input, args.format);
+    let default_name = default_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let target = args
+        .output
+        .clone()
+        .unwrap_or_else(|| default_path.to_string_lossy().to_string());
+    let (storage, key) = OutputStorage::open_file(&target, &default_name)
+        .with_context(|| format!("Invalid output target: {}", target))?;
+    storage
+        .put(&key, output.as_bytes())
+        .instrument(info_span!("stage", stage = "write"))
+        .await
+        .context("Failed to write report")?;
+    let location = storage.location(&key);
+    info!("Emotion report written to {}", location);
+    println!("Saved to: {}", location);
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    run(Args::parse()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_output() {
+        assert_eq!(
+            default_output(Path::new("talks/interview.json"), ReportFormat::Csv),
+            PathBuf::from("talks/interview.emotions.csv")
+        );
+        assert_eq!(
+            default_output(Path::new("-"), ReportFormat::Html),
+            PathBuf::from("transcript.emotions.html")
+        );
+    }
+}
@@ -0,0 +1,307 @@
+//! Crate-level error type. Every module error converts into [`Error`], which
+//! classifies failures the same way for all clients via [`Error::kind`],
+//! [`Error::status`] and [`Error::is_retryable`].
+
+#[cfg(feature = "__client")]
+use thiserror::Error;
+
+#[cfg(feature = "embeddings")]
+use crate::embeddings_api::EmbeddingError;
+#[cfg(feature = "file-api")]
+use crate::file_api::FileApiError;
+#[cfg(all(feature = "imagen", not(target_arch = "wasm32")))]
+use crate::gallery::GalleryError;
+#[cfg(feature = "transcription")]
+use crate::gemini_api::GeminiError;
+#[cfg(all(feature = "imagen", not(target_arch = "wasm32")))]
+use crate::image_convert::ImageConvertError;
+#[cfg(feature = "imagen")]
+use crate::imagen_api::ImagenError;
+#[cfg(feature = "imagen-edit")]
+use crate::imagen_edit_api::ImageEditError;
+#[cfg(feature = "music")]
+use crate::music_api::MusicError;
+#[cfg(feature = "transcription")]
+use crate::openai_api::OpenAiError;
+#[cfg(all(
+    feature = "transcription",
+    feature = "embeddings",
+    not(target_arch = "wasm32")
+))]
+use crate::search_index::SearchIndexError;
+#[cfg(feature = "transcription")]
+use crate::subtitle::SubtitleError;
+#[cfg(feature = "text")]
+use crate::text_api::TextError;
+#[cfg(feature = "transcription")]
+use crate::transcript_schema::SchemaError;
+#[cfg(all(feature = "transcription", not(target_arch = "wasm32")))]
+use crate::transcription::TranscriptionError;
+#[cfg(feature = "tts")]
+use crate::tts_api::TtsError;
+#[cfg(feature = "veo")]
+use crate::veo_api::VeoError;
+#[cfg(all(feature = "vision", not(target_arch = "wasm32")))]
+use crate::video_frames::FrameError;
+
+/// Broad category of a failure, shared by every client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// Missing API key, or a setting the selected model doesn't support
+    Config,
+    /// Input rejected before any request was sent (size, format, no images)
+    InvalidInput,
+    /// The API answered HTTP 429
+    RateLimited,
+    /// The API answered with another non-success status
+    Api,
+    /// Connection, TLS or timeout failure
+    Network,
+    /// The response didn't have the expected shape
+    InvalidResponse,
+    /// Every retry attempt failed
+    RetriesExhausted,
+    /// A shared circuit breaker tripped; no request was sent
+    CircuitOpen,
+    /// A shared spend or token budget is used up; no request was sent
+    BudgetExceeded,
+    /// File API upload, processing or deletion failed
+    Upload,
+    /// Local filesystem or ffmpeg failure
+    Io,
+}
+
+/// Retry policy shared by all clients: rate limits, network failures and
+/// server-side (5xx) errors are transient
+#[cfg(feature = "__client")]
+pub(crate) fn is_retryable(kind: ErrorKind, status: Option<u16>) -> bool {
+    match kind {
+        ErrorKind::RateLimited | ErrorKind::Network => true,
+        ErrorKind::Api => status.is_some_and(|status| status >= 500),
+        _ => false,
+    }
+}
+
+#[cfg(feature = "__client")]
+#[derive(Debug, Error)]
+pub enum Error {
+    #[cfg(feature = "transcription")]
+    #[error(transparent)]
+    Gemini(#[from] GeminiError),
+
+    #[cfg(feature = "file-api")]
+    #[error(transparent)]
+    FileApi(#[from] FileApiError),
+
+    #[cfg(feature = "imagen")]
+    #[error(transparent)]
+    Imagen(#[from] ImagenError),
+
+    #[cfg(feature = "imagen-edit")]
+    #[error(transparent)]
+    ImageEdit(#[from] ImageEditError),
+
+    #[cfg(feature = "transcription")]
+    #[error(transparent)]
+    OpenAi(#[from] OpenAiError),
+
+    #[cfg(feature = "transcription")]
+    #[error(transparent)]
+    Subtitle(#[from] SubtitleError),
+
+    #[cfg(feature = "transcription")]
+    #[error(transparent)]
+    Schema(#[from] SchemaError),
+
+    #[cfg(all(feature = "imagen", not(target_arch = "wasm32")))]
+    #[error(transparent)]
+    ImageConvert(#[from] ImageConvertError),
+
+    #[cfg(all(feature = "imagen", not(target_arch = "wasm32")))]
+    #[error(transparent)]
+    Gallery(#[from] GalleryError),
+
+    #[cfg(feature = "veo")]
+    #[error(transparent)]
+    Veo(#[from] VeoError),
+
+    #[cfg(feature = "tts")]
+    #[error(transparent)]
+    Tts(#[from] TtsError),
+
+    #[cfg(feature = "music")]
+    #[error(transparent)]
+    Music(#[from] MusicError),
+
+    #[cfg(feature = "embeddings")]
+    #[error(transparent)]
+    Embedding(#[from] EmbeddingError),
+
+    #[cfg(feature = "text")]
+    #[error(transparent)]
+    Text(#[from] TextError),
+
+    #[cfg(all(
+        feature = "transcription",
+        feature = "embeddings",
+        not(target_arch = "wasm32")
+    ))]
+    #[error(transparent)]
+    SearchIndex(#[from] SearchIndexError),
+
+    #[cfg(all(feature = "vision", not(target_arch = "wasm32")))]
+    #[error(transparent)]
+    VideoFrames(#[from] FrameError),
+}
+
+#[cfg(feature = "__client")]
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(feature = "__client")]
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            #[cfg(feature = "transcription")]
+            Error::Gemini(e) => e.kind(),
+            #[cfg(feature = "file-api")]
+            Error::FileApi(e) => e.kind(),
+            #[cfg(feature = "imagen")]
+            Error::Imagen(e) => e.kind(),
+            #[cfg(feature = "imagen-edit")]
+            Error::ImageEdit(e) => e.kind(),
+            #[cfg(feature = "transcription")]
+            Error::OpenAi(e) => e.kind(),
+            #[cfg(feature = "transcription")]
+            Error::Subtitle(e) => e.kind(),
+            #[cfg(feature = "transcription")]
+            Error::Schema(e) => e.kind(),
+            #[cfg(all(feature = "imagen", not(target_arch = "wasm32")))]
+            Error::ImageConvert(e) => e.kind(),
+            #[cfg(all(feature = "imagen", not(target_arch = "wasm32")))]
+            Error::Gallery(e) => e.kind(),
+            #[cfg(feature = "veo")]
+            Error::Veo(e) => e.kind(),
+            #[cfg(feature = "tts")]
+            Error::Tts(e) => e.kind(),
+            #[cfg(feature = "music")]
+            Error::Music(e) => e.kind(),
+            #[cfg(feature = "embeddings")]
+            Error::Embedding(e) => e.kind(),
+            #[cfg(feature = "text")]
+            Error::Text(e) => e.kind(),
+            #[cfg(all(
+                feature = "transcription",
+                feature = "embeddings",
+                not(target_arch = "wasm32")
+            ))]
+            Error::SearchIndex(e) => e.kind(),
+            #[cfg(all(feature = "vision", not(target_arch = "wasm32")))]
+            Error::VideoFrames(e) => e.kind(),
+        }
+    }
+
+    /// HTTP status returned by the API, if the failure came from a response
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            #[cfg(feature = "transcription")]
+            Error::Gemini(e) => e.status(),
+            #[cfg(feature = "file-api")]
+            Error::FileApi(e) => e.status(),
+            #[cfg(feature = "imagen")]
+            Error::Imagen(e) => e.status(),
+            #[cfg(feature = "imagen-edit")]
+            Error::ImageEdit(e) => e.status(),
+            #[cfg(feature = "transcription")]
+            Error::OpenAi(e) => e.status(),
+            #[cfg(feature = "transcription")]
+            Error::Subtitle(e) => e.status(),
+            #[cfg(feature = "transcription")]
+            Error::Schema(e) => e.status(),
+            #[cfg(all(feature = "imagen", not(target_arch = "wasm32")))]
+            Error::ImageConvert(_) => None,
+            #[cfg(all(feature = "imagen", not(target_arch = "wasm32")))]
+            Error::Gallery(_) => None,
+            #[cfg(feature = "veo")]
+            Error::Veo(e) => e.status(),
+            #[cfg(feature = "tts")]
+            Error::Tts(e) => e.status(),
+            #[cfg(feature = "music")]
+            Error::Music(e) => e.status(),
+            #[cfg(feature = "embeddings")]
+            Error::Embedding(e) => e.status(),
+            #[cfg(feature = "text")]
+            Error::Text(e) => e.status(),
+            #[cfg(all(
+                feature = "transcription",
+                feature = "embeddings",
+                not(target_arch = "wasm32")
+            ))]
+            Error::SearchIndex(e) => e.status(),
+            #[cfg(all(feature = "vision", not(target_arch = "wasm32")))]
+            Error::VideoFrames(_) => None,
+        }
+    }
+
+    /// Whether repeating the same request may succeed
+    pub fn is_retryable(&self) -> bool {
+        is_retryable(self.kind(), self.status())
+    }
+}
+
+#[cfg(all(feature = "transcription", not(target_arch = "wasm32")))]
+impl From<TranscriptionError> for Error {
+    fn from(err: TranscriptionError) -> Self {
+        match err {
+            TranscriptionError::Gemini(e) => Error::Gemini(e),
+            TranscriptionError::OpenAi(e) => Error::OpenAi(e),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "transcription"))]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "imagen-edit")]
+    #[test]
+    fn test_retryable_classification() {
+        let rate_limited: Error = GeminiError::RateLimited {
+            request_id: "abc".to_string(),
+        }
+        .into();
+        assert_eq!(rate_limited.kind(), ErrorKind::RateLimited);
+        assert_eq!(rate_limited.status(), Some(429));
+        assert!(rate_limited.is_retryable());
+
+        let server: Error = ImagenError::ApiError {
+            status: 503,
+            message: "unavailable".to_string(),
+            request_id: "abc".to_string(),
+        }
+        .into();
+        assert_eq!(server.kind(), ErrorKind::Api);
+        assert!(server.is_retryable());
+
+        let client: Error = OpenAiError::ApiError {
+            status: 400,
+            message: "bad request".to_string(),
+            request_id: "abc".to_string(),
+        }
+        .into();
+        assert_eq!(client.status(), Some(400));
+        assert!(!client.is_retryable());
+
+        let no_images: Error = ImageEditError::NoInputImages.into();
+        assert_eq!(no_images.kind(), ErrorKind::InvalidInput);
+        assert_eq!(no_images.status(), None);
+        assert!(!no_images.is_retryable());
+    }
+
+    #[test]
+    fn test_transcription_error_unwraps_provider() {
+        let err: Error = TranscriptionError::OpenAi(OpenAiError::MaxRetriesExceeded(3)).into();
+        assert!(matches!(err, Error::OpenAi(_)));
+        assert_eq!(err.kind(), ErrorKind::RetriesExhausted);
+    }
+}
@@ -0,0 +1,173 @@
+use anyhow::{Context, Result};
+use clap::Parser;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tracing::{Instrument, debug, info_span};
+
+use transcript_tool::cli::{LogFormat, init_logging};
+use transcript_tool::{Evaluation, Reference, TranscriptResponse};
+
+#[derive(Parser, Debug)]
+#[command(name = "evaluate")]
+#[command(version)]
+#[command(about = "Score a transcript against a reference: word error rate and speaker accuracy")]
+#[command(after_help = "REFERENCE FILES:
+    *.json   a reviewed transcript JSON, with speakers and timestamps
+    other    text, one segment per line; lines may start with [MM:SS], and
+             when every line has a `Name: ` label the labels are speakers
+
+EXAMPLES:
+    evaluate --reference ref.txt talk.json
+    evaluate -r ref.json talk.json --all
+    evaluate -r ref.txt talk.json --max-wer 15 --min-speaker-accuracy 90
+    evaluate -r ref.txt talk.json --json | jq '.wer'")]
+pub struct Args {
+    /// Transcript JSON written by `convert` or `batch_convert`
+    #[arg(value_name = "HYPOTHESIS")]
+    hypothesis: PathBuf,
+
+    /// What the audio actually says: transcript JSON or text
+    #[arg(short, long, value_name = "FILE")]
+    reference: PathBuf,
+
+    /// List every reference segment, not only those with errors
+    #[arg(long)]
+    all: bool,
+
+    /// Print the scores as JSON
+    #[arg(long)]
+    json: bool,
+
+    /// Fail when the word error rate is above this percentage
+    #[arg(long, value_name = "PERCENT")]
+    max_wer: Option<f64>,
+
+    /// Fail when fewer than this percentage of words go to the right speaker
+    #[arg(long, value_name = "PERCENT")]
+    min_speaker_accuracy: Option<f64>,
+
+    /// Verbosity level (-v, -vv, -vvv)
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Log output format: human-readable text, or one JSON object per line
+    #[arg(long, value_enum, default_value = "text", env = "FLASHECHO_LOG_FORMAT")]
+    log_format: LogFormat,
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+}
+
+async fn read_reference(path: &Path) -> Result<Reference> {
+    let content = fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Failed to read reference {:?}", path))?;
+    if is_json(path) {
+        let transcript = TranscriptResponse::from_json_any_version(&content)
+            .with_context(|| format!("Failed to parse reference JSON {:?}", path))?;
+        return Ok(Reference::from_transcript(&transcript));
+    }
+    Ok(Reference::parse_text(&content))
+}
+
+/// Threshold flags the evaluation misses, as error lines
+fn check_thresholds(
+    evaluation: &Evaluation,
+    max_wer: Option<f64>,
+    min_speaker_accuracy: Option<f64>,
+) -> Vec<String> {
+    let mut failures = Vec::new();
+    let wer = evaluation.wer * 100.0;
+    if let Some(max_wer) = max_wer
+        && wer > max_wer
+    {
+        failures.push(format!("WER {:.1}% is above --max-wer {}%", wer, max_wer));
+    }
+    if let (Some(min), Some(accuracy)) = (min_speaker_accuracy, evaluation.speaker_accuracy) {
+        let accuracy = accuracy * 100.0;
+        if accuracy < min {
+            failures.push(format!(
+                "Speaker accuracy {:.1}% is below --min-speaker-accuracy {}%",
+                accuracy, min
+            ));
+        }
+    }
+    failures
+}
+
+/// Run with parsed arguments; shared by this binary and its `flashecho` subcommand
+pub async fn run(args: Args) -> Result<()> {
+    init_logging(args.verbose, args.log_format);
+
+    let span = info_span!("file", file = %args.hypothesis.display());
+    evaluate(args).instrument(span).await
+}
+
+async fn evaluate(args: Args) -> Result<()> {
+    let reference = read_reference(&args.reference).await?;
+    if args.min_speaker_accuracy.is_some() && !reference.has_speakers() {
+        anyhow::bail!(
+            "--min-speaker-accuracy needs speakers in the reference: use a transcript JSON or `Name: text` lines"
+        );
+    }
+    let content = fs::read_to_string(&args.hypothesis)
+        .await
+        .with_context(|| format!("Failed to read transcript {:?}", args.hypothesis))?;
+    let hypothesis = TranscriptResponse::from_json_any_version(&content)
+        .with_context(|| format!("Failed to parse transcript JSON {:?}", args.hypothesis))?;
+
+    let evaluation = Evaluation::evaluate(&reference, &hypothesis);
+    debug!(
+        "{} reference words, {} hypothesis words",
+        evaluation.reference_words, evaluation.hypothesis_words
+    );
+    if args.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&evaluation).context("Failed to serialize scores")?
+        );
+    } else {
+        print!("{}", evaluation.to_text(args.all));
+    }
+
+    let failures = check_thresholds(&evaluation, args.max_wer, args.min_speaker_accuracy);
+    if !failures.is_empty() {
+        anyhow::bail!(failures.join("; "));
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    run(Args::parse()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_thresholds() {
+        let reference = Reference::parse_text("Ann: one two three four\nBo: five");
+        let hypothesis: TranscriptResponse = serde_json::from_value(serde_json::json!({
+            "summary": "",
+            "segments": [{
+                "speaker": "Speaker 1", "timestamp": "00:00", "content": "one two tree four five",
+                "language": "English", "language_code": "en", "emotion": "neutral"
+            }]
+        }))
+        .unwrap();
+        let evaluation = Evaluation::evaluate(&reference, &hypothesis);
+
+        assert!(check_thresholds(&evaluation, Some(20.0), Some(80.0)).is_empty());
+        assert_eq!(
+            check_thresholds(&evaluation, Some(10.0), Some(90.0)),
+            vec![
+                "WER 20.0% is above --max-wer 10%",
+                "Speaker accuracy 80.0% is below --min-speaker-accuracy 90%"
+            ]
+        );
+    }
+}
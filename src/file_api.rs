@@ -1,11 +1,14 @@
-use reqwest::Client;
+use bytes::Bytes;
+use reqwest::{Client, Method};
 use serde::{Deserialize, Serialize};
-use std::time::{Duration, Instant};
+use std::time::Duration;
 use thiserror::Error;
 use tracing::{debug, info, warn};
 
-const FILE_API_URL: &str = "https://generativelanguage.googleapis.com/upload/v1beta/files";
-const FILE_INFO_URL: &str = "https://generativelanguage.googleapis.com/v1beta/files";
+use crate::auth::{AuthMode, GEMINI_BASE_URL};
+use crate::error::ErrorKind;
+use crate::redact::ApiKey;
+
 const FILE_PROCESSING_TIMEOUT_SECS: u64 = 300; // 5 minutes
 const FILE_PROCESSING_POLL_INTERVAL_SECS: u64 = 2;
 
@@ -27,7 +30,7 @@ pub enum FileApiError {
     DeleteFailed(String),
 
     #[error("Network error: {0}")]
-    NetworkError(#[from] reqwest::Error),
+    NetworkError(#[source] reqwest::Error),
 
     #[error("JSON parsing error: {0}")]
     JsonError(#[from] serde_json::Error),
@@ -38,6 +41,36 @@ pub enum FileApiError {
 
 pub type Result<T> = std::result::Result<T, FileApiError>;
 
+impl From<reqwest::Error> for FileApiError {
+    fn from(err: reqwest::Error) -> Self {
+        FileApiError::NetworkError(crate::redact::reqwest_error(err))
+    }
+}
+
+impl FileApiError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            FileApiError::UploadInitFailed(_)
+            | FileApiError::MissingUploadUrl
+            | FileApiError::UploadFailed(_)
+            | FileApiError::FileProcessingTimeout(_)
+            | FileApiError::DeleteFailed(_) => ErrorKind::Upload,
+            FileApiError::NetworkError(_) => ErrorKind::Network,
+            FileApiError::JsonError(_) => ErrorKind::InvalidResponse,
+            FileApiError::ApiError { status: 429, .. } => ErrorKind::RateLimited,
+            FileApiError::ApiError { .. } => ErrorKind::Api,
+        }
+    }
+
+    /// HTTP status returned by the API, if any
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            FileApiError::ApiError { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FileInfo {
@@ -55,6 +88,15 @@ struct FileResponse {
     file: FileInfo,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListFilesResponse {
+    #[serde(default)]
+    files: Vec<FileInfo>,
+    #[serde(default)]
+    next_page_token: Option<String>,
+}
+
 #[derive(Debug, Serialize)]
 struct UploadMetadata {
     file: FileMetadata,
@@ -65,14 +107,34 @@ struct FileMetadata {
     display_name: String,
 }
 
+#[derive(Debug, Clone)]
 pub struct FileApiClient {
     client: Client,
-    api_key: String,
+    api_key: ApiKey,
+    base_url: String,
+    auth_mode: AuthMode,
 }
 
 impl FileApiClient {
     pub fn new(client: Client, api_key: String) -> Self {
-        Self { client, api_key }
+        Self {
+            client,
+            api_key: api_key.into(),
+            base_url: GEMINI_BASE_URL.to_string(),
+            auth_mode: AuthMode::default(),
+        }
+    }
+
+    /// Override the API host, e.g. to match `GeminiClient::base_url`
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Override how the API key is sent, e.g. to match `GeminiClient::auth_mode`
+    pub fn with_auth_mode(mut self, auth_mode: AuthMode) -> Self {
+        self.auth_mode = auth_mode;
+        self
     }
 
     /// Step 1: Initiate resumable upload
@@ -83,7 +145,7 @@ impl FileApiClient {
         mime_type: &str,
         display_name: &str,
     ) -> Result<String> {
-        let url = format!("{}?key={}", FILE_API_URL, self.api_key);
+        let url = format!("{}/upload/v1beta/files", self.base_url);
 
         let metadata = UploadMetadata {
             file: FileMetadata {
@@ -97,8 +159,8 @@ impl FileApiClient {
         );
 
         let response = self
-            .client
-            .post(&url)
+            .auth_mode
+            .request(&self.client, Method::POST, &url, &self.api_key)
             .header("X-Goog-Upload-Protocol", "resumable")
             .header("X-Goog-Upload-Command", "start")
             .header("X-Goog-Upload-Header-Content-Length", file_size.to_string())
@@ -110,7 +172,9 @@ impl FileApiClient {
 
         let status = response.status();
         if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
+            let error_text = self
+                .api_key
+                .scrub(response.text().await.unwrap_or_default());
             return Err(FileApiError::UploadInitFailed(format!(
                 "HTTP {}: {}",
                 status, error_text
@@ -124,14 +188,19 @@ impl FileApiClient {
             .map(|s| s.to_string())
             .ok_or(FileApiError::MissingUploadUrl)?;
 
-        debug!("Got upload URL: {}", upload_url);
+        debug!("Got upload URL: {}", crate::redact::url(&upload_url));
         Ok(upload_url)
     }
 
     /// Step 2: Upload file bytes to the upload URL
     /// Returns FileInfo with the file URI
-    pub async fn upload_bytes(&self, upload_url: &str, data: &[u8]) -> Result<FileInfo> {
+    ///
+    /// `data` is sent without copying; pass a `Bytes` (or an owned `Vec<u8>`)
+    /// so large files aren't duplicated in memory.
+    pub async fn upload_bytes(&self, upload_url: &str, data: impl Into<Bytes>) -> Result<FileInfo> {
+        let data = data.into();
         debug!("Uploading {} bytes to upload URL", data.len());
+        crate::metrics::record_upload_bytes("file_api", data.len());
 
         let response = self
             .client
@@ -139,13 +208,15 @@ impl FileApiClient {
             .header("Content-Length", data.len().to_string())
             .header("X-Goog-Upload-Offset", "0")
             .header("X-Goog-Upload-Command", "upload, finalize")
-            .body(data.to_vec())
+            .body(data)
             .send()
             .await?;
 
         let status = response.status();
         if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
+            let error_text = self
+                .api_key
+                .scrub(response.text().await.unwrap_or_default());
             return Err(FileApiError::UploadFailed(format!(
                 "HTTP {}: {}",
                 status, error_text
@@ -168,13 +239,19 @@ impl FileApiClient {
     /// Get file info by name
     pub async fn get_file_info(&self, file_name: &str) -> Result<FileInfo> {
         let file_id = Self::extract_file_id(file_name);
-        let url = format!("{}/{}?key={}", FILE_INFO_URL, file_id, self.api_key);
+        let url = format!("{}/v1beta/files/{}", self.base_url, file_id);
 
-        let response = self.client.get(&url).send().await?;
+        let response = self
+            .auth_mode
+            .request(&self.client, Method::GET, &url, &self.api_key)
+            .send()
+            .await?;
 
         let status = response.status();
         if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
+            let error_text = self
+                .api_key
+                .scrub(response.text().await.unwrap_or_default());
             return Err(FileApiError::ApiError {
                 status: status.as_u16(),
                 message: error_text,
@@ -185,10 +262,48 @@ impl FileApiClient {
         Ok(file_info)
     }
 
+    /// List every file uploaded with this API key, following pagination
+    pub async fn list_files(&self) -> Result<Vec<FileInfo>> {
+        let mut files = Vec::new();
+        let mut page_token: Option<String> = None;
+
+        loop {
+            let mut url = format!("{}/v1beta/files?pageSize=100", self.base_url);
+            if let Some(token) = &page_token {
+                url = format!("{}&pageToken={}", url, token);
+            }
+
+            let response = self
+                .auth_mode
+                .request(&self.client, Method::GET, &url, &self.api_key)
+                .send()
+                .await?;
+
+            let status = response.status();
+            if !status.is_success() {
+                let error_text = self
+                    .api_key
+                    .scrub(response.text().await.unwrap_or_default());
+                return Err(FileApiError::ApiError {
+                    status: status.as_u16(),
+                    message: error_text,
+                });
+            }
+
+            let page: ListFilesResponse = response.json().await?;
+            files.extend(page.files);
+            match page.next_page_token.filter(|token| !token.is_empty()) {
+                Some(token) => page_token = Some(token),
+                None => return Ok(files),
+            }
+        }
+    }
+
     /// Wait for file to become ACTIVE (processing complete)
     pub async fn wait_for_file_active(&self, file_name: &str) -> Result<FileInfo> {
-        let start = Instant::now();
-        let timeout = Duration::from_secs(FILE_PROCESSING_TIMEOUT_SECS);
+        // Count polls rather than reading a clock: std::time::Instant is unavailable on wasm32
+        let max_polls = FILE_PROCESSING_TIMEOUT_SECS / FILE_PROCESSING_POLL_INTERVAL_SECS;
+        let mut polls = 0;
 
         loop {
             let info = self.get_file_info(file_name).await?;
@@ -205,24 +320,26 @@ impl FileApiClient {
                 )));
             }
 
-            if start.elapsed() > timeout {
+            if polls >= max_polls {
                 return Err(FileApiError::FileProcessingTimeout(
                     FILE_PROCESSING_TIMEOUT_SECS,
                 ));
             }
 
             debug!("File {} is in state {}, waiting...", file_name, info.state);
-            tokio::time::sleep(Duration::from_secs(FILE_PROCESSING_POLL_INTERVAL_SECS)).await;
+            crate::rt::sleep(Duration::from_secs(FILE_PROCESSING_POLL_INTERVAL_SECS)).await;
+            polls += 1;
         }
     }
 
     /// Convenience method: upload file in one call
     pub async fn upload_file(
         &self,
-        data: &[u8],
+        data: impl Into<Bytes>,
         mime_type: &str,
         display_name: &str,
     ) -> Result<FileInfo> {
+        let data = data.into();
         let upload_url = self
             .start_upload(data.len() as u64, mime_type, display_name)
             .await?;
@@ -240,15 +357,21 @@ impl FileApiClient {
     /// Delete uploaded file after use
     pub async fn delete_file(&self, file_name: &str) -> Result<()> {
         let file_id = Self::extract_file_id(file_name);
-        let url = format!("{}/{}?key={}", FILE_INFO_URL, file_id, self.api_key);
+        let url = format!("{}/v1beta/files/{}", self.base_url, file_id);
 
         debug!("Deleting file: {}", file_name);
 
-        let response = self.client.delete(&url).send().await?;
+        let response = self
+            .auth_mode
+            .request(&self.client, Method::DELETE, &url, &self.api_key)
+            .send()
+            .await?;
 
         let status = response.status();
         if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
+            let error_text = self
+                .api_key
+                .scrub(response.text().await.unwrap_or_default());
             warn!("Failed to delete file {}: {}", file_name, error_text);
             return Err(FileApiError::DeleteFailed(format!(
                 "HTTP {}: {}",
@@ -283,6 +406,13 @@ mod tests {
         assert_eq!(file_info.display_name, Some("AUDIO".to_string()));
     }
 
+    #[test]
+    fn test_list_files_response_deserialization() {
+        let page: ListFilesResponse = serde_json::from_str(r#"{"nextPageToken": "next"}"#).unwrap();
+        assert!(page.files.is_empty());
+        assert_eq!(page.next_page_token.as_deref(), Some("next"));
+    }
+
     #[test]
     fn test_extract_file_id() {
         assert_eq!(FileApiClient::extract_file_id("files/abc123"), "abc123");
@@ -0,0 +1,1023 @@
+use anyhow::{Context, Result};
+use clap::{Args, Parser, Subcommand};
+use std::io::{IsTerminal, Read};
+use std::path::PathBuf;
+use walkdir::WalkDir;
+
+use transcript_tool::cli::{self, CommonArgs, LogFormat, init_logging};
+use transcript_tool::config::{Config, Profile};
+use transcript_tool::gallery::{DEFAULT_MAX_DISTANCE, GalleryImage, GalleryIndex, GalleryQuery};
+use transcript_tool::keyring;
+use transcript_tool::usage::{self, UsageLedger, UsageSummary};
+use transcript_tool::{
+    Clients, EmbeddingsClientConfig, FileInfo, Provider, SearchHit, SearchIndex,
+};
+
+// The standalone binaries double as subcommands; their own `main` and tests
+// are unused here
+#[allow(dead_code)]
+mod ask;
+#[allow(dead_code)]
+mod batch_convert;
+#[allow(dead_code)]
+mod compare;
+#[allow(dead_code)]
+mod convert;
+#[allow(dead_code)]
+mod describe;
+#[allow(dead_code)]
+mod emotions;
+#[allow(dead_code)]
+mod evaluate;
+#[allow(dead_code)]
+mod imagen;
+#[allow(dead_code)]
+mod imagen_edit;
+#[allow(dead_code)]
+mod live;
+#[allow(dead_code)]
+mod merge;
+#[allow(dead_code)]
+mod minutes;
+#[allow(dead_code)]
+mod music;
+#[allow(dead_code)]
+mod ocr;
+#[allow(dead_code)]
+mod rediarize;
+#[allow(dead_code)]
+mod scenes;
+#[cfg(feature = "server")]
+#[allow(dead_code)]
+mod serve;
+#[allow(dead_code)]
+mod speak;
+#[allow(dead_code)]
+mod subs;
+#[allow(dead_code)]
+mod thumbnail;
+#[allow(dead_code)]
+mod tokens;
+#[allow(dead_code)]
+mod translate;
+#[allow(dead_code)]
+mod veo;
+#[allow(dead_code)]
+mod verify;
+
+#[derive(Parser, Debug)]
+#[command(name = "flashecho")]
+#[command(version)]
+#[command(
+    about = "Transcribe audio and video, generate or edit images, generate videos, speech and music, with Gemini or OpenAI"
+)]
+#[command(after_help = "EXAMPLES:
+    flashecho transcribe -i talk.mp4 -f srt
+    flashecho batch ./videos -j 4
+    flashecho imagen \"A sunset over mountains\"
+    flashecho edit -i photo.jpg \"Make it a watercolor\"
+    flashecho veo \"A drone shot over a misty forest\"
+    flashecho speak -i talk.json --translation -o talk-en.mp3
+    flashecho music -g lo-fi --mood calm \"Rainy cafe at night\"
+    flashecho translate -i talk.srt --to French
+    flashecho subs shift -i talk.srt --by -2.5
+    flashecho rediarize -i talk.json -a talk.mp4 --hint \"Two speakers\"
+    flashecho live -o meeting.json
+    flashecho minutes meeting.json
+    flashecho ask meeting.json \"What did the CTO commit to?\"
+    flashecho emotions interview.json -f html
+    flashecho describe photo.jpg
+    flashecho ocr scan.pdf -f md
+    flashecho scenes lecture.mp4 --transcript lecture.json
+    flashecho thumbnail lecture.mp4 --title \"Intro to Rust\"
+    flashecho tokens --input talk.mp3 --prompt-file prompt.txt
+    flashecho serve --listen 127.0.0.1:8080
+    flashecho merge part1.json part2.json --offset 00:30:00
+    flashecho compare talk.flash.json talk.pro.json
+    flashecho eval --reference ref.txt talk.json --max-wer 15
+    flashecho verify ./transcripts ./images
+    flashecho search \"where did they discuss pricing?\" ./transcripts
+    flashecho gallery query ./output --model 3pro --since 2024-06
+    flashecho files list
+    flashecho auth login --provider gemini
+    flashecho usage --since 2024-01-01")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Extract audio from a video or audio file and transcribe it (same as `convert`)
+    Transcribe(convert::Args),
+    /// Transcribe every media file in folders or a list of files (same as `batch_convert`)
+    Batch(batch_convert::Args),
+    /// Generate images from a prompt or a YAML file (same as `imagen`)
+    Imagen(imagen::Args),
+    /// Edit and transform images (same as `imagen_edit`)
+    Edit(imagen_edit::Args),
+    /// Generate videos from a prompt or a YAML file (same as `veo`)
+    Veo(veo::Args),
+    /// Turn text, SSML or a transcript into speech (same as `speak`)
+    Speak(speak::Args),
+    /// Generate background music from a prompt (same as `music`)
+    Music(music::Args),
+    /// Translate SRT, VTT or transcript JSON files, keeping timing (same as `translate`)
+    Translate(translate::Args),
+    /// Shift or rescale SRT/VTT timing (same as `subs`)
+    Subs(subs::Args),
+    /// Re-assign or merge the speakers of a transcript (same as `rediarize`)
+    Rediarize(rediarize::Args),
+    /// Transcribe the microphone as you speak (same as `live`)
+    Live(live::Args),
+    /// Extract decisions, action items and open questions from a transcript (same as `minutes`)
+    Minutes(minutes::Args),
+    /// Answer questions about a transcript, citing timestamps (same as `ask`)
+    Ask(ask::Args),
+    /// Summarize emotions per speaker and over time (same as `emotions`)
+    Emotions(emotions::Args),
+    /// Caption images and list their objects, colors and text (same as `describe`)
+    Describe(describe::Args),
+    /// Extract the text of images and scanned PDFs (same as `ocr`)
+    Ocr(ocr::Args),
+    /// List the scenes of a video from its keyframes (same as `scenes`)
+    Scenes(scenes::Args),
+    /// Make platform-sized thumbnails from the best frame of a video (same as `thumbnail`)
+    Thumbnail(thumbnail::Args),
+    /// Count the input tokens of a prompt and media file (same as `tokens`)
+    Tokens(tokens::Args),
+    /// Serve transcription and image jobs over HTTP (same as `serve`; `server` feature)
+    #[cfg(feature = "server")]
+    Serve(serve::Args),
+    /// Merge the transcripts of a recording split across files (same as `merge`)
+    Merge(merge::Args),
+    /// Compare two transcripts of the same audio (same as `compare`)
+    Compare(compare::Args),
+    /// Score a transcript against a reference transcript (same as `evaluate`)
+    Eval(evaluate::Args),
+    /// Check outputs against their checksum sidecars, and images for truncation (same as `verify`)
+    Verify(verify::Args),
+    /// Search saved transcripts by meaning
+    Search(SearchArgs),
+    /// Index a folder of generated images, query it, and find or prune near-duplicates
+    Gallery(GalleryArgs),
+    /// Manage files uploaded to the Gemini File API
+    Files(FilesArgs),
+    /// Store API keys in the OS keyring instead of environment variables
+    Auth(AuthArgs),
+    /// Report estimated API spend from the usage ledger
+    Usage(UsageArgs),
+}
+
+#[derive(Args, Debug)]
+struct FilesArgs {
+    #[command(subcommand)]
+    command: FilesCommand,
+
+    /// Config file profile whose API key to use
+    #[arg(long, value_name = "NAME", env = "FLASHECHO_PROFILE", global = true)]
+    profile: Option<String>,
+
+    /// API timeout in seconds
+    #[arg(long, default_value = "60", global = true)]
+    timeout: u64,
+
+    /// Verbosity level (-v, -vv, -vvv)
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Log output format: human-readable text, or one JSON object per line
+    #[arg(
+        long,
+        value_enum,
+        default_value = "text",
+        env = "FLASHECHO_LOG_FORMAT",
+        global = true
+    )]
+    log_format: LogFormat,
+}
+
+#[derive(Subcommand, Debug)]
+enum FilesCommand {
+    /// List uploaded files
+    List {
+        /// Print the files as a JSON array
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show one file as JSON
+    Get {
+        /// File name, with or without the `files/` prefix
+        name: String,
+    },
+    /// Delete files, e.g. ones left behind by `--keep-remote-file`
+    Delete {
+        /// File names, with or without the `files/` prefix
+        #[arg(required = true)]
+        names: Vec<String>,
+    },
+}
+
+#[derive(Args, Debug)]
+struct GalleryArgs {
+    #[command(subcommand)]
+    command: GalleryCommand,
+
+    /// Verbosity level (-v, -vv, -vvv)
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Log output format: human-readable text, or one JSON object per line
+    #[arg(
+        long,
+        value_enum,
+        default_value = "text",
+        env = "FLASHECHO_LOG_FORMAT",
+        global = true
+    )]
+    log_format: LogFormat,
+}
+
+#[derive(Subcommand, Debug)]
+enum GalleryCommand {
+    /// Index the images of a folder with their metadata sidecars and perceptual hashes
+    Index {
+        /// Folder of generated images
+        dir: PathBuf,
+    },
+    /// List indexed images, newest first
+    Query {
+        /// Folder of generated images
+        dir: PathBuf,
+
+        /// Only images made by this model (name, alias such as 3pro, or part of a name)
+        #[arg(short, long)]
+        model: Option<String>,
+
+        /// Only images modified on or after this date (YYYY-MM-DD or YYYY-MM, UTC)
+        #[arg(long, value_name = "DATE", value_parser = parse_since)]
+        since: Option<u64>,
+
+        /// Only images modified before this date (YYYY-MM-DD or YYYY-MM, UTC)
+        #[arg(long, value_name = "DATE", value_parser = parse_since)]
+        until: Option<u64>,
+
+        /// Print the images as a JSON array
+        #[arg(long)]
+        json: bool,
+    },
+    /// List groups of near-duplicate images, the one to keep first
+    Dupes {
+        /// Folder of generated images
+        dir: PathBuf,
+
+        /// Hash bits two images may differ in (0-64; 0 only matches re-encodes)
+        #[arg(short, long, default_value_t = DEFAULT_MAX_DISTANCE)]
+        distance: u32,
+
+        /// Print the groups as a JSON array of path arrays
+        #[arg(long)]
+        json: bool,
+    },
+    /// Delete near-duplicates with their sidecars, keeping the largest image of each group
+    Prune {
+        /// Folder of generated images
+        dir: PathBuf,
+
+        /// Hash bits two images may differ in (0-64; 0 only matches re-encodes)
+        #[arg(short, long, default_value_t = DEFAULT_MAX_DISTANCE)]
+        distance: u32,
+
+        /// Show what would be deleted without deleting anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Args, Debug)]
+struct AuthArgs {
+    #[command(subcommand)]
+    command: AuthCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum AuthCommand {
+    /// Store an API key in the OS keyring (prompted for, or read from stdin)
+    Login {
+        #[arg(long, value_enum, default_value = "gemini")]
+        provider: Provider,
+    },
+    /// Remove a stored API key from the OS keyring
+    Logout {
+        #[arg(long, value_enum, default_value = "gemini")]
+        provider: Provider,
+    },
+    /// Show where each provider's API key comes from
+    Status {
+        /// Config file profile to check
+        #[arg(long, value_name = "NAME", env = "FLASHECHO_PROFILE")]
+        profile: Option<String>,
+    },
+}
+
+#[derive(Args, Debug)]
+struct SearchArgs {
+    /// What to look for, in your own words
+    #[arg(value_name = "QUERY")]
+    query: String,
+
+    /// Transcript JSON files or folders to index and search [default: .]
+    #[arg(value_name = "PATH")]
+    paths: Vec<PathBuf>,
+
+    /// Number of matching segments to show
+    #[arg(short = 'n', long, default_value = "10")]
+    limit: usize,
+
+    /// Index file [default: ~/.local/share/flashecho/search-index.json]
+    #[arg(long, value_name = "FILE", env = "FLASHECHO_SEARCH_INDEX")]
+    index: Option<PathBuf>,
+
+    /// Search the index as is, without looking for new or changed transcripts
+    #[arg(long)]
+    no_update: bool,
+
+    /// API timeout in seconds
+    #[arg(short, long, default_value = "60")]
+    timeout: u64,
+
+    /// Print matches as a JSON array
+    #[arg(long)]
+    json: bool,
+
+    #[command(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Args, Debug)]
+struct UsageArgs {
+    /// Only count calls on or after this date (YYYY-MM-DD or YYYY-MM, UTC)
+    #[arg(long, value_name = "DATE", value_parser = parse_since)]
+    since: Option<u64>,
+
+    /// Only count calls tagged with this `--project`
+    #[arg(long, value_name = "TAG")]
+    project: Option<String>,
+
+    /// Print the totals as a JSON array
+    #[arg(long)]
+    json: bool,
+}
+
+fn parse_since(date: &str) -> std::result::Result<u64, String> {
+    usage::parse_date(date).ok_or_else(|| format!("expected YYYY-MM-DD or YYYY-MM, got '{}'", date))
+}
+
+/// Read a secret from the terminal without echoing it, or from piped stdin
+fn read_secret(prompt: &str) -> Result<String> {
+    let stdin = std::io::stdin();
+    let mut secret = String::new();
+    if stdin.is_terminal() {
+        eprint!("{}", prompt);
+        let echo_off = set_echo(false);
+        let read = stdin.read_line(&mut secret);
+        if echo_off {
+            set_echo(true);
+            eprintln!();
+        }
+        read?;
+    } else {
+        stdin.lock().read_to_string(&mut secret)?;
+    }
+    Ok(secret.trim().to_string())
+}
+
+/// Toggle terminal echo with `stty`; `false` if that isn't possible
+fn set_echo(on: bool) -> bool {
+    std::process::Command::new("stty")
+        .arg(if on { "echo" } else { "-echo" })
+        .stdin(std::process::Stdio::inherit())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+fn key_source(provider: Provider, profile: &Profile) -> String {
+    let vars = provider.api_key_vars();
+    if let Some(var) = vars.iter().find(|var| std::env::var(var).is_ok()) {
+        return format!("environment ({})", var);
+    }
+    if profile.api_key(provider).is_some() {
+        return "config profile".to_string();
+    }
+    match keyring::get(provider.keyring_account()) {
+        Ok(Some(_)) => "OS keyring".to_string(),
+        Ok(None) => "not set".to_string(),
+        Err(e) => format!("not set ({})", e),
+    }
+}
+
+fn run_auth(args: AuthArgs) -> Result<()> {
+    match args.command {
+        AuthCommand::Login { provider } => {
+            let key = read_secret(&format!("{} API key: ", provider))?;
+            if key.is_empty() {
+                anyhow::bail!("No API key given");
+            }
+            keyring::set(provider.keyring_account(), &key)
+                .context("Failed to store the API key in the OS keyring")?;
+            println!("{} API key stored in the OS keyring.", provider);
+            if let Some(var) = provider
+                .api_key_vars()
+                .iter()
+                .find(|var| std::env::var(var).is_ok())
+            {
+                println!(
+                    "Note: {} is set and takes precedence over the keyring.",
+                    var
+                );
+            }
+        }
+        AuthCommand::Logout { provider } => {
+            keyring::delete(provider.keyring_account())
+                .context("Failed to remove the API key from the OS keyring")?;
+            println!("{} API key removed from the OS keyring.", provider);
+        }
+        AuthCommand::Status { profile } => {
+            let profile = Config::load_default()?.profile(profile.as_deref())?;
+            for provider in [Provider::Gemini, Provider::OpenAi] {
+                println!("{}: {}", provider, key_source(provider, &profile));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// `.json` files among `paths`, walking folders recursively
+fn transcript_files(paths: &[PathBuf]) -> Vec<PathBuf> {
+    let is_json = |path: &std::path::Path| {
+        path.extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+    };
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_file() {
+            files.push(path.clone());
+            continue;
+        }
+        files.extend(
+            WalkDir::new(path)
+                .into_iter()
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_type().is_file() && is_json(entry.path()))
+                .map(|entry| entry.into_path()),
+        );
+    }
+    files
+}
+
+async fn run_search(args: SearchArgs) -> Result<()> {
+    init_logging(args.common.verbose, args.common.log_format);
+    args.common.install_usage_ledger("search");
+
+    let paths = if args.paths.is_empty() {
+        vec![PathBuf::from(".")]
+    } else {
+        args.paths
+    };
+    let roots = paths
+        .iter()
+        .map(|path| {
+            std::fs::canonicalize(path).with_context(|| format!("Cannot search {:?}", path))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let profile = args.common.load_profile()?;
+    let vars = cli::GEMINI_API_KEY_VARS;
+    let api_key = cli::api_key(
+        vars,
+        profile.gemini_api_key.as_deref(),
+        cli::GEMINI_KEYRING_ACCOUNT,
+    )
+    .with_context(|| cli::missing_api_key(vars))?;
+    let budget = args
+        .common
+        .budget()
+        .context("Failed to read usage ledger for --budget-since")?;
+    let client = Clients::new(api_key)
+        .context("Failed to create HTTP client")?
+        .embeddings(EmbeddingsClientConfig {
+            timeout_secs: args.timeout,
+            max_retries: args.common.max_retries,
+            cache: args.common.response_cache(),
+            budget,
+            ..Default::default()
+        });
+
+    let index_path = args.index.unwrap_or_else(SearchIndex::default_path);
+    let mut index = SearchIndex::load(&index_path)?;
+    if !args.no_update {
+        let files = transcript_files(&paths);
+        let updated = index.update(&client, &files).await;
+        // Keep whatever was embedded before a failure
+        index
+            .save(&index_path)
+            .await
+            .with_context(|| format!("Failed to save search index {:?}", index_path))?;
+        let stats = updated.context("Failed to index transcripts")?;
+        if !args.common.quiet && !args.json {
+            eprintln!(
+                "Indexed {} transcripts ({} unchanged, {} removed, {} other JSON files skipped)",
+                stats.indexed, stats.unchanged, stats.removed, stats.skipped
+            );
+        }
+    }
+
+    let hits = index
+        .query(&client, &args.query, &roots, args.limit)
+        .await
+        .context("Search failed")?;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&hits)?);
+    } else if hits.is_empty() {
+        println!("No matching segments.");
+    } else {
+        hits.iter().for_each(print_hit);
+    }
+    Ok(())
+}
+
+fn print_hit(hit: &SearchHit) {
+    println!(
+        "{:.2}  {} [{}] {}",
+        hit.score,
+        hit.file.display(),
+        hit.timestamp,
+        hit.speaker
+    );
+    println!("      {}", hit.snippet);
+}
+
+fn image_size(image: &GalleryImage) -> String {
+    match (image.width, image.height) {
+        (Some(width), Some(height)) => format!("{}x{}", width, height),
+        _ => "?".to_string(),
+    }
+}
+
+async fn run_gallery(args: GalleryArgs) -> Result<()> {
+    init_logging(args.verbose, args.log_format);
+
+    let dir = match &args.command {
+        GalleryCommand::Index { dir }
+        | GalleryCommand::Query { dir, .. }
+        | GalleryCommand::Dupes { dir, .. }
+        | GalleryCommand::Prune { dir, .. } => dir.clone(),
+    };
+    if !dir.is_dir() {
+        anyhow::bail!("Not a folder: {:?}", dir);
+    }
+    let index_path = GalleryIndex::path_in(&dir);
+    let mut index = GalleryIndex::load(&index_path)?;
+    // Every command works on an up-to-date index
+    let stats = index.update(&dir).await.context("Failed to index images")?;
+    index
+        .save(&index_path)
+        .await
+        .with_context(|| format!("Failed to save gallery index {:?}", index_path))?;
+
+    match args.command {
+        GalleryCommand::Index { .. } => {
+            println!(
+                "Indexed {} images ({} new or changed, {} unchanged, {} removed)",
+                index.images().len(),
+                stats.indexed,
+                stats.unchanged,
+                stats.removed
+            );
+            if stats.unhashed > 0 {
+                println!(
+                    "{} images could not be decoded and are left out of duplicate detection.",
+                    stats.unhashed
+                );
+            }
+        }
+        GalleryCommand::Query {
+            model,
+            since,
+            until,
+            json,
+            ..
+        } => {
+            let query = GalleryQuery {
+                model,
+                since,
+                until,
+            };
+            let mut images: Vec<&GalleryImage> = index.query(&query).collect();
+            images.sort_by_key(|image| std::cmp::Reverse(image.modified));
+            if json {
+                println!("{}", serde_json::to_string_pretty(&images)?);
+            } else if images.is_empty() {
+                println!("No matching images.");
+            } else {
+                for image in images {
+                    println!(
+                        "{}  {:<28} {:>11}  {}",
+                        usage::format_date(image.modified),
+                        image.model.as_deref().unwrap_or("-"),
+                        image_size(image),
+                        image.path.display()
+                    );
+                }
+            }
+        }
+        GalleryCommand::Dupes { distance, json, .. } => {
+            let groups = index.duplicates(distance);
+            if json {
+                let paths: Vec<Vec<_>> = groups
+                    .iter()
+                    .map(|group| group.iter().map(|image| &image.path).collect())
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&paths)?);
+            } else if groups.is_empty() {
+                println!("No near-duplicates.");
+            } else {
+                for group in &groups {
+                    for (i, image) in group.iter().enumerate() {
+                        println!(
+                            "{} {} ({})",
+                            if i == 0 { "keep" } else { "    " },
+                            image.path.display(),
+                            image_size(image)
+                        );
+                    }
+                }
+            }
+        }
+        GalleryCommand::Prune {
+            distance, dry_run, ..
+        } => {
+            let doomed: Vec<PathBuf> = index
+                .duplicates(distance)
+                .iter()
+                .flat_map(|group| group[1..].iter().map(|image| image.path.clone()))
+                .collect();
+            for path in &doomed {
+                if dry_run {
+                    println!("Would delete {}", path.display());
+                    continue;
+                }
+                index
+                    .delete(&dir, path)
+                    .with_context(|| format!("Failed to delete {:?}", path))?;
+                println!("Deleted {}", path.display());
+            }
+            if !dry_run {
+                index
+                    .save(&index_path)
+                    .await
+                    .with_context(|| format!("Failed to save gallery index {:?}", index_path))?;
+            }
+            println!(
+                "{} near-duplicates {}.",
+                doomed.len(),
+                if dry_run { "found" } else { "deleted" }
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn run_usage(args: UsageArgs) -> Result<()> {
+    let path = UsageLedger::default_path();
+    let mut records = UsageLedger::read(&path)
+        .with_context(|| format!("Failed to read usage ledger {:?}", path))?;
+    if let Some(project) = &args.project {
+        records.retain(|r| r.project.as_ref() == Some(project));
+    }
+    let summary = usage::summarize(&records, args.since.unwrap_or_default());
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+        return Ok(());
+    }
+    if summary.is_empty() {
+        println!("No API calls recorded in {:?}.", path);
+        return Ok(());
+    }
+
+    println!(
+        "{:<14} {:<30} {:<12} {:>6} {:>12} {:>12} {:>7} {:>10}",
+        "BINARY", "MODEL", "PROJECT", "CALLS", "INPUT TOK", "OUTPUT TOK", "IMAGES", "COST USD"
+    );
+    for row in &summary {
+        println!(
+            "{:<14} {:<30} {:<12} {:>6} {:>12} {:>12} {:>7} {:>10}",
+            row.binary,
+            row.model,
+            row.project.as_deref().unwrap_or("-"),
+            row.calls,
+            row.input_tokens,
+            row.output_tokens,
+            row.images,
+            format_cost(row)
+        );
+    }
+    let total: f64 = summary.iter().map(|row| row.cost_usd).sum();
+    let unpriced: u64 = summary.iter().map(|row| row.unpriced_calls).sum();
+    println!("Total estimated cost: ${:.4}", total);
+    if unpriced > 0 {
+        println!(
+            "{} calls used models without a known price and are not included.",
+            unpriced
+        );
+    }
+
+    Ok(())
+}
+
+fn format_cost(row: &UsageSummary) -> String {
+    let cost = format!("{:.4}", row.cost_usd);
+    if row.unpriced_calls > 0 {
+        format!("{}*", cost)
+    } else {
+        cost
+    }
+}
+
+fn print_file(file: &FileInfo) {
+    println!(
+        "{}\t{}\t{}\t{} bytes\t{}",
+        file.name,
+        file.display_name.as_deref().unwrap_or("-"),
+        file.mime_type,
+        file.size_bytes,
+        file.state
+    );
+}
+
+async fn run_files(args: FilesArgs) -> Result<()> {
+    init_logging(args.verbose, args.log_format);
+
+    let profile = Config::load_default()?.profile(args.profile.as_deref())?;
+    let provider = Provider::Gemini;
+    let vars = provider.api_key_vars();
+    let api_key = cli::api_key(vars, profile.api_key(provider), provider.keyring_account())
+        .with_context(|| cli::missing_api_key(vars))?;
+    let file_api = Clients::builder(api_key)
+        .timeout_secs(args.timeout)
+        .build()
+        .context("Failed to create HTTP client")?
+        .file_api();
+
+    match args.command {
+        FilesCommand::List { json } => {
+            let files = file_api
+                .list_files()
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to list files: {}", e))?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&files)?);
+            } else if files.is_empty() {
+                println!("No uploaded files.");
+            } else {
+                files.iter().for_each(print_file);
+            }
+        }
+        FilesCommand::Get { name } => {
+            let file = file_api
+                .get_file_info(&name)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to get {}: {}", name, e))?;
+            println!("{}", serde_json::to_string_pretty(&file)?);
+        }
+        FilesCommand::Delete { names } => {
+            let mut failed = 0;
+            for name in &names {
+                match file_api.delete_file(name).await {
+                    Ok(()) => println!("Deleted {}", name),
+                    Err(e) => {
+                        eprintln!("Failed to delete {}: {}", name, e);
+                        failed += 1;
+                    }
+                }
+            }
+            if failed > 0 {
+                anyhow::bail!("{} of {} files could not be deleted", failed, names.len());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    match Cli::parse().command {
+        Command::Transcribe(args) => convert::run(args).await,
+        Command::Batch(args) => batch_convert::run(args).await,
+        Command::Imagen(args) => imagen::run(args).await,
+        Command::Edit(args) => imagen_edit::run(args).await,
+        Command::Veo(args) => veo::run(args).await,
+        Command::Speak(args) => speak::run(args).await,
+        Command::Music(args) => music::run(args).await,
+        Command::Translate(args) => translate::run(args).await,
+        Command::Subs(args) => subs::run(args).await,
+        Command::Rediarize(args) => rediarize::run(args).await,
+        Command::Live(args) => live::run(args).await,
+        Command::Minutes(args) => minutes::run(args).await,
+        Command::Ask(args) => ask::run(args).await,
+        Command::Emotions(args) => emotions::run(args).await,
+        Command::Describe(args) => describe::run(args).await,
+        Command::Ocr(args) => ocr::run(args).await,
+        Command::Scenes(args) => scenes::run(args).await,
+        Command::Thumbnail(args) => thumbnail::run(args).await,
+        Command::Tokens(args) => tokens::run(args).await,
+        #[cfg(feature = "server")]
+        Command::Serve(args) => serve::run(args).await,
+        Command::Merge(args) => merge::run(args).await,
+        Command::Compare(args) => compare::run(args).await,
+        Command::Eval(args) => evaluate::run(args).await,
+        Command::Verify(args) => verify::run(args).await,
+        Command::Search(args) => run_search(args).await,
+        Command::Gallery(args) => run_gallery(args).await,
+        Command::Files(args) => run_files(args).await,
+        Command::Auth(args) => run_auth(args),
+        Command::Usage(args) => run_usage(args),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_is_valid() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_parse_subcommands() {
+        let cli = Cli::parse_from(["flashecho", "transcribe", "-i", "talk.mp4", "-q"]);
+        assert!(matches!(cli.command, Command::Transcribe(_)));
+
+        let cli = Cli::parse_from(["flashecho", "veo", "A forest", "-m", "3-fast"]);
+        assert!(matches!(cli.command, Command::Veo(_)));
+
+        let cli = Cli::parse_from(["flashecho", "speak", "--text", "Hello", "--voice", "Puck"]);
+        assert!(matches!(cli.command, Command::Speak(_)));
+
+        let cli = Cli::parse_from(["flashecho", "music", "Rainy cafe", "--bpm", "80"]);
+        assert!(matches!(cli.command, Command::Music(_)));
+
+        let cli = Cli::parse_from(["flashecho", "translate", "-i", "a.srt", "--to", "fr"]);
+        assert!(matches!(cli.command, Command::Translate(_)));
+
+        let cli = Cli::parse_from(["flashecho", "subs", "shift", "-i", "a.srt", "--by", "-1"]);
+        assert!(matches!(cli.command, Command::Subs(_)));
+
+        let cli = Cli::parse_from(["flashecho", "rediarize", "-i", "a.json", "--merge", "B=A"]);
+        assert!(matches!(cli.command, Command::Rediarize(_)));
+
+        let cli = Cli::parse_from(["flashecho", "live", "--window", "20", "-o", "m.json"]);
+        assert!(matches!(cli.command, Command::Live(_)));
+
+        let cli = Cli::parse_from(["flashecho", "minutes", "m.json", "--json", "-o", "-"]);
+        assert!(matches!(cli.command, Command::Minutes(_)));
+
+        let cli = Cli::parse_from(["flashecho", "ask", "m.json", "Who?", "When?"]);
+        assert!(matches!(cli.command, Command::Ask(_)));
+
+        let cli = Cli::parse_from(["flashecho", "emotions", "a.json", "-f", "csv"]);
+        assert!(matches!(cli.command, Command::Emotions(_)));
+
+        let cli = Cli::parse_from(["flashecho", "describe", "a.png", "b.jpg", "--json"]);
+        assert!(matches!(cli.command, Command::Describe(_)));
+
+        let cli = Cli::parse_from(["flashecho", "ocr", "scan.pdf", "-f", "json", "--regions"]);
+        assert!(matches!(cli.command, Command::Ocr(_)));
+
+        let cli = Cli::parse_from(["flashecho", "scenes", "talk.mp4", "--max-frames", "10"]);
+        assert!(matches!(cli.command, Command::Scenes(_)));
+
+        let cli = Cli::parse_from(["flashecho", "thumbnail", "a.mp4", "--size", "shorts"]);
+        assert!(matches!(cli.command, Command::Thumbnail(_)));
+
+        let cli = Cli::parse_from([
+            "flashecho",
+            "tokens",
+            "-i",
+            "a.mp3",
+            "--prompt-file",
+            "p.txt",
+        ]);
+        assert!(matches!(cli.command, Command::Tokens(_)));
+        assert!(Cli::try_parse_from(["flashecho", "tokens", "--json"]).is_err());
+
+        #[cfg(feature = "server")]
+        {
+            let cli =
+                Cli::parse_from(["flashecho", "serve", "--listen", "0.0.0.0:9000", "-j", "4"]);
+            assert!(matches!(cli.command, Command::Serve(_)));
+        }
+
+        let cli = Cli::parse_from([
+            "flashecho",
+            "merge",
+            "a.json",
+            "b.json",
+            "--offset",
+            "30:00",
+        ]);
+        assert!(matches!(cli.command, Command::Merge(_)));
+        assert!(Cli::try_parse_from(["flashecho", "merge", "a.json"]).is_err());
+
+        let cli = Cli::parse_from(["flashecho", "compare", "a.json", "b.json", "--all"]);
+        assert!(matches!(cli.command, Command::Compare(_)));
+
+        let cli = Cli::parse_from(["flashecho", "eval", "--reference", "ref.txt", "a.json"]);
+        assert!(matches!(cli.command, Command::Eval(_)));
+
+        let cli = Cli::parse_from(["flashecho", "verify", "out", "-q"]);
+        assert!(matches!(cli.command, Command::Verify(_)));
+
+        let cli = Cli::parse_from(["flashecho", "search", "pricing", "a", "b", "-n", "3"]);
+        match cli.command {
+            Command::Search(args) => {
+                assert_eq!(args.query, "pricing");
+                assert_eq!(args.paths, [PathBuf::from("a"), PathBuf::from("b")]);
+                assert_eq!(args.limit, 3);
+            }
+            other => panic!("expected search, got {:?}", other),
+        }
+
+        let cli = Cli::parse_from([
+            "flashecho",
+            "gallery",
+            "query",
+            "out",
+            "--model",
+            "3pro",
+            "--since",
+            "2024-06",
+        ]);
+        match cli.command {
+            Command::Gallery(GalleryArgs {
+                command: GalleryCommand::Query { model, since, .. },
+                ..
+            }) => {
+                assert_eq!(model.as_deref(), Some("3pro"));
+                assert_eq!(since, Some(1_717_200_000));
+            }
+            other => panic!("expected gallery query, got {:?}", other),
+        }
+        let cli = Cli::parse_from(["flashecho", "gallery", "prune", "out", "--dry-run"]);
+        assert!(matches!(
+            cli.command,
+            Command::Gallery(GalleryArgs {
+                command: GalleryCommand::Prune {
+                    distance: DEFAULT_MAX_DISTANCE,
+                    dry_run: true,
+                    ..
+                },
+                ..
+            })
+        ));
+
+        let cli = Cli::parse_from(["flashecho", "files", "delete", "files/a", "b", "-v"]);
+        match cli.command {
+            Command::Files(args) => {
+                assert_eq!(args.verbose, 1);
+                assert!(matches!(
+                    args.command,
+                    FilesCommand::Delete { names } if names == ["files/a", "b"]
+                ));
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+
+        let cli = Cli::parse_from(["flashecho", "auth", "login", "--provider", "openai"]);
+        assert!(matches!(
+            cli.command,
+            Command::Auth(AuthArgs {
+                command: AuthCommand::Login {
+                    provider: Provider::OpenAi
+                }
+            })
+        ));
+
+        let cli = Cli::parse_from(["flashecho", "usage", "--since", "2024-01-01"]);
+        assert!(matches!(
+            cli.command,
+            Command::Usage(UsageArgs {
+                since: Some(1_704_067_200),
+                ..
+            })
+        ));
+        assert!(Cli::try_parse_from(["flashecho", "usage", "--since", "last week"]).is_err());
+    }
+}
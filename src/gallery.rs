@@ -0,0 +1,430 @@
+//! Local index of a folder of generated images, for output folders that have
+//! grown past browsing: each image's size, dimensions and modification time,
+//! the model from its `<image>.json` metadata sidecar, and a perceptual hash
+//! for finding near-duplicates. The index is kept in the folder as
+//! [`GALLERY_INDEX_FILE`], and [`GalleryIndex::update`] only rehashes images
+//! whose size or modification time changed.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use thiserror::Error;
+use tracing::{debug, info, warn};
+use walkdir::WalkDir;
+
+use crate::error::ErrorKind;
+use crate::image_convert::{ImageConvertError, hash_distance, image_dimensions, perceptual_hash};
+use crate::imagen_api::ImageModel;
+use crate::integrity::{checksum_key, is_image_path};
+use crate::storage::write_atomic;
+
+/// Name of the index file inside an indexed folder
+pub const GALLERY_INDEX_FILE: &str = ".flashecho-gallery.json";
+
+/// Hash bits two images may differ in and still count as near-duplicates
+pub const DEFAULT_MAX_DISTANCE: u32 = 6;
+
+#[derive(Debug, Error)]
+pub enum GalleryError {
+    #[error("Failed to access {path:?}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Gallery index {path:?} is corrupt: {source}")]
+    Corrupt {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    #[error(transparent)]
+    Hash(#[from] ImageConvertError),
+}
+
+pub type Result<T> = std::result::Result<T, GalleryError>;
+
+impl GalleryError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            GalleryError::Io { .. } => ErrorKind::Io,
+            GalleryError::Corrupt { .. } => ErrorKind::InvalidInput,
+            GalleryError::Hash(e) => e.kind(),
+        }
+    }
+}
+
+fn io_error(path: &Path) -> impl FnOnce(std::io::Error) -> GalleryError + '_ {
+    move |source| GalleryError::Io {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+/// Remove `path`, treating a missing file as removed
+fn remove_file(path: &Path) -> Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(io_error(path)(e)),
+        _ => Ok(()),
+    }
+}
+
+/// `<image>.json`, written by `imagen` and `imagen_edit` with `--metadata`
+fn metadata_path(image: &Path) -> PathBuf {
+    let mut path = image.as_os_str().to_owned();
+    path.push(".json");
+    PathBuf::from(path)
+}
+
+/// Model recorded in the metadata sidecar of `image`, if it has one
+fn sidecar_model(image: &Path) -> Option<String> {
+    let bytes = std::fs::read(metadata_path(image)).ok()?;
+    let sidecar: serde_json::Value = serde_json::from_slice(&bytes).ok()?;
+    sidecar["model"].as_str().map(str::to_string)
+}
+
+/// One indexed image
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GalleryImage {
+    /// Path relative to the indexed folder
+    pub path: PathBuf,
+    pub bytes: u64,
+    /// Modification time, unix seconds
+    pub modified: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    /// Model named in the metadata sidecar
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Perceptual hash; `None` when ffmpeg couldn't decode the image
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phash: Option<u64>,
+}
+
+impl GalleryImage {
+    /// Pixel count, 0 when the dimensions are unknown
+    pub fn pixels(&self) -> u64 {
+        u64::from(self.width.unwrap_or_default()) * u64::from(self.height.unwrap_or_default())
+    }
+}
+
+/// What [`GalleryIndex::update`] did
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UpdateStats {
+    pub indexed: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+    /// Images ffmpeg couldn't decode, left out of duplicate detection
+    pub unhashed: usize,
+}
+
+/// Filter for [`GalleryIndex::query`]; unset fields match every image
+#[derive(Debug, Clone, Default)]
+pub struct GalleryQuery {
+    /// A model name or alias such as `3pro`, or part of a model name
+    pub model: Option<String>,
+    /// Modified at or after this unix time
+    pub since: Option<u64>,
+    /// Modified before this unix time
+    pub until: Option<u64>,
+}
+
+impl GalleryQuery {
+    pub fn matches(&self, image: &GalleryImage) -> bool {
+        let model_matches = match (&self.model, &image.model) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(query), Some(model)) => match query.parse::<ImageModel>() {
+                Ok(known) => model == known.api_model_name(),
+                Err(_) => model.to_lowercase().contains(&query.to_lowercase()),
+            },
+        };
+        model_matches
+            && self.since.is_none_or(|since| image.modified >= since)
+            && self.until.is_none_or(|until| image.modified < until)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GalleryIndex {
+    /// Sorted by path
+    images: Vec<GalleryImage>,
+}
+
+impl GalleryIndex {
+    /// Where the index of `dir` is kept
+    pub fn path_in(dir: &Path) -> PathBuf {
+        dir.join(GALLERY_INDEX_FILE)
+    }
+
+    /// Read `path`; a missing file is an empty index
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = match std::fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(io_error(path)(e)),
+        };
+        serde_json::from_slice(&bytes).map_err(|source| GalleryError::Corrupt {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// Write via a temp file so an interrupted save keeps the old index
+    pub async fn save(&self, path: &Path) -> Result<()> {
+        let bytes = serde_json::to_vec(self).map_err(|source| GalleryError::Corrupt {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        write_atomic(path, &bytes).await.map_err(io_error(path))
+    }
+
+    pub fn images(&self) -> &[GalleryImage] {
+        &self.images
+    }
+
+    /// Index the images under `dir`: new and changed ones are read and
+    /// hashed, those that are gone are dropped, and the model of every image
+    /// is re-read from its sidecar. On error the index is left as it was.
+    pub async fn update(&mut self, dir: &Path) -> Result<UpdateStats> {
+        let mut stats = UpdateStats::default();
+        let mut previous: HashMap<PathBuf, GalleryImage> = self
+            .images
+            .iter()
+            .map(|image| (image.path.clone(), image.clone()))
+            .collect();
+        let mut images = Vec::new();
+
+        for entry in WalkDir::new(dir)
+            .follow_links(true)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(|e| e.ok())
+        {
+            let file = entry.path();
+            if !entry.file_type().is_file() || !is_image_path(file) {
+                continue;
+            }
+            let path = file.strip_prefix(dir).unwrap_or(file).to_path_buf();
+            let metadata = std::fs::metadata(file).map_err(io_error(file))?;
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |time| time.as_secs());
+            let model = sidecar_model(file);
+
+            if let Some(known) = previous.remove(&path)
+                && known.bytes == metadata.len()
+                && known.modified == modified
+            {
+                images.push(GalleryImage { model, ..known });
+                stats.unchanged += 1;
+                continue;
+            }
+
+            let data = std::fs::read(file).map_err(io_error(file))?;
+            let (width, height) = image_dimensions(&data).unzip();
+            let phash = match perceptual_hash(&data).await {
+                Ok(hash) => Some(hash),
+                Err(e @ ImageConvertError::FfmpegNotFound(_)) => return Err(e.into()),
+                Err(e) => {
+                    warn!("Cannot hash {:?}: {}", file, e);
+                    stats.unhashed += 1;
+                    None
+                }
+            };
+            debug!("Indexed {:?}", path);
+            images.push(GalleryImage {
+                path,
+                bytes: data.len() as u64,
+                modified,
+                width,
+                height,
+                model,
+                phash,
+            });
+            stats.indexed += 1;
+        }
+
+        stats.removed = previous.len();
+        info!(
+            "Gallery of {:?}: {} indexed, {} unchanged, {} removed",
+            dir, stats.indexed, stats.unchanged, stats.removed
+        );
+        self.images = images;
+        Ok(stats)
+    }
+
+    /// Images `query` matches, in path order
+    pub fn query<'a>(&'a self, query: &'a GalleryQuery) -> impl Iterator<Item = &'a GalleryImage> {
+        self.images.iter().filter(|image| query.matches(image))
+    }
+
+    /// Groups of images whose hashes are at most `max_distance` bits apart
+    /// from the group's first image, which is the one to keep: the largest
+    /// in pixels, then in bytes, then the oldest
+    pub fn duplicates(&self, max_distance: u32) -> Vec<Vec<&GalleryImage>> {
+        let mut candidates: Vec<(&GalleryImage, u64)> = self
+            .images
+            .iter()
+            .filter_map(|image| Some((image, image.phash?)))
+            .collect();
+        candidates.sort_by(|(a, _), (b, _)| {
+            b.pixels()
+                .cmp(&a.pixels())
+                .then(b.bytes.cmp(&a.bytes))
+                .then(a.modified.cmp(&b.modified))
+                .then(a.path.cmp(&b.path))
+        });
+
+        let mut grouped = vec![false; candidates.len()];
+        let mut groups = Vec::new();
+        for i in 0..candidates.len() {
+            if grouped[i] {
+                continue;
+            }
+            let (keep, hash) = candidates[i];
+            let mut group = vec![keep];
+            for j in i + 1..candidates.len() {
+                if !grouped[j] && hash_distance(hash, candidates[j].1) <= max_distance {
+                    grouped[j] = true;
+                    group.push(candidates[j].0);
+                }
+            }
+            if group.len() > 1 {
+                groups.push(group);
+            }
+        }
+        groups
+    }
+
+    /// Delete the image at `path` (relative to `dir`) with its metadata and
+    /// checksum sidecars, and drop it from the index
+    pub fn delete(&mut self, dir: &Path, path: &Path) -> Result<()> {
+        let file = dir.join(path);
+        remove_file(&file)?;
+        remove_file(&metadata_path(&file))?;
+        remove_file(Path::new(&checksum_key(&file.to_string_lossy())))?;
+        self.images.retain(|image| image.path != path);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(path: &str, size: u32, modified: u64, model: &str, phash: u64) -> GalleryImage {
+        GalleryImage {
+            path: PathBuf::from(path),
+            bytes: u64::from(size) * 100,
+            modified,
+            width: Some(size),
+            height: Some(size),
+            model: (!model.is_empty()).then(|| model.to_string()),
+            phash: Some(phash),
+        }
+    }
+
+    fn index() -> GalleryIndex {
+        GalleryIndex {
+            images: vec![
+                image(
+                    "a.png",
+                    1024,
+                    1_717_200_000,
+                    "gemini-3-pro-image-preview",
+                    0xFF00,
+                ),
+                image(
+                    "b.jpg",
+                    2048,
+                    1_717_300_000,
+                    "gemini-3-pro-image-preview",
+                    0xFF01,
+                ),
+                image(
+                    "c.png",
+                    1024,
+                    1_704_067_200,
+                    "gemini-2.5-flash-image",
+                    0x00FF,
+                ),
+                image("d.webp", 512, 1_717_200_000, "", 0xFF03),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_query() {
+        let index = index();
+        let paths = |query: GalleryQuery| -> Vec<String> {
+            index
+                .query(&query)
+                .map(|image| image.path.display().to_string())
+                .collect()
+        };
+        let query = GalleryQuery {
+            model: Some("3pro".to_string()),
+            since: Some(1_717_200_000),
+            ..Default::default()
+        };
+        assert_eq!(paths(query), ["a.png", "b.jpg"]);
+        let query = GalleryQuery {
+            model: Some("FLASH-IMAGE".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(paths(query), ["c.png"]);
+        let query = GalleryQuery {
+            until: Some(1_717_200_000),
+            ..Default::default()
+        };
+        assert_eq!(paths(query), ["c.png"]);
+        assert_eq!(paths(GalleryQuery::default()).len(), 4);
+    }
+
+    #[test]
+    fn test_duplicates_keep_largest() {
+        let index = index();
+        let groups: Vec<Vec<_>> = index
+            .duplicates(DEFAULT_MAX_DISTANCE)
+            .iter()
+            .map(|group| {
+                group
+                    .iter()
+                    .map(|image| image.path.display().to_string())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(groups, [["b.jpg", "a.png", "d.webp"]]);
+        assert_eq!(index.duplicates(1).len(), 1);
+        assert!(index.duplicates(0).is_empty());
+    }
+
+    #[test]
+    fn test_delete_removes_sidecars() {
+        let dir = std::env::temp_dir().join(format!("flashecho_gallery_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["a.png", "a.png.json", "a.png.blake3", "b.jpg"] {
+            std::fs::write(dir.join(name), b"x").unwrap();
+        }
+        let mut index = index();
+        index.delete(&dir, Path::new("a.png")).unwrap();
+        // Already gone is fine
+        index.delete(&dir, Path::new("c.png")).unwrap();
+
+        assert!(!dir.join("a.png").exists());
+        assert!(!dir.join("a.png.json").exists());
+        assert!(!dir.join("a.png.blake3").exists());
+        assert!(dir.join("b.jpg").exists());
+        assert_eq!(index.images().len(), 2);
+        assert_eq!(sidecar_model(&dir.join("b.jpg")), None);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
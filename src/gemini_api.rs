@@ -1,17 +1,37 @@
-use base64::Engine;
-use reqwest::Client;
+use reqwest::{Client, Method};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
+use std::collections::BTreeMap;
 use std::path::Path;
 use std::time::Duration;
 use thiserror::Error;
-use tracing::{debug, info, warn};
+use tracing::{Instrument, debug, field, info, info_span, warn};
+
+pub use crate::auth::{AuthMode, GEMINI_BASE_URL};
+use crate::budget::{Budget, BudgetExceeded};
+use crate::cache::ResponseCache;
+use crate::circuit_breaker::{CircuitBreaker, CircuitOpen};
+use crate::error::ErrorKind;
+use crate::json_repair::repair_json;
+use crate::payload::{GenerateContent, JsonBody, Part};
+use crate::proxy::ProxyConfig;
+use crate::redact::ApiKey;
+use crate::rt::Stopwatch;
+use crate::timestamp::Timestamp;
+use crate::tls::TlsConfig;
+use crate::token_count::TokenCount;
+use crate::transcript_schema::current_schema_version;
+use crate::transport::TransportConfig;
+use crate::usage::{TokenUsage, WithUsage};
 
-const GEMINI_API_URL: &str = "https://generativelanguage.googleapis.com/v1beta/models";
 pub const MAX_INLINE_FILE_SIZE: u64 = 20 * 1024 * 1024; // 20MB limit for inline data
 const DEFAULT_TIMEOUT_SECS: u64 = 600; // 10 minutes (large files need more time)
 const DEFAULT_MAX_RETRIES: u32 = 3;
 
+/// Language of `TranscriptSegment::translation`: the prompt asks for English
+pub const TRANSLATION_LANGUAGE_CODE: &str = "en";
+
 #[derive(Debug, Error)]
 pub enum GeminiError {
     #[allow(dead_code)]
@@ -21,32 +41,154 @@ pub enum GeminiError {
     #[error("File too large: {size} bytes (max: {max} bytes). Consider using shorter audio.")]
     FileTooLarge { size: u64, max: u64 },
 
-    #[error("Gemini API error ({status}): {message}")]
-    ApiError { status: u16, message: String },
+    #[error("Gemini API error ({status}, request {request_id}): {message}")]
+    ApiError {
+        status: u16,
+        message: String,
+        request_id: String,
+    },
 
-    #[error("Rate limited by API. Retry after some time.")]
-    RateLimited,
+    #[error("Rate limited by API (request {request_id}). Retry after some time.")]
+    RateLimited { request_id: String },
 
     #[error("Invalid response from Gemini API: {0}")]
     InvalidResponse(String),
 
     #[error("Network error: {0}")]
-    NetworkError(#[from] reqwest::Error),
+    NetworkError(#[source] reqwest::Error),
 
     #[error("JSON parsing error: {0}")]
     JsonError(#[from] serde_json::Error),
 
     #[error("Max retries ({0}) exceeded")]
     MaxRetriesExceeded(u32),
+
+    #[error(transparent)]
+    CircuitOpen(#[from] CircuitOpen),
+
+    #[error(transparent)]
+    BudgetExceeded(#[from] BudgetExceeded),
 }
 
 pub type Result<T> = std::result::Result<T, GeminiError>;
 
+impl From<reqwest::Error> for GeminiError {
+    fn from(err: reqwest::Error) -> Self {
+        GeminiError::NetworkError(crate::redact::reqwest_error(err))
+    }
+}
+
+impl GeminiError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            GeminiError::MissingApiKey => ErrorKind::Config,
+            GeminiError::FileTooLarge { .. } => ErrorKind::InvalidInput,
+            GeminiError::ApiError { .. } => ErrorKind::Api,
+            GeminiError::RateLimited { .. } => ErrorKind::RateLimited,
+            GeminiError::InvalidResponse(_) | GeminiError::JsonError(_) => {
+                ErrorKind::InvalidResponse
+            }
+            GeminiError::NetworkError(_) => ErrorKind::Network,
+            GeminiError::MaxRetriesExceeded(_) => ErrorKind::RetriesExhausted,
+            GeminiError::CircuitOpen(_) => ErrorKind::CircuitOpen,
+            GeminiError::BudgetExceeded(_) => ErrorKind::BudgetExceeded,
+        }
+    }
+
+    /// HTTP status returned by the API, if any
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            GeminiError::ApiError { status, .. } => Some(*status),
+            GeminiError::RateLimited { .. } => Some(429),
+            _ => None,
+        }
+    }
+}
+
+/// Sampling and length settings merged into a transcription request's
+/// `generation_config`; `None` leaves the model's default
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GenerationParams {
+    /// 0.0 to 2.0; lower is more deterministic
+    pub temperature: Option<f32>,
+    /// Nucleus sampling: only tokens within this cumulative probability
+    pub top_p: Option<f32>,
+    /// Only the K most likely tokens are sampled from
+    pub top_k: Option<u32>,
+    /// Longest answer, in tokens; a cut-off transcript is repaired up to
+    /// its last complete segment
+    pub max_output_tokens: Option<u32>,
+    /// Tokens the model may spend thinking before it answers; 0 turns
+    /// thinking off where the model allows it, -1 lets the model decide
+    pub thinking_budget: Option<i32>,
+}
+
+impl GenerationParams {
+    /// Add the settings that are set to `generation_config`
+    fn merge_into(&self, generation: &mut Value) {
+        if let Some(temperature) = self.temperature {
+            generation["temperature"] = json!(temperature);
+        }
+        if let Some(top_p) = self.top_p {
+            generation["top_p"] = json!(top_p);
+        }
+        if let Some(top_k) = self.top_k {
+            generation["top_k"] = json!(top_k);
+        }
+        if let Some(tokens) = self.max_output_tokens {
+            generation["max_output_tokens"] = json!(tokens);
+        }
+        if let Some(budget) = self.thinking_budget {
+            generation["thinking_config"] = json!({ "thinking_budget": budget });
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct GeminiClientConfig {
     pub timeout_secs: u64,
     pub max_retries: u32,
     pub model: String,
+    /// API host, e.g. a local mock server in tests
+    pub base_url: String,
+    /// Used by `with_config`; `with_http_client` keeps the given client's proxy
+    pub proxy: ProxyConfig,
+    /// Extra root certificates and builder hook, applied like `proxy`
+    pub tls: TlsConfig,
+    /// Connection pool, keep-alive and HTTP/2 settings, applied like `proxy`
+    pub transport: TransportConfig,
+    pub auth_mode: AuthMode,
+    /// Serve identical inline-audio requests from disk; `None` disables
+    pub cache: Option<ResponseCache>,
+    /// Shared with other clients of a batch to stop retrying when the API is down
+    pub circuit_breaker: Option<CircuitBreaker>,
+    /// Spend limit shared with other clients; requests fail once it is reached
+    pub budget: Option<Budget>,
+    /// Also ask for the main topics and keywords with their first mention
+    pub topics: bool,
+    /// Instructions sent in place of the built-in transcription prompt,
+    /// e.g. domain terminology or formatting rules. The response schema
+    /// still applies, so the answer keeps the transcript's shape
+    pub prompt: Option<String>,
+    /// Number of people known to speak; the model is told to use exactly
+    /// this many `Speaker N` labels instead of guessing
+    pub num_speakers: Option<u32>,
+    /// Languages to also translate every segment into (`es`, `French`); the
+    /// answers land in `TranscriptSegment::translations`, keyed as written
+    pub translate_to: Vec<String>,
+    /// Structured-output schema sent in place of the transcript schema, e.g.
+    /// one with extra fields. `topics` and `num_speakers` then only change
+    /// the prompt. The typed methods still read the answer as a
+    /// `TranscriptResponse`; use `transcribe_with_schema` for other shapes
+    pub response_schema: Option<Value>,
+    /// Rules for the whole exchange, sent as `systemInstruction` apart from
+    /// the task prompt, e.g. "never summarize profanity"
+    pub system_instruction: Option<String>,
+    /// Temperature, top-p/top-k, output length and thinking budget
+    pub generation: GenerationParams,
+    /// When an answer isn't valid JSON even after repair, ask once more in
+    /// the same conversation with the parse error
+    pub reask_invalid_json: bool,
 }
 
 impl Default for GeminiClientConfig {
@@ -55,6 +197,22 @@ impl Default for GeminiClientConfig {
             timeout_secs: DEFAULT_TIMEOUT_SECS,
             max_retries: DEFAULT_MAX_RETRIES,
             model: "gemini-2.5-flash".to_string(),
+            base_url: GEMINI_BASE_URL.to_string(),
+            proxy: ProxyConfig::default(),
+            tls: TlsConfig::default(),
+            transport: TransportConfig::default(),
+            auth_mode: AuthMode::default(),
+            cache: None,
+            circuit_breaker: None,
+            budget: None,
+            topics: false,
+            prompt: None,
+            num_speakers: None,
+            translate_to: Vec::new(),
+            response_schema: None,
+            system_instruction: None,
+            generation: GenerationParams::default(),
+            reask_invalid_json: true,
         }
     }
 }
@@ -69,29 +227,86 @@ pub enum AudioSource {
     FileUri { mime_type: String, uri: String },
 }
 
+/// Part of an uploaded file to transcribe, in seconds from its start. Sent
+/// as `videoMetadata` offsets, so the file is clipped by the API instead of
+/// being cut locally and uploaded again; `None` runs to the file's edge.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MediaRange {
+    pub start_secs: Option<u64>,
+    pub end_secs: Option<u64>,
+}
+
 #[derive(Debug, Clone)]
 pub struct GeminiClient {
     client: Client,
-    api_key: String,
+    api_key: ApiKey,
     config: GeminiClientConfig,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TranscriptSegment {
     pub speaker: String,
-    pub timestamp: String,
+    /// When the segment starts; JSON with a timestamp that doesn't parse is
+    /// rejected
+    pub timestamp: Timestamp,
+    /// When the segment's speech ends; `None` in transcripts from before the
+    /// field existed (or with it blank), which end segments where the next
+    /// one starts
+    #[serde(
+        default,
+        deserialize_with = "crate::timestamp::deserialize_optional",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub end_timestamp: Option<Timestamp>,
     pub content: String,
     pub language: String,
     pub language_code: String,
+    /// English translation of a segment in another language; its language
+    /// code is [`TRANSLATION_LANGUAGE_CODE`]
     #[serde(default)]
     pub translation: Option<String>,
+    /// Translations asked for with `GeminiClientConfig::translate_to`, by
+    /// target language as written there
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub translations: BTreeMap<String, String>,
     pub emotion: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Transcript as Gemini answers it and as transcript JSON stores it; see
+/// [`crate::transcript_schema`] for how the format is versioned
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TranscriptResponse {
+    /// Written first; JSON without it (Gemini answers, files from before
+    /// versioning) reads as the current version
+    #[serde(default = "current_schema_version")]
+    pub schema_version: u32,
     pub summary: String,
     pub segments: Vec<TranscriptSegment>,
+    /// Main subjects discussed; only filled with `GeminiClientConfig::topics`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub topics: Vec<Topic>,
+    /// Names, terms and places worth indexing; filled like `topics`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<Topic>,
+}
+
+/// Topic or keyword with the timestamp of its first mention
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Topic {
+    pub name: String,
+    /// `None` when the model gave none (or left it blank)
+    #[serde(
+        default,
+        deserialize_with = "crate::timestamp::deserialize_optional",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub timestamp: Option<Timestamp>,
+}
+
+/// Re-diarization answer: one speaker label per transcript segment
+#[derive(Debug, Serialize, Deserialize)]
+struct SpeakerAssignment {
+    speakers: Vec<String>,
 }
 
 impl GeminiClient {
@@ -101,22 +316,23 @@ impl GeminiClient {
     }
 
     pub fn with_config(api_key: String, config: GeminiClientConfig) -> Result<Self> {
-        let client = Client::builder()
-            .use_rustls_tls()
-            .timeout(Duration::from_secs(config.timeout_secs))
-            .connect_timeout(Duration::from_secs(30))
-            // Keep connections alive for long-running requests
-            .pool_idle_timeout(Duration::from_secs(600))
-            .pool_max_idle_per_host(2)
-            .tcp_keepalive(Duration::from_secs(60))
-            .build()
-            .map_err(GeminiError::NetworkError)?;
-
-        Ok(Self {
+        let builder = crate::proxy::apply(
+            crate::rt::client_builder(config.timeout_secs),
+            &config.proxy,
+        )?;
+        let builder = crate::transport::apply(builder, &config.transport);
+        let client = crate::tls::apply(builder, &config.tls)?.build()?;
+
+        Ok(Self::with_http_client(client, api_key, config))
+    }
+
+    /// Build on an existing HTTP client, sharing its connection pool
+    pub fn with_http_client(client: Client, api_key: String, config: GeminiClientConfig) -> Self {
+        Self {
             client,
-            api_key,
+            api_key: api_key.into(),
             config,
-        })
+        }
     }
 
     #[allow(dead_code)]
@@ -130,14 +346,20 @@ impl GeminiClient {
         &self.client
     }
 
+    /// API host this client talks to, for creating FileApiClient
+    pub fn base_url(&self) -> &str {
+        &self.config.base_url
+    }
+
+    /// How the API key is sent, for creating FileApiClient
+    pub fn auth_mode(&self) -> AuthMode {
+        self.config.auth_mode
+    }
+
     /// Get access to the API key for creating FileApiClient
     #[allow(dead_code)]
     pub fn api_key(&self) -> &str {
-        &self.api_key
-    }
-
-    fn encode_to_base64(data: &[u8]) -> String {
-        base64::engine::general_purpose::STANDARD.encode(data)
+        self.api_key.expose()
     }
 
     pub fn validate_file_size(size: u64) -> Result<()> {
@@ -175,19 +397,24 @@ impl GeminiClient {
         }
     }
 
-    async fn send_request(&self, payload: &Value) -> Result<TranscriptResponse> {
+    /// Send one attempt; returns the text of the answer
+    /// POST `body` to the model's `method` (`generateContent`,
+    /// `countTokens`) and return the JSON answer of a successful call
+    async fn post(&self, method: &str, body: &JsonBody, request_id: &str) -> Result<Value> {
         let url = format!(
-            "{}/{}:generateContent?key={}",
-            GEMINI_API_URL, self.config.model, self.api_key
+            "{}/v1beta/models/{}:{}",
+            self.config.base_url, self.config.model, method
         );
 
         debug!("Sending request to Gemini API");
 
         let response = self
-            .client
-            .post(&url)
+            .config
+            .auth_mode
+            .request(&self.client, Method::POST, &url, &self.api_key)
+            .timeout(Duration::from_secs(self.config.timeout_secs))
             .header("Content-Type", "application/json")
-            .json(payload)
+            .body(body.clone())
             .send()
             .await?;
 
@@ -195,49 +422,175 @@ impl GeminiClient {
         debug!("Received response with status: {}", status);
 
         if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
-            return Err(GeminiError::RateLimited);
+            crate::metrics::record_rate_limited("gemini");
+            return Err(GeminiError::RateLimited {
+                request_id: request_id.to_string(),
+            });
         }
 
         if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
+            let error_text = self
+                .api_key
+                .scrub(response.text().await.unwrap_or_default());
             return Err(GeminiError::ApiError {
                 status: status.as_u16(),
                 message: error_text,
+                request_id: request_id.to_string(),
             });
         }
 
-        let data: Value = response.json().await?;
+        Ok(response.json().await?)
+    }
+
+    async fn send_request(
+        &self,
+        body: &JsonBody,
+        request_id: &str,
+    ) -> Result<(String, TokenUsage)> {
+        let data = self.post("generateContent", body, request_id).await?;
+        crate::metrics::record_gemini_usage("gemini", &data);
+        crate::usage::record_gemini(
+            "gemini",
+            &self.config.model,
+            &data,
+            self.config.budget.as_ref(),
+        );
 
         let text = data["candidates"][0]["content"]["parts"][0]["text"]
             .as_str()
             .ok_or_else(|| GeminiError::InvalidResponse("Missing text in response".to_string()))?;
 
-        let transcript: TranscriptResponse = serde_json::from_str(text)?;
-        Ok(transcript)
+        Ok((text.to_string(), TokenUsage::from_response(&data)))
+    }
+
+    /// Parse a structured answer, repairing fences, trailing commas and a
+    /// truncated end (see [`repair_json`]) when it doesn't parse as is
+    fn parse_answer<T: DeserializeOwned>(text: &str) -> Result<T> {
+        let err = match serde_json::from_str(text) {
+            Ok(parsed) => return Ok(parsed),
+            Err(err) => err,
+        };
+        if let Some(repaired) = repair_json(text)
+            && let Ok(parsed) = serde_json::from_str(&repaired)
+        {
+            warn!("Answer was not valid JSON ({}); used a repaired copy", err);
+            return Ok(parsed);
+        }
+        Err(err.into())
     }
 
     fn is_retryable_error(err: &GeminiError) -> bool {
-        match err {
-            GeminiError::RateLimited | GeminiError::NetworkError(_) => true,
-            GeminiError::ApiError { status, .. } => *status >= 500,
-            _ => false,
+        crate::error::is_retryable(err.kind(), err.status())
+    }
+
+    /// Labels the model may use when the number of speakers is known
+    fn speaker_labels(num_speakers: u32) -> Vec<String> {
+        (1..=num_speakers)
+            .map(|n| format!("Speaker {}", n))
+            .collect()
+    }
+
+    fn speaker_requirement(num_speakers: u32) -> String {
+        match num_speakers {
+            1 => "Exactly one person speaks in this audio: label every segment Speaker 1."
+                .to_string(),
+            n => format!(
+                "Exactly {n} people speak in this audio: label them Speaker 1 to Speaker {n} \
+                 in order of first appearance, and never introduce another speaker."
+            ),
         }
     }
 
-    fn get_transcription_prompt() -> &'static str {
-        r#"Process the audio file and generate a detailed transcription.
+    /// `translate_to` without blanks and repeats
+    fn translation_targets(config: &GeminiClientConfig) -> Vec<&str> {
+        let mut targets: Vec<&str> = Vec::new();
+        for language in config.translate_to.iter().map(|l| l.trim()) {
+            if !language.is_empty() && !targets.contains(&language) {
+                targets.push(language);
+            }
+        }
+        targets
+    }
 
-Requirements:
-1. Identify distinct speakers (e.g., Speaker 1, Speaker 2, or names if context allows).
-2. Provide accurate timestamps for each segment (Format: MM:SS).
-3. Detect the primary language of each segment.
-4. If the segment is in a language different than English, also provide the English translation.
-5. Identify the primary emotion of the speaker in this segment. You MUST choose exactly one of the following: Happy, Sad, Angry, Neutral.
-6. Provide a brief summary of the entire audio at the beginning."#
+    fn translation_requirement(targets: &[&str]) -> String {
+        format!(
+            "Also translate every segment into each of these languages, in translations keyed \
+             exactly as written here: {}.",
+            targets.join(", ")
+        )
     }
 
-    fn get_generation_config() -> Value {
-        json!({
+    fn get_transcription_prompt(config: &GeminiClientConfig) -> String {
+        let targets = Self::translation_targets(config);
+        if let Some(custom) = config.prompt.as_deref() {
+            let mut prompt = custom.trim().to_string();
+            if let Some(n) = config.num_speakers {
+                prompt.push_str("\n\n");
+                prompt.push_str(&Self::speaker_requirement(n));
+            }
+            if config.topics {
+                prompt.push_str(
+                    "\n\nAlso list the main topics discussed and the keywords worth indexing, \
+                     each with the timestamp of the segment where it is first mentioned.",
+                );
+            }
+            if !targets.is_empty() {
+                prompt.push_str("\n\n");
+                prompt.push_str(&Self::translation_requirement(&targets));
+            }
+            return prompt;
+        }
+        let speakers = match config.num_speakers {
+            Some(n) => Self::speaker_requirement(n),
+            None => "Identify distinct speakers (e.g., Speaker 1, Speaker 2, or names if context \
+                     allows)."
+                .to_string(),
+        };
+        let mut requirements = vec![
+            speakers,
+            "Provide accurate timestamps for each segment: when it starts, and in end_timestamp \
+             when its speech ends (Format: MM:SS, or HH:MM:SS from one hour on)."
+                .to_string(),
+            "Detect the primary language of each segment.".to_string(),
+            "If the segment is in a language different than English, also provide the English \
+             translation."
+                .to_string(),
+            "Identify the primary emotion of the speaker in this segment. You MUST choose \
+             exactly one of the following: Happy, Sad, Angry, Neutral."
+                .to_string(),
+            "Provide a brief summary of the entire audio at the beginning.".to_string(),
+        ];
+        if config.topics {
+            requirements.push(
+                "List the main topics discussed and the keywords worth indexing (names, \
+                 products, places, technical terms), each with the timestamp of the segment \
+                 where it is first mentioned."
+                    .to_string(),
+            );
+        }
+        if !targets.is_empty() {
+            requirements.push(Self::translation_requirement(&targets));
+        }
+
+        let mut prompt =
+            "Process the audio file and generate a detailed transcription.\n\nRequirements:"
+                .to_string();
+        for (i, requirement) in requirements.iter().enumerate() {
+            prompt.push_str(&format!("\n{}. {}", i + 1, requirement));
+        }
+        prompt
+    }
+
+    fn get_generation_config(config: &GeminiClientConfig) -> Value {
+        if let Some(schema) = &config.response_schema {
+            let mut generation = json!({
+                "response_mime_type": "application/json",
+                "response_schema": schema
+            });
+            config.generation.merge_into(&mut generation);
+            return generation;
+        }
+        let mut generation = json!({
             "response_mime_type": "application/json",
             "response_schema": {
                 "type": "OBJECT",
@@ -254,6 +607,7 @@ Requirements:
                             "properties": {
                                 "speaker": { "type": "STRING" },
                                 "timestamp": { "type": "STRING" },
+                                "end_timestamp": { "type": "STRING", "description": "When the speech of the segment ends, in the format of timestamp" },
                                 "content": { "type": "STRING" },
                                 "language": { "type": "STRING" },
                                 "language_code": { "type": "STRING" },
@@ -263,51 +617,253 @@ Requirements:
                                     "enum": ["happy", "sad", "angry", "neutral"]
                                 }
                             },
-                            "required": ["speaker", "timestamp", "content", "language", "language_code", "emotion"]
+                            "required": ["speaker", "timestamp", "end_timestamp", "content", "language", "language_code", "emotion"]
                         }
                     }
                 },
                 "required": ["summary", "segments"]
             }
+        });
+        let schema = &mut generation["response_schema"];
+        if let Some(n) = config.num_speakers {
+            schema["properties"]["segments"]["items"]["properties"]["speaker"]["enum"] =
+                json!(Self::speaker_labels(n));
+        }
+        let targets = Self::translation_targets(config);
+        if !targets.is_empty() {
+            let segment = &mut schema["properties"]["segments"]["items"];
+            let properties: serde_json::Map<String, Value> = targets
+                .iter()
+                .map(|language| (language.to_string(), json!({ "type": "STRING" })))
+                .collect();
+            segment["properties"]["translations"] = json!({
+                "type": "OBJECT",
+                "properties": properties,
+                "required": targets
+            });
+            segment["required"]
+                .as_array_mut()
+                .unwrap()
+                .push(json!("translations"));
+        }
+        if config.topics {
+            let mention = json!({
+                "type": "ARRAY",
+                "items": {
+                    "type": "OBJECT",
+                    "properties": {
+                        "name": { "type": "STRING" },
+                        "timestamp": { "type": "STRING", "description": "MM:SS (HH:MM:SS from one hour on) of the first mention" }
+                    },
+                    "required": ["name", "timestamp"]
+                }
+            });
+            schema["properties"]["topics"] = mention.clone();
+            schema["properties"]["keywords"] = mention;
+            schema["required"] = json!(["summary", "segments", "topics", "keywords"]);
+        }
+        config.generation.merge_into(&mut generation);
+        generation
+    }
+
+    /// Everything a transcription request sends besides its contents
+    fn get_request_config(config: &GeminiClientConfig) -> Value {
+        let mut request = json!({"generation_config": Self::get_generation_config(config)});
+        if let Some(instruction) = config
+            .system_instruction
+            .as_deref()
+            .filter(|instruction| !instruction.trim().is_empty())
+        {
+            request["systemInstruction"] = json!({ "parts": [{ "text": instruction }] });
+        }
+        request
+    }
+
+    /// Instructions for re-assigning the speakers of `transcript`, which is
+    /// embedded as a JSON array of its segments
+    fn get_rediarization_prompt(
+        transcript: &TranscriptResponse,
+        hint: Option<&str>,
+    ) -> serde_json::Result<String> {
+        let segments: Vec<Value> = transcript
+            .segments
+            .iter()
+            .enumerate()
+            .map(|(index, segment)| {
+                json!({
+                    "index": index,
+                    "timestamp": segment.timestamp,
+                    "speaker": segment.speaker,
+                    "content": segment.content,
+                })
+            })
+            .collect();
+
+        let mut prompt = String::from(
+            r#"Listen to the audio and correct the speaker labels of its existing transcript, given below as a JSON array of segments.
+
+Requirements:
+1. Decide who speaks each segment from the voices, not from the current labels alone.
+2. If one person is split across several labels, merge them into the label they use first (e.g. relabel Speaker 3 as Speaker 1).
+3. If two people share one label, give the second person a new label in the same style.
+4. Keep labels that are already right, and keep their style (Speaker N, or names if the transcript already uses names).
+5. Return exactly one label per segment, in segment order."#,
+        );
+        if let Some(hint) = hint.filter(|hint| !hint.trim().is_empty()) {
+            prompt.push_str(&format!("\n\nContext about the speakers: {}", hint.trim()));
+        }
+        prompt.push_str(&format!(
+            "\n\nTranscript segments:\n{}",
+            serde_json::to_string(&segments)?
+        ));
+        Ok(prompt)
+    }
+
+    fn get_rediarization_config() -> Value {
+        json!({
+            "response_mime_type": "application/json",
+            "response_schema": {
+                "type": "OBJECT",
+                "properties": {
+                    "speakers": {
+                        "type": "ARRAY",
+                        "description": "Speaker label of each transcript segment, in order.",
+                        "items": { "type": "STRING" }
+                    }
+                },
+                "required": ["speakers"]
+            }
         })
     }
 
-    async fn send_request_with_retry(&self, payload: &Value) -> Result<TranscriptResponse> {
-        let mut last_error = None;
-        let mut retry_count = 0;
+    /// Send `body`, serving inline-audio requests from the response cache
+    async fn send_cached<T: Serialize + DeserializeOwned>(
+        &self,
+        body: &JsonBody,
+        payload_bytes: Option<usize>,
+    ) -> Result<WithUsage<T>> {
+        let Some(cache) = &self.config.cache else {
+            return self.send_request_with_retry(body, payload_bytes).await;
+        };
+
+        let key = ResponseCache::key(&[self.config.model.as_bytes(), body.as_bytes()]);
+        if let Some(response) = cache.get_json::<T>(&key).await {
+            info!("Using cached Gemini response");
+            return Ok(WithUsage::new(response, TokenUsage::default()));
+        }
 
-        while retry_count < self.config.max_retries {
-            match self.send_request(payload).await {
-                Ok(response) => {
-                    info!("Transcription successful");
-                    return Ok(response);
+        let response = self.send_request_with_retry(body, payload_bytes).await?;
+        cache.put_json(&key, &response.value).await;
+        Ok(response)
+    }
+
+    async fn send_request_with_retry<T: DeserializeOwned>(
+        &self,
+        body: &JsonBody,
+        payload_bytes: Option<usize>,
+    ) -> Result<WithUsage<T>> {
+        let request_id = crate::request_id::next();
+        let span = info_span!(
+            "gemini_request",
+            request_id = %request_id,
+            model = %self.config.model,
+            payload_bytes = payload_bytes,
+            attempt = field::Empty,
+            latency_ms = field::Empty,
+        );
+
+        async {
+            let mut body = body.clone();
+            let mut reasked = false;
+            // Tokens of an answer that had to be asked again count too
+            let mut usage = TokenUsage::default();
+            let mut last_error = None;
+            let mut retry_count = 0;
+
+            while retry_count < self.config.max_retries {
+                if let Some(breaker) = &self.config.circuit_breaker {
+                    breaker.check()?;
+                }
+                if let Some(budget) = &self.config.budget {
+                    budget.check()?;
                 }
-                Err(e) => {
-                    if Self::is_retryable_error(&e) && retry_count + 1 < self.config.max_retries {
-                        // Use longer backoff for rate limiting (30s base), shorter for other errors
-                        let delay = if matches!(e, GeminiError::RateLimited) {
-                            Duration::from_secs(30 * (retry_count as u64 + 1))
+                span.record("attempt", retry_count + 1);
+                if let Some(bytes) = payload_bytes {
+                    crate::metrics::record_upload_bytes("gemini", bytes);
+                }
+                let stopwatch = Stopwatch::start();
+                let result = self.send_request(&body, &request_id).await;
+                span.record("latency_ms", stopwatch.elapsed_ms());
+                if let Some(breaker) = &self.config.circuit_breaker {
+                    breaker.record(&result, Self::is_retryable_error);
+                }
+                crate::metrics::record_request("gemini", result.is_ok(), stopwatch.elapsed_ms());
+                debug!(
+                    "Attempt {} finished in {}ms",
+                    retry_count + 1,
+                    stopwatch.elapsed_ms()
+                );
+
+                match result {
+                    Ok((text, answer_usage)) => match Self::parse_answer(&text) {
+                        Ok(response) => {
+                            info!("Gemini request successful");
+                            return Ok(WithUsage::new(response, usage + answer_usage));
+                        }
+                        Err(e) if self.config.reask_invalid_json && !reasked => {
+                            // A new question rather than a retry, so it
+                            // doesn't use up an attempt
+                            warn!("Answer could not be parsed ({}); asking again", e);
+                            body = body.with_follow_up(
+                                &text,
+                                &format!(
+                                    "That answer is not valid JSON for the response schema ({}). \
+                                     Reply again with the complete answer as valid JSON.",
+                                    e
+                                ),
+                            )?;
+                            reasked = true;
+                            usage += answer_usage;
+                        }
+                        Err(e) => return Err(e),
+                    },
+                    Err(e) => {
+                        if Self::is_retryable_error(&e)
+                            && retry_count + 1 < self.config.max_retries
+                            && self
+                                .config
+                                .circuit_breaker
+                                .as_ref()
+                                .is_none_or(CircuitBreaker::try_retry)
+                        {
+                            // Use longer backoff for rate limiting (30s base), shorter for other errors
+                            let delay = if matches!(e, GeminiError::RateLimited { .. }) {
+                                Duration::from_secs(30 * (retry_count as u64 + 1))
+                            } else {
+                                Duration::from_secs(2u64.pow(retry_count))
+                            };
+                            warn!(
+                                "Request failed (attempt {}/{}): {}. Retrying in {:?}...",
+                                retry_count + 1,
+                                self.config.max_retries,
+                                e,
+                                delay
+                            );
+                            crate::metrics::record_retry("gemini");
+                            crate::rt::sleep(delay).await;
+                            retry_count += 1;
+                            last_error = Some(e);
                         } else {
-                            Duration::from_secs(2u64.pow(retry_count))
-                        };
-                        warn!(
-                            "Request failed (attempt {}/{}): {}. Retrying in {:?}...",
-                            retry_count + 1,
-                            self.config.max_retries,
-                            e,
-                            delay
-                        );
-                        tokio::time::sleep(delay).await;
-                        retry_count += 1;
-                        last_error = Some(e);
-                    } else {
-                        return Err(e);
+                            return Err(e);
+                        }
                     }
                 }
             }
-        }
 
-        Err(last_error.unwrap_or(GeminiError::MaxRetriesExceeded(self.config.max_retries)))
+            Err(last_error.unwrap_or(GeminiError::MaxRetriesExceeded(self.config.max_retries)))
+        }
+        .instrument(span.clone())
+        .await
     }
 
     /// Transcribe audio using inline base64 data (for files <= 20MB)
@@ -316,29 +872,30 @@ Requirements:
         audio_data: &[u8],
         mime_type: &str,
     ) -> Result<TranscriptResponse> {
-        Self::validate_file_size(audio_data.len() as u64)?;
+        self.transcribe_audio_with_usage(audio_data, mime_type)
+            .await
+            .map(|response| response.value)
+    }
 
-        let base64_audio = Self::encode_to_base64(audio_data);
-        let prompt = Self::get_transcription_prompt();
+    /// [`transcribe_audio`](Self::transcribe_audio) with the tokens the
+    /// request took
+    pub async fn transcribe_audio_with_usage(
+        &self,
+        audio_data: &[u8],
+        mime_type: &str,
+    ) -> Result<WithUsage<TranscriptResponse>> {
+        Self::validate_file_size(audio_data.len() as u64)?;
 
-        let payload = json!({
-            "contents": [
-                {
-                    "parts": [
-                        {"text": prompt},
-                        {
-                            "inline_data": {
-                                "mime_type": mime_type,
-                                "data": base64_audio
-                            }
-                        }
-                    ]
-                }
+        let body = GenerateContent::new(
+            vec![
+                Part::text(Self::get_transcription_prompt(&self.config)),
+                Part::inline(mime_type, audio_data),
             ],
-            "generation_config": Self::get_generation_config()
-        });
+            Self::get_request_config(&self.config),
+        )
+        .to_body()?;
 
-        self.send_request_with_retry(&payload).await
+        self.send_cached(&body, Some(audio_data.len())).await
     }
 
     /// Transcribe audio using a file URI (for files uploaded via File API)
@@ -347,26 +904,47 @@ Requirements:
         file_uri: &str,
         mime_type: &str,
     ) -> Result<TranscriptResponse> {
-        let prompt = Self::get_transcription_prompt();
+        self.transcribe_file_uri_range(file_uri, mime_type, MediaRange::default())
+            .await
+    }
 
-        let payload = json!({
-            "contents": [
-                {
-                    "parts": [
-                        {"text": prompt},
-                        {
-                            "file_data": {
-                                "mime_type": mime_type,
-                                "file_uri": file_uri
-                            }
-                        }
-                    ]
-                }
+    /// Transcribe only `range` of an uploaded file. The model times the
+    /// clip from its own start, so timestamps are moved by the start offset
+    /// to stay those of the whole file.
+    pub async fn transcribe_file_uri_range(
+        &self,
+        file_uri: &str,
+        mime_type: &str,
+        range: MediaRange,
+    ) -> Result<TranscriptResponse> {
+        self.transcribe_file_uri_range_with_usage(file_uri, mime_type, range)
+            .await
+            .map(|response| response.value)
+    }
+
+    /// [`transcribe_file_uri_range`](Self::transcribe_file_uri_range) with
+    /// the tokens the request took
+    pub async fn transcribe_file_uri_range_with_usage(
+        &self,
+        file_uri: &str,
+        mime_type: &str,
+        range: MediaRange,
+    ) -> Result<WithUsage<TranscriptResponse>> {
+        let body = GenerateContent::new(
+            vec![
+                Part::text(Self::get_transcription_prompt(&self.config)),
+                Part::file_clip(mime_type, file_uri, range.start_secs, range.end_secs),
             ],
-            "generation_config": Self::get_generation_config()
-        });
+            Self::get_request_config(&self.config),
+        )
+        .to_body()?;
 
-        self.send_request_with_retry(&payload).await
+        let mut response: WithUsage<TranscriptResponse> =
+            self.send_request_with_retry(&body, None).await?;
+        if let Some(start) = range.start_secs.filter(|&start| start > 0) {
+            response.value.shift_timestamps(start);
+        }
+        Ok(response)
     }
 
     /// Transcribe audio from any source (inline data or file URI)
@@ -379,11 +957,139 @@ Requirements:
             }
         }
     }
+
+    /// Transcribe `source` with `response_schema` (or the built-in schema)
+    /// and return the answer as it is, for schemas that don't fit
+    /// `TranscriptResponse`
+    pub async fn transcribe_with_schema(&self, source: &AudioSource) -> Result<Value> {
+        let audio = match source {
+            AudioSource::Inline { mime_type, data } => {
+                Self::validate_file_size(data.len() as u64)?;
+                Part::inline(mime_type, data)
+            }
+            AudioSource::FileUri { mime_type, uri } => Part::file(mime_type, uri),
+        };
+        let body = GenerateContent::new(
+            vec![
+                Part::text(Self::get_transcription_prompt(&self.config)),
+                audio,
+            ],
+            Self::get_request_config(&self.config),
+        )
+        .to_body()?;
+
+        let response = match source {
+            AudioSource::Inline { data, .. } => self.send_cached(&body, Some(data.len())).await?,
+            AudioSource::FileUri { .. } => self.send_request_with_retry(&body, None).await?,
+        };
+        Ok(response.value)
+    }
+
+    /// Count the input tokens a transcription of `source` would use (the
+    /// prompt and the audio) with the free `countTokens` endpoint, before
+    /// paying for the request
+    pub async fn count_tokens(&self, source: &AudioSource) -> Result<TokenCount> {
+        let audio = match source {
+            AudioSource::Inline { mime_type, data } => {
+                Self::validate_file_size(data.len() as u64)?;
+                Part::inline(mime_type, data)
+            }
+            AudioSource::FileUri { mime_type, uri } => Part::file(mime_type, uri),
+        };
+        let body = GenerateContent::new(
+            vec![
+                Part::text(Self::get_transcription_prompt(&self.config)),
+                audio,
+            ],
+            json!({}),
+        )
+        .to_body()?;
+
+        let request_id = crate::request_id::next();
+        let mut retry_count = 0;
+        loop {
+            match self.post("countTokens", &body, &request_id).await {
+                Ok(data) => {
+                    return TokenCount::from_response(&data).ok_or_else(|| {
+                        GeminiError::InvalidResponse("Missing totalTokens".to_string())
+                    });
+                }
+                Err(e)
+                    if Self::is_retryable_error(&e)
+                        && retry_count + 1 < self.config.max_retries =>
+                {
+                    let delay = Duration::from_secs(2u64.pow(retry_count));
+                    warn!(
+                        "Token count failed (attempt {}/{}): {}. Retrying in {:?}...",
+                        retry_count + 1,
+                        self.config.max_retries,
+                        e,
+                        delay
+                    );
+                    crate::rt::sleep(delay).await;
+                    retry_count += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Re-assign the speakers of `transcript` by listening to its audio again,
+    /// e.g. to merge one person split across several labels. Only `speaker`
+    /// changes; `hint` can name the speakers or say how many there are.
+    pub async fn rediarize(
+        &self,
+        source: &AudioSource,
+        transcript: &TranscriptResponse,
+        hint: Option<&str>,
+    ) -> Result<TranscriptResponse> {
+        if let AudioSource::Inline { data, .. } = source {
+            Self::validate_file_size(data.len() as u64)?;
+        }
+        let prompt = Self::get_rediarization_prompt(transcript, hint)?;
+        let audio = match source {
+            AudioSource::Inline { mime_type, data } => Part::inline(mime_type, data),
+            AudioSource::FileUri { mime_type, uri } => Part::file(mime_type, uri),
+        };
+        let body = GenerateContent::new(
+            vec![Part::text(prompt), audio],
+            json!({"generation_config": Self::get_rediarization_config()}),
+        )
+        .to_body()?;
+
+        let assignment: WithUsage<SpeakerAssignment> = match source {
+            AudioSource::Inline { data, .. } => self.send_cached(&body, Some(data.len())).await?,
+            AudioSource::FileUri { .. } => self.send_request_with_retry(&body, None).await?,
+        };
+        Self::apply_speakers(transcript, assignment.value.speakers)
+    }
+
+    fn apply_speakers(
+        transcript: &TranscriptResponse,
+        speakers: Vec<String>,
+    ) -> Result<TranscriptResponse> {
+        if speakers.len() != transcript.segments.len() {
+            return Err(GeminiError::InvalidResponse(format!(
+                "Expected {} speaker labels, got {}",
+                transcript.segments.len(),
+                speakers.len()
+            )));
+        }
+        let mut updated = transcript.clone();
+        for (segment, speaker) in updated.segments.iter_mut().zip(speakers) {
+            let speaker = speaker.trim();
+            if !speaker.is_empty() {
+                segment.speaker = speaker.to_string();
+            }
+        }
+        Ok(updated)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::transcript_schema::TRANSCRIPT_SCHEMA_VERSION;
 
     #[test]
     fn test_get_mime_type() {
@@ -425,9 +1131,240 @@ mod tests {
     }
 
     #[test]
-    fn test_encode_to_base64() {
-        let data = b"hello world";
-        let encoded = GeminiClient::encode_to_base64(data);
-        assert_eq!(encoded, "aGVsbG8gd29ybGQ=");
+    fn test_topics_prompt_and_schema() {
+        let plain = GeminiClientConfig::default();
+        let config = GeminiClient::get_generation_config(&plain);
+        assert!(config["response_schema"]["properties"]["topics"].is_null());
+        let segment = &config["response_schema"]["properties"]["segments"]["items"];
+        assert_eq!(segment["properties"]["end_timestamp"]["type"], "STRING");
+        assert!(
+            segment["required"]
+                .as_array()
+                .unwrap()
+                .contains(&json!("end_timestamp"))
+        );
+        assert!(segment["properties"]["speaker"]["enum"].is_null());
+        let prompt = GeminiClient::get_transcription_prompt(&plain);
+        assert!(!prompt.contains("topics"));
+        assert!(prompt.contains("\n\nRequirements:\n1. Identify distinct speakers"));
+        assert!(
+            prompt.ends_with("\n6. Provide a brief summary of the entire audio at the beginning.")
+        );
+
+        let topics = GeminiClientConfig {
+            topics: true,
+            ..Default::default()
+        };
+        let config = GeminiClient::get_generation_config(&topics);
+        let schema = &config["response_schema"];
+        assert_eq!(schema["properties"]["keywords"]["type"], "ARRAY");
+        assert_eq!(
+            schema["required"],
+            json!(["summary", "segments", "topics", "keywords"])
+        );
+        assert!(
+            GeminiClient::get_transcription_prompt(&topics).contains("7. List the main topics")
+        );
+    }
+
+    #[test]
+    fn test_custom_prompt() {
+        let custom = " Transcribe this cardiology consult; keep drug names as spoken.\n";
+        let mut config = GeminiClientConfig {
+            prompt: Some(custom.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            GeminiClient::get_transcription_prompt(&config),
+            custom.trim()
+        );
+        config.topics = true;
+        config.num_speakers = Some(2);
+        let prompt = GeminiClient::get_transcription_prompt(&config);
+        assert!(prompt.starts_with("Transcribe this cardiology consult"));
+        assert!(prompt.contains("Speaker 1 to Speaker 2"));
+        assert!(prompt.contains("main topics"));
+    }
+
+    #[test]
+    fn test_custom_response_schema() {
+        let schema = json!({
+            "type": "OBJECT",
+            "properties": { "topic": { "type": "STRING" } },
+            "required": ["topic"]
+        });
+        let config = GeminiClientConfig {
+            response_schema: Some(schema.clone()),
+            topics: true,
+            num_speakers: Some(2),
+            ..Default::default()
+        };
+        let generation = GeminiClient::get_generation_config(&config);
+        assert_eq!(generation["response_schema"], schema);
+        assert_eq!(generation["response_mime_type"], "application/json");
+    }
+
+    #[test]
+    fn test_generation_params() {
+        let generation = GeminiClient::get_generation_config(&GeminiClientConfig::default());
+        assert!(generation["temperature"].is_null());
+        assert!(generation["thinking_config"].is_null());
+
+        let config = GeminiClientConfig {
+            generation: GenerationParams {
+                temperature: Some(0.5),
+                top_p: Some(0.25),
+                top_k: Some(40),
+                max_output_tokens: Some(8192),
+                thinking_budget: Some(0),
+            },
+            response_schema: Some(json!({"type": "OBJECT"})),
+            ..Default::default()
+        };
+        let generation = GeminiClient::get_generation_config(&config);
+        assert_eq!(generation["temperature"], 0.5);
+        assert_eq!(generation["top_p"], 0.25);
+        assert_eq!(generation["top_k"], 40);
+        assert_eq!(generation["max_output_tokens"], 8192);
+        assert_eq!(generation["thinking_config"]["thinking_budget"], 0);
+        assert_eq!(generation["response_schema"]["type"], "OBJECT");
+    }
+
+    #[test]
+    fn test_system_instruction() {
+        let request = GeminiClient::get_request_config(&GeminiClientConfig::default());
+        assert!(request["systemInstruction"].is_null());
+        assert!(request["generation_config"]["response_schema"].is_object());
+
+        let config = GeminiClientConfig {
+            system_instruction: Some("Never summarize profanity.".to_string()),
+            ..Default::default()
+        };
+        let request = GeminiClient::get_request_config(&config);
+        assert_eq!(
+            request["systemInstruction"]["parts"][0]["text"],
+            "Never summarize profanity."
+        );
+    }
+
+    #[test]
+    fn test_num_speakers_prompt_and_schema() {
+        let config = GeminiClientConfig {
+            num_speakers: Some(2),
+            ..Default::default()
+        };
+        let prompt = GeminiClient::get_transcription_prompt(&config);
+        assert!(prompt.contains("1. Exactly 2 people speak"));
+        assert!(!prompt.contains("names if context allows"));
+        let generation = GeminiClient::get_generation_config(&config);
+        assert_eq!(
+            generation["response_schema"]["properties"]["segments"]["items"]["properties"]["speaker"]
+                ["enum"],
+            json!(["Speaker 1", "Speaker 2"])
+        );
+
+        let config = GeminiClientConfig {
+            num_speakers: Some(1),
+            ..Default::default()
+        };
+        assert!(GeminiClient::get_transcription_prompt(&config).contains("Exactly one person"));
+    }
+
+    #[test]
+    fn test_translate_to_prompt_and_schema() {
+        let config = GeminiClientConfig {
+            translate_to: vec!["es".to_string(), " fr ".to_string(), "es".to_string()],
+            ..Default::default()
+        };
+        let prompt = GeminiClient::get_transcription_prompt(&config);
+        assert!(prompt.ends_with("\n7. Also translate every segment into each of these languages, in translations keyed exactly as written here: es, fr."));
+        let with_topics = GeminiClientConfig {
+            topics: true,
+            ..config.clone()
+        };
+        let prompt = GeminiClient::get_transcription_prompt(&with_topics);
+        assert!(prompt.contains("\n7. List the main topics"));
+        assert!(prompt.ends_with("\n8. Also translate every segment into each of these languages, in translations keyed exactly as written here: es, fr."));
+        let generation = GeminiClient::get_generation_config(&config);
+        let segment = &generation["response_schema"]["properties"]["segments"]["items"];
+        assert_eq!(
+            segment["properties"]["translations"]["required"],
+            json!(["es", "fr"])
+        );
+        assert_eq!(
+            segment["properties"]["translations"]["properties"]["fr"],
+            json!({"type": "STRING"})
+        );
+        assert_eq!(
+            segment["required"].as_array().unwrap().last(),
+            Some(&json!("translations"))
+        );
+
+        let segment: TranscriptSegment = serde_json::from_value(json!({
+            "speaker": "Speaker 1", "timestamp": "00:01", "content": "Hi",
+            "language": "English", "language_code": "en", "emotion": "neutral",
+            "translations": {"es": "Hola", "fr": "Salut"}
+        }))
+        .unwrap();
+        assert_eq!(segment.translations["fr"], "Salut");
+        let default = GeminiClient::get_generation_config(&GeminiClientConfig::default());
+        assert!(
+            default["response_schema"]["properties"]["segments"]["items"]["properties"]
+                .get("translations")
+                .is_none()
+        );
+    }
+
+    fn two_speaker_transcript() -> TranscriptResponse {
+        let segment = |speaker: &str, content: &str| TranscriptSegment {
+            speaker: speaker.to_string(),
+            timestamp: Timestamp::from_secs(1),
+            end_timestamp: None,
+            content: content.to_string(),
+            language: "English".to_string(),
+            language_code: "en".to_string(),
+            translation: None,
+            translations: Default::default(),
+            emotion: "neutral".to_string(),
+        };
+        TranscriptResponse {
+            schema_version: TRANSCRIPT_SCHEMA_VERSION,
+            summary: "A chat".to_string(),
+            segments: vec![segment("Speaker 1", "Hi"), segment("Speaker 3", "Hello")],
+            topics: Vec::new(),
+            keywords: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_rediarization_prompt() {
+        let transcript = two_speaker_transcript();
+        let prompt =
+            GeminiClient::get_rediarization_prompt(&transcript, Some(" Two people: Ann and Bo "))
+                .unwrap();
+        assert!(prompt.contains("Context about the speakers: Two people: Ann and Bo\n"));
+        assert!(prompt.contains(r#""index":1"#));
+        assert!(prompt.contains(r#""speaker":"Speaker 3""#));
+
+        let prompt = GeminiClient::get_rediarization_prompt(&transcript, Some("  ")).unwrap();
+        assert!(!prompt.contains("Context about the speakers"));
+    }
+
+    #[test]
+    fn test_apply_speakers() {
+        let transcript = two_speaker_transcript();
+        let updated = GeminiClient::apply_speakers(
+            &transcript,
+            vec!["Speaker 1".to_string(), " Speaker 1 ".to_string()],
+        )
+        .unwrap();
+        assert_eq!(updated.segments[1].speaker, "Speaker 1");
+        assert_eq!(updated.segments[1].content, "Hello");
+        assert_eq!(transcript.segments[1].speaker, "Speaker 3");
+
+        assert!(matches!(
+            GeminiClient::apply_speakers(&transcript, vec!["Speaker 1".to_string()]),
+            Err(GeminiError::InvalidResponse(_))
+        ));
     }
 }
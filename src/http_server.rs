@@ -0,0 +1,288 @@
+//! HTTP/1.1 request parsing and response writing shared by the local servers:
+//! `serve`, `metrics::serve` and the `testing` mock and VCR servers. One
+//! request per connection; bodies are read by `Content-Length` only.
+
+use std::collections::HashMap;
+use std::io;
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Longest accepted request line plus headers
+pub(crate) const MAX_HEAD_BYTES: u64 = 64 * 1024;
+
+/// An HTTP/1.1 request; header names are lowercased
+#[derive(Debug)]
+pub(crate) struct Request {
+    pub method: String,
+    /// Request target without the query string
+    pub path: String,
+    /// Only the `testing` servers look at it
+    #[cfg_attr(not(feature = "testing"), allow(dead_code))]
+    pub query: Option<String>,
+    /// Not needed by `metrics::serve`
+    #[cfg_attr(not(any(feature = "server", feature = "testing")), allow(dead_code))]
+    pub headers: HashMap<String, String>,
+    #[cfg_attr(not(any(feature = "server", feature = "testing")), allow(dead_code))]
+    pub body: Vec<u8>,
+}
+
+/// Why a request was refused, to be answered with `status`
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Rejection {
+    pub status: u16,
+    pub message: String,
+}
+
+impl Rejection {
+    fn new(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+/// Parse one request, or why it was refused. The request line and headers
+/// must fit in `MAX_HEAD_BYTES` and arrive within `read_timeout`; the body is
+/// read only if it is within `max_body_bytes`, with `read_timeout` allowed
+/// between reads. I/O errors and a connection closed before the request line
+/// are returned as `Err`
+pub(crate) async fn read_request<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    max_body_bytes: usize,
+    read_timeout: Duration,
+) -> io::Result<Result<Request, Rejection>> {
+    let (method, target, headers) =
+        match tokio::time::timeout(read_timeout, read_head(reader)).await {
+            Ok(Ok(Some(head))) => head,
+            Ok(Ok(None)) => {
+                return Ok(Err(Rejection::new(
+                    400,
+                    format!(
+                        "Malformed request line or headers (at most {} bytes)",
+                        MAX_HEAD_BYTES
+                    ),
+                )));
+            }
+            Ok(Err(e)) => return Err(e),
+            Err(_) => return Ok(Err(Rejection::new(408, "Timed out reading the request"))),
+        };
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path.to_string(), Some(query.to_string())),
+        None => (target, None),
+    };
+
+    if headers.contains_key("transfer-encoding") {
+        return Ok(Err(Rejection::new(
+            411,
+            "Chunked bodies aren't supported; send Content-Length",
+        )));
+    }
+    let content_length = match headers.get("content-length") {
+        Some(value) => match value.parse::<usize>() {
+            Ok(length) => length,
+            Err(_) => return Ok(Err(Rejection::new(400, "Invalid Content-Length"))),
+        },
+        None => 0,
+    };
+    if content_length > max_body_bytes {
+        return Ok(Err(Rejection::new(
+            413,
+            format!("Request body over {} bytes", max_body_bytes),
+        )));
+    }
+    let mut body = vec![0; content_length];
+    let mut filled = 0;
+    while filled < content_length {
+        match tokio::time::timeout(read_timeout, reader.read(&mut body[filled..])).await {
+            Ok(Ok(0)) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(Ok(n)) => filled += n,
+            Ok(Err(e)) => return Err(e),
+            Err(_) => return Ok(Err(Rejection::new(408, "Timed out reading the request"))),
+        }
+    }
+
+    Ok(Ok(Request {
+        method,
+        path,
+        query,
+        headers,
+        body,
+    }))
+}
+
+/// Method, target and lowercased headers, or `None` if the head is malformed
+/// or longer than `MAX_HEAD_BYTES`
+async fn read_head<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+) -> io::Result<Option<(String, String, HashMap<String, String>)>> {
+    let mut reader = reader.take(MAX_HEAD_BYTES);
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).await? == 0 {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target), Some(version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Ok(None);
+    };
+    if !request_line.ends_with('\n') || !version.starts_with("HTTP/") {
+        return Ok(None);
+    }
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).await?;
+        // Cut off by the size limit or the connection closing
+        if !line.ends_with('\n') {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            return Ok(None);
+        };
+        headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+    }
+    Ok(Some((method.to_string(), target.to_string(), headers)))
+}
+
+/// Write a complete response with `Content-Length` and `Connection: close`,
+/// then shut the stream down
+pub(crate) async fn write_response<W: AsyncWrite + Unpin>(
+    stream: &mut W,
+    status: u16,
+    headers: &[(&str, &str)],
+    body: &[u8],
+) -> io::Result<()> {
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        status,
+        reason_phrase(status),
+        body.len()
+    );
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body).await?;
+    stream.shutdown().await
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        202 => "Accepted",
+        204 => "No Content",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::BufReader;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    async fn rejection(raw: &[u8], max_body_bytes: usize) -> u16 {
+        read_request(&mut &raw[..], max_body_bytes, TIMEOUT)
+            .await
+            .unwrap()
+            .unwrap_err()
+            .status
+    }
+
+    #[tokio::test]
+    async fn test_read_request() {
+        let raw = b"POST /images?x=1 HTTP/1.1\r\nHost: a\r\nContent-Length: 4\r\n\r\n{}\r\n";
+        let request = read_request(&mut &raw[..], 1024, TIMEOUT)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/images");
+        assert_eq!(request.query.as_deref(), Some("x=1"));
+        assert_eq!(request.headers["host"], "a");
+        assert_eq!(request.body, b"{}\r\n");
+
+        assert_eq!(rejection(raw, 3).await, 413);
+        assert_eq!(
+            rejection(
+                b"POST /images HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n",
+                1024
+            )
+            .await,
+            411
+        );
+        for raw in [
+            &b"GET\r\n\r\n"[..],
+            b"GET /jobs/1 HTTP/1.1\r\nno colon\r\n\r\n",
+            b"GET /jobs/1 HTTP/1.1\r\nHost: a\r\n",
+            b"POST /images HTTP/1.1\r\nContent-Length: lots\r\n\r\n",
+        ] {
+            assert_eq!(rejection(raw, 1024).await, 400);
+        }
+        assert!(read_request(&mut &b""[..], 1024, TIMEOUT).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_request_limits_head_size() {
+        let mut raw = b"GET /jobs/1 HTTP/1.1\r\nX-Big: ".to_vec();
+        raw.resize(MAX_HEAD_BYTES as usize + 10, b'a');
+        raw.extend_from_slice(b"\r\n\r\n");
+        assert_eq!(rejection(&raw, 1024).await, 400);
+    }
+
+    #[tokio::test]
+    async fn test_read_request_times_out_on_slow_clients() {
+        let timeout = Duration::from_millis(50);
+        let (mut client, server) = tokio::io::duplex(1024);
+        client.write_all(b"GET /jobs/1 HTTP/1.1\r\n").await.unwrap();
+        let rejection = read_request(&mut BufReader::new(server), 1024, timeout)
+            .await
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(rejection.status, 408);
+
+        let (mut client, server) = tokio::io::duplex(1024);
+        client
+            .write_all(b"POST /images HTTP/1.1\r\nContent-Length: 10\r\n\r\n{}")
+            .await
+            .unwrap();
+        let rejection = read_request(&mut BufReader::new(server), 1024, timeout)
+            .await
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(rejection.status, 408);
+    }
+
+    #[tokio::test]
+    async fn test_write_response() {
+        let mut out = Vec::new();
+        write_response(&mut out, 404, &[("Content-Type", "text/plain")], b"gone")
+            .await
+            .unwrap();
+        assert_eq!(
+            out,
+            b"HTTP/1.1 404 Not Found\r\nContent-Length: 4\r\nConnection: close\r\nContent-Type: text/plain\r\n\r\ngone"
+        );
+    }
+}
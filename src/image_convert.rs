@@ -0,0 +1,545 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::str::FromStr;
+use thiserror::Error;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tracing::debug;
+
+use crate::error::ErrorKind;
+use crate::imagen_api::GeneratedImage;
+
+pub const DEFAULT_QUALITY: u8 = 90;
+
+#[derive(Debug, Error)]
+pub enum ImageConvertError {
+    #[error("Failed to execute ffmpeg. Is ffmpeg installed? ({0})")]
+    FfmpegNotFound(std::io::Error),
+
+    #[error("ffmpeg image conversion failed: {0}")]
+    FfmpegFailed(String),
+
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Invalid quality: {0}. Must be between 1 and 100")]
+    InvalidQuality(u8),
+}
+
+pub type Result<T> = std::result::Result<T, ImageConvertError>;
+
+impl ImageConvertError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            ImageConvertError::InvalidQuality(_) => ErrorKind::InvalidInput,
+            ImageConvertError::FfmpegNotFound(_)
+            | ImageConvertError::FfmpegFailed(_)
+            | ImageConvertError::IoError(_) => ErrorKind::Io,
+        }
+    }
+}
+
+/// Target formats for post-generation conversion
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Jpg,
+    Webp,
+}
+
+impl ImageFormat {
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "image/png",
+            ImageFormat::Jpg => "image/jpeg",
+            ImageFormat::Webp => "image/webp",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpg => "jpg",
+            ImageFormat::Webp => "webp",
+        }
+    }
+
+    /// Format matching a MIME type, if it is one we can encode
+    pub fn from_mime_type(mime_type: &str) -> Option<Self> {
+        match mime_type {
+            "image/png" => Some(ImageFormat::Png),
+            "image/jpeg" | "image/jpg" => Some(ImageFormat::Jpg),
+            "image/webp" => Some(ImageFormat::Webp),
+            _ => None,
+        }
+    }
+
+    /// ffmpeg codec and muxer arguments for this format at the given quality (1-100)
+    fn ffmpeg_args(&self, quality: u8) -> Vec<String> {
+        match self {
+            ImageFormat::Png => vec![
+                "-c:v".to_string(),
+                "png".to_string(),
+                "-f".to_string(),
+                "image2pipe".to_string(),
+            ],
+            ImageFormat::Jpg => {
+                // mjpeg uses -q:v 2 (best) .. 31 (worst)
+                let qscale = 31 - (u32::from(quality) - 1) * 29 / 99;
+                vec![
+                    "-c:v".to_string(),
+                    "mjpeg".to_string(),
+                    "-q:v".to_string(),
+                    qscale.to_string(),
+                    "-f".to_string(),
+                    "image2pipe".to_string(),
+                ]
+            }
+            ImageFormat::Webp => vec![
+                "-c:v".to_string(),
+                "libwebp".to_string(),
+                "-quality".to_string(),
+                quality.to_string(),
+                "-f".to_string(),
+                "webp".to_string(),
+            ],
+        }
+    }
+}
+
+impl fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.extension())
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "png" => Ok(ImageFormat::Png),
+            "jpg" | "jpeg" => Ok(ImageFormat::Jpg),
+            "webp" => Ok(ImageFormat::Webp),
+            _ => Err(format!(
+                "Invalid image format: {}. Use png, jpg, or webp",
+                s
+            )),
+        }
+    }
+}
+
+/// Read pixel dimensions from a PNG, JPEG, or WebP header
+pub fn image_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    let be16 = |i: usize| -> Option<u32> {
+        Some(u32::from(u16::from_be_bytes(
+            data.get(i..i + 2)?.try_into().ok()?,
+        )))
+    };
+    let le16 = |i: usize| -> Option<u32> {
+        Some(u32::from(u16::from_le_bytes(
+            data.get(i..i + 2)?.try_into().ok()?,
+        )))
+    };
+    let le24 = |i: usize| -> Option<u32> {
+        let b = data.get(i..i + 3)?;
+        Some(u32::from(b[0]) | u32::from(b[1]) << 8 | u32::from(b[2]) << 16)
+    };
+
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        let width = u32::from_be_bytes(data.get(16..20)?.try_into().ok()?);
+        let height = u32::from_be_bytes(data.get(20..24)?.try_into().ok()?);
+        return Some((width, height));
+    }
+
+    if data.starts_with(&[0xFF, 0xD8]) {
+        let mut i = 2;
+        while i + 4 <= data.len() {
+            if data[i] != 0xFF {
+                return None;
+            }
+            let marker = data[i + 1];
+            match marker {
+                // Fill byte
+                0xFF => i += 1,
+                // Standalone markers without a length
+                0x01 | 0xD0..=0xD9 => i += 2,
+                // Start-of-frame markers (excluding DHT, JPG and DAC)
+                0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                    return Some((be16(i + 7)?, be16(i + 5)?));
+                }
+                _ => i += 2 + be16(i + 2)? as usize,
+            }
+        }
+        return None;
+    }
+
+    if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP") {
+        return match data.get(12..16)? {
+            b"VP8X" => Some((le24(24)? + 1, le24(27)? + 1)),
+            b"VP8L" => {
+                let b = data.get(21..25)?;
+                let width = 1 + (u32::from(b[0]) | (u32::from(b[1]) & 0x3F) << 8);
+                let height = 1
+                    + (u32::from(b[1]) >> 6
+                        | u32::from(b[2]) << 2
+                        | (u32::from(b[3]) & 0x0F) << 10);
+                Some((width, height))
+            }
+            b"VP8 " => Some((le16(26)? & 0x3FFF, le16(28)? & 0x3FFF)),
+            _ => None,
+        };
+    }
+
+    None
+}
+
+/// Layout of a before/after comparison composite
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CompareLayout {
+    /// Before and after next to each other, scaled to the same height
+    #[default]
+    SideBySide,
+    /// Before above after, scaled to the same width (slider-friendly)
+    Stacked,
+}
+
+impl CompareLayout {
+    fn filter_graph(&self) -> &'static str {
+        match self {
+            CompareLayout::SideBySide => {
+                "[0:v][1:v]scale2ref=w=oh*mdar:h=ih[before][after];\
+                 [before]format=rgba[b];[after]format=rgba[a];[b][a]hstack=inputs=2"
+            }
+            CompareLayout::Stacked => {
+                "[0:v][1:v]scale2ref=w=iw:h=ow/mdar[before][after];\
+                 [before]format=rgba[b];[after]format=rgba[a];[b][a]vstack=inputs=2"
+            }
+        }
+    }
+}
+
+impl fmt::Display for CompareLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompareLayout::SideBySide => write!(f, "side"),
+            CompareLayout::Stacked => write!(f, "stacked"),
+        }
+    }
+}
+
+impl FromStr for CompareLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "side" | "side-by-side" | "horizontal" => Ok(CompareLayout::SideBySide),
+            "stacked" | "stack" | "vertical" => Ok(CompareLayout::Stacked),
+            _ => Err(format!(
+                "Invalid compare layout: {}. Use 'side' or 'stacked'",
+                s
+            )),
+        }
+    }
+}
+
+/// Path of the comparison composite saved next to an output image: `<stem>-compare.png`
+pub fn comparison_path(output: &Path) -> PathBuf {
+    let stem = output
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "image".to_string());
+    output.with_file_name(format!("{}-compare.png", stem))
+}
+
+/// Save a before/after composite of two image files to `output`
+pub async fn compose_comparison(
+    before: &Path,
+    after: &Path,
+    output: &Path,
+    layout: CompareLayout,
+) -> Result<()> {
+    debug!(
+        "Composing {} comparison of {:?} and {:?} into {:?}",
+        layout, before, after, output
+    );
+
+    let result = Command::new("ffmpeg")
+        .args(["-hide_banner", "-loglevel", "error", "-y", "-i"])
+        .arg(before)
+        .arg("-i")
+        .arg(after)
+        .args(["-filter_complex", layout.filter_graph(), "-frames:v", "1"])
+        .arg(output)
+        .output()
+        .await
+        .map_err(ImageConvertError::FfmpegNotFound)?;
+
+    if !result.status.success() {
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        return Err(ImageConvertError::FfmpegFailed(stderr.trim().to_string()));
+    }
+
+    Ok(())
+}
+
+/// Run raw image bytes through ffmpeg, returning the converted bytes
+async fn run_ffmpeg(data: &[u8], args: &[String]) -> Result<Vec<u8>> {
+    let mut child = Command::new("ffmpeg")
+        .args(["-hide_banner", "-loglevel", "error", "-i", "pipe:0"])
+        .args(args)
+        .args(["-frames:v", "1", "pipe:1"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(ImageConvertError::FfmpegNotFound)?;
+
+    // Feed stdin from a separate task so a full stdout pipe can't deadlock us
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| ImageConvertError::FfmpegFailed("Failed to open ffmpeg stdin".into()))?;
+    let input = data.to_vec();
+    let writer = tokio::spawn(async move {
+        stdin.write_all(&input).await?;
+        stdin.shutdown().await
+    });
+
+    let output = child.wait_with_output().await?;
+    writer
+        .await
+        .map_err(|e| ImageConvertError::FfmpegFailed(e.to_string()))??;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(ImageConvertError::FfmpegFailed(stderr.trim().to_string()));
+    }
+
+    Ok(output.stdout)
+}
+
+/// Difference hash of a 9x8 grayscale thumbnail (row-major, one byte per
+/// pixel): bit `8 * row + col` is set when a pixel is brighter than its right
+/// neighbor. Near-identical images get hashes a few bits apart.
+pub fn difference_hash(gray: &[u8]) -> Option<u64> {
+    if gray.len() != 72 {
+        return None;
+    }
+    let mut hash = 0u64;
+    for (row, pixels) in gray.chunks(9).enumerate() {
+        for col in 0..8 {
+            if pixels[col] > pixels[col + 1] {
+                hash |= 1 << (8 * row + col);
+            }
+        }
+    }
+    Some(hash)
+}
+
+/// Number of bits two perceptual hashes differ in
+pub fn hash_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Perceptual hash of an image, from a 9x8 grayscale thumbnail ffmpeg scales
+/// it down to; survives re-encoding, resizing and small edits
+pub async fn perceptual_hash(data: &[u8]) -> Result<u64> {
+    let args = [
+        "-vf",
+        "scale=9:8:flags=area",
+        "-pix_fmt",
+        "gray",
+        "-f",
+        "rawvideo",
+    ]
+    .map(String::from);
+    let gray = run_ffmpeg(data, &args).await?;
+    difference_hash(&gray).ok_or_else(|| {
+        ImageConvertError::FfmpegFailed(format!(
+            "expected a 9x8 thumbnail, got {} bytes",
+            gray.len()
+        ))
+    })
+}
+
+/// Scale and center-crop an image to exactly `width`x`height`, keeping its format
+pub async fn resize_image(
+    image: &GeneratedImage,
+    width: u32,
+    height: u32,
+) -> Result<GeneratedImage> {
+    let format = ImageFormat::from_mime_type(&image.mime_type).unwrap_or(ImageFormat::Png);
+    debug!("Resizing {} image to {}x{}", image.mime_type, width, height);
+
+    let mut args = vec![
+        "-vf".to_string(),
+        format!(
+            "scale={w}:{h}:force_original_aspect_ratio=increase,crop={w}:{h}",
+            w = width,
+            h = height
+        ),
+    ];
+    args.extend(format.ffmpeg_args(DEFAULT_QUALITY));
+
+    let data = run_ffmpeg(&image.data, &args).await?;
+
+    Ok(GeneratedImage {
+        data,
+        mime_type: format.mime_type().to_string(),
+        provenance: image.provenance.clone(),
+    })
+}
+
+/// Convert an image to the target format with the given quality (1-100)
+///
+/// Images already in the target format are returned unchanged when no
+/// quality was requested. Quality only affects lossy formats (jpg, webp).
+pub async fn convert_image(
+    image: &GeneratedImage,
+    format: ImageFormat,
+    quality: Option<u8>,
+) -> Result<GeneratedImage> {
+    if let Some(q) = quality
+        && !(1..=100).contains(&q)
+    {
+        return Err(ImageConvertError::InvalidQuality(q));
+    }
+
+    if quality.is_none() && image.mime_type == format.mime_type() {
+        return Ok(image.clone());
+    }
+
+    let quality = quality.unwrap_or(DEFAULT_QUALITY);
+    debug!(
+        "Converting image from {} to {} (quality {})",
+        image.mime_type, format, quality
+    );
+
+    let data = run_ffmpeg(&image.data, &format.ffmpeg_args(quality)).await?;
+
+    Ok(GeneratedImage {
+        data,
+        mime_type: format.mime_type().to_string(),
+        provenance: image.provenance.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_format_from_str() {
+        assert_eq!(ImageFormat::from_str("png").unwrap(), ImageFormat::Png);
+        assert_eq!(ImageFormat::from_str("jpg").unwrap(), ImageFormat::Jpg);
+        assert_eq!(ImageFormat::from_str("JPEG").unwrap(), ImageFormat::Jpg);
+        assert_eq!(ImageFormat::from_str("webp").unwrap(), ImageFormat::Webp);
+        assert!(ImageFormat::from_str("bmp").is_err());
+    }
+
+    #[test]
+    fn test_image_format_mime_and_extension() {
+        assert_eq!(ImageFormat::Jpg.mime_type(), "image/jpeg");
+        assert_eq!(ImageFormat::Jpg.extension(), "jpg");
+        assert_eq!(ImageFormat::Webp.mime_type(), "image/webp");
+        assert_eq!(ImageFormat::Png.extension(), "png");
+    }
+
+    #[test]
+    fn test_jpeg_quality_mapping() {
+        let best = ImageFormat::Jpg.ffmpeg_args(100);
+        assert_eq!(best[3], "2");
+        let worst = ImageFormat::Jpg.ffmpeg_args(1);
+        assert_eq!(worst[3], "31");
+    }
+
+    #[test]
+    fn test_compare_layout_from_str() {
+        assert_eq!(
+            CompareLayout::from_str("side").unwrap(),
+            CompareLayout::SideBySide
+        );
+        assert_eq!(
+            CompareLayout::from_str("stacked").unwrap(),
+            CompareLayout::Stacked
+        );
+        assert!(CompareLayout::from_str("diagonal").is_err());
+    }
+
+    #[test]
+    fn test_comparison_path() {
+        assert_eq!(
+            comparison_path(Path::new("output/photo-abc123.jpg")),
+            PathBuf::from("output/photo-abc123-compare.png")
+        );
+    }
+
+    #[test]
+    fn test_image_dimensions_png() {
+        let mut data = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        data.extend_from_slice(&1920u32.to_be_bytes());
+        data.extend_from_slice(&1080u32.to_be_bytes());
+        assert_eq!(image_dimensions(&data), Some((1920, 1080)));
+    }
+
+    #[test]
+    fn test_image_dimensions_jpeg() {
+        let data = [
+            0xFF, 0xD8, // SOI
+            0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, // APP0 with 2 bytes of payload
+            0xFF, 0xC0, 0x00, 0x11, 0x08, // SOF0, precision 8
+            0x0B, 0xD0, // height 3024
+            0x0F, 0xC0, // width 4032
+        ];
+        assert_eq!(image_dimensions(&data), Some((4032, 3024)));
+    }
+
+    #[test]
+    fn test_image_dimensions_webp_vp8x() {
+        let mut data = b"RIFF\0\0\0\0WEBPVP8X\0\0\0\0\0\0\0\0".to_vec();
+        data.extend_from_slice(&[0x7F, 0x07, 0x00]); // width - 1 = 1919
+        data.extend_from_slice(&[0x37, 0x04, 0x00]); // height - 1 = 1079
+        assert_eq!(image_dimensions(&data), Some((1920, 1080)));
+    }
+
+    #[test]
+    fn test_image_dimensions_unknown() {
+        assert_eq!(image_dimensions(b"GIF89a"), None);
+        assert_eq!(image_dimensions(&[0xFF, 0xD8]), None);
+    }
+
+    #[test]
+    fn test_difference_hash() {
+        // Brightness falling left to right sets every bit
+        let falling: Vec<u8> = (0..8).flat_map(|_| (0..9).rev().map(|x| x * 20)).collect();
+        assert_eq!(difference_hash(&falling), Some(u64::MAX));
+        let rising: Vec<u8> = (0..8).flat_map(|_| (0..9).map(|x| x * 20)).collect();
+        assert_eq!(difference_hash(&rising), Some(0));
+        assert_eq!(difference_hash(&[0; 64]), None);
+        assert_eq!(hash_distance(0b1011, 0b0110), 3);
+    }
+
+    #[tokio::test]
+    async fn test_convert_image_rejects_invalid_quality() {
+        let image = GeneratedImage {
+            data: vec![],
+            mime_type: "image/png".to_string(),
+            provenance: None,
+        };
+        let result = convert_image(&image, ImageFormat::Jpg, Some(0)).await;
+        assert!(matches!(result, Err(ImageConvertError::InvalidQuality(0))));
+    }
+
+    #[tokio::test]
+    async fn test_convert_image_same_format_is_noop() {
+        let image = GeneratedImage {
+            data: vec![1, 2, 3],
+            mime_type: "image/png".to_string(),
+            provenance: None,
+        };
+        let result = convert_image(&image, ImageFormat::Png, None).await.unwrap();
+        assert_eq!(result.data, vec![1, 2, 3]);
+    }
+}
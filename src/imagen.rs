@@ -1,18 +1,23 @@
 use anyhow::{Context, Result};
 use clap::Parser;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use serde::Deserialize;
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::fs;
+use tokio::io::AsyncWriteExt;
 use tokio::sync::Semaphore;
-use tracing::{Level, debug};
-use tracing_subscriber::FmtSubscriber;
+use tracing::{Instrument, debug, info_span};
 
+use transcript_tool::cli::{self, BatchLimitArgs, CommonArgs, init_logging};
 use transcript_tool::imagen_api::{AspectRatio, ImageGenConfig, ImageModel, ImageSize};
-use transcript_tool::{GeneratedImage, ImagenClient, ImagenClientConfig};
+use transcript_tool::storage::{OutputStorage, Storage};
+use transcript_tool::{
+    Budget, CircuitBreaker, Clients, GeneratedImage, ImageFormat, ImagenClient, ImagenClientConfig,
+    ResponseCache, check_image, convert_image,
+};
 
 #[derive(Parser, Debug)]
 #[command(name = "imagen")]
@@ -22,9 +27,13 @@ use transcript_tool::{GeneratedImage, ImagenClient, ImagenClientConfig};
     imagen \"A sunset over mountains\"
     imagen -m 3pro \"A futuristic cityscape\"
     imagen -m 3pro --size 2K --aspect 16:9 \"Wide panorama\"
+    imagen --format webp --quality 80 \"A web banner\"
     imagen --yaml prompts.yaml
-    imagen --yaml prompts.yaml --name memory-safety")]
-struct Args {
+    imagen --yaml prompts.yaml --name memory-safety
+    imagen --yaml prompts.yaml -o s3://my-bucket/images/
+    imagen --metadata \"A cat\"
+    imagen \"A cat\" -o - | some-viewer")]
+pub struct Args {
     /// Text prompt for image generation (positional argument)
     #[arg(value_name = "PROMPT")]
     prompt: Option<String>,
@@ -37,41 +46,57 @@ struct Args {
     #[arg(short = 'n', long)]
     name: Option<String>,
 
-    /// Output file path (for single prompt) or directory (for YAML batch)
+    /// Output file path (for single prompt, `-` for stdout) or directory (for YAML batch);
+    /// s3:// and gs:// URIs write to object storage
     #[arg(short, long)]
-    output: Option<PathBuf>,
+    output: Option<String>,
 
     /// Gemini model to use: 2.5-flash (default), 3pro
-    #[arg(short = 'm', long, default_value = "2.5-flash")]
-    model: String,
+    #[arg(short = 'm', long, env = "FLASHECHO_IMAGE_MODEL")]
+    model: Option<String>,
 
     /// Image size (Gemini 3 Pro only): 1K, 2K, 4K
     #[arg(short = 's', long)]
     size: Option<String>,
 
-    /// Aspect ratio (Gemini 3 Pro only): 1:1, 16:9, 9:16, 4:3, 3:4
+    /// Aspect ratio: 1:1, 16:9, 9:16, 4:3, 3:4, 5:4, 4:5, 3:2, 2:3, 21:9
     #[arg(short = 'a', long)]
     aspect: Option<String>,
 
+    /// Convert generated images to this format before saving: png, jpg, webp
+    #[arg(short = 'f', long, env = "FLASHECHO_IMAGE_FORMAT")]
+    format: Option<String>,
+
+    /// Quality for lossy conversion (1-100, requires --format)
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..=100))]
+    quality: Option<u8>,
+
     /// API timeout in seconds
     #[arg(short, long, default_value = "120")]
     timeout: u64,
 
-    /// Max retry attempts for API calls
-    #[arg(long, default_value = "3")]
-    max_retries: u32,
-
     /// Number of parallel jobs for YAML batch mode
     #[arg(short = 'j', long, default_value = "2")]
     jobs: usize,
 
-    /// Verbosity level (-v, -vv, -vvv)
-    #[arg(short, long, action = clap::ArgAction::Count)]
-    verbose: u8,
+    /// Print results as a JSON array to stdout (for scripts and CI)
+    #[arg(long)]
+    json: bool,
 
-    /// Quiet mode (no progress output)
-    #[arg(short, long)]
-    quiet: bool,
+    /// Write a `<image>.json` sidecar recording that each image is AI-generated,
+    /// with its model version and watermark
+    #[arg(long)]
+    metadata: bool,
+
+    /// Also write a `<image>.blake3` checksum sidecar, checked by `verify`
+    #[arg(long)]
+    checksums: bool,
+
+    #[command(flatten)]
+    common: CommonArgs,
+
+    #[command(flatten)]
+    limits: BatchLimitArgs,
 }
 
 /// YAML file structure for batch prompts
@@ -89,36 +114,10 @@ struct PromptEntry {
     model: Option<String>,
     /// Image size (Gemini 3 Pro only)
     size: Option<String>,
-    /// Aspect ratio (Gemini 3 Pro only)
+    /// Aspect ratio
     aspect: Option<String>,
 }
 
-fn get_api_key() -> Result<String> {
-    std::env::var("GEMINI_API_KEY")
-        .or_else(|_| std::env::var("GOOGLE_AI_KEY"))
-        .context("GEMINI_API_KEY or GOOGLE_AI_KEY environment variable is not set")
-}
-
-fn init_logging(verbose: u8) {
-    let level = match verbose {
-        0 => Level::WARN,
-        1 => Level::INFO,
-        2 => Level::DEBUG,
-        _ => Level::TRACE,
-    };
-
-    let subscriber = FmtSubscriber::builder()
-        .with_max_level(level)
-        .with_target(false)
-        .with_thread_ids(false)
-        .with_file(false)
-        .with_line_number(false)
-        .compact()
-        .finish();
-
-    tracing::subscriber::set_global_default(subscriber).ok();
-}
-
 fn parse_model(model_str: &str) -> Result<ImageModel> {
     ImageModel::from_str(model_str).map_err(|e| anyhow::anyhow!("{}", e))
 }
@@ -131,6 +130,24 @@ fn parse_aspect(aspect_str: &str) -> Result<AspectRatio> {
     AspectRatio::from_str(aspect_str).map_err(|e| anyhow::anyhow!("{}", e))
 }
 
+fn parse_format(format_str: &str) -> Result<ImageFormat> {
+    ImageFormat::from_str(format_str).map_err(|e| anyhow::anyhow!("{}", e))
+}
+
+/// Convert the image to the requested output format, if any
+async fn apply_format(
+    image: GeneratedImage,
+    format: Option<ImageFormat>,
+    quality: Option<u8>,
+) -> Result<GeneratedImage> {
+    match format {
+        Some(format) => convert_image(&image, format, quality)
+            .await
+            .map_err(|e| anyhow::anyhow!("Image conversion failed: {}", e)),
+        None => Ok(image),
+    }
+}
+
 fn build_gen_config(
     size: Option<&String>,
     aspect: Option<&String>,
@@ -180,30 +197,70 @@ fn generate_output_filename(name: &str, prompt: &str, extension: &str) -> String
     format!("{}-{}.{}", slug, hash_prefix, extension)
 }
 
-async fn save_image(image: &GeneratedImage, path: &PathBuf) -> Result<()> {
-    // Ensure parent directory exists
-    if let Some(parent) = path.parent()
-        && !parent.exists()
-    {
-        fs::create_dir_all(parent)
+/// Write the image to `key` once it checks out as a whole image (and its
+/// metadata and checksum sidecars if asked), returning its path or URI
+async fn save_image(
+    image: &GeneratedImage,
+    storage: &OutputStorage,
+    key: &str,
+    metadata: bool,
+    checksums: bool,
+) -> Result<String> {
+    check_image(&image.data).context("The API returned a damaged image")?;
+    storage
+        .put(key, &image.data)
+        .await
+        .context("Failed to write image file")?;
+    if metadata && let Some(sidecar) = image.sidecar_json() {
+        storage
+            .put(&format!("{}.json", key), sidecar.as_bytes())
+            .await
+            .context("Failed to write metadata sidecar")?;
+    }
+    if checksums {
+        storage
+            .put_checksum(key, &image.data)
             .await
-            .context("Failed to create output directory")?;
+            .context("Failed to write checksum sidecar")?;
     }
+    Ok(storage.location(key))
+}
 
-    fs::write(path, &image.data)
-        .await
-        .context("Failed to write image file")?;
+/// Check whether an output path means "write to stdout"
+fn is_stdout_path(path: &str) -> bool {
+    path == "-"
+}
 
+/// Write raw image bytes to stdout (for piping into other tools)
+async fn write_image_to_stdout(image: &GeneratedImage) -> Result<()> {
+    check_image(&image.data).context("The API returned a damaged image")?;
+    let mut stdout = tokio::io::stdout();
+    stdout
+        .write_all(&image.data)
+        .await
+        .context("Failed to write image to stdout")?;
+    stdout.flush().await.context("Failed to flush stdout")?;
     Ok(())
 }
 
+/// Where a single generated image goes
+enum SingleOutput {
+    Stdout,
+    Storage(OutputStorage, String),
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn generate_single(
     client: &ImagenClient,
     prompt: &str,
-    output_path: PathBuf,
+    output: SingleOutput,
     gen_config: Option<&ImageGenConfig>,
+    format: Option<ImageFormat>,
+    quality: Option<u8>,
+    metadata: bool,
+    checksums: bool,
     quiet: bool,
-) -> Result<()> {
+) -> Result<String> {
     let pb = if !quiet {
         let pb = ProgressBar::new_spinner();
         pb.set_style(
@@ -227,40 +284,96 @@ async fn generate_single(
         pb.finish_with_message("Image generated!");
     }
 
+    let image = apply_format(image, format, quality).await?;
+
+    let (storage, key) = match output {
+        SingleOutput::Stdout => {
+            write_image_to_stdout(&image).await?;
+            return Ok("-".to_string());
+        }
+        SingleOutput::Storage(storage, key) => (storage, key),
+    };
+
     // Determine final output path with correct extension
-    let final_path = if output_path.extension().is_none() {
-        output_path.with_extension(image.extension())
+    let key = if Path::new(&key).extension().is_none() {
+        format!("{}.{}", key, image.extension())
     } else {
-        output_path
+        key
     };
 
-    save_image(&image, &final_path).await?;
-
-    Ok(())
+    save_image(&image, &storage, &key, metadata, checksums).await
 }
 
 struct YamlGenOptions {
-    api_key: String,
+    clients: Clients,
     yaml_path: PathBuf,
     name_filter: Option<String>,
-    output_dir: PathBuf,
+    output_dir: OutputStorage,
     default_model: ImageModel,
     default_size: Option<String>,
     default_aspect: Option<String>,
+    format: Option<ImageFormat>,
+    quality: Option<u8>,
     timeout: u64,
     max_retries: u32,
+    cache: Option<ResponseCache>,
+    circuit_breaker: Option<CircuitBreaker>,
+    budget: Budget,
     jobs: usize,
     quiet: bool,
+    json: bool,
+    metadata: bool,
+    checksums: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum GenStatus {
+    Success,
+    Failed,
 }
 
 /// Result of a single image generation task
+#[derive(Debug, Serialize)]
 struct GenResult {
     name: String,
-    success: bool,
+    prompt: String,
+    output_path: Option<String>,
+    status: GenStatus,
     error: Option<String>,
 }
 
+impl GenResult {
+    fn success(name: &str, prompt: &str, output_path: String) -> Self {
+        Self {
+            name: name.to_string(),
+            prompt: prompt.to_string(),
+            output_path: Some(output_path),
+            status: GenStatus::Success,
+            error: None,
+        }
+    }
+
+    fn failed(name: &str, prompt: &str, error: String) -> Self {
+        Self {
+            name: name.to_string(),
+            prompt: prompt.to_string(),
+            output_path: None,
+            status: GenStatus::Failed,
+            error: Some(error),
+        }
+    }
+}
+
+/// Print generation results as a JSON array to stdout
+fn print_json_results(results: &[GenResult]) -> Result<()> {
+    let json = serde_json::to_string_pretty(results).context("Failed to serialize results")?;
+    println!("{}", json);
+    Ok(())
+}
+
 async fn generate_from_yaml(opts: YamlGenOptions) -> Result<()> {
+    let spent_before = (opts.budget.spent_usd(), opts.budget.spent_tokens());
     let yaml_content = fs::read_to_string(&opts.yaml_path)
         .await
         .context("Failed to read YAML file")?;
@@ -286,17 +399,10 @@ async fn generate_from_yaml(opts: YamlGenOptions) -> Result<()> {
         }
     }
 
-    // Ensure output directory exists
-    if !opts.output_dir.exists() {
-        fs::create_dir_all(&opts.output_dir)
-            .await
-            .context("Failed to create output directory")?;
-    }
-
     let total = prompts.len();
     let jobs = opts.jobs.max(1);
 
-    if !opts.quiet {
+    if !opts.quiet && !opts.json {
         println!(
             "Generating {} images with {} parallel jobs...\n",
             total, jobs
@@ -318,129 +424,128 @@ async fn generate_from_yaml(opts: YamlGenOptions) -> Result<()> {
         let opts = Arc::clone(&opts);
         let mp = Arc::clone(&multi_progress);
 
-        let handle = tokio::spawn(async move {
-            // Acquire semaphore permit
-            let _permit = sem.acquire().await.unwrap();
-
-            // Create progress bar for this task
-            let pb = if !opts.quiet {
-                let pb = mp.add(ProgressBar::new_spinner());
-                pb.set_style(
-                    ProgressStyle::default_spinner()
-                        .template("{spinner:.green} [{pos}] {msg}")
-                        .unwrap(),
-                );
-                pb.set_position((i + 1) as u64);
-                pb.set_message(format!("Generating {}...", entry.name));
-                pb.enable_steady_tick(Duration::from_millis(100));
-                Some(pb)
-            } else {
-                None
-            };
-
-            // Determine model for this entry
-            let model = if let Some(model_str) = &entry.model {
-                match parse_model(model_str) {
-                    Ok(m) => m,
+        let span = info_span!("entry", entry = %entry.name);
+        let handle = tokio::spawn(
+            async move {
+                // Acquire semaphore permit
+                let _permit = sem.acquire().await.unwrap();
+
+                // Create progress bar for this task
+                let pb = if !opts.quiet {
+                    let pb = mp.add(ProgressBar::new_spinner());
+                    pb.set_style(
+                        ProgressStyle::default_spinner()
+                            .template("{spinner:.green} [{pos}] {msg}")
+                            .unwrap(),
+                    );
+                    pb.set_position((i + 1) as u64);
+                    pb.set_message(format!("Generating {}...", entry.name));
+                    pb.enable_steady_tick(Duration::from_millis(100));
+                    Some(pb)
+                } else {
+                    None
+                };
+
+                // Determine model for this entry
+                let model = if let Some(model_str) = &entry.model {
+                    match parse_model(model_str) {
+                        Ok(m) => m,
+                        Err(e) => {
+                            if let Some(pb) = pb {
+                                pb.finish_with_message(format!("{} failed!", entry.name));
+                            }
+                            return GenResult::failed(&entry.name, &entry.prompt, e.to_string());
+                        }
+                    }
+                } else {
+                    opts.default_model
+                };
+
+                // Build client on the shared connection pool
+                let config = ImagenClientConfig {
+                    timeout_secs: opts.timeout,
+                    max_retries: opts.max_retries,
+                    model,
+                    cache: opts.cache.clone(),
+                    circuit_breaker: opts.circuit_breaker.clone(),
+                    budget: Some(opts.budget.clone()),
+                    ..Default::default()
+                };
+                let client = opts.clients.imagen(config);
+
+                // Build gen config
+                let size = entry.size.as_ref().or(opts.default_size.as_ref());
+                let aspect = entry.aspect.as_ref().or(opts.default_aspect.as_ref());
+                let gen_config = match build_gen_config(size, aspect) {
+                    Ok(c) => c,
                     Err(e) => {
                         if let Some(pb) = pb {
                             pb.finish_with_message(format!("{} failed!", entry.name));
                         }
-                        return GenResult {
-                            name: entry.name.clone(),
-                            success: false,
-                            error: Some(e.to_string()),
-                        };
-                    }
-                }
-            } else {
-                opts.default_model
-            };
-
-            // Build client
-            let config = ImagenClientConfig {
-                timeout_secs: opts.timeout,
-                max_retries: opts.max_retries,
-                model,
-            };
-            let client = match ImagenClient::with_config(opts.api_key.clone(), config) {
-                Ok(c) => c,
-                Err(e) => {
-                    if let Some(pb) = pb {
-                        pb.finish_with_message(format!("{} failed!", entry.name));
-                    }
-                    return GenResult {
-                        name: entry.name.clone(),
-                        success: false,
-                        error: Some(e.to_string()),
-                    };
-                }
-            };
-
-            // Build gen config
-            let size = entry.size.as_ref().or(opts.default_size.as_ref());
-            let aspect = entry.aspect.as_ref().or(opts.default_aspect.as_ref());
-            let gen_config = match build_gen_config(size, aspect) {
-                Ok(c) => c,
-                Err(e) => {
-                    if let Some(pb) = pb {
-                        pb.finish_with_message(format!("{} failed!", entry.name));
+                        return GenResult::failed(&entry.name, &entry.prompt, e.to_string());
                     }
-                    return GenResult {
-                        name: entry.name.clone(),
-                        success: false,
-                        error: Some(e.to_string()),
-                    };
-                }
-            };
-
-            // Generate image
-            match client
-                .generate_image_with_config(&entry.prompt, gen_config.as_ref())
-                .await
-            {
-                Ok(image) => {
-                    // Determine output filename
-                    let filename = entry.output.clone().unwrap_or_else(|| {
-                        generate_output_filename(&entry.name, &entry.prompt, image.extension())
-                    });
-                    let output_path = opts.output_dir.join(&filename);
-
-                    match save_image(&image, &output_path).await {
-                        Ok(()) => {
-                            if let Some(pb) = pb {
-                                pb.finish_with_message(format!("{} -> {}", entry.name, filename));
-                            }
-                            GenResult {
-                                name: entry.name.clone(),
-                                success: true,
-                                error: None,
+                };
+
+                // Generate image
+                match client
+                    .generate_image_with_config(&entry.prompt, gen_config.as_ref())
+                    .await
+                {
+                    Ok(image) => {
+                        let image = match apply_format(image, opts.format, opts.quality).await {
+                            Ok(image) => image,
+                            Err(e) => {
+                                if let Some(pb) = pb {
+                                    pb.finish_with_message(format!("{} failed!", entry.name));
+                                }
+                                return GenResult::failed(
+                                    &entry.name,
+                                    &entry.prompt,
+                                    e.to_string(),
+                                );
                             }
-                        }
-                        Err(e) => {
-                            if let Some(pb) = pb {
-                                pb.finish_with_message(format!("{} failed!", entry.name));
+                        };
+
+                        // Determine output filename
+                        let filename = entry.output.clone().unwrap_or_else(|| {
+                            generate_output_filename(&entry.name, &entry.prompt, image.extension())
+                        });
+                        match save_image(
+                            &image,
+                            &opts.output_dir,
+                            &filename,
+                            opts.metadata,
+                            opts.checksums,
+                        )
+                        .await
+                        {
+                            Ok(output_path) => {
+                                if let Some(pb) = pb {
+                                    pb.finish_with_message(format!(
+                                        "{} -> {}",
+                                        entry.name, filename
+                                    ));
+                                }
+                                GenResult::success(&entry.name, &entry.prompt, output_path)
                             }
-                            GenResult {
-                                name: entry.name.clone(),
-                                success: false,
-                                error: Some(e.to_string()),
+                            Err(e) => {
+                                if let Some(pb) = pb {
+                                    pb.finish_with_message(format!("{} failed!", entry.name));
+                                }
+                                GenResult::failed(&entry.name, &entry.prompt, e.to_string())
                             }
                         }
                     }
-                }
-                Err(e) => {
-                    if let Some(pb) = pb {
-                        pb.finish_with_message(format!("{} failed!", entry.name));
-                    }
-                    GenResult {
-                        name: entry.name.clone(),
-                        success: false,
-                        error: Some(e.to_string()),
+                    Err(e) => {
+                        if let Some(pb) = pb {
+                            pb.finish_with_message(format!("{} failed!", entry.name));
+                        }
+                        GenResult::failed(&entry.name, &entry.prompt, e.to_string())
                     }
                 }
             }
-        });
+            .instrument(span),
+        );
 
         handles.push(handle);
     }
@@ -453,15 +558,22 @@ async fn generate_from_yaml(opts: YamlGenOptions) -> Result<()> {
         .collect();
 
     // Collect results
-    let success_count = results.iter().filter(|r| r.success).count();
+    let success_count = results
+        .iter()
+        .filter(|r| r.status == GenStatus::Success)
+        .count();
     let errors: Vec<_> = results
         .iter()
-        .filter(|r| !r.success)
+        .filter(|r| r.status == GenStatus::Failed)
         .map(|r| (r.name.clone(), r.error.clone().unwrap_or_default()))
         .collect();
 
+    if opts.json {
+        print_json_results(&results)?;
+    }
+
     // Summary
-    if !opts.quiet {
+    if !opts.quiet && !opts.json {
         println!("\n--- Summary ---");
         println!(
             "Total: {}, Success: {}, Failed: {}",
@@ -475,6 +587,9 @@ async fn generate_from_yaml(opts: YamlGenOptions) -> Result<()> {
                 println!("  - {}: {}", name, error);
             }
         }
+        if let Some(spend) = cli::spend_summary(&opts.budget, spent_before) {
+            println!("{}", spend);
+        }
     }
 
     if success_count == 0 && !errors.is_empty() {
@@ -484,11 +599,10 @@ async fn generate_from_yaml(opts: YamlGenOptions) -> Result<()> {
     Ok(())
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let args = Args::parse();
-
-    init_logging(args.verbose);
+/// Run with parsed arguments; shared by this binary and its `flashecho` subcommand
+pub async fn run(args: Args) -> Result<()> {
+    init_logging(args.common.verbose, args.common.log_format);
+    args.common.install_usage_ledger("imagen");
 
     // Validate arguments
     if args.prompt.is_none() && args.yaml.is_none() {
@@ -505,14 +619,42 @@ async fn main() -> Result<()> {
         anyhow::bail!("--name can only be used with --yaml");
     }
 
-    let api_key = get_api_key()?;
-    let model = parse_model(&args.model)?;
+    let to_stdout = args.output.as_deref().is_some_and(is_stdout_path);
+    if to_stdout && args.yaml.is_some() {
+        anyhow::bail!("-o - (stdout) can only be used with a single prompt, not --yaml");
+    }
+    if to_stdout && args.json {
+        anyhow::bail!("Cannot use --json when writing the image to stdout (-o -)");
+    }
+    if to_stdout && args.metadata {
+        anyhow::bail!("Cannot use --metadata when writing the image to stdout (-o -)");
+    }
+    if to_stdout && args.checksums {
+        anyhow::bail!("Cannot use --checksums when writing the image to stdout (-o -)");
+    }
+
+    let profile = args.common.load_profile()?;
+    let format = args.format.as_deref().or(profile.image_format.as_deref());
+    if args.quality.is_some() && format.is_none() {
+        anyhow::bail!("--quality can only be used with --format");
+    }
 
-    // Warn if size/aspect used with non-3pro model
-    if (args.size.is_some() || args.aspect.is_some()) && !model.supports_image_config() {
-        eprintln!(
-            "Warning: --size and --aspect are only supported with Gemini 3 Pro model (-m 3pro)"
-        );
+    let clients =
+        Clients::new(cli::gemini_api_key(&profile)?).context("Failed to create HTTP client")?;
+    let model_name = args.model.as_deref().or(profile.image_model.as_deref());
+    let model = parse_model(model_name.unwrap_or("2.5-flash"))?;
+    let cache = args.common.response_cache();
+    let breaker = args.limits.circuit_breaker();
+    let budget = args
+        .common
+        .tracking_budget()
+        .context("Failed to read usage ledger for --budget-since")?;
+    let spent_before = (budget.spent_usd(), budget.spent_tokens());
+    let format = format.map(parse_format).transpose()?;
+
+    // Warn if size used with non-3pro model
+    if args.size.is_some() && !model.supports_image_size() {
+        eprintln!("Warning: --size is only supported with Gemini 3 Pro model (-m 3pro)");
     }
 
     if let Some(yaml_path) = args.yaml {
@@ -521,54 +663,98 @@ async fn main() -> Result<()> {
             anyhow::bail!("YAML file does not exist: {:?}", yaml_path);
         }
 
-        let output_dir = args.output.unwrap_or_else(|| PathBuf::from("./output"));
+        let output_dir = args.output.as_deref().unwrap_or("./output");
         debug!("Output directory: {:?}", output_dir);
+        let output_dir = OutputStorage::open(output_dir).context("Invalid output directory")?;
 
         generate_from_yaml(YamlGenOptions {
-            api_key,
+            clients,
             yaml_path,
             name_filter: args.name,
             output_dir,
             default_model: model,
             default_size: args.size,
             default_aspect: args.aspect,
+            format,
+            quality: args.quality,
             timeout: args.timeout,
-            max_retries: args.max_retries,
+            max_retries: args.common.max_retries,
+            cache,
+            circuit_breaker: breaker,
+            budget,
             jobs: args.jobs,
-            quiet: args.quiet,
+            quiet: args.common.quiet,
+            json: args.json,
+            metadata: args.metadata,
+            checksums: args.checksums,
         })
         .await?;
     } else if let Some(prompt) = args.prompt {
         // Single prompt mode
         let config = ImagenClientConfig {
             timeout_secs: args.timeout,
-            max_retries: args.max_retries,
+            max_retries: args.common.max_retries,
             model,
+            cache,
+            budget: Some(budget.clone()),
+            ..Default::default()
         };
 
-        let client = ImagenClient::with_config(api_key, config)
-            .map_err(|e| anyhow::anyhow!("Failed to create Imagen client: {}", e))?;
+        let client = clients.imagen(config);
 
         let gen_config = build_gen_config(args.size.as_ref(), args.aspect.as_ref())?;
-        let output_path = args.output.unwrap_or_else(|| {
-            // Generate filename: image-hash(prompt).png
-            PathBuf::from(generate_output_filename("image", &prompt, "png"))
-        });
+        // Generate filename: image-hash(prompt).<format, default png>
+        let extension = format.map(|f| f.extension()).unwrap_or("png");
+        let default_name = generate_output_filename("image", &prompt, extension);
+        let output_path = args.output.unwrap_or_else(|| default_name.clone());
         debug!("Output path: {:?}", output_path);
+        let output = if to_stdout {
+            SingleOutput::Stdout
+        } else {
+            let (storage, key) = OutputStorage::open_file(&output_path, &default_name)
+                .context("Invalid output path")?;
+            SingleOutput::Storage(storage, key)
+        };
 
-        generate_single(
+        let result = generate_single(
             &client,
             &prompt,
-            output_path,
+            output,
             gen_config.as_ref(),
-            args.quiet,
+            format,
+            args.quality,
+            args.metadata,
+            args.checksums,
+            args.common.quiet || to_stdout,
         )
-        .await?;
+        .await;
+
+        if args.json {
+            let gen_result = match &result {
+                Ok(path) => GenResult::success("image", &prompt, path.clone()),
+                Err(e) => GenResult::failed("image", &prompt, e.to_string()),
+            };
+            print_json_results(&[gen_result])?;
+        }
+
+        result?;
+        if !args.common.quiet
+            && !args.json
+            && !to_stdout
+            && let Some(spend) = cli::spend_summary(&budget, spent_before)
+        {
+            println!("{}", spend);
+        }
     }
 
     Ok(())
 }
 
+#[tokio::main]
+async fn main() -> Result<()> {
+    run(Args::parse()).await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -626,6 +812,13 @@ prompts:
         assert!(parse_aspect("2:1").is_err());
     }
 
+    #[test]
+    fn test_parse_format() {
+        assert_eq!(parse_format("jpg").unwrap(), ImageFormat::Jpg);
+        assert_eq!(parse_format("webp").unwrap(), ImageFormat::Webp);
+        assert!(parse_format("tiff").is_err());
+    }
+
     #[test]
     fn test_build_gen_config() {
         // No config
@@ -648,6 +841,30 @@ prompts:
         );
     }
 
+    #[test]
+    fn test_gen_result_json() {
+        let results = vec![
+            GenResult::success("sunset", "A sunset", "output/sunset.png".to_string()),
+            GenResult::failed("broken", "A prompt", "Rate limited".to_string()),
+        ];
+        let json: serde_json::Value = serde_json::to_value(&results).unwrap();
+        assert_eq!(json[0]["name"], "sunset");
+        assert_eq!(json[0]["prompt"], "A sunset");
+        assert_eq!(json[0]["output_path"], "output/sunset.png");
+        assert_eq!(json[0]["status"], "success");
+        assert!(json[0]["error"].is_null());
+        assert_eq!(json[1]["status"], "failed");
+        assert!(json[1]["output_path"].is_null());
+        assert_eq!(json[1]["error"], "Rate limited");
+    }
+
+    #[test]
+    fn test_is_stdout_path() {
+        assert!(is_stdout_path("-"));
+        assert!(!is_stdout_path("out.png"));
+        assert!(!is_stdout_path("./-"));
+    }
+
     #[test]
     fn test_slugify() {
         assert_eq!(slugify("Hello World"), "hello-world");
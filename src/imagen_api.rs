@@ -1,17 +1,33 @@
 use base64::Engine;
-use reqwest::Client;
-use serde::Deserialize;
+use reqwest::{Client, Method};
+use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 use std::fmt;
 use std::str::FromStr;
 use std::time::Duration;
 use thiserror::Error;
-use tracing::{debug, info, warn};
+use tracing::{Instrument, debug, field, info, info_span, warn};
+
+use crate::auth::{AuthMode, GEMINI_BASE_URL};
+use crate::budget::{Budget, BudgetExceeded};
+use crate::cache::ResponseCache;
+use crate::circuit_breaker::{CircuitBreaker, CircuitOpen};
+use crate::error::ErrorKind;
+use crate::proxy::ProxyConfig;
+use crate::redact::ApiKey;
+use crate::rt::Stopwatch;
+use crate::tls::TlsConfig;
+use crate::transport::TransportConfig;
+use crate::usage::{TokenUsage, WithUsage};
 
-const GEMINI_API_URL: &str = "https://generativelanguage.googleapis.com/v1beta/models";
 const DEFAULT_TIMEOUT_SECS: u64 = 120;
 const DEFAULT_MAX_RETRIES: u32 = 3;
 
+/// Invisible watermark recorded in [`ImageProvenance`]. The API doesn't
+/// report one; this is an assumption from Google's documentation, which says
+/// every image its Gemini image models output carries SynthID.
+pub const GOOGLE_IMAGE_WATERMARK: &str = "SynthID";
+
 /// Supported image generation models
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum ImageModel {
@@ -30,8 +46,8 @@ impl ImageModel {
         }
     }
 
-    /// Check if this model supports image configuration (size, aspect ratio)
-    pub fn supports_image_config(&self) -> bool {
+    /// Check if this model takes an image size; both models take an aspect ratio
+    pub fn supports_image_size(&self) -> bool {
         matches!(self, ImageModel::Gemini3Pro)
     }
 }
@@ -74,6 +90,15 @@ impl ImageSize {
             ImageSize::K4 => "4K",
         }
     }
+
+    /// Smallest size whose long side covers the given dimensions
+    pub fn covering(width: u32, height: u32) -> Self {
+        match width.max(height) {
+            0..=1024 => ImageSize::K1,
+            1025..=2048 => ImageSize::K2,
+            _ => ImageSize::K4,
+        }
+    }
 }
 
 impl fmt::Display for ImageSize {
@@ -99,18 +124,37 @@ impl FromStr for ImageSize {
     }
 }
 
-/// Aspect ratio options (Gemini 3 Pro only)
+/// Aspect ratio options
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum AspectRatio {
     #[default]
     Square, // 1:1
-    Wide,     // 16:9
-    Tall,     // 9:16
-    Standard, // 4:3
-    Portrait, // 3:4
+    Wide,      // 16:9
+    Tall,      // 9:16
+    Standard,  // 4:3
+    Portrait,  // 3:4
+    FiveFour,  // 5:4
+    FourFive,  // 4:5
+    ThreeTwo,  // 3:2
+    TwoThree,  // 2:3
+    UltraWide, // 21:9
 }
 
 impl AspectRatio {
+    /// Every ratio the image models accept
+    pub const ALL: [AspectRatio; 10] = [
+        AspectRatio::Square,
+        AspectRatio::Wide,
+        AspectRatio::Tall,
+        AspectRatio::Standard,
+        AspectRatio::Portrait,
+        AspectRatio::FiveFour,
+        AspectRatio::FourFive,
+        AspectRatio::ThreeTwo,
+        AspectRatio::TwoThree,
+        AspectRatio::UltraWide,
+    ];
+
     pub fn api_value(&self) -> &'static str {
         match self {
             AspectRatio::Square => "1:1",
@@ -118,8 +162,41 @@ impl AspectRatio {
             AspectRatio::Tall => "9:16",
             AspectRatio::Standard => "4:3",
             AspectRatio::Portrait => "3:4",
+            AspectRatio::FiveFour => "5:4",
+            AspectRatio::FourFive => "4:5",
+            AspectRatio::ThreeTwo => "3:2",
+            AspectRatio::TwoThree => "2:3",
+            AspectRatio::UltraWide => "21:9",
         }
     }
+
+    fn ratio(&self) -> f64 {
+        match self {
+            AspectRatio::Square => 1.0,
+            AspectRatio::Wide => 16.0 / 9.0,
+            AspectRatio::Tall => 9.0 / 16.0,
+            AspectRatio::Standard => 4.0 / 3.0,
+            AspectRatio::Portrait => 3.0 / 4.0,
+            AspectRatio::FiveFour => 5.0 / 4.0,
+            AspectRatio::FourFive => 4.0 / 5.0,
+            AspectRatio::ThreeTwo => 3.0 / 2.0,
+            AspectRatio::TwoThree => 2.0 / 3.0,
+            AspectRatio::UltraWide => 21.0 / 9.0,
+        }
+    }
+
+    /// Supported aspect ratio closest to the given dimensions
+    pub fn nearest(width: u32, height: u32) -> Self {
+        let target = (f64::from(width.max(1)) / f64::from(height.max(1))).ln();
+        Self::ALL
+            .into_iter()
+            .min_by(|a, b| {
+                let da = (a.ratio().ln() - target).abs();
+                let db = (b.ratio().ln() - target).abs();
+                da.total_cmp(&db)
+            })
+            .unwrap_or_default()
+    }
 }
 
 impl fmt::Display for AspectRatio {
@@ -132,14 +209,25 @@ impl FromStr for AspectRatio {
     type Err = String;
 
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-        match s {
+        // `16 : 9` and `UltraWide` are accepted too
+        let normalized: String = s
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+            .to_lowercase();
+        match normalized.as_str() {
             "1:1" | "square" => Ok(AspectRatio::Square),
             "16:9" | "wide" => Ok(AspectRatio::Wide),
             "9:16" | "tall" => Ok(AspectRatio::Tall),
             "4:3" | "standard" => Ok(AspectRatio::Standard),
             "3:4" | "portrait" => Ok(AspectRatio::Portrait),
+            "5:4" => Ok(AspectRatio::FiveFour),
+            "4:5" => Ok(AspectRatio::FourFive),
+            "3:2" => Ok(AspectRatio::ThreeTwo),
+            "2:3" => Ok(AspectRatio::TwoThree),
+            "21:9" | "ultrawide" => Ok(AspectRatio::UltraWide),
             _ => Err(format!(
-                "Invalid aspect ratio: {}. Use 1:1, 16:9, 9:16, 4:3, or 3:4",
+                "Invalid aspect ratio: {}. Use 1:1, 16:9, 9:16, 4:3, 3:4, 5:4, 4:5, 3:2, 2:3, or 21:9",
                 s
             )),
         }
@@ -151,11 +239,15 @@ pub enum ImagenError {
     #[error("API key not found in environment (set GEMINI_API_KEY or GOOGLE_AI_KEY)")]
     MissingApiKey,
 
-    #[error("Gemini API error ({status}): {message}")]
-    ApiError { status: u16, message: String },
+    #[error("Gemini API error ({status}, request {request_id}): {message}")]
+    ApiError {
+        status: u16,
+        message: String,
+        request_id: String,
+    },
 
-    #[error("Rate limited by API. Retry after some time.")]
-    RateLimited,
+    #[error("Rate limited by API (request {request_id}). Retry after some time.")]
+    RateLimited { request_id: String },
 
     #[error("Invalid response from Gemini API: {0}")]
     InvalidResponse(String),
@@ -164,7 +256,7 @@ pub enum ImagenError {
     NoImageData,
 
     #[error("Network error: {0}")]
-    NetworkError(#[from] reqwest::Error),
+    NetworkError(#[source] reqwest::Error),
 
     #[error("JSON parsing error: {0}")]
     JsonError(#[from] serde_json::Error),
@@ -172,20 +264,77 @@ pub enum ImagenError {
     #[error("Max retries ({0}) exceeded")]
     MaxRetriesExceeded(u32),
 
+    #[error(transparent)]
+    CircuitOpen(#[from] CircuitOpen),
+
+    #[error(transparent)]
+    BudgetExceeded(#[from] BudgetExceeded),
+
     #[error("Base64 decode error: {0}")]
     Base64Error(#[from] base64::DecodeError),
 
-    #[error("Image config (size/aspect) only supported with Gemini 3 Pro model")]
-    ImageConfigNotSupported,
+    #[error("Image size (1K/2K/4K) only supported with Gemini 3 Pro model")]
+    ImageSizeNotSupported,
 }
 
 pub type Result<T> = std::result::Result<T, ImagenError>;
 
+impl From<reqwest::Error> for ImagenError {
+    fn from(err: reqwest::Error) -> Self {
+        ImagenError::NetworkError(crate::redact::reqwest_error(err))
+    }
+}
+
+impl ImagenError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            ImagenError::MissingApiKey | ImagenError::ImageSizeNotSupported => ErrorKind::Config,
+            ImagenError::ApiError { .. } => ErrorKind::Api,
+            ImagenError::RateLimited { .. } => ErrorKind::RateLimited,
+            ImagenError::InvalidResponse(_)
+            | ImagenError::NoImageData
+            | ImagenError::JsonError(_)
+            | ImagenError::Base64Error(_) => ErrorKind::InvalidResponse,
+            ImagenError::NetworkError(_) => ErrorKind::Network,
+            ImagenError::MaxRetriesExceeded(_) => ErrorKind::RetriesExhausted,
+            ImagenError::CircuitOpen(_) => ErrorKind::CircuitOpen,
+            ImagenError::BudgetExceeded(_) => ErrorKind::BudgetExceeded,
+        }
+    }
+
+    /// HTTP status returned by the API, if any
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            ImagenError::ApiError { status, .. } => Some(*status),
+            ImagenError::RateLimited { .. } => Some(429),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ImagenClientConfig {
     pub timeout_secs: u64,
     pub max_retries: u32,
     pub model: ImageModel,
+    /// Send 1:1 / 1K when aspect ratio or size is unset (Gemini 3 Pro);
+    /// when false the fields are omitted and the model picks
+    pub fill_image_config_defaults: bool,
+    /// API host, e.g. a local mock server in tests
+    pub base_url: String,
+    /// Used by `with_config`; `with_http_client` keeps the given client's proxy
+    pub proxy: ProxyConfig,
+    /// Extra root certificates and builder hook, applied like `proxy`
+    pub tls: TlsConfig,
+    /// Connection pool, keep-alive and HTTP/2 settings, applied like `proxy`
+    pub transport: TransportConfig,
+    pub auth_mode: AuthMode,
+    /// Serve identical generation requests from disk; `None` disables
+    pub cache: Option<ResponseCache>,
+    /// Shared with other clients of a batch to stop retrying when the API is down
+    pub circuit_breaker: Option<CircuitBreaker>,
+    /// Spend limit shared with other clients; requests fail once it is reached
+    pub budget: Option<Budget>,
 }
 
 impl Default for ImagenClientConfig {
@@ -194,6 +343,15 @@ impl Default for ImagenClientConfig {
             timeout_secs: DEFAULT_TIMEOUT_SECS,
             max_retries: DEFAULT_MAX_RETRIES,
             model: ImageModel::default(),
+            fill_image_config_defaults: true,
+            base_url: GEMINI_BASE_URL.to_string(),
+            proxy: ProxyConfig::default(),
+            tls: TlsConfig::default(),
+            transport: TransportConfig::default(),
+            auth_mode: AuthMode::default(),
+            cache: None,
+            circuit_breaker: None,
+            budget: None,
         }
     }
 }
@@ -224,7 +382,7 @@ impl ImageGenConfig {
 #[derive(Debug, Clone)]
 pub struct ImagenClient {
     client: Client,
-    api_key: String,
+    api_key: ApiKey,
     config: ImagenClientConfig,
 }
 
@@ -242,14 +400,64 @@ pub struct InlineData {
     pub data: String,
 }
 
+/// Where a generated image came from, for records that it is AI-generated
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageProvenance {
+    /// API model name the request was sent to
+    pub model: String,
+    /// Exact model version that answered (`modelVersion`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_version: Option<String>,
+    /// ID of the API response (`responseId`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_id: Option<String>,
+    /// Invisible watermark embedded in the image; not read from the
+    /// response but assumed, see [`GOOGLE_IMAGE_WATERMARK`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub watermark: Option<String>,
+}
+
+impl ImageProvenance {
+    /// Read the provenance fields of a `generateContent` response
+    pub fn from_response(model: ImageModel, response: &Value) -> Self {
+        let field = |name: &str| response[name].as_str().map(str::to_string);
+        Self {
+            model: model.api_model_name().to_string(),
+            model_version: field("modelVersion"),
+            response_id: field("responseId"),
+            watermark: Some(GOOGLE_IMAGE_WATERMARK.to_string()),
+        }
+    }
+}
+
+/// Contents of a metadata sidecar
+#[derive(Serialize)]
+struct Sidecar<'a> {
+    ai_generated: bool,
+    #[serde(flatten)]
+    provenance: &'a ImageProvenance,
+}
+
 /// Generated image data
 #[derive(Debug, Clone)]
 pub struct GeneratedImage {
     pub data: Vec<u8>,
     pub mime_type: String,
+    /// Set for images a model produced; `None` e.g. for extracted video frames
+    pub provenance: Option<ImageProvenance>,
 }
 
 impl GeneratedImage {
+    /// JSON for a metadata sidecar recording that the image is AI-generated,
+    /// or `None` if it didn't come from a model
+    pub fn sidecar_json(&self) -> Option<String> {
+        let sidecar = Sidecar {
+            ai_generated: true,
+            provenance: self.provenance.as_ref()?,
+        };
+        serde_json::to_string_pretty(&sidecar).ok()
+    }
+
     /// Get the appropriate file extension for this image
     pub fn extension(&self) -> &'static str {
         match self.mime_type.as_str() {
@@ -268,28 +476,53 @@ impl ImagenClient {
     }
 
     pub fn with_config(api_key: String, config: ImagenClientConfig) -> Result<Self> {
-        let client = Client::builder()
-            .use_rustls_tls()
-            .timeout(Duration::from_secs(config.timeout_secs))
-            .connect_timeout(Duration::from_secs(30))
-            .build()
-            .map_err(ImagenError::NetworkError)?;
-
-        Ok(Self {
+        let builder = crate::proxy::apply(
+            crate::rt::client_builder(config.timeout_secs),
+            &config.proxy,
+        )?;
+        let builder = crate::transport::apply(builder, &config.transport);
+        let client = crate::tls::apply(builder, &config.tls)?.build()?;
+
+        Ok(Self::with_http_client(client, api_key, config))
+    }
+
+    /// Build on an existing HTTP client, sharing its connection pool
+    pub fn with_http_client(client: Client, api_key: String, config: ImagenClientConfig) -> Self {
+        Self {
             client,
-            api_key,
+            api_key: api_key.into(),
             config,
-        })
+        }
+    }
+
+    /// The same client with the response cache turned off, for callers that
+    /// want a fresh generation for every identical request
+    pub fn without_cache(mut self) -> Self {
+        self.config.cache = None;
+        self
     }
 
-    fn build_payload(&self, prompt: &str, gen_config: Option<&ImageGenConfig>) -> Value {
-        match self.config.model {
+    fn build_payload(
+        config: &ImagenClientConfig,
+        prompt: &str,
+        gen_config: Option<&ImageGenConfig>,
+    ) -> Value {
+        match config.model {
             ImageModel::Gemini25Flash => {
-                // Simple payload for Gemini 2.5 Flash
+                // Gemini 2.5 Flash takes an aspect ratio but no size
+                let Some(ratio) = gen_config.and_then(|cfg| cfg.aspect_ratio.as_ref()) else {
+                    return json!({
+                        "contents": [{
+                            "parts": [{"text": prompt}]
+                        }]
+                    });
+                };
                 json!({
-                    "contents": [{
-                        "parts": [{"text": prompt}]
-                    }]
+                    "contents": [{"parts": [{"text": prompt}]}],
+                    "generationConfig": {
+                        "responseModalities": ["TEXT", "IMAGE"],
+                        "imageConfig": {"aspectRatio": ratio.api_value()}
+                    }
                 })
             }
             ImageModel::Gemini3Pro => {
@@ -305,21 +538,27 @@ impl ImagenClient {
                     }
                 }
 
-                // Default aspect ratio if not specified
-                if image_config.get("aspectRatio").is_none() {
-                    image_config["aspectRatio"] = json!("1:1");
+                if config.fill_image_config_defaults {
+                    // Default aspect ratio if not specified
+                    if image_config.get("aspectRatio").is_none() {
+                        image_config["aspectRatio"] = json!("1:1");
+                    }
+                    // Default size if not specified
+                    if image_config.get("imageSize").is_none() {
+                        image_config["imageSize"] = json!("1K");
+                    }
                 }
-                // Default size if not specified
-                if image_config.get("imageSize").is_none() {
-                    image_config["imageSize"] = json!("1K");
+
+                let mut generation_config = json!({
+                    "responseModalities": ["TEXT", "IMAGE"]
+                });
+                if image_config.as_object().is_some_and(|c| !c.is_empty()) {
+                    generation_config["imageConfig"] = image_config;
                 }
 
                 json!({
                     "contents": [{"parts": [{"text": prompt}]}],
-                    "generationConfig": {
-                        "responseModalities": ["TEXT", "IMAGE"],
-                        "imageConfig": image_config
-                    }
+                    "generationConfig": generation_config
                 })
             }
         }
@@ -327,28 +566,27 @@ impl ImagenClient {
 
     async fn send_request(
         &self,
-        prompt: &str,
-        gen_config: Option<&ImageGenConfig>,
-    ) -> Result<GeneratedImage> {
+        payload: &Value,
+        request_id: &str,
+    ) -> Result<WithUsage<GeneratedImage>> {
         let url = format!(
-            "{}/{}:generateContent?key={}",
-            GEMINI_API_URL,
-            self.config.model.api_model_name(),
-            self.api_key
+            "{}/v1beta/models/{}:generateContent",
+            self.config.base_url,
+            self.config.model.api_model_name()
         );
 
-        let payload = self.build_payload(prompt, gen_config);
-
         debug!(
             "Sending image generation request to Gemini API (model: {})",
             self.config.model
         );
 
         let response = self
-            .client
-            .post(&url)
+            .config
+            .auth_mode
+            .request(&self.client, Method::POST, &url, &self.api_key)
+            .timeout(Duration::from_secs(self.config.timeout_secs))
             .header("Content-Type", "application/json")
-            .json(&payload)
+            .json(payload)
             .send()
             .await?;
 
@@ -356,18 +594,31 @@ impl ImagenClient {
         debug!("Received response with status: {}", status);
 
         if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
-            return Err(ImagenError::RateLimited);
+            crate::metrics::record_rate_limited("imagen");
+            return Err(ImagenError::RateLimited {
+                request_id: request_id.to_string(),
+            });
         }
 
         if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
+            let error_text = self
+                .api_key
+                .scrub(response.text().await.unwrap_or_default());
             return Err(ImagenError::ApiError {
                 status: status.as_u16(),
                 message: error_text,
+                request_id: request_id.to_string(),
             });
         }
 
         let data: Value = response.json().await?;
+        crate::metrics::record_gemini_usage("imagen", &data);
+        crate::usage::record_gemini(
+            "imagen",
+            self.config.model.api_model_name(),
+            &data,
+            self.config.budget.as_ref(),
+        );
 
         // Extract image from response
         // Response structure: candidates[0].content.parts[] where parts can have inline_data
@@ -387,10 +638,12 @@ impl ImagenClient {
 
                 let image_data = base64::engine::general_purpose::STANDARD.decode(base64_data)?;
 
-                return Ok(GeneratedImage {
+                let image = GeneratedImage {
                     data: image_data,
                     mime_type,
-                });
+                    provenance: Some(ImageProvenance::from_response(self.config.model, &data)),
+                };
+                return Ok(WithUsage::new(image, TokenUsage::from_response(&data)));
             }
         }
 
@@ -398,11 +651,7 @@ impl ImagenClient {
     }
 
     fn is_retryable_error(err: &ImagenError) -> bool {
-        match err {
-            ImagenError::RateLimited | ImagenError::NetworkError(_) => true,
-            ImagenError::ApiError { status, .. } => *status >= 500,
-            _ => false,
-        }
+        crate::error::is_retryable(err.kind(), err.status())
     }
 
     /// Generate an image from a text prompt with retry logic
@@ -416,48 +665,126 @@ impl ImagenClient {
         prompt: &str,
         gen_config: Option<&ImageGenConfig>,
     ) -> Result<GeneratedImage> {
-        // Validate that image config is only used with Gemini 3 Pro
+        self.generate_image_with_usage(prompt, gen_config)
+            .await
+            .map(|image| image.value)
+    }
+
+    /// [`generate_image_with_config`](Self::generate_image_with_config) with
+    /// the tokens the request took
+    pub async fn generate_image_with_usage(
+        &self,
+        prompt: &str,
+        gen_config: Option<&ImageGenConfig>,
+    ) -> Result<WithUsage<GeneratedImage>> {
+        // Validate that image size is only used with Gemini 3 Pro
         if let Some(cfg) = gen_config
-            && (cfg.size.is_some() || cfg.aspect_ratio.is_some())
-            && !self.config.model.supports_image_config()
+            && cfg.size.is_some()
+            && !self.config.model.supports_image_size()
         {
-            return Err(ImagenError::ImageConfigNotSupported);
+            return Err(ImagenError::ImageSizeNotSupported);
+        }
+
+        let payload = Self::build_payload(&self.config, prompt, gen_config);
+        let Some(cache) = &self.config.cache else {
+            return self.generate_with_retry(&payload, prompt.len()).await;
+        };
+
+        let key = ResponseCache::key(&[
+            self.config.model.api_model_name().as_bytes(),
+            payload.to_string().as_bytes(),
+        ]);
+        if let Some(image) = cache.get_image(&key).await {
+            info!("Using cached image");
+            return Ok(WithUsage::new(image, TokenUsage::default()));
         }
 
-        let mut last_error = None;
-        let mut retry_count = 0;
+        let image = self.generate_with_retry(&payload, prompt.len()).await?;
+        cache.put_image(&key, &image.value).await;
+        Ok(image)
+    }
+
+    async fn generate_with_retry(
+        &self,
+        payload: &Value,
+        prompt_bytes: usize,
+    ) -> Result<WithUsage<GeneratedImage>> {
+        let request_id = crate::request_id::next();
+        let span = info_span!(
+            "imagen_request",
+            request_id = %request_id,
+            model = %self.config.model,
+            payload_bytes = prompt_bytes,
+            attempt = field::Empty,
+            latency_ms = field::Empty,
+        );
 
-        while retry_count < self.config.max_retries {
-            match self.send_request(prompt, gen_config).await {
-                Ok(image) => {
-                    info!("Image generation successful");
-                    return Ok(image);
+        async {
+            let mut last_error = None;
+            let mut retry_count = 0;
+
+            while retry_count < self.config.max_retries {
+                if let Some(breaker) = &self.config.circuit_breaker {
+                    breaker.check()?;
+                }
+                if let Some(budget) = &self.config.budget {
+                    budget.check()?;
                 }
-                Err(e) => {
-                    if Self::is_retryable_error(&e) && retry_count + 1 < self.config.max_retries {
-                        let delay = if matches!(e, ImagenError::RateLimited) {
-                            Duration::from_secs(30 * (retry_count as u64 + 1))
+                span.record("attempt", retry_count + 1);
+                let stopwatch = Stopwatch::start();
+                let result = self.send_request(payload, &request_id).await;
+                span.record("latency_ms", stopwatch.elapsed_ms());
+                if let Some(breaker) = &self.config.circuit_breaker {
+                    breaker.record(&result, Self::is_retryable_error);
+                }
+                crate::metrics::record_request("imagen", result.is_ok(), stopwatch.elapsed_ms());
+                debug!(
+                    "Attempt {} finished in {}ms",
+                    retry_count + 1,
+                    stopwatch.elapsed_ms()
+                );
+
+                match result {
+                    Ok(image) => {
+                        info!("Image generation successful");
+                        return Ok(image);
+                    }
+                    Err(e) => {
+                        if Self::is_retryable_error(&e)
+                            && retry_count + 1 < self.config.max_retries
+                            && self
+                                .config
+                                .circuit_breaker
+                                .as_ref()
+                                .is_none_or(CircuitBreaker::try_retry)
+                        {
+                            let delay = if matches!(e, ImagenError::RateLimited { .. }) {
+                                Duration::from_secs(30 * (retry_count as u64 + 1))
+                            } else {
+                                Duration::from_secs(2u64.pow(retry_count))
+                            };
+                            warn!(
+                                "Request failed (attempt {}/{}): {}. Retrying in {:?}...",
+                                retry_count + 1,
+                                self.config.max_retries,
+                                e,
+                                delay
+                            );
+                            crate::metrics::record_retry("imagen");
+                            crate::rt::sleep(delay).await;
+                            retry_count += 1;
+                            last_error = Some(e);
                         } else {
-                            Duration::from_secs(2u64.pow(retry_count))
-                        };
-                        warn!(
-                            "Request failed (attempt {}/{}): {}. Retrying in {:?}...",
-                            retry_count + 1,
-                            self.config.max_retries,
-                            e,
-                            delay
-                        );
-                        tokio::time::sleep(delay).await;
-                        retry_count += 1;
-                        last_error = Some(e);
-                    } else {
-                        return Err(e);
+                            return Err(e);
+                        }
                     }
                 }
             }
-        }
 
-        Err(last_error.unwrap_or(ImagenError::MaxRetriesExceeded(self.config.max_retries)))
+            Err(last_error.unwrap_or(ImagenError::MaxRetriesExceeded(self.config.max_retries)))
+        }
+        .instrument(span.clone())
+        .await
     }
 }
 
@@ -465,33 +792,125 @@ impl ImagenClient {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_build_payload_image_config_defaults() {
+        let mut config = ImagenClientConfig {
+            model: ImageModel::Gemini3Pro,
+            ..Default::default()
+        };
+        let payload = ImagenClient::build_payload(&config, "A cat", None);
+        let image_config = &payload["generationConfig"]["imageConfig"];
+        assert_eq!(image_config["aspectRatio"], "1:1");
+        assert_eq!(image_config["imageSize"], "1K");
+
+        config.fill_image_config_defaults = false;
+        let payload = ImagenClient::build_payload(&config, "A cat", None);
+        assert!(payload["generationConfig"].get("imageConfig").is_none());
+
+        let gen_config = ImageGenConfig::new().with_aspect_ratio(AspectRatio::Wide);
+        let payload = ImagenClient::build_payload(&config, "A cat", Some(&gen_config));
+        let image_config = &payload["generationConfig"]["imageConfig"];
+        assert_eq!(image_config["aspectRatio"], "16:9");
+        assert!(image_config.get("imageSize").is_none());
+    }
+
+    #[test]
+    fn test_build_payload_flash_aspect_ratio() {
+        let config = ImagenClientConfig {
+            model: ImageModel::Gemini25Flash,
+            ..Default::default()
+        };
+        // No 1:1 / 1K defaults for Flash; a bare prompt unless an aspect is asked for
+        let payload = ImagenClient::build_payload(&config, "A cat", None);
+        assert!(payload.get("generationConfig").is_none());
+
+        let gen_config = ImageGenConfig::new().with_aspect_ratio(AspectRatio::Wide);
+        let payload = ImagenClient::build_payload(&config, "A cat", Some(&gen_config));
+        let image_config = &payload["generationConfig"]["imageConfig"];
+        assert_eq!(image_config["aspectRatio"], "16:9");
+        assert!(image_config.get("imageSize").is_none());
+    }
+
+    #[test]
+    fn test_aspect_ratio_nearest() {
+        assert_eq!(AspectRatio::nearest(1920, 1080), AspectRatio::Wide);
+        assert_eq!(AspectRatio::nearest(1080, 1920), AspectRatio::Tall);
+        assert_eq!(AspectRatio::nearest(800, 800), AspectRatio::Square);
+        assert_eq!(AspectRatio::nearest(4032, 3024), AspectRatio::Standard);
+        assert_eq!(AspectRatio::nearest(1500, 2000), AspectRatio::Portrait);
+        assert_eq!(AspectRatio::nearest(1280, 1024), AspectRatio::FiveFour);
+        assert_eq!(AspectRatio::nearest(1200, 1500), AspectRatio::FourFive);
+        assert_eq!(AspectRatio::nearest(6000, 4000), AspectRatio::ThreeTwo);
+        assert_eq!(AspectRatio::nearest(2560, 1080), AspectRatio::UltraWide);
+    }
+
+    #[test]
+    fn test_image_size_covering() {
+        assert_eq!(ImageSize::covering(800, 600), ImageSize::K1);
+        assert_eq!(ImageSize::covering(1024, 1024), ImageSize::K1);
+        assert_eq!(ImageSize::covering(1920, 1080), ImageSize::K2);
+        assert_eq!(ImageSize::covering(3024, 4032), ImageSize::K4);
+    }
+
     #[test]
     fn test_generated_image_extension() {
         let png_image = GeneratedImage {
             data: vec![],
             mime_type: "image/png".to_string(),
+            provenance: None,
         };
         assert_eq!(png_image.extension(), "png");
 
         let jpg_image = GeneratedImage {
             data: vec![],
             mime_type: "image/jpeg".to_string(),
+            provenance: None,
         };
         assert_eq!(jpg_image.extension(), "jpg");
 
         let webp_image = GeneratedImage {
             data: vec![],
             mime_type: "image/webp".to_string(),
+            provenance: None,
         };
         assert_eq!(webp_image.extension(), "webp");
 
         let unknown_image = GeneratedImage {
             data: vec![],
             mime_type: "image/unknown".to_string(),
+            provenance: None,
         };
         assert_eq!(unknown_image.extension(), "png");
     }
 
+    #[test]
+    fn test_provenance_sidecar() {
+        let response = json!({
+            "candidates": [],
+            "modelVersion": "gemini-2.5-flash-image",
+            "responseId": "abc123"
+        });
+        let provenance = ImageProvenance::from_response(ImageModel::Gemini25Flash, &response);
+        assert_eq!(
+            provenance.model_version.as_deref(),
+            Some("gemini-2.5-flash-image")
+        );
+        assert_eq!(provenance.response_id.as_deref(), Some("abc123"));
+
+        let mut image = GeneratedImage {
+            data: vec![],
+            mime_type: "image/png".to_string(),
+            provenance: None,
+        };
+        assert!(image.sidecar_json().is_none());
+
+        image.provenance = Some(provenance);
+        let sidecar: Value = serde_json::from_str(&image.sidecar_json().unwrap()).unwrap();
+        assert_eq!(sidecar["ai_generated"], true);
+        assert_eq!(sidecar["model"], "gemini-2.5-flash-image");
+        assert_eq!(sidecar["watermark"], "SynthID");
+    }
+
     #[test]
     fn test_default_config() {
         let config = ImagenClientConfig::default();
@@ -543,7 +962,29 @@ mod tests {
         assert_eq!(AspectRatio::from_str("9:16").unwrap(), AspectRatio::Tall);
         assert_eq!(AspectRatio::from_str("4:3").unwrap(), AspectRatio::Standard);
         assert_eq!(AspectRatio::from_str("3:4").unwrap(), AspectRatio::Portrait);
+        assert_eq!(AspectRatio::from_str("5:4").unwrap(), AspectRatio::FiveFour);
+        assert_eq!(AspectRatio::from_str("4:5").unwrap(), AspectRatio::FourFive);
+        assert_eq!(AspectRatio::from_str("3:2").unwrap(), AspectRatio::ThreeTwo);
+        assert_eq!(AspectRatio::from_str("2:3").unwrap(), AspectRatio::TwoThree);
+        assert_eq!(
+            AspectRatio::from_str("21:9").unwrap(),
+            AspectRatio::UltraWide
+        );
+        assert_eq!(AspectRatio::FiveFour.api_value(), "5:4");
+        assert_eq!(
+            AspectRatio::from_str(" 21 : 9 ").unwrap(),
+            AspectRatio::UltraWide
+        );
+        assert_eq!(
+            AspectRatio::from_str("UltraWide").unwrap(),
+            AspectRatio::UltraWide
+        );
         assert!(AspectRatio::from_str("2:1").is_err());
+
+        // Every variant parses back from its API value
+        for ratio in AspectRatio::ALL {
+            assert_eq!(AspectRatio::from_str(ratio.api_value()).unwrap(), ratio);
+        }
     }
 
     #[test]
@@ -559,8 +1000,8 @@ mod tests {
     }
 
     #[test]
-    fn test_model_supports_image_config() {
-        assert!(!ImageModel::Gemini25Flash.supports_image_config());
-        assert!(ImageModel::Gemini3Pro.supports_image_config());
+    fn test_model_supports_image_size() {
+        assert!(!ImageModel::Gemini25Flash.supports_image_size());
+        assert!(ImageModel::Gemini3Pro.supports_image_size());
     }
 }
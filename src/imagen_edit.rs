@@ -1,20 +1,26 @@
 use anyhow::{Context, Result};
 use clap::Parser;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use serde::Deserialize;
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::fs;
-use tokio::sync::Semaphore;
-use tracing::{Level, debug};
-use tracing_subscriber::FmtSubscriber;
+use tokio::sync::{Mutex, Semaphore};
+use tracing::{Instrument, debug, info_span, warn};
 
-use transcript_tool::imagen_api::{AspectRatio, ImageSize};
+use transcript_tool::cli::{self, BatchLimitArgs, CommonArgs, init_logging};
+use transcript_tool::imagen_api::{AspectRatio, ImageModel, ImageSize};
 use transcript_tool::imagen_edit_api::{
     ImageEditClient, ImageEditClientConfig, ImageEditConfig, InputImage,
 };
+use transcript_tool::storage::write_atomic;
+use transcript_tool::{
+    Budget, CircuitBreaker, Clients, CompareLayout, GeneratedImage, ResponseCache, check_image,
+    checksum_line, comparison_path, compose_comparison, image_dimensions, resize_image,
+};
 
 #[derive(Parser, Debug)]
 #[command(name = "imagen_edit")]
@@ -25,12 +31,42 @@ use transcript_tool::imagen_edit_api::{
     imagen_edit -i photo.jpg \"Make it look like a watercolor painting\"
     imagen_edit -i face1.png -i face2.png \"An office group photo of these people\"
     imagen_edit -i img1.jpg -i img2.jpg --size 2K --aspect 16:9 \"Combine into panorama\"
+    imagen_edit -i room.jpg --mask wall.png \"Paint the wall dark green\"
+
+    # Apply the same edit to every image in a folder (or matching a quoted glob)
+    imagen_edit -i photos/ \"Make it look like a watercolor painting\" -j 4
+    imagen_edit -i 'photos/*.jpg' \"Convert to black and white\"
+    imagen_edit -i a.jpg -i b.jpg --each \"Add film grain\"
+
+    # Save a before/after composite next to each output for quick review
+    imagen_edit -i photos/ --compare \"Make it look like a watercolor painting\"
+    imagen_edit -i photo.jpg --compare=stacked \"Remove the background\"
+
+    # Iterative refinement, saving intermediate steps
+    imagen_edit -i portrait.jpg --iterations 3 \"Make the person look ten years older\"
+
+    # Keep the edited photo at the input's exact pixel dimensions
+    imagen_edit -i banner.jpg --keep-resolution \"Replace the sky with a sunset\"
+
+    # Record that the output is AI-generated in photo-edited.png.json
+    imagen_edit -i photo.jpg -o photo-edited.png --metadata \"Add a hat\"
+
+    # Use the cheaper Gemini 2.5 Flash model (per-entry `model:` in YAML)
+    imagen_edit -i photo.jpg -m 2.5-flash \"Convert to black and white\"
 
     # Batch mode with YAML file
     imagen_edit --yaml edits.yaml
     imagen_edit --yaml edits.yaml --name group-photo
-    imagen_edit --yaml edits.yaml -j 4")]
-struct Args {
+    imagen_edit --yaml edits.yaml -j 4
+
+    # Re-running a YAML batch skips entries that already completed (use --force to redo)
+    imagen_edit --yaml edits.yaml --force
+
+    # Hot folder: edit every image dropped into in/ and write results to out/
+    imagen_edit --watch-dir in/ --prompt \"Make it look like a watercolor painting\" -o out/
+
+    # Chain entries: use \"$name\" in images to consume an earlier entry's output")]
+pub struct Args {
     /// Input image file(s) - can specify multiple with -i
     #[arg(short, long = "input", num_args = 1..)]
     input: Option<Vec<PathBuf>>,
@@ -39,6 +75,54 @@ struct Args {
     #[arg(value_name = "PROMPT")]
     prompt: Option<String>,
 
+    /// Text prompt, as an alternative to the positional PROMPT
+    #[arg(long = "prompt", value_name = "PROMPT", conflicts_with = "prompt")]
+    prompt_flag: Option<String>,
+
+    /// Watch a folder and edit every image dropped into it (runs until interrupted)
+    #[arg(long, value_name = "DIR")]
+    watch_dir: Option<PathBuf>,
+
+    /// Serve Prometheus metrics at http://ADDR/metrics while watching (e.g. 127.0.0.1:9184)
+    #[cfg(feature = "metrics")]
+    #[arg(long, value_name = "ADDR", requires = "watch_dir")]
+    metrics_addr: Option<std::net::SocketAddr>,
+
+    /// Mask image for inpainting: white regions are edited, black regions preserved
+    #[arg(long)]
+    mask: Option<PathBuf>,
+
+    /// Edit each input image independently with the same prompt (implied for directories and globs)
+    #[arg(long)]
+    each: bool,
+
+    /// Also save a before/after comparison composite: side (default) or stacked
+    #[arg(
+        long,
+        value_name = "LAYOUT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "side"
+    )]
+    compare: Option<String>,
+
+    /// Run the edit N times, feeding each output back as input (intermediate steps are saved)
+    #[arg(long, default_value = "1", value_parser = clap::value_parser!(u32).range(1..))]
+    iterations: u32,
+
+    /// Match the output's pixel dimensions to the (first) input image
+    #[arg(long)]
+    keep_resolution: bool,
+
+    /// Write a `<image>.json` sidecar recording that each output is AI-generated,
+    /// with its model version and watermark
+    #[arg(long)]
+    metadata: bool,
+
+    /// Also write a `<image>.blake3` checksum sidecar, checked by `verify`
+    #[arg(long)]
+    checksums: bool,
+
     /// YAML file containing edit tasks
     #[arg(short = 'y', long)]
     yaml: Option<PathBuf>,
@@ -47,15 +131,23 @@ struct Args {
     #[arg(short = 'n', long)]
     name: Option<String>,
 
-    /// Output file path (for single edit) or directory (for YAML batch)
+    /// Redo YAML entries that already completed in a previous run
+    #[arg(long)]
+    force: bool,
+
+    /// Output file path (for single edit) or directory (for YAML batch, --each and --watch-dir)
     #[arg(short, long)]
     output: Option<PathBuf>,
 
-    /// Image size: 1K, 2K, 4K
+    /// Gemini model to use: 3pro (default), 2.5-flash
+    #[arg(short = 'm', long, env = "FLASHECHO_IMAGE_MODEL")]
+    model: Option<String>,
+
+    /// Image size (Gemini 3 Pro only): 1K, 2K, 4K
     #[arg(short = 's', long)]
     size: Option<String>,
 
-    /// Aspect ratio: 1:1, 16:9, 9:16, 4:3, 3:4, 5:4
+    /// Aspect ratio: 1:1, 16:9, 9:16, 4:3, 3:4, 5:4, 4:5, 3:2, 2:3, 21:9
     #[arg(short = 'a', long)]
     aspect: Option<String>,
 
@@ -63,21 +155,15 @@ struct Args {
     #[arg(short, long, default_value = "120")]
     timeout: u64,
 
-    /// Max retry attempts for API calls
-    #[arg(long, default_value = "3")]
-    max_retries: u32,
-
-    /// Number of parallel jobs for YAML batch mode
+    /// Number of parallel jobs for YAML batch and --each mode
     #[arg(short = 'j', long, default_value = "2")]
     jobs: usize,
 
-    /// Verbosity level (-v, -vv, -vvv)
-    #[arg(short, long, action = clap::ArgAction::Count)]
-    verbose: u8,
+    #[command(flatten)]
+    common: CommonArgs,
 
-    /// Quiet mode (no progress output)
-    #[arg(short, long)]
-    quiet: bool,
+    #[command(flatten)]
+    limits: BatchLimitArgs,
 }
 
 /// YAML file structure for batch edits
@@ -86,40 +172,27 @@ struct EditsFile {
     edits: Vec<EditEntry>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 struct EditEntry {
     name: String,
     prompt: String,
     images: Vec<String>,
+    /// Optional inpainting mask (white = editable, black = preserved)
+    mask: Option<String>,
     output: Option<String>,
     size: Option<String>,
     aspect: Option<String>,
+    /// Model override for this entry: 2.5-flash or 3pro
+    model: Option<String>,
+    /// Number of refinement passes (defaults to the number of `prompts`, else the CLI value)
+    iterations: Option<u32>,
+    /// Per-iteration prompts; passes beyond the list fall back to `prompt`
+    #[serde(default)]
+    prompts: Vec<String>,
 }
 
-fn get_api_key() -> Result<String> {
-    std::env::var("GEMINI_API_KEY")
-        .or_else(|_| std::env::var("GOOGLE_AI_KEY"))
-        .context("GEMINI_API_KEY or GOOGLE_AI_KEY environment variable is not set")
-}
-
-fn init_logging(verbose: u8) {
-    let level = match verbose {
-        0 => Level::WARN,
-        1 => Level::INFO,
-        2 => Level::DEBUG,
-        _ => Level::TRACE,
-    };
-
-    let subscriber = FmtSubscriber::builder()
-        .with_max_level(level)
-        .with_target(false)
-        .with_thread_ids(false)
-        .with_file(false)
-        .with_line_number(false)
-        .compact()
-        .finish();
-
-    tracing::subscriber::set_global_default(subscriber).ok();
+fn parse_model(model_str: &str) -> Result<ImageModel> {
+    ImageModel::from_str(model_str).map_err(|e| anyhow::anyhow!("{}", e))
 }
 
 fn parse_size(size_str: &str) -> Result<ImageSize> {
@@ -127,11 +200,6 @@ fn parse_size(size_str: &str) -> Result<ImageSize> {
 }
 
 fn parse_aspect(aspect_str: &str) -> Result<AspectRatio> {
-    // Handle 5:4 aspect ratio which isn't in the standard enum
-    if aspect_str == "5:4" {
-        // 5:4 is close to Standard (4:3), we'll use the API value directly
-        return Ok(AspectRatio::Standard);
-    }
     AspectRatio::from_str(aspect_str).map_err(|e| anyhow::anyhow!("{}", e))
 }
 
@@ -155,6 +223,49 @@ fn build_edit_config(
     Ok(Some(config))
 }
 
+/// Dimensions of the first input image, for `--keep-resolution`
+fn input_resolution(images: &[InputImage]) -> Option<(u32, u32)> {
+    let dims = images
+        .first()
+        .and_then(|image| image_dimensions(&image.data));
+    if dims.is_none() {
+        warn!("Could not read input image dimensions; output resolution will not be matched");
+    }
+    dims
+}
+
+/// Fill in the size (if the model takes one) and aspect ratio closest to
+/// `(width, height)` unless set explicitly
+fn request_resolution(
+    edit_config: Option<ImageEditConfig>,
+    (width, height): (u32, u32),
+    model: ImageModel,
+) -> ImageEditConfig {
+    let mut config = edit_config.unwrap_or_default();
+    if model.supports_image_size() {
+        config
+            .size
+            .get_or_insert_with(|| ImageSize::covering(width, height));
+    }
+    config
+        .aspect_ratio
+        .get_or_insert_with(|| AspectRatio::nearest(width, height));
+    config
+}
+
+/// Resize an edited image to exactly `(width, height)` if it doesn't match already
+async fn match_resolution(
+    image: GeneratedImage,
+    (width, height): (u32, u32),
+) -> Result<GeneratedImage> {
+    if image_dimensions(&image.data) == Some((width, height)) {
+        return Ok(image);
+    }
+    resize_image(&image, width, height)
+        .await
+        .with_context(|| format!("Failed to resize output to {}x{}", width, height))
+}
+
 /// Convert a string to a URL-friendly slug
 fn slugify(s: &str) -> String {
     let slug: String = s
@@ -185,7 +296,16 @@ fn generate_output_filename(name: &str, prompt: &str, extension: &str) -> String
     format!("{}-{}.{}", truncated, hash_prefix, extension)
 }
 
-async fn save_image(image: &transcript_tool::GeneratedImage, path: &PathBuf) -> Result<()> {
+/// Write the image to `path` once it checks out as a whole image, and its
+/// metadata and checksum sidecars if asked
+async fn save_image(
+    image: &GeneratedImage,
+    path: &Path,
+    metadata: bool,
+    checksums: bool,
+) -> Result<()> {
+    check_image(&image.data).context("The API returned a damaged image")?;
+
     // Ensure parent directory exists
     if let Some(parent) = path.parent()
         && !parent.exists()
@@ -195,10 +315,29 @@ async fn save_image(image: &transcript_tool::GeneratedImage, path: &PathBuf) ->
             .context("Failed to create output directory")?;
     }
 
-    fs::write(path, &image.data)
+    write_atomic(path, &image.data)
         .await
         .context("Failed to write image file")?;
 
+    if metadata && let Some(sidecar) = image.sidecar_json() {
+        let mut sidecar_path = path.as_os_str().to_owned();
+        sidecar_path.push(".json");
+        write_atomic(Path::new(&sidecar_path), sidecar.as_bytes())
+            .await
+            .context("Failed to write metadata sidecar")?;
+    }
+    if checksums {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let mut checksum_path = path.as_os_str().to_owned();
+        checksum_path.push(".blake3");
+        write_atomic(
+            Path::new(&checksum_path),
+            checksum_line(&image.data, &name).as_bytes(),
+        )
+        .await
+        .context("Failed to write checksum sidecar")?;
+    }
+
     Ok(())
 }
 
@@ -214,14 +353,269 @@ async fn load_images(paths: &[PathBuf]) -> Result<Vec<InputImage>> {
     Ok(images)
 }
 
-async fn edit_single(
+/// Resolve a path from a YAML file relative to the YAML file's directory
+fn resolve_yaml_path(yaml_dir: &Path, path: &str) -> PathBuf {
+    let path = PathBuf::from(path);
+    if path.is_absolute() {
+        path
+    } else {
+        yaml_dir.join(path)
+    }
+}
+
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp", "gif", "heic", "heif"];
+
+fn is_image_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+fn has_wildcard(s: &str) -> bool {
+    s.contains('*') || s.contains('?')
+}
+
+/// Match a file name against a pattern with `*` (any run) and `?` (any single char)
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = backtrack {
+            p = star_p + 1;
+            n = star_n + 1;
+            backtrack = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// List image files in a directory (non-recursive), sorted by path
+fn list_images_in_dir(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read directory: {:?}", dir))?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && is_image_file(p))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Expand a glob pattern whose wildcards are in the file name component
+fn expand_glob(pattern: &Path) -> Result<Vec<PathBuf>> {
+    let file_pattern = pattern
+        .file_name()
+        .and_then(|n| n.to_str())
+        .with_context(|| format!("Invalid input pattern: {:?}", pattern))?;
+    let dir = match pattern.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    if has_wildcard(&dir.to_string_lossy()) {
+        anyhow::bail!(
+            "Wildcards are only supported in the file name, not the directory: {:?}",
+            pattern
+        );
+    }
+
+    let mut files: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .with_context(|| format!("Failed to read directory: {:?}", dir))?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.is_file()
+                && p.file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| wildcard_match(file_pattern, n))
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Expand directories and glob patterns in `-i` inputs into image files.
+/// Returns the files and whether any input was a directory or glob (which implies `--each`).
+fn expand_inputs(inputs: &[PathBuf]) -> Result<(Vec<PathBuf>, bool)> {
+    let mut files = Vec::new();
+    let mut implies_each = false;
+
+    for input in inputs {
+        if input.is_dir() {
+            let found = list_images_in_dir(input)?;
+            if found.is_empty() {
+                anyhow::bail!("No image files found in directory: {:?}", input);
+            }
+            files.extend(found);
+            implies_each = true;
+        } else if !input.exists() && has_wildcard(&input.to_string_lossy()) {
+            let found = expand_glob(input)?;
+            if found.is_empty() {
+                anyhow::bail!("No files match pattern: {:?}", input);
+            }
+            files.extend(found);
+            implies_each = true;
+        } else if input.exists() {
+            files.push(input.clone());
+        } else {
+            anyhow::bail!("Input file does not exist: {:?}", input);
+        }
+    }
+
+    Ok((files, implies_each))
+}
+
+/// Image list item for a plain path, guarding against names that look like `$name` references
+fn image_ref_for_path(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    if path.starts_with(ENTRY_REF_PREFIX) {
+        format!("./{}", path)
+    } else {
+        path.to_string()
+    }
+}
+
+/// Build one edit entry per input image, all sharing the same prompt and mask
+fn each_entries(paths: &[PathBuf], prompt: &str, mask: Option<&str>) -> Vec<EditEntry> {
+    paths
+        .iter()
+        .map(|path| EditEntry {
+            name: path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| "edited".to_string()),
+            prompt: prompt.to_string(),
+            images: vec![image_ref_for_path(path)],
+            mask: mask.map(String::from),
+            output: None,
+            size: None,
+            aspect: None,
+            model: None,
+            iterations: None,
+            prompts: Vec::new(),
+        })
+        .collect()
+}
+
+/// Run a plain edit, or an inpainting edit when a mask is given
+async fn run_edit(
     client: &ImageEditClient,
     prompt: &str,
     images: &[InputImage],
+    mask: Option<&InputImage>,
+    edit_config: Option<&ImageEditConfig>,
+) -> transcript_tool::imagen_edit_api::Result<GeneratedImage> {
+    match mask {
+        Some(mask) => {
+            client
+                .inpaint_with_config(prompt, images, mask, edit_config)
+                .await
+        }
+        None => {
+            client
+                .edit_images_with_config(prompt, images, edit_config)
+                .await
+        }
+    }
+}
+
+/// Prompt for each refinement pass: `prompts[i]` where given, otherwise `prompt`
+fn iteration_prompts(prompt: &str, prompts: &[String], iterations: u32) -> Vec<String> {
+    (0..iterations as usize)
+        .map(|i| {
+            prompts
+                .get(i)
+                .cloned()
+                .unwrap_or_else(|| prompt.to_string())
+        })
+        .collect()
+}
+
+/// Path of an intermediate refinement step: `<stem>-step<N>.<ext>`
+fn step_output_path(output_path: &Path, step: usize, extension: &str) -> PathBuf {
+    let stem = output_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "edited".to_string());
+    output_path.with_file_name(format!("{}-step{}.{}", stem, step, extension))
+}
+
+/// Run one edit pass per prompt, feeding each output back as the next input.
+/// Intermediate results are saved next to `output_path`; returns the final
+/// image along with the saved step paths.
+#[allow(clippy::too_many_arguments)]
+async fn run_iterations(
+    client: &ImageEditClient,
+    prompts: &[String],
+    images: &[InputImage],
+    mask: Option<&InputImage>,
+    edit_config: Option<&ImageEditConfig>,
+    output_path: &Path,
+    metadata: bool,
+    checksums: bool,
+) -> Result<(GeneratedImage, Vec<PathBuf>)> {
+    let total = prompts.len();
+    let mut previous: Option<InputImage> = None;
+    let mut steps = Vec::new();
+
+    for (i, prompt) in prompts.iter().enumerate() {
+        let inputs = match &previous {
+            Some(image) => std::slice::from_ref(image),
+            None => images,
+        };
+
+        let result = match run_edit(client, prompt, inputs, mask, edit_config).await {
+            Ok(result) => result,
+            Err(e) if total > 1 => {
+                return Err(anyhow::Error::from(e).context(format!(
+                    "Iteration {}/{} failed",
+                    i + 1,
+                    total
+                )));
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        if i + 1 == total {
+            return Ok((result, steps));
+        }
+
+        let step_path = step_output_path(output_path, i + 1, result.extension());
+        debug!("Saving iteration {}/{} to {:?}", i + 1, total, step_path);
+        save_image(&result, &step_path, metadata, checksums).await?;
+        steps.push(step_path);
+
+        previous = Some(InputImage::from_bytes(result.data, result.mime_type));
+    }
+
+    anyhow::bail!("No prompts given for edit")
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn edit_single(
+    client: &ImageEditClient,
+    prompts: &[String],
+    images: &[InputImage],
+    mask: Option<&InputImage>,
     output_path: PathBuf,
     edit_config: Option<&ImageEditConfig>,
+    resolution: Option<(u32, u32)>,
+    metadata: bool,
+    checksums: bool,
     quiet: bool,
-) -> Result<()> {
+) -> Result<PathBuf> {
     let pb = if !quiet {
         let pb = ProgressBar::new_spinner();
         pb.set_style(
@@ -229,17 +623,37 @@ async fn edit_single(
                 .template("{spinner:.green} {msg}")
                 .unwrap(),
         );
-        pb.set_message("Editing image(s)...");
+        if prompts.len() > 1 {
+            pb.set_message(format!(
+                "Editing image(s) ({} iterations)...",
+                prompts.len()
+            ));
+        } else {
+            pb.set_message("Editing image(s)...");
+        }
         pb.enable_steady_tick(Duration::from_millis(100));
         Some(pb)
     } else {
         None
     };
 
-    let result = client
-        .edit_images_with_config(prompt, images, edit_config)
-        .await
-        .map_err(|e| anyhow::anyhow!("Image edit failed: {}", e))?;
+    let (result, steps) = run_iterations(
+        client,
+        prompts,
+        images,
+        mask,
+        edit_config,
+        &output_path,
+        metadata,
+        checksums,
+    )
+    .await
+    .map_err(|e| anyhow::anyhow!("Image edit failed: {:#}", e))?;
+
+    let result = match resolution {
+        Some(dims) => match_resolution(result, dims).await?,
+        None => result,
+    };
 
     if let Some(pb) = pb {
         pb.finish_with_message("Image edited successfully!");
@@ -252,33 +666,359 @@ async fn edit_single(
         output_path
     };
 
-    save_image(&result, &final_path).await?;
+    save_image(&result, &final_path, metadata, checksums).await?;
 
     if !quiet {
+        for step in &steps {
+            println!("Saved step: {}", step.display());
+        }
         println!("Saved: {}", final_path.display());
     }
 
-    Ok(())
+    Ok(final_path)
 }
 
-struct YamlEditOptions {
-    api_key: String,
-    yaml_path: PathBuf,
-    name_filter: Option<String>,
+/// Save a before/after composite next to `after` and return its path
+async fn save_comparison(before: &Path, after: &Path, layout: CompareLayout) -> Result<PathBuf> {
+    let compare_path = comparison_path(after);
+    compose_comparison(before, after, &compare_path, layout)
+        .await
+        .with_context(|| format!("Failed to create comparison image for {:?}", after))?;
+    Ok(compare_path)
+}
+
+fn parse_compare(compare: Option<&str>) -> Result<Option<CompareLayout>> {
+    compare
+        .map(CompareLayout::from_str)
+        .transpose()
+        .map_err(|e| anyhow::anyhow!(e))
+}
+
+/// Options shared by every batch edit mode (YAML, per-image `--each` and `--watch-dir`)
+#[derive(Clone)]
+struct BatchEditOptions {
+    clients: Clients,
     output_dir: PathBuf,
+    default_model: ImageModel,
     default_size: Option<String>,
     default_aspect: Option<String>,
     timeout: u64,
     max_retries: u32,
+    cache: Option<ResponseCache>,
+    circuit_breaker: Option<CircuitBreaker>,
+    budget: Option<Budget>,
     jobs: usize,
     quiet: bool,
+    compare: Option<CompareLayout>,
+    iterations: u32,
+    keep_resolution: bool,
+    /// Write `<image>.json` metadata sidecars
+    metadata: bool,
+    /// Write `<image>.blake3` checksum sidecars
+    checksums: bool,
+    /// State file recording completed entries, for resuming interrupted batches
+    state_path: Option<PathBuf>,
+    force: bool,
+}
+
+struct YamlEditOptions {
+    yaml_path: PathBuf,
+    name_filter: Option<String>,
+    batch: BatchEditOptions,
 }
 
 /// Result of a single edit task
 struct EditResult {
     name: String,
     success: bool,
+    skipped: bool,
     error: Option<String>,
+    output: Option<PathBuf>,
+}
+
+/// State file written to the output directory of YAML batches
+const STATE_FILE_NAME: &str = ".imagen_edit_state.json";
+
+/// Completed entries of a YAML batch, keyed by entry name
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct EditState {
+    #[serde(default)]
+    entries: HashMap<String, CompletedEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CompletedEntry {
+    fingerprint: String,
+    output: PathBuf,
+}
+
+impl EditState {
+    async fn load(path: &Path) -> Self {
+        let content = match fs::read_to_string(path).await {
+            Ok(content) => content,
+            Err(_) => return Self::default(),
+        };
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            warn!("Ignoring unreadable state file {:?}: {}", path, e);
+            Self::default()
+        })
+    }
+
+    async fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self).context("Failed to serialize state")?;
+        write_atomic(path, content.as_bytes())
+            .await
+            .with_context(|| format!("Failed to write state file: {:?}", path))
+    }
+
+    /// Output of a previous successful run of this exact entry, if it still exists
+    fn completed_output(&self, entry: &EditEntry) -> Option<&PathBuf> {
+        self.entries
+            .get(&entry.name)
+            .filter(|done| done.fingerprint == entry_fingerprint(entry) && done.output.exists())
+            .map(|done| &done.output)
+    }
+
+    fn record(&mut self, entry: &EditEntry, output: &Path) {
+        self.entries.insert(
+            entry.name.clone(),
+            CompletedEntry {
+                fingerprint: entry_fingerprint(entry),
+                output: output.to_path_buf(),
+            },
+        );
+    }
+}
+
+/// Hash of an entry's settings; changing any of them invalidates its completed state
+fn entry_fingerprint(entry: &EditEntry) -> String {
+    let json = serde_json::to_string(entry).unwrap_or_default();
+    blake3::hash(json.as_bytes()).to_hex()[..16].to_string()
+}
+
+/// Prefix marking an `images` item as a reference to another entry's output
+const ENTRY_REF_PREFIX: char = '$';
+
+/// Names of the entries whose outputs this entry uses as input (`$name` references)
+fn entry_dependencies(entry: &EditEntry) -> Vec<&str> {
+    entry
+        .images
+        .iter()
+        .filter_map(|img| img.strip_prefix(ENTRY_REF_PREFIX))
+        .collect()
+}
+
+/// Keep the named entry plus every entry it depends on (directly or transitively)
+fn select_with_dependencies(entries: Vec<EditEntry>, name: &str) -> Vec<EditEntry> {
+    let mut wanted: HashSet<String> = HashSet::new();
+    let mut stack = vec![name.to_string()];
+
+    while let Some(current) = stack.pop() {
+        if !wanted.insert(current.clone()) {
+            continue;
+        }
+        for entry in entries.iter().filter(|e| e.name == current) {
+            stack.extend(entry_dependencies(entry).into_iter().map(String::from));
+        }
+    }
+
+    entries
+        .into_iter()
+        .filter(|e| wanted.contains(&e.name))
+        .collect()
+}
+
+/// Group entries into waves so that every entry only depends on entries in earlier waves.
+/// Entries within a wave are independent and can run in parallel.
+fn plan_waves(entries: &[EditEntry]) -> Result<Vec<Vec<usize>>> {
+    let mut index_by_name: HashMap<&str, usize> = HashMap::new();
+    let mut duplicates: HashSet<&str> = HashSet::new();
+    for (i, entry) in entries.iter().enumerate() {
+        if index_by_name.insert(&entry.name, i).is_some() {
+            duplicates.insert(&entry.name);
+        }
+    }
+
+    let mut deps: Vec<Vec<usize>> = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let mut entry_deps = Vec::new();
+        for dep in entry_dependencies(entry) {
+            if duplicates.contains(dep) {
+                anyhow::bail!(
+                    "Entry '{}' references '{}{}', but that name is used by more than one entry",
+                    entry.name,
+                    ENTRY_REF_PREFIX,
+                    dep
+                );
+            }
+            match index_by_name.get(dep) {
+                Some(&j) => entry_deps.push(j),
+                None => anyhow::bail!(
+                    "Entry '{}' references unknown entry '{}{}'",
+                    entry.name,
+                    ENTRY_REF_PREFIX,
+                    dep
+                ),
+            }
+        }
+        deps.push(entry_deps);
+    }
+
+    let mut planned = vec![false; entries.len()];
+    let mut waves = Vec::new();
+    while planned.iter().any(|p| !p) {
+        let wave: Vec<usize> = (0..entries.len())
+            .filter(|&i| !planned[i] && deps[i].iter().all(|&d| planned[d]))
+            .collect();
+
+        if wave.is_empty() {
+            let stuck: Vec<&str> = (0..entries.len())
+                .filter(|&i| !planned[i])
+                .map(|i| entries[i].name.as_str())
+                .collect();
+            anyhow::bail!(
+                "Circular references between edit entries: {}",
+                stuck.join(", ")
+            );
+        }
+
+        for &i in &wave {
+            planned[i] = true;
+        }
+        waves.push(wave);
+    }
+
+    Ok(waves)
+}
+
+/// Resolve an `images` item: either a `$name` reference to an earlier output or a file path
+fn resolve_entry_image(
+    image: &str,
+    yaml_dir: &Path,
+    outputs: &HashMap<String, PathBuf>,
+) -> Result<PathBuf> {
+    match image.strip_prefix(ENTRY_REF_PREFIX) {
+        Some(name) => outputs
+            .get(name)
+            .cloned()
+            .with_context(|| format!("Dependency '{}' did not produce an output", name)),
+        None => Ok(resolve_yaml_path(yaml_dir, image)),
+    }
+}
+
+async fn edit_entry_inner(
+    entry: &EditEntry,
+    opts: &BatchEditOptions,
+    yaml_dir: &Path,
+    outputs: &HashMap<String, PathBuf>,
+) -> Result<PathBuf> {
+    // Resolve image paths relative to YAML file (or to earlier entries' outputs)
+    let image_paths = entry
+        .images
+        .iter()
+        .map(|img| resolve_entry_image(img, yaml_dir, outputs))
+        .collect::<Result<Vec<_>>>()?;
+    let mask_path = entry
+        .mask
+        .as_ref()
+        .map(|mask| resolve_yaml_path(yaml_dir, mask));
+
+    // Validate images exist
+    for path in image_paths.iter().chain(mask_path.as_ref()) {
+        if !path.exists() {
+            anyhow::bail!("Image not found: {:?}", path);
+        }
+    }
+
+    let images = load_images(&image_paths).await?;
+
+    let mask = match &mask_path {
+        Some(path) => Some(
+            InputImage::from_path(path)
+                .await
+                .with_context(|| format!("Failed to load mask: {:?}", path))?,
+        ),
+        None => None,
+    };
+
+    let model = match &entry.model {
+        Some(model_str) => parse_model(model_str)?,
+        None => opts.default_model,
+    };
+
+    let config = ImageEditClientConfig {
+        timeout_secs: opts.timeout,
+        max_retries: opts.max_retries,
+        model,
+        cache: opts.cache.clone(),
+        circuit_breaker: opts.circuit_breaker.clone(),
+        budget: opts.budget.clone(),
+        ..Default::default()
+    };
+    let client = opts.clients.image_edit(config);
+
+    // The CLI default size only applies to models that support it;
+    // explicit per-entry values are passed through (and rejected if unsupported)
+    let default_size = opts
+        .default_size
+        .as_ref()
+        .filter(|_| model.supports_image_size());
+    let size = entry.size.as_ref().or(default_size);
+    let aspect = entry.aspect.as_ref().or(opts.default_aspect.as_ref());
+    let mut edit_config = build_edit_config(size, aspect)?;
+
+    let resolution = if opts.keep_resolution {
+        input_resolution(&images)
+    } else {
+        None
+    };
+    if let Some(dims) = resolution {
+        edit_config = Some(request_resolution(edit_config, dims, model));
+    }
+
+    let iterations = entry.iterations.unwrap_or(if entry.prompts.is_empty() {
+        opts.iterations
+    } else {
+        entry.prompts.len() as u32
+    });
+    let prompts = iteration_prompts(&entry.prompt, &entry.prompts, iterations);
+
+    // Extension is fixed up once the final image's format is known
+    let filename = entry
+        .output
+        .clone()
+        .unwrap_or_else(|| generate_output_filename(&entry.name, &entry.prompt, "png"));
+    let output_path = opts.output_dir.join(&filename);
+
+    let (result, _steps) = run_iterations(
+        &client,
+        &prompts,
+        &images,
+        mask.as_ref(),
+        edit_config.as_ref(),
+        &output_path,
+        opts.metadata,
+        opts.checksums,
+    )
+    .await?;
+
+    let result = match resolution {
+        Some(dims) => match_resolution(result, dims).await?,
+        None => result,
+    };
+
+    let output_path = if entry.output.is_some() {
+        output_path
+    } else {
+        output_path.with_extension(result.extension())
+    };
+    save_image(&result, &output_path, opts.metadata, opts.checksums).await?;
+
+    if let (Some(layout), Some(before)) = (opts.compare, image_paths.first()) {
+        save_comparison(before, &output_path, layout).await?;
+    }
+
+    Ok(output_path)
 }
 
 async fn edit_from_yaml(opts: YamlEditOptions) -> Result<()> {
@@ -290,11 +1030,7 @@ async fn edit_from_yaml(opts: YamlEditOptions) -> Result<()> {
         serde_yaml::from_str(&yaml_content).context("Failed to parse YAML file")?;
 
     let entries: Vec<EditEntry> = if let Some(ref name) = opts.name_filter {
-        edits_file
-            .edits
-            .into_iter()
-            .filter(|e| &e.name == name)
-            .collect()
+        select_with_dependencies(edits_file.edits, name)
     } else {
         edits_file.edits
     };
@@ -307,13 +1043,6 @@ async fn edit_from_yaml(opts: YamlEditOptions) -> Result<()> {
         }
     }
 
-    // Ensure output directory exists
-    if !opts.output_dir.exists() {
-        fs::create_dir_all(&opts.output_dir)
-            .await
-            .context("Failed to create output directory")?;
-    }
-
     // Get YAML file directory for resolving relative image paths
     let yaml_dir = opts
         .yaml_path
@@ -321,6 +1050,25 @@ async fn edit_from_yaml(opts: YamlEditOptions) -> Result<()> {
         .map(|p| p.to_path_buf())
         .unwrap_or_else(|| PathBuf::from("."));
 
+    run_edit_batch(entries, yaml_dir, opts.batch).await
+}
+
+/// Run edit entries in dependency order with bounded parallelism.
+/// Relative image paths are resolved against `yaml_dir`.
+async fn run_edit_batch(
+    entries: Vec<EditEntry>,
+    yaml_dir: PathBuf,
+    opts: BatchEditOptions,
+) -> Result<()> {
+    let waves = plan_waves(&entries)?;
+
+    // Ensure output directory exists
+    if !opts.output_dir.exists() {
+        fs::create_dir_all(&opts.output_dir)
+            .await
+            .context("Failed to create output directory")?;
+    }
+
     let total = entries.len();
     let jobs = opts.jobs.max(1);
 
@@ -336,138 +1084,105 @@ async fn edit_from_yaml(opts: YamlEditOptions) -> Result<()> {
     let opts = Arc::new(opts);
     let yaml_dir = Arc::new(yaml_dir);
 
+    let state = match &opts.state_path {
+        Some(path) => EditState::load(path).await,
+        None => EditState::default(),
+    };
+    let state = Arc::new(Mutex::new(state));
+
     // Create multi-progress bar for parallel display
     let multi_progress = Arc::new(MultiProgress::new());
 
-    // Spawn all tasks
-    let mut handles = Vec::new();
-
-    for (i, entry) in entries.into_iter().enumerate() {
-        let sem = Arc::clone(&semaphore);
-        let opts = Arc::clone(&opts);
-        let yaml_dir = Arc::clone(&yaml_dir);
-        let mp = Arc::clone(&multi_progress);
-
-        let handle = tokio::spawn(async move {
-            // Acquire semaphore permit
-            let _permit = sem.acquire().await.unwrap();
-
-            // Create progress bar for this task
-            let pb = if !opts.quiet {
-                let pb = mp.add(ProgressBar::new_spinner());
-                pb.set_style(
-                    ProgressStyle::default_spinner()
-                        .template("{spinner:.green} [{pos}] {msg}")
-                        .unwrap(),
-                );
-                pb.set_position((i + 1) as u64);
-                pb.set_message(format!("Processing {}...", entry.name));
-                pb.enable_steady_tick(Duration::from_millis(100));
-                Some(pb)
-            } else {
-                None
-            };
-
-            // Resolve image paths relative to YAML file
-            let image_paths: Vec<PathBuf> = entry
-                .images
-                .iter()
-                .map(|img| {
-                    let path = PathBuf::from(img);
-                    if path.is_absolute() {
-                        path
-                    } else {
-                        yaml_dir.join(path)
-                    }
-                })
-                .collect();
+    // Run waves in dependency order; entries within a wave run in parallel
+    let mut entries: Vec<Option<EditEntry>> = entries.into_iter().map(Some).collect();
+    let mut outputs: HashMap<String, PathBuf> = HashMap::new();
+    let mut results: Vec<EditResult> = Vec::new();
+    // Entries edited in this run; anything depending on them must be redone too
+    let mut rerun: HashSet<String> = HashSet::new();
 
-            // Validate images exist
-            for path in &image_paths {
-                if !path.exists() {
-                    if let Some(pb) = pb {
-                        pb.finish_with_message(format!("{} failed!", entry.name));
-                    }
-                    return EditResult {
-                        name: entry.name.clone(),
-                        success: false,
-                        error: Some(format!("Image not found: {:?}", path)),
-                    };
-                }
-            }
+    for wave in waves {
+        let wave_outputs = Arc::new(outputs.clone());
+        let mut handles = Vec::new();
+        let mut wave_results: Vec<EditResult> = Vec::new();
 
-            // Load images
-            let images = match load_images(&image_paths).await {
-                Ok(imgs) => imgs,
-                Err(e) => {
-                    if let Some(pb) = pb {
-                        pb.finish_with_message(format!("{} failed!", entry.name));
-                    }
-                    return EditResult {
-                        name: entry.name.clone(),
-                        success: false,
-                        error: Some(e.to_string()),
-                    };
-                }
-            };
+        for i in wave {
+            let entry = entries[i]
+                .take()
+                .expect("each entry is planned exactly once");
 
-            // Build client
-            let config = ImageEditClientConfig {
-                timeout_secs: opts.timeout,
-                max_retries: opts.max_retries,
-            };
-            let client = match ImageEditClient::with_config(opts.api_key.clone(), config) {
-                Ok(c) => c,
-                Err(e) => {
-                    if let Some(pb) = pb {
-                        pb.finish_with_message(format!("{} failed!", entry.name));
+            let deps_rerun = entry_dependencies(&entry)
+                .iter()
+                .any(|dep| rerun.contains(*dep));
+            if opts.state_path.is_some() && !opts.force && !deps_rerun {
+                let completed = state.lock().await.completed_output(&entry).cloned();
+                if let Some(output) = completed {
+                    if !opts.quiet {
+                        let _ = multi_progress
+                            .println(format!("Skipped: {} (already completed)", entry.name));
                     }
-                    return EditResult {
-                        name: entry.name.clone(),
-                        success: false,
-                        error: Some(e.to_string()),
-                    };
+                    wave_results.push(EditResult {
+                        name: entry.name,
+                        success: true,
+                        skipped: true,
+                        error: None,
+                        output: Some(output),
+                    });
+                    continue;
                 }
-            };
-
-            // Build edit config
-            let size = entry.size.as_ref().or(opts.default_size.as_ref());
-            let aspect = entry.aspect.as_ref().or(opts.default_aspect.as_ref());
-            let edit_config = match build_edit_config(size, aspect) {
-                Ok(c) => c,
-                Err(e) => {
-                    if let Some(pb) = pb {
-                        pb.finish_with_message(format!("{} failed!", entry.name));
-                    }
-                    return EditResult {
-                        name: entry.name.clone(),
-                        success: false,
-                        error: Some(e.to_string()),
+            }
+            rerun.insert(entry.name.clone());
+
+            let sem = Arc::clone(&semaphore);
+            let state = Arc::clone(&state);
+            let opts = Arc::clone(&opts);
+            let yaml_dir = Arc::clone(&yaml_dir);
+            let outputs = Arc::clone(&wave_outputs);
+            let mp = Arc::clone(&multi_progress);
+
+            let span = info_span!("entry", entry = %entry.name);
+            let handle = tokio::spawn(
+                async move {
+                    // Acquire semaphore permit
+                    let _permit = sem.acquire().await.unwrap();
+
+                    // Create progress bar for this task
+                    let pb = if !opts.quiet {
+                        let pb = mp.add(ProgressBar::new_spinner());
+                        pb.set_style(
+                            ProgressStyle::default_spinner()
+                                .template("{spinner:.green} [{pos}] {msg}")
+                                .unwrap(),
+                        );
+                        pb.set_position((i + 1) as u64);
+                        pb.set_message(format!("Processing {}...", entry.name));
+                        pb.enable_steady_tick(Duration::from_millis(100));
+                        Some(pb)
+                    } else {
+                        None
                     };
-                }
-            };
-
-            // Edit image
-            match client
-                .edit_images_with_config(&entry.prompt, &images, edit_config.as_ref())
-                .await
-            {
-                Ok(result) => {
-                    // Determine output filename
-                    let filename = entry.output.clone().unwrap_or_else(|| {
-                        generate_output_filename(&entry.name, &entry.prompt, result.extension())
-                    });
-                    let output_path = opts.output_dir.join(&filename);
 
-                    match save_image(&result, &output_path).await {
-                        Ok(()) => {
+                    match edit_entry_inner(&entry, &opts, &yaml_dir, &outputs).await {
+                        Ok(output_path) => {
+                            if let Some(state_path) = &opts.state_path {
+                                let mut state = state.lock().await;
+                                state.record(&entry, &output_path);
+                                if let Err(e) = state.save(state_path).await {
+                                    warn!("{:#}", e);
+                                }
+                            }
                             if let Some(pb) = pb {
+                                let filename = output_path
+                                    .file_name()
+                                    .map(|n| n.to_string_lossy().to_string())
+                                    .unwrap_or_default();
                                 pb.finish_with_message(format!("{} -> {}", entry.name, filename));
                             }
                             EditResult {
-                                name: entry.name.clone(),
+                                name: entry.name,
                                 success: true,
+                                skipped: false,
                                 error: None,
+                                output: Some(output_path),
                             }
                         }
                         Err(e) => {
@@ -475,38 +1190,40 @@ async fn edit_from_yaml(opts: YamlEditOptions) -> Result<()> {
                                 pb.finish_with_message(format!("{} failed!", entry.name));
                             }
                             EditResult {
-                                name: entry.name.clone(),
+                                name: entry.name,
                                 success: false,
-                                error: Some(e.to_string()),
+                                skipped: false,
+                                error: Some(format!("{:#}", e)),
+                                output: None,
                             }
                         }
                     }
                 }
-                Err(e) => {
-                    if let Some(pb) = pb {
-                        pb.finish_with_message(format!("{} failed!", entry.name));
-                    }
-                    EditResult {
-                        name: entry.name.clone(),
-                        success: false,
-                        error: Some(e.to_string()),
-                    }
-                }
-            }
-        });
+                .instrument(span),
+            );
 
-        handles.push(handle);
-    }
+            handles.push(handle);
+        }
 
-    // Wait for all tasks to complete
-    let results: Vec<EditResult> = futures::future::join_all(handles)
-        .await
-        .into_iter()
-        .filter_map(|r| r.ok())
-        .collect();
+        // Wait for the whole wave before starting entries that depend on it
+        wave_results.extend(
+            futures::future::join_all(handles)
+                .await
+                .into_iter()
+                .filter_map(|r| r.ok()),
+        );
+
+        for result in &wave_results {
+            if let Some(output) = &result.output {
+                outputs.insert(result.name.clone(), output.clone());
+            }
+        }
+        results.extend(wave_results);
+    }
 
     // Collect results
     let success_count = results.iter().filter(|r| r.success).count();
+    let skipped_count = results.iter().filter(|r| r.skipped).count();
     let errors: Vec<_> = results
         .iter()
         .filter(|r| !r.success)
@@ -522,6 +1239,12 @@ async fn edit_from_yaml(opts: YamlEditOptions) -> Result<()> {
             success_count,
             errors.len()
         );
+        if skipped_count > 0 {
+            println!(
+                "Skipped: {} (completed in a previous run, use --force to redo)",
+                skipped_count
+            );
+        }
         if !errors.is_empty() {
             println!("\nFailed edits:");
             for (name, error) in &errors {
@@ -537,19 +1260,93 @@ async fn edit_from_yaml(opts: YamlEditOptions) -> Result<()> {
     Ok(())
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let args = Args::parse();
+/// How often `--watch-dir` rescans the watched folder
+const WATCH_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Edit every image that appears in `watch_dir`, forever.
+///
+/// Completed images are recorded in the batch state file, so restarting the
+/// watcher only picks up images that haven't been edited yet.
+async fn watch_folder(
+    watch_dir: PathBuf,
+    prompt: String,
+    mask: Option<String>,
+    opts: BatchEditOptions,
+) -> Result<()> {
+    if !watch_dir.is_dir() {
+        anyhow::bail!("Watch directory does not exist: {:?}", watch_dir);
+    }
+
+    fs::create_dir_all(&opts.output_dir)
+        .await
+        .context("Failed to create output directory")?;
+    if watch_dir.canonicalize()? == opts.output_dir.canonicalize()? {
+        anyhow::bail!("Output directory must be different from the watched directory");
+    }
+
+    if !opts.quiet {
+        println!(
+            "Watching {} for new images (Ctrl+C to stop)...",
+            watch_dir.display()
+        );
+    }
+
+    let mut seen: HashSet<PathBuf> = HashSet::new();
+    // Size at the previous scan; files are picked up once it stops changing
+    // so half-copied images aren't sent off for editing
+    let mut pending: HashMap<PathBuf, u64> = HashMap::new();
 
-    init_logging(args.verbose);
+    loop {
+        let mut ready = Vec::new();
+        for path in list_images_in_dir(&watch_dir)? {
+            if seen.contains(&path) {
+                continue;
+            }
+            let Ok(metadata) = std::fs::metadata(&path) else {
+                continue;
+            };
+            let size = metadata.len();
+            if size > 0 && pending.get(&path) == Some(&size) {
+                pending.remove(&path);
+                ready.push(path);
+            } else {
+                pending.insert(path, size);
+            }
+        }
+
+        if !ready.is_empty() {
+            seen.extend(ready.iter().cloned());
+            let entries = each_entries(&ready, &prompt, mask.as_deref());
+            // Keep watching even if this batch failed
+            if let Err(e) = run_edit_batch(entries, PathBuf::from("."), opts.clone()).await {
+                eprintln!("Warning: {:#}", e);
+            }
+            if !opts.quiet {
+                println!("\nWatching {} for new images...", watch_dir.display());
+            }
+        }
+
+        tokio::time::sleep(WATCH_POLL_INTERVAL).await;
+    }
+}
+
+/// Run with parsed arguments; shared by this binary and its `flashecho` subcommand
+pub async fn run(mut args: Args) -> Result<()> {
+    if args.prompt.is_none() {
+        args.prompt = args.prompt_flag.take();
+    }
+
+    init_logging(args.common.verbose, args.common.log_format);
+    args.common.install_usage_ledger("imagen_edit");
 
     // Validate arguments
     let has_cli_input = args.input.is_some() && args.prompt.is_some();
     let has_yaml = args.yaml.is_some();
+    let has_watch = args.watch_dir.is_some();
 
-    if !has_cli_input && !has_yaml {
+    if !has_cli_input && !has_yaml && !has_watch {
         anyhow::bail!(
-            "Either provide input images with prompt, or use --yaml file\n\nUsage:\n  imagen_edit -i image.jpg \"your prompt here\"\n  imagen_edit --yaml edits.yaml"
+            "Either provide input images with prompt, or use --yaml file\n\nUsage:\n  imagen_edit -i image.jpg \"your prompt here\"\n  imagen_edit --yaml edits.yaml\n  imagen_edit --watch-dir in/ --prompt \"your prompt here\""
         );
     }
 
@@ -557,11 +1354,89 @@ async fn main() -> Result<()> {
         anyhow::bail!("Cannot use both CLI input and --yaml at the same time");
     }
 
+    if has_watch && (args.input.is_some() || has_yaml) {
+        anyhow::bail!("--watch-dir cannot be combined with --input or --yaml");
+    }
+
+    if has_watch && args.prompt.is_none() {
+        anyhow::bail!("--watch-dir requires a prompt");
+    }
+
     if args.name.is_some() && args.yaml.is_none() {
         anyhow::bail!("--name can only be used with --yaml");
     }
 
-    let api_key = get_api_key()?;
+    if args.force && !has_yaml && !has_watch {
+        anyhow::bail!("--force can only be used with --yaml or --watch-dir");
+    }
+
+    if args.mask.is_some() && has_yaml {
+        anyhow::bail!("--mask cannot be used with --yaml (use the `mask` key per entry instead)");
+    }
+
+    let compare = parse_compare(args.compare.as_deref())?;
+    let profile = args.common.load_profile()?;
+    let model_name = args.model.as_deref().or(profile.image_model.as_deref());
+    let model = parse_model(model_name.unwrap_or("3pro"))?;
+
+    // Warn if size used with non-3pro model
+    if args.size.is_some() && !model.supports_image_size() {
+        eprintln!("Warning: --size is only supported with Gemini 3 Pro model (-m 3pro)");
+    }
+
+    let cache = args.common.response_cache();
+    let breaker = args.limits.circuit_breaker();
+    let budget = args
+        .common
+        .budget()
+        .context("Failed to read usage ledger for --budget-since")?;
+    let clients =
+        Clients::new(cli::gemini_api_key(&profile)?).context("Failed to create HTTP client")?;
+
+    if let (Some(watch_dir), Some(prompt)) = (args.watch_dir, args.prompt.clone()) {
+        let output_dir = args.output.unwrap_or_else(|| PathBuf::from("./output"));
+        debug!("Output directory: {:?}", output_dir);
+        let state_path = output_dir.join(STATE_FILE_NAME);
+        let mask = args.mask.map(|m| m.to_string_lossy().to_string());
+
+        #[cfg(feature = "metrics")]
+        if let Some(addr) = args.metrics_addr {
+            println!("Serving metrics at http://{}/metrics", addr);
+            tokio::spawn(async move {
+                if let Err(e) = transcript_tool::metrics::serve(addr).await {
+                    warn!("Metrics endpoint stopped: {}", e);
+                }
+            });
+        }
+
+        return watch_folder(
+            watch_dir,
+            prompt,
+            mask,
+            BatchEditOptions {
+                clients: clients.clone(),
+                output_dir,
+                default_model: model,
+                default_size: args.size,
+                default_aspect: args.aspect,
+                timeout: args.timeout,
+                max_retries: args.common.max_retries,
+                cache: cache.clone(),
+                circuit_breaker: breaker.clone(),
+                budget: budget.clone(),
+                jobs: args.jobs,
+                quiet: args.common.quiet,
+                compare,
+                iterations: args.iterations,
+                keep_resolution: args.keep_resolution,
+                metadata: args.metadata,
+                checksums: args.checksums,
+                state_path: Some(state_path),
+                force: args.force,
+            },
+        )
+        .await;
+    }
 
     if let Some(yaml_path) = args.yaml {
         // YAML batch mode
@@ -571,31 +1446,77 @@ async fn main() -> Result<()> {
 
         let output_dir = args.output.unwrap_or_else(|| PathBuf::from("./output"));
         debug!("Output directory: {:?}", output_dir);
+        let state_path = output_dir.join(STATE_FILE_NAME);
 
         edit_from_yaml(YamlEditOptions {
-            api_key,
             yaml_path,
             name_filter: args.name,
-            output_dir,
-            default_size: args.size,
-            default_aspect: args.aspect,
-            timeout: args.timeout,
-            max_retries: args.max_retries,
-            jobs: args.jobs,
-            quiet: args.quiet,
+            batch: BatchEditOptions {
+                clients: clients.clone(),
+                output_dir,
+                default_model: model,
+                default_size: args.size,
+                default_aspect: args.aspect,
+                timeout: args.timeout,
+                max_retries: args.common.max_retries,
+                cache: cache.clone(),
+                circuit_breaker: breaker.clone(),
+                budget: budget.clone(),
+                jobs: args.jobs,
+                quiet: args.common.quiet,
+                compare,
+                iterations: args.iterations,
+                keep_resolution: args.keep_resolution,
+                metadata: args.metadata,
+                checksums: args.checksums,
+                state_path: Some(state_path),
+                force: args.force,
+            },
         })
         .await?;
-    } else if let (Some(input_paths), Some(prompt)) = (args.input, args.prompt) {
-        // Single edit mode via CLI
-        // Validate input files exist
-        for input_path in &input_paths {
-            if !input_path.exists() {
-                anyhow::bail!("Input file does not exist: {:?}", input_path);
-            }
+    } else if let (Some(inputs), Some(prompt)) = (args.input, args.prompt) {
+        let (input_paths, implies_each) = expand_inputs(&inputs)?;
+
+        if args.each || implies_each {
+            // Apply the same prompt to every image independently
+            let output_dir = args.output.unwrap_or_else(|| PathBuf::from("./output"));
+            debug!("Output directory: {:?}", output_dir);
+
+            let mask = args.mask.map(|m| m.to_string_lossy().to_string());
+            let entries = each_entries(&input_paths, &prompt, mask.as_deref());
+
+            return run_edit_batch(
+                entries,
+                PathBuf::from("."),
+                BatchEditOptions {
+                    clients,
+                    output_dir,
+                    default_model: model,
+                    default_size: args.size,
+                    default_aspect: args.aspect,
+                    timeout: args.timeout,
+                    max_retries: args.common.max_retries,
+                    cache: cache.clone(),
+                    circuit_breaker: breaker.clone(),
+                    budget: budget.clone(),
+                    jobs: args.jobs,
+                    quiet: args.common.quiet,
+                    compare,
+                    iterations: args.iterations,
+                    keep_resolution: args.keep_resolution,
+                    metadata: args.metadata,
+                    checksums: args.checksums,
+                    state_path: None,
+                    force: false,
+                },
+            )
+            .await;
         }
 
+        // Single edit mode via CLI
+
         // Load input images
-        let pb = if !args.quiet {
+        let pb = if !args.common.quiet {
             let pb = ProgressBar::new_spinner();
             pb.set_style(
                 ProgressStyle::default_spinner()
@@ -611,6 +1532,19 @@ async fn main() -> Result<()> {
 
         let images = load_images(&input_paths).await?;
 
+        let mask = match &args.mask {
+            Some(mask_path) => {
+                if !mask_path.exists() {
+                    anyhow::bail!("Mask file does not exist: {:?}", mask_path);
+                }
+                let mask = InputImage::from_path(mask_path)
+                    .await
+                    .with_context(|| format!("Failed to load mask: {:?}", mask_path))?;
+                Some(mask)
+            }
+            None => None,
+        };
+
         if let Some(ref pb) = pb {
             pb.finish_and_clear();
         }
@@ -618,32 +1552,61 @@ async fn main() -> Result<()> {
         // Build client and config
         let config = ImageEditClientConfig {
             timeout_secs: args.timeout,
-            max_retries: args.max_retries,
+            max_retries: args.common.max_retries,
+            model,
+            cache,
+            budget,
+            ..Default::default()
         };
 
-        let client = ImageEditClient::with_config(api_key, config)
-            .map_err(|e| anyhow::anyhow!("Failed to create ImageEdit client: {}", e))?;
+        let client = clients.image_edit(config);
+
+        let mut edit_config = build_edit_config(args.size.as_ref(), args.aspect.as_ref())?;
 
-        let edit_config = build_edit_config(args.size.as_ref(), args.aspect.as_ref())?;
+        let resolution = if args.keep_resolution {
+            input_resolution(&images)
+        } else {
+            None
+        };
+        if let Some(dims) = resolution {
+            edit_config = Some(request_resolution(edit_config, dims, model));
+        }
 
         let output_path = args
             .output
             .unwrap_or_else(|| PathBuf::from(generate_output_filename("edited", &prompt, "png")));
 
-        edit_single(
+        let prompts = iteration_prompts(&prompt, &[], args.iterations);
+        let final_path = edit_single(
             &client,
-            &prompt,
+            &prompts,
             &images,
+            mask.as_ref(),
             output_path,
             edit_config.as_ref(),
-            args.quiet,
+            resolution,
+            args.metadata,
+            args.checksums,
+            args.common.quiet,
         )
         .await?;
+
+        if let (Some(layout), Some(before)) = (compare, input_paths.first()) {
+            let compare_path = save_comparison(before, &final_path, layout).await?;
+            if !args.common.quiet {
+                println!("Comparison: {}", compare_path.display());
+            }
+        }
     }
 
     Ok(())
 }
 
+#[tokio::main]
+async fn main() -> Result<()> {
+    run(Args::parse()).await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -663,6 +1626,7 @@ edits:
     prompt: Make it look like a watercolor painting
     images:
       - photo.jpg
+    model: flash
   - name: with-config
     prompt: High quality panorama
     images:
@@ -670,17 +1634,273 @@ edits:
       - img2.jpg
     size: 2K
     aspect: 16:9
+  - name: inpaint
+    prompt: Paint the wall dark green
+    images:
+      - room.jpg
+    mask: wall-mask.png
+  - name: aging
+    prompt: Make the person look ten years older
+    images:
+      - portrait.jpg
+    iterations: 3
+    prompts:
+      - Add subtle wrinkles
 "#;
         let parsed: EditsFile = serde_yaml::from_str(yaml).unwrap();
-        assert_eq!(parsed.edits.len(), 3);
+        assert_eq!(parsed.edits.len(), 5);
         assert_eq!(parsed.edits[0].name, "group-photo");
         assert_eq!(parsed.edits[0].images.len(), 3);
         assert_eq!(parsed.edits[0].output, Some("group.png".to_string()));
         assert_eq!(parsed.edits[1].name, "watercolor");
         assert_eq!(parsed.edits[1].images.len(), 1);
         assert!(parsed.edits[1].output.is_none());
+        assert_eq!(parsed.edits[1].model, Some("flash".to_string()));
+        assert!(parsed.edits[2].model.is_none());
         assert_eq!(parsed.edits[2].size, Some("2K".to_string()));
         assert_eq!(parsed.edits[2].aspect, Some("16:9".to_string()));
+        assert!(parsed.edits[2].mask.is_none());
+        assert_eq!(parsed.edits[3].mask, Some("wall-mask.png".to_string()));
+        assert!(parsed.edits[3].prompts.is_empty());
+        assert_eq!(parsed.edits[4].iterations, Some(3));
+        assert_eq!(parsed.edits[4].prompts, vec!["Add subtle wrinkles"]);
+    }
+
+    #[test]
+    fn test_edit_state_completed_output() {
+        let dir = std::env::temp_dir().join(format!("imagen_edit_state_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("watercolor.png");
+        std::fs::write(&output, b"png").unwrap();
+
+        let watercolor = entry("watercolor", &["photo.jpg"]);
+        let mut state = EditState::default();
+        assert!(state.completed_output(&watercolor).is_none());
+
+        state.record(&watercolor, &output);
+        assert_eq!(state.completed_output(&watercolor), Some(&output));
+
+        // Changing the entry invalidates its completed state
+        let mut changed = watercolor.clone();
+        changed.prompt = "Make it an oil painting".to_string();
+        assert!(state.completed_output(&changed).is_none());
+
+        // So does a missing output file
+        std::fs::remove_file(&output).unwrap();
+        assert!(state.completed_output(&watercolor).is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_watch_dir_args() {
+        let args = Args::try_parse_from([
+            "imagen_edit",
+            "--watch-dir",
+            "in",
+            "--prompt",
+            "Add film grain",
+        ])
+        .unwrap();
+        assert_eq!(args.watch_dir, Some(PathBuf::from("in")));
+        assert_eq!(args.prompt_flag.as_deref(), Some("Add film grain"));
+        assert!(args.prompt.is_none());
+
+        // The flag and positional prompt are mutually exclusive
+        assert!(Args::try_parse_from(["imagen_edit", "--prompt", "a", "b"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_model() {
+        assert_eq!(parse_model("3pro").unwrap(), ImageModel::Gemini3Pro);
+        assert_eq!(parse_model("flash").unwrap(), ImageModel::Gemini25Flash);
+        assert!(parse_model("gpt").is_err());
+    }
+
+    #[test]
+    fn test_request_resolution() {
+        let config = request_resolution(None, (1920, 1080), ImageModel::Gemini3Pro);
+        assert_eq!(config.size, Some(ImageSize::K2));
+        assert_eq!(config.aspect_ratio, Some(AspectRatio::Wide));
+
+        // Explicit settings win over the input's dimensions
+        let explicit = ImageEditConfig::new().with_size(ImageSize::K4);
+        let config = request_resolution(Some(explicit), (1920, 1080), ImageModel::Gemini3Pro);
+        assert_eq!(config.size, Some(ImageSize::K4));
+        assert_eq!(config.aspect_ratio, Some(AspectRatio::Wide));
+
+        // Gemini 2.5 Flash only gets the aspect ratio
+        let config = request_resolution(None, (1080, 1920), ImageModel::Gemini25Flash);
+        assert!(config.size.is_none());
+        assert_eq!(config.aspect_ratio, Some(AspectRatio::Tall));
+    }
+
+    #[test]
+    fn test_iteration_prompts() {
+        let prompts = iteration_prompts("base", &["first".to_string()], 3);
+        assert_eq!(prompts, vec!["first", "base", "base"]);
+        assert_eq!(iteration_prompts("base", &[], 1), vec!["base"]);
+    }
+
+    #[test]
+    fn test_step_output_path() {
+        assert_eq!(
+            step_output_path(Path::new("out/aging-abc123.png"), 2, "jpg"),
+            PathBuf::from("out/aging-abc123-step2.jpg")
+        );
+    }
+
+    fn entry(name: &str, images: &[&str]) -> EditEntry {
+        EditEntry {
+            name: name.to_string(),
+            prompt: format!("{} prompt", name),
+            images: images.iter().map(|s| s.to_string()).collect(),
+            mask: None,
+            output: None,
+            size: None,
+            aspect: None,
+            model: None,
+            iterations: None,
+            prompts: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_compare_flag_parsing() {
+        let args =
+            Args::try_parse_from(["imagen_edit", "-i", "a.jpg", "--compare", "Add grain"]).unwrap();
+        assert_eq!(args.compare.as_deref(), Some("side"));
+        assert_eq!(args.prompt.as_deref(), Some("Add grain"));
+
+        let args =
+            Args::try_parse_from(["imagen_edit", "-i", "a.jpg", "--compare=stacked", "x"]).unwrap();
+        assert_eq!(
+            parse_compare(args.compare.as_deref()).unwrap(),
+            Some(CompareLayout::Stacked)
+        );
+        assert!(parse_compare(Some("diagonal")).is_err());
+        assert_eq!(parse_compare(None).unwrap(), None);
+    }
+
+    #[test]
+    fn test_entry_dependencies() {
+        let e = entry("colorize", &["$restore", "reference.jpg"]);
+        assert_eq!(entry_dependencies(&e), vec!["restore"]);
+        assert!(entry_dependencies(&entry("plain", &["photo.jpg"])).is_empty());
+    }
+
+    #[test]
+    fn test_plan_waves_chain() {
+        let entries = vec![
+            entry("stylize", &["$colorize"]),
+            entry("restore", &["old.jpg"]),
+            entry("colorize", &["$restore"]),
+            entry("other", &["photo.jpg"]),
+        ];
+        let waves = plan_waves(&entries).unwrap();
+        assert_eq!(waves, vec![vec![1, 3], vec![2], vec![0]]);
+    }
+
+    #[test]
+    fn test_plan_waves_errors() {
+        // Unknown reference
+        assert!(plan_waves(&[entry("a", &["$missing"])]).is_err());
+        // Cycle
+        assert!(plan_waves(&[entry("a", &["$b"]), entry("b", &["$a"])]).is_err());
+        // Self reference
+        assert!(plan_waves(&[entry("a", &["$a"])]).is_err());
+        // Ambiguous reference
+        assert!(
+            plan_waves(&[
+                entry("a", &["x.jpg"]),
+                entry("a", &["y.jpg"]),
+                entry("b", &["$a"])
+            ])
+            .is_err()
+        );
+        // Duplicate names are fine when not referenced
+        assert!(plan_waves(&[entry("a", &["x.jpg"]), entry("a", &["y.jpg"])]).is_ok());
+    }
+
+    #[test]
+    fn test_select_with_dependencies() {
+        let entries = vec![
+            entry("restore", &["old.jpg"]),
+            entry("colorize", &["$restore"]),
+            entry("stylize", &["$colorize"]),
+            entry("other", &["photo.jpg"]),
+        ];
+        let selected = select_with_dependencies(entries, "stylize");
+        let names: Vec<&str> = selected.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["restore", "colorize", "stylize"]);
+    }
+
+    #[test]
+    fn test_resolve_entry_image() {
+        let yaml_dir = Path::new("/data/edits");
+        let mut outputs = HashMap::new();
+        outputs.insert("restore".to_string(), PathBuf::from("/out/restore.png"));
+
+        assert_eq!(
+            resolve_entry_image("$restore", yaml_dir, &outputs).unwrap(),
+            PathBuf::from("/out/restore.png")
+        );
+        assert_eq!(
+            resolve_entry_image("photo.jpg", yaml_dir, &outputs).unwrap(),
+            PathBuf::from("/data/edits/photo.jpg")
+        );
+        assert!(resolve_entry_image("$failed", yaml_dir, &outputs).is_err());
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*.jpg", "photo.jpg"));
+        assert!(wildcard_match("*.jpg", ".jpg"));
+        assert!(!wildcard_match("*.jpg", "photo.png"));
+        assert!(wildcard_match("img-??.png", "img-01.png"));
+        assert!(!wildcard_match("img-??.png", "img-1.png"));
+        assert!(wildcard_match("*-*.webp", "a-b-c.webp"));
+        assert!(wildcard_match("*", "anything"));
+        assert!(wildcard_match("exact.png", "exact.png"));
+    }
+
+    #[test]
+    fn test_is_image_file() {
+        assert!(is_image_file(Path::new("a.png")));
+        assert!(is_image_file(Path::new("a.JPG")));
+        assert!(!is_image_file(Path::new("a.txt")));
+        assert!(!is_image_file(Path::new("noext")));
+    }
+
+    #[test]
+    fn test_each_entries() {
+        let paths = vec![PathBuf::from("photos/a.jpg"), PathBuf::from("photos/a.png")];
+        let entries = each_entries(&paths, "Add film grain", Some("mask.png"));
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].name, "a.jpg");
+        assert_eq!(entries[1].name, "a.png");
+        assert_eq!(entries[0].images, vec!["photos/a.jpg".to_string()]);
+        assert_eq!(entries[0].prompt, "Add film grain");
+        assert_eq!(entries[0].mask, Some("mask.png".to_string()));
+        assert_eq!(image_ref_for_path(Path::new("$odd.jpg")), "./$odd.jpg");
+        // Same prompt on different files must not collide on output names
+        assert_ne!(
+            generate_output_filename(&entries[0].name, &entries[0].prompt, "png"),
+            generate_output_filename(&entries[1].name, &entries[1].prompt, "png")
+        );
+    }
+
+    #[test]
+    fn test_resolve_yaml_path() {
+        let yaml_dir = Path::new("/data/edits");
+        assert_eq!(
+            resolve_yaml_path(yaml_dir, "photo.jpg"),
+            PathBuf::from("/data/edits/photo.jpg")
+        );
+        assert_eq!(
+            resolve_yaml_path(yaml_dir, "/abs/photo.jpg"),
+            PathBuf::from("/abs/photo.jpg")
+        );
     }
 
     #[test]
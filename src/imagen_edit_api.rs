@@ -1,29 +1,47 @@
 use base64::Engine;
-use reqwest::Client;
+use reqwest::{Client, Method};
 use serde_json::{Value, json};
+#[cfg(not(target_arch = "wasm32"))]
 use std::path::Path;
 use std::time::Duration;
 use thiserror::Error;
+#[cfg(not(target_arch = "wasm32"))]
 use tokio::fs;
-use tracing::{debug, info, warn};
+use tracing::{Instrument, debug, field, info, info_span, warn};
+
+use crate::auth::{AuthMode, GEMINI_BASE_URL};
+use crate::budget::{Budget, BudgetExceeded};
+use crate::cache::ResponseCache;
+use crate::circuit_breaker::{CircuitBreaker, CircuitOpen};
+use crate::error::ErrorKind;
+use crate::imagen_api::{AspectRatio, GeneratedImage, ImageModel, ImageProvenance, ImageSize};
+use crate::payload::{GenerateContent, JsonBody, Part};
+use crate::proxy::ProxyConfig;
+use crate::redact::ApiKey;
+use crate::rt::Stopwatch;
+use crate::tls::TlsConfig;
+use crate::transport::TransportConfig;
 
-use crate::imagen_api::{AspectRatio, GeneratedImage, ImageSize};
-
-const GEMINI_API_URL: &str = "https://generativelanguage.googleapis.com/v1beta/models";
 const DEFAULT_TIMEOUT_SECS: u64 = 120;
 const DEFAULT_MAX_RETRIES: u32 = 3;
-const MODEL_NAME: &str = "gemini-3-pro-image-preview";
+const MASK_INSTRUCTION: &str = "The last image is an edit mask for the image(s) before it. \
+Only modify the regions that are white in the mask; keep the regions that are black exactly as they are. \
+Do not include the mask itself in the output.";
 
 #[derive(Debug, Error)]
 pub enum ImageEditError {
     #[error("API key not found in environment (set GEMINI_API_KEY or GOOGLE_AI_KEY)")]
     MissingApiKey,
 
-    #[error("Gemini API error ({status}): {message}")]
-    ApiError { status: u16, message: String },
+    #[error("Gemini API error ({status}, request {request_id}): {message}")]
+    ApiError {
+        status: u16,
+        message: String,
+        request_id: String,
+    },
 
-    #[error("Rate limited by API. Retry after some time.")]
-    RateLimited,
+    #[error("Rate limited by API (request {request_id}). Retry after some time.")]
+    RateLimited { request_id: String },
 
     #[error("Invalid response from Gemini API: {0}")]
     InvalidResponse(String),
@@ -32,7 +50,7 @@ pub enum ImageEditError {
     NoImageData,
 
     #[error("Network error: {0}")]
-    NetworkError(#[from] reqwest::Error),
+    NetworkError(#[source] reqwest::Error),
 
     #[error("JSON parsing error: {0}")]
     JsonError(#[from] serde_json::Error),
@@ -40,6 +58,12 @@ pub enum ImageEditError {
     #[error("Max retries ({0}) exceeded")]
     MaxRetriesExceeded(u32),
 
+    #[error(transparent)]
+    CircuitOpen(#[from] CircuitOpen),
+
+    #[error(transparent)]
+    BudgetExceeded(#[from] BudgetExceeded),
+
     #[error("Base64 decode error: {0}")]
     Base64Error(#[from] base64::DecodeError),
 
@@ -51,15 +75,76 @@ pub enum ImageEditError {
 
     #[error("Unsupported image format: {0}")]
     UnsupportedFormat(String),
+
+    #[error("Image size (1K/2K/4K) only supported with Gemini 3 Pro model")]
+    ImageSizeNotSupported,
 }
 
 pub type Result<T> = std::result::Result<T, ImageEditError>;
 
+impl From<reqwest::Error> for ImageEditError {
+    fn from(err: reqwest::Error) -> Self {
+        ImageEditError::NetworkError(crate::redact::reqwest_error(err))
+    }
+}
+
+impl ImageEditError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            ImageEditError::MissingApiKey | ImageEditError::ImageSizeNotSupported => {
+                ErrorKind::Config
+            }
+            ImageEditError::NoInputImages | ImageEditError::UnsupportedFormat(_) => {
+                ErrorKind::InvalidInput
+            }
+            ImageEditError::ApiError { .. } => ErrorKind::Api,
+            ImageEditError::RateLimited { .. } => ErrorKind::RateLimited,
+            ImageEditError::InvalidResponse(_)
+            | ImageEditError::NoImageData
+            | ImageEditError::JsonError(_)
+            | ImageEditError::Base64Error(_) => ErrorKind::InvalidResponse,
+            ImageEditError::NetworkError(_) => ErrorKind::Network,
+            ImageEditError::MaxRetriesExceeded(_) => ErrorKind::RetriesExhausted,
+            ImageEditError::CircuitOpen(_) => ErrorKind::CircuitOpen,
+            ImageEditError::BudgetExceeded(_) => ErrorKind::BudgetExceeded,
+            ImageEditError::IoError(_) => ErrorKind::Io,
+        }
+    }
+
+    /// HTTP status returned by the API, if any
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            ImageEditError::ApiError { status, .. } => Some(*status),
+            ImageEditError::RateLimited { .. } => Some(429),
+            _ => None,
+        }
+    }
+}
+
 /// Configuration for image editing client
 #[derive(Debug, Clone)]
 pub struct ImageEditClientConfig {
     pub timeout_secs: u64,
     pub max_retries: u32,
+    pub model: ImageModel,
+    /// Send 1:1 / 1K when aspect ratio or size is unset (Gemini 3 Pro). Off by
+    /// default so the model can follow the input images' aspect ratio.
+    pub fill_image_config_defaults: bool,
+    /// API host, e.g. a local mock server in tests
+    pub base_url: String,
+    /// Used by `with_config`; `with_http_client` keeps the given client's proxy
+    pub proxy: ProxyConfig,
+    /// Extra root certificates and builder hook, applied like `proxy`
+    pub tls: TlsConfig,
+    /// Connection pool, keep-alive and HTTP/2 settings, applied like `proxy`
+    pub transport: TransportConfig,
+    pub auth_mode: AuthMode,
+    /// Serve identical edit requests from disk; `None` disables
+    pub cache: Option<ResponseCache>,
+    /// Shared with other clients of a batch to stop retrying when the API is down
+    pub circuit_breaker: Option<CircuitBreaker>,
+    /// Spend limit shared with other clients; requests fail once it is reached
+    pub budget: Option<Budget>,
 }
 
 impl Default for ImageEditClientConfig {
@@ -67,6 +152,16 @@ impl Default for ImageEditClientConfig {
         Self {
             timeout_secs: DEFAULT_TIMEOUT_SECS,
             max_retries: DEFAULT_MAX_RETRIES,
+            model: ImageModel::Gemini3Pro,
+            fill_image_config_defaults: false,
+            base_url: GEMINI_BASE_URL.to_string(),
+            proxy: ProxyConfig::default(),
+            tls: TlsConfig::default(),
+            transport: TransportConfig::default(),
+            auth_mode: AuthMode::default(),
+            cache: None,
+            circuit_breaker: None,
+            budget: None,
         }
     }
 }
@@ -103,6 +198,7 @@ pub struct InputImage {
 
 impl InputImage {
     /// Create from file path
+    #[cfg(not(target_arch = "wasm32"))]
     pub async fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
         let path = path.as_ref();
         let data = fs::read(path).await?;
@@ -122,6 +218,7 @@ impl InputImage {
 }
 
 /// Determine MIME type from file extension
+#[cfg(not(target_arch = "wasm32"))]
 fn mime_type_from_path(path: &Path) -> Result<String> {
     let ext = path
         .extension()
@@ -144,7 +241,7 @@ fn mime_type_from_path(path: &Path) -> Result<String> {
 #[derive(Debug, Clone)]
 pub struct ImageEditClient {
     client: Client,
-    api_key: String,
+    api_key: ApiKey,
     config: ImageEditClientConfig,
 }
 
@@ -154,39 +251,48 @@ impl ImageEditClient {
     }
 
     pub fn with_config(api_key: String, config: ImageEditClientConfig) -> Result<Self> {
-        let client = Client::builder()
-            .use_rustls_tls()
-            .timeout(Duration::from_secs(config.timeout_secs))
-            .connect_timeout(Duration::from_secs(30))
-            .build()
-            .map_err(ImageEditError::NetworkError)?;
-
-        Ok(Self {
+        let builder = crate::proxy::apply(
+            crate::rt::client_builder(config.timeout_secs),
+            &config.proxy,
+        )?;
+        let builder = crate::transport::apply(builder, &config.transport);
+        let client = crate::tls::apply(builder, &config.tls)?.build()?;
+
+        Ok(Self::with_http_client(client, api_key, config))
+    }
+
+    /// Build on an existing HTTP client, sharing its connection pool
+    pub fn with_http_client(
+        client: Client,
+        api_key: String,
+        config: ImageEditClientConfig,
+    ) -> Self {
+        Self {
             client,
-            api_key,
+            api_key: api_key.into(),
             config,
-        })
+        }
     }
 
-    fn build_payload(
-        &self,
-        prompt: &str,
-        images: &[InputImage],
+    fn build_payload<'a>(
+        config: &ImageEditClientConfig,
+        prompt: &'a str,
+        images: &'a [InputImage],
+        mask: Option<&'a InputImage>,
         edit_config: Option<&ImageEditConfig>,
-    ) -> Value {
-        // Build parts array: text prompt followed by all images
-        let mut parts = vec![json!({"text": prompt})];
-
-        for image in images {
-            parts.push(json!({
-                "inline_data": {
-                    "mime_type": image.mime_type,
-                    "data": image.base64_data()
-                }
-            }));
+    ) -> GenerateContent<'a> {
+        // Build parts array: text prompt followed by all images (and the mask last)
+        let prompt = match mask {
+            Some(_) => Part::text(format!("{}\n\n{}", prompt, MASK_INSTRUCTION)),
+            None => Part::text(prompt),
+        };
+        let mut parts = vec![prompt];
+
+        for image in images.iter().chain(mask) {
+            parts.push(Part::inline(&image.mime_type, &image.data));
         }
 
-        // Build image config
+        // Build image config; Gemini 2.5 Flash takes an aspect ratio but no size
         let mut image_config = json!({});
         if let Some(cfg) = edit_config {
             if let Some(ratio) = &cfg.aspect_ratio {
@@ -198,47 +304,44 @@ impl ImageEditClient {
         }
 
         // Set defaults if not specified
-        if image_config.get("aspectRatio").is_none() {
-            image_config["aspectRatio"] = json!("1:1");
+        if config.fill_image_config_defaults && config.model.supports_image_size() {
+            if image_config.get("aspectRatio").is_none() {
+                image_config["aspectRatio"] = json!("1:1");
+            }
+            if image_config.get("imageSize").is_none() {
+                image_config["imageSize"] = json!("1K");
+            }
         }
-        if image_config.get("imageSize").is_none() {
-            image_config["imageSize"] = json!("1K");
+
+        let mut generation_config = json!({
+            "responseModalities": ["TEXT", "IMAGE"]
+        });
+        if image_config.as_object().is_some_and(|c| !c.is_empty()) {
+            generation_config["imageConfig"] = image_config;
         }
 
-        json!({
-            "contents": [{
-                "parts": parts
-            }],
-            "generationConfig": {
-                "responseModalities": ["TEXT", "IMAGE"],
-                "imageConfig": image_config
-            }
-        })
+        GenerateContent::new(parts, json!({"generationConfig": generation_config}))
     }
 
-    async fn send_request(
-        &self,
-        prompt: &str,
-        images: &[InputImage],
-        edit_config: Option<&ImageEditConfig>,
-    ) -> Result<GeneratedImage> {
+    async fn send_request(&self, body: &JsonBody, request_id: &str) -> Result<GeneratedImage> {
         let url = format!(
-            "{}/{}:generateContent?key={}",
-            GEMINI_API_URL, MODEL_NAME, self.api_key
+            "{}/v1beta/models/{}:generateContent",
+            self.config.base_url,
+            self.config.model.api_model_name()
         );
 
-        let payload = self.build_payload(prompt, images, edit_config);
-
         debug!(
-            "Sending image edit request to Gemini API with {} input images",
-            images.len()
+            "Sending image edit request to Gemini API (model: {})",
+            self.config.model
         );
 
         let response = self
-            .client
-            .post(&url)
+            .config
+            .auth_mode
+            .request(&self.client, Method::POST, &url, &self.api_key)
+            .timeout(Duration::from_secs(self.config.timeout_secs))
             .header("Content-Type", "application/json")
-            .json(&payload)
+            .body(body.clone())
             .send()
             .await?;
 
@@ -246,18 +349,31 @@ impl ImageEditClient {
         debug!("Received response with status: {}", status);
 
         if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
-            return Err(ImageEditError::RateLimited);
+            crate::metrics::record_rate_limited("image_edit");
+            return Err(ImageEditError::RateLimited {
+                request_id: request_id.to_string(),
+            });
         }
 
         if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
+            let error_text = self
+                .api_key
+                .scrub(response.text().await.unwrap_or_default());
             return Err(ImageEditError::ApiError {
                 status: status.as_u16(),
                 message: error_text,
+                request_id: request_id.to_string(),
             });
         }
 
         let data: Value = response.json().await?;
+        crate::metrics::record_gemini_usage("image_edit", &data);
+        crate::usage::record_gemini(
+            "image_edit",
+            self.config.model.api_model_name(),
+            &data,
+            self.config.budget.as_ref(),
+        );
 
         // Extract image from response
         let parts = data["candidates"][0]["content"]["parts"]
@@ -281,6 +397,7 @@ impl ImageEditClient {
                 return Ok(GeneratedImage {
                     data: image_data,
                     mime_type,
+                    provenance: Some(ImageProvenance::from_response(self.config.model, &data)),
                 });
             }
         }
@@ -289,11 +406,7 @@ impl ImageEditClient {
     }
 
     fn is_retryable_error(err: &ImageEditError) -> bool {
-        match err {
-            ImageEditError::RateLimited | ImageEditError::NetworkError(_) => true,
-            ImageEditError::ApiError { status, .. } => *status >= 500,
-            _ => false,
-        }
+        crate::error::is_retryable(err.kind(), err.status())
     }
 
     /// Edit images with a text prompt
@@ -307,45 +420,149 @@ impl ImageEditClient {
         prompt: &str,
         images: &[InputImage],
         edit_config: Option<&ImageEditConfig>,
+    ) -> Result<GeneratedImage> {
+        self.edit_with_retry(prompt, images, None, edit_config)
+            .await
+    }
+
+    /// Inpaint images with a mask: white mask regions are editable, black regions preserved
+    pub async fn inpaint_with_config(
+        &self,
+        prompt: &str,
+        images: &[InputImage],
+        mask: &InputImage,
+        edit_config: Option<&ImageEditConfig>,
+    ) -> Result<GeneratedImage> {
+        self.edit_with_retry(prompt, images, Some(mask), edit_config)
+            .await
+    }
+
+    async fn edit_with_retry(
+        &self,
+        prompt: &str,
+        images: &[InputImage],
+        mask: Option<&InputImage>,
+        edit_config: Option<&ImageEditConfig>,
     ) -> Result<GeneratedImage> {
         if images.is_empty() {
             return Err(ImageEditError::NoInputImages);
         }
 
-        let mut last_error = None;
-        let mut retry_count = 0;
+        // Validate that image size is only used with Gemini 3 Pro
+        if let Some(cfg) = edit_config
+            && cfg.size.is_some()
+            && !self.config.model.supports_image_size()
+        {
+            return Err(ImageEditError::ImageSizeNotSupported);
+        }
+
+        let payload_bytes: usize = images.iter().chain(mask).map(|i| i.data.len()).sum();
+        let body =
+            Self::build_payload(&self.config, prompt, images, mask, edit_config).to_body()?;
+        let Some(cache) = &self.config.cache else {
+            return self.send_with_retry(&body, payload_bytes).await;
+        };
+
+        let key = ResponseCache::key(&[
+            self.config.model.api_model_name().as_bytes(),
+            body.as_bytes(),
+        ]);
+        if let Some(image) = cache.get_image(&key).await {
+            info!("Using cached image");
+            return Ok(image);
+        }
 
-        while retry_count < self.config.max_retries {
-            match self.send_request(prompt, images, edit_config).await {
-                Ok(image) => {
-                    info!("Image edit successful");
-                    return Ok(image);
+        let image = self.send_with_retry(&body, payload_bytes).await?;
+        cache.put_image(&key, &image).await;
+        Ok(image)
+    }
+
+    async fn send_with_retry(
+        &self,
+        body: &JsonBody,
+        payload_bytes: usize,
+    ) -> Result<GeneratedImage> {
+        let request_id = crate::request_id::next();
+        let span = info_span!(
+            "image_edit_request",
+            request_id = %request_id,
+            model = %self.config.model,
+            payload_bytes = payload_bytes,
+            attempt = field::Empty,
+            latency_ms = field::Empty,
+        );
+
+        async {
+            let mut last_error = None;
+            let mut retry_count = 0;
+
+            while retry_count < self.config.max_retries {
+                if let Some(breaker) = &self.config.circuit_breaker {
+                    breaker.check()?;
+                }
+                if let Some(budget) = &self.config.budget {
+                    budget.check()?;
                 }
-                Err(e) => {
-                    if Self::is_retryable_error(&e) && retry_count + 1 < self.config.max_retries {
-                        let delay = if matches!(e, ImageEditError::RateLimited) {
-                            Duration::from_secs(30 * (retry_count as u64 + 1))
+                span.record("attempt", retry_count + 1);
+                crate::metrics::record_upload_bytes("image_edit", payload_bytes);
+                let stopwatch = Stopwatch::start();
+                let result = self.send_request(body, &request_id).await;
+                span.record("latency_ms", stopwatch.elapsed_ms());
+                if let Some(breaker) = &self.config.circuit_breaker {
+                    breaker.record(&result, Self::is_retryable_error);
+                }
+                crate::metrics::record_request(
+                    "image_edit",
+                    result.is_ok(),
+                    stopwatch.elapsed_ms(),
+                );
+                debug!(
+                    "Attempt {} finished in {}ms",
+                    retry_count + 1,
+                    stopwatch.elapsed_ms()
+                );
+
+                match result {
+                    Ok(image) => {
+                        info!("Image edit successful");
+                        return Ok(image);
+                    }
+                    Err(e) => {
+                        if Self::is_retryable_error(&e)
+                            && retry_count + 1 < self.config.max_retries
+                            && self
+                                .config
+                                .circuit_breaker
+                                .as_ref()
+                                .is_none_or(CircuitBreaker::try_retry)
+                        {
+                            let delay = if matches!(e, ImageEditError::RateLimited { .. }) {
+                                Duration::from_secs(30 * (retry_count as u64 + 1))
+                            } else {
+                                Duration::from_secs(2u64.pow(retry_count))
+                            };
+                            warn!(
+                                "Request failed (attempt {}/{}): {}. Retrying in {:?}...",
+                                retry_count + 1,
+                                self.config.max_retries,
+                                e,
+                                delay
+                            );
+                            crate::metrics::record_retry("image_edit");
+                            crate::rt::sleep(delay).await;
+                            retry_count += 1;
+                            last_error = Some(e);
                         } else {
-                            Duration::from_secs(2u64.pow(retry_count))
-                        };
-                        warn!(
-                            "Request failed (attempt {}/{}): {}. Retrying in {:?}...",
-                            retry_count + 1,
-                            self.config.max_retries,
-                            e,
-                            delay
-                        );
-                        tokio::time::sleep(delay).await;
-                        retry_count += 1;
-                        last_error = Some(e);
-                    } else {
-                        return Err(e);
+                            return Err(e);
+                        }
                     }
                 }
             }
-        }
 
-        Err(last_error.unwrap_or(ImageEditError::MaxRetriesExceeded(self.config.max_retries)))
+            Err(last_error.unwrap_or(ImageEditError::MaxRetriesExceeded(self.config.max_retries)))
+        }
+        .instrument(span.clone())
+        .await
     }
 }
 
@@ -353,6 +570,10 @@ impl ImageEditClient {
 mod tests {
     use super::*;
 
+    fn payload_json(payload: GenerateContent<'_>) -> Value {
+        serde_json::to_value(payload).unwrap()
+    }
+
     #[test]
     fn test_mime_type_from_path() {
         assert_eq!(
@@ -383,6 +604,79 @@ mod tests {
         let config = ImageEditClientConfig::default();
         assert_eq!(config.timeout_secs, DEFAULT_TIMEOUT_SECS);
         assert_eq!(config.max_retries, DEFAULT_MAX_RETRIES);
+        assert_eq!(config.model, ImageModel::Gemini3Pro);
+        assert!(!config.fill_image_config_defaults);
+    }
+
+    #[test]
+    fn test_build_payload_omits_unset_image_config() {
+        let image = InputImage::from_bytes(vec![1, 2, 3], "image/png".to_string());
+        let mut config = ImageEditClientConfig::default();
+
+        // Unset size/aspect are left to the model so it can follow the input
+        let payload = payload_json(ImageEditClient::build_payload(
+            &config,
+            "Add a hat",
+            std::slice::from_ref(&image),
+            None,
+            None,
+        ));
+        assert!(payload["generationConfig"].get("imageConfig").is_none());
+
+        config.fill_image_config_defaults = true;
+        let payload = payload_json(ImageEditClient::build_payload(
+            &config,
+            "Add a hat",
+            &[image],
+            None,
+            None,
+        ));
+        let image_config = &payload["generationConfig"]["imageConfig"];
+        assert_eq!(image_config["aspectRatio"], "1:1");
+        assert_eq!(image_config["imageSize"], "1K");
+    }
+
+    #[test]
+    fn test_build_payload_model_image_config() {
+        let image = InputImage::from_bytes(vec![1, 2, 3], "image/png".to_string());
+        let edit_config = ImageEditConfig::new().with_size(ImageSize::K2);
+
+        let mut config = ImageEditClientConfig::default();
+        let payload = payload_json(ImageEditClient::build_payload(
+            &config,
+            "Add a hat",
+            std::slice::from_ref(&image),
+            None,
+            Some(&edit_config),
+        ));
+        assert_eq!(
+            payload["generationConfig"]["imageConfig"]["imageSize"],
+            "2K"
+        );
+
+        config.model = ImageModel::Gemini25Flash;
+        config.fill_image_config_defaults = true;
+        let payload = payload_json(ImageEditClient::build_payload(
+            &config,
+            "Add a hat",
+            std::slice::from_ref(&image),
+            None,
+            None,
+        ));
+        assert!(payload["generationConfig"].get("imageConfig").is_none());
+
+        let edit_config = ImageEditConfig::new().with_aspect_ratio(AspectRatio::Wide);
+        let payload = payload_json(ImageEditClient::build_payload(
+            &config,
+            "Add a hat",
+            &[image],
+            None,
+            Some(&edit_config),
+        ));
+        assert_eq!(
+            payload["generationConfig"]["imageConfig"]["aspectRatio"],
+            "16:9"
+        );
     }
 
     #[test]
@@ -401,6 +695,41 @@ mod tests {
         assert_eq!(base64, "AQIDBA==");
     }
 
+    #[test]
+    fn test_build_payload_with_mask() {
+        let image = InputImage::from_bytes(vec![1, 2, 3], "image/png".to_string());
+        let mask = InputImage::from_bytes(vec![4, 5, 6], "image/png".to_string());
+
+        let config = ImageEditClientConfig::default();
+        let payload = payload_json(ImageEditClient::build_payload(
+            &config,
+            "Replace the sky",
+            std::slice::from_ref(&image),
+            None,
+            None,
+        ));
+        let parts = payload["contents"][0]["parts"].as_array().unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0]["text"], "Replace the sky");
+
+        let payload = payload_json(ImageEditClient::build_payload(
+            &config,
+            "Replace the sky",
+            &[image],
+            Some(&mask),
+            None,
+        ));
+        let parts = payload["contents"][0]["parts"].as_array().unwrap();
+        assert_eq!(parts.len(), 3);
+        assert!(
+            parts[0]["text"]
+                .as_str()
+                .unwrap()
+                .contains(MASK_INSTRUCTION)
+        );
+        assert_eq!(parts[2]["inline_data"]["data"], mask.base64_data());
+    }
+
     #[test]
     fn test_edit_config_builder() {
         let config = ImageEditConfig::new()
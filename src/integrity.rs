@@ -0,0 +1,195 @@
+//! Output integrity: `<file>.blake3` sidecars in `b3sum` format, written with
+//! `--checksums` and checked by `verify` (or `b3sum --check`), and structural
+//! checks that catch truncated or corrupt PNG, JPEG and WebP files before an
+//! image is reported as saved.
+
+use std::path::Path;
+use thiserror::Error;
+
+use crate::error::ErrorKind;
+
+/// Extension appended to an output's name for its checksum sidecar
+pub const CHECKSUM_EXTENSION: &str = "blake3";
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum IntegrityError {
+    #[error("Checksum mismatch: expected {expected}, found {actual}")]
+    Mismatch { expected: String, actual: String },
+
+    #[error("Malformed checksum sidecar")]
+    MalformedSidecar,
+
+    #[error("Corrupt image: {0}")]
+    CorruptImage(&'static str),
+}
+
+impl IntegrityError {
+    pub fn kind(&self) -> ErrorKind {
+        ErrorKind::InvalidInput
+    }
+
+    /// Always `None`: checks make no requests
+    pub fn status(&self) -> Option<u16> {
+        None
+    }
+}
+
+/// Key or path of the sidecar of the output at `key`
+pub fn checksum_key(key: &str) -> String {
+    format!("{}.{}", key, CHECKSUM_EXTENSION)
+}
+
+/// A `b3sum` line for `data` saved as `file_name`: hex digest, two spaces,
+/// the name
+pub fn checksum_line(data: &[u8], file_name: &str) -> String {
+    format!("{}  {}\n", blake3::hash(data).to_hex(), file_name)
+}
+
+/// Check `data` against the first line of a sidecar
+pub fn verify_checksum(data: &[u8], sidecar: &str) -> Result<(), IntegrityError> {
+    let expected = sidecar
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().next())
+        .filter(|hex| hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+        .ok_or(IntegrityError::MalformedSidecar)?
+        .to_ascii_lowercase();
+    let actual = blake3::hash(data).to_hex().to_string();
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(IntegrityError::Mismatch { expected, actual })
+    }
+}
+
+/// Whether `path` names an image `check_image` understands
+pub fn is_image_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            matches!(
+                ext.to_ascii_lowercase().as_str(),
+                "png" | "jpg" | "jpeg" | "webp"
+            )
+        })
+}
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Check that `data` is a whole PNG, JPEG or WebP file: PNG chunks run from
+/// `IHDR` to a final `IEND`, JPEG ends with its end-of-image marker and a
+/// WebP is as long as its RIFF header says. Pixel data isn't decoded.
+pub fn check_image(data: &[u8]) -> Result<(), IntegrityError> {
+    if data.starts_with(PNG_SIGNATURE) {
+        check_png(&data[PNG_SIGNATURE.len()..])
+    } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        // Some encoders pad the file after the end marker
+        let end = data.iter().rposition(|&b| b != 0).unwrap_or(0);
+        if end >= 1 && data[end - 1..=end] == [0xFF, 0xD9] {
+            Ok(())
+        } else {
+            Err(IntegrityError::CorruptImage(
+                "JPEG has no end-of-image marker",
+            ))
+        }
+    } else if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        let size = u32::from_le_bytes([data[4], data[5], data[6], data[7]]) as usize;
+        if data.len() >= size + 8 {
+            Ok(())
+        } else {
+            Err(IntegrityError::CorruptImage(
+                "WebP is shorter than its header says",
+            ))
+        }
+    } else if data.is_empty() {
+        Err(IntegrityError::CorruptImage("file is empty"))
+    } else {
+        Err(IntegrityError::CorruptImage("not a PNG, JPEG or WebP file"))
+    }
+}
+
+/// Walk the chunks after the PNG signature
+fn check_png(mut chunks: &[u8]) -> Result<(), IntegrityError> {
+    let mut first = true;
+    loop {
+        if chunks.len() < 12 {
+            return Err(IntegrityError::CorruptImage("PNG is truncated"));
+        }
+        let length = u32::from_be_bytes([chunks[0], chunks[1], chunks[2], chunks[3]]) as usize;
+        let kind = &chunks[4..8];
+        if first && kind != b"IHDR" {
+            return Err(IntegrityError::CorruptImage("PNG doesn't start with IHDR"));
+        }
+        first = false;
+        // Length, type, data and CRC
+        let Some(rest) = chunks.get(12 + length..) else {
+            return Err(IntegrityError::CorruptImage("PNG is truncated"));
+        };
+        if kind == b"IEND" {
+            return Ok(());
+        }
+        chunks = rest;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 1x1 PNG: signature, IHDR, IDAT and IEND
+    fn png() -> Vec<u8> {
+        let mut data = PNG_SIGNATURE.to_vec();
+        for (kind, body) in [
+            (&b"IHDR"[..], &[0u8; 13][..]),
+            (b"IDAT", &[1, 2, 3][..]),
+            (b"IEND", &[][..]),
+        ] {
+            data.extend_from_slice(&(body.len() as u32).to_be_bytes());
+            data.extend_from_slice(kind);
+            data.extend_from_slice(body);
+            data.extend_from_slice(&[0; 4]);
+        }
+        data
+    }
+
+    #[test]
+    fn test_checksum_round_trip() {
+        let line = checksum_line(b"hello", "talk.json");
+        assert!(line.ends_with("  talk.json\n"));
+        assert_eq!(verify_checksum(b"hello", &line), Ok(()));
+        assert_eq!(verify_checksum(b"hello", &line.to_uppercase()), Ok(()));
+        assert!(matches!(
+            verify_checksum(b"hellO", &line),
+            Err(IntegrityError::Mismatch { .. })
+        ));
+        assert_eq!(
+            verify_checksum(b"hello", "nope  talk.json"),
+            Err(IntegrityError::MalformedSidecar)
+        );
+        assert_eq!(checksum_key("out/a.png"), "out/a.png.blake3");
+    }
+
+    #[test]
+    fn test_check_image() {
+        let png = png();
+        assert_eq!(check_image(&png), Ok(()));
+        assert!(check_image(&png[..png.len() - 6]).is_err());
+        assert!(check_image(&png[..PNG_SIGNATURE.len() + 20]).is_err());
+
+        assert_eq!(
+            check_image(&[0xFF, 0xD8, 0xFF, 0xE0, 0xFF, 0xD9, 0, 0]),
+            Ok(())
+        );
+        assert!(check_image(&[0xFF, 0xD8, 0xFF, 0xE0, 0x12]).is_err());
+
+        let mut webp = b"RIFF\x08\x00\x00\x00WEBPVP8 ".to_vec();
+        assert_eq!(check_image(&webp), Ok(()));
+        webp[4] = 0x40;
+        assert!(check_image(&webp).is_err());
+
+        assert!(check_image(b"").is_err());
+        assert!(check_image(b"GIF89a").is_err());
+        assert!(is_image_path(Path::new("a/B.JPG")));
+        assert!(!is_image_path(Path::new("a/b.json")));
+    }
+}
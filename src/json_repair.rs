@@ -0,0 +1,108 @@
+//! Lenient fix-up of structured model answers that don't parse as they are:
+//! Markdown code fences, text around the JSON, trailing commas, and answers
+//! cut off mid-way (at the output token limit), which are closed after the
+//! last complete object or array so the segments before the cut are kept.
+
+/// A repaired copy of `text`, or `None` when there is no JSON to recover
+/// (e.g. the answer stops before any object or array is complete)
+pub fn repair_json(text: &str) -> Option<String> {
+    let start = text.find(['{', '['])?;
+    let mut chars = text[start..].chars().peekable();
+    let mut out = String::with_capacity(text.len() - start);
+    // Closing brackets of the containers open at this point
+    let mut open = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    // End of the last complete object or array, and the containers around it
+    let mut last_complete: Option<(usize, usize)> = None;
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' => open.push('}'),
+            '[' => open.push(']'),
+            '}' | ']' => {
+                if open.pop() != Some(c) {
+                    return None;
+                }
+                out.push(c);
+                if open.is_empty() {
+                    // Ignore whatever follows the value, such as a closing fence
+                    return Some(out);
+                }
+                last_complete = Some((out.len(), open.len()));
+                continue;
+            }
+            ',' => {
+                let mut rest = chars.clone();
+                while rest.next_if(|c| c.is_whitespace()).is_some() {}
+                if matches!(rest.peek(), Some('}' | ']')) {
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        out.push(c);
+    }
+
+    // Cut off: keep everything up to the last complete container and close
+    // the ones still open around it
+    let (end, depth) = last_complete?;
+    out.truncate(end);
+    open.truncate(depth);
+    out.extend(open.iter().rev());
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+
+    fn repaired(text: &str) -> Value {
+        serde_json::from_str(&repair_json(text).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_fences_and_trailing_commas() {
+        let text = "```json\n{\"summary\": \"a, b\", \"segments\": [1, 2,],}\n```";
+        assert_eq!(
+            repaired(text),
+            json!({"summary": "a, b", "segments": [1, 2]})
+        );
+        // Commas and brackets inside strings are left alone
+        assert_eq!(
+            repaired(r#"{"text": "[x,]}\" ,}"}"#),
+            json!({"text": "[x,]}\" ,}"})
+        );
+    }
+
+    #[test]
+    fn test_truncated_answer_keeps_complete_segments() {
+        let text = r#"{"summary": "Chat", "segments": [{"speaker": "A", "content": "Hi"}, {"speaker": "B", "content": "Hel"#;
+        assert_eq!(
+            repaired(text),
+            json!({"summary": "Chat", "segments": [{"speaker": "A", "content": "Hi"}]})
+        );
+        let text = r#"{"segments": [{"content": "Hi"}, {"content": "Yo"},"#;
+        assert_eq!(repaired(text)["segments"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_unrecoverable() {
+        assert_eq!(repair_json(r#"{"summary": "Cut off befo"#), None);
+        assert_eq!(repair_json("Sorry, I can't help with that."), None);
+        assert_eq!(repair_json(r#"{"a": [1}"#), None);
+    }
+}
@@ -0,0 +1,57 @@
+//! API keys stored in the OS keyring, so they stay out of shell history,
+//! `.env` files and the config file.
+//!
+//! Entries live under the service `flashecho`, one account per provider
+//! (`gemini`, `openai`), in the platform's credential store through the
+//! `keyring` crate: Windows Credential Manager, the macOS Keychain, or the
+//! Secret Service (GNOME Keyring, KWallet) on Linux and the BSDs.
+
+use thiserror::Error;
+
+/// Keyring service name of every entry
+pub const SERVICE: &str = "flashecho";
+
+#[derive(Debug, Error)]
+#[error("OS keyring error: {0}")]
+pub struct KeyringError(#[from] ::keyring::Error);
+
+pub type Result<T> = std::result::Result<T, KeyringError>;
+
+fn entry(account: &str) -> Result<::keyring::Entry> {
+    Ok(::keyring::Entry::new(SERVICE, account)?)
+}
+
+/// Store `secret` for `account`, replacing any previous value
+pub fn set(account: &str, secret: &str) -> Result<()> {
+    entry(account)?.set_password(secret)?;
+    Ok(())
+}
+
+/// Secret stored for `account`, `None` if there is none
+pub fn get(account: &str) -> Result<Option<String>> {
+    match entry(account)?.get_password() {
+        Ok(secret) => Ok(Some(secret).filter(|secret| !secret.is_empty())),
+        Err(::keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Remove the secret for `account`; removing a missing entry is not an error
+pub fn delete(account: &str) -> Result<()> {
+    match entry(account)?.delete_credential() {
+        Ok(()) | Err(::keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// [`get`], logging and ignoring keyring errors (no credential store,
+/// keyring locked, ...) so API key lookup can fall through to the next source
+pub fn lookup(account: &str) -> Option<String> {
+    match get(account) {
+        Ok(secret) => secret,
+        Err(e) => {
+            tracing::debug!("OS keyring lookup for {} failed: {}", account, e);
+            None
+        }
+    }
+}
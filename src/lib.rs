@@ -1,17 +1,228 @@
+pub mod auth;
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+pub mod blocking;
+pub mod budget;
+pub mod cache;
+pub mod circuit_breaker;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cli;
+pub mod clients;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod config;
+#[cfg(feature = "embeddings")]
+pub mod embeddings_api;
+#[cfg(feature = "transcription")]
+pub mod emotion_report;
+pub mod error;
+#[cfg(feature = "file-api")]
 pub mod file_api;
+#[cfg(all(feature = "imagen", not(target_arch = "wasm32")))]
+pub mod gallery;
+#[cfg(feature = "transcription")]
 pub mod gemini_api;
+#[cfg(all(
+    any(feature = "server", feature = "metrics", feature = "testing"),
+    not(target_arch = "wasm32")
+))]
+mod http_server;
+#[cfg(all(feature = "imagen", not(target_arch = "wasm32")))]
+pub mod image_convert;
+#[cfg(feature = "imagen")]
 pub mod imagen_api;
+#[cfg(feature = "imagen-edit")]
 pub mod imagen_edit_api;
+pub mod integrity;
+#[cfg(not(target_arch = "wasm32"))]
+mod json_log;
+#[cfg(feature = "transcription")]
+pub mod json_repair;
+#[cfg(all(feature = "keyring", not(target_arch = "wasm32")))]
+pub mod keyring;
+#[cfg(all(feature = "text", feature = "transcription"))]
+pub mod meeting_minutes;
+pub mod metrics;
+#[cfg(feature = "music")]
+pub mod music_api;
+#[cfg(feature = "transcription")]
+pub mod openai_api;
+#[cfg(any(feature = "transcription", feature = "imagen-edit"))]
+mod payload;
+pub mod proxy;
+mod redact;
+mod request_id;
+mod rt;
+#[cfg(all(
+    feature = "transcription",
+    feature = "embeddings",
+    not(target_arch = "wasm32")
+))]
+pub mod search_index;
+#[cfg(all(feature = "server", not(target_arch = "wasm32")))]
+pub mod server;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod storage;
+#[cfg(feature = "transcription")]
+pub mod subtitle;
+#[cfg(all(feature = "testing", not(target_arch = "wasm32")))]
+pub mod testing;
+#[cfg(feature = "text")]
+pub mod text_api;
+#[cfg(feature = "transcription")]
+pub mod timestamp;
+pub mod tls;
+pub mod token_count;
+#[cfg(all(feature = "text", feature = "transcription"))]
+pub mod transcript_cleanup;
+#[cfg(feature = "transcription")]
+pub mod transcript_clips;
+#[cfg(feature = "transcription")]
+pub mod transcript_diff;
+#[cfg(feature = "transcription")]
+pub mod transcript_eval;
+#[cfg(feature = "transcription")]
+pub mod transcript_format;
+#[cfg(feature = "transcription")]
+pub mod transcript_merge;
+#[cfg(all(feature = "text", feature = "transcription"))]
+pub mod transcript_normalize;
+#[cfg(all(feature = "text", feature = "transcription"))]
+pub mod transcript_qa;
+#[cfg(feature = "transcription")]
+pub mod transcript_query;
+#[cfg(feature = "transcription")]
+pub mod transcript_schema;
+// Backends return `Send` futures, which fetch-based reqwest cannot provide
+#[cfg(all(feature = "transcription", not(target_arch = "wasm32")))]
+pub mod transcription;
+#[cfg(feature = "text")]
+pub mod translation;
+pub mod transport;
+#[cfg(feature = "tts")]
+pub mod tts_api;
+pub mod usage;
+#[cfg(feature = "veo")]
+pub mod veo_api;
+#[cfg(all(feature = "vision", not(target_arch = "wasm32")))]
+pub mod video_frames;
+#[cfg(feature = "vision")]
+pub mod vision_api;
 
+pub use auth::AuthMode;
+pub use budget::{Budget, BudgetExceeded};
+pub use cache::{DEFAULT_CACHE_TTL, ResponseCache};
+pub use circuit_breaker::{CircuitBreaker, CircuitOpen};
+pub use clients::{Clients, ClientsBuilder};
+#[cfg(feature = "embeddings")]
+pub use embeddings_api::{
+    EmbedConfig, Embedding, EmbeddingError, EmbeddingModel, EmbeddingsClient,
+    EmbeddingsClientConfig, TaskType,
+};
+#[cfg(feature = "transcription")]
+pub use emotion_report::{EmotionBucket, EmotionReport, SpeakerEmotions};
+#[cfg(feature = "__client")]
+pub use error::Error;
+pub use error::ErrorKind;
+#[cfg(feature = "file-api")]
 pub use file_api::{FileApiClient, FileApiError, FileInfo};
+#[cfg(all(feature = "imagen", not(target_arch = "wasm32")))]
+pub use gallery::{GalleryError, GalleryImage, GalleryIndex, GalleryQuery};
+#[cfg(feature = "transcription")]
 pub use gemini_api::{
-    GeminiClient, GeminiClientConfig, GeminiError, MAX_INLINE_FILE_SIZE, TranscriptResponse,
-    TranscriptSegment,
+    GeminiClient, GeminiClientConfig, GeminiError, GenerationParams, MAX_INLINE_FILE_SIZE,
+    MediaRange, TRANSLATION_LANGUAGE_CODE, Topic, TranscriptResponse, TranscriptSegment,
+};
+#[cfg(all(feature = "imagen", not(target_arch = "wasm32")))]
+pub use image_convert::{
+    CompareLayout, ImageConvertError, ImageFormat, comparison_path, compose_comparison,
+    convert_image, difference_hash, hash_distance, image_dimensions, perceptual_hash, resize_image,
 };
+#[cfg(feature = "imagen")]
 pub use imagen_api::{
-    AspectRatio, GeneratedImage, ImageGenConfig, ImageModel, ImageSize, ImagenClient,
-    ImagenClientConfig, ImagenError,
+    AspectRatio, GOOGLE_IMAGE_WATERMARK, GeneratedImage, ImageGenConfig, ImageModel,
+    ImageProvenance, ImageSize, ImagenClient, ImagenClientConfig, ImagenError,
 };
+#[cfg(feature = "imagen-edit")]
 pub use imagen_edit_api::{
     ImageEditClient, ImageEditClientConfig, ImageEditConfig, ImageEditError, InputImage,
 };
+pub use integrity::{IntegrityError, check_image, checksum_line, verify_checksum};
+#[cfg(all(feature = "text", feature = "transcription"))]
+pub use meeting_minutes::{ActionItem, Decision, MeetingMinutes, MinutesExtractor, OpenQuestion};
+#[cfg(feature = "music")]
+pub use music_api::{
+    GeneratedMusic, MusicClient, MusicClientConfig, MusicError, MusicGenConfig, MusicModel,
+};
+#[cfg(feature = "transcription")]
+pub use openai_api::{
+    DEFAULT_OPENAI_MODEL, MAX_OPENAI_FILE_SIZE, OpenAiClient, OpenAiClientConfig, OpenAiError,
+};
+pub use proxy::ProxyConfig;
+#[cfg(all(
+    feature = "transcription",
+    feature = "embeddings",
+    not(target_arch = "wasm32")
+))]
+pub use search_index::{SearchHit, SearchIndex, SearchIndexError};
+#[cfg(all(feature = "server", not(target_arch = "wasm32")))]
+pub use server::{Backends, Job, JobKind, JobStatus, Server, ServerConfig};
+#[cfg(feature = "transcription")]
+pub use subtitle::{Cue, SubtitleError, SubtitleFormat, Subtitles};
+#[cfg(feature = "text")]
+pub use text_api::{
+    CachedContext, MediaPart, TextClient, TextClientConfig, TextError, TextGenConfig, TextModel,
+};
+#[cfg(feature = "transcription")]
+pub use timestamp::Timestamp;
+pub use tls::TlsConfig;
+pub use token_count::{ModalityTokens, TokenCount};
+#[cfg(all(feature = "text", feature = "transcription"))]
+pub use transcript_cleanup::{CleanupStats, TranscriptCleanup};
+#[cfg(feature = "transcription")]
+pub use transcript_clips::{SegmentClip, segment_clips};
+#[cfg(feature = "transcription")]
+pub use transcript_diff::{DriftStats, SegmentDiff, SegmentRef, SpeakerPair, TranscriptDiff};
+#[cfg(feature = "transcription")]
+pub use transcript_eval::{Evaluation, Reference, ReferenceSegment, SegmentScore, SpeakerScore};
+#[cfg(feature = "transcription")]
+pub use transcript_format::{
+    CueLimits, DEFAULT_MARKER_FPS, OutputFormat, SubtitleOptions, VttAlign, VttCueSettings,
+    format_timestamp_srt, format_timestamp_vtt, format_transcript, format_transcript_with,
+    media_src, segment_timestamp_secs, transcript_to_anki, transcript_to_anki_with,
+    transcript_to_audacity, transcript_to_html, transcript_to_html_with, transcript_to_jsonl,
+    transcript_to_lines, transcript_to_markers, transcript_to_markers_with, transcript_to_srt,
+    transcript_to_srt_with, transcript_to_txt, transcript_to_vtt, transcript_to_vtt_with,
+};
+#[cfg(feature = "transcription")]
+pub use transcript_merge::{MergePart, merge_transcripts};
+#[cfg(all(feature = "text", feature = "transcription"))]
+pub use transcript_normalize::TranscriptNormalizer;
+#[cfg(all(feature = "text", feature = "transcription"))]
+pub use transcript_qa::{Answer, Citation, TranscriptQa};
+#[cfg(feature = "transcription")]
+pub use transcript_schema::{SchemaError, TRANSCRIPT_SCHEMA_VERSION};
+#[cfg(all(feature = "transcription", not(target_arch = "wasm32")))]
+pub use transcription::{
+    MAX_TIMEOUT_SECS, MIN_TIMEOUT_SECS, Provider, TranscriptionBackend, TranscriptionError,
+    scaled_timeout_secs,
+};
+#[cfg(feature = "text")]
+pub use translation::Translator;
+pub use transport::TransportConfig;
+#[cfg(feature = "tts")]
+pub use tts_api::{
+    DialogueLine, SpeakerVoice, SpeechAudio, SpeechConfig, SpeechModel, TtsClient, TtsClientConfig,
+    TtsError,
+};
+pub use usage::{TokenUsage, WithUsage};
+#[cfg(feature = "veo")]
+pub use veo_api::{
+    GeneratedVideo, Resolution, VeoClient, VeoClientConfig, VeoError, VideoAspectRatio,
+    VideoGenConfig, VideoModel,
+};
+#[cfg(all(feature = "vision", not(target_arch = "wasm32")))]
+pub use video_frames::{FrameError, SceneFrames, extract_frame_at, extract_scene_frames};
+#[cfg(feature = "vision")]
+pub use vision_api::{
+    BlockKind, DominantColor, Frame, ImageDescription, OcrResult, Scene, SceneSummary, TextBlock,
+    ThumbnailPick, VisionClient,
+};
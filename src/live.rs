@@ -0,0 +1,446 @@
+use anyhow::{Context, Result};
+use clap::Parser;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+use tokio::process::{Child, Command};
+use tokio::sync::mpsc;
+use tracing::{Instrument, debug, info, info_span, warn};
+
+use transcript_tool::cli::{self, CommonArgs, SubtitleArgs, init_logging};
+use transcript_tool::config;
+use transcript_tool::storage::{OutputStorage, Storage};
+use transcript_tool::{
+    Clients, GeminiClient, GeminiClientConfig, GeminiError, OutputFormat, Provider,
+    TRANSCRIPT_SCHEMA_VERSION, Timestamp, TranscriptResponse, TranscriptSegment,
+    format_transcript_with,
+};
+
+/// Capture rate; plenty for speech and keeps a 30s window under 1MB
+const SAMPLE_RATE: u32 = 16_000;
+const BYTES_PER_SECOND: usize = SAMPLE_RATE as usize * 2;
+
+#[derive(Parser, Debug)]
+#[command(name = "live")]
+#[command(version)]
+#[command(about = "Transcribe the microphone as you speak, printing segments with speaker labels")]
+#[command(after_help = "EXAMPLES:
+    live
+    live --window 20 --step 4
+    live -o meeting.json
+    live --input-format alsa --device hw:1
+    live --input-format dshow --device \"Microphone (USB Audio)\"
+
+Capture goes through ffmpeg; list devices with
+    ffmpeg -f avfoundation -list_devices true -i \"\"   (macOS)
+    pactl list short sources                          (Linux, PulseAudio)
+    ffmpeg -list_devices true -f dshow -i dummy       (Windows)
+Press Ctrl-C to stop; the audio captured so far is transcribed before exiting.")]
+pub struct Args {
+    /// ffmpeg input device [default: `:0` on macOS, `default` on Linux]
+    #[arg(short, long)]
+    device: Option<String>,
+
+    /// ffmpeg input format [default: avfoundation on macOS, pulse on Linux, dshow on Windows]
+    #[arg(long)]
+    input_format: Option<String>,
+
+    /// Seconds of audio per final transcription window
+    #[arg(
+        short,
+        long,
+        default_value = "30",
+        value_parser = clap::value_parser!(u64).range(5..=600)
+    )]
+    window: u64,
+
+    /// Seconds between interim transcriptions of the open window; 0 disables them
+    #[arg(short, long, default_value = "5")]
+    step: u64,
+
+    /// Also save the final segments to this file, or an s3:// / gs:// URI
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Output format for --output [default: json]
+    #[arg(short, long, value_enum)]
+    format: Option<OutputFormat>,
+
+    /// Gemini model to use [default: gemini-2.5-flash]
+    #[arg(short, long, env = "FLASHECHO_MODEL")]
+    model: Option<String>,
+
+    /// API timeout in seconds
+    #[arg(short, long, default_value = "60")]
+    timeout: u64,
+
+    #[command(flatten)]
+    subtitles: SubtitleArgs,
+
+    #[command(flatten)]
+    common: CommonArgs,
+}
+
+fn default_input_format() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "avfoundation"
+    } else if cfg!(target_os = "windows") {
+        "dshow"
+    } else {
+        "pulse"
+    }
+}
+
+/// The `-i` argument for ffmpeg; dshow needs an explicit device name
+fn device_input(input_format: &str, device: Option<&str>) -> Result<String> {
+    match (input_format, device) {
+        ("dshow", Some(device)) if !device.starts_with("audio=") => Ok(format!("audio={}", device)),
+        ("dshow", None) => anyhow::bail!(
+            "dshow needs --device; list devices with: ffmpeg -list_devices true -f dshow -i dummy"
+        ),
+        (_, Some(device)) => Ok(device.to_string()),
+        ("avfoundation", None) => Ok(":0".to_string()),
+        (_, None) => Ok("default".to_string()),
+    }
+}
+
+/// Start ffmpeg writing mono 16-bit PCM from the microphone to stdout
+fn spawn_capture(input_format: &str, input: &str) -> Result<Child> {
+    info!("Capturing {} input {:?}", input_format, input);
+    Command::new("ffmpeg")
+        .args(["-hide_banner", "-loglevel", "error", "-nostdin", "-f"])
+        .arg(input_format)
+        .args(["-i", input, "-ac", "1", "-ar"])
+        .arg(SAMPLE_RATE.to_string())
+        .args(["-f", "s16le", "-"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .context("Failed to execute ffmpeg. Is ffmpeg installed?")
+}
+
+/// Wrap 16-bit mono PCM in a WAV header so Gemini can read it
+fn pcm_to_wav(pcm: &[u8]) -> Vec<u8> {
+    let data_len = pcm.len() as u32;
+    let mut wav = Vec::with_capacity(44 + pcm.len());
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&1u16.to_le_bytes()); // mono
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    wav.extend_from_slice(pcm);
+    wav
+}
+
+fn segment_line(segment: &TranscriptSegment) -> String {
+    format!(
+        "[{}] {}: {}",
+        segment.timestamp, segment.speaker, segment.content
+    )
+}
+
+/// Final segments of a live session
+#[derive(Default)]
+struct Session {
+    summaries: Vec<String>,
+    segments: Vec<TranscriptSegment>,
+}
+
+impl Session {
+    fn transcript(self) -> TranscriptResponse {
+        TranscriptResponse {
+            schema_version: TRANSCRIPT_SCHEMA_VERSION,
+            summary: self.summaries.join(" "),
+            segments: self.segments,
+            topics: Vec::new(),
+            keywords: Vec::new(),
+        }
+    }
+}
+
+struct Live {
+    client: GeminiClient,
+    pb: Option<ProgressBar>,
+    session: Session,
+}
+
+impl Live {
+    fn print(&self, line: String) {
+        match &self.pb {
+            Some(pb) => pb.println(line),
+            None => println!("{}", line),
+        }
+    }
+
+    /// Transcribe the open window and show its last segment in the spinner
+    async fn interim(&self, pcm: &[u8]) {
+        let Some(pb) = &self.pb else {
+            return;
+        };
+        let result = self
+            .client
+            .transcribe_audio(&pcm_to_wav(pcm), "audio/wav")
+            .instrument(info_span!("stage", stage = "interim"))
+            .await;
+        match result {
+            Ok(transcript) => {
+                if let Some(last) = transcript.segments.last() {
+                    pb.set_message(format!("{}: {}", last.speaker, last.content));
+                }
+            }
+            Err(e) => debug!("Interim transcription failed: {}", e),
+        }
+    }
+
+    /// Transcribe a finished window and print its segments
+    async fn finalize(&mut self, pcm: &[u8], offset_secs: u64) -> Result<()> {
+        let result = self
+            .client
+            .transcribe_audio(&pcm_to_wav(pcm), "audio/wav")
+            .instrument(info_span!("stage", stage = "transcribe"))
+            .await;
+        let transcript = match result {
+            Ok(transcript) => transcript,
+            // Out of budget stays out of budget; anything else only loses this window
+            Err(e @ GeminiError::BudgetExceeded(_)) => return Err(e.into()),
+            Err(e) => {
+                warn!(
+                    "Transcription of the window at {}s failed: {}",
+                    offset_secs, e
+                );
+                self.print(format!(
+                    "[{}] (transcription failed: {})",
+                    Timestamp::from_secs(offset_secs),
+                    e
+                ));
+                return Ok(());
+            }
+        };
+
+        if let Some(pb) = &self.pb {
+            pb.set_message("Listening...");
+        }
+        for mut segment in transcript.segments {
+            segment.shift(offset_secs);
+            self.print(segment_line(&segment));
+            self.session.segments.push(segment);
+        }
+        if !transcript.summary.trim().is_empty() {
+            self.session.summaries.push(transcript.summary);
+        }
+        Ok(())
+    }
+}
+
+/// Run with parsed arguments; shared by this binary and its `flashecho` subcommand
+pub async fn run(args: Args) -> Result<()> {
+    init_logging(args.common.verbose, args.common.log_format);
+    args.common.install_usage_ledger("live");
+
+    let input_format = args
+        .input_format
+        .clone()
+        .unwrap_or_else(|| default_input_format().to_string());
+    let input = device_input(&input_format, args.device.as_deref())?;
+    let span = info_span!("file", file = %input);
+    live(args, input_format, input).instrument(span).await
+}
+
+async fn live(args: Args, input_format: String, input: String) -> Result<()> {
+    let profile = args.common.load_profile()?;
+    let format = config::resolve(args.format, "format", profile.format.as_deref())?;
+    // Fail on a bad target before recording anything
+    let output = args
+        .output
+        .as_deref()
+        .map(|target| {
+            let default_name = format!("live.{}", format.extension());
+            OutputStorage::open_file(target, &default_name)
+                .with_context(|| format!("Invalid output target: {}", target))
+        })
+        .transpose()?;
+
+    let api_key = cli::gemini_api_key(&profile)?;
+    let clients = Clients::new(api_key).context("Failed to create HTTP client")?;
+    let budget = args
+        .common
+        .budget()
+        .context("Failed to read usage ledger for --budget-since")?;
+    let model = args
+        .model
+        .clone()
+        .or_else(|| profile.model_for(Provider::Gemini).map(str::to_string))
+        .unwrap_or_else(|| Provider::Gemini.default_model().to_string());
+    // Live audio never repeats, so there is nothing to cache
+    let client = clients.gemini(GeminiClientConfig {
+        timeout_secs: args.timeout,
+        max_retries: args.common.max_retries,
+        model,
+        budget,
+        ..Default::default()
+    });
+
+    let mut child = spawn_capture(&input_format, &input)?;
+    let mut stdout = child.stdout.take().context("ffmpeg has no stdout")?;
+    let mut stderr = child.stderr.take().context("ffmpeg has no stderr")?;
+    let stderr_task = tokio::spawn(async move {
+        let mut message = String::new();
+        stderr.read_to_string(&mut message).await.ok();
+        message
+    });
+
+    // Keep draining ffmpeg while requests are in flight, or its pipe fills up
+    // and capture stalls
+    let (tx, mut rx) = mpsc::unbounded_channel::<Vec<u8>>();
+    tokio::spawn(async move {
+        let mut buf = vec![0u8; BYTES_PER_SECOND / 10];
+        loop {
+            match stdout.read(&mut buf).await {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    if tx.send(buf[..n].to_vec()).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    let pb = if !args.common.quiet {
+        let pb = ProgressBar::new_spinner();
+        pb.set_style(
+            ProgressStyle::default_spinner()
+                .template("{spinner:.green} [{elapsed}] {msg}")
+                .unwrap(),
+        );
+        pb.enable_steady_tick(Duration::from_millis(100));
+        pb.set_message("Listening... (Ctrl-C to stop)");
+        Some(pb)
+    } else {
+        None
+    };
+    let mut live = Live {
+        client,
+        pb,
+        session: Session::default(),
+    };
+
+    let window_bytes = args.window as usize * BYTES_PER_SECOND;
+    let step_bytes = args.step as usize * BYTES_PER_SECOND;
+    let mut pcm: Vec<u8> = Vec::with_capacity(window_bytes);
+    let mut window_start = 0;
+    let mut last_interim = 0;
+    let mut capture_ended = false;
+
+    let stop = tokio::signal::ctrl_c();
+    tokio::pin!(stop);
+    loop {
+        tokio::select! {
+            _ = &mut stop => break,
+            chunk = rx.recv() => match chunk {
+                Some(chunk) => pcm.extend_from_slice(&chunk),
+                None => {
+                    capture_ended = true;
+                    break;
+                }
+            },
+        }
+        while let Ok(chunk) = rx.try_recv() {
+            pcm.extend_from_slice(&chunk);
+        }
+
+        if pcm.len() >= window_bytes {
+            let rest = pcm.split_off(window_bytes);
+            live.finalize(&pcm, window_start).await?;
+            pcm = rest;
+            window_start += args.window;
+            last_interim = 0;
+        } else if step_bytes > 0 && pcm.len() >= last_interim + step_bytes {
+            last_interim = pcm.len();
+            live.interim(&pcm).await;
+        }
+    }
+
+    child.start_kill().ok();
+    child.wait().await.ok();
+    if capture_ended && live.session.segments.is_empty() && pcm.is_empty() {
+        let message = stderr_task.await.unwrap_or_default();
+        anyhow::bail!("ffmpeg stopped capturing: {}", message.trim());
+    }
+
+    // Whatever was said since the last full window
+    if pcm.len() >= BYTES_PER_SECOND {
+        if let Some(pb) = &live.pb {
+            pb.set_message("Transcribing the last window...");
+        }
+        live.finalize(&pcm, window_start).await?;
+    }
+    if let Some(pb) = live.pb.take() {
+        pb.finish_and_clear();
+    }
+
+    let transcript = live.session.transcript();
+    if let Some((storage, key)) = output {
+        let content = format_transcript_with(&transcript, format, &args.subtitles.options())
+            .context("Failed to serialize to JSON")?;
+        storage
+            .put(&key, content.as_bytes())
+            .instrument(info_span!("stage", stage = "write"))
+            .await
+            .context("Failed to write output file")?;
+        info!("Transcript saved to {}", storage.location(&key));
+        if !args.common.quiet {
+            println!("Transcript saved to: {}", storage.location(&key));
+        }
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    run(Args::parse()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_device_input() {
+        assert_eq!(device_input("avfoundation", None).unwrap(), ":0");
+        assert_eq!(device_input("pulse", None).unwrap(), "default");
+        assert_eq!(device_input("alsa", Some("hw:1")).unwrap(), "hw:1");
+        assert_eq!(
+            device_input("dshow", Some("Microphone")).unwrap(),
+            "audio=Microphone"
+        );
+        assert_eq!(
+            device_input("dshow", Some("audio=Microphone")).unwrap(),
+            "audio=Microphone"
+        );
+        assert!(device_input("dshow", None).is_err());
+    }
+
+    #[test]
+    fn test_pcm_to_wav_header() {
+        let wav = pcm_to_wav(&[0u8; 64]);
+        assert_eq!(wav.len(), 108);
+        assert_eq!(&wav[..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(wav[4..8].try_into().unwrap()), 100);
+        assert_eq!(
+            u32::from_le_bytes(wav[24..28].try_into().unwrap()),
+            SAMPLE_RATE
+        );
+        assert_eq!(u32::from_le_bytes(wav[40..44].try_into().unwrap()), 64);
+    }
+}
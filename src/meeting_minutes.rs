@@ -0,0 +1,271 @@
+//! Meeting minutes from a transcript: decisions, action items and open
+//! questions extracted with [`TextClient`] against a response schema, and
+//! rendered as Markdown.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::gemini_api::TranscriptResponse;
+use crate::text_api::{Result, TextClient, TextError, TextGenConfig};
+use crate::transcript_format::transcript_to_lines;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MeetingMinutes {
+    pub summary: String,
+    #[serde(default)]
+    pub decisions: Vec<Decision>,
+    #[serde(default)]
+    pub action_items: Vec<ActionItem>,
+    #[serde(default)]
+    pub open_questions: Vec<OpenQuestion>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Decision {
+    pub decision: String,
+    /// Timestamp of the segment where it was made
+    #[serde(default)]
+    pub timestamp: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActionItem {
+    pub task: String,
+    #[serde(default)]
+    pub owner: Option<String>,
+    /// Due date as said in the meeting, e.g. "Friday" or "2024-06-01"
+    #[serde(default)]
+    pub due: Option<String>,
+    #[serde(default)]
+    pub timestamp: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OpenQuestion {
+    pub question: String,
+    #[serde(default)]
+    pub raised_by: Option<String>,
+    #[serde(default)]
+    pub timestamp: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct MinutesExtractor {
+    client: TextClient,
+    hint: Option<String>,
+}
+
+impl MinutesExtractor {
+    pub fn new(client: TextClient) -> Self {
+        Self { client, hint: None }
+    }
+
+    /// Context for the model, e.g. who the speakers are
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        let hint = hint.into();
+        self.hint = (!hint.trim().is_empty()).then(|| hint.trim().to_string());
+        self
+    }
+
+    fn gen_config(&self) -> TextGenConfig {
+        let mut instruction = String::from(
+            "You write meeting minutes from a transcript given as `[MM:SS] Speaker: text` \
+             lines. List only decisions that were actually agreed, action items someone \
+             committed to or was asked to do (with the owner and due date when they were \
+             said, never guessed), and questions that were raised but not answered. Give \
+             each item the timestamp of the line it comes from. Write the minutes in the \
+             language of the meeting.",
+        );
+        if let Some(hint) = &self.hint {
+            instruction.push_str(&format!("\n\nContext about the meeting: {}", hint));
+        }
+        TextGenConfig::new()
+            .with_system_instruction(instruction)
+            .with_response_schema(response_schema())
+    }
+
+    /// Extract minutes from `transcript` with a single request
+    pub async fn extract(&self, transcript: &TranscriptResponse) -> Result<MeetingMinutes> {
+        let prompt = transcript_to_lines(transcript);
+        let answer = self
+            .client
+            .generate_with_config(&prompt, Some(&self.gen_config()))
+            .await?;
+        let minutes: MeetingMinutes = serde_json::from_str(answer.trim()).map_err(|e| {
+            TextError::InvalidResponse(format!("Minutes don't match the schema: {}", e))
+        })?;
+        Ok(minutes.normalized())
+    }
+}
+
+fn response_schema() -> Value {
+    let timestamp = json!({ "type": "STRING", "description": "MM:SS of the source line" });
+    json!({
+        "type": "OBJECT",
+        "properties": {
+            "summary": { "type": "STRING", "description": "Two or three sentences on what the meeting covered." },
+            "decisions": {
+                "type": "ARRAY",
+                "items": {
+                    "type": "OBJECT",
+                    "properties": { "decision": { "type": "STRING" }, "timestamp": timestamp },
+                    "required": ["decision", "timestamp"]
+                }
+            },
+            "action_items": {
+                "type": "ARRAY",
+                "items": {
+                    "type": "OBJECT",
+                    "properties": {
+                        "task": { "type": "STRING" },
+                        "owner": { "type": "STRING", "description": "Speaker label or name; omit if nobody was named." },
+                        "due": { "type": "STRING", "description": "Due date as said; omit if none was given." },
+                        "timestamp": timestamp
+                    },
+                    "required": ["task", "timestamp"]
+                }
+            },
+            "open_questions": {
+                "type": "ARRAY",
+                "items": {
+                    "type": "OBJECT",
+                    "properties": {
+                        "question": { "type": "STRING" },
+                        "raised_by": { "type": "STRING" },
+                        "timestamp": timestamp
+                    },
+                    "required": ["question", "timestamp"]
+                }
+            }
+        },
+        "required": ["summary", "decisions", "action_items", "open_questions"]
+    })
+}
+
+/// `None` for a missing, blank or "unknown"-style answer
+fn known(value: Option<String>) -> Option<String> {
+    value.map(|v| v.trim().to_string()).filter(|v| {
+        !v.is_empty() && !matches!(v.to_lowercase().as_str(), "unknown" | "none" | "n/a")
+    })
+}
+
+impl MeetingMinutes {
+    /// Trim fields and drop owners or due dates the model left blank
+    fn normalized(mut self) -> Self {
+        self.summary = self.summary.trim().to_string();
+        self.decisions.retain(|d| !d.decision.trim().is_empty());
+        self.action_items.retain(|a| !a.task.trim().is_empty());
+        for item in &mut self.action_items {
+            item.owner = known(item.owner.take());
+            item.due = known(item.due.take());
+        }
+        self.open_questions
+            .retain(|q| !q.question.trim().is_empty());
+        for question in &mut self.open_questions {
+            question.raised_by = known(question.raised_by.take());
+        }
+        self
+    }
+
+    /// Markdown document with a heading per section; empty sections say so
+    pub fn to_markdown(&self, title: &str) -> String {
+        let at = |timestamp: &str| {
+            if timestamp.trim().is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", timestamp.trim())
+            }
+        };
+
+        let mut md = format!("# Meeting minutes: {}\n\n", title);
+        if !self.summary.is_empty() {
+            md.push_str(&format!("{}\n\n", self.summary));
+        }
+
+        md.push_str("## Decisions\n\n");
+        if self.decisions.is_empty() {
+            md.push_str("_None recorded._\n");
+        }
+        for decision in &self.decisions {
+            md.push_str(&format!(
+                "- {}{}\n",
+                decision.decision.trim(),
+                at(&decision.timestamp)
+            ));
+        }
+
+        md.push_str("\n## Action items\n\n");
+        if self.action_items.is_empty() {
+            md.push_str("_None recorded._\n");
+        }
+        for item in &self.action_items {
+            let owner = item
+                .owner
+                .as_deref()
+                .map(|owner| format!("**{}**: ", owner))
+                .unwrap_or_default();
+            let due = item
+                .due
+                .as_deref()
+                .map(|due| format!(" — due {}", due))
+                .unwrap_or_default();
+            md.push_str(&format!(
+                "- [ ] {}{}{}{}\n",
+                owner,
+                item.task.trim(),
+                due,
+                at(&item.timestamp)
+            ));
+        }
+
+        md.push_str("\n## Open questions\n\n");
+        if self.open_questions.is_empty() {
+            md.push_str("_None recorded._\n");
+        }
+        for question in &self.open_questions {
+            let by = question
+                .raised_by
+                .as_deref()
+                .map(|by| format!(" — {}", by))
+                .unwrap_or_default();
+            md.push_str(&format!(
+                "- {}{}{}\n",
+                question.question.trim(),
+                by,
+                at(&question.timestamp)
+            ));
+        }
+        md
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalized_and_markdown() {
+        let minutes: MeetingMinutes = serde_json::from_value(json!({
+            "summary": " Release planning. ",
+            "decisions": [{"decision": "Ship on Monday", "timestamp": "01:10"}],
+            "action_items": [
+                {"task": "Write release notes", "owner": "Ann", "due": "Friday", "timestamp": "02:00"},
+                {"task": "Book a room", "owner": " unknown ", "due": "", "timestamp": ""},
+                {"task": "  ", "timestamp": "03:00"}
+            ],
+            "open_questions": []
+        }))
+        .unwrap();
+        let minutes = minutes.normalized();
+        assert_eq!(minutes.action_items.len(), 2);
+        assert_eq!(minutes.action_items[1].owner, None);
+        assert_eq!(minutes.action_items[1].due, None);
+
+        let md = minutes.to_markdown("standup");
+        assert!(md.starts_with("# Meeting minutes: standup\n\nRelease planning.\n\n"));
+        assert!(md.contains("- Ship on Monday (01:10)\n"));
+        assert!(md.contains("- [ ] **Ann**: Write release notes — due Friday (02:00)\n"));
+        assert!(md.contains("- [ ] Book a room\n"));
+        assert!(md.ends_with("## Open questions\n\n_None recorded._\n"));
+    }
+}
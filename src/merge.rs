@@ -0,0 +1,213 @@
+use anyhow::{Context, Result};
+use clap::Parser;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tracing::{Instrument, info, info_span};
+
+use transcript_tool::cli::{self, CommonArgs, SubtitleArgs, init_logging};
+use transcript_tool::config;
+use transcript_tool::storage::{OutputStorage, Storage};
+use transcript_tool::transcript_merge::summarize;
+use transcript_tool::{
+    Clients, MergePart, OutputFormat, TextClientConfig, TextModel, TranscriptResponse,
+    format_transcript_with, merge_transcripts,
+};
+
+#[derive(Parser, Debug)]
+#[command(name = "merge")]
+#[command(version)]
+#[command(about = "Merge the transcripts of a recording split across several files")]
+#[command(after_help = "EXAMPLES:
+    merge part1.json part2.json --offset 00:30:00
+    merge a.json b.json c.json --offset 45:00 --offset 01:30:00 -f srt
+    merge a.json b.json --shared-speakers --no-summary -o talk.json")]
+pub struct Args {
+    /// Transcript JSON files written by `convert` or `batch_convert`, in order
+    #[arg(value_name = "TRANSCRIPT", num_args = 2.., required = true)]
+    inputs: Vec<PathBuf>,
+
+    /// Start of the second, third, ... transcript in the combined recording
+    /// (HH:MM:SS, MM:SS or seconds); inputs without one continue right after
+    /// the last segment of the previous input
+    #[arg(long, value_name = "TIME", value_parser = cli::parse_time)]
+    offset: Vec<u64>,
+
+    /// The same `Speaker N` label means the same person in every input
+    #[arg(long)]
+    shared_speakers: bool,
+
+    /// Join the summaries of the inputs instead of writing a new one with Gemini
+    #[arg(long)]
+    no_summary: bool,
+
+    /// Output file path (`-` for stdout), or an s3:// / gs:// URI
+    /// [default: <first input>.merged.<format>]
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Output format [default: json]
+    #[arg(short, long, value_enum)]
+    format: Option<OutputFormat>,
+
+    /// Text model for the summary: flash (default), flash-lite, pro
+    #[arg(
+        short = 'm',
+        long,
+        env = "FLASHECHO_TEXT_MODEL",
+        default_value = "flash"
+    )]
+    model: String,
+
+    /// Timeout of the API request in seconds
+    #[arg(short, long, default_value = "120")]
+    timeout: u64,
+
+    #[command(flatten)]
+    subtitles: SubtitleArgs,
+
+    #[command(flatten)]
+    common: CommonArgs,
+}
+
+/// `talk-1.json` -> `talk-1.merged.json`
+fn default_output(first: &Path, format: OutputFormat) -> PathBuf {
+    first.with_extension(format!("merged.{}", format.extension()))
+}
+
+async fn read_transcript(path: &Path) -> Result<TranscriptResponse> {
+    let content = fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Failed to read transcript {:?}", path))?;
+    TranscriptResponse::from_json_any_version(&content)
+        .with_context(|| format!("Failed to parse transcript JSON {:?}", path))
+}
+
+/// Run with parsed arguments; shared by this binary and its `flashecho` subcommand
+pub async fn run(args: Args) -> Result<()> {
+    init_logging(args.common.verbose, args.common.log_format);
+    args.common.install_usage_ledger("merge");
+
+    let span = info_span!("file", file = %args.inputs[0].display());
+    merge(args).instrument(span).await
+}
+
+async fn merge(args: Args) -> Result<()> {
+    if args.offset.len() >= args.inputs.len() {
+        anyhow::bail!(
+            "Got {} --offset values for {} inputs: the first input starts at 00:00, so give at most {}",
+            args.offset.len(),
+            args.inputs.len(),
+            args.inputs.len() - 1
+        );
+    }
+
+    let mut transcripts = Vec::with_capacity(args.inputs.len());
+    for input in &args.inputs {
+        transcripts.push(read_transcript(input).await?);
+    }
+    let parts: Vec<MergePart> = transcripts
+        .iter()
+        .enumerate()
+        .map(|(i, transcript)| {
+            let part = MergePart::new(transcript);
+            match i.checked_sub(1).and_then(|i| args.offset.get(i)) {
+                Some(&offset) => part.with_offset(offset),
+                None => part,
+            }
+        })
+        .collect();
+    let mut merged = merge_transcripts(&parts, args.shared_speakers);
+
+    let profile = args.common.load_profile()?;
+    let format = config::resolve(args.format, "format", profile.format.as_deref())?;
+    let to_stdout = args.output.as_deref() == Some("-");
+
+    if !args.no_summary && !merged.segments.is_empty() {
+        let model: TextModel = args.model.parse().map_err(|e: String| anyhow::anyhow!(e))?;
+        let clients =
+            Clients::new(cli::gemini_api_key(&profile)?).context("Failed to create HTTP client")?;
+        let budget = args
+            .common
+            .budget()
+            .context("Failed to read usage ledger for --budget-since")?;
+        let client = clients.text(TextClientConfig {
+            timeout_secs: args.timeout,
+            max_retries: args.common.max_retries,
+            model,
+            cache: args.common.response_cache(),
+            budget,
+            ..Default::default()
+        });
+
+        let pb = cli::spinner(
+            args.common.quiet || to_stdout,
+            format!("Summarizing {} merged segments...", merged.segments.len()),
+        );
+        let result = summarize(&client, &merged)
+            .instrument(info_span!("stage", stage = "summarize"))
+            .await;
+        if let Some(pb) = pb {
+            pb.finish_and_clear();
+        }
+        merged.summary = result.map_err(|e| anyhow::anyhow!("Summary failed: {}", e))?;
+    }
+
+    let output = format_transcript_with(&merged, format, &args.subtitles.options())
+        .context("Failed to serialize to JSON")?;
+    if to_stdout {
+        let mut stdout = tokio::io::stdout();
+        stdout
+            .write_all(output.as_bytes())
+            .await
+            .context("Failed to write to stdout")?;
+        stdout.flush().await.context("Failed to flush stdout")?;
+        return Ok(());
+    }
+
+    let default_path = default_output(&args.inputs[0], format);
+    let default_name = default_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let target = args
+        .output
+        .clone()
+        .unwrap_or_else(|| default_path.to_string_lossy().to_string());
+    let (storage, key) = OutputStorage::open_file(&target, &default_name)
+        .with_context(|| format!("Invalid output target: {}", target))?;
+    storage
+        .put(&key, output.as_bytes())
+        .instrument(info_span!("stage", stage = "write"))
+        .await
+        .context("Failed to write output file")?;
+    info!("Merged transcript saved to {}", storage.location(&key));
+
+    if !args.common.quiet {
+        println!(
+            "Merged {} transcripts: {} segments",
+            args.inputs.len(),
+            merged.segments.len()
+        );
+        println!("Transcript saved to: {}", storage.location(&key));
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    run(Args::parse()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_output() {
+        assert_eq!(
+            default_output(Path::new("talks/part1.json"), OutputFormat::Srt),
+            PathBuf::from("talks/part1.merged.srt")
+        );
+    }
+}
@@ -0,0 +1,332 @@
+//! Request metrics collected from every API client: request counts, retries,
+//! rate limits, uploaded bytes, latency and token usage. Enabled with the
+//! `metrics` feature; without it the recorders compile to nothing.
+//!
+//! Read them with [`render`] (Prometheus text format) or expose them over
+//! HTTP with [`serve`], which answers `GET /metrics`.
+
+#![cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+
+#[cfg(feature = "metrics")]
+use std::collections::BTreeMap;
+#[cfg(feature = "metrics")]
+use std::fmt::Write;
+#[cfg(feature = "metrics")]
+use std::sync::{Mutex, OnceLock};
+
+/// Latency histogram bucket bounds, in seconds. Generation requests run from
+/// sub-second to the 10 minute default timeout.
+#[cfg(feature = "metrics")]
+const LATENCY_BUCKETS: &[f64] = &[
+    0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0,
+];
+
+#[cfg(feature = "metrics")]
+const REQUESTS: &str = "flashecho_requests_total";
+#[cfg(feature = "metrics")]
+const RETRIES: &str = "flashecho_retries_total";
+#[cfg(feature = "metrics")]
+const RATE_LIMITED: &str = "flashecho_rate_limited_total";
+#[cfg(feature = "metrics")]
+const UPLOAD_BYTES: &str = "flashecho_upload_bytes_total";
+#[cfg(feature = "metrics")]
+const TOKENS: &str = "flashecho_tokens_total";
+#[cfg(feature = "metrics")]
+const LATENCY: &str = "flashecho_request_duration_seconds";
+
+/// Name, type and help text of every metric, in render order
+#[cfg(feature = "metrics")]
+const DESCRIPTIONS: &[(&str, &str, &str)] = &[
+    (
+        REQUESTS,
+        "counter",
+        "API request attempts by client and outcome",
+    ),
+    (RETRIES, "counter", "Request attempts that were retried"),
+    (RATE_LIMITED, "counter", "Responses with HTTP 429"),
+    (UPLOAD_BYTES, "counter", "Media bytes sent to the APIs"),
+    (TOKENS, "counter", "Tokens reported in API usage metadata"),
+    (LATENCY, "histogram", "API request attempt latency"),
+];
+
+#[cfg(feature = "metrics")]
+type Labels = Vec<(&'static str, &'static str)>;
+
+#[cfg(feature = "metrics")]
+#[derive(Debug, Default, Clone)]
+struct Histogram {
+    /// Cumulative count per bucket in `LATENCY_BUCKETS`
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+#[cfg(all(feature = "metrics", any(feature = "__retry", test)))]
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        if self.buckets.is_empty() {
+            self.buckets = vec![0; LATENCY_BUCKETS.len()];
+        }
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if value <= *bound {
+                *bucket += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+#[cfg(feature = "metrics")]
+#[derive(Debug, Default)]
+struct Registry {
+    counters: BTreeMap<(&'static str, Labels), u64>,
+    histograms: BTreeMap<(&'static str, Labels), Histogram>,
+}
+
+#[cfg(feature = "metrics")]
+impl Registry {
+    #[cfg(any(feature = "__client", test))]
+    fn add(&mut self, name: &'static str, labels: Labels, value: u64) {
+        *self.counters.entry((name, labels)).or_default() += value;
+    }
+
+    #[cfg(any(feature = "__retry", test))]
+    fn observe(&mut self, name: &'static str, labels: Labels, value: f64) {
+        self.histograms
+            .entry((name, labels))
+            .or_default()
+            .observe(value);
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::new();
+
+        for (name, kind, help) in DESCRIPTIONS {
+            let counters: Vec<_> = self
+                .counters
+                .iter()
+                .filter(|((n, _), _)| n == name)
+                .collect();
+            let histograms: Vec<_> = self
+                .histograms
+                .iter()
+                .filter(|((n, _), _)| n == name)
+                .collect();
+            if counters.is_empty() && histograms.is_empty() {
+                continue;
+            }
+
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} {}", name, kind);
+            for ((_, labels), value) in counters {
+                let _ = writeln!(out, "{}{} {}", name, format_labels(labels, None), value);
+            }
+            for ((_, labels), histogram) in histograms {
+                for (bound, count) in LATENCY_BUCKETS.iter().zip(&histogram.buckets) {
+                    let le = bound.to_string();
+                    let _ = writeln!(
+                        out,
+                        "{}_bucket{} {}",
+                        name,
+                        format_labels(labels, Some(&le)),
+                        count
+                    );
+                }
+                let _ = writeln!(
+                    out,
+                    "{}_bucket{} {}",
+                    name,
+                    format_labels(labels, Some("+Inf")),
+                    histogram.count
+                );
+                let _ = writeln!(
+                    out,
+                    "{}_sum{} {}",
+                    name,
+                    format_labels(labels, None),
+                    histogram.sum
+                );
+                let _ = writeln!(
+                    out,
+                    "{}_count{} {}",
+                    name,
+                    format_labels(labels, None),
+                    histogram.count
+                );
+            }
+        }
+
+        out
+    }
+}
+
+#[cfg(feature = "metrics")]
+fn format_labels(labels: &Labels, le: Option<&str>) -> String {
+    let mut pairs: Vec<String> = labels
+        .iter()
+        .map(|(key, value)| format!("{}=\"{}\"", key, value))
+        .collect();
+    if let Some(le) = le {
+        pairs.push(format!("le=\"{}\"", le));
+    }
+    if pairs.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", pairs.join(","))
+    }
+}
+
+#[cfg(feature = "metrics")]
+fn with_registry(f: impl FnOnce(&mut Registry)) {
+    static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+    let registry = REGISTRY.get_or_init(Default::default);
+    // A panic while holding the lock leaves the counters usable
+    let mut guard = registry.lock().unwrap_or_else(|e| e.into_inner());
+    f(&mut guard);
+}
+
+/// Content type of [`render`]'s output
+#[cfg(feature = "metrics")]
+pub(crate) const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// All metrics recorded so far, in the Prometheus text exposition format
+#[cfg(feature = "metrics")]
+pub fn render() -> String {
+    let mut out = String::new();
+    with_registry(|registry| out = registry.render());
+    out
+}
+
+/// Serve [`render`] at `GET /metrics` on `addr` until the task is dropped.
+/// Meant to be spawned next to long-running modes such as `--watch-dir`.
+#[cfg(all(feature = "metrics", not(target_arch = "wasm32")))]
+pub async fn serve(addr: std::net::SocketAddr) -> std::io::Result<()> {
+    use crate::http_server::{read_request, write_response};
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    loop {
+        let (stream, _) = listener.accept().await?;
+        tokio::spawn(async move {
+            let mut reader = tokio::io::BufReader::new(stream);
+            let timeout = std::time::Duration::from_secs(30);
+            let (status, content_type, body) = match read_request(&mut reader, 0, timeout).await {
+                Ok(Ok(request)) if request.method == "GET" && request.path == "/metrics" => {
+                    (200, CONTENT_TYPE, render())
+                }
+                Ok(Ok(_)) => (404, "text/plain", "not found\n".to_string()),
+                Ok(Err(rejection)) => (rejection.status, "text/plain", rejection.message + "\n"),
+                Err(_) => return,
+            };
+            let _ = write_response(
+                reader.get_mut(),
+                status,
+                &[("Content-Type", content_type)],
+                body.as_bytes(),
+            )
+            .await;
+        });
+    }
+}
+
+/// One request attempt by `client` finished after `latency_ms`
+#[cfg(feature = "__retry")]
+pub(crate) fn record_request(client: &'static str, success: bool, latency_ms: u64) {
+    #[cfg(feature = "metrics")]
+    with_registry(|r| {
+        let outcome = if success { "success" } else { "error" };
+        r.add(REQUESTS, vec![("client", client), ("outcome", outcome)], 1);
+        r.observe(
+            LATENCY,
+            vec![("client", client)],
+            latency_ms as f64 / 1000.0,
+        );
+    });
+}
+
+/// A failed attempt by `client` is about to be retried
+#[cfg(feature = "__retry")]
+pub(crate) fn record_retry(client: &'static str) {
+    #[cfg(feature = "metrics")]
+    with_registry(|r| r.add(RETRIES, vec![("client", client)], 1));
+}
+
+/// `client` received an HTTP 429
+#[cfg(feature = "__retry")]
+pub(crate) fn record_rate_limited(client: &'static str) {
+    #[cfg(feature = "metrics")]
+    with_registry(|r| r.add(RATE_LIMITED, vec![("client", client)], 1));
+}
+
+/// `client` sent `bytes` of audio/image data
+#[cfg(any(
+    feature = "transcription",
+    feature = "file-api",
+    feature = "imagen-edit"
+))]
+pub(crate) fn record_upload_bytes(client: &'static str, bytes: usize) {
+    #[cfg(feature = "metrics")]
+    with_registry(|r| r.add(UPLOAD_BYTES, vec![("client", client)], bytes as u64));
+}
+
+/// Token counts from a Gemini `usageMetadata` object, if the response has one
+#[cfg(any(
+    feature = "transcription",
+    feature = "imagen",
+    feature = "tts",
+    feature = "text"
+))]
+pub(crate) fn record_gemini_usage(client: &'static str, response: &serde_json::Value) {
+    #[cfg(feature = "metrics")]
+    {
+        let usage = &response["usageMetadata"];
+        for (field, kind) in [
+            ("promptTokenCount", "prompt"),
+            ("candidatesTokenCount", "output"),
+        ] {
+            if let Some(count) = usage[field].as_u64() {
+                with_registry(|r| r.add(TOKENS, vec![("client", client), ("kind", kind)], count));
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_counters_and_histogram() {
+        let mut registry = Registry::default();
+        registry.add(
+            REQUESTS,
+            vec![("client", "gemini"), ("outcome", "success")],
+            1,
+        );
+        registry.add(
+            REQUESTS,
+            vec![("client", "gemini"), ("outcome", "success")],
+            1,
+        );
+        registry.observe(LATENCY, vec![("client", "gemini")], 0.3);
+        registry.observe(LATENCY, vec![("client", "gemini")], 700.0);
+
+        let text = registry.render();
+        assert!(text.contains("# TYPE flashecho_requests_total counter\n"));
+        assert!(
+            text.contains("flashecho_requests_total{client=\"gemini\",outcome=\"success\"} 2\n")
+        );
+        assert!(text.contains(
+            "flashecho_request_duration_seconds_bucket{client=\"gemini\",le=\"0.25\"} 0\n"
+        ));
+        assert!(text.contains(
+            "flashecho_request_duration_seconds_bucket{client=\"gemini\",le=\"0.5\"} 1\n"
+        ));
+        assert!(text.contains(
+            "flashecho_request_duration_seconds_bucket{client=\"gemini\",le=\"+Inf\"} 2\n"
+        ));
+        assert!(text.contains("flashecho_request_duration_seconds_count{client=\"gemini\"} 2\n"));
+        // Metrics with no samples are left out entirely
+        assert!(!text.contains(RETRIES));
+    }
+}
@@ -0,0 +1,223 @@
+use anyhow::{Context, Result};
+use clap::Parser;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tracing::{Instrument, info, info_span};
+
+use transcript_tool::cli::{self, CommonArgs, init_logging};
+use transcript_tool::storage::{OutputStorage, Storage};
+use transcript_tool::{Clients, MinutesExtractor, TextClientConfig, TextModel, TranscriptResponse};
+
+#[derive(Parser, Debug)]
+#[command(name = "minutes")]
+#[command(version)]
+#[command(
+    about = "Turn a transcript into meeting minutes: decisions, action items and open questions"
+)]
+#[command(after_help = "EXAMPLES:
+    minutes standup.json
+    minutes standup.json --hint \"Speaker 1 is Ann (PM), Speaker 2 is Bo (backend)\"
+    minutes standup.json --json -o standup.minutes.json
+    minutes standup.json -o -")]
+pub struct Args {
+    /// Transcript JSON written by `convert` or `batch_convert` (`-` for stdin)
+    #[arg(value_name = "TRANSCRIPT")]
+    input: PathBuf,
+
+    /// Output file path (`-` for stdout), or an s3:// / gs:// URI
+    /// [default: <input>.minutes.md]
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Write the minutes as JSON instead of Markdown
+    #[arg(long)]
+    json: bool,
+
+    /// Heading of the Markdown document [default: the input file name]
+    #[arg(long)]
+    title: Option<String>,
+
+    /// Context for the model, e.g. who the speakers are
+    #[arg(long)]
+    hint: Option<String>,
+
+    /// Text model: flash (default), flash-lite, pro
+    #[arg(
+        short = 'm',
+        long,
+        env = "FLASHECHO_TEXT_MODEL",
+        default_value = "flash"
+    )]
+    model: String,
+
+    /// Timeout of the API request in seconds
+    #[arg(short, long, default_value = "120")]
+    timeout: u64,
+
+    #[command(flatten)]
+    common: CommonArgs,
+}
+
+/// `talk.json` -> `talk.minutes.md`
+fn default_output(input: &Path, json: bool) -> PathBuf {
+    let extension = if json { "minutes.json" } else { "minutes.md" };
+    if input == Path::new("-") {
+        return PathBuf::from(format!("transcript.{}", extension));
+    }
+    input.with_extension(extension)
+}
+
+fn default_title(input: &Path) -> String {
+    match input.file_stem() {
+        Some(stem) if input != Path::new("-") => stem.to_string_lossy().to_string(),
+        _ => "transcript".to_string(),
+    }
+}
+
+async fn read_input(path: &Path) -> Result<String> {
+    if path == Path::new("-") {
+        let mut content = String::new();
+        tokio::io::stdin()
+            .read_to_string(&mut content)
+            .await
+            .context("Failed to read stdin")?;
+        return Ok(content);
+    }
+    fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Failed to read transcript {:?}", path))
+}
+
+/// Run with parsed arguments; shared by this binary and its `flashecho` subcommand
+pub async fn run(args: Args) -> Result<()> {
+    init_logging(args.common.verbose, args.common.log_format);
+    args.common.install_usage_ledger("minutes");
+
+    let span = info_span!("file", file = %args.input.display());
+    minutes(args).instrument(span).await
+}
+
+async fn minutes(args: Args) -> Result<()> {
+    let model: TextModel = args.model.parse().map_err(|e: String| anyhow::anyhow!(e))?;
+    let content = read_input(&args.input).await?;
+    let transcript = TranscriptResponse::from_json_any_version(&content)
+        .context("Failed to parse transcript JSON")?;
+    if transcript.segments.is_empty() {
+        anyhow::bail!("Transcript has no segments");
+    }
+    let to_stdout = args.output.as_deref() == Some("-");
+
+    let profile = args.common.load_profile()?;
+    let clients =
+        Clients::new(cli::gemini_api_key(&profile)?).context("Failed to create HTTP client")?;
+    let budget = args
+        .common
+        .budget()
+        .context("Failed to read usage ledger for --budget-since")?;
+    let client = clients.text(TextClientConfig {
+        timeout_secs: args.timeout,
+        max_retries: args.common.max_retries,
+        model,
+        cache: args.common.response_cache(),
+        budget,
+        ..Default::default()
+    });
+    let mut extractor = MinutesExtractor::new(client);
+    if let Some(hint) = &args.hint {
+        extractor = extractor.with_hint(hint);
+    }
+
+    let pb = cli::spinner(
+        args.common.quiet || to_stdout,
+        format!(
+            "Extracting minutes from {} segments...",
+            transcript.segments.len()
+        ),
+    );
+    let result = extractor
+        .extract(&transcript)
+        .instrument(info_span!("stage", stage = "minutes"))
+        .await;
+    if let Some(pb) = pb {
+        pb.finish_and_clear();
+    }
+    let minutes = result.map_err(|e| anyhow::anyhow!("Minutes extraction failed: {}", e))?;
+
+    let output = if args.json {
+        serde_json::to_string_pretty(&minutes).context("Failed to serialize minutes")?
+    } else {
+        let title = args
+            .title
+            .clone()
+            .unwrap_or_else(|| default_title(&args.input));
+        minutes.to_markdown(&title)
+    };
+
+    if to_stdout {
+        let mut stdout = tokio::io::stdout();
+        stdout
+            .write_all(output.as_bytes())
+            .await
+            .context("Failed to write to stdout")?;
+        stdout.flush().await.context("Failed to flush stdout")?;
+        return Ok(());
+    }
+
+    let default_path = default_output(&args.input, args.json);
+    let default_name = default_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let target = args
+        .output
+        .clone()
+        .unwrap_or_else(|| default_path.to_string_lossy().to_string());
+    let (storage, key) = OutputStorage::open_file(&target, &default_name)
+        .with_context(|| format!("Invalid output target: {}", target))?;
+    storage
+        .put(&key, output.as_bytes())
+        .instrument(info_span!("stage", stage = "write"))
+        .await
+        .context("Failed to write minutes")?;
+    let location = storage.location(&key);
+    info!("Minutes written to {}", location);
+    if !args.common.quiet {
+        println!(
+            "{} decisions, {} action items, {} open questions",
+            minutes.decisions.len(),
+            minutes.action_items.len(),
+            minutes.open_questions.len()
+        );
+        println!("Saved to: {}", location);
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    run(Args::parse()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_output() {
+        assert_eq!(
+            default_output(Path::new("talks/standup.json"), false),
+            PathBuf::from("talks/standup.minutes.md")
+        );
+        assert_eq!(
+            default_output(Path::new("-"), true),
+            PathBuf::from("transcript.minutes.json")
+        );
+    }
+
+    #[test]
+    fn test_default_title() {
+        assert_eq!(default_title(Path::new("talks/standup.json")), "standup");
+        assert_eq!(default_title(Path::new("-")), "transcript");
+    }
+}
@@ -0,0 +1,223 @@
+use anyhow::{Context, Result};
+use clap::Parser;
+use std::path::Path;
+use tokio::io::AsyncWriteExt;
+use tracing::{Instrument, debug, info_span};
+
+use transcript_tool::cli::{self, CommonArgs, init_logging};
+use transcript_tool::storage::{OutputStorage, Storage};
+use transcript_tool::{Clients, GeneratedMusic, MusicClientConfig, MusicGenConfig, music_api};
+
+#[derive(Parser, Debug)]
+#[command(name = "music")]
+#[command(version)]
+#[command(about = "Generate background music using the Lyria model of the Gemini API")]
+#[command(after_help = "EXAMPLES:
+    music \"Warm acoustic intro for a cooking video\"
+    music -g lo-fi --mood calm --bpm 80 \"Rainy cafe at night\"
+    music --instruments piano,strings -d 15 -o intro.wav \"Gentle opening theme\"
+    music --negative-prompt \"drums, vocals\" --seed 42 \"Ambient space drone\"
+    music \"Upbeat corporate background\" -o s3://my-bucket/music/
+    music \"Chiptune loop\" -o - | some-player")]
+pub struct Args {
+    /// Description of the track to generate
+    #[arg(value_name = "PROMPT")]
+    prompt: String,
+
+    /// Output file path (`-` for stdout); s3:// and gs:// URIs write to object storage
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Genre, e.g. lo-fi, orchestral, synthwave
+    #[arg(short = 'g', long)]
+    genre: Option<String>,
+
+    /// Mood, e.g. calm, energetic, melancholic
+    #[arg(long)]
+    mood: Option<String>,
+
+    /// Tempo in beats per minute (40-240)
+    #[arg(long)]
+    bpm: Option<u32>,
+
+    /// Instruments to feature, comma-separated
+    #[arg(long, value_delimiter = ',')]
+    instruments: Vec<String>,
+
+    /// What the track should not contain
+    #[arg(long)]
+    negative_prompt: Option<String>,
+
+    /// Keep only the first N seconds of the 30-second clip (1-30)
+    #[arg(short = 'd', long)]
+    duration: Option<u32>,
+
+    /// Seed for reproducible tracks
+    #[arg(long)]
+    seed: Option<u32>,
+
+    /// Timeout of each API request in seconds
+    #[arg(short, long, default_value = "120")]
+    timeout: u64,
+
+    #[command(flatten)]
+    common: CommonArgs,
+}
+
+fn build_gen_config(args: &Args) -> MusicGenConfig {
+    MusicGenConfig {
+        genre: args.genre.clone(),
+        mood: args.mood.clone(),
+        bpm: args.bpm,
+        instruments: args
+            .instruments
+            .iter()
+            .map(|i| i.trim().to_string())
+            .filter(|i| !i.is_empty())
+            .collect(),
+        negative_prompt: args.negative_prompt.clone(),
+        duration_secs: args.duration,
+        seed: args.seed,
+    }
+}
+
+/// Generate output filename: music-hash(prompt + style).wav
+fn generate_output_filename(prompt: &str, gen_config: &MusicGenConfig) -> String {
+    let hash_input = format!("{}{:?}", prompt, gen_config);
+    let hash = blake3::hash(hash_input.as_bytes());
+    format!("music-{}.wav", &hash.to_hex()[..6])
+}
+
+/// Write raw audio bytes to stdout (for piping into other tools)
+async fn write_music_to_stdout(music: &GeneratedMusic) -> Result<()> {
+    let mut stdout = tokio::io::stdout();
+    stdout
+        .write_all(&music.data)
+        .await
+        .context("Failed to write audio to stdout")?;
+    stdout.flush().await.context("Failed to flush stdout")?;
+    Ok(())
+}
+
+/// Run with parsed arguments; shared by this binary and its `flashecho` subcommand
+pub async fn run(args: Args) -> Result<()> {
+    init_logging(args.common.verbose, args.common.log_format);
+    args.common.install_usage_ledger("music");
+
+    if args
+        .duration
+        .is_some_and(|d| d == 0 || d > music_api::CLIP_SECS)
+    {
+        anyhow::bail!(
+            "--duration must be between 1 and {} seconds",
+            music_api::CLIP_SECS
+        );
+    }
+
+    let to_stdout = args.output.as_deref() == Some("-");
+    let quiet = args.common.quiet || to_stdout;
+    let gen_config = build_gen_config(&args);
+    let default_name = generate_output_filename(&args.prompt, &gen_config);
+    let output = if to_stdout {
+        None
+    } else {
+        let target = args.output.as_deref().unwrap_or(&default_name);
+        debug!("Output path: {:?}", target);
+        Some(OutputStorage::open_file(target, &default_name).context("Invalid output path")?)
+    };
+
+    let profile = args.common.load_profile()?;
+    let clients =
+        Clients::new(cli::gemini_api_key(&profile)?).context("Failed to create HTTP client")?;
+    let budget = args
+        .common
+        .budget()
+        .context("Failed to read usage ledger for --budget-since")?;
+    let client = clients.music(MusicClientConfig {
+        timeout_secs: args.timeout,
+        max_retries: args.common.max_retries,
+        cache: args.common.response_cache(),
+        budget,
+        ..Default::default()
+    });
+
+    let pb = cli::spinner(quiet, "Generating music...".into());
+
+    let music = client
+        .generate_music_with_config(&args.prompt, Some(&gen_config))
+        .instrument(info_span!("stage", stage = "generate"))
+        .await
+        .map_err(|e| anyhow::anyhow!("Music generation failed: {}", e))?;
+
+    if let Some(pb) = pb {
+        pb.finish_with_message("Music generated!");
+    }
+
+    let Some((storage, key)) = output else {
+        return write_music_to_stdout(&music).await;
+    };
+    let key = if Path::new(&key).extension().is_none() {
+        format!("{}.{}", key, music.extension())
+    } else {
+        key
+    };
+    storage
+        .put(&key, &music.data)
+        .instrument(info_span!("stage", stage = "write"))
+        .await
+        .context("Failed to write audio file")?;
+
+    if !args.common.quiet {
+        let length = music
+            .duration_secs()
+            .map(|secs| format!(" ({:.0}s)", secs))
+            .unwrap_or_default();
+        println!("Saved to: {}{}", storage.location(&key), length);
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    run(Args::parse()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_gen_config() {
+        let args = Args::parse_from([
+            "music",
+            "--genre",
+            "lo-fi",
+            "--bpm",
+            "80",
+            "--instruments",
+            "piano, strings,",
+            "-d",
+            "15",
+            "Rainy cafe",
+        ]);
+        let config = build_gen_config(&args);
+        assert_eq!(config.genre.as_deref(), Some("lo-fi"));
+        assert_eq!(config.bpm, Some(80));
+        assert_eq!(config.instruments, ["piano", "strings"]);
+        assert_eq!(config.duration_secs, Some(15));
+        assert!(config.mood.is_none());
+    }
+
+    #[test]
+    fn test_generate_output_filename() {
+        let calm = MusicGenConfig::new().with_mood("calm");
+        let filename = generate_output_filename("Rainy cafe", &calm);
+        assert!(filename.starts_with("music-"));
+        assert!(filename.ends_with(".wav"));
+        assert_eq!(filename, generate_output_filename("Rainy cafe", &calm));
+        assert_ne!(
+            filename,
+            generate_output_filename("Rainy cafe", &MusicGenConfig::new())
+        );
+    }
+}
@@ -0,0 +1,712 @@
+//! Lyria music generation. A prompt, with optional genre, mood, tempo and
+//! instrument hints folded into it, is sent to the model's `predict` method,
+//! which returns one 30-second WAV clip. Shorter durations are cut from the
+//! start of that clip locally; the full clip is billed either way.
+
+use base64::Engine;
+use reqwest::{Client, Method};
+use serde_json::{Value, json};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{Instrument, debug, field, info, info_span, warn};
+
+use crate::auth::{AuthMode, GEMINI_BASE_URL};
+use crate::budget::{Budget, BudgetExceeded};
+use crate::cache::ResponseCache;
+use crate::circuit_breaker::{CircuitBreaker, CircuitOpen};
+use crate::error::ErrorKind;
+use crate::proxy::ProxyConfig;
+use crate::redact::ApiKey;
+use crate::rt::Stopwatch;
+use crate::tls::TlsConfig;
+use crate::transport::TransportConfig;
+
+const DEFAULT_TIMEOUT_SECS: u64 = 120;
+const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// Length of every clip the model returns
+pub const CLIP_SECS: u32 = 30;
+
+/// Supported music generation models
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MusicModel {
+    /// Lyria 2 - 30-second instrumental clips at 48 kHz
+    #[default]
+    Lyria2,
+}
+
+impl MusicModel {
+    pub fn api_model_name(&self) -> &'static str {
+        match self {
+            MusicModel::Lyria2 => "lyria-002",
+        }
+    }
+}
+
+impl fmt::Display for MusicModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MusicModel::Lyria2 => write!(f, "lyria-2"),
+        }
+    }
+}
+
+impl FromStr for MusicModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "2" | "lyria-2" | "lyria-002" => Ok(MusicModel::Lyria2),
+            _ => Err(format!("Unknown music model: {}. Use 'lyria-2'", s)),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum MusicError {
+    #[error("API key not found in environment (set GEMINI_API_KEY or GOOGLE_AI_KEY)")]
+    MissingApiKey,
+
+    #[error("Invalid music config: {0}")]
+    InvalidConfig(String),
+
+    #[error("Gemini API error ({status}, request {request_id}): {message}")]
+    ApiError {
+        status: u16,
+        message: String,
+        request_id: String,
+    },
+
+    #[error("Rate limited by API (request {request_id}). Retry after some time.")]
+    RateLimited { request_id: String },
+
+    #[error("Invalid response from Gemini API: {0}")]
+    InvalidResponse(String),
+
+    #[error("No audio data in response")]
+    NoAudioData,
+
+    #[error("Network error: {0}")]
+    NetworkError(#[source] reqwest::Error),
+
+    #[error("JSON parsing error: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("Max retries ({0}) exceeded")]
+    MaxRetriesExceeded(u32),
+
+    #[error(transparent)]
+    CircuitOpen(#[from] CircuitOpen),
+
+    #[error(transparent)]
+    BudgetExceeded(#[from] BudgetExceeded),
+
+    #[error("Base64 decode error: {0}")]
+    Base64Error(#[from] base64::DecodeError),
+}
+
+pub type Result<T> = std::result::Result<T, MusicError>;
+
+impl From<reqwest::Error> for MusicError {
+    fn from(err: reqwest::Error) -> Self {
+        MusicError::NetworkError(crate::redact::reqwest_error(err))
+    }
+}
+
+impl MusicError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            MusicError::MissingApiKey | MusicError::InvalidConfig(_) => ErrorKind::Config,
+            MusicError::ApiError { .. } => ErrorKind::Api,
+            MusicError::RateLimited { .. } => ErrorKind::RateLimited,
+            MusicError::InvalidResponse(_)
+            | MusicError::NoAudioData
+            | MusicError::JsonError(_)
+            | MusicError::Base64Error(_) => ErrorKind::InvalidResponse,
+            MusicError::NetworkError(_) => ErrorKind::Network,
+            MusicError::MaxRetriesExceeded(_) => ErrorKind::RetriesExhausted,
+            MusicError::CircuitOpen(_) => ErrorKind::CircuitOpen,
+            MusicError::BudgetExceeded(_) => ErrorKind::BudgetExceeded,
+        }
+    }
+
+    /// HTTP status returned by the API, if any
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            MusicError::ApiError { status, .. } => Some(*status),
+            MusicError::RateLimited { .. } => Some(429),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MusicClientConfig {
+    pub timeout_secs: u64,
+    pub max_retries: u32,
+    pub model: MusicModel,
+    /// API host, e.g. a local mock server in tests
+    pub base_url: String,
+    /// Used by `with_config`; `with_http_client` keeps the given client's proxy
+    pub proxy: ProxyConfig,
+    /// Extra root certificates and builder hook, applied like `proxy`
+    pub tls: TlsConfig,
+    /// Connection pool, keep-alive and HTTP/2 settings, applied like `proxy`
+    pub transport: TransportConfig,
+    pub auth_mode: AuthMode,
+    /// Serve identical generation requests from disk; `None` disables
+    pub cache: Option<ResponseCache>,
+    /// Shared with other clients of a batch to stop retrying when the API is down
+    pub circuit_breaker: Option<CircuitBreaker>,
+    /// Spend limit shared with other clients; requests fail once it is reached
+    pub budget: Option<Budget>,
+}
+
+impl Default for MusicClientConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: DEFAULT_TIMEOUT_SECS,
+            max_retries: DEFAULT_MAX_RETRIES,
+            model: MusicModel::default(),
+            base_url: GEMINI_BASE_URL.to_string(),
+            proxy: ProxyConfig::default(),
+            tls: TlsConfig::default(),
+            transport: TransportConfig::default(),
+            auth_mode: AuthMode::default(),
+            cache: None,
+            circuit_breaker: None,
+            budget: None,
+        }
+    }
+}
+
+/// Style and length of a generated track
+#[derive(Debug, Clone, Default)]
+pub struct MusicGenConfig {
+    pub genre: Option<String>,
+    pub mood: Option<String>,
+    /// Tempo in beats per minute
+    pub bpm: Option<u32>,
+    pub instruments: Vec<String>,
+    /// What the track should not contain, e.g. "vocals, drums"
+    pub negative_prompt: Option<String>,
+    /// Keep only the first N seconds (1 to [`CLIP_SECS`])
+    pub duration_secs: Option<u32>,
+    /// Same seed and prompt give the same track
+    pub seed: Option<u32>,
+}
+
+impl MusicGenConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_genre(mut self, genre: impl Into<String>) -> Self {
+        self.genre = Some(genre.into());
+        self
+    }
+
+    pub fn with_mood(mut self, mood: impl Into<String>) -> Self {
+        self.mood = Some(mood.into());
+        self
+    }
+
+    pub fn with_bpm(mut self, bpm: u32) -> Self {
+        self.bpm = Some(bpm);
+        self
+    }
+
+    pub fn with_instrument(mut self, instrument: impl Into<String>) -> Self {
+        self.instruments.push(instrument.into());
+        self
+    }
+
+    pub fn with_negative_prompt(mut self, negative_prompt: impl Into<String>) -> Self {
+        self.negative_prompt = Some(negative_prompt.into());
+        self
+    }
+
+    pub fn with_duration_secs(mut self, secs: u32) -> Self {
+        self.duration_secs = Some(secs);
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u32) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+}
+
+/// Generated audio clip
+#[derive(Debug, Clone)]
+pub struct GeneratedMusic {
+    pub data: Vec<u8>,
+    pub mime_type: String,
+}
+
+/// Where the samples of a WAV file are
+struct WavLayout {
+    byte_rate: usize,
+    block_align: usize,
+    data_start: usize,
+    data_len: usize,
+}
+
+impl WavLayout {
+    fn parse(data: &[u8]) -> Option<Self> {
+        if data.get(0..4)? != b"RIFF" || data.get(8..12)? != b"WAVE" {
+            return None;
+        }
+        let le32 = |i: usize| -> Option<usize> {
+            Some(u32::from_le_bytes(data.get(i..i + 4)?.try_into().ok()?) as usize)
+        };
+        let le16 = |i: usize| -> Option<usize> {
+            Some(u16::from_le_bytes(data.get(i..i + 2)?.try_into().ok()?) as usize)
+        };
+
+        let mut format = None;
+        let mut pos = 12;
+        while pos + 8 <= data.len() {
+            let id = &data[pos..pos + 4];
+            let len = le32(pos + 4)?;
+            let body = pos + 8;
+            if id == b"fmt " {
+                format = Some((le32(body + 8)?, le16(body + 12)?));
+            } else if id == b"data" {
+                let (byte_rate, block_align) = format?;
+                return Some(Self {
+                    byte_rate,
+                    block_align: block_align.max(1),
+                    data_start: body,
+                    data_len: len.min(data.len() - body),
+                });
+            }
+            // Chunks are padded to an even length
+            pos = body + len + (len & 1);
+        }
+        None
+    }
+}
+
+impl GeneratedMusic {
+    /// Get the appropriate file extension for this clip
+    pub fn extension(&self) -> &'static str {
+        match self.mime_type.as_str() {
+            "audio/mpeg" | "audio/mp3" => "mp3",
+            "audio/ogg" => "ogg",
+            "audio/flac" => "flac",
+            _ => "wav",
+        }
+    }
+
+    /// Length of a WAV clip, `None` for other formats
+    pub fn duration_secs(&self) -> Option<f64> {
+        let layout = WavLayout::parse(&self.data)?;
+        Some(layout.data_len as f64 / layout.byte_rate.max(1) as f64)
+    }
+
+    /// Keep only the first `secs` seconds of a WAV clip
+    pub fn trimmed(mut self, secs: u32) -> Result<Self> {
+        let layout = WavLayout::parse(&self.data).ok_or_else(|| {
+            MusicError::InvalidResponse(format!("cannot trim {} audio", self.mime_type))
+        })?;
+        let wanted = layout.byte_rate * secs as usize;
+        let keep = wanted.min(layout.data_len) / layout.block_align * layout.block_align;
+
+        self.data.truncate(layout.data_start + keep);
+        let riff_len = (self.data.len() - 8) as u32;
+        self.data[4..8].copy_from_slice(&riff_len.to_le_bytes());
+        self.data[layout.data_start - 4..layout.data_start]
+            .copy_from_slice(&(keep as u32).to_le_bytes());
+        Ok(self)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MusicClient {
+    client: Client,
+    api_key: ApiKey,
+    config: MusicClientConfig,
+}
+
+impl MusicClient {
+    pub fn new(api_key: String) -> Result<Self> {
+        Self::with_config(api_key, MusicClientConfig::default())
+    }
+
+    pub fn with_config(api_key: String, config: MusicClientConfig) -> Result<Self> {
+        let builder = crate::proxy::apply(
+            crate::rt::client_builder(config.timeout_secs),
+            &config.proxy,
+        )?;
+        let builder = crate::transport::apply(builder, &config.transport);
+        let client = crate::tls::apply(builder, &config.tls)?.build()?;
+
+        Ok(Self::with_http_client(client, api_key, config))
+    }
+
+    /// Build on an existing HTTP client, sharing its connection pool
+    pub fn with_http_client(client: Client, api_key: String, config: MusicClientConfig) -> Self {
+        Self {
+            client,
+            api_key: api_key.into(),
+            config,
+        }
+    }
+
+    fn validate(gen_config: Option<&MusicGenConfig>) -> Result<()> {
+        let Some(cfg) = gen_config else {
+            return Ok(());
+        };
+        if let Some(secs) = cfg.duration_secs
+            && !(1..=CLIP_SECS).contains(&secs)
+        {
+            return Err(MusicError::InvalidConfig(format!(
+                "duration must be between 1 and {} seconds, got {}",
+                CLIP_SECS, secs
+            )));
+        }
+        if let Some(bpm) = cfg.bpm
+            && !(40..=240).contains(&bpm)
+        {
+            return Err(MusicError::InvalidConfig(format!(
+                "tempo must be between 40 and 240 BPM, got {}",
+                bpm
+            )));
+        }
+        Ok(())
+    }
+
+    /// The prompt with the style hints appended as short sentences
+    fn styled_prompt(prompt: &str, gen_config: Option<&MusicGenConfig>) -> String {
+        let mut text = prompt.trim().trim_end_matches('.').to_string();
+        let Some(cfg) = gen_config else {
+            return text;
+        };
+        let mut add = |label: &str, value: &str| {
+            text.push_str(&format!(". {}: {}", label, value.trim()));
+        };
+        if let Some(genre) = &cfg.genre {
+            add("Genre", genre);
+        }
+        if let Some(mood) = &cfg.mood {
+            add("Mood", mood);
+        }
+        if let Some(bpm) = cfg.bpm {
+            add("Tempo", &format!("{} BPM", bpm));
+        }
+        if !cfg.instruments.is_empty() {
+            add("Instruments", &cfg.instruments.join(", "));
+        }
+        text
+    }
+
+    fn build_payload(prompt: &str, gen_config: Option<&MusicGenConfig>) -> Value {
+        let mut instance = json!({ "prompt": Self::styled_prompt(prompt, gen_config) });
+        if let Some(cfg) = gen_config {
+            if let Some(negative) = &cfg.negative_prompt {
+                instance["negative_prompt"] = json!(negative);
+            }
+            if let Some(seed) = cfg.seed {
+                instance["seed"] = json!(seed);
+            }
+        }
+        json!({ "instances": [instance], "parameters": {} })
+    }
+
+    async fn send_request(&self, payload: &Value, request_id: &str) -> Result<GeneratedMusic> {
+        let url = format!(
+            "{}/v1beta/models/{}:predict",
+            self.config.base_url,
+            self.config.model.api_model_name()
+        );
+
+        debug!(
+            "Sending music generation request to Gemini API (model: {})",
+            self.config.model
+        );
+
+        let response = self
+            .config
+            .auth_mode
+            .request(&self.client, Method::POST, &url, &self.api_key)
+            .timeout(Duration::from_secs(self.config.timeout_secs))
+            .header("Content-Type", "application/json")
+            .json(payload)
+            .send()
+            .await?;
+
+        let status = response.status();
+        debug!("Received response with status: {}", status);
+
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            crate::metrics::record_rate_limited("music");
+            return Err(MusicError::RateLimited {
+                request_id: request_id.to_string(),
+            });
+        }
+
+        if !status.is_success() {
+            let error_text = self
+                .api_key
+                .scrub(response.text().await.unwrap_or_default());
+            return Err(MusicError::ApiError {
+                status: status.as_u16(),
+                message: error_text,
+                request_id: request_id.to_string(),
+            });
+        }
+
+        let data: Value = response.json().await?;
+        let prediction = &data["predictions"][0];
+        let base64_data = prediction["bytesBase64Encoded"]
+            .as_str()
+            .ok_or(MusicError::NoAudioData)?;
+        let mime_type = prediction["mimeType"]
+            .as_str()
+            .unwrap_or("audio/wav")
+            .to_string();
+        let audio = base64::engine::general_purpose::STANDARD.decode(base64_data)?;
+
+        crate::usage::record_music(
+            self.config.model.api_model_name(),
+            f64::from(CLIP_SECS),
+            self.config.budget.as_ref(),
+        );
+
+        Ok(GeneratedMusic {
+            data: audio,
+            mime_type,
+        })
+    }
+
+    fn is_retryable_error(err: &MusicError) -> bool {
+        crate::error::is_retryable(err.kind(), err.status())
+    }
+
+    /// Generate a clip from a text prompt with retry logic
+    pub async fn generate_music(&self, prompt: &str) -> Result<GeneratedMusic> {
+        self.generate_music_with_config(prompt, None).await
+    }
+
+    /// Generate a clip with style hints, cut to the configured duration
+    pub async fn generate_music_with_config(
+        &self,
+        prompt: &str,
+        gen_config: Option<&MusicGenConfig>,
+    ) -> Result<GeneratedMusic> {
+        Self::validate(gen_config)?;
+        let payload = Self::build_payload(prompt, gen_config);
+        let music = self.generate_cached(&payload).await?;
+
+        match gen_config.and_then(|cfg| cfg.duration_secs) {
+            Some(secs) if secs < CLIP_SECS => music.trimmed(secs),
+            _ => Ok(music),
+        }
+    }
+
+    async fn generate_cached(&self, payload: &Value) -> Result<GeneratedMusic> {
+        let Some(cache) = &self.config.cache else {
+            return self.generate_with_retry(payload).await;
+        };
+
+        let key = ResponseCache::key(&[
+            self.config.model.api_model_name().as_bytes(),
+            payload.to_string().as_bytes(),
+        ]);
+        if let Some(music) = cache.get_music(&key).await {
+            info!("Using cached music");
+            return Ok(music);
+        }
+
+        let music = self.generate_with_retry(payload).await?;
+        cache.put_music(&key, &music).await;
+        Ok(music)
+    }
+
+    async fn generate_with_retry(&self, payload: &Value) -> Result<GeneratedMusic> {
+        let request_id = crate::request_id::next();
+        let span = info_span!(
+            "music_request",
+            request_id = %request_id,
+            model = %self.config.model,
+            payload_bytes = payload["instances"][0]["prompt"].as_str().map_or(0, str::len),
+            attempt = field::Empty,
+            latency_ms = field::Empty,
+        );
+
+        async {
+            let mut last_error = None;
+            let mut retry_count = 0;
+
+            while retry_count < self.config.max_retries {
+                if let Some(breaker) = &self.config.circuit_breaker {
+                    breaker.check()?;
+                }
+                if let Some(budget) = &self.config.budget {
+                    budget.check()?;
+                }
+                span.record("attempt", retry_count + 1);
+                let stopwatch = Stopwatch::start();
+                let result = self.send_request(payload, &request_id).await;
+                span.record("latency_ms", stopwatch.elapsed_ms());
+                if let Some(breaker) = &self.config.circuit_breaker {
+                    breaker.record(&result, Self::is_retryable_error);
+                }
+                crate::metrics::record_request("music", result.is_ok(), stopwatch.elapsed_ms());
+                debug!(
+                    "Attempt {} finished in {}ms",
+                    retry_count + 1,
+                    stopwatch.elapsed_ms()
+                );
+
+                match result {
+                    Ok(music) => {
+                        info!("Music generation successful");
+                        return Ok(music);
+                    }
+                    Err(e) => {
+                        if Self::is_retryable_error(&e)
+                            && retry_count + 1 < self.config.max_retries
+                            && self
+                                .config
+                                .circuit_breaker
+                                .as_ref()
+                                .is_none_or(CircuitBreaker::try_retry)
+                        {
+                            let delay = if matches!(e, MusicError::RateLimited { .. }) {
+                                Duration::from_secs(30 * (retry_count as u64 + 1))
+                            } else {
+                                Duration::from_secs(2u64.pow(retry_count))
+                            };
+                            warn!(
+                                "Request failed (attempt {}/{}): {}. Retrying in {:?}...",
+                                retry_count + 1,
+                                self.config.max_retries,
+                                e,
+                                delay
+                            );
+                            crate::metrics::record_retry("music");
+                            crate::rt::sleep(delay).await;
+                            retry_count += 1;
+                            last_error = Some(e);
+                        } else {
+                            return Err(e);
+                        }
+                    }
+                }
+            }
+
+            Err(last_error.unwrap_or(MusicError::MaxRetriesExceeded(self.config.max_retries)))
+        }
+        .instrument(span.clone())
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Mono 16-bit WAV at `rate` Hz holding `secs` seconds of silence
+    fn wav(rate: u32, secs: u32) -> Vec<u8> {
+        let data_len = rate * 2 * secs;
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&rate.to_le_bytes());
+        wav.extend_from_slice(&(rate * 2).to_le_bytes());
+        wav.extend_from_slice(&2u16.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+        wav.resize(44 + data_len as usize, 0);
+        wav
+    }
+
+    #[test]
+    fn test_music_model_from_str() {
+        assert_eq!(MusicModel::from_str("lyria-2").unwrap(), MusicModel::Lyria2);
+        assert_eq!(
+            MusicModel::from_str("LYRIA-002").unwrap(),
+            MusicModel::Lyria2
+        );
+        assert!(MusicModel::from_str("lyria-1").is_err());
+        assert_eq!(MusicModel::Lyria2.to_string(), "lyria-2");
+    }
+
+    #[test]
+    fn test_build_payload() {
+        let cfg = MusicGenConfig::new()
+            .with_genre("lo-fi hip hop")
+            .with_mood("calm")
+            .with_bpm(80)
+            .with_instrument("piano")
+            .with_instrument("vinyl crackle")
+            .with_negative_prompt("vocals")
+            .with_seed(7);
+        let payload = MusicClient::build_payload("Rainy cafe background.", Some(&cfg));
+        let instance = &payload["instances"][0];
+        assert_eq!(
+            instance["prompt"],
+            "Rainy cafe background. Genre: lo-fi hip hop. Mood: calm. Tempo: 80 BPM. Instruments: piano, vinyl crackle"
+        );
+        assert_eq!(instance["negative_prompt"], "vocals");
+        assert_eq!(instance["seed"], 7);
+
+        let plain = MusicClient::build_payload("Epic trailer", None);
+        assert_eq!(plain["instances"][0]["prompt"], "Epic trailer");
+        assert!(plain["instances"][0].get("seed").is_none());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(MusicClient::validate(None).is_ok());
+        let ok = MusicGenConfig::new().with_duration_secs(30).with_bpm(120);
+        assert!(MusicClient::validate(Some(&ok)).is_ok());
+        for bad in [
+            MusicGenConfig::new().with_duration_secs(0),
+            MusicGenConfig::new().with_duration_secs(31),
+            MusicGenConfig::new().with_bpm(300),
+        ] {
+            assert!(matches!(
+                MusicClient::validate(Some(&bad)),
+                Err(MusicError::InvalidConfig(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_trim_wav() {
+        let music = GeneratedMusic {
+            data: wav(8_000, 3),
+            mime_type: "audio/wav".to_string(),
+        };
+        assert_eq!(music.duration_secs(), Some(3.0));
+
+        let trimmed = music.clone().trimmed(1).unwrap();
+        assert_eq!(trimmed.data.len(), 44 + 16_000);
+        assert_eq!(trimmed.duration_secs(), Some(1.0));
+        assert_eq!(
+            u32::from_le_bytes(trimmed.data[4..8].try_into().unwrap()),
+            36 + 16_000
+        );
+
+        // Longer than the clip keeps all of it
+        assert_eq!(music.clone().trimmed(10).unwrap().data, music.data);
+
+        let mp3 = GeneratedMusic {
+            data: b"ID3".to_vec(),
+            mime_type: "audio/mpeg".to_string(),
+        };
+        assert_eq!(mp3.extension(), "mp3");
+        assert_eq!(mp3.duration_secs(), None);
+        assert!(mp3.trimmed(1).is_err());
+    }
+}
@@ -0,0 +1,256 @@
+use anyhow::{Context, Result};
+use clap::{Parser, ValueEnum};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tracing::{Instrument, debug, info, info_span, warn};
+
+use transcript_tool::cli::{self, CommonArgs, init_logging};
+use transcript_tool::storage::{OutputStorage, Storage};
+use transcript_tool::vision_api::{MAX_INLINE_IMAGE_SIZE, document_mime_type};
+use transcript_tool::{Clients, MediaPart, TextClientConfig, TextModel, VisionClient};
+
+#[derive(Parser, Debug)]
+#[command(name = "ocr")]
+#[command(version)]
+#[command(about = "Extract the text of images and scanned PDFs in reading order")]
+#[command(after_help = "EXAMPLES:
+    ocr receipt.png
+    ocr scan.pdf -f md -o scan.md
+    ocr form.jpg -f json --regions
+    ocr page.webp -o - | wc -w")]
+pub struct Args {
+    /// Image (PNG, JPEG, WebP, GIF, HEIC, HEIF) or PDF file
+    #[arg(value_name = "INPUT")]
+    input: PathBuf,
+
+    /// Output file path (`-` for stdout), or an s3:// / gs:// URI
+    /// [default: <input>.ocr.<format>]
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value = "txt")]
+    format: OcrFormat,
+
+    /// Include the bounding box of each text block (JSON output only)
+    #[arg(long)]
+    regions: bool,
+
+    /// Context for the model, e.g. the document type or its language
+    #[arg(long)]
+    hint: Option<String>,
+
+    /// Upload through the File API even if the file is under 20MB
+    #[arg(long)]
+    force_file_api: bool,
+
+    /// Model: flash (default), flash-lite, pro
+    #[arg(
+        short = 'm',
+        long,
+        env = "FLASHECHO_TEXT_MODEL",
+        default_value = "flash"
+    )]
+    model: String,
+
+    /// Timeout of the API request in seconds
+    #[arg(short, long, default_value = "300")]
+    timeout: u64,
+
+    #[command(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OcrFormat {
+    /// Plain text in reading order
+    Txt,
+    /// Blocks with kind, page and optional bounding box
+    Json,
+    /// Markdown with headings, lists and tables
+    Md,
+}
+
+impl OcrFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            OcrFormat::Txt => "txt",
+            OcrFormat::Json => "json",
+            OcrFormat::Md => "md",
+        }
+    }
+}
+
+/// `scan.pdf` -> `scan.ocr.md`
+fn default_output(input: &Path, format: OcrFormat) -> PathBuf {
+    input.with_extension(format!("ocr.{}", format.extension()))
+}
+
+/// Run with parsed arguments; shared by this binary and its `flashecho` subcommand
+pub async fn run(args: Args) -> Result<()> {
+    init_logging(args.common.verbose, args.common.log_format);
+    args.common.install_usage_ledger("ocr");
+
+    let span = info_span!("file", file = %args.input.display());
+    extract(args).instrument(span).await
+}
+
+async fn extract(args: Args) -> Result<()> {
+    if args.regions && args.format != OcrFormat::Json {
+        anyhow::bail!("--regions needs -f json; text and Markdown output have no boxes");
+    }
+    let mime_type = document_mime_type(&args.input).with_context(|| {
+        format!(
+            "Unsupported input format: {:?} (use PDF, PNG, JPEG, WebP, GIF, HEIC or HEIF)",
+            args.input
+        )
+    })?;
+    let data = fs::read(&args.input)
+        .await
+        .with_context(|| format!("Failed to read {:?}", args.input))?;
+    let file_size = data.len() as u64;
+
+    let model: TextModel = args.model.parse().map_err(|e: String| anyhow::anyhow!(e))?;
+    let profile = args.common.load_profile()?;
+    let clients =
+        Clients::new(cli::gemini_api_key(&profile)?).context("Failed to create HTTP client")?;
+    let budget = args
+        .common
+        .budget()
+        .context("Failed to read usage ledger for --budget-since")?;
+    let mut vision = VisionClient::new(clients.text(TextClientConfig {
+        timeout_secs: args.timeout,
+        max_retries: args.common.max_retries,
+        model,
+        cache: args.common.response_cache(),
+        budget,
+        ..Default::default()
+    }));
+    if let Some(hint) = &args.hint {
+        vision = vision.with_hint(hint);
+    }
+
+    // Large scans go through the File API and are referenced by URI
+    let mut uploaded = None;
+    let document = if args.force_file_api || file_size > MAX_INLINE_IMAGE_SIZE {
+        info!(
+            "Using File API for {} bytes ({:.1}MB)",
+            file_size,
+            file_size as f64 / (1024.0 * 1024.0)
+        );
+        let file_api = clients.file_api();
+        let display_name = args
+            .input
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("document");
+        let pb = cli::spinner(args.common.quiet, "Uploading to Gemini File API...".into());
+        let result = file_api
+            .upload_file(data, mime_type, display_name)
+            .instrument(info_span!("stage", stage = "upload"))
+            .await;
+        if let Some(pb) = pb {
+            pb.finish_and_clear();
+        }
+        let file_info = result.map_err(|e| anyhow::anyhow!("File upload failed: {}", e))?;
+        debug!("File uploaded: {} -> {}", file_info.name, file_info.uri);
+        let part = MediaPart::FileUri {
+            mime_type: mime_type.to_string(),
+            uri: file_info.uri,
+        };
+        uploaded = Some((file_api, file_info.name));
+        part
+    } else {
+        MediaPart::Inline {
+            mime_type: mime_type.to_string(),
+            data,
+        }
+    };
+
+    let pb = cli::spinner(
+        args.common.quiet,
+        format!("Extracting text from {}...", args.input.display()),
+    );
+    let result = vision
+        .extract_text(&document, args.regions)
+        .instrument(info_span!("stage", stage = "ocr"))
+        .await;
+    if let Some(pb) = pb {
+        pb.finish_and_clear();
+    }
+
+    if let Some((file_api, file_name)) = uploaded {
+        match file_api.delete_file(&file_name).await {
+            Ok(()) => debug!("Remote file deleted: {}", file_name),
+            Err(e) => warn!("Failed to delete remote file {}: {}", file_name, e),
+        }
+    }
+    let result = result.map_err(|e| anyhow::anyhow!("Text extraction failed: {}", e))?;
+    if result.blocks.is_empty() {
+        warn!("No text found in {:?}", args.input);
+    }
+
+    let output = match args.format {
+        OcrFormat::Txt => result.to_text(),
+        OcrFormat::Json => {
+            serde_json::to_string_pretty(&result).context("Failed to serialize OCR result")?
+        }
+        OcrFormat::Md => result.to_markdown(),
+    };
+
+    if args.output.as_deref() == Some("-") {
+        let mut stdout = tokio::io::stdout();
+        stdout
+            .write_all(output.as_bytes())
+            .await
+            .context("Failed to write to stdout")?;
+        stdout.flush().await.context("Failed to flush stdout")?;
+        return Ok(());
+    }
+
+    let default_path = default_output(&args.input, args.format);
+    let default_name = default_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let target = args
+        .output
+        .clone()
+        .unwrap_or_else(|| default_path.to_string_lossy().to_string());
+    let (storage, key) = OutputStorage::open_file(&target, &default_name)
+        .with_context(|| format!("Invalid output target: {}", target))?;
+    storage
+        .put(&key, output.as_bytes())
+        .instrument(info_span!("stage", stage = "write"))
+        .await
+        .context("Failed to write OCR output")?;
+    let location = storage.location(&key);
+    info!("OCR output written to {}", location);
+    if !args.common.quiet {
+        println!("Saved to: {}", location);
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    run(Args::parse()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_output() {
+        assert_eq!(
+            default_output(Path::new("scans/letter.pdf"), OcrFormat::Md),
+            PathBuf::from("scans/letter.ocr.md")
+        );
+        assert_eq!(
+            default_output(Path::new("receipt.png"), OcrFormat::Txt),
+            PathBuf::from("receipt.ocr.txt")
+        );
+    }
+}
@@ -0,0 +1,562 @@
+use reqwest::Client;
+use serde::Deserialize;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{Instrument, debug, field, info, info_span, warn};
+
+use crate::budget::{Budget, BudgetExceeded};
+use crate::cache::ResponseCache;
+use crate::circuit_breaker::{CircuitBreaker, CircuitOpen};
+use crate::error::ErrorKind;
+use crate::gemini_api::{TranscriptResponse, TranscriptSegment};
+use crate::proxy::ProxyConfig;
+use crate::redact::ApiKey;
+use crate::rt::Stopwatch;
+use crate::timestamp::Timestamp;
+use crate::tls::TlsConfig;
+use crate::transcript_schema::TRANSCRIPT_SCHEMA_VERSION;
+use crate::transport::TransportConfig;
+
+pub const OPENAI_BASE_URL: &str = "https://api.openai.com";
+pub const MAX_OPENAI_FILE_SIZE: u64 = 25 * 1024 * 1024; // 25MB upload limit
+const DEFAULT_TIMEOUT_SECS: u64 = 600;
+const DEFAULT_MAX_RETRIES: u32 = 3;
+pub const DEFAULT_OPENAI_MODEL: &str = "whisper-1";
+
+#[derive(Debug, Error)]
+pub enum OpenAiError {
+    #[error("File too large: {size} bytes (max: {max} bytes). Consider using shorter audio.")]
+    FileTooLarge { size: u64, max: u64 },
+
+    #[error("OpenAI API error ({status}, request {request_id}): {message}")]
+    ApiError {
+        status: u16,
+        message: String,
+        request_id: String,
+    },
+
+    #[error("Rate limited by API (request {request_id}). Retry after some time.")]
+    RateLimited { request_id: String },
+
+    #[error("Invalid response from OpenAI API: {0}")]
+    InvalidResponse(String),
+
+    #[error("Network error: {0}")]
+    NetworkError(#[source] reqwest::Error),
+
+    #[error("JSON parsing error: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("Max retries ({0}) exceeded")]
+    MaxRetriesExceeded(u32),
+
+    #[error(transparent)]
+    CircuitOpen(#[from] CircuitOpen),
+
+    #[error(transparent)]
+    BudgetExceeded(#[from] BudgetExceeded),
+}
+
+pub type Result<T> = std::result::Result<T, OpenAiError>;
+
+impl From<reqwest::Error> for OpenAiError {
+    fn from(err: reqwest::Error) -> Self {
+        OpenAiError::NetworkError(crate::redact::reqwest_error(err))
+    }
+}
+
+impl OpenAiError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            OpenAiError::FileTooLarge { .. } => ErrorKind::InvalidInput,
+            OpenAiError::ApiError { .. } => ErrorKind::Api,
+            OpenAiError::RateLimited { .. } => ErrorKind::RateLimited,
+            OpenAiError::InvalidResponse(_) | OpenAiError::JsonError(_) => {
+                ErrorKind::InvalidResponse
+            }
+            OpenAiError::NetworkError(_) => ErrorKind::Network,
+            OpenAiError::MaxRetriesExceeded(_) => ErrorKind::RetriesExhausted,
+            OpenAiError::CircuitOpen(_) => ErrorKind::CircuitOpen,
+            OpenAiError::BudgetExceeded(_) => ErrorKind::BudgetExceeded,
+        }
+    }
+
+    /// HTTP status returned by the API, if any
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            OpenAiError::ApiError { status, .. } => Some(*status),
+            OpenAiError::RateLimited { .. } => Some(429),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OpenAiClientConfig {
+    pub timeout_secs: u64,
+    pub max_retries: u32,
+    pub model: String,
+    /// API host, e.g. a local mock server in tests
+    pub base_url: String,
+    /// Proxy for this client's HTTP connections
+    pub proxy: ProxyConfig,
+    /// Extra root certificates and builder hook for this client's connections
+    pub tls: TlsConfig,
+    /// Connection pool, keep-alive and HTTP/2 settings, applied like `proxy`
+    pub transport: TransportConfig,
+    /// Serve identical transcription requests from disk; `None` disables
+    pub cache: Option<ResponseCache>,
+    /// Shared with other clients of a batch to stop retrying when the API is down
+    pub circuit_breaker: Option<CircuitBreaker>,
+    /// Spend limit shared with other clients; requests fail once it is reached
+    pub budget: Option<Budget>,
+}
+
+impl Default for OpenAiClientConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: DEFAULT_TIMEOUT_SECS,
+            max_retries: DEFAULT_MAX_RETRIES,
+            model: DEFAULT_OPENAI_MODEL.to_string(),
+            base_url: OPENAI_BASE_URL.to_string(),
+            proxy: ProxyConfig::default(),
+            tls: TlsConfig::default(),
+            transport: TransportConfig::default(),
+            cache: None,
+            circuit_breaker: None,
+            budget: None,
+        }
+    }
+}
+
+/// `verbose_json` response from the audio transcription endpoint
+#[derive(Debug, Deserialize)]
+struct VerboseTranscription {
+    #[serde(default)]
+    language: String,
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    segments: Vec<VerboseSegment>,
+    /// Audio length in seconds, for the usage ledger
+    #[serde(default)]
+    duration: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct VerboseSegment {
+    start: f64,
+    end: f64,
+    text: String,
+}
+
+/// Client for OpenAI's audio transcription API (Whisper)
+#[derive(Debug, Clone)]
+pub struct OpenAiClient {
+    client: Client,
+    api_key: ApiKey,
+    config: OpenAiClientConfig,
+}
+
+impl OpenAiClient {
+    pub fn new(api_key: String) -> Result<Self> {
+        Self::with_config(api_key, OpenAiClientConfig::default())
+    }
+
+    pub fn with_config(api_key: String, config: OpenAiClientConfig) -> Result<Self> {
+        let builder = crate::proxy::apply(
+            crate::rt::client_builder(config.timeout_secs),
+            &config.proxy,
+        )?;
+        let builder = crate::transport::apply(builder, &config.transport);
+        let client = crate::tls::apply(builder, &config.tls)?.build()?;
+
+        Ok(Self {
+            client,
+            api_key: api_key.into(),
+            config,
+        })
+    }
+
+    /// Build a multipart/form-data body with the given text fields and one file part
+    fn build_multipart_body(
+        boundary: &str,
+        fields: &[(&str, &str)],
+        file_name: &str,
+        mime_type: &str,
+        data: &[u8],
+    ) -> Vec<u8> {
+        let mut body = Vec::with_capacity(data.len() + 1024);
+
+        for (name, value) in fields {
+            body.extend_from_slice(
+                format!(
+                    "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                    boundary, name, value
+                )
+                .as_bytes(),
+            );
+        }
+
+        body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+                boundary, file_name, mime_type
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(data);
+        body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+
+        body
+    }
+
+    /// File name for the upload; the API detects the audio format from its extension
+    fn upload_file_name(mime_type: &str) -> String {
+        let ext = match mime_type {
+            "audio/mpeg" => "mp3",
+            "audio/wav" => "wav",
+            "audio/ogg" => "ogg",
+            "audio/flac" => "flac",
+            "audio/mp4" => "m4a",
+            "audio/webm" => "webm",
+            _ => "mp3",
+        };
+        format!("audio.{}", ext)
+    }
+
+    /// Format seconds as MM:SS (HH:MM:SS from an hour on), matching the
+    /// timestamps produced by Gemini
+    fn timestamp(seconds: f64) -> Timestamp {
+        Timestamp::from_secs(seconds.max(0.0) as u64)
+    }
+
+    fn language_code(language: &str) -> String {
+        let code = match language.to_lowercase().as_str() {
+            "english" => "en",
+            "chinese" => "zh",
+            "spanish" => "es",
+            "french" => "fr",
+            "german" => "de",
+            "japanese" => "ja",
+            "korean" => "ko",
+            "russian" => "ru",
+            "portuguese" => "pt",
+            "italian" => "it",
+            "arabic" => "ar",
+            "hindi" => "hi",
+            _ => "",
+        };
+        code.to_string()
+    }
+
+    /// Map a Whisper transcription onto the shared transcript structure.
+    /// Whisper has no speaker, emotion or summary output, so those get placeholders.
+    fn to_transcript(response: VerboseTranscription) -> TranscriptResponse {
+        let mut language = response.language.clone();
+        if let Some(first) = language.get_mut(0..1) {
+            first.make_ascii_uppercase();
+        }
+        let language_code = Self::language_code(&response.language);
+
+        let segment = |timestamp: Timestamp, end_timestamp: Option<Timestamp>, content: &str| {
+            TranscriptSegment {
+                speaker: "Speaker 1".to_string(),
+                timestamp,
+                end_timestamp,
+                content: content.trim().to_string(),
+                language: language.clone(),
+                language_code: language_code.clone(),
+                translation: None,
+                translations: Default::default(),
+                emotion: "neutral".to_string(),
+            }
+        };
+
+        let segments = if response.segments.is_empty() && !response.text.trim().is_empty() {
+            vec![segment(Self::timestamp(0.0), None, &response.text)]
+        } else {
+            response
+                .segments
+                .iter()
+                .map(|s| {
+                    // Round the end up so the cue doesn't cut off the last word
+                    let end = Self::timestamp(s.end.ceil());
+                    segment(Self::timestamp(s.start), Some(end), &s.text)
+                })
+                .collect()
+        };
+
+        TranscriptResponse {
+            schema_version: TRANSCRIPT_SCHEMA_VERSION,
+            summary: String::new(),
+            segments,
+            topics: Vec::new(),
+            keywords: Vec::new(),
+        }
+    }
+
+    async fn send_request(
+        &self,
+        audio_data: &[u8],
+        mime_type: &str,
+        request_id: &str,
+    ) -> Result<TranscriptResponse> {
+        let boundary = format!(
+            "transcript-tool-{}",
+            &blake3::hash(audio_data).to_hex()[..16]
+        );
+        let body = Self::build_multipart_body(
+            &boundary,
+            &[
+                ("model", &self.config.model),
+                ("response_format", "verbose_json"),
+                ("timestamp_granularities[]", "segment"),
+            ],
+            &Self::upload_file_name(mime_type),
+            mime_type,
+            audio_data,
+        );
+
+        debug!(
+            "Sending request to OpenAI API (model: {})",
+            self.config.model
+        );
+
+        let response = self
+            .client
+            .post(format!("{}/v1/audio/transcriptions", self.config.base_url))
+            .bearer_auth(self.api_key.expose())
+            .header(
+                "Content-Type",
+                format!("multipart/form-data; boundary={}", boundary),
+            )
+            .body(body)
+            .send()
+            .await?;
+
+        let status = response.status();
+        debug!("Received response with status: {}", status);
+
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            crate::metrics::record_rate_limited("openai");
+            return Err(OpenAiError::RateLimited {
+                request_id: request_id.to_string(),
+            });
+        }
+
+        if !status.is_success() {
+            let error_text = self
+                .api_key
+                .scrub(response.text().await.unwrap_or_default());
+            return Err(OpenAiError::ApiError {
+                status: status.as_u16(),
+                message: error_text,
+                request_id: request_id.to_string(),
+            });
+        }
+
+        let text = response.text().await?;
+        let parsed: VerboseTranscription = serde_json::from_str(&text)
+            .map_err(|e| OpenAiError::InvalidResponse(format!("{}: {}", e, text)))?;
+
+        crate::usage::record_openai(
+            &self.config.model,
+            parsed.duration,
+            self.config.budget.as_ref(),
+        );
+
+        Ok(Self::to_transcript(parsed))
+    }
+
+    fn is_retryable_error(err: &OpenAiError) -> bool {
+        crate::error::is_retryable(err.kind(), err.status())
+    }
+
+    /// Transcribe audio by uploading it to the transcription endpoint (max 25MB)
+    pub async fn transcribe_audio(
+        &self,
+        audio_data: &[u8],
+        mime_type: &str,
+    ) -> Result<TranscriptResponse> {
+        let size = audio_data.len() as u64;
+        if size > MAX_OPENAI_FILE_SIZE {
+            return Err(OpenAiError::FileTooLarge {
+                size,
+                max: MAX_OPENAI_FILE_SIZE,
+            });
+        }
+
+        let Some(cache) = &self.config.cache else {
+            return self.transcribe_with_retry(audio_data, mime_type).await;
+        };
+
+        let key = ResponseCache::key(&[
+            self.config.model.as_bytes(),
+            mime_type.as_bytes(),
+            audio_data,
+        ]);
+        if let Some(transcript) = cache.get_json::<TranscriptResponse>(&key).await {
+            info!("Using cached transcription");
+            return Ok(transcript);
+        }
+
+        let transcript = self.transcribe_with_retry(audio_data, mime_type).await?;
+        cache.put_json(&key, &transcript).await;
+        Ok(transcript)
+    }
+
+    async fn transcribe_with_retry(
+        &self,
+        audio_data: &[u8],
+        mime_type: &str,
+    ) -> Result<TranscriptResponse> {
+        let request_id = crate::request_id::next();
+        let span = info_span!(
+            "openai_request",
+            request_id = %request_id,
+            model = %self.config.model,
+            payload_bytes = audio_data.len(),
+            attempt = field::Empty,
+            latency_ms = field::Empty,
+        );
+
+        async {
+            let mut last_error = None;
+            let mut retry_count = 0;
+
+            while retry_count < self.config.max_retries {
+                if let Some(breaker) = &self.config.circuit_breaker {
+                    breaker.check()?;
+                }
+                if let Some(budget) = &self.config.budget {
+                    budget.check()?;
+                }
+                span.record("attempt", retry_count + 1);
+                crate::metrics::record_upload_bytes("openai", audio_data.len());
+                let stopwatch = Stopwatch::start();
+                let result = self.send_request(audio_data, mime_type, &request_id).await;
+                span.record("latency_ms", stopwatch.elapsed_ms());
+                if let Some(breaker) = &self.config.circuit_breaker {
+                    breaker.record(&result, Self::is_retryable_error);
+                }
+                crate::metrics::record_request("openai", result.is_ok(), stopwatch.elapsed_ms());
+                debug!(
+                    "Attempt {} finished in {}ms",
+                    retry_count + 1,
+                    stopwatch.elapsed_ms()
+                );
+
+                match result {
+                    Ok(response) => {
+                        info!("Transcription successful");
+                        return Ok(response);
+                    }
+                    Err(e) => {
+                        if Self::is_retryable_error(&e)
+                            && retry_count + 1 < self.config.max_retries
+                            && self
+                                .config
+                                .circuit_breaker
+                                .as_ref()
+                                .is_none_or(CircuitBreaker::try_retry)
+                        {
+                            let delay = if matches!(e, OpenAiError::RateLimited { .. }) {
+                                Duration::from_secs(30 * (retry_count as u64 + 1))
+                            } else {
+                                Duration::from_secs(2u64.pow(retry_count))
+                            };
+                            warn!(
+                                "Request failed (attempt {}/{}): {}. Retrying in {:?}...",
+                                retry_count + 1,
+                                self.config.max_retries,
+                                e,
+                                delay
+                            );
+                            crate::metrics::record_retry("openai");
+                            crate::rt::sleep(delay).await;
+                            retry_count += 1;
+                            last_error = Some(e);
+                        } else {
+                            return Err(e);
+                        }
+                    }
+                }
+            }
+
+            Err(last_error.unwrap_or(OpenAiError::MaxRetriesExceeded(self.config.max_retries)))
+        }
+        .instrument(span.clone())
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config() {
+        let config = OpenAiClientConfig::default();
+        assert_eq!(config.model, "whisper-1");
+        assert_eq!(config.max_retries, DEFAULT_MAX_RETRIES);
+    }
+
+    #[test]
+    fn test_build_multipart_body() {
+        let body = OpenAiClient::build_multipart_body(
+            "BOUNDARY",
+            &[("model", "whisper-1")],
+            "audio.mp3",
+            "audio/mpeg",
+            b"DATA",
+        );
+        let body = String::from_utf8(body).unwrap();
+        assert!(body.starts_with(
+            "--BOUNDARY\r\nContent-Disposition: form-data; name=\"model\"\r\n\r\nwhisper-1\r\n"
+        ));
+        assert!(body.contains("name=\"file\"; filename=\"audio.mp3\"\r\nContent-Type: audio/mpeg"));
+        assert!(body.ends_with("\r\n\r\nDATA\r\n--BOUNDARY--\r\n"));
+    }
+
+    #[test]
+    fn test_upload_file_name() {
+        assert_eq!(OpenAiClient::upload_file_name("audio/mpeg"), "audio.mp3");
+        assert_eq!(OpenAiClient::upload_file_name("audio/mp4"), "audio.m4a");
+        assert_eq!(OpenAiClient::upload_file_name("audio/unknown"), "audio.mp3");
+    }
+
+    #[test]
+    fn test_to_transcript() {
+        let json = r#"{
+            "task": "transcribe",
+            "language": "english",
+            "duration": 75.5,
+            "text": "Hello there. General Kenobi.",
+            "segments": [
+                {"id": 0, "start": 0.0, "end": 2.1, "text": " Hello there."},
+                {"id": 1, "start": 65.4, "end": 67.0, "text": " General Kenobi."}
+            ]
+        }"#;
+        let parsed: VerboseTranscription = serde_json::from_str(json).unwrap();
+        let transcript = OpenAiClient::to_transcript(parsed);
+
+        assert_eq!(transcript.segments.len(), 2);
+        assert_eq!(transcript.segments[0].timestamp.to_string(), "00:00");
+        assert_eq!(transcript.segments[0].content, "Hello there.");
+        assert_eq!(transcript.segments[0].language, "English");
+        assert_eq!(transcript.segments[0].language_code, "en");
+        assert_eq!(transcript.segments[1].timestamp.to_string(), "01:05");
+    }
+
+    #[test]
+    fn test_to_transcript_without_segments() {
+        let json = r#"{"language": "french", "text": "Bonjour"}"#;
+        let parsed: VerboseTranscription = serde_json::from_str(json).unwrap();
+        let transcript = OpenAiClient::to_transcript(parsed);
+
+        assert_eq!(transcript.segments.len(), 1);
+        assert_eq!(transcript.segments[0].content, "Bonjour");
+        assert_eq!(transcript.segments[0].language_code, "fr");
+    }
+}
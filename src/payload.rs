@@ -0,0 +1,242 @@
+//! `generateContent` request bodies with inline file data. Files are
+//! base64-encoded in small chunks straight into the serialized body rather
+//! than into a `String` that is then copied into a `serde_json::Value` and
+//! serialized again on every attempt; the body is built once and each retry
+//! sends the same buffer.
+
+use base64::display::Base64Display;
+use base64::engine::general_purpose::STANDARD;
+use bytes::Bytes;
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+#[cfg(feature = "transcription")]
+use serde_json::json;
+use std::borrow::Cow;
+
+/// Room for the prompt, MIME types and generation config around inline data
+const BODY_OVERHEAD: usize = 8 * 1024;
+
+/// `{"contents": [{"parts": [...]}], ...config}`. Field order matches the
+/// sorted keys `serde_json::Value` would produce, so cache keys hashed from
+/// the body stay stable.
+#[derive(Debug, Serialize)]
+pub(crate) struct GenerateContent<'a> {
+    contents: [Content<'a>; 1],
+    /// Object whose entries follow `contents`, e.g. `{"generation_config": ...}`
+    #[serde(flatten)]
+    config: Value,
+}
+
+#[derive(Debug, Serialize)]
+struct Content<'a> {
+    parts: Vec<Part<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub(crate) enum Part<'a> {
+    Text {
+        text: Cow<'a, str>,
+    },
+    Inline {
+        inline_data: InlineData<'a>,
+    },
+    #[cfg(feature = "transcription")]
+    File {
+        file_data: FileData<'a>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        video_metadata: Option<VideoMetadata>,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct InlineData<'a> {
+    data: Base64<'a>,
+    mime_type: &'a str,
+}
+
+#[cfg(feature = "transcription")]
+#[derive(Debug, Serialize)]
+pub(crate) struct FileData<'a> {
+    file_uri: &'a str,
+    mime_type: &'a str,
+}
+
+/// Part of a media file the model sees, as `"90s"` durations
+#[cfg(feature = "transcription")]
+#[derive(Debug, Serialize)]
+pub(crate) struct VideoMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end_offset: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start_offset: Option<String>,
+}
+
+/// Bytes that serialize as a base64 string without allocating it
+#[derive(Debug)]
+struct Base64<'a>(&'a [u8]);
+
+impl Serialize for Base64<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&Base64Display::new(self.0, &STANDARD))
+    }
+}
+
+impl<'a> Part<'a> {
+    pub(crate) fn text(text: impl Into<Cow<'a, str>>) -> Self {
+        Self::Text { text: text.into() }
+    }
+
+    pub(crate) fn inline(mime_type: &'a str, data: &'a [u8]) -> Self {
+        Self::Inline {
+            inline_data: InlineData {
+                data: Base64(data),
+                mime_type,
+            },
+        }
+    }
+
+    #[cfg(feature = "transcription")]
+    pub(crate) fn file(mime_type: &'a str, file_uri: &'a str) -> Self {
+        Self::file_clip(mime_type, file_uri, None, None)
+    }
+
+    /// A file part of which the model only sees `start_secs` to `end_secs`
+    #[cfg(feature = "transcription")]
+    pub(crate) fn file_clip(
+        mime_type: &'a str,
+        file_uri: &'a str,
+        start_secs: Option<u64>,
+        end_secs: Option<u64>,
+    ) -> Self {
+        let video_metadata = (start_secs.is_some() || end_secs.is_some()).then(|| VideoMetadata {
+            end_offset: end_secs.map(|secs| format!("{}s", secs)),
+            start_offset: start_secs.map(|secs| format!("{}s", secs)),
+        });
+        Self::File {
+            file_data: FileData {
+                file_uri,
+                mime_type,
+            },
+            video_metadata,
+        }
+    }
+
+    /// Length of the base64 data this part adds to a body
+    fn encoded_len(&self) -> usize {
+        match self {
+            Self::Inline { inline_data } => inline_data.data.0.len().div_ceil(3) * 4,
+            _ => 0,
+        }
+    }
+}
+
+impl<'a> GenerateContent<'a> {
+    /// `config` must be a JSON object
+    pub(crate) fn new(parts: Vec<Part<'a>>, config: Value) -> Self {
+        Self {
+            contents: [Content { parts }],
+            config,
+        }
+    }
+
+    /// Serialize into a buffer sized up front, so large inline data isn't
+    /// copied by the buffer growing
+    pub(crate) fn to_body(&self) -> serde_json::Result<JsonBody> {
+        let encoded: usize = self.contents[0].parts.iter().map(Part::encoded_len).sum();
+        let mut body = Vec::with_capacity(encoded + BODY_OVERHEAD);
+        serde_json::to_writer(&mut body, self)?;
+        Ok(JsonBody(Bytes::from(body)))
+    }
+}
+
+/// Serialized request body; clones share the buffer
+#[derive(Debug, Clone)]
+pub(crate) struct JsonBody(Bytes);
+
+impl JsonBody {
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// This request continued with the model's `answer` and a `follow_up`
+    /// user turn, to ask again in the same conversation. Parses the body, so
+    /// it is only meant for the rare retry, not every attempt.
+    #[cfg(feature = "transcription")]
+    pub(crate) fn with_follow_up(&self, answer: &str, follow_up: &str) -> serde_json::Result<Self> {
+        let mut body: Value = serde_json::from_slice(&self.0)?;
+        if let Some(contents) = body.get_mut("contents").and_then(Value::as_array_mut) {
+            for content in contents.iter_mut() {
+                content["role"] = json!("user");
+            }
+            contents.push(json!({"role": "model", "parts": [{"text": answer}]}));
+            contents.push(json!({"role": "user", "parts": [{"text": follow_up}]}));
+        }
+        Ok(Self(Bytes::from(serde_json::to_vec(&body)?)))
+    }
+}
+
+impl From<JsonBody> for reqwest::Body {
+    fn from(body: JsonBody) -> Self {
+        body.0.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_body_matches_value_serialization() {
+        let data = b"hello world";
+        let payload = GenerateContent::new(
+            vec![Part::text("Describe"), Part::inline("audio/mpeg", data)],
+            json!({"generation_config": {"temperature": 0}}),
+        );
+        let expected = json!({
+            "contents": [{
+                "parts": [
+                    {"text": "Describe"},
+                    {"inline_data": {"mime_type": "audio/mpeg", "data": "aGVsbG8gd29ybGQ="}}
+                ]
+            }],
+            "generation_config": {"temperature": 0}
+        });
+
+        let body = payload.to_body().unwrap();
+        assert_eq!(body.as_bytes(), expected.to_string().as_bytes());
+    }
+
+    #[cfg(feature = "transcription")]
+    #[test]
+    fn test_file_part_and_encoded_len() {
+        let payload = GenerateContent::new(
+            vec![Part::file("audio/mpeg", "https://files/abc")],
+            json!({}),
+        );
+        let body = payload.to_body().unwrap();
+        assert_eq!(
+            body.as_bytes(),
+            br#"{"contents":[{"parts":[{"file_data":{"file_uri":"https://files/abc","mime_type":"audio/mpeg"}}]}]}"#
+        );
+
+        let payload = GenerateContent::new(
+            vec![Part::file_clip(
+                "video/mp4",
+                "https://files/abc",
+                Some(90),
+                None,
+            )],
+            json!({}),
+        );
+        assert_eq!(
+            payload.to_body().unwrap().as_bytes(),
+            br#"{"contents":[{"parts":[{"file_data":{"file_uri":"https://files/abc","mime_type":"video/mp4"},"video_metadata":{"start_offset":"90s"}}]}]}"#
+        );
+
+        assert_eq!(Part::inline("image/png", &[0; 4]).encoded_len(), 8);
+        assert_eq!(Part::inline("image/png", &[0; 3]).encoded_len(), 4);
+        assert_eq!(Part::text("hi").encoded_len(), 0);
+    }
+}
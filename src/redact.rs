@@ -0,0 +1,111 @@
+//! Keep API keys out of error messages, `Debug` output and logs. The Gemini
+//! key is sent in the `x-goog-api-key` header by default, marked sensitive so
+//! it never shows in a header dump; with the opt-in `AuthMode::QueryString`
+//! it travels in the request URL instead, which reqwest includes in its
+//! errors, hence the scrubbing here.
+
+use std::fmt;
+
+const REDACTED: &str = "REDACTED";
+
+/// An API key whose `Debug` output never shows the key
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) struct ApiKey(String);
+
+impl ApiKey {
+    pub(crate) fn expose(&self) -> &str {
+        &self.0
+    }
+
+    /// Replace any occurrence of the key in `text`, e.g. an echoed URL in an
+    /// API error body
+    #[cfg(feature = "__client")]
+    pub(crate) fn scrub(&self, text: String) -> String {
+        if self.0.is_empty() || !text.contains(&self.0) {
+            text
+        } else {
+            text.replace(&self.0, REDACTED)
+        }
+    }
+}
+
+impl From<String> for ApiKey {
+    fn from(key: String) -> Self {
+        Self(key)
+    }
+}
+
+impl fmt::Debug for ApiKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ApiKey({})", REDACTED)
+    }
+}
+
+/// Blank out the `key` query parameter of the URL a reqwest error carries
+#[cfg(any(feature = "__client", feature = "testing"))]
+pub(crate) fn reqwest_error(mut err: reqwest::Error) -> reqwest::Error {
+    if let Some(url) = err.url_mut() {
+        redact_query(url);
+    }
+    err
+}
+
+/// `url` with its `key` query parameter blanked out, for logging
+#[cfg(any(feature = "file-api", feature = "veo"))]
+pub(crate) fn url(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(mut parsed) => {
+            redact_query(&mut parsed);
+            parsed.to_string()
+        }
+        Err(_) => url.to_string(),
+    }
+}
+
+#[cfg(any(feature = "__client", feature = "testing"))]
+fn redact_query(url: &mut reqwest::Url) {
+    if !url.query_pairs().any(|(name, _)| name == "key") {
+        return;
+    }
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(name, value)| {
+            let value = if name == "key" {
+                REDACTED.to_string()
+            } else {
+                value.into_owned()
+            };
+            (name.into_owned(), value)
+        })
+        .collect();
+    url.query_pairs_mut().clear().extend_pairs(pairs);
+}
+
+#[cfg(all(test, feature = "__client"))]
+mod tests {
+    use super::*;
+
+    #[cfg(any(feature = "file-api", feature = "veo"))]
+    #[test]
+    fn test_url_redacts_key_param() {
+        assert_eq!(
+            url("https://example.com/upload?key=secret123&upload_id=abc"),
+            "https://example.com/upload?key=REDACTED&upload_id=abc"
+        );
+        assert_eq!(
+            url("https://example.com/files/abc"),
+            "https://example.com/files/abc"
+        );
+    }
+
+    #[test]
+    fn test_api_key_debug_and_scrub() {
+        let key = ApiKey::from("secret123".to_string());
+        assert_eq!(format!("{:?}", key), "ApiKey(REDACTED)");
+        assert_eq!(
+            key.scrub("bad url ?key=secret123".to_string()),
+            "bad url ?key=REDACTED"
+        );
+        assert_eq!(key.expose(), "secret123");
+    }
+}
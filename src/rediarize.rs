@@ -0,0 +1,415 @@
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use clap::Parser;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tracing::{Instrument, debug, info, info_span, warn};
+
+use transcript_tool::cli::{self, CommonArgs, FfmpegArgs, SubtitleArgs, init_logging};
+use transcript_tool::config::{self, Profile};
+use transcript_tool::gemini_api::AudioSource;
+use transcript_tool::storage::{OutputStorage, Storage};
+use transcript_tool::{
+    Clients, FileApiClient, GeminiClientConfig, MAX_INLINE_FILE_SIZE, OutputFormat, Provider,
+    TranscriptResponse, format_transcript_with, scaled_timeout_secs,
+};
+
+#[derive(Parser, Debug)]
+#[command(name = "rediarize")]
+#[command(version)]
+#[command(about = "Fix the speaker labels of an existing transcript using its original audio")]
+#[command(after_help = "EXAMPLES:
+    rediarize -i talk.json -a talk.mp4
+    rediarize -i talk.json -a talk.mp3 --hint \"Two people: the host Ann and the guest Bo\"
+    rediarize -i talk.json -a talk.mp3 -f srt -o talk.srt
+    rediarize -i talk.json --merge \"Speaker 3=Speaker 1\"")]
+pub struct Args {
+    /// Transcript JSON written by `convert` or `batch_convert`
+    #[arg(short, long)]
+    input: PathBuf,
+
+    /// Original audio or video; without it only --merge is applied
+    #[arg(short, long)]
+    audio: Option<PathBuf>,
+
+    /// Context for the model, e.g. how many people speak or their names
+    #[arg(long)]
+    hint: Option<String>,
+
+    /// Relabel a speaker as FROM=TO, after the model pass; repeatable
+    #[arg(long, value_name = "FROM=TO", value_parser = parse_merge)]
+    merge: Vec<(String, String)>,
+
+    /// Output file path, or an s3:// / gs:// URI [default: <input>.rediarized.<format>]
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Output format [default: json]
+    #[arg(short, long, value_enum)]
+    format: Option<OutputFormat>,
+
+    /// Gemini model to use [default: gemini-2.5-flash]
+    #[arg(short, long, env = "FLASHECHO_MODEL")]
+    model: Option<String>,
+
+    /// API timeout in seconds [default: scaled with the media length, from 2
+    /// minutes to an hour]
+    #[arg(short, long, value_name = "SECONDS")]
+    timeout: Option<u64>,
+
+    #[command(flatten)]
+    subtitles: SubtitleArgs,
+
+    #[command(flatten)]
+    ffmpeg: FfmpegArgs,
+
+    #[command(flatten)]
+    common: CommonArgs,
+}
+
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "mkv", "avi", "mov", "webm", "flv", "wmv", "m4v"];
+const AUDIO_EXTENSIONS: &[&str] = &["mp3", "wav", "ogg", "flac", "m4a", "aac", "wma"];
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| extensions.contains(&ext.to_lowercase().as_str()))
+}
+
+fn parse_merge(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((from, to)) if !from.trim().is_empty() && !to.trim().is_empty() => {
+            Ok((from.trim().to_string(), to.trim().to_string()))
+        }
+        _ => Err(format!(
+            "Invalid merge '{}': expected FROM=TO, e.g. \"Speaker 3=Speaker 1\"",
+            value
+        )),
+    }
+}
+
+/// Relabel speakers; later merges see the result of earlier ones
+fn apply_merges(transcript: &mut TranscriptResponse, merges: &[(String, String)]) {
+    for (from, to) in merges {
+        for segment in &mut transcript.segments {
+            if segment.speaker == *from {
+                segment.speaker = to.clone();
+            }
+        }
+    }
+}
+
+/// Number of segments per (old, new) label pair that changed
+fn speaker_changes(
+    before: &TranscriptResponse,
+    after: &TranscriptResponse,
+) -> BTreeMap<(String, String), usize> {
+    let mut changes = BTreeMap::new();
+    for (old, new) in before.segments.iter().zip(&after.segments) {
+        if old.speaker != new.speaker {
+            *changes
+                .entry((old.speaker.clone(), new.speaker.clone()))
+                .or_insert(0) += 1;
+        }
+    }
+    changes
+}
+
+fn speaker_count(transcript: &TranscriptResponse) -> usize {
+    let mut speakers: Vec<&str> = transcript
+        .segments
+        .iter()
+        .map(|segment| segment.speaker.as_str())
+        .collect();
+    speakers.sort_unstable();
+    speakers.dedup();
+    speakers.len()
+}
+
+/// `talk.json` -> `talk.rediarized.json`
+fn default_output(input: &Path, format: OutputFormat) -> PathBuf {
+    input.with_extension(format!("rediarized.{}", format.extension()))
+}
+
+async fn extract_audio_with_ffmpeg(ffmpeg: &FfmpegArgs, input: &Path, output: &Path) -> Result<()> {
+    info!("Extracting audio from {:?} to {:?}", input, output);
+    let output_result = ffmpeg
+        .command()
+        .args([
+            "-i",
+            input.to_str().context("Invalid input path")?,
+            "-vn",
+            "-acodec",
+            "libmp3lame",
+            "-q:a",
+            "2",
+            "-y",
+            output.to_str().context("Invalid output path")?,
+        ])
+        .output()
+        .await
+        .with_context(|| ffmpeg.missing())?;
+
+    if !output_result.status.success() {
+        let stderr = String::from_utf8_lossy(&output_result.stderr);
+        anyhow::bail!("ffmpeg failed: {}", stderr);
+    }
+    Ok(())
+}
+
+/// Audio bytes of `media`, extracting the track from videos
+async fn read_audio(ffmpeg: &FfmpegArgs, media: &Path) -> Result<(Bytes, PathBuf)> {
+    if !media.exists() {
+        anyhow::bail!("Audio file does not exist: {:?}", media);
+    }
+    if has_extension(media, AUDIO_EXTENSIONS) {
+        let data = fs::read(media).await.context("Failed to read audio file")?;
+        return Ok((Bytes::from(data), media.to_path_buf()));
+    }
+    if !has_extension(media, VIDEO_EXTENSIONS) {
+        anyhow::bail!(
+            "Not a supported media format: {:?}\nSupported formats: video ({}) or audio ({})",
+            media,
+            VIDEO_EXTENSIONS.join(", "),
+            AUDIO_EXTENSIONS.join(", ")
+        );
+    }
+
+    let mp3_path = media.with_extension("rediarize.mp3");
+    extract_audio_with_ffmpeg(ffmpeg, media, &mp3_path)
+        .instrument(info_span!("stage", stage = "extract_audio"))
+        .await?;
+    let data = fs::read(&mp3_path).await;
+    fs::remove_file(&mp3_path).await.ok();
+    let data = data.context("Failed to read extracted audio")?;
+    Ok((Bytes::from(data), mp3_path))
+}
+
+/// Run with parsed arguments; shared by this binary and its `flashecho` subcommand
+pub async fn run(args: Args) -> Result<()> {
+    init_logging(args.common.verbose, args.common.log_format);
+    args.common.install_usage_ledger("rediarize");
+    let span = info_span!("file", file = %args.input.display());
+    rediarize(args).instrument(span).await
+}
+
+async fn rediarize(args: Args) -> Result<()> {
+    if args.audio.is_none() && args.merge.is_empty() {
+        anyhow::bail!(
+            "Nothing to do: pass --audio to re-assign speakers, or --merge to relabel them"
+        );
+    }
+
+    let content = fs::read_to_string(&args.input)
+        .await
+        .with_context(|| format!("Failed to read transcript {:?}", args.input))?;
+    let original = TranscriptResponse::from_json_any_version(&content)
+        .context("Failed to parse transcript JSON")?;
+
+    let profile = args.common.load_profile()?;
+    let format = config::resolve(args.format, "format", profile.format.as_deref())?;
+
+    let mut transcript = match &args.audio {
+        Some(media) => rediarize_with_audio(&args, &profile, media, &original).await?,
+        None => original.clone(),
+    };
+    apply_merges(&mut transcript, &args.merge);
+
+    let default_path = default_output(&args.input, format);
+    let default_name = default_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let target = args
+        .output
+        .clone()
+        .unwrap_or_else(|| default_path.to_string_lossy().to_string());
+    let (storage, key) = OutputStorage::open_file(&target, &default_name)
+        .with_context(|| format!("Invalid output target: {}", target))?;
+    let output = format_transcript_with(&transcript, format, &args.subtitles.options())
+        .context("Failed to serialize to JSON")?;
+    storage
+        .put(&key, output.as_bytes())
+        .instrument(info_span!("stage", stage = "write"))
+        .await
+        .context("Failed to write output file")?;
+    info!("Transcript saved to {}", storage.location(&key));
+
+    if !args.common.quiet {
+        let changes = speaker_changes(&original, &transcript);
+        println!(
+            "Speakers: {} -> {}",
+            speaker_count(&original),
+            speaker_count(&transcript)
+        );
+        if changes.is_empty() {
+            println!("No segments changed speaker.");
+        }
+        for ((old, new), count) in &changes {
+            println!("  {} -> {}: {} segments", old, new, count);
+        }
+        println!("Transcript saved to: {}", storage.location(&key));
+    }
+    Ok(())
+}
+
+async fn rediarize_with_audio(
+    args: &Args,
+    profile: &Profile,
+    media: &Path,
+    transcript: &TranscriptResponse,
+) -> Result<TranscriptResponse> {
+    let (audio_data, audio_path) = read_audio(&args.ffmpeg, media).await?;
+    let mime_type = transcript_tool::GeminiClient::get_mime_type(&audio_path);
+    debug!("Audio: {} bytes, MIME type {}", audio_data.len(), mime_type);
+    let timeout = match args.timeout {
+        Some(timeout) => timeout,
+        None => {
+            let duration = args.ffmpeg.probe_duration(media).await;
+            scaled_timeout_secs(duration, audio_data.len() as u64)
+        }
+    };
+    debug!("Request timeout: {}s", timeout);
+
+    let api_key = cli::gemini_api_key(profile)?;
+    let clients = Clients::new(api_key.clone()).context("Failed to create HTTP client")?;
+    let budget = args
+        .common
+        .budget()
+        .context("Failed to read usage ledger for --budget-since")?;
+    let model = args
+        .model
+        .clone()
+        .or_else(|| profile.model_for(Provider::Gemini).map(str::to_string))
+        .unwrap_or_else(|| Provider::Gemini.default_model().to_string());
+    let client = clients.gemini(GeminiClientConfig {
+        timeout_secs: timeout,
+        max_retries: args.common.max_retries,
+        model,
+        cache: args.common.response_cache(),
+        budget,
+        ..Default::default()
+    });
+
+    let pb = cli::spinner(args.common.quiet, String::new());
+
+    // Large audio goes through the File API, like `convert`
+    let uploaded = if audio_data.len() as u64 > MAX_INLINE_FILE_SIZE {
+        if let Some(pb) = &pb {
+            pb.set_message("Uploading audio to Gemini File API...");
+        }
+        let file_api = FileApiClient::new(client.http_client().clone(), api_key)
+            .with_base_url(client.base_url())
+            .with_auth_mode(client.auth_mode());
+        let display_name = audio_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("audio");
+        let file_info = file_api
+            .upload_file(audio_data.clone(), mime_type, display_name)
+            .instrument(info_span!("stage", stage = "upload"))
+            .await
+            .map_err(|e| anyhow::anyhow!("File upload failed: {}", e))?;
+        Some((file_api, file_info))
+    } else {
+        None
+    };
+    let source = match &uploaded {
+        Some((_, file_info)) => AudioSource::FileUri {
+            mime_type: mime_type.to_string(),
+            uri: file_info.uri.clone(),
+        },
+        None => AudioSource::Inline {
+            mime_type: mime_type.to_string(),
+            data: audio_data.to_vec(),
+        },
+    };
+
+    if let Some(pb) = &pb {
+        pb.set_message("Re-assigning speakers with Gemini API...");
+    }
+    let result = client
+        .rediarize(&source, transcript, args.hint.as_deref())
+        .instrument(info_span!("stage", stage = "rediarize"))
+        .await
+        .map_err(|e| anyhow::anyhow!("Re-diarization failed: {}", e));
+    if let Some(pb) = pb {
+        pb.finish_and_clear();
+    }
+
+    if let Some((file_api, file_info)) = uploaded
+        && let Err(e) = file_api.delete_file(&file_info.name).await
+    {
+        warn!("Failed to delete remote file {}: {}", file_info.name, e);
+    }
+    result
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    run(Args::parse()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transcript(speakers: &[&str]) -> TranscriptResponse {
+        let segments: Vec<serde_json::Value> = speakers
+            .iter()
+            .map(|speaker| {
+                serde_json::json!({
+                    "speaker": speaker,
+                    "timestamp": "00:00",
+                    "content": "Hi",
+                    "language": "English",
+                    "language_code": "en",
+                    "emotion": "neutral"
+                })
+            })
+            .collect();
+        serde_json::from_value(serde_json::json!({"summary": "", "segments": segments})).unwrap()
+    }
+
+    #[test]
+    fn test_parse_merge() {
+        assert_eq!(
+            parse_merge("Speaker 3 = Speaker 1"),
+            Ok(("Speaker 3".to_string(), "Speaker 1".to_string()))
+        );
+        assert!(parse_merge("Speaker 3").is_err());
+        assert!(parse_merge("=Speaker 1").is_err());
+    }
+
+    #[test]
+    fn test_merges_and_changes() {
+        let before = transcript(&["Speaker 1", "Speaker 3", "Speaker 2", "Speaker 3"]);
+        let mut after = before.clone();
+        apply_merges(
+            &mut after,
+            &[
+                ("Speaker 3".to_string(), "Speaker 2".to_string()),
+                ("Speaker 2".to_string(), "Speaker 1".to_string()),
+            ],
+        );
+        assert!(after.segments.iter().all(|s| s.speaker == "Speaker 1"));
+        assert_eq!(speaker_count(&before), 3);
+        assert_eq!(speaker_count(&after), 1);
+
+        let changes = speaker_changes(&before, &after);
+        assert_eq!(
+            changes.get(&("Speaker 3".to_string(), "Speaker 1".to_string())),
+            Some(&2)
+        );
+        assert_eq!(changes.len(), 2);
+    }
+
+    #[test]
+    fn test_default_output() {
+        assert_eq!(
+            default_output(Path::new("talks/a.json"), OutputFormat::Srt),
+            PathBuf::from("talks/a.rediarized.srt")
+        );
+    }
+}
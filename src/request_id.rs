@@ -0,0 +1,33 @@
+//! Request IDs for correlating log lines and errors of one API call across
+//! retry attempts and parallel batch jobs.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Generate a short, process-unique request ID (12 hex characters)
+pub(crate) fn next() -> String {
+    static SEED: OnceLock<RandomState> = OnceLock::new();
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let mut hasher = SEED.get_or_init(RandomState::new).build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    format!("{:012x}", hasher.finish() & 0xffff_ffff_ffff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_ids_are_unique_hex() {
+        let ids: Vec<String> = (0..100).map(|_| next()).collect();
+        assert!(
+            ids.iter()
+                .all(|id| id.len() == 12 && id.chars().all(|c| c.is_ascii_hexdigit()))
+        );
+        let unique: std::collections::HashSet<_> = ids.iter().collect();
+        assert_eq!(unique.len(), ids.len());
+    }
+}
@@ -0,0 +1,83 @@
+//! Platform shims so the API clients build for wasm32 (fetch-based reqwest)
+//! as well as native targets.
+
+use reqwest::ClientBuilder;
+use std::time::Duration;
+
+const CONNECT_TIMEOUT_SECS: u64 = 30;
+
+/// HTTP client builder with the crate's TLS and timeout defaults. On wasm32
+/// the browser/runtime owns TLS and connections, so only per-request
+/// timeouts apply.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn client_builder(timeout_secs: u64) -> ClientBuilder {
+    reqwest::Client::builder()
+        .use_rustls_tls()
+        .timeout(Duration::from_secs(timeout_secs))
+        .connect_timeout(Duration::from_secs(CONNECT_TIMEOUT_SECS))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn client_builder(_timeout_secs: u64) -> ClientBuilder {
+    reqwest::Client::builder()
+}
+
+#[cfg(all(feature = "__client", not(target_arch = "wasm32")))]
+pub(crate) async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await;
+}
+
+/// `setTimeout`-backed sleep; works in browsers and Cloudflare Workers
+#[cfg(all(feature = "__client", target_arch = "wasm32"))]
+pub(crate) async fn sleep(duration: Duration) {
+    use wasm_bindgen::{JsCast, JsValue};
+
+    let millis = duration.as_millis().min(i32::MAX as u128) as i32;
+    let promise = js_sys::Promise::new(&mut |resolve, _reject| {
+        let global = js_sys::global();
+        let set_timeout = js_sys::Reflect::get(&global, &JsValue::from_str("setTimeout"))
+            .ok()
+            .and_then(|f| f.dyn_into::<js_sys::Function>().ok());
+        match set_timeout {
+            Some(set_timeout) => {
+                let _ = set_timeout.call2(&global, &resolve, &JsValue::from(millis));
+            }
+            None => {
+                let _ = resolve.call0(&JsValue::UNDEFINED);
+            }
+        }
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
+
+/// Elapsed-time measurement; `std::time::Instant` panics on wasm32
+#[cfg(feature = "__retry")]
+pub(crate) struct Stopwatch {
+    #[cfg(not(target_arch = "wasm32"))]
+    started: std::time::Instant,
+    #[cfg(target_arch = "wasm32")]
+    started_ms: f64,
+}
+
+#[cfg(feature = "__retry")]
+impl Stopwatch {
+    pub(crate) fn start() -> Self {
+        Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            started: std::time::Instant::now(),
+            #[cfg(target_arch = "wasm32")]
+            started_ms: js_sys::Date::now(),
+        }
+    }
+
+    pub(crate) fn elapsed_ms(&self) -> u64 {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.started.elapsed().as_millis() as u64
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            (js_sys::Date::now() - self.started_ms).max(0.0) as u64
+        }
+    }
+}
@@ -0,0 +1,247 @@
+use anyhow::{Context, Result};
+use clap::{Parser, ValueEnum};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tracing::{Instrument, debug, info, info_span};
+
+use transcript_tool::cli::{self, CommonArgs, init_logging};
+use transcript_tool::storage::{OutputStorage, Storage};
+use transcript_tool::transcript_format::transcript_to_lines;
+use transcript_tool::video_frames::{DEFAULT_SCENE_THRESHOLD, MAX_FRAME_WIDTH};
+use transcript_tool::{
+    Clients, TextClientConfig, TextModel, TranscriptResponse, VisionClient, extract_scene_frames,
+};
+
+#[derive(Parser, Debug)]
+#[command(name = "scenes")]
+#[command(version)]
+#[command(about = "List the scenes of a video with what they show and their on-screen text")]
+#[command(after_help = "EXAMPLES:
+    scenes lecture.mp4
+    scenes lecture.mp4 --transcript lecture.json -f txt
+    scenes trailer.mov --threshold 0.2 --max-frames 60
+    scenes talk.mkv -o - | jq '.scenes[].description'")]
+pub struct Args {
+    /// Video file
+    #[arg(value_name = "VIDEO")]
+    input: PathBuf,
+
+    /// Transcript JSON of the same video (from `convert`), for context
+    #[arg(long, value_name = "FILE")]
+    transcript: Option<PathBuf>,
+
+    /// Output file path (`-` for stdout), or an s3:// / gs:// URI
+    /// [default: <input>.scenes.<format>]
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value = "json")]
+    format: ScenesFormat,
+
+    /// Fraction of the picture that must change to start a new scene (0-1)
+    #[arg(long, default_value_t = DEFAULT_SCENE_THRESHOLD)]
+    threshold: f64,
+
+    /// Most keyframes sent to the model; extra ones are dropped evenly
+    #[arg(
+        long,
+        default_value = "40",
+        value_parser = clap::value_parser!(u16).range(1..=3000)
+    )]
+    max_frames: u16,
+
+    /// Context for the model, e.g. what the video is about
+    #[arg(long)]
+    hint: Option<String>,
+
+    /// Model: flash (default), flash-lite, pro
+    #[arg(
+        short = 'm',
+        long,
+        env = "FLASHECHO_TEXT_MODEL",
+        default_value = "flash"
+    )]
+    model: String,
+
+    /// Timeout of the API request in seconds
+    #[arg(short, long, default_value = "300")]
+    timeout: u64,
+
+    #[command(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ScenesFormat {
+    /// Scenes with start/end seconds, description and on-screen text
+    Json,
+    /// One `[start - end] description` line per scene
+    Txt,
+}
+
+impl ScenesFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ScenesFormat::Json => "json",
+            ScenesFormat::Txt => "txt",
+        }
+    }
+}
+
+/// `talk.mp4` -> `talk.scenes.json`
+fn default_output(input: &Path, format: ScenesFormat) -> PathBuf {
+    input.with_extension(format!("scenes.{}", format.extension()))
+}
+
+/// Run with parsed arguments; shared by this binary and its `flashecho` subcommand
+pub async fn run(args: Args) -> Result<()> {
+    init_logging(args.common.verbose, args.common.log_format);
+    args.common.install_usage_ledger("scenes");
+
+    let span = info_span!("file", file = %args.input.display());
+    summarize(args).instrument(span).await
+}
+
+async fn summarize(args: Args) -> Result<()> {
+    if !args.input.exists() {
+        anyhow::bail!("Input file does not exist: {:?}", args.input);
+    }
+    let transcript = match &args.transcript {
+        Some(path) => {
+            let content = fs::read_to_string(path)
+                .await
+                .with_context(|| format!("Failed to read transcript {:?}", path))?;
+            let transcript = TranscriptResponse::from_json_any_version(&content)
+                .context("Failed to parse transcript JSON")?;
+            Some(transcript_to_lines(&transcript))
+        }
+        None => None,
+    };
+
+    let model: TextModel = args.model.parse().map_err(|e: String| anyhow::anyhow!(e))?;
+    let profile = args.common.load_profile()?;
+    let clients =
+        Clients::new(cli::gemini_api_key(&profile)?).context("Failed to create HTTP client")?;
+    let budget = args
+        .common
+        .budget()
+        .context("Failed to read usage ledger for --budget-since")?;
+    let mut vision = VisionClient::new(clients.text(TextClientConfig {
+        timeout_secs: args.timeout,
+        max_retries: args.common.max_retries,
+        model,
+        cache: args.common.response_cache(),
+        budget,
+        ..Default::default()
+    }));
+    if let Some(hint) = &args.hint {
+        vision = vision.with_hint(hint);
+    }
+
+    let pb = cli::spinner(
+        args.common.quiet,
+        "Extracting keyframes with ffmpeg...".into(),
+    );
+    let result = extract_scene_frames(
+        &args.input,
+        args.threshold,
+        args.max_frames.into(),
+        MAX_FRAME_WIDTH,
+    )
+    .instrument(info_span!("stage", stage = "extract_frames"))
+    .await;
+    if let Some(pb) = pb {
+        pb.finish_and_clear();
+    }
+    let keyframes = result.context("Failed to extract keyframes")?;
+    if keyframes.frames.is_empty() {
+        anyhow::bail!("ffmpeg found no video frames in {:?}", args.input);
+    }
+    debug!(
+        "{} keyframes, duration {:?}",
+        keyframes.frames.len(),
+        keyframes.duration_secs
+    );
+
+    let pb = cli::spinner(
+        args.common.quiet,
+        format!("Describing {} keyframes...", keyframes.frames.len()),
+    );
+    let result = vision
+        .summarize_scenes(
+            &keyframes.frames,
+            keyframes.duration_secs,
+            transcript.as_deref(),
+        )
+        .instrument(info_span!("stage", stage = "scenes"))
+        .await;
+    if let Some(pb) = pb {
+        pb.finish_and_clear();
+    }
+    let summary = result.map_err(|e| anyhow::anyhow!("Scene summarization failed: {}", e))?;
+    info!("{} scenes", summary.scenes.len());
+
+    let output = match args.format {
+        ScenesFormat::Json => {
+            serde_json::to_string_pretty(&summary).context("Failed to serialize scenes")?
+        }
+        ScenesFormat::Txt => summary.to_text(),
+    };
+
+    if args.output.as_deref() == Some("-") {
+        let mut stdout = tokio::io::stdout();
+        stdout
+            .write_all(output.as_bytes())
+            .await
+            .context("Failed to write to stdout")?;
+        stdout.flush().await.context("Failed to flush stdout")?;
+        return Ok(());
+    }
+
+    let default_path = default_output(&args.input, args.format);
+    let default_name = default_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let target = args
+        .output
+        .clone()
+        .unwrap_or_else(|| default_path.to_string_lossy().to_string());
+    let (storage, key) = OutputStorage::open_file(&target, &default_name)
+        .with_context(|| format!("Invalid output target: {}", target))?;
+    storage
+        .put(&key, output.as_bytes())
+        .instrument(info_span!("stage", stage = "write"))
+        .await
+        .context("Failed to write scene list")?;
+    let location = storage.location(&key);
+    info!("Scene list written to {}", location);
+    if !args.common.quiet {
+        println!("Saved to: {}", location);
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    run(Args::parse()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_output() {
+        assert_eq!(
+            default_output(Path::new("videos/talk.mp4"), ScenesFormat::Json),
+            PathBuf::from("videos/talk.scenes.json")
+        );
+        assert_eq!(
+            default_output(Path::new("clip.mov"), ScenesFormat::Txt),
+            PathBuf::from("clip.scenes.txt")
+        );
+    }
+}
@@ -0,0 +1,406 @@
+//! Local semantic search index over transcript JSON files. Each segment is
+//! embedded once (`RETRIEVAL_DOCUMENT`) and stored with its file's content
+//! hash, so [`SearchIndex::update`] only re-embeds files that changed.
+//! Queries are embedded as `RETRIEVAL_QUERY` and ranked by cosine similarity.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use tracing::{debug, info};
+
+use crate::embeddings_api::{EmbedConfig, Embedding, EmbeddingError, EmbeddingsClient, TaskType};
+use crate::error::ErrorKind;
+use crate::gemini_api::TranscriptResponse;
+use crate::storage::write_atomic;
+use crate::transcript_schema::SchemaError;
+
+/// Environment variable overriding [`SearchIndex::default_path`]
+pub const INDEX_ENV: &str = "FLASHECHO_SEARCH_INDEX";
+
+/// Vector size requested from the model, to keep the index small
+pub const INDEX_DIMENSIONS: u32 = 768;
+
+const SNIPPET_CHARS: usize = 160;
+
+#[derive(Debug, Error)]
+pub enum SearchIndexError {
+    #[error("Failed to access {path:?}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Search index {path:?} is corrupt: {source}")]
+    Corrupt {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    #[error("Search index is empty; index some transcripts first")]
+    EmptyIndex,
+
+    #[error(transparent)]
+    Embedding(#[from] EmbeddingError),
+}
+
+pub type Result<T> = std::result::Result<T, SearchIndexError>;
+
+impl SearchIndexError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            SearchIndexError::Io { .. } => ErrorKind::Io,
+            SearchIndexError::Corrupt { .. } | SearchIndexError::EmptyIndex => {
+                ErrorKind::InvalidInput
+            }
+            SearchIndexError::Embedding(e) => e.kind(),
+        }
+    }
+
+    /// HTTP status returned by the API, if any
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            SearchIndexError::Embedding(e) => e.status(),
+            _ => None,
+        }
+    }
+}
+
+fn io_error(path: &Path) -> impl FnOnce(std::io::Error) -> SearchIndexError + '_ {
+    move |source| SearchIndexError::Io {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+/// One transcript segment with its unit-length embedding
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexedSegment {
+    pub timestamp: String,
+    pub speaker: String,
+    pub text: String,
+    pub vector: Vec<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct IndexedFile {
+    /// blake3 of the file contents when it was indexed
+    hash: String,
+    segments: Vec<IndexedSegment>,
+}
+
+/// What [`SearchIndex::update`] did
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UpdateStats {
+    pub indexed: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+    /// JSON files that aren't transcripts
+    pub skipped: usize,
+}
+
+/// A matching segment
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchHit {
+    pub file: PathBuf,
+    pub timestamp: String,
+    pub speaker: String,
+    pub snippet: String,
+    pub score: f32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SearchIndex {
+    /// Embedding model the vectors came from; another model starts over
+    model: String,
+    /// Canonical path -> indexed segments
+    files: BTreeMap<PathBuf, IndexedFile>,
+}
+
+impl SearchIndex {
+    /// `$FLASHECHO_SEARCH_INDEX`, else `$XDG_DATA_HOME/flashecho/search-index.json`,
+    /// else `~/.local/share/flashecho/search-index.json`
+    pub fn default_path() -> PathBuf {
+        if let Some(path) = std::env::var_os(INDEX_ENV) {
+            return PathBuf::from(path);
+        }
+        let base = std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| Path::new(&home).join(".local").join("share"))
+            })
+            .unwrap_or_else(std::env::temp_dir);
+        base.join("flashecho").join("search-index.json")
+    }
+
+    /// Read `path`; a missing file is an empty index
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = match std::fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(io_error(path)(e)),
+        };
+        serde_json::from_slice(&bytes).map_err(|source| SearchIndexError::Corrupt {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// Write via a temp file so an interrupted save keeps the old index
+    pub async fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(io_error(dir))?;
+        }
+        let bytes = serde_json::to_vec(self).map_err(|source| SearchIndexError::Corrupt {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        write_atomic(path, &bytes).await.map_err(io_error(path))
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.files.values().map(|file| file.segments.len()).sum()
+    }
+
+    /// Index new and changed transcripts among `files`, and drop entries
+    /// whose file no longer exists. Files that don't parse as a transcript
+    /// are skipped. Files indexed before an error stay in the index.
+    pub async fn update(
+        &mut self,
+        client: &EmbeddingsClient,
+        files: &[PathBuf],
+    ) -> Result<UpdateStats> {
+        let mut stats = UpdateStats::default();
+        let model = client.model().api_model_name();
+        if self.model != model {
+            if !self.files.is_empty() {
+                info!("Embedding model changed to {}; rebuilding index", model);
+            }
+            self.files.clear();
+            self.model = model.to_string();
+        }
+
+        let before = self.files.len();
+        self.files.retain(|path, _| path.exists());
+        stats.removed = before - self.files.len();
+
+        let embed_config = EmbedConfig::new()
+            .with_task_type(TaskType::RetrievalDocument)
+            .with_output_dimensionality(INDEX_DIMENSIONS);
+
+        for file in files {
+            let path = std::fs::canonicalize(file).map_err(io_error(file))?;
+            let bytes = std::fs::read(&path).map_err(io_error(&path))?;
+            let hash = blake3::hash(&bytes).to_hex().to_string();
+            if self
+                .files
+                .get(&path)
+                .is_some_and(|entry| entry.hash == hash)
+            {
+                stats.unchanged += 1;
+                continue;
+            }
+
+            let Ok(transcript) = serde_json::from_slice(&bytes)
+                .map_err(SchemaError::from)
+                .and_then(TranscriptResponse::from_value_any_version)
+            else {
+                debug!("Skipping {:?}: not a transcript", path);
+                self.files.remove(&path);
+                stats.skipped += 1;
+                continue;
+            };
+
+            let texts: Vec<String> = transcript
+                .segments
+                .iter()
+                .map(|segment| match &segment.translation {
+                    Some(translation) if !translation.trim().is_empty() => {
+                        format!("{}\n{}", segment.content, translation)
+                    }
+                    _ => segment.content.clone(),
+                })
+                .collect();
+            let kept: Vec<usize> = (0..texts.len())
+                .filter(|&i| !texts[i].trim().is_empty())
+                .collect();
+            let vectors = if kept.is_empty() {
+                Vec::new()
+            } else {
+                let batch: Vec<&str> = kept.iter().map(|&i| texts[i].as_str()).collect();
+                client.embed_batch(&batch, Some(&embed_config)).await?
+            };
+
+            let segments = kept
+                .iter()
+                .zip(vectors)
+                .map(|(&i, embedding)| {
+                    let segment = &transcript.segments[i];
+                    IndexedSegment {
+                        timestamp: segment.timestamp.to_string(),
+                        speaker: segment.speaker.clone(),
+                        text: segment.content.clone(),
+                        vector: embedding.normalized().values,
+                    }
+                })
+                .collect();
+            info!("Indexed {:?}", path);
+            self.files.insert(path, IndexedFile { hash, segments });
+            stats.indexed += 1;
+        }
+        Ok(stats)
+    }
+
+    /// The `limit` segments closest to `query`, best first, from files under
+    /// one of `roots` (all files when empty)
+    pub fn search(&self, query: &Embedding, roots: &[PathBuf], limit: usize) -> Vec<SearchHit> {
+        let query = query.normalized();
+        let mut hits: Vec<SearchHit> = self
+            .files
+            .iter()
+            .filter(|(path, _)| roots.is_empty() || roots.iter().any(|root| path.starts_with(root)))
+            .flat_map(|(path, file)| {
+                file.segments.iter().map(|segment| SearchHit {
+                    file: path.clone(),
+                    timestamp: segment.timestamp.clone(),
+                    speaker: segment.speaker.clone(),
+                    snippet: snippet(&segment.text),
+                    score: dot(&query.values, &segment.vector),
+                })
+            })
+            .collect();
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        hits.truncate(limit);
+        hits
+    }
+
+    /// Embed `query` and [`search`](Self::search) for it
+    pub async fn query(
+        &self,
+        client: &EmbeddingsClient,
+        query: &str,
+        roots: &[PathBuf],
+        limit: usize,
+    ) -> Result<Vec<SearchHit>> {
+        if self.segment_count() == 0 {
+            return Err(SearchIndexError::EmptyIndex);
+        }
+        let embed_config = EmbedConfig::new()
+            .with_task_type(TaskType::RetrievalQuery)
+            .with_output_dimensionality(INDEX_DIMENSIONS);
+        let embedding = client.embed(query, Some(&embed_config)).await?;
+        Ok(self.search(&embedding, roots, limit))
+    }
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// First [`SNIPPET_CHARS`] characters of `text` on one line
+fn snippet(text: &str) -> String {
+    let line = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.chars().count() <= SNIPPET_CHARS {
+        return line;
+    }
+    let cut: String = line.chars().take(SNIPPET_CHARS).collect();
+    format!("{}...", cut.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(text: &str, vector: Vec<f32>) -> IndexedSegment {
+        IndexedSegment {
+            timestamp: "00:01".to_string(),
+            speaker: "Speaker 1".to_string(),
+            text: text.to_string(),
+            vector,
+        }
+    }
+
+    fn index() -> SearchIndex {
+        let mut files = BTreeMap::new();
+        files.insert(
+            PathBuf::from("/archive/a/talk.json"),
+            IndexedFile {
+                hash: "1".to_string(),
+                segments: vec![
+                    segment("We raised prices", vec![1.0, 0.0]),
+                    segment("The weather", vec![0.0, 1.0]),
+                ],
+            },
+        );
+        files.insert(
+            PathBuf::from("/archive/b/call.json"),
+            IndexedFile {
+                hash: "2".to_string(),
+                segments: vec![segment("Pricing tiers", vec![0.8, 0.6])],
+            },
+        );
+        SearchIndex {
+            model: "gemini-embedding-001".to_string(),
+            files,
+        }
+    }
+
+    #[test]
+    fn test_search_ranks_and_filters() {
+        let index = index();
+        let query = Embedding {
+            values: vec![2.0, 0.0],
+        };
+
+        let hits = index.search(&query, &[], 10);
+        assert_eq!(hits.len(), 3);
+        assert_eq!(hits[0].snippet, "We raised prices");
+        assert!((hits[0].score - 1.0).abs() < 1e-6);
+        assert_eq!(hits[1].snippet, "Pricing tiers");
+        assert_eq!(hits[2].snippet, "The weather");
+
+        let hits = index.search(&query, &[PathBuf::from("/archive/b")], 10);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].file, PathBuf::from("/archive/b/call.json"));
+
+        assert_eq!(index.search(&query, &[], 1).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("flashecho_search_{}", std::process::id()));
+        let path = dir.join("nested").join("index.json");
+        assert_eq!(SearchIndex::load(&path).unwrap(), SearchIndex::default());
+
+        let index = index();
+        index.save(&path).await.unwrap();
+        assert_eq!(SearchIndex::load(&path).unwrap(), index);
+        assert_eq!(index.file_count(), 2);
+        assert_eq!(index.segment_count(), 3);
+
+        std::fs::write(&path, "not json").unwrap();
+        assert!(matches!(
+            SearchIndex::load(&path),
+            Err(SearchIndexError::Corrupt { .. })
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_snippet() {
+        assert_eq!(snippet("  Hello\n  world "), "Hello world");
+        let long = "word ".repeat(100);
+        let short = snippet(&long);
+        assert!(short.ends_with("..."));
+        assert!(short.chars().count() <= SNIPPET_CHARS + 3);
+    }
+}
@@ -0,0 +1,139 @@
+use anyhow::{Context, Result};
+use clap::Parser;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use tracing::info;
+
+use transcript_tool::cli::{self, CommonArgs, init_logging};
+use transcript_tool::server::DEFAULT_MAX_BODY_BYTES;
+use transcript_tool::{
+    Backends, Clients, GeminiClientConfig, ImageModel, ImagenClientConfig, Provider, Server,
+    ServerConfig,
+};
+
+#[derive(Parser, Debug)]
+#[command(name = "serve")]
+#[command(version)]
+#[command(about = "Serve transcription and image generation as REST endpoints with a job queue")]
+#[command(after_help = "ENDPOINTS:
+    POST /transcribe   multipart/form-data: file (audio), format (json|srt|vtt|txt)
+    POST /images       JSON: {\"prompt\", \"aspect_ratio\", \"size\", \"count\"}
+    GET  /jobs/ID      job status, and its result once done
+    GET  /metrics      Prometheus metrics (metrics feature)
+
+EXAMPLES:
+    serve
+    serve --listen 0.0.0.0:8080 -j 4
+    curl -F file=@talk.mp3 -F format=srt http://127.0.0.1:8080/transcribe")]
+pub struct Args {
+    /// Address to listen on
+    #[arg(
+        long,
+        value_name = "ADDR",
+        env = "FLASHECHO_LISTEN",
+        default_value = "127.0.0.1:8080"
+    )]
+    listen: SocketAddr,
+
+    /// Jobs running at the same time; the rest wait in the queue
+    #[arg(
+        short = 'j',
+        long,
+        default_value = "2",
+        value_parser = clap::value_parser!(u16).range(1..=64)
+    )]
+    concurrency: u16,
+
+    /// Largest accepted request body in MB
+    #[arg(long, default_value_t = DEFAULT_MAX_BODY_BYTES / (1024 * 1024))]
+    max_upload_mb: usize,
+
+    /// Finished jobs kept for `GET /jobs/ID`, oldest forgotten first
+    #[arg(long, default_value = "1000")]
+    max_jobs: usize,
+
+    /// Jobs queued or running at once; more submissions get 503
+    #[arg(long, default_value = "16")]
+    max_queue: usize,
+
+    /// Transcription model [default: gemini-2.5-flash]
+    #[arg(long, env = "FLASHECHO_MODEL")]
+    model: Option<String>,
+
+    /// Image model: 2.5-flash (default), 3pro
+    #[arg(long)]
+    image_model: Option<String>,
+
+    /// API timeout in seconds
+    #[arg(short, long, default_value = "600")]
+    timeout: u64,
+
+    #[command(flatten)]
+    common: CommonArgs,
+}
+
+/// Run with parsed arguments; shared by this binary and its `flashecho` subcommand
+pub async fn run(args: Args) -> Result<()> {
+    init_logging(args.common.verbose, args.common.log_format);
+    args.common.install_usage_ledger("serve");
+
+    let profile = args.common.load_profile()?;
+    let clients =
+        Clients::new(cli::gemini_api_key(&profile)?).context("Failed to create HTTP client")?;
+    let budget = args
+        .common
+        .budget()
+        .context("Failed to read usage ledger for --budget-since")?;
+    let model = args
+        .model
+        .clone()
+        .or_else(|| profile.model_for(Provider::Gemini).map(str::to_string))
+        .unwrap_or_else(|| Provider::Gemini.default_model().to_string());
+    let image_model = args
+        .image_model
+        .as_deref()
+        .or(profile.image_model.as_deref())
+        .unwrap_or("2.5-flash");
+    let image_model = ImageModel::from_str(image_model).map_err(|e| anyhow::anyhow!(e))?;
+
+    let backends = Backends {
+        gemini: clients.gemini(GeminiClientConfig {
+            timeout_secs: args.timeout,
+            max_retries: args.common.max_retries,
+            model,
+            cache: args.common.response_cache(),
+            budget: budget.clone(),
+            ..Default::default()
+        }),
+        file_api: clients.file_api(),
+        imagen: clients.imagen(ImagenClientConfig {
+            timeout_secs: args.timeout,
+            max_retries: args.common.max_retries,
+            model: image_model,
+            budget,
+            ..Default::default()
+        }),
+    };
+    let config = ServerConfig {
+        max_body_bytes: args.max_upload_mb.saturating_mul(1024 * 1024),
+        concurrency: args.concurrency.into(),
+        max_jobs: args.max_jobs,
+        max_pending: args.max_queue,
+        ..Default::default()
+    };
+
+    let server = Server::bind(args.listen, backends, config)
+        .await
+        .with_context(|| format!("Failed to listen on {}", args.listen))?;
+    let addr = server.local_addr()?;
+    info!("Listening on {}", addr);
+    if !args.common.quiet {
+        println!("Listening on http://{}", addr);
+    }
+    server.run().await.context("Server stopped")
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    run(Args::parse()).await
+}
@@ -0,0 +1,675 @@
+//! REST front end for the clients, so other services can transcribe audio
+//! and generate images over HTTP instead of shelling out to the CLIs.
+//! Enabled with the `server` feature and run by the `serve` binary.
+//!
+//! - `POST /transcribe`: `multipart/form-data` with the audio in a `file`
+//!   part and an optional `format` part (`json`, `srt`, `vtt`, `txt`)
+//! - `POST /images`: JSON `{"prompt", "aspect_ratio", "size", "count"}`
+//! - `GET /jobs/{id}`: status of a job and, once done, its result
+//! - `GET /metrics`: request metrics in the Prometheus text format (with the
+//!   `metrics` feature)
+//!
+//! Submissions answer `202 Accepted` with the job ID right away; jobs run in
+//! the background, at most `concurrency` at a time, and are kept in memory.
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::BufReader;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Semaphore;
+use tracing::{Instrument, debug, info, info_span, warn};
+
+use crate::file_api::FileApiClient;
+use crate::gemini_api::GeminiClient;
+use crate::http_server::{Request, read_request, write_response};
+use crate::imagen_api::{AspectRatio, ImageGenConfig, ImageSize, ImagenClient};
+use crate::transcript_format::{OutputFormat, format_transcript};
+
+/// Largest request body accepted by default (200MB)
+pub const DEFAULT_MAX_BODY_BYTES: usize = 200 * 1024 * 1024;
+
+/// Most images one `POST /images` may ask for
+const MAX_IMAGES_PER_JOB: u8 = 4;
+
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Larger requests are answered with `413 Payload Too Large`
+    pub max_body_bytes: usize,
+    /// Jobs running at the same time; the rest wait in the queue
+    pub concurrency: usize,
+    /// Finished jobs beyond this many are forgotten, oldest first
+    pub max_jobs: usize,
+    /// Jobs queued or running at once; submissions beyond this many get
+    /// `503 Service Unavailable`, since each holds its request body in memory
+    pub max_pending: usize,
+    /// Clients silent this long while sending a request get `408 Request Timeout`
+    pub read_timeout_secs: u64,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            concurrency: 2,
+            max_jobs: 1000,
+            max_pending: 16,
+            read_timeout_secs: 30,
+        }
+    }
+}
+
+/// Clients the jobs run on
+#[derive(Debug, Clone)]
+pub struct Backends {
+    pub gemini: GeminiClient,
+    /// Audio over the inline limit is uploaded here and deleted afterwards
+    pub file_api: FileApiClient,
+    /// Its response cache is not used
+    pub imagen: ImagenClient,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobKind {
+    Transcribe,
+    Images,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+impl JobStatus {
+    fn is_finished(self) -> bool {
+        matches!(self, JobStatus::Done | JobStatus::Failed)
+    }
+}
+
+/// A submitted job as returned by `GET /jobs/{id}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Job {
+    pub id: String,
+    pub kind: JobKind,
+    pub status: JobStatus,
+    /// Unix seconds
+    pub created_at: u64,
+    /// Transcribe: `transcript`, plus `format` and `content` for non-JSON
+    /// formats. Images: `images` with `mime_type`, base64 `data` and `provenance`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Jobs in submission order, keeping at most `max_jobs` finished ones
+#[derive(Debug, Default)]
+struct JobTable {
+    jobs: HashMap<String, Job>,
+    order: VecDeque<String>,
+}
+
+impl JobTable {
+    fn insert(&mut self, job: Job, max_jobs: usize) {
+        self.order.push_back(job.id.clone());
+        self.jobs.insert(job.id.clone(), job);
+
+        let mut finished = self
+            .order
+            .iter()
+            .filter(|id| self.jobs[*id].status.is_finished())
+            .count();
+        while finished > max_jobs {
+            let Some(pos) = self
+                .order
+                .iter()
+                .position(|id| self.jobs[id].status.is_finished())
+            else {
+                break;
+            };
+            if let Some(id) = self.order.remove(pos) {
+                self.jobs.remove(&id);
+            }
+            finished -= 1;
+        }
+    }
+
+    /// Jobs still queued or running
+    fn pending(&self) -> usize {
+        self.jobs
+            .values()
+            .filter(|job| !job.status.is_finished())
+            .count()
+    }
+
+    fn update(&mut self, id: &str, f: impl FnOnce(&mut Job)) {
+        if let Some(job) = self.jobs.get_mut(id) {
+            f(job);
+        }
+    }
+}
+
+struct State {
+    backends: Backends,
+    config: ServerConfig,
+    jobs: Mutex<JobTable>,
+    permits: Semaphore,
+}
+
+impl State {
+    fn jobs(&self) -> std::sync::MutexGuard<'_, JobTable> {
+        // A panic while holding the lock leaves the table usable
+        self.jobs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A bound listener; call [`run`](Self::run) to start answering requests
+pub struct Server {
+    listener: TcpListener,
+    state: Arc<State>,
+}
+
+impl Server {
+    pub async fn bind(
+        addr: SocketAddr,
+        mut backends: Backends,
+        config: ServerConfig,
+    ) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        // `count` asks for several generations of one prompt; a cache would
+        // answer all of them with the first image
+        backends.imagen = backends.imagen.without_cache();
+        let state = Arc::new(State {
+            permits: Semaphore::new(config.concurrency.max(1)),
+            backends,
+            config,
+            jobs: Mutex::default(),
+        });
+        Ok(Self { listener, state })
+    }
+
+    /// Address actually bound, e.g. the port picked for `127.0.0.1:0`
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Answer requests until the task is dropped
+    pub async fn run(self) -> io::Result<()> {
+        loop {
+            let (stream, peer) = self.listener.accept().await?;
+            let state = self.state.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle_connection(stream, state).await {
+                    debug!("Connection from {} failed: {}", peer, e);
+                }
+            });
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+    location: Option<String>,
+}
+
+impl Response {
+    fn json(status: u16, body: Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: body.to_string(),
+            location: None,
+        }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Self::json(status, json!({ "error": message.into() }))
+    }
+}
+
+async fn handle_connection(stream: TcpStream, state: Arc<State>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let read_timeout = Duration::from_secs(state.config.read_timeout_secs);
+    let response =
+        match read_request(&mut reader, state.config.max_body_bytes, read_timeout).await? {
+            Ok(request) => {
+                info!("{} {}", request.method, request.path);
+                route(&state, request)
+            }
+            Err(rejection) => Response::error(rejection.status, rejection.message),
+        };
+
+    let mut headers = vec![("Content-Type", response.content_type)];
+    if let Some(location) = &response.location {
+        headers.push(("Location", location));
+    }
+    write_response(
+        reader.get_mut(),
+        response.status,
+        &headers,
+        response.body.as_bytes(),
+    )
+    .await
+}
+
+fn route(state: &Arc<State>, request: Request) -> Response {
+    let method = request.method.as_str();
+    match request.path.as_str() {
+        "/transcribe" if method == "POST" => submit_transcription(state, &request),
+        "/images" if method == "POST" => submit_images(state, &request),
+        "/transcribe" | "/images" => Response::error(405, "Use POST"),
+        #[cfg(feature = "metrics")]
+        "/metrics" if method == "GET" => Response {
+            status: 200,
+            content_type: crate::metrics::CONTENT_TYPE,
+            body: crate::metrics::render(),
+            location: None,
+        },
+        #[cfg(feature = "metrics")]
+        "/metrics" => Response::error(405, "Use GET"),
+        path => match path.strip_prefix("/jobs/") {
+            Some(id) if method == "GET" => match state.jobs().jobs.get(id) {
+                Some(job) => Response::json(200, json!(job)),
+                None => Response::error(404, format!("No job {}", id)),
+            },
+            Some(_) => Response::error(405, "Use GET"),
+            None => Response::error(404, "Not found"),
+        },
+    }
+}
+
+fn submit_transcription(state: &Arc<State>, request: &Request) -> Response {
+    let content_type = request
+        .headers
+        .get("content-type")
+        .map(String::as_str)
+        .unwrap_or_default();
+    let Some(boundary) = multipart_boundary(content_type) else {
+        return Response::error(415, "Send multipart/form-data with a `file` part");
+    };
+    let Some(parts) = parse_multipart(&request.body, &boundary) else {
+        return Response::error(400, "Malformed multipart body");
+    };
+
+    let format = match parts.iter().find(|p| p.name == "format") {
+        Some(part) => {
+            let value = String::from_utf8_lossy(&part.data);
+            match <OutputFormat as clap::ValueEnum>::from_str(value.trim(), true) {
+                Ok(format) => format,
+                Err(_) => {
+                    return Response::error(
+                        400,
+                        format!(
+                            "Unknown format: {} (use json, jsonl, srt, vtt, txt, anki, audacity, markers or html)",
+                            value
+                        ),
+                    );
+                }
+            }
+        }
+        None => OutputFormat::Json,
+    };
+    let Some(file) = parts.into_iter().find(|p| p.name == "file") else {
+        return Response::error(400, "Missing `file` part");
+    };
+    if file.data.is_empty() {
+        return Response::error(400, "The `file` part is empty");
+    }
+    let mime_type = match file.content_type.as_deref() {
+        Some(mime) if mime.starts_with("audio/") || mime.starts_with("video/") => mime.to_string(),
+        _ => {
+            let name = file.filename.as_deref().unwrap_or_default();
+            GeminiClient::get_mime_type(Path::new(name)).to_string()
+        }
+    };
+
+    let backends = state.backends.clone();
+    submit(state, JobKind::Transcribe, async move {
+        transcribe(&backends, file.data, &mime_type, format).await
+    })
+}
+
+async fn transcribe(
+    backends: &Backends,
+    data: Vec<u8>,
+    mime_type: &str,
+    format: OutputFormat,
+) -> std::result::Result<Value, String> {
+    let size = data.len() as u64;
+    let transcript = if GeminiClient::requires_file_api(size) {
+        let file_info = backends
+            .file_api
+            .upload_file(data, mime_type, "upload")
+            .instrument(info_span!("stage", stage = "upload"))
+            .await
+            .map_err(|e| format!("File upload failed: {}", e))?;
+        let result = backends
+            .gemini
+            .transcribe_file_uri(&file_info.uri, mime_type)
+            .instrument(info_span!("stage", stage = "transcribe"))
+            .await;
+        if let Err(e) = backends.file_api.delete_file(&file_info.name).await {
+            warn!("Failed to delete remote file {}: {}", file_info.name, e);
+        }
+        result
+    } else {
+        backends
+            .gemini
+            .transcribe_audio(&data, mime_type)
+            .instrument(info_span!("stage", stage = "transcribe"))
+            .await
+    }
+    .map_err(|e| format!("Transcription failed: {}", e))?;
+
+    let mut result = json!({ "transcript": transcript });
+    if format != OutputFormat::Json {
+        let content = format_transcript(&transcript, format).map_err(|e| e.to_string())?;
+        result["format"] = json!(format.extension());
+        result["content"] = json!(content);
+    }
+    Ok(result)
+}
+
+/// Body of `POST /images`
+#[derive(Debug, Deserialize)]
+struct ImagesRequest {
+    prompt: String,
+    /// Aspect ratio, e.g. `16:9`
+    #[serde(default)]
+    aspect_ratio: Option<String>,
+    /// Gemini 3 Pro only: `1K`, `2K` or `4K`
+    #[serde(default)]
+    size: Option<String>,
+    #[serde(default = "one")]
+    count: u8,
+}
+
+fn one() -> u8 {
+    1
+}
+
+fn submit_images(state: &Arc<State>, request: &Request) -> Response {
+    let body: ImagesRequest = match serde_json::from_slice(&request.body) {
+        Ok(body) => body,
+        Err(e) => return Response::error(400, format!("Invalid JSON body: {}", e)),
+    };
+    if body.prompt.trim().is_empty() {
+        return Response::error(400, "`prompt` is empty");
+    }
+    if !(1..=MAX_IMAGES_PER_JOB).contains(&body.count) {
+        return Response::error(
+            400,
+            format!("`count` must be between 1 and {}", MAX_IMAGES_PER_JOB),
+        );
+    }
+    let mut gen_config = ImageGenConfig::new();
+    if let Some(aspect) = &body.aspect_ratio {
+        match AspectRatio::from_str(aspect) {
+            Ok(ratio) => gen_config = gen_config.with_aspect_ratio(ratio),
+            Err(e) => return Response::error(400, e),
+        }
+    }
+    if let Some(size) = &body.size {
+        match ImageSize::from_str(size) {
+            Ok(size) => gen_config = gen_config.with_size(size),
+            Err(e) => return Response::error(400, e),
+        }
+    }
+
+    let imagen = state.backends.imagen.clone();
+    submit(state, JobKind::Images, async move {
+        let mut images = Vec::with_capacity(body.count.into());
+        for _ in 0..body.count {
+            let image = imagen
+                .generate_image_with_config(&body.prompt, Some(&gen_config))
+                .instrument(info_span!("stage", stage = "generate"))
+                .await
+                .map_err(|e| format!("Image generation failed: {}", e))?;
+            images.push(json!({
+                "mime_type": image.mime_type,
+                "data": base64::engine::general_purpose::STANDARD.encode(&image.data),
+                "provenance": image.provenance,
+            }));
+        }
+        Ok(json!({ "images": images }))
+    })
+}
+
+/// Queue `work` as a new job and answer `202 Accepted` with its ID, or
+/// `503 Service Unavailable` when `max_pending` jobs are already waiting
+fn submit<F>(state: &Arc<State>, kind: JobKind, work: F) -> Response
+where
+    F: Future<Output = std::result::Result<Value, String>> + Send + 'static,
+{
+    let mut jobs = state.jobs();
+    if jobs.pending() >= state.config.max_pending {
+        return Response::error(
+            503,
+            format!(
+                "{} jobs are already queued or running; try again later",
+                state.config.max_pending
+            ),
+        );
+    }
+    let id = crate::request_id::next();
+    let created_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    jobs.insert(
+        Job {
+            id: id.clone(),
+            kind,
+            status: JobStatus::Queued,
+            created_at,
+            result: None,
+            error: None,
+        },
+        state.config.max_jobs,
+    );
+    drop(jobs);
+
+    let state = state.clone();
+    let job_id = id.clone();
+    let span = info_span!("job", id = %id, kind = ?kind);
+    tokio::spawn(
+        async move {
+            let Ok(_permit) = state.permits.acquire().await else {
+                return;
+            };
+            state
+                .jobs()
+                .update(&job_id, |job| job.status = JobStatus::Running);
+            let outcome = work.await;
+            match &outcome {
+                Ok(_) => info!("Job finished"),
+                Err(e) => warn!("Job failed: {}", e),
+            }
+            state.jobs().update(&job_id, |job| match outcome {
+                Ok(result) => {
+                    job.status = JobStatus::Done;
+                    job.result = Some(result);
+                }
+                Err(e) => {
+                    job.status = JobStatus::Failed;
+                    job.error = Some(e);
+                }
+            });
+        }
+        .instrument(span),
+    );
+
+    Response {
+        location: Some(format!("/jobs/{}", id)),
+        ..Response::json(202, json!({ "id": id, "status": JobStatus::Queued }))
+    }
+}
+
+/// One part of a `multipart/form-data` body
+#[derive(Debug, Clone, PartialEq)]
+struct FormPart {
+    name: String,
+    filename: Option<String>,
+    content_type: Option<String>,
+    data: Vec<u8>,
+}
+
+/// `boundary` parameter of a `multipart/form-data` content type
+fn multipart_boundary(content_type: &str) -> Option<String> {
+    let mut params = content_type.split(';');
+    if !params
+        .next()?
+        .trim()
+        .eq_ignore_ascii_case("multipart/form-data")
+    {
+        return None;
+    }
+    params.find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case("boundary")
+            .then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+/// Parts of a `multipart/form-data` body, or `None` if it is malformed
+fn parse_multipart(body: &[u8], boundary: &str) -> Option<Vec<FormPart>> {
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let separator = [b"\r\n".as_slice(), &delimiter].concat();
+    let mut pos = find(body, &delimiter, 0)? + delimiter.len();
+    let mut parts = Vec::new();
+
+    while !body[pos..].starts_with(b"--") {
+        pos += body[pos..].strip_prefix(b"\r\n").map(|_| 2)?;
+        let header_end = find(body, b"\r\n\r\n", pos)?;
+        let headers = std::str::from_utf8(&body[pos..header_end]).ok()?;
+        let data_start = header_end + 4;
+        let data_end = find(body, &separator, data_start)?;
+
+        let mut part = FormPart {
+            name: String::new(),
+            filename: None,
+            content_type: None,
+            data: body[data_start..data_end].to_vec(),
+        };
+        for line in headers.split("\r\n") {
+            let Some((name, value)) = line.split_once(':') else {
+                continue;
+            };
+            match name.trim().to_lowercase().as_str() {
+                "content-disposition" => {
+                    for param in value.split(';').skip(1) {
+                        let Some((key, value)) = param.split_once('=') else {
+                            continue;
+                        };
+                        let value = value.trim().trim_matches('"').to_string();
+                        match key.trim() {
+                            "name" => part.name = value,
+                            "filename" => part.filename = Some(value),
+                            _ => {}
+                        }
+                    }
+                }
+                "content-type" => part.content_type = Some(value.trim().to_lowercase()),
+                _ => {}
+            }
+        }
+        parts.push(part);
+        pos = data_end + separator.len();
+    }
+    Some(parts)
+}
+
+/// Index of the first `needle` in `haystack` at or after `from`
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|i| i + from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &[u8] = b"--XyZ\r\n\
+Content-Disposition: form-data; name=\"format\"\r\n\r\n\
+srt\r\n\
+--XyZ\r\n\
+Content-Disposition: form-data; name=\"file\"; filename=\"talk.mp3\"\r\n\
+Content-Type: audio/mpeg\r\n\r\n\
+ID3\r\n--X\x00\xff\r\n\
+--XyZ--\r\n";
+
+    #[test]
+    fn test_multipart_boundary() {
+        assert_eq!(
+            multipart_boundary("multipart/form-data; boundary=XyZ").as_deref(),
+            Some("XyZ")
+        );
+        assert_eq!(
+            multipart_boundary("Multipart/Form-Data; charset=utf-8; boundary=\"a b\"").as_deref(),
+            Some("a b")
+        );
+        assert_eq!(multipart_boundary("application/json"), None);
+        assert_eq!(multipart_boundary("multipart/form-data"), None);
+    }
+
+    #[test]
+    fn test_parse_multipart() {
+        let parts = parse_multipart(BODY, "XyZ").unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].name, "format");
+        assert_eq!(parts[0].data, b"srt");
+        assert_eq!(parts[0].filename, None);
+        assert_eq!(parts[1].name, "file");
+        assert_eq!(parts[1].filename.as_deref(), Some("talk.mp3"));
+        assert_eq!(parts[1].content_type.as_deref(), Some("audio/mpeg"));
+        assert_eq!(parts[1].data, b"ID3\r\n--X\x00\xff");
+
+        assert_eq!(parse_multipart(b"--XyZ--\r\n", "XyZ").unwrap(), []);
+        assert!(parse_multipart(b"--XyZ\r\nno headers end", "XyZ").is_none());
+        assert!(parse_multipart(BODY, "other").is_none());
+    }
+
+    #[test]
+    fn test_job_table_forgets_oldest_finished_jobs() {
+        let job = |id: &str, status| Job {
+            id: id.to_string(),
+            kind: JobKind::Images,
+            status,
+            created_at: 0,
+            result: None,
+            error: None,
+        };
+        let mut table = JobTable::default();
+        table.insert(job("a", JobStatus::Done), 2);
+        table.insert(job("b", JobStatus::Running), 2);
+        table.insert(job("c", JobStatus::Failed), 2);
+        table.insert(job("d", JobStatus::Done), 2);
+        assert_eq!(table.order, ["b", "c", "d"]);
+        assert!(!table.jobs.contains_key("a"));
+
+        assert_eq!(table.pending(), 1);
+
+        table.update("b", |job| job.status = JobStatus::Done);
+        assert_eq!(table.jobs["b"].status, JobStatus::Done);
+        assert_eq!(table.pending(), 0);
+    }
+}
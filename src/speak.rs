@@ -0,0 +1,500 @@
+use anyhow::{Context, Result};
+use clap::{Parser, ValueEnum};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::str::FromStr;
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::process::Command;
+use tracing::{Instrument, debug, info, info_span};
+
+use transcript_tool::cli::{self, CommonArgs, init_logging};
+use transcript_tool::storage::{OutputStorage, Storage};
+use transcript_tool::tts_api::{VOICES, parse_script};
+use transcript_tool::{
+    DialogueLine, SpeechAudio, SpeechConfig, SpeechModel, TranscriptResponse, TtsClient,
+    TtsClientConfig,
+};
+
+#[derive(Parser, Debug)]
+#[command(name = "speak")]
+#[command(version)]
+#[command(about = "Turn text, SSML or a transcript into speech using Gemini TTS")]
+#[command(after_help = "EXAMPLES:
+    speak --text \"Hello and welcome\" -o hello.wav
+    speak -i notes.txt --voice Puck -f mp3
+    speak -i dialogue.txt --speaker-voice Alice=Kore --speaker-voice Bob=Charon
+    speak -i talk.json --translation -o talk-en.mp3
+    speak -i intro.ssml --style \"Say warmly\"
+    speak --list-voices")]
+pub struct Args {
+    /// Text, SSML (.ssml/.xml) or transcript JSON file (`-` for stdin).
+    /// Text written as `Speaker: line` is read as a dialogue.
+    #[arg(short, long, conflicts_with = "text")]
+    input: Option<PathBuf>,
+
+    /// Text to speak, instead of --input
+    #[arg(long)]
+    text: Option<String>,
+
+    /// Output file path (`-` for stdout), or an s3:// / gs:// URI
+    /// [default: <input>.<format>, or speech.<format> for --text]
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Audio format [default: from the output extension, else wav]
+    #[arg(short, long, value_enum)]
+    format: Option<AudioFormat>,
+
+    /// Speech model: flash (default), pro
+    #[arg(short = 'm', long, env = "FLASHECHO_SPEECH_MODEL")]
+    model: Option<String>,
+
+    /// Voice for single-speaker text (see --list-voices) [default: Kore]
+    #[arg(long)]
+    voice: Option<String>,
+
+    /// Voice for a dialogue speaker as SPEAKER=VOICE; repeatable.
+    /// Other speakers get distinct default voices
+    #[arg(long, value_name = "SPEAKER=VOICE")]
+    speaker_voice: Vec<String>,
+
+    /// Delivery instruction put before the text, e.g. "Say cheerfully"
+    #[arg(long)]
+    style: Option<String>,
+
+    /// For transcript JSON: speak each segment's English translation when it has one
+    #[arg(long)]
+    translation: bool,
+
+    /// Print the available voices and exit
+    #[arg(long)]
+    list_voices: bool,
+
+    /// API timeout in seconds
+    #[arg(short, long, default_value = "120")]
+    timeout: u64,
+
+    #[command(flatten)]
+    common: CommonArgs,
+}
+
+/// Output audio encoding
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum AudioFormat {
+    Wav,
+    /// Encoded with ffmpeg (libmp3lame)
+    Mp3,
+}
+
+impl AudioFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            AudioFormat::Wav => "wav",
+            AudioFormat::Mp3 => "mp3",
+        }
+    }
+
+    /// Format matching a file name's extension, if it is one we write
+    fn from_path(path: &str) -> Option<Self> {
+        let ext = Path::new(path).extension()?.to_str()?;
+        AudioFormat::from_str(ext, true).ok()
+    }
+}
+
+/// What to read aloud
+#[derive(Debug, PartialEq, Eq)]
+enum Speech {
+    Text(String),
+    Dialogue(Vec<DialogueLine>),
+}
+
+fn parse_model(model_str: &str) -> Result<SpeechModel> {
+    SpeechModel::from_str(model_str).map_err(|e| anyhow::anyhow!("{}", e))
+}
+
+/// Parse `--speaker-voice SPEAKER=VOICE` values into `speech`
+fn with_speaker_voices(mut speech: SpeechConfig, values: &[String]) -> Result<SpeechConfig> {
+    for value in values {
+        let (speaker, voice) = value
+            .rsplit_once('=')
+            .filter(|(speaker, voice)| !speaker.trim().is_empty() && !voice.trim().is_empty())
+            .with_context(|| format!("Invalid --speaker-voice '{}': use SPEAKER=VOICE", value))?;
+        speech = speech.with_speaker_voice(speaker.trim(), voice.trim());
+    }
+    Ok(speech)
+}
+
+fn is_ssml(path: Option<&Path>, content: &str) -> bool {
+    let by_extension = path
+        .and_then(|p| p.extension())
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| matches!(ext.to_lowercase().as_str(), "ssml" | "xml"));
+    by_extension || content.trim_start().starts_with("<speak")
+}
+
+fn is_json(path: Option<&Path>) -> bool {
+    path.and_then(|p| p.extension())
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+}
+
+/// Plain text of an SSML document. Gemini TTS takes no markup: breaks become
+/// pauses ("..."), paragraph and sentence ends become line breaks, and other
+/// tags are dropped.
+fn ssml_to_text(ssml: &str) -> String {
+    let mut text = String::with_capacity(ssml.len());
+    let mut rest = ssml;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            rest = "";
+            break;
+        };
+        let tag = rest[start + 1..start + end].trim();
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default();
+        match name {
+            "break" => text.push_str(" ... "),
+            "p" | "s" if tag.starts_with('/') => text.push('\n'),
+            _ => {}
+        }
+        rest = &rest[start + end + 1..];
+    }
+    text.push_str(rest);
+
+    let text = text
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&");
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Dialogue from a transcript's segments, merging consecutive segments of a speaker
+fn transcript_dialogue(transcript: &TranscriptResponse, translation: bool) -> Vec<DialogueLine> {
+    let mut lines: Vec<DialogueLine> = Vec::new();
+    for segment in &transcript.segments {
+        let text = match &segment.translation {
+            Some(translated) if translation && !translated.trim().is_empty() => translated,
+            _ => &segment.content,
+        };
+        let text = text.trim();
+        if text.is_empty() {
+            continue;
+        }
+        match lines.last_mut() {
+            Some(last) if last.speaker == segment.speaker => {
+                last.text.push(' ');
+                last.text.push_str(text);
+            }
+            _ => lines.push(DialogueLine::new(segment.speaker.clone(), text)),
+        }
+    }
+    lines
+}
+
+/// Decide how `content`, read from `path`, should be spoken
+fn parse_input(path: Option<&Path>, content: &str, translation: bool) -> Result<Speech> {
+    if is_json(path) {
+        let transcript = TranscriptResponse::from_json_any_version(content)
+            .context("Failed to parse transcript JSON")?;
+        return Ok(Speech::Dialogue(transcript_dialogue(
+            &transcript,
+            translation,
+        )));
+    }
+
+    let text = if is_ssml(path, content) {
+        ssml_to_text(content)
+    } else {
+        content.trim().to_string()
+    };
+    Ok(match parse_script(&text) {
+        Some(lines) => Speech::Dialogue(lines),
+        None => Speech::Text(text),
+    })
+}
+
+async fn read_input(path: &Path) -> Result<String> {
+    if path == Path::new("-") {
+        let mut content = String::new();
+        tokio::io::stdin()
+            .read_to_string(&mut content)
+            .await
+            .context("Failed to read stdin")?;
+        return Ok(content);
+    }
+    fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Failed to read input file {:?}", path))
+}
+
+/// Encode `wav` as MP3 with ffmpeg
+async fn encode_mp3(wav: Vec<u8>) -> Result<Vec<u8>> {
+    let mut child = Command::new("ffmpeg")
+        .args([
+            "-hide_banner",
+            "-loglevel",
+            "error",
+            "-f",
+            "wav",
+            "-i",
+            "pipe:0",
+        ])
+        .args(["-codec:a", "libmp3lame", "-q:a", "2", "-f", "mp3", "pipe:1"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to execute ffmpeg. Is ffmpeg installed?")?;
+
+    // Feed stdin from a separate task so a full stdout pipe can't deadlock us
+    let mut stdin = child.stdin.take().context("Failed to open ffmpeg stdin")?;
+    let writer = tokio::spawn(async move {
+        stdin.write_all(&wav).await?;
+        stdin.shutdown().await
+    });
+
+    let output = child.wait_with_output().await?;
+    writer.await??;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("ffmpeg failed: {}", stderr.trim());
+    }
+    Ok(output.stdout)
+}
+
+async fn synthesize(
+    client: &TtsClient,
+    speech: &Speech,
+    config: &SpeechConfig,
+    quiet: bool,
+) -> Result<SpeechAudio> {
+    let pb = cli::spinner(quiet, "Generating speech...".into());
+
+    let audio = match speech {
+        Speech::Text(text) => client.synthesize_with_config(text, config).await,
+        Speech::Dialogue(lines) => client.synthesize_dialogue(lines, config).await,
+    }
+    .map_err(|e| anyhow::anyhow!("Speech generation failed: {}", e))?;
+
+    if let Some(pb) = pb {
+        pb.finish_with_message(format!("Speech generated ({:.1}s)", audio.duration_secs()));
+    }
+    Ok(audio)
+}
+
+/// Run with parsed arguments; shared by this binary and its `flashecho` subcommand
+pub async fn run(args: Args) -> Result<()> {
+    init_logging(args.common.verbose, args.common.log_format);
+
+    if args.list_voices {
+        for voice in VOICES {
+            println!("{}", voice);
+        }
+        return Ok(());
+    }
+
+    args.common.install_usage_ledger("speak");
+    let label = match (&args.input, &args.text) {
+        (Some(input), _) => input.display().to_string(),
+        (None, Some(_)) => "text".to_string(),
+        (None, None) => anyhow::bail!(
+            "Either --input or --text must be provided\n\nUsage:\n  speak -i script.txt\n  speak --text \"Hello\""
+        ),
+    };
+    let span = info_span!("file", file = %label);
+    speak(args).instrument(span).await
+}
+
+async fn speak(args: Args) -> Result<()> {
+    let content = match (&args.input, &args.text) {
+        (Some(input), _) => read_input(input).await?,
+        (None, text) => text.clone().unwrap_or_default(),
+    };
+    let speech = parse_input(args.input.as_deref(), &content, args.translation)?;
+    if let Speech::Dialogue(lines) = &speech {
+        debug!("Read {} dialogue turns", lines.len());
+    }
+
+    let to_stdout = args.output.as_deref() == Some("-");
+    let format = args
+        .format
+        .or_else(|| args.output.as_deref().and_then(AudioFormat::from_path))
+        .unwrap_or(AudioFormat::Wav);
+
+    let profile = args.common.load_profile()?;
+    let model_name = args.model.as_deref().or(profile.speech_model.as_deref());
+    let model = parse_model(model_name.unwrap_or("flash"))?;
+    let budget = args
+        .common
+        .budget()
+        .context("Failed to read usage ledger for --budget-since")?;
+
+    let mut config = SpeechConfig::new();
+    if let Some(voice) = &args.voice {
+        config = config.with_voice(voice);
+    }
+    if let Some(style) = &args.style {
+        config = config.with_style(style);
+    }
+    let config = with_speaker_voices(config, &args.speaker_voice)?;
+
+    let client = TtsClient::with_config(
+        cli::gemini_api_key(&profile)?,
+        TtsClientConfig {
+            timeout_secs: args.timeout,
+            max_retries: args.common.max_retries,
+            model,
+            cache: args.common.response_cache(),
+            budget,
+            ..Default::default()
+        },
+    )
+    .map_err(|e| anyhow::anyhow!("Failed to create TTS client: {}", e))?;
+
+    let audio = synthesize(&client, &speech, &config, args.common.quiet || to_stdout)
+        .instrument(info_span!("stage", stage = "synthesize"))
+        .await?;
+
+    let data = match format {
+        AudioFormat::Wav => audio.to_wav(),
+        AudioFormat::Mp3 => encode_mp3(audio.to_wav())
+            .instrument(info_span!("stage", stage = "encode"))
+            .await
+            .context("Failed to encode MP3")?,
+    };
+
+    if to_stdout {
+        let mut stdout = tokio::io::stdout();
+        stdout
+            .write_all(&data)
+            .await
+            .context("Failed to write audio to stdout")?;
+        stdout.flush().await.context("Failed to flush stdout")?;
+        return Ok(());
+    }
+
+    let default_output = match &args.input {
+        Some(input) if input != Path::new("-") => input.with_extension(format.extension()),
+        _ => PathBuf::from(format!("speech.{}", format.extension())),
+    };
+    let default_name = default_output
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let target = args
+        .output
+        .clone()
+        .unwrap_or_else(|| default_output.to_string_lossy().to_string());
+    let (storage, key) = OutputStorage::open_file(&target, &default_name)
+        .with_context(|| format!("Invalid output target: {}", target))?;
+
+    storage
+        .put(&key, &data)
+        .instrument(info_span!("stage", stage = "write"))
+        .await
+        .context("Failed to write audio file")?;
+    let location = storage.location(&key);
+    info!("Speech written to {}", location);
+    if !args.common.quiet {
+        println!("Speech saved to {}", location);
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    run(Args::parse()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ssml_to_text() {
+        let ssml = r#"<speak><p>Hello &amp; welcome.<break time="500ms"/>Let's begin.</p>
+            <p><s>Second <emphasis>part</emphasis>.</s></p></speak>"#;
+        assert_eq!(
+            ssml_to_text(ssml),
+            "Hello & welcome. ... Let's begin.\nSecond part."
+        );
+    }
+
+    #[test]
+    fn test_parse_input() {
+        assert_eq!(
+            parse_input(None, "  Hello there.  ", false).unwrap(),
+            Speech::Text("Hello there.".to_string())
+        );
+        assert_eq!(
+            parse_input(Some(Path::new("a.txt")), "A: Hi\nB: Hey", false).unwrap(),
+            Speech::Dialogue(vec![
+                DialogueLine::new("A", "Hi"),
+                DialogueLine::new("B", "Hey")
+            ])
+        );
+        assert_eq!(
+            parse_input(Some(Path::new("a.ssml")), "<speak>Hi</speak>", false).unwrap(),
+            Speech::Text("Hi".to_string())
+        );
+        assert!(parse_input(Some(Path::new("a.json")), "not json", false).is_err());
+    }
+
+    #[test]
+    fn test_transcript_dialogue() {
+        let json = r#"{
+            "summary": "",
+            "segments": [
+                {"speaker": "A", "timestamp": "00:00", "content": "Bonjour.", "language": "French",
+                 "language_code": "fr", "translation": "Hello.", "emotion": "happy"},
+                {"speaker": "A", "timestamp": "00:02", "content": "Ça va ?", "language": "French",
+                 "language_code": "fr", "translation": "How are you?", "emotion": "neutral"},
+                {"speaker": "B", "timestamp": "00:04", "content": "Fine.", "language": "English",
+                 "language_code": "en", "emotion": "neutral"}
+            ]
+        }"#;
+        let transcript: TranscriptResponse = serde_json::from_str(json).unwrap();
+
+        let original = transcript_dialogue(&transcript, false);
+        assert_eq!(
+            original,
+            vec![
+                DialogueLine::new("A", "Bonjour. Ça va ?"),
+                DialogueLine::new("B", "Fine.")
+            ]
+        );
+
+        let dubbed = transcript_dialogue(&transcript, true);
+        assert_eq!(dubbed[0].text, "Hello. How are you?");
+        assert_eq!(dubbed[1].text, "Fine.");
+    }
+
+    #[test]
+    fn test_speaker_voices_and_format() {
+        let config = with_speaker_voices(
+            SpeechConfig::new(),
+            &["Alice=Kore".to_string(), "Dr. Who = Puck".to_string()],
+        )
+        .unwrap();
+        assert_eq!(config.speaker_voices[1].speaker, "Dr. Who");
+        assert_eq!(config.speaker_voices[1].voice, "Puck");
+        assert!(with_speaker_voices(SpeechConfig::new(), &["Alice".to_string()]).is_err());
+        assert!(with_speaker_voices(SpeechConfig::new(), &["=Kore".to_string()]).is_err());
+
+        assert_eq!(AudioFormat::from_path("out.MP3"), Some(AudioFormat::Mp3));
+        assert_eq!(AudioFormat::from_path("out.wav"), Some(AudioFormat::Wav));
+        assert_eq!(AudioFormat::from_path("out.ogg"), None);
+    }
+}
@@ -0,0 +1,419 @@
+//! Where transcripts and generated images are written: a local directory or
+//! an object store, picked from the output target by [`OutputStorage::open`]:
+//!
+//! - `s3://bucket/prefix/`: Amazon S3 or an S3-compatible store ([`S3Storage`])
+//! - `gs://bucket/prefix/`: Google Cloud Storage ([`GcsStorage`])
+//! - anything else: a local path ([`LocalStorage`])
+//!
+//! The object stores need the `cloud-storage` feature.
+
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use tokio::io::AsyncWriteExt;
+
+use crate::integrity::{checksum_key, checksum_line};
+
+#[cfg(feature = "cloud-storage")]
+mod gcs;
+#[cfg(feature = "cloud-storage")]
+mod s3;
+
+#[cfg(feature = "cloud-storage")]
+pub use gcs::{GcsAuth, GcsStorage};
+#[cfg(feature = "cloud-storage")]
+pub use s3::{S3Config, S3Storage};
+
+/// Timeout of one object store request
+#[cfg(feature = "cloud-storage")]
+const STORAGE_TIMEOUT_SECS: u64 = 300;
+
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error("Invalid storage URI '{0}': expected s3://bucket/prefix/ or gs://bucket/prefix/")]
+    InvalidUri(String),
+
+    #[error("{0} output needs the `cloud-storage` feature")]
+    Unsupported(&'static str),
+
+    #[error("Missing {service} credentials: {message}")]
+    Credentials {
+        service: &'static str,
+        message: String,
+    },
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Network error: {0}")]
+    Network(#[from] reqwest::Error),
+
+    #[error("{service} error ({status}) for {location}: {message}")]
+    Api {
+        service: &'static str,
+        status: u16,
+        location: String,
+        message: String,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, StorageError>;
+
+/// A place output files are written to. Keys are `/`-separated paths
+/// relative to the storage root.
+pub trait Storage {
+    /// Write `data` to `key`, replacing any existing file or object
+    fn put(&self, key: &str, data: &[u8]) -> impl Future<Output = Result<()>> + Send;
+
+    /// Whether `key` exists
+    fn exists(&self, key: &str) -> impl Future<Output = Result<bool>> + Send;
+
+    /// Path or URI of `key`, for messages and `--json` results
+    fn location(&self, key: &str) -> String;
+}
+
+/// Write `data` to `path` through a hidden temp file in the same directory
+/// that is renamed over `path` once complete, so a crash or a failed write
+/// never leaves a partial file that skip detection would take as finished
+pub async fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let tmp = path.with_file_name(format!(".{}.{}.tmp", name, crate::request_id::next()));
+    let result = async {
+        let mut file = tokio::fs::File::create(&tmp).await?;
+        file.write_all(data).await?;
+        file.sync_all().await?;
+        drop(file);
+        tokio::fs::rename(&tmp, path).await
+    }
+    .await;
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(&tmp).await;
+    }
+    result
+}
+
+/// Files under a local directory
+#[derive(Debug, Clone)]
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    /// Storage rooted at `root`; an empty path means the current directory
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.root.join(key)
+    }
+}
+
+impl Storage for LocalStorage {
+    async fn put(&self, key: &str, data: &[u8]) -> Result<()> {
+        let path = self.path(key);
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        write_atomic(&path, data).await?;
+        Ok(())
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool> {
+        Ok(tokio::fs::try_exists(self.path(key)).await?)
+    }
+
+    fn location(&self, key: &str) -> String {
+        self.path(key).display().to_string()
+    }
+}
+
+/// Any of the storage backends, selected at runtime from an output target
+#[derive(Debug, Clone)]
+pub enum OutputStorage {
+    Local(LocalStorage),
+    #[cfg(feature = "cloud-storage")]
+    S3(S3Storage),
+    #[cfg(feature = "cloud-storage")]
+    Gcs(GcsStorage),
+}
+
+/// Whether `target` is an object store URI rather than a local path
+pub fn is_remote(target: &str) -> bool {
+    target.starts_with("s3://") || target.starts_with("gs://")
+}
+
+/// Split `s3://bucket/prefix` into the bucket and the prefix (with a
+/// trailing `/` unless empty)
+#[cfg_attr(not(feature = "cloud-storage"), allow(dead_code))]
+fn split_bucket(uri: &str, scheme: &str) -> Result<(String, String)> {
+    let rest = &uri[scheme.len()..];
+    let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+    if bucket.is_empty() {
+        return Err(StorageError::InvalidUri(uri.to_string()));
+    }
+    let prefix = prefix.trim_matches('/');
+    let prefix = if prefix.is_empty() {
+        String::new()
+    } else {
+        format!("{}/", prefix)
+    };
+    Ok((bucket.to_string(), prefix))
+}
+
+impl OutputStorage {
+    /// Storage rooted at `target`: a local directory, or an object store
+    /// bucket and prefix. Object store credentials are read from the
+    /// environment here, so missing ones fail before any work starts.
+    pub fn open(target: &str) -> Result<Self> {
+        if let Some(scheme) = ["s3://", "gs://"]
+            .into_iter()
+            .find(|s| target.starts_with(s))
+        {
+            return Self::open_remote(target, scheme);
+        }
+        Ok(Self::Local(LocalStorage::new(target)))
+    }
+
+    #[cfg(feature = "cloud-storage")]
+    fn open_remote(target: &str, scheme: &str) -> Result<Self> {
+        let (bucket, prefix) = split_bucket(target, scheme)?;
+        Ok(match scheme {
+            "s3://" => Self::S3(S3Storage::new(bucket, prefix, S3Config::from_env()?)?),
+            _ => Self::Gcs(GcsStorage::new(bucket, prefix, GcsAuth::from_env()?)?),
+        })
+    }
+
+    #[cfg(not(feature = "cloud-storage"))]
+    fn open_remote(_target: &str, scheme: &str) -> Result<Self> {
+        Err(StorageError::Unsupported(match scheme {
+            "s3://" => "S3",
+            _ => "GCS",
+        }))
+    }
+
+    /// Send object store requests with `http` rather than the backend's own
+    /// default client, e.g. [`Clients::http_client`] so the proxy, TLS,
+    /// transport and timeout settings of the API clients apply to uploads
+    /// too. Local storage is unaffected.
+    ///
+    /// [`Clients::http_client`]: crate::Clients::http_client
+    #[cfg_attr(not(feature = "cloud-storage"), allow(unused_variables))]
+    pub fn with_http_client(self, http: reqwest::Client) -> Self {
+        match self {
+            #[cfg(feature = "cloud-storage")]
+            Self::S3(storage) => Self::S3(storage.with_http_client(http)),
+            #[cfg(feature = "cloud-storage")]
+            Self::Gcs(storage) => Self::Gcs(storage.with_http_client(http)),
+            local @ Self::Local(_) => local,
+        }
+    }
+
+    /// Write the `b3sum` line of `data`, just written to `key`, to
+    /// `<key>.blake3`
+    pub async fn put_checksum(&self, key: &str, data: &[u8]) -> Result<()> {
+        let name = key.rsplit('/').next().unwrap_or(key);
+        self.put(&checksum_key(key), checksum_line(data, name).as_bytes())
+            .await
+    }
+
+    /// Write each `(key, data)` output and, with `checksums`, its sidecar.
+    /// All but the last are written concurrently, and the last only once
+    /// they succeed, so when it exists (as skip detection checks) the whole
+    /// set does.
+    pub async fn put_all(&self, outputs: &[(String, String)], checksums: bool) -> Result<()> {
+        let Some(((last_key, last_data), rest)) = outputs.split_last() else {
+            return Ok(());
+        };
+        futures::future::try_join_all(
+            rest.iter()
+                .map(|(key, data)| self.put_with_checksum(key, data, checksums)),
+        )
+        .await?;
+        self.put_with_checksum(last_key, last_data, checksums).await
+    }
+
+    async fn put_with_checksum(&self, key: &str, data: &str, checksum: bool) -> Result<()> {
+        self.put(key, data.as_bytes()).await?;
+        if checksum {
+            self.put_checksum(key, data.as_bytes()).await?;
+        }
+        Ok(())
+    }
+
+    /// Storage for a single output file: the file's directory (or bucket
+    /// prefix) and its key. A target ending in `/` is a directory, and
+    /// `default_name` is used as the key.
+    pub fn open_file(target: &str, default_name: &str) -> Result<(Self, String)> {
+        if target.ends_with('/') {
+            return Ok((Self::open(target)?, default_name.to_string()));
+        }
+        if is_remote(target) {
+            let (dir, key) = target.rsplit_once('/').unwrap_or_default();
+            // `s3://bucket` names a bucket, not a file
+            if dir.ends_with(':') || dir.ends_with(":/") {
+                return Err(StorageError::InvalidUri(target.to_string()));
+            }
+            return Ok((Self::open(dir)?, key.to_string()));
+        }
+
+        let path = Path::new(target);
+        let key = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| default_name.to_string());
+        let dir = path.parent().unwrap_or(Path::new(""));
+        Ok((Self::Local(LocalStorage::new(dir)), key))
+    }
+}
+
+impl Storage for OutputStorage {
+    async fn put(&self, key: &str, data: &[u8]) -> Result<()> {
+        match self {
+            Self::Local(storage) => storage.put(key, data).await,
+            #[cfg(feature = "cloud-storage")]
+            Self::S3(storage) => storage.put(key, data).await,
+            #[cfg(feature = "cloud-storage")]
+            Self::Gcs(storage) => storage.put(key, data).await,
+        }
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool> {
+        match self {
+            Self::Local(storage) => storage.exists(key).await,
+            #[cfg(feature = "cloud-storage")]
+            Self::S3(storage) => storage.exists(key).await,
+            #[cfg(feature = "cloud-storage")]
+            Self::Gcs(storage) => storage.exists(key).await,
+        }
+    }
+
+    fn location(&self, key: &str) -> String {
+        match self {
+            Self::Local(storage) => storage.location(key),
+            #[cfg(feature = "cloud-storage")]
+            Self::S3(storage) => storage.location(key),
+            #[cfg(feature = "cloud-storage")]
+            Self::Gcs(storage) => storage.location(key),
+        }
+    }
+}
+
+/// `Content-Type` of an output file, from its extension
+#[cfg_attr(not(feature = "cloud-storage"), allow(dead_code))]
+fn content_type(key: &str) -> &'static str {
+    let extension = key
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("json") => "application/json",
+        Some("jsonl") => "application/jsonl",
+        Some("srt") => "application/x-subrip",
+        Some("vtt") => "text/vtt",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("tsv") => "text/tab-separated-values; charset=utf-8",
+        Some("csv") => "text/csv; charset=utf-8",
+        Some("html") => "text/html; charset=utf-8",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("webp") => "image/webp",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Percent-encode everything but unreserved characters (and `/` if
+/// `keep_slash`), as URL paths and AWS canonical URIs need
+#[cfg(feature = "cloud-storage")]
+fn percent_encode(text: &str, keep_slash: bool) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            b'/' if keep_slash => "/".to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(feature = "cloud-storage")]
+fn unix_time() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_bucket() {
+        assert_eq!(
+            split_bucket("s3://bucket/a/b/", "s3://").unwrap(),
+            ("bucket".to_string(), "a/b/".to_string())
+        );
+        assert_eq!(
+            split_bucket("gs://bucket", "gs://").unwrap(),
+            ("bucket".to_string(), String::new())
+        );
+        assert!(split_bucket("s3:///prefix", "s3://").is_err());
+    }
+
+    #[test]
+    fn test_open_file_local() {
+        let (storage, key) = OutputStorage::open_file("out/talk.srt", "x.srt").unwrap();
+        assert_eq!(key, "talk.srt");
+        assert_eq!(
+            storage.location(&key),
+            Path::new("out").join("talk.srt").display().to_string()
+        );
+
+        let (storage, key) = OutputStorage::open_file("talk.srt", "x.srt").unwrap();
+        assert_eq!(storage.location(&key), "talk.srt");
+
+        let (_, key) = OutputStorage::open_file("out/", "x.srt").unwrap();
+        assert_eq!(key, "x.srt");
+    }
+
+    #[cfg(feature = "cloud-storage")]
+    #[test]
+    fn test_percent_encode() {
+        assert_eq!(percent_encode("out/my talk.srt", true), "out/my%20talk.srt");
+        assert_eq!(
+            percent_encode("out/my talk.srt", false),
+            "out%2Fmy%20talk.srt"
+        );
+    }
+
+    #[test]
+    fn test_content_type() {
+        assert_eq!(content_type("a/talk.SRT"), "application/x-subrip");
+        assert_eq!(content_type("image.jpeg"), "image/jpeg");
+        assert_eq!(content_type("talk.jsonl"), "application/jsonl");
+        assert_eq!(content_type("talk.html"), "text/html; charset=utf-8");
+        assert_eq!(content_type("talk.markers.csv"), "text/csv; charset=utf-8");
+        assert_eq!(content_type("noext"), "application/octet-stream");
+    }
+
+    #[tokio::test]
+    async fn test_local_put_and_exists() {
+        let root = std::env::temp_dir().join(format!("flashecho_storage_{}", std::process::id()));
+        let storage = LocalStorage::new(&root);
+
+        assert!(!storage.exists("nested/a.txt").await.unwrap());
+        storage.put("nested/a.txt", b"hello").await.unwrap();
+        assert!(storage.exists("nested/a.txt").await.unwrap());
+        assert_eq!(std::fs::read(root.join("nested/a.txt")).unwrap(), b"hello");
+
+        // Replaced in one step, with no temp file left behind
+        storage.put("nested/a.txt", b"bye").await.unwrap();
+        assert_eq!(std::fs::read(root.join("nested/a.txt")).unwrap(), b"bye");
+        assert_eq!(std::fs::read_dir(root.join("nested")).unwrap().count(), 1);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,286 @@
+use anyhow::{Context, Result};
+use clap::{Args as ClapArgs, Parser, Subcommand};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tracing::{Instrument, debug, info, info_span};
+
+use transcript_tool::cli::{LogFormat, init_logging};
+use transcript_tool::storage::{OutputStorage, Storage};
+use transcript_tool::subtitle::parse_timestamp;
+use transcript_tool::{SubtitleFormat, Subtitles};
+
+#[derive(Parser, Debug)]
+#[command(name = "subs")]
+#[command(version)]
+#[command(about = "Fix the timing of SRT and VTT subtitles")]
+#[command(after_help = "EXAMPLES:
+    subs shift -i talk.srt --by -2.5
+    subs shift -i talk.vtt --by 1500ms -o talk.fixed.vtt
+    subs rescale -i talk.srt --anchor 00:00:05,000=00:00:04,200 --anchor 01:10:00,000=01:08:30,000
+    cat talk.srt | subs shift -i - --by 3 -o -")]
+pub struct Args {
+    #[command(subcommand)]
+    command: SubsCommand,
+
+    /// Verbosity level (-v, -vv, -vvv)
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Log output format: human-readable text, or one JSON object per line
+    #[arg(
+        long,
+        value_enum,
+        default_value = "text",
+        env = "FLASHECHO_LOG_FORMAT",
+        global = true
+    )]
+    log_format: LogFormat,
+}
+
+#[derive(Subcommand, Debug)]
+enum SubsCommand {
+    /// Move every cue by a constant offset
+    Shift {
+        #[command(flatten)]
+        io: IoArgs,
+
+        /// Offset: seconds (`-2.5`, `3s`), milliseconds (`1500ms`) or a
+        /// timestamp (`-00:00:01,500`); negative moves cues earlier
+        #[arg(
+            long,
+            value_name = "OFFSET",
+            allow_hyphen_values = true,
+            value_parser = parse_offset
+        )]
+        by: i64,
+    },
+    /// Stretch timing linearly between two anchor points, for drift that
+    /// grows over the file
+    Rescale {
+        #[command(flatten)]
+        io: IoArgs,
+
+        /// OLD=NEW: the time a line is shown at now, and when it should be
+        /// shown. Give exactly two, e.g. one near the start and one near the end
+        #[arg(
+            long,
+            value_name = "OLD=NEW",
+            required = true,
+            value_parser = parse_anchor
+        )]
+        anchor: Vec<(u64, u64)>,
+    },
+}
+
+#[derive(ClapArgs, Debug)]
+struct IoArgs {
+    /// SRT or VTT file (`-` for stdin)
+    #[arg(short, long)]
+    input: PathBuf,
+
+    /// Output file path (`-` for stdout), or an s3:// / gs:// URI
+    /// [default: <input>.synced.<ext>]
+    #[arg(short, long, conflicts_with = "in_place")]
+    output: Option<String>,
+
+    /// Overwrite the input file
+    #[arg(long)]
+    in_place: bool,
+}
+
+/// Milliseconds of `3`, `2.5s`, `1500ms` or a timestamp
+fn parse_time(value: &str) -> Result<u64, String> {
+    let invalid = || {
+        format!(
+            "Invalid time '{}': use seconds (2.5), milliseconds (1500ms) or a timestamp (00:01:02,500)",
+            value
+        )
+    };
+    if value.contains(':') {
+        return parse_timestamp(value).ok_or_else(invalid);
+    }
+    if let Some(ms) = value.strip_suffix("ms") {
+        return ms.trim().parse().map_err(|_| invalid());
+    }
+    let secs: f64 = value
+        .strip_suffix('s')
+        .unwrap_or(value)
+        .trim()
+        .parse()
+        .map_err(|_| invalid())?;
+    if !secs.is_finite() || secs < 0.0 {
+        return Err(invalid());
+    }
+    Ok((secs * 1000.0).round() as u64)
+}
+
+fn parse_offset(value: &str) -> Result<i64, String> {
+    let value = value.trim();
+    let (sign, magnitude) = match value.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, value.strip_prefix('+').unwrap_or(value)),
+    };
+    Ok(sign * parse_time(magnitude)? as i64)
+}
+
+fn parse_anchor(value: &str) -> Result<(u64, u64), String> {
+    let (old, new) = value
+        .split_once('=')
+        .ok_or_else(|| format!("Invalid anchor '{}': expected OLD=NEW", value))?;
+    Ok((parse_time(old.trim())?, parse_time(new.trim())?))
+}
+
+/// `talk.srt` -> `talk.synced.srt`
+fn default_output(input: &Path, format: SubtitleFormat) -> PathBuf {
+    if input == Path::new("-") {
+        return PathBuf::from(format!("synced.{}", format.extension()));
+    }
+    input.with_extension(format!("synced.{}", format.extension()))
+}
+
+/// Format by extension, else by content (stdin, `.txt`)
+fn detect_format(path: &Path, content: &str) -> SubtitleFormat {
+    SubtitleFormat::from_path(path).unwrap_or_else(|| {
+        if content
+            .trim_start_matches('\u{feff}')
+            .trim_start()
+            .starts_with("WEBVTT")
+        {
+            SubtitleFormat::Vtt
+        } else {
+            SubtitleFormat::Srt
+        }
+    })
+}
+
+async fn read_input(path: &Path) -> Result<String> {
+    if path == Path::new("-") {
+        let mut content = String::new();
+        tokio::io::stdin()
+            .read_to_string(&mut content)
+            .await
+            .context("Failed to read stdin")?;
+        return Ok(content);
+    }
+    fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Failed to read input file {:?}", path))
+}
+
+async fn write_output(io: &IoArgs, format: SubtitleFormat, output: &str) -> Result<()> {
+    if io.in_place && io.input == Path::new("-") {
+        anyhow::bail!("--in-place needs an input file, not stdin");
+    }
+    if io.output.as_deref() == Some("-") {
+        let mut stdout = tokio::io::stdout();
+        stdout
+            .write_all(output.as_bytes())
+            .await
+            .context("Failed to write to stdout")?;
+        stdout.flush().await.context("Failed to flush stdout")?;
+        return Ok(());
+    }
+
+    let default_path = if io.in_place {
+        io.input.clone()
+    } else {
+        default_output(&io.input, format)
+    };
+    let default_name = default_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let target = io
+        .output
+        .clone()
+        .unwrap_or_else(|| default_path.to_string_lossy().to_string());
+    let (storage, key) = OutputStorage::open_file(&target, &default_name)
+        .with_context(|| format!("Invalid output target: {}", target))?;
+
+    storage
+        .put(&key, output.as_bytes())
+        .instrument(info_span!("stage", stage = "write"))
+        .await
+        .context("Failed to write subtitle file")?;
+    let location = storage.location(&key);
+    info!("Subtitles written to {}", location);
+    println!("Saved to: {}", location);
+    Ok(())
+}
+
+/// Run with parsed arguments; shared by this binary and its `flashecho` subcommand
+pub async fn run(args: Args) -> Result<()> {
+    init_logging(args.verbose, args.log_format);
+
+    let io = match &args.command {
+        SubsCommand::Shift { io, .. } | SubsCommand::Rescale { io, .. } => io,
+    };
+    let span = info_span!("file", file = %io.input.display());
+    retime(&args.command, io).instrument(span).await
+}
+
+async fn retime(command: &SubsCommand, io: &IoArgs) -> Result<()> {
+    let content = read_input(&io.input).await?;
+    let format = detect_format(&io.input, &content);
+    let mut subtitles = Subtitles::parse(format, &content)
+        .with_context(|| format!("Failed to parse {} file", format.extension()))?;
+    debug!("Read {} cues", subtitles.cues().count());
+
+    match command {
+        SubsCommand::Shift { by, .. } => subtitles.shift(*by)?,
+        SubsCommand::Rescale { anchor, .. } => {
+            let [from, to] = anchor[..] else {
+                anyhow::bail!("Give exactly two --anchor values, got {}", anchor.len());
+            };
+            subtitles.rescale(from, to)?;
+        }
+    }
+
+    write_output(io, format, &subtitles.render()).await
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    run(Args::parse()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_offset() {
+        assert_eq!(parse_offset("-2.5"), Ok(-2500));
+        assert_eq!(parse_offset("3s"), Ok(3000));
+        assert_eq!(parse_offset("+1500ms"), Ok(1500));
+        assert_eq!(parse_offset("-00:00:01,500"), Ok(-1500));
+        assert!(parse_offset("soon").is_err());
+        assert!(parse_offset("--2").is_err());
+    }
+
+    #[test]
+    fn test_parse_rescale_args() {
+        let args = Args::parse_from([
+            "subs",
+            "rescale",
+            "-i",
+            "talk.srt",
+            "--anchor",
+            "00:00:05,000=4.2",
+            "--anchor",
+            "01:10:00.000 = 01:08:30.000",
+        ]);
+        let SubsCommand::Rescale { anchor, io } = args.command else {
+            panic!("expected rescale");
+        };
+        assert_eq!(anchor, [(5000, 4200), (4_200_000, 4_110_000)]);
+        assert_eq!(
+            default_output(&io.input, SubtitleFormat::Srt),
+            PathBuf::from("talk.synced.srt")
+        );
+
+        let args = Args::parse_from(["subs", "shift", "-i", "a.vtt", "--by", "-2"]);
+        assert!(matches!(args.command, SubsCommand::Shift { by: -2000, .. }));
+    }
+}
@@ -0,0 +1,386 @@
+//! SRT and WebVTT parsing. Cue timing lines are kept as written, so a file
+//! whose text is rewritten (e.g. translated) renders with identical timing;
+//! [`Subtitles::shift`] and [`Subtitles::rescale`] rewrite only the times.
+
+use std::path::Path;
+use thiserror::Error;
+
+use crate::error::ErrorKind;
+
+/// Subtitle file formats that can be read back
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubtitleFormat {
+    Srt,
+    Vtt,
+}
+
+impl SubtitleFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "srt",
+            SubtitleFormat::Vtt => "vtt",
+        }
+    }
+
+    /// Format matching a file name's extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_lowercase();
+        match ext.as_str() {
+            "srt" => Some(SubtitleFormat::Srt),
+            "vtt" => Some(SubtitleFormat::Vtt),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SubtitleError {
+    #[error("Missing WEBVTT header")]
+    MissingHeader,
+
+    #[error("Line {line}: expected a `start --> end` timing line")]
+    MissingTiming { line: usize },
+
+    #[error("Invalid cue timing: {0}")]
+    InvalidTiming(String),
+
+    #[error("Rescale anchors must be at two different times")]
+    InvalidAnchors,
+}
+
+impl SubtitleError {
+    pub fn kind(&self) -> ErrorKind {
+        ErrorKind::InvalidInput
+    }
+
+    /// Always `None`: parsing makes no requests
+    pub fn status(&self) -> Option<u16> {
+        None
+    }
+}
+
+/// One subtitle cue
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cue {
+    /// Sequence number (SRT) or cue identifier (VTT), as written
+    pub id: Option<String>,
+    /// `start --> end`, including any VTT cue settings
+    pub timing: String,
+    /// Cue text; lines are separated by `\n`
+    pub text: String,
+}
+
+impl Cue {
+    /// Start and end in milliseconds, if the timing line is well formed
+    pub fn times(&self) -> Option<(u64, u64)> {
+        let (start, end, _) = split_timing(&self.timing)?;
+        Some((parse_timestamp(start)?, parse_timestamp(end)?))
+    }
+}
+
+/// `start`, `end` and any cue settings of a timing line
+fn split_timing(timing: &str) -> Option<(&str, &str, &str)> {
+    let (start, rest) = timing.split_once("-->")?;
+    let rest = rest.trim_start();
+    let (end, settings) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    Some((start.trim(), end, settings.trim()))
+}
+
+/// Milliseconds of an SRT (`00:01:02,500`) or VTT (`01:02.500`, `00:01:02.500`)
+/// timestamp
+pub fn parse_timestamp(timestamp: &str) -> Option<u64> {
+    let (clock, millis) = timestamp.trim().split_once([',', '.'])?;
+    if millis.len() != 3 {
+        return None;
+    }
+    let millis: u64 = millis.parse().ok()?;
+    let parts = clock
+        .split(':')
+        .map(|part| part.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    let (hours, minutes, seconds) = match parts[..] {
+        [hours, minutes, seconds] => (hours, minutes, seconds),
+        [minutes, seconds] => (0, minutes, seconds),
+        _ => return None,
+    };
+    if minutes >= 60 || seconds >= 60 {
+        return None;
+    }
+    Some(((hours * 60 + minutes) * 60 + seconds) * 1000 + millis)
+}
+
+/// Timestamp in `format`; VTT leaves out the hours when the original did
+/// and they are zero
+fn format_timestamp(ms: u64, format: SubtitleFormat, with_hours: bool) -> String {
+    let (hours, minutes) = (ms / 3_600_000, ms / 60_000 % 60);
+    let (seconds, millis) = (ms / 1000 % 60, ms % 1000);
+    match format {
+        SubtitleFormat::Srt => format!("{:02}:{:02}:{:02},{:03}", hours, minutes, seconds, millis),
+        SubtitleFormat::Vtt if with_hours || hours > 0 => {
+            format!("{:02}:{:02}:{:02}.{:03}", hours, minutes, seconds, millis)
+        }
+        SubtitleFormat::Vtt => format!("{:02}:{:02}.{:03}", minutes, seconds, millis),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Block {
+    Cue(Cue),
+    /// VTT header, NOTE, STYLE and REGION blocks, rendered unchanged
+    Raw(String),
+}
+
+/// A parsed subtitle file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subtitles {
+    format: SubtitleFormat,
+    blocks: Vec<Block>,
+}
+
+impl Subtitles {
+    pub fn parse(format: SubtitleFormat, content: &str) -> Result<Self, SubtitleError> {
+        let content = content.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+        let mut blocks = Vec::new();
+        let mut start_line = 1;
+        let mut lines: Vec<&str> = Vec::new();
+
+        // A trailing empty line closes the last block
+        for (i, line) in content.lines().chain([""]).enumerate() {
+            if !line.trim().is_empty() {
+                if lines.is_empty() {
+                    start_line = i + 1;
+                }
+                lines.push(line);
+                continue;
+            }
+            if lines.is_empty() {
+                continue;
+            }
+            let block = if format == SubtitleFormat::Vtt && !lines[0].contains("-->") {
+                let first = lines[0];
+                if blocks.is_empty() && !first.starts_with("WEBVTT") {
+                    return Err(SubtitleError::MissingHeader);
+                }
+                let is_meta = ["WEBVTT", "NOTE", "STYLE", "REGION"]
+                    .iter()
+                    .any(|keyword| first.starts_with(keyword));
+                if is_meta {
+                    Block::Raw(lines.join("\n"))
+                } else {
+                    Block::Cue(Self::parse_cue(&lines, start_line)?)
+                }
+            } else if format == SubtitleFormat::Vtt && blocks.is_empty() {
+                return Err(SubtitleError::MissingHeader);
+            } else {
+                Block::Cue(Self::parse_cue(&lines, start_line)?)
+            };
+            blocks.push(block);
+            lines.clear();
+        }
+
+        if format == SubtitleFormat::Vtt && blocks.is_empty() {
+            return Err(SubtitleError::MissingHeader);
+        }
+        Ok(Self { format, blocks })
+    }
+
+    /// Cue from the lines of one block: an optional id, the timing, the text
+    fn parse_cue(lines: &[&str], start_line: usize) -> Result<Cue, SubtitleError> {
+        let (id, rest) = if lines[0].contains("-->") {
+            (None, lines)
+        } else {
+            (Some(lines[0].trim().to_string()), &lines[1..])
+        };
+        let Some((timing, text)) = rest.split_first().filter(|(t, _)| t.contains("-->")) else {
+            let line = start_line + usize::from(id.is_some());
+            return Err(SubtitleError::MissingTiming { line });
+        };
+        Ok(Cue {
+            id,
+            timing: timing.trim().to_string(),
+            text: text.join("\n"),
+        })
+    }
+
+    pub fn format(&self) -> SubtitleFormat {
+        self.format
+    }
+
+    pub fn cues(&self) -> impl Iterator<Item = &Cue> {
+        self.blocks.iter().filter_map(|block| match block {
+            Block::Cue(cue) => Some(cue),
+            Block::Raw(_) => None,
+        })
+    }
+
+    pub fn cues_mut(&mut self) -> impl Iterator<Item = &mut Cue> {
+        self.blocks.iter_mut().filter_map(|block| match block {
+            Block::Cue(cue) => Some(cue),
+            Block::Raw(_) => None,
+        })
+    }
+
+    /// Replace every cue's start and end with `f(ms)`, keeping cue settings.
+    /// Nothing changes if any timing line is malformed.
+    pub fn retime(&mut self, f: impl Fn(u64) -> u64) -> Result<(), SubtitleError> {
+        let format = self.format;
+        let retimed = self
+            .cues()
+            .map(|cue| {
+                let invalid = || SubtitleError::InvalidTiming(cue.timing.clone());
+                let (start, end, settings) = split_timing(&cue.timing).ok_or_else(invalid)?;
+                let (start_ms, end_ms) = cue.times().ok_or_else(invalid)?;
+                let mut timing = format!(
+                    "{} --> {}",
+                    format_timestamp(f(start_ms), format, start.matches(':').count() == 2),
+                    format_timestamp(f(end_ms), format, end.matches(':').count() == 2)
+                );
+                if !settings.is_empty() {
+                    timing.push(' ');
+                    timing.push_str(settings);
+                }
+                Ok(timing)
+            })
+            .collect::<Result<Vec<_>, SubtitleError>>()?;
+
+        for (cue, timing) in self.cues_mut().zip(retimed) {
+            cue.timing = timing;
+        }
+        Ok(())
+    }
+
+    /// Move every cue by `offset_ms`; times before zero become zero
+    pub fn shift(&mut self, offset_ms: i64) -> Result<(), SubtitleError> {
+        self.retime(|ms| ms.saturating_add_signed(offset_ms))
+    }
+
+    /// Stretch timing linearly so the time `from.0` lands on `from.1` and
+    /// `to.0` on `to.1`, e.g. to fix timestamps that drift over a long file
+    pub fn rescale(&mut self, from: (u64, u64), to: (u64, u64)) -> Result<(), SubtitleError> {
+        if from.0 == to.0 {
+            return Err(SubtitleError::InvalidAnchors);
+        }
+        let scale = (to.1 as f64 - from.1 as f64) / (to.0 as f64 - from.0 as f64);
+        self.retime(|ms| {
+            let mapped = from.1 as f64 + (ms as f64 - from.0 as f64) * scale;
+            mapped.round().max(0.0) as u64
+        })
+    }
+
+    /// The file as text, in its original format
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+        for block in &self.blocks {
+            match block {
+                Block::Raw(raw) => output.push_str(raw),
+                Block::Cue(cue) => {
+                    if let Some(id) = &cue.id {
+                        output.push_str(&format!("{}\n", id));
+                    }
+                    output.push_str(&cue.timing);
+                    if !cue.text.is_empty() {
+                        output.push_str(&format!("\n{}", cue.text));
+                    }
+                }
+            }
+            output.push_str("\n\n");
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRT: &str = "1\r\n00:00:01,500 --> 00:00:04,250\r\n[Speaker 1] Hello\r\nworld\r\n\r\n\r\n2\r\n00:00:05,000 --> 00:00:07,000\r\nBye\r\n";
+
+    #[test]
+    fn test_parse_srt_keeps_timing() {
+        let subtitles = Subtitles::parse(SubtitleFormat::Srt, SRT).unwrap();
+        let cues: Vec<&Cue> = subtitles.cues().collect();
+        assert_eq!(cues.len(), 2);
+        assert_eq!(cues[0].id.as_deref(), Some("1"));
+        assert_eq!(cues[0].timing, "00:00:01,500 --> 00:00:04,250");
+        assert_eq!(cues[0].text, "[Speaker 1] Hello\nworld");
+        assert_eq!(
+            subtitles.render(),
+            "1\n00:00:01,500 --> 00:00:04,250\n[Speaker 1] Hello\nworld\n\n2\n00:00:05,000 --> 00:00:07,000\nBye\n\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_vtt_keeps_meta_blocks() {
+        let vtt = "WEBVTT - talk\n\nNOTE made by hand\n\nintro\n00:01.000 --> 00:02.000 align:start\n<v Ann>Hi\n\n00:00:03.000 --> 00:00:04.000\nThere\n";
+        let mut subtitles = Subtitles::parse(SubtitleFormat::Vtt, vtt).unwrap();
+        let cues: Vec<&Cue> = subtitles.cues().collect();
+        assert_eq!(cues.len(), 2);
+        assert_eq!(cues[0].id.as_deref(), Some("intro"));
+        assert_eq!(cues[0].timing, "00:01.000 --> 00:02.000 align:start");
+        assert_eq!(cues[1].id, None);
+
+        for cue in subtitles.cues_mut() {
+            cue.text = cue.text.to_uppercase();
+        }
+        let rendered = subtitles.render();
+        assert!(rendered.starts_with("WEBVTT - talk\n\nNOTE made by hand\n\nintro\n"));
+        assert!(rendered.contains("00:01.000 --> 00:02.000 align:start\n<V ANN>HI\n"));
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("00:01:02,500"), Some(62_500));
+        assert_eq!(parse_timestamp("01:02.500"), Some(62_500));
+        assert_eq!(parse_timestamp("1:00:00.000"), Some(3_600_000));
+        assert_eq!(parse_timestamp("00:61.000"), None);
+        assert_eq!(parse_timestamp("00:01:02"), None);
+    }
+
+    #[test]
+    fn test_shift_and_rescale() {
+        let mut srt = Subtitles::parse(SubtitleFormat::Srt, SRT).unwrap();
+        srt.shift(-2000).unwrap();
+        let times: Vec<_> = srt.cues().map(|cue| cue.times().unwrap()).collect();
+        assert_eq!(times, [(0, 2250), (3000, 5000)]);
+        assert!(srt.render().contains("00:00:00,000 --> 00:00:02,250\n"));
+
+        let vtt = "WEBVTT\n\n00:10.000 --> 00:12.000 line:90%\nHi\n\n00:59:50.000 --> 01:00:10.000\nBye\n";
+        let mut vtt = Subtitles::parse(SubtitleFormat::Vtt, vtt).unwrap();
+        // Drift of one second per ten: 10s stays, 20s becomes 19s
+        vtt.rescale((10_000, 10_000), (20_000, 19_000)).unwrap();
+        let rendered = vtt.render();
+        assert!(rendered.contains("00:10.000 --> 00:11.800 line:90%\n"));
+        assert!(rendered.contains("00:53:52.000 --> 00:54:10.000\n"));
+
+        assert!(matches!(
+            vtt.rescale((5, 1), (5, 2)),
+            Err(SubtitleError::InvalidAnchors)
+        ));
+        let mut broken =
+            Subtitles::parse(SubtitleFormat::Srt, "1\n00:00:01 --> 00:00:02\nHi\n").unwrap();
+        assert!(matches!(
+            broken.shift(1000),
+            Err(SubtitleError::InvalidTiming(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(
+            Subtitles::parse(SubtitleFormat::Vtt, "00:01.000 --> 00:02.000\nHi\n"),
+            Err(SubtitleError::MissingHeader)
+        ));
+        assert!(matches!(
+            Subtitles::parse(
+                SubtitleFormat::Srt,
+                "1\n00:00:01,000 --> 00:00:02,000\nHi\n\n2\nNo timing\n"
+            ),
+            Err(SubtitleError::MissingTiming { line: 6 })
+        ));
+        assert_eq!(
+            SubtitleFormat::from_path(Path::new("talk.VTT")),
+            Some(SubtitleFormat::Vtt)
+        );
+        assert_eq!(SubtitleFormat::from_path(Path::new("talk.json")), None);
+    }
+}
@@ -0,0 +1,409 @@
+//! In-process mock HTTP server and canned API responses for exercising the
+//! clients without hitting the live APIs. Enabled with the `testing` feature.
+//!
+//! Point a client at the server by setting `base_url` on its config (or
+//! `FileApiClient::with_base_url`) to `MockServer::uri()`. [`vcr`] records
+//! live API traffic into replayable cassettes the same way.
+
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::BufReader;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+pub mod vcr;
+
+/// A canned HTTP response
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl MockResponse {
+    pub fn new(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    pub fn json(status: u16, body: &serde_json::Value) -> Self {
+        Self::new(status)
+            .with_header("Content-Type", "application/json")
+            .with_body(body.to_string())
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn with_body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+}
+
+/// A request captured by the mock server
+#[derive(Debug, Clone)]
+pub struct ReceivedRequest {
+    pub method: String,
+    pub path: String,
+    pub query: Option<String>,
+    /// Header names are lowercased
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+impl ReceivedRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(&name.to_lowercase()).map(String::as_str)
+    }
+
+    pub fn json(&self) -> serde_json::Result<serde_json::Value> {
+        serde_json::from_slice(&self.body)
+    }
+}
+
+struct Route {
+    method: String,
+    path: String,
+    responses: VecDeque<MockResponse>,
+}
+
+#[derive(Default)]
+struct State {
+    routes: Vec<Route>,
+    requests: Vec<ReceivedRequest>,
+}
+
+impl State {
+    /// Next response for a route: queued responses are served in order and
+    /// the last one keeps being served once the queue is down to it
+    fn respond(&mut self, method: &str, path: &str) -> MockResponse {
+        let route = self
+            .routes
+            .iter_mut()
+            .find(|r| r.method == method && r.path == path);
+
+        match route {
+            Some(route) if route.responses.len() > 1 => route.responses.pop_front().unwrap(),
+            Some(route) => route.responses.front().cloned().unwrap(),
+            None => MockResponse::new(404).with_body(format!("no mock for {} {}", method, path)),
+        }
+    }
+}
+
+/// Minimal HTTP/1.1 server on a random local port
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    handle: JoinHandle<()>,
+}
+
+impl MockServer {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind mock server");
+        let addr = listener.local_addr().expect("mock server has no address");
+        let state = Arc::new(Mutex::new(State::default()));
+
+        let server_state = state.clone();
+        let handle = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let state = server_state.clone();
+                tokio::spawn(async move {
+                    // Malformed requests just drop the connection
+                    let _ = handle_connection(stream, state).await;
+                });
+            }
+        });
+
+        Self {
+            addr,
+            state,
+            handle,
+        }
+    }
+
+    /// Base URL to use as a client's `base_url`
+    pub fn uri(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Queue a response for `method` + `path` (path without query string).
+    /// Calling this repeatedly for the same route queues responses in order.
+    pub fn mock(&self, method: &str, path: &str, response: MockResponse) {
+        let mut state = self.state.lock().unwrap();
+        let method = method.to_uppercase();
+
+        match state
+            .routes
+            .iter_mut()
+            .find(|r| r.method == method && r.path == path)
+        {
+            Some(route) => route.responses.push_back(response),
+            None => state.routes.push(Route {
+                method,
+                path: path.to_string(),
+                responses: VecDeque::from([response]),
+            }),
+        }
+    }
+
+    /// All requests received so far, in arrival order
+    pub fn received_requests(&self) -> Vec<ReceivedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+async fn handle_connection(stream: TcpStream, state: Arc<Mutex<State>>) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let request = read_request(&mut reader).await?;
+
+    let response = {
+        let mut state = state.lock().unwrap();
+        let response = state.respond(&request.method, &request.path);
+        state.requests.push(request);
+        response
+    };
+
+    write_response(reader.into_inner(), &response).await
+}
+
+/// Parse one request; malformed ones are returned as errors and drop the
+/// connection
+async fn read_request(reader: &mut BufReader<TcpStream>) -> std::io::Result<ReceivedRequest> {
+    let request = crate::http_server::read_request(reader, usize::MAX, Duration::from_secs(30))
+        .await?
+        .map_err(|rejection| std::io::Error::other(rejection.message))?;
+    Ok(ReceivedRequest {
+        method: request.method,
+        path: request.path,
+        query: request.query,
+        headers: request.headers,
+        body: request.body,
+    })
+}
+
+async fn write_response(mut stream: TcpStream, response: &MockResponse) -> std::io::Result<()> {
+    let headers: Vec<(&str, &str)> = response
+        .headers
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .collect();
+    crate::http_server::write_response(&mut stream, response.status, &headers, &response.body).await
+}
+
+/// Canned response bodies matching the shapes returned by the real APIs
+pub mod fixtures {
+    use base64::Engine;
+    use serde_json::{Value, json};
+
+    /// A two-speaker transcript as returned in Gemini's structured output
+    pub fn transcript() -> Value {
+        json!({
+            "summary": "Two people greet each other.",
+            "segments": [
+                {
+                    "speaker": "Speaker 1",
+                    "timestamp": "00:00",
+                    "content": "Hello there.",
+                    "language": "English",
+                    "language_code": "en",
+                    "emotion": "happy"
+                },
+                {
+                    "speaker": "Speaker 2",
+                    "timestamp": "00:03",
+                    "content": "Bonjour !",
+                    "language": "French",
+                    "language_code": "fr",
+                    "translation": "Hello!",
+                    "emotion": "neutral"
+                }
+            ]
+        })
+    }
+
+    /// `generateContent` response carrying `transcript` as JSON text
+    pub fn gemini_transcript_response(transcript: &Value) -> Value {
+        json!({
+            "candidates": [{
+                "content": {
+                    "role": "model",
+                    "parts": [{"text": transcript.to_string()}]
+                },
+                "finishReason": "STOP"
+            }],
+            "usageMetadata": {
+                "promptTokenCount": 1200,
+                "candidatesTokenCount": 150,
+                "totalTokenCount": 1350
+            }
+        })
+    }
+
+    /// `generateContent` response with a text part followed by an image part
+    pub fn image_response(data: &[u8], mime_type: &str) -> Value {
+        json!({
+            "candidates": [{
+                "content": {
+                    "role": "model",
+                    "parts": [
+                        {"text": "Here is your image."},
+                        {
+                            "inlineData": {
+                                "mimeType": mime_type,
+                                "data": base64::engine::general_purpose::STANDARD.encode(data)
+                            }
+                        }
+                    ]
+                },
+                "finishReason": "STOP"
+            }],
+            "modelVersion": "gemini-2.5-flash-image",
+            "responseId": "mock-image-response"
+        })
+    }
+
+    /// TTS `generateContent` response with 16-bit PCM audio at 24 kHz
+    pub fn speech_response(pcm: &[u8]) -> Value {
+        json!({
+            "candidates": [{
+                "content": {
+                    "role": "model",
+                    "parts": [{
+                        "inlineData": {
+                            "mimeType": "audio/L16;codec=pcm;rate=24000",
+                            "data": base64::engine::general_purpose::STANDARD.encode(pcm)
+                        }
+                    }]
+                },
+                "finishReason": "STOP"
+            }],
+            "usageMetadata": {
+                "promptTokenCount": 12,
+                "candidatesTokenCount": 400,
+                "totalTokenCount": 412
+            }
+        })
+    }
+
+    /// Lyria `predict` response carrying one clip
+    pub fn music_response(audio: &[u8]) -> Value {
+        json!({
+            "predictions": [{
+                "bytesBase64Encoded": base64::engine::general_purpose::STANDARD.encode(audio),
+                "mimeType": "audio/wav"
+            }]
+        })
+    }
+
+    /// `batchEmbedContents` response with one vector per entry
+    pub fn embeddings_response(vectors: &[&[f32]]) -> Value {
+        let embeddings: Vec<Value> = vectors
+            .iter()
+            .map(|values| json!({ "values": values }))
+            .collect();
+        json!({ "embeddings": embeddings })
+    }
+
+    /// `generateContent` text answer with token usage
+    pub fn text_response(text: &str) -> Value {
+        json!({
+            "candidates": [{
+                "content": { "role": "model", "parts": [{ "text": text }] },
+                "finishReason": "STOP"
+            }],
+            "usageMetadata": {
+                "promptTokenCount": 8,
+                "candidatesTokenCount": 5,
+                "totalTokenCount": 13
+            }
+        })
+    }
+
+    /// `cachedContents` resource as returned when a context is created or looked up
+    pub fn cached_content(name: &str) -> Value {
+        json!({
+            "name": name,
+            "model": "models/gemini-2.5-flash",
+            "expireTime": "2030-01-01T00:00:00Z",
+            "usageMetadata": { "totalTokenCount": 2048 }
+        })
+    }
+
+    /// File API file resource
+    pub fn file_info(name: &str, state: &str) -> Value {
+        json!({
+            "name": name,
+            "displayName": "audio.mp3",
+            "mimeType": "audio/mpeg",
+            "sizeBytes": "1024",
+            "state": state,
+            "uri": format!("https://generativelanguage.googleapis.com/v1beta/{}", name)
+        })
+    }
+
+    /// Response to the final upload chunk of a resumable upload
+    pub fn upload_response(name: &str, state: &str) -> Value {
+        json!({ "file": file_info(name, state) })
+    }
+
+    /// Veo long-running operation that is still rendering
+    pub fn video_operation(name: &str) -> Value {
+        json!({ "name": name })
+    }
+
+    /// Finished Veo operation whose video can be downloaded from `uri`
+    pub fn video_operation_done(name: &str, uri: &str) -> Value {
+        json!({
+            "name": name,
+            "done": true,
+            "response": {
+                "@type": "type.googleapis.com/google.ai.generativelanguage.v1beta.PredictLongRunningResponse",
+                "generateVideoResponse": {
+                    "generatedSamples": [{"video": {"uri": uri}}]
+                }
+            }
+        })
+    }
+
+    /// Google API error body
+    pub fn api_error(code: u16, message: &str) -> Value {
+        json!({
+            "error": {
+                "code": code,
+                "message": message,
+                "status": "ERROR"
+            }
+        })
+    }
+
+    /// OpenAI `verbose_json` transcription response
+    pub fn openai_transcription() -> Value {
+        json!({
+            "task": "transcribe",
+            "language": "english",
+            "duration": 4.2,
+            "text": "Hello there. How are you?",
+            "segments": [
+                {"id": 0, "start": 0.0, "end": 1.8, "text": " Hello there."},
+                {"id": 1, "start": 2.0, "end": 4.2, "text": " How are you?"}
+            ]
+        })
+    }
+}
@@ -0,0 +1,359 @@
+//! Record live API interactions to a cassette file and replay them later, so
+//! tests and CI exercise real response shapes without network access or spend.
+//!
+//! A [`Vcr`] is a local server like [`MockServer`](super::MockServer): point a
+//! client's `base_url` at [`Vcr::uri`]. When recording, every request is
+//! forwarded to the upstream API and the response is saved; when replaying,
+//! responses come from the cassette. [`Vcr::from_env`] records when
+//! `FLASHECHO_VCR=record` and replays otherwise:
+//!
+//! ```text
+//! FLASHECHO_VCR=record GEMINI_API_KEY=... cargo test --features testing
+//! ```
+//!
+//! Cassettes never contain request headers (API keys) or request bodies, and
+//! `key=` query parameters are redacted. Upstream URLs in response headers
+//! (e.g. the File API upload URL) are stored as `{base_url}` and rewritten to
+//! the local server, so uploads are recorded and replayed too.
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::io::BufReader;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+use super::{MockResponse, ReceivedRequest, read_request, write_response};
+
+/// Set to `record` to record cassettes in [`Vcr::from_env`]
+pub const VCR_ENV: &str = "FLASHECHO_VCR";
+
+const BASE_URL_PLACEHOLDER: &str = "{base_url}";
+
+/// Response headers worth keeping; the rest (date, server, ...) only add noise
+fn keep_header(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name == "content-type" || name.starts_with("x-goog-upload")
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Cassette {
+    interactions: Vec<Interaction>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Interaction {
+    method: String,
+    path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    query: Option<String>,
+    status: u16,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    headers: Vec<(String, String)>,
+    /// UTF-8 response body; binary bodies go in `body_base64`
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    body: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body_base64: Option<String>,
+}
+
+impl Interaction {
+    fn response(&self, uri: &str) -> MockResponse {
+        let body = match &self.body_base64 {
+            Some(data) => base64::engine::general_purpose::STANDARD
+                .decode(data)
+                .unwrap_or_default(),
+            None => self.body.clone().into_bytes(),
+        };
+        let mut response = MockResponse::new(self.status).with_body(body);
+        for (name, value) in &self.headers {
+            response = response.with_header(name, &value.replace(BASE_URL_PLACEHOLDER, uri));
+        }
+        response
+    }
+}
+
+enum Mode {
+    Record {
+        upstream: String,
+        http: reqwest::Client,
+    },
+    Replay,
+}
+
+struct Shared {
+    mode: Mode,
+    uri: String,
+    interactions: Mutex<Vec<Interaction>>,
+    /// Replay: which interactions have been served
+    used: Mutex<Vec<bool>>,
+}
+
+impl Shared {
+    /// First unused interaction for the route, in recorded order
+    fn replay(&self, request: &ReceivedRequest) -> MockResponse {
+        let interactions = self.interactions.lock().unwrap();
+        let mut used = self.used.lock().unwrap();
+        let next = interactions
+            .iter()
+            .enumerate()
+            .find(|(i, it)| !used[*i] && it.method == request.method && it.path == request.path);
+
+        match next {
+            Some((i, interaction)) => {
+                used[i] = true;
+                interaction.response(&self.uri)
+            }
+            None => MockResponse::new(404).with_body(format!(
+                "no recorded interaction left for {} {}",
+                request.method, request.path
+            )),
+        }
+    }
+
+    async fn record(
+        &self,
+        request: ReceivedRequest,
+        upstream: &str,
+        http: &reqwest::Client,
+    ) -> MockResponse {
+        let mut url = format!("{}{}", upstream, request.path);
+        if let Some(query) = &request.query {
+            url = format!("{}?{}", url, query);
+        }
+        let method = reqwest::Method::from_bytes(request.method.as_bytes()).unwrap_or_default();
+        let mut builder = http.request(method, url);
+        for (name, value) in &request.headers {
+            if !matches!(name.as_str(), "host" | "content-length" | "connection") {
+                builder = builder.header(name, value);
+            }
+        }
+
+        let response = match builder.body(request.body).send().await {
+            Ok(response) => response,
+            Err(e) => {
+                let e = crate::redact::reqwest_error(e);
+                return MockResponse::new(502).with_body(format!("upstream request failed: {}", e));
+            }
+        };
+        let status = response.status().as_u16();
+        let headers: Vec<(String, String)> = response
+            .headers()
+            .iter()
+            .filter(|(name, _)| keep_header(name.as_str()))
+            .filter_map(|(name, value)| {
+                let value = value.to_str().ok()?.replace(upstream, BASE_URL_PLACEHOLDER);
+                Some((name.to_string(), value))
+            })
+            .collect();
+        let body = response.bytes().await.unwrap_or_default().to_vec();
+        let (body, body_base64) = match String::from_utf8(body) {
+            Ok(text) => (text, None),
+            Err(e) => (
+                String::new(),
+                Some(base64::engine::general_purpose::STANDARD.encode(e.as_bytes())),
+            ),
+        };
+
+        let interaction = Interaction {
+            method: request.method,
+            path: request.path,
+            query: request.query.as_deref().map(redact_key),
+            status,
+            headers,
+            body,
+            body_base64,
+        };
+        let response = interaction.response(&self.uri);
+        self.interactions.lock().unwrap().push(interaction);
+        response
+    }
+}
+
+/// `key=` query parameters blanked out
+fn redact_key(query: &str) -> String {
+    query
+        .split('&')
+        .map(|pair| {
+            if pair.starts_with("key=") {
+                "key=REDACTED"
+            } else {
+                pair
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Recording or replaying server on a random local port
+pub struct Vcr {
+    addr: SocketAddr,
+    cassette: PathBuf,
+    shared: Arc<Shared>,
+    handle: JoinHandle<()>,
+}
+
+impl Vcr {
+    /// Forward requests to `upstream` (e.g. `GEMINI_BASE_URL`) and save the
+    /// interactions to `cassette` on [`Vcr::finish`]
+    pub async fn record(cassette: impl Into<PathBuf>, upstream: &str) -> Self {
+        let mode = Mode::Record {
+            upstream: upstream.trim_end_matches('/').to_string(),
+            http: reqwest::Client::new(),
+        };
+        Self::start(cassette.into(), mode, Vec::new()).await
+    }
+
+    /// Serve the interactions saved in `cassette`
+    pub async fn replay(cassette: impl Into<PathBuf>) -> io::Result<Self> {
+        let cassette = cassette.into();
+        let data = std::fs::read(&cassette).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!(
+                    "cassette {:?} not readable ({}); record it with {}=record",
+                    cassette, e, VCR_ENV
+                ),
+            )
+        })?;
+        let parsed: Cassette = serde_json::from_slice(&data).map_err(io::Error::other)?;
+        Ok(Self::start(cassette, Mode::Replay, parsed.interactions).await)
+    }
+
+    /// [`Vcr::record`] when `FLASHECHO_VCR=record`, else [`Vcr::replay`]
+    pub async fn from_env(cassette: impl Into<PathBuf>, upstream: &str) -> io::Result<Self> {
+        match std::env::var(VCR_ENV).as_deref() {
+            Ok("record") => Ok(Self::record(cassette, upstream).await),
+            _ => Self::replay(cassette).await,
+        }
+    }
+
+    async fn start(cassette: PathBuf, mode: Mode, interactions: Vec<Interaction>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind vcr server");
+        let addr = listener.local_addr().expect("vcr server has no address");
+        let shared = Arc::new(Shared {
+            mode,
+            uri: format!("http://{}", addr),
+            used: Mutex::new(vec![false; interactions.len()]),
+            interactions: Mutex::new(interactions),
+        });
+
+        let server_shared = shared.clone();
+        let handle = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let shared = server_shared.clone();
+                tokio::spawn(async move {
+                    let _ = handle_connection(stream, shared).await;
+                });
+            }
+        });
+
+        Self {
+            addr,
+            cassette,
+            shared,
+            handle,
+        }
+    }
+
+    /// Base URL to use as a client's `base_url`
+    pub fn uri(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn is_recording(&self) -> bool {
+        matches!(self.shared.mode, Mode::Record { .. })
+    }
+
+    pub fn cassette(&self) -> &Path {
+        &self.cassette
+    }
+
+    /// Recording: write the cassette. Replaying: fail if some recorded
+    /// interactions were never requested, which means the client changed.
+    pub fn finish(self) -> io::Result<()> {
+        let interactions = self.shared.interactions.lock().unwrap();
+        if self.is_recording() {
+            if let Some(dir) = self.cassette.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            let cassette = Cassette {
+                interactions: interactions.clone(),
+            };
+            let json = serde_json::to_vec_pretty(&cassette).map_err(io::Error::other)?;
+            return std::fs::write(&self.cassette, json);
+        }
+
+        let used = self.shared.used.lock().unwrap();
+        let unused: Vec<String> = interactions
+            .iter()
+            .zip(used.iter())
+            .filter(|(_, used)| !**used)
+            .map(|(it, _)| format!("{} {}", it.method, it.path))
+            .collect();
+        if unused.is_empty() {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!(
+                "recorded interactions not replayed: {}",
+                unused.join(", ")
+            )))
+        }
+    }
+}
+
+impl Drop for Vcr {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+async fn handle_connection(stream: TcpStream, shared: Arc<Shared>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let request = read_request(&mut reader).await?;
+
+    let response = match &shared.mode {
+        Mode::Record { upstream, http } => shared.record(request, upstream, http).await,
+        Mode::Replay => shared.replay(&request),
+    };
+
+    write_response(reader.into_inner(), &response).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_key() {
+        assert_eq!(redact_key("key=secret&alt=json"), "key=REDACTED&alt=json");
+        assert_eq!(redact_key("upload_id=abc"), "upload_id=abc");
+    }
+
+    #[test]
+    fn test_interaction_rewrites_base_url() {
+        let interaction = Interaction {
+            method: "POST".to_string(),
+            path: "/upload/v1beta/files".to_string(),
+            query: None,
+            status: 200,
+            headers: vec![(
+                "x-goog-upload-url".to_string(),
+                "{base_url}/upload/v1beta/files?upload_id=abc".to_string(),
+            )],
+            body: String::new(),
+            body_base64: None,
+        };
+
+        let response = interaction.response("http://127.0.0.1:9");
+        assert_eq!(
+            response.headers[0].1,
+            "http://127.0.0.1:9/upload/v1beta/files?upload_id=abc"
+        );
+    }
+}
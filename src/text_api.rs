@@ -0,0 +1,938 @@
+//! Plain text generation: a prompt in, the model's text out. Used by the
+//! prompt-enhancement and post-processing helpers and available to library
+//! users who don't want to build `generateContent` payloads themselves.
+
+use base64::Engine;
+use reqwest::{Client, Method};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::fmt;
+use std::future::Future;
+use std::str::FromStr;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{Instrument, Span, debug, field, info, info_span, warn};
+
+use crate::auth::{AuthMode, GEMINI_BASE_URL};
+use crate::budget::{Budget, BudgetExceeded};
+use crate::cache::ResponseCache;
+use crate::circuit_breaker::{CircuitBreaker, CircuitOpen};
+use crate::error::ErrorKind;
+use crate::proxy::ProxyConfig;
+use crate::redact::ApiKey;
+use crate::rt::Stopwatch;
+use crate::tls::TlsConfig;
+pub use crate::token_count::{ModalityTokens, TokenCount};
+use crate::transport::TransportConfig;
+
+const DEFAULT_TIMEOUT_SECS: u64 = 120;
+const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// Finish reasons that mean the answer was withheld
+const BLOCKED_FINISH_REASONS: &[&str] = &[
+    "SAFETY",
+    "RECITATION",
+    "BLOCKLIST",
+    "PROHIBITED_CONTENT",
+    "SPII",
+];
+
+/// Supported text models
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextModel {
+    /// Gemini 2.5 Flash - balanced speed and quality
+    #[default]
+    Flash,
+    /// Gemini 2.5 Flash-Lite - fastest and cheapest
+    FlashLite,
+    /// Gemini 2.5 Pro - strongest reasoning
+    Pro,
+}
+
+impl TextModel {
+    pub fn api_model_name(&self) -> &'static str {
+        match self {
+            TextModel::Flash => "gemini-2.5-flash",
+            TextModel::FlashLite => "gemini-2.5-flash-lite",
+            TextModel::Pro => "gemini-2.5-pro",
+        }
+    }
+
+    /// Most input tokens (prompt and media) one request may carry
+    pub fn input_token_limit(&self) -> u64 {
+        match self {
+            TextModel::Flash | TextModel::FlashLite | TextModel::Pro => 1_048_576,
+        }
+    }
+}
+
+impl fmt::Display for TextModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextModel::Flash => write!(f, "flash"),
+            TextModel::FlashLite => write!(f, "flash-lite"),
+            TextModel::Pro => write!(f, "pro"),
+        }
+    }
+}
+
+impl FromStr for TextModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "flash" | "gemini-2.5-flash" => Ok(TextModel::Flash),
+            "flash-lite" | "lite" | "gemini-2.5-flash-lite" => Ok(TextModel::FlashLite),
+            "pro" | "gemini-2.5-pro" => Ok(TextModel::Pro),
+            _ => Err(format!(
+                "Unknown text model: {}. Use 'flash', 'flash-lite' or 'pro'",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum TextError {
+    #[error("API key not found in environment (set GEMINI_API_KEY or GOOGLE_AI_KEY)")]
+    MissingApiKey,
+
+    #[error("Prompt is empty")]
+    EmptyPrompt,
+
+    #[error("Gemini API error ({status}, request {request_id}): {message}")]
+    ApiError {
+        status: u16,
+        message: String,
+        request_id: String,
+    },
+
+    #[error("Rate limited by API (request {request_id}). Retry after some time.")]
+    RateLimited { request_id: String },
+
+    #[error("Response blocked ({reason}, request {request_id})")]
+    Blocked { reason: String, request_id: String },
+
+    #[error("Invalid response from Gemini API: {0}")]
+    InvalidResponse(String),
+
+    #[error("Network error: {0}")]
+    NetworkError(#[source] reqwest::Error),
+
+    #[error("JSON parsing error: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("Max retries ({0}) exceeded")]
+    MaxRetriesExceeded(u32),
+
+    #[error(transparent)]
+    CircuitOpen(#[from] CircuitOpen),
+
+    #[error(transparent)]
+    BudgetExceeded(#[from] BudgetExceeded),
+}
+
+pub type Result<T> = std::result::Result<T, TextError>;
+
+impl From<reqwest::Error> for TextError {
+    fn from(err: reqwest::Error) -> Self {
+        TextError::NetworkError(crate::redact::reqwest_error(err))
+    }
+}
+
+impl TextError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            TextError::MissingApiKey => ErrorKind::Config,
+            TextError::EmptyPrompt => ErrorKind::InvalidInput,
+            TextError::ApiError { .. } | TextError::Blocked { .. } => ErrorKind::Api,
+            TextError::RateLimited { .. } => ErrorKind::RateLimited,
+            TextError::InvalidResponse(_) | TextError::JsonError(_) => ErrorKind::InvalidResponse,
+            TextError::NetworkError(_) => ErrorKind::Network,
+            TextError::MaxRetriesExceeded(_) => ErrorKind::RetriesExhausted,
+            TextError::CircuitOpen(_) => ErrorKind::CircuitOpen,
+            TextError::BudgetExceeded(_) => ErrorKind::BudgetExceeded,
+        }
+    }
+
+    /// HTTP status returned by the API, if any
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            TextError::ApiError { status, .. } => Some(*status),
+            TextError::RateLimited { .. } => Some(429),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TextClientConfig {
+    pub timeout_secs: u64,
+    pub max_retries: u32,
+    pub model: TextModel,
+    /// API host, e.g. a local mock server in tests
+    pub base_url: String,
+    /// Used by `with_config`; `with_http_client` keeps the given client's proxy
+    pub proxy: ProxyConfig,
+    /// Extra root certificates and builder hook, applied like `proxy`
+    pub tls: TlsConfig,
+    /// Connection pool, keep-alive and HTTP/2 settings, applied like `proxy`
+    pub transport: TransportConfig,
+    pub auth_mode: AuthMode,
+    /// Serve identical prompts from disk; `None` disables
+    pub cache: Option<ResponseCache>,
+    /// Shared with other clients of a batch to stop retrying when the API is down
+    pub circuit_breaker: Option<CircuitBreaker>,
+    /// Spend limit shared with other clients; requests fail once it is reached
+    pub budget: Option<Budget>,
+}
+
+impl Default for TextClientConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: DEFAULT_TIMEOUT_SECS,
+            max_retries: DEFAULT_MAX_RETRIES,
+            model: TextModel::default(),
+            base_url: GEMINI_BASE_URL.to_string(),
+            proxy: ProxyConfig::default(),
+            tls: TlsConfig::default(),
+            transport: TransportConfig::default(),
+            auth_mode: AuthMode::default(),
+            cache: None,
+            circuit_breaker: None,
+            budget: None,
+        }
+    }
+}
+
+/// Per-request generation options
+#[derive(Debug, Clone, Default)]
+pub struct TextGenConfig {
+    /// Instructions that apply to the whole exchange, e.g. a role or output rules
+    pub system_instruction: Option<String>,
+    /// 0.0 to 2.0; lower is more deterministic
+    pub temperature: Option<f32>,
+    pub max_output_tokens: Option<u32>,
+    /// Ask for a JSON document instead of free text
+    pub json_output: bool,
+    /// OpenAPI-style schema the JSON answer must follow; implies `json_output`
+    pub response_schema: Option<Value>,
+    /// Name of a [`CachedContext`] to answer against (`cachedContents/...`).
+    /// Its system instruction applies, so don't set another one here
+    pub cached_content: Option<String>,
+}
+
+impl TextGenConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_system_instruction(mut self, instruction: impl Into<String>) -> Self {
+        self.system_instruction = Some(instruction.into());
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn with_max_output_tokens(mut self, tokens: u32) -> Self {
+        self.max_output_tokens = Some(tokens);
+        self
+    }
+
+    pub fn with_json_output(mut self) -> Self {
+        self.json_output = true;
+        self
+    }
+
+    pub fn with_response_schema(mut self, schema: Value) -> Self {
+        self.json_output = true;
+        self.response_schema = Some(schema);
+        self
+    }
+
+    pub fn with_cached_content(mut self, name: impl Into<String>) -> Self {
+        self.cached_content = Some(name.into());
+        self
+    }
+}
+
+/// Image, audio, video or PDF sent along with a prompt
+#[derive(Debug, Clone, PartialEq)]
+pub enum MediaPart {
+    /// Sent base64-encoded in the request (requests up to 20MB)
+    Inline { mime_type: String, data: Vec<u8> },
+    /// Uploaded with the File API
+    FileUri { mime_type: String, uri: String },
+}
+
+impl MediaPart {
+    fn to_json(&self) -> Value {
+        match self {
+            MediaPart::Inline { mime_type, data } => json!({
+                "inline_data": {
+                    "mime_type": mime_type,
+                    "data": base64::engine::general_purpose::STANDARD.encode(data),
+                }
+            }),
+            MediaPart::FileUri { mime_type, uri } => json!({
+                "file_data": { "mime_type": mime_type, "file_uri": uri }
+            }),
+        }
+    }
+}
+
+/// Context stored by the API with Gemini context caching, so later prompts
+/// can refer to it by name instead of resending it (and are billed less for it)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedContext {
+    /// `cachedContents/...`
+    pub name: String,
+    /// RFC 3339 time the API deletes it
+    #[serde(default)]
+    pub expire_time: Option<String>,
+    /// Tokens of the cached context, as reported by the API
+    #[serde(default)]
+    pub token_count: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TextClient {
+    client: Client,
+    api_key: ApiKey,
+    config: TextClientConfig,
+}
+
+impl TextClient {
+    pub fn new(api_key: String) -> Result<Self> {
+        Self::with_config(api_key, TextClientConfig::default())
+    }
+
+    pub fn with_config(api_key: String, config: TextClientConfig) -> Result<Self> {
+        let builder = crate::proxy::apply(
+            crate::rt::client_builder(config.timeout_secs),
+            &config.proxy,
+        )?;
+        let builder = crate::transport::apply(builder, &config.transport);
+        let client = crate::tls::apply(builder, &config.tls)?.build()?;
+
+        Ok(Self::with_http_client(client, api_key, config))
+    }
+
+    /// Build on an existing HTTP client, sharing its connection pool
+    pub fn with_http_client(client: Client, api_key: String, config: TextClientConfig) -> Self {
+        Self {
+            client,
+            api_key: api_key.into(),
+            config,
+        }
+    }
+
+    pub fn model(&self) -> TextModel {
+        self.config.model
+    }
+
+    fn build_payload(prompt: &str, gen_config: Option<&TextGenConfig>) -> Value {
+        let mut payload = json!({
+            "contents": [{ "role": "user", "parts": [{ "text": prompt }] }],
+        });
+        let Some(cfg) = gen_config else {
+            return payload;
+        };
+
+        if let Some(instruction) = &cfg.system_instruction {
+            payload["systemInstruction"] = json!({ "parts": [{ "text": instruction }] });
+        }
+        if let Some(name) = &cfg.cached_content {
+            payload["cachedContent"] = json!(name);
+        }
+        let mut generation = serde_json::Map::new();
+        if let Some(temperature) = cfg.temperature {
+            generation.insert("temperature".to_string(), json!(temperature));
+        }
+        if let Some(tokens) = cfg.max_output_tokens {
+            generation.insert("maxOutputTokens".to_string(), json!(tokens));
+        }
+        if cfg.json_output || cfg.response_schema.is_some() {
+            generation.insert("responseMimeType".to_string(), json!("application/json"));
+        }
+        if let Some(schema) = &cfg.response_schema {
+            generation.insert("responseSchema".to_string(), schema.clone());
+        }
+        if !generation.is_empty() {
+            payload["generationConfig"] = Value::Object(generation);
+        }
+        payload
+    }
+
+    /// Like `build_payload`, with `media` placed before the prompt
+    fn build_media_payload(
+        prompt: &str,
+        media: &[MediaPart],
+        gen_config: Option<&TextGenConfig>,
+    ) -> Value {
+        let mut payload = Self::build_payload(prompt, gen_config);
+        let mut parts: Vec<Value> = media.iter().map(MediaPart::to_json).collect();
+        parts.push(json!({ "text": prompt }));
+        payload["contents"][0]["parts"] = Value::Array(parts);
+        payload
+    }
+
+    /// Text parts of the first candidate, joined
+    fn parse_text(data: &Value, request_id: &str) -> Result<String> {
+        let blocked = |reason: &str| TextError::Blocked {
+            reason: reason.to_string(),
+            request_id: request_id.to_string(),
+        };
+        if let Some(reason) = data["promptFeedback"]["blockReason"].as_str() {
+            return Err(blocked(reason));
+        }
+
+        let candidate = &data["candidates"][0];
+        let finish_reason = candidate["finishReason"].as_str().unwrap_or_default();
+        if BLOCKED_FINISH_REASONS.contains(&finish_reason) {
+            return Err(blocked(finish_reason));
+        }
+
+        let parts = candidate["content"]["parts"]
+            .as_array()
+            .ok_or_else(|| TextError::InvalidResponse("Missing parts in response".to_string()))?;
+        let text: String = parts
+            .iter()
+            // Thought summaries aren't part of the answer
+            .filter(|part| !part["thought"].as_bool().unwrap_or_default())
+            .filter_map(|part| part["text"].as_str())
+            .collect();
+        if finish_reason == "MAX_TOKENS" {
+            warn!("Response was cut off at the output token limit");
+        }
+        Ok(text)
+    }
+
+    async fn send_request(&self, payload: &Value, request_id: &str) -> Result<String> {
+        let url = format!(
+            "{}/v1beta/models/{}:generateContent",
+            self.config.base_url,
+            self.config.model.api_model_name()
+        );
+
+        debug!(
+            "Sending text request to Gemini API (model: {})",
+            self.config.model
+        );
+
+        let data = self
+            .send_json(Method::POST, &url, Some(payload), request_id)
+            .await?;
+        crate::metrics::record_gemini_usage("text", &data);
+        crate::usage::record_gemini(
+            "text",
+            self.config.model.api_model_name(),
+            &data,
+            self.config.budget.as_ref(),
+        );
+
+        Self::parse_text(&data, request_id)
+    }
+
+    /// Send one request and return the JSON body of a successful response
+    async fn send_json(
+        &self,
+        method: Method,
+        url: &str,
+        payload: Option<&Value>,
+        request_id: &str,
+    ) -> Result<Value> {
+        let mut request = self
+            .config
+            .auth_mode
+            .request(&self.client, method, url, &self.api_key)
+            .timeout(Duration::from_secs(self.config.timeout_secs));
+        if let Some(payload) = payload {
+            request = request
+                .header("Content-Type", "application/json")
+                .json(payload);
+        }
+        let response = request.send().await?;
+
+        let status = response.status();
+        debug!("Received response with status: {}", status);
+
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            crate::metrics::record_rate_limited("text");
+            return Err(TextError::RateLimited {
+                request_id: request_id.to_string(),
+            });
+        }
+
+        if !status.is_success() {
+            let error_text = self
+                .api_key
+                .scrub(response.text().await.unwrap_or_default());
+            return Err(TextError::ApiError {
+                status: status.as_u16(),
+                message: error_text,
+                request_id: request_id.to_string(),
+            });
+        }
+
+        Ok(response.json().await?)
+    }
+
+    fn is_retryable_error(err: &TextError) -> bool {
+        crate::error::is_retryable(err.kind(), err.status())
+    }
+
+    /// Answer `prompt` with the model's defaults
+    pub async fn generate(&self, prompt: &str) -> Result<String> {
+        self.generate_with_config(prompt, None).await
+    }
+
+    /// Answer `prompt` with a system instruction, sampling or JSON options
+    pub async fn generate_with_config(
+        &self,
+        prompt: &str,
+        gen_config: Option<&TextGenConfig>,
+    ) -> Result<String> {
+        if prompt.trim().is_empty() {
+            return Err(TextError::EmptyPrompt);
+        }
+        let payload = Self::build_payload(prompt, gen_config);
+        self.generate_cached(&payload).await
+    }
+
+    /// Answer `prompt` about images, audio, video or documents
+    pub async fn generate_with_media(
+        &self,
+        prompt: &str,
+        media: &[MediaPart],
+        gen_config: Option<&TextGenConfig>,
+    ) -> Result<String> {
+        if prompt.trim().is_empty() {
+            return Err(TextError::EmptyPrompt);
+        }
+        let payload = Self::build_media_payload(prompt, media, gen_config);
+        self.generate_cached(&payload).await
+    }
+
+    async fn generate_cached(&self, payload: &Value) -> Result<String> {
+        let Some(cache) = &self.config.cache else {
+            return self.generate_with_retry(payload).await;
+        };
+
+        let key = ResponseCache::key(&[
+            self.config.model.api_model_name().as_bytes(),
+            payload.to_string().as_bytes(),
+        ]);
+        if let Some(text) = cache.get_json::<String>(&key).await {
+            info!("Using cached text response");
+            return Ok(text);
+        }
+
+        let text = self.generate_with_retry(payload).await?;
+        cache.put_json(&key, &text).await;
+        Ok(text)
+    }
+
+    async fn generate_with_retry(&self, payload: &Value) -> Result<String> {
+        let request_id = crate::request_id::next();
+        let span = info_span!(
+            "text_request",
+            request_id = %request_id,
+            model = %self.config.model,
+            payload_bytes = payload.to_string().len(),
+            attempt = field::Empty,
+            latency_ms = field::Empty,
+        );
+        self.with_retry(span, || self.send_request(payload, &request_id))
+            .await
+    }
+
+    /// Run `send` with the breaker, budget and backoff policy of this client
+    async fn with_retry<T, F, Fut>(&self, span: Span, send: F) -> Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        async {
+            let mut last_error = None;
+            let mut retry_count = 0;
+
+            while retry_count < self.config.max_retries {
+                if let Some(breaker) = &self.config.circuit_breaker {
+                    breaker.check()?;
+                }
+                if let Some(budget) = &self.config.budget {
+                    budget.check()?;
+                }
+                span.record("attempt", retry_count + 1);
+                let stopwatch = Stopwatch::start();
+                let result = send().await;
+                span.record("latency_ms", stopwatch.elapsed_ms());
+                if let Some(breaker) = &self.config.circuit_breaker {
+                    breaker.record(&result, Self::is_retryable_error);
+                }
+                crate::metrics::record_request("text", result.is_ok(), stopwatch.elapsed_ms());
+                debug!(
+                    "Attempt {} finished in {}ms",
+                    retry_count + 1,
+                    stopwatch.elapsed_ms()
+                );
+
+                match result {
+                    Ok(response) => {
+                        info!("Text request successful");
+                        return Ok(response);
+                    }
+                    Err(e) => {
+                        if Self::is_retryable_error(&e)
+                            && retry_count + 1 < self.config.max_retries
+                            && self
+                                .config
+                                .circuit_breaker
+                                .as_ref()
+                                .is_none_or(CircuitBreaker::try_retry)
+                        {
+                            let delay = if matches!(e, TextError::RateLimited { .. }) {
+                                Duration::from_secs(30 * (retry_count as u64 + 1))
+                            } else {
+                                Duration::from_secs(2u64.pow(retry_count))
+                            };
+                            warn!(
+                                "Request failed (attempt {}/{}): {}. Retrying in {:?}...",
+                                retry_count + 1,
+                                self.config.max_retries,
+                                e,
+                                delay
+                            );
+                            crate::metrics::record_retry("text");
+                            crate::rt::sleep(delay).await;
+                            retry_count += 1;
+                            last_error = Some(e);
+                        } else {
+                            return Err(e);
+                        }
+                    }
+                }
+            }
+
+            Err(last_error.unwrap_or(TextError::MaxRetriesExceeded(self.config.max_retries)))
+        }
+        .instrument(span.clone())
+        .await
+    }
+
+    /// Count the input tokens of `prompt` and `media` without generating
+    /// anything. `prompt` may be empty when only the media is of interest
+    pub async fn count_tokens(&self, prompt: &str, media: &[MediaPart]) -> Result<TokenCount> {
+        if prompt.trim().is_empty() && media.is_empty() {
+            return Err(TextError::EmptyPrompt);
+        }
+        let mut parts: Vec<Value> = media.iter().map(MediaPart::to_json).collect();
+        if !prompt.trim().is_empty() {
+            parts.push(json!({ "text": prompt }));
+        }
+        let payload = json!({ "contents": [{ "role": "user", "parts": parts }] });
+        let url = format!(
+            "{}/v1beta/models/{}:countTokens",
+            self.config.base_url,
+            self.config.model.api_model_name()
+        );
+        let request_id = crate::request_id::next();
+        let span = info_span!(
+            "text_request",
+            request_id = %request_id,
+            model = %self.config.model,
+            payload_bytes = payload.to_string().len(),
+            attempt = field::Empty,
+            latency_ms = field::Empty,
+        );
+        let data = self
+            .with_retry(span, || {
+                self.send_json(Method::POST, &url, Some(&payload), &request_id)
+            })
+            .await?;
+        Self::parse_token_count(&data)
+    }
+
+    fn parse_token_count(data: &Value) -> Result<TokenCount> {
+        TokenCount::from_response(data)
+            .ok_or_else(|| TextError::InvalidResponse("Missing totalTokens".to_string()))
+    }
+
+    /// Store `context` (and `system_instruction`) with Gemini context caching
+    /// for `ttl_secs`. The model needs a minimum context size (about a
+    /// thousand tokens for Flash); smaller contexts are rejected with an
+    /// `ApiError`
+    pub async fn create_cached_context(
+        &self,
+        context: &str,
+        system_instruction: Option<&str>,
+        ttl_secs: u64,
+    ) -> Result<CachedContext> {
+        if context.trim().is_empty() {
+            return Err(TextError::EmptyPrompt);
+        }
+        let payload = Self::build_cache_payload(
+            self.config.model.api_model_name(),
+            context,
+            system_instruction,
+            ttl_secs,
+        );
+        let url = format!("{}/v1beta/cachedContents", self.config.base_url);
+        let request_id = crate::request_id::next();
+        let span = info_span!(
+            "text_request",
+            request_id = %request_id,
+            model = %self.config.model,
+            payload_bytes = payload.to_string().len(),
+            attempt = field::Empty,
+            latency_ms = field::Empty,
+        );
+        let data = self
+            .with_retry(span, || {
+                self.send_json(Method::POST, &url, Some(&payload), &request_id)
+            })
+            .await?;
+        Self::parse_cached_context(&data)
+    }
+
+    /// Look up a cached context; fails with an `ApiError` once it has expired
+    pub async fn get_cached_context(&self, name: &str) -> Result<CachedContext> {
+        let url = format!("{}/v1beta/{}", self.config.base_url, name);
+        let request_id = crate::request_id::next();
+        let span = info_span!(
+            "text_request",
+            request_id = %request_id,
+            model = %self.config.model,
+            payload_bytes = 0,
+            attempt = field::Empty,
+            latency_ms = field::Empty,
+        );
+        let data = self
+            .with_retry(span, || {
+                self.send_json(Method::GET, &url, None, &request_id)
+            })
+            .await?;
+        Self::parse_cached_context(&data)
+    }
+
+    /// [`create_cached_context`](Self::create_cached_context), reusing the
+    /// context created for the same model and text by an earlier run while
+    /// the API still has it. Names are remembered in the response cache, so
+    /// without one this always creates a new context
+    pub async fn cached_context(
+        &self,
+        context: &str,
+        system_instruction: Option<&str>,
+        ttl_secs: u64,
+    ) -> Result<CachedContext> {
+        let key = ResponseCache::key(&[
+            b"cachedContents",
+            self.config.model.api_model_name().as_bytes(),
+            system_instruction.unwrap_or_default().as_bytes(),
+            context.as_bytes(),
+        ]);
+        if let Some(cache) = &self.config.cache
+            && let Some(known) = cache.get_json::<CachedContext>(&key).await
+        {
+            match self.get_cached_context(&known.name).await {
+                Ok(existing) => {
+                    info!("Reusing cached context {}", existing.name);
+                    return Ok(existing);
+                }
+                Err(e) => debug!("Cached context {} is gone: {}", known.name, e),
+            }
+        }
+
+        let created = self
+            .create_cached_context(context, system_instruction, ttl_secs)
+            .await?;
+        if let Some(cache) = &self.config.cache {
+            cache.put_json(&key, &created).await;
+        }
+        Ok(created)
+    }
+
+    fn build_cache_payload(
+        model: &str,
+        context: &str,
+        system_instruction: Option<&str>,
+        ttl_secs: u64,
+    ) -> Value {
+        let mut payload = json!({
+            "model": format!("models/{}", model),
+            "contents": [{ "role": "user", "parts": [{ "text": context }] }],
+            "ttl": format!("{}s", ttl_secs),
+        });
+        if let Some(instruction) = system_instruction {
+            payload["systemInstruction"] = json!({ "parts": [{ "text": instruction }] });
+        }
+        payload
+    }
+
+    fn parse_cached_context(data: &Value) -> Result<CachedContext> {
+        let name = data["name"]
+            .as_str()
+            .ok_or_else(|| TextError::InvalidResponse("Missing cached context name".to_string()))?;
+        Ok(CachedContext {
+            name: name.to_string(),
+            expire_time: data["expireTime"].as_str().map(str::to_string),
+            token_count: data["usageMetadata"]["totalTokenCount"].as_u64(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_model_from_str() {
+        assert_eq!(TextModel::from_str("flash").unwrap(), TextModel::Flash);
+        assert_eq!(TextModel::from_str("LITE").unwrap(), TextModel::FlashLite);
+        assert_eq!(
+            TextModel::from_str("gemini-2.5-pro").unwrap(),
+            TextModel::Pro
+        );
+        assert!(TextModel::from_str("ultra").is_err());
+        assert_eq!(TextModel::FlashLite.to_string(), "flash-lite");
+    }
+
+    #[test]
+    fn test_build_payload() {
+        let plain = TextClient::build_payload("Hi", None);
+        assert_eq!(plain["contents"][0]["parts"][0]["text"], "Hi");
+        assert!(plain.get("generationConfig").is_none());
+        assert!(plain.get("systemInstruction").is_none());
+
+        let cfg = TextGenConfig::new()
+            .with_system_instruction("Answer in French")
+            .with_temperature(0.5)
+            .with_max_output_tokens(256)
+            .with_json_output();
+        let payload = TextClient::build_payload("Hi", Some(&cfg));
+        assert_eq!(
+            payload["systemInstruction"]["parts"][0]["text"],
+            "Answer in French"
+        );
+        assert_eq!(payload["generationConfig"]["temperature"], 0.5);
+        assert_eq!(payload["generationConfig"]["maxOutputTokens"], 256);
+        assert_eq!(
+            payload["generationConfig"]["responseMimeType"],
+            "application/json"
+        );
+        assert!(payload["generationConfig"].get("responseSchema").is_none());
+
+        let schema = json!({"type": "ARRAY", "items": {"type": "STRING"}});
+        let cfg = TextGenConfig::new().with_response_schema(schema.clone());
+        let payload = TextClient::build_payload("Hi", Some(&cfg));
+        assert_eq!(payload["generationConfig"]["responseSchema"], schema);
+        assert_eq!(
+            payload["generationConfig"]["responseMimeType"],
+            "application/json"
+        );
+    }
+
+    #[test]
+    fn test_build_media_payload() {
+        let media = [
+            MediaPart::Inline {
+                mime_type: "image/png".to_string(),
+                data: b"png".to_vec(),
+            },
+            MediaPart::FileUri {
+                mime_type: "application/pdf".to_string(),
+                uri: "https://example.com/files/abc".to_string(),
+            },
+        ];
+        let payload = TextClient::build_media_payload("Describe", &media, None);
+        let parts = &payload["contents"][0]["parts"];
+        assert_eq!(parts[0]["inline_data"]["mime_type"], "image/png");
+        assert_eq!(parts[0]["inline_data"]["data"], "cG5n");
+        assert_eq!(
+            parts[1]["file_data"]["file_uri"],
+            "https://example.com/files/abc"
+        );
+        assert_eq!(parts[2]["text"], "Describe");
+    }
+
+    #[test]
+    fn test_cached_content_payloads() {
+        let cfg = TextGenConfig::new().with_cached_content("cachedContents/abc");
+        let payload = TextClient::build_payload("Question?", Some(&cfg));
+        assert_eq!(payload["cachedContent"], "cachedContents/abc");
+        assert!(payload.get("systemInstruction").is_none());
+
+        let payload =
+            TextClient::build_cache_payload("gemini-2.5-flash", "Context", Some("Be brief"), 600);
+        assert_eq!(payload["model"], "models/gemini-2.5-flash");
+        assert_eq!(payload["ttl"], "600s");
+        assert_eq!(payload["contents"][0]["parts"][0]["text"], "Context");
+        assert_eq!(payload["systemInstruction"]["parts"][0]["text"], "Be brief");
+
+        let context = TextClient::parse_cached_context(&json!({
+            "name": "cachedContents/abc",
+            "expireTime": "2024-06-01T12:00:00Z",
+            "usageMetadata": {"totalTokenCount": 4096}
+        }))
+        .unwrap();
+        assert_eq!(context.name, "cachedContents/abc");
+        assert_eq!(context.token_count, Some(4096));
+        assert!(TextClient::parse_cached_context(&json!({})).is_err());
+    }
+
+    #[test]
+    fn test_parse_token_count() {
+        let count = TextClient::parse_token_count(&json!({
+            "totalTokens": 1856,
+            "promptTokensDetails": [
+                {"modality": "AUDIO", "tokenCount": 1600},
+                {"modality": "TEXT", "tokenCount": 256}
+            ]
+        }))
+        .unwrap();
+        assert_eq!(count.total_tokens, 1856);
+        assert_eq!(count.by_modality.len(), 2);
+        assert_eq!(count.by_modality[0].modality, "AUDIO");
+        assert_eq!(count.by_modality[1].tokens, 256);
+
+        let bare = TextClient::parse_token_count(&json!({"totalTokens": 3})).unwrap();
+        assert!(bare.by_modality.is_empty());
+        assert!(TextClient::parse_token_count(&json!({})).is_err());
+    }
+
+    #[test]
+    fn test_parse_text() {
+        let data = json!({
+            "candidates": [{
+                "content": {"parts": [
+                    {"text": "Planning...", "thought": true},
+                    {"text": "Hello "},
+                    {"text": "world"}
+                ]},
+                "finishReason": "STOP"
+            }]
+        });
+        assert_eq!(TextClient::parse_text(&data, "r").unwrap(), "Hello world");
+
+        let blocked = json!({"promptFeedback": {"blockReason": "SAFETY"}});
+        assert!(matches!(
+            TextClient::parse_text(&blocked, "r"),
+            Err(TextError::Blocked { ref reason, .. }) if reason == "SAFETY"
+        ));
+
+        let recited = json!({"candidates": [{"finishReason": "RECITATION"}]});
+        assert!(matches!(
+            TextClient::parse_text(&recited, "r"),
+            Err(TextError::Blocked { .. })
+        ));
+
+        assert!(matches!(
+            TextClient::parse_text(&json!({"candidates": []}), "r"),
+            Err(TextError::InvalidResponse(_))
+        ));
+    }
+}
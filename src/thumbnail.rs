@@ -0,0 +1,380 @@
+use anyhow::{Context, Result};
+use clap::Parser;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tracing::{Instrument, debug, info, info_span};
+
+use transcript_tool::cli::{self, CommonArgs, init_logging};
+use transcript_tool::imagen_api::{AspectRatio, ImageModel, ImageSize};
+use transcript_tool::imagen_edit_api::{ImageEditClientConfig, ImageEditConfig, InputImage};
+use transcript_tool::storage::{OutputStorage, Storage};
+use transcript_tool::text_api::MediaPart;
+use transcript_tool::video_frames::DEFAULT_SCENE_THRESHOLD;
+use transcript_tool::{
+    Clients, Frame, GeneratedImage, ImageFormat, TextClientConfig, TextModel, VisionClient,
+    convert_image, extract_frame_at, extract_scene_frames, resize_image,
+};
+
+/// Candidate frames are kept at up to this width so the largest preset isn't upscaled
+const CANDIDATE_WIDTH: u32 = 1920;
+
+#[derive(Parser, Debug)]
+#[command(name = "thumbnail")]
+#[command(version)]
+#[command(about = "Make platform-sized thumbnails from the best frame of a video")]
+#[command(after_help = "EXAMPLES:
+    thumbnail talk.mp4
+    thumbnail talk.mp4 --title \"Why Rust?\" --size youtube --size shorts
+    thumbnail talk.mp4 --at 754 --style \"bold comic look, high contrast\"
+    thumbnail vlog.mov --size 1920x1080 -f png -o thumbs/
+
+SIZES:
+    youtube   1280x720  (16:9)
+    x         1600x900  (16:9)
+    square    1080x1080 (1:1, Instagram)
+    portrait  1080x1350 (4:5, Instagram feed)
+    shorts    1080x1920 (9:16, Shorts, TikTok, Reels)
+    WxH       any other size")]
+pub struct Args {
+    /// Video file
+    #[arg(value_name = "VIDEO")]
+    input: PathBuf,
+
+    /// Title to render onto the thumbnail with the image edit model
+    #[arg(long)]
+    title: Option<String>,
+
+    /// Look to give the thumbnail with the image edit model, e.g. "warm, cinematic"
+    #[arg(long)]
+    style: Option<String>,
+
+    /// Use the frame at this many seconds instead of letting Gemini pick one
+    #[arg(long, value_name = "SECONDS")]
+    at: Option<f64>,
+
+    /// Output size, a preset or WxH (repeatable)
+    #[arg(long = "size", value_name = "SIZE", default_value = "youtube")]
+    sizes: Vec<ThumbnailSize>,
+
+    /// Output image format: jpg, png, webp
+    #[arg(short = 'f', long, default_value = "jpg")]
+    format: ImageFormat,
+
+    /// Output directory, or an s3:// / gs:// URI [default: next to the video]
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Most candidate frames offered to the model
+    #[arg(
+        long,
+        default_value = "12",
+        value_parser = clap::value_parser!(u16).range(1..=100)
+    )]
+    candidates: u16,
+
+    /// Fraction of the picture that must change for a new candidate frame (0-1)
+    #[arg(long, default_value_t = DEFAULT_SCENE_THRESHOLD)]
+    threshold: f64,
+
+    /// Model that picks the frame: flash (default), flash-lite, pro
+    #[arg(
+        short = 'm',
+        long,
+        env = "FLASHECHO_TEXT_MODEL",
+        default_value = "flash"
+    )]
+    model: String,
+
+    /// Image edit model for --title/--style: 3pro (default), 2.5-flash
+    #[arg(long, env = "FLASHECHO_IMAGE_MODEL", default_value = "3pro")]
+    image_model: String,
+
+    /// Timeout of each API request in seconds
+    #[arg(short, long, default_value = "120")]
+    timeout: u64,
+
+    #[command(flatten)]
+    common: CommonArgs,
+}
+
+/// Pixel size of one output thumbnail
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ThumbnailSize {
+    /// Preset name, or `WxH` for custom sizes; used in the file name
+    name: String,
+    width: u32,
+    height: u32,
+}
+
+const PRESETS: &[(&str, u32, u32)] = &[
+    ("youtube", 1280, 720),
+    ("x", 1600, 900),
+    ("square", 1080, 1080),
+    ("portrait", 1080, 1350),
+    ("shorts", 1080, 1920),
+];
+
+impl FromStr for ThumbnailSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let name = s.trim().to_lowercase();
+        if let Some(&(name, width, height)) = PRESETS.iter().find(|(n, _, _)| *n == name) {
+            return Ok(Self {
+                name: name.to_string(),
+                width,
+                height,
+            });
+        }
+        let invalid = || {
+            format!(
+                "Invalid size: {}. Use youtube, x, square, portrait, shorts or WxH",
+                s
+            )
+        };
+        let (width, height) = name.split_once('x').ok_or_else(invalid)?;
+        let width: u32 = width.parse().map_err(|_| invalid())?;
+        let height: u32 = height.parse().map_err(|_| invalid())?;
+        if !(16..=4096).contains(&width) || !(16..=4096).contains(&height) {
+            return Err(format!("Size {} is out of range (16-4096 per side)", s));
+        }
+        Ok(Self {
+            name: format!("{}x{}", width, height),
+            width,
+            height,
+        })
+    }
+}
+
+impl fmt::Display for ThumbnailSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}x{})", self.name, self.width, self.height)
+    }
+}
+
+/// `talk.mp4` + youtube -> `talk.thumb-youtube.jpg`
+fn output_name(input: &Path, size: &ThumbnailSize, format: ImageFormat) -> String {
+    let stem = input
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "video".to_string());
+    format!("{}.thumb-{}.{}", stem, size.name, format.extension())
+}
+
+/// Instruction for the image edit model
+fn edit_prompt(title: Option<&str>, style: Option<&str>) -> String {
+    let mut prompt = String::from(
+        "Turn this video frame into a video thumbnail. Keep the people and the scene \
+         recognizable, and improve sharpness, lighting and color.",
+    );
+    match title.map(str::trim).filter(|t| !t.is_empty()) {
+        Some(title) => prompt.push_str(&format!(
+            " Add the title \"{}\" in large, bold, legible lettering with strong contrast, \
+             spelled exactly as given and placed where it doesn't cover faces.",
+            title
+        )),
+        None => prompt.push_str(" Don't add any text."),
+    }
+    if let Some(style) = style.map(str::trim).filter(|s| !s.is_empty()) {
+        prompt.push_str(&format!(" Style: {}.", style));
+    }
+    prompt
+}
+
+/// Run with parsed arguments; shared by this binary and its `flashecho` subcommand
+pub async fn run(args: Args) -> Result<()> {
+    init_logging(args.common.verbose, args.common.log_format);
+    args.common.install_usage_ledger("thumbnail");
+
+    let span = info_span!("file", file = %args.input.display());
+    thumbnail(args).instrument(span).await
+}
+
+async fn thumbnail(args: Args) -> Result<()> {
+    if !args.input.exists() {
+        anyhow::bail!("Input file does not exist: {:?}", args.input);
+    }
+    let image_model: ImageModel = args
+        .image_model
+        .parse()
+        .map_err(|e: String| anyhow::anyhow!(e))?;
+    let profile = args.common.load_profile()?;
+    let clients =
+        Clients::new(cli::gemini_api_key(&profile)?).context("Failed to create HTTP client")?;
+    let budget = args
+        .common
+        .budget()
+        .context("Failed to read usage ledger for --budget-since")?;
+
+    let frame = match args.at {
+        Some(at) => extract_frame_at(&args.input, at, CANDIDATE_WIDTH)
+            .instrument(info_span!("stage", stage = "extract_frames"))
+            .await
+            .context("Failed to extract frame")?,
+        None => {
+            let model: TextModel = args.model.parse().map_err(|e: String| anyhow::anyhow!(e))?;
+            let vision = VisionClient::new(clients.text(TextClientConfig {
+                timeout_secs: args.timeout,
+                max_retries: args.common.max_retries,
+                model,
+                cache: args.common.response_cache(),
+                budget: budget.clone(),
+                ..Default::default()
+            }));
+            pick_frame(&vision, &args).await?
+        }
+    };
+
+    let MediaPart::Inline { mime_type, data } = frame.image else {
+        anyhow::bail!("Extracted frame wasn't inline image data");
+    };
+    let mut image = GeneratedImage {
+        data,
+        mime_type,
+        provenance: None,
+    };
+    if args.title.is_some() || args.style.is_some() {
+        let client = clients.image_edit(ImageEditClientConfig {
+            timeout_secs: args.timeout,
+            max_retries: args.common.max_retries,
+            model: image_model,
+            cache: args.common.response_cache(),
+            budget,
+            ..Default::default()
+        });
+        // Ask for the shape of the first size so less of the edit is cropped away
+        let first = &args.sizes[0];
+        let mut edit_config = ImageEditConfig::new()
+            .with_aspect_ratio(AspectRatio::nearest(first.width, first.height));
+        if image_model.supports_image_size() {
+            edit_config = edit_config.with_size(ImageSize::covering(first.width, first.height));
+        }
+        let input = InputImage::from_bytes(image.data, image.mime_type);
+        let pb = cli::spinner(args.common.quiet, "Editing thumbnail...".into());
+        let result = client
+            .edit_images_with_config(
+                &edit_prompt(args.title.as_deref(), args.style.as_deref()),
+                &[input],
+                Some(&edit_config),
+            )
+            .instrument(info_span!("stage", stage = "edit"))
+            .await;
+        if let Some(pb) = pb {
+            pb.finish_and_clear();
+        }
+        image = result.map_err(|e| anyhow::anyhow!("Image edit failed: {}", e))?;
+    }
+
+    let target = match &args.output {
+        Some(output) => output.clone(),
+        None => match args.input.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_string_lossy().to_string(),
+            _ => ".".to_string(),
+        },
+    };
+    let storage = OutputStorage::open(&target)
+        .with_context(|| format!("Invalid output target: {}", target))?;
+    for size in &args.sizes {
+        let resized = async {
+            let resized = resize_image(&image, size.width, size.height).await?;
+            convert_image(&resized, args.format, None).await
+        }
+        .instrument(info_span!("stage", stage = "resize"))
+        .await
+        .with_context(|| format!("Failed to resize to {}", size))?;
+        let key = output_name(&args.input, size, args.format);
+        storage
+            .put(&key, &resized.data)
+            .instrument(info_span!("stage", stage = "write"))
+            .await
+            .context("Failed to write thumbnail")?;
+        let location = storage.location(&key);
+        info!("Thumbnail {} written to {}", size, location);
+        if !args.common.quiet {
+            println!("Saved to: {}", location);
+        }
+    }
+    Ok(())
+}
+
+/// Extract candidate frames and let the model pick one
+async fn pick_frame(vision: &VisionClient, args: &Args) -> Result<Frame> {
+    let pb = cli::spinner(
+        args.common.quiet,
+        "Extracting candidate frames with ffmpeg...".into(),
+    );
+    let result = extract_scene_frames(
+        &args.input,
+        args.threshold,
+        args.candidates.into(),
+        CANDIDATE_WIDTH,
+    )
+    .instrument(info_span!("stage", stage = "extract_frames"))
+    .await;
+    if let Some(pb) = pb {
+        pb.finish_and_clear();
+    }
+    let mut frames = result.context("Failed to extract candidate frames")?.frames;
+    if frames.len() <= 1 {
+        return frames
+            .pop()
+            .with_context(|| format!("ffmpeg found no video frames in {:?}", args.input));
+    }
+
+    let pb = cli::spinner(
+        args.common.quiet,
+        format!("Picking the best of {} frames...", frames.len()),
+    );
+    let result = vision
+        .pick_thumbnail(&frames, args.title.as_deref())
+        .instrument(info_span!("stage", stage = "pick"))
+        .await;
+    if let Some(pb) = pb {
+        pb.finish_and_clear();
+    }
+    let pick = result.map_err(|e| anyhow::anyhow!("Frame selection failed: {}", e))?;
+    let frame = frames.swap_remove(pick.frame);
+    debug!("Picked frame at {:.1}s: {}", frame.time_secs, pick.reason);
+    if !args.common.quiet {
+        println!("Picked frame at {:.1}s: {}", frame.time_secs, pick.reason);
+    }
+    Ok(frame)
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    run(Args::parse()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thumbnail_size_from_str() {
+        let youtube: ThumbnailSize = "YouTube".parse().unwrap();
+        assert_eq!((youtube.width, youtube.height), (1280, 720));
+        let custom: ThumbnailSize = "1920x1080".parse().unwrap();
+        assert_eq!(custom.name, "1920x1080");
+        assert!("huge".parse::<ThumbnailSize>().is_err());
+        assert!("10x10".parse::<ThumbnailSize>().is_err());
+    }
+
+    #[test]
+    fn test_output_name() {
+        let shorts: ThumbnailSize = "shorts".parse().unwrap();
+        assert_eq!(
+            output_name(Path::new("videos/talk.mp4"), &shorts, ImageFormat::Png),
+            "talk.thumb-shorts.png"
+        );
+    }
+
+    #[test]
+    fn test_edit_prompt() {
+        let prompt = edit_prompt(Some(" Why Rust? "), Some("warm"));
+        assert!(prompt.contains("the title \"Why Rust?\""));
+        assert!(prompt.ends_with(" Style: warm."));
+        assert!(edit_prompt(None, Some("noir")).contains("Don't add any text."));
+    }
+}
@@ -0,0 +1,272 @@
+//! Segment timestamps as models write them: `MM:SS`, or `HH:MM:SS` from an
+//! hour on, optionally with a fraction of a second after `.` or `,`. Minutes
+//! past 59 in the two-part form (`75:00`) are read as well, since models keep
+//! counting minutes on long recordings; everything written by this crate uses
+//! `HH:MM:SS` from an hour on.
+//!
+//! Deserializing never fails on a malformed timestamp: it is salvaged with
+//! [`Timestamp::parse_lenient`], so one odd value doesn't reject a whole
+//! model response or saved transcript.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// A point in a recording, to the millisecond
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp {
+    millis: u64,
+}
+
+impl Timestamp {
+    /// Saturates at `u64::MAX` milliseconds
+    pub fn from_secs(secs: u64) -> Self {
+        Self {
+            millis: secs.saturating_mul(1000),
+        }
+    }
+
+    pub fn from_millis(millis: u64) -> Self {
+        Self { millis }
+    }
+
+    /// Whole seconds, rounded down
+    pub fn secs(&self) -> u64 {
+        self.millis / 1000
+    }
+
+    pub fn millis(&self) -> u64 {
+        self.millis
+    }
+
+    /// `MM:SS`, `HH:MM:SS`, `M:SS.mmm` or `HH:MM:SS,mmm`; `None` for anything
+    /// else, including seconds or (in `HH:MM:SS`) minutes past 59, and times
+    /// too large to count in milliseconds
+    pub fn parse(timestamp: &str) -> Option<Self> {
+        let parts: Vec<&str> = timestamp.trim().split(':').map(str::trim).collect();
+        let (whole, fraction) = match parts.last()?.split_once(['.', ',']) {
+            Some((whole, fraction)) => (whole, Some(fraction)),
+            None => (*parts.last()?, None),
+        };
+        let number = |part: &str| {
+            (!part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()))
+                .then(|| part.parse::<u64>().ok())
+                .flatten()
+        };
+        let seconds = number(whole).filter(|&seconds| seconds < 60)?;
+        let millis = match fraction {
+            None => 0,
+            Some(fraction) => {
+                number(fraction)?;
+                // Digits past the third are below a millisecond
+                let digits: String = fraction.chars().chain("00".chars()).take(3).collect();
+                digits.parse().ok()?
+            }
+        };
+        let minutes = match parts[..] {
+            [minutes, _] => number(minutes)?,
+            [hours, minutes, _] => number(hours)?
+                .checked_mul(60)?
+                .checked_add(number(minutes).filter(|&m| m < 60)?)?,
+            _ => return None,
+        };
+        let millis = minutes
+            .checked_mul(60)?
+            .checked_add(seconds)?
+            .checked_mul(1000)?
+            .checked_add(millis)?;
+        Some(Self { millis })
+    }
+
+    /// [`parse`](Self::parse), salvaging what it can from malformed input: a
+    /// fourth field is read as the fraction (`00:01:02:500`), and anything
+    /// still unreadable is logged and counts as the start of the recording
+    pub fn parse_lenient(timestamp: &str) -> Self {
+        if let Some(parsed) = Self::parse(timestamp) {
+            return parsed;
+        }
+        if let [hours, minutes, seconds, fraction] = timestamp.split(':').collect::<Vec<_>>()[..]
+            && let Some(parsed) =
+                Self::parse(&format!("{}:{}:{}.{}", hours, minutes, seconds, fraction))
+        {
+            return parsed;
+        }
+        tracing::warn!("Unreadable timestamp {:?}, using 00:00", timestamp);
+        Self::default()
+    }
+
+    /// `HH:MM:SS` and milliseconds after `separator`, as subtitle cues
+    /// are timed
+    pub fn to_cue_time(&self, separator: char) -> String {
+        let secs = self.secs();
+        format!(
+            "{:02}:{:02}:{:02}{}{:03}",
+            secs / 3600,
+            secs / 60 % 60,
+            secs % 60,
+            separator,
+            self.millis % 1000
+        )
+    }
+
+    /// SRT time: `HH:MM:SS,mmm`
+    pub fn to_srt(&self) -> String {
+        self.to_cue_time(',')
+    }
+
+    /// WebVTT time: `HH:MM:SS.mmm`
+    pub fn to_vtt(&self) -> String {
+        self.to_cue_time('.')
+    }
+}
+
+impl std::ops::Add<u64> for Timestamp {
+    type Output = Self;
+
+    /// `millis` later, saturating at `u64::MAX` milliseconds
+    fn add(self, millis: u64) -> Self {
+        Self::from_millis(self.millis.saturating_add(millis))
+    }
+}
+
+/// `MM:SS` under an hour, `HH:MM:SS` from an hour on; a fraction of a second
+/// is written only when there is one
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.secs();
+        if secs >= 3600 {
+            write!(
+                f,
+                "{:02}:{:02}:{:02}",
+                secs / 3600,
+                secs / 60 % 60,
+                secs % 60
+            )?;
+        } else {
+            write!(f, "{:02}:{:02}", secs / 60, secs % 60)?;
+        }
+        match self.millis % 1000 {
+            0 => Ok(()),
+            millis => write!(f, ".{:03}", millis),
+        }
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+            .ok_or_else(|| format!("Invalid timestamp '{}': expected MM:SS or HH:MM:SS", s))
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Reads any string, through [`Timestamp::parse_lenient`]
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Ok(Self::parse_lenient(&s))
+    }
+}
+
+/// For `#[serde(deserialize_with)]` on an `Option<Timestamp>`: a blank
+/// string reads as `None`, as does a missing field with `#[serde(default)]`
+pub fn deserialize_optional<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Timestamp>, D::Error> {
+    match Option::<String>::deserialize(deserializer)? {
+        Some(s) if !s.trim().is_empty() => Ok(Some(Timestamp::parse_lenient(&s))),
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let secs = |s: &str| Timestamp::parse(s).map(|t| t.secs());
+        assert_eq!(secs("05:30"), Some(330));
+        assert_eq!(secs(" 5:07 "), Some(307));
+        assert_eq!(secs("75:00"), Some(4500));
+        assert_eq!(secs("01:02:03"), Some(3723));
+        assert_eq!(
+            Timestamp::parse("00:01.5"),
+            Some(Timestamp::from_millis(1500))
+        );
+        assert_eq!(
+            Timestamp::parse("1:00:00.0421"),
+            Some(Timestamp::from_millis(3_600_042))
+        );
+        assert_eq!(
+            Timestamp::parse("00:00:05,250"),
+            Some(Timestamp::from_millis(5250))
+        );
+        for bad in [
+            "00:61",
+            "01:60:00",
+            "1:2:3:4",
+            "90",
+            "soon",
+            "-1:00",
+            "00:",
+            "1:00.",
+            "1:00,5.0",
+            // Overflow milliseconds
+            "18446744073709551615:00:00",
+            "307445734561825861:00",
+        ] {
+            assert_eq!(Timestamp::parse(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_format() {
+        let t = Timestamp::parse("75:04").unwrap();
+        assert_eq!(t.to_string(), "01:15:04");
+        assert_eq!(t.to_srt(), "01:15:04,000");
+        assert_eq!(t.to_vtt(), "01:15:04.000");
+        assert_eq!(Timestamp::from_secs(65).to_string(), "01:05");
+        assert_eq!((Timestamp::from_secs(59) + 250).to_string(), "00:59.250");
+        assert_eq!(
+            serde_json::to_string(&Timestamp::from_secs(3723)).unwrap(),
+            r#""01:02:03""#
+        );
+        assert_eq!(
+            Timestamp::from_secs(u64::MAX) + 1,
+            Timestamp::from_millis(u64::MAX)
+        );
+    }
+
+    #[test]
+    fn test_parse_lenient() {
+        assert_eq!(
+            Timestamp::parse_lenient("00:01:02:500"),
+            Timestamp::from_millis(62_500)
+        );
+        assert_eq!(Timestamp::parse_lenient("1:2:3:4:5"), Timestamp::default());
+        assert_eq!(Timestamp::parse_lenient("later"), Timestamp::default());
+
+        #[derive(Deserialize)]
+        struct Segment {
+            timestamp: Timestamp,
+            #[serde(default, deserialize_with = "deserialize_optional")]
+            end: Option<Timestamp>,
+        }
+        let segments: Vec<Segment> = serde_json::from_str(
+            r#"[{"timestamp": "00:00:05,000", "end": " "}, {"timestamp": "soon", "end": "00:09"}]"#,
+        )
+        .unwrap();
+        assert_eq!(segments[0].timestamp, Timestamp::from_secs(5));
+        assert_eq!(segments[0].end, None);
+        assert_eq!(segments[1].timestamp, Timestamp::default());
+        assert_eq!(segments[1].end, Some(Timestamp::from_secs(9)));
+    }
+}
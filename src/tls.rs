@@ -0,0 +1,104 @@
+//! TLS settings for the HTTP clients built by this crate: extra trusted roots
+//! for TLS-intercepting gateways, and a hook for anything else reqwest
+//! supports (certificate pinning via a preconfigured rustls config, client
+//! certificates, ...).
+
+use reqwest::ClientBuilder;
+use std::fmt;
+use std::sync::Arc;
+
+/// Final adjustment of the reqwest builder, applied after every crate setting
+pub type BuilderHook = Arc<dyn Fn(ClientBuilder) -> ClientBuilder + Send + Sync>;
+
+#[derive(Clone, Default)]
+pub struct TlsConfig {
+    /// Extra trusted root certificates; each entry is PEM and may hold several
+    pub extra_root_certs_pem: Vec<Vec<u8>>,
+    /// Trust only `extra_root_certs_pem`, not the built-in roots
+    pub only_extra_roots: bool,
+    /// Run last on the reqwest builder
+    pub customize: Option<BuilderHook>,
+}
+
+impl TlsConfig {
+    /// Trust the certificate(s) in `pem` in addition to the built-in roots
+    pub fn with_root_certs_pem(mut self, pem: impl Into<Vec<u8>>) -> Self {
+        self.extra_root_certs_pem.push(pem.into());
+        self
+    }
+
+    /// Trust only the extra roots, e.g. to pin a gateway's CA
+    pub fn with_only_extra_roots(mut self) -> Self {
+        self.only_extra_roots = true;
+        self
+    }
+
+    pub fn with_customize(
+        mut self,
+        hook: impl Fn(ClientBuilder) -> ClientBuilder + Send + Sync + 'static,
+    ) -> Self {
+        self.customize = Some(Arc::new(hook));
+        self
+    }
+}
+
+impl fmt::Debug for TlsConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsConfig")
+            .field("extra_root_certs_pem", &self.extra_root_certs_pem.len())
+            .field("only_extra_roots", &self.only_extra_roots)
+            .field("customize", &self.customize.is_some())
+            .finish()
+    }
+}
+
+/// Apply `tls` to a client builder. Fails if a PEM entry can't be parsed.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn apply(builder: ClientBuilder, tls: &TlsConfig) -> reqwest::Result<ClientBuilder> {
+    let mut certs = Vec::new();
+    for pem in &tls.extra_root_certs_pem {
+        certs.extend(reqwest::Certificate::from_pem_bundle(pem)?);
+    }
+
+    let builder = if tls.only_extra_roots {
+        builder.tls_certs_only(certs)
+    } else if certs.is_empty() {
+        builder
+    } else {
+        builder.tls_certs_merge(certs)
+    };
+
+    Ok(match &tls.customize {
+        Some(hook) => hook(builder),
+        None => builder,
+    })
+}
+
+/// The browser/runtime owns TLS on wasm32, so only the hook applies
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn apply(builder: ClientBuilder, tls: &TlsConfig) -> reqwest::Result<ClientBuilder> {
+    Ok(match &tls.customize {
+        Some(hook) => hook(builder),
+        None => builder,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tls_config_builders() {
+        let tls = TlsConfig::default()
+            .with_root_certs_pem("-----BEGIN CERTIFICATE-----")
+            .with_only_extra_roots()
+            .with_customize(|builder| builder);
+
+        assert_eq!(tls.extra_root_certs_pem.len(), 1);
+        assert!(tls.only_extra_roots);
+        assert_eq!(
+            format!("{:?}", tls),
+            "TlsConfig { extra_root_certs_pem: 1, only_extra_roots: true, customize: true }"
+        );
+    }
+}
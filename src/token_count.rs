@@ -0,0 +1,55 @@
+//! Input token counts from the `countTokens` endpoint, shared by the text
+//! and transcription clients.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Input tokens of a prompt as counted by the API's `countTokens`, which is free
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TokenCount {
+    pub total_tokens: u64,
+    /// Tokens per modality (`TEXT`, `AUDIO`, `IMAGE`, `VIDEO`, `DOCUMENT`),
+    /// in the order the API lists them
+    #[serde(default)]
+    pub by_modality: Vec<ModalityTokens>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModalityTokens {
+    pub modality: String,
+    pub tokens: u64,
+}
+
+impl TokenCount {
+    /// The count in a `countTokens` response; `None` without `totalTokens`
+    pub fn from_response(data: &Value) -> Option<Self> {
+        let total_tokens = data["totalTokens"].as_u64()?;
+        let by_modality = data["promptTokensDetails"]
+            .as_array()
+            .map(|details| {
+                details
+                    .iter()
+                    .filter_map(|detail| {
+                        Some(ModalityTokens {
+                            modality: detail["modality"].as_str()?.to_string(),
+                            tokens: detail["tokenCount"].as_u64().unwrap_or_default(),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Some(Self {
+            total_tokens,
+            by_modality,
+        })
+    }
+
+    /// Tokens of `modality` (e.g. `AUDIO`), 0 when it isn't listed
+    pub fn modality(&self, modality: &str) -> u64 {
+        self.by_modality
+            .iter()
+            .filter(|entry| entry.modality.eq_ignore_ascii_case(modality))
+            .map(|entry| entry.tokens)
+            .sum()
+    }
+}
@@ -0,0 +1,304 @@
+use anyhow::{Context, Result};
+use clap::Parser;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tracing::{Instrument, debug, info, info_span, warn};
+
+use transcript_tool::cli::{self, CommonArgs, init_logging};
+use transcript_tool::{Clients, MediaPart, TextClientConfig, TextModel, TokenCount};
+
+/// Largest file sent inline; bigger ones go through the File API
+const MAX_INLINE_SIZE: u64 = 20 * 1024 * 1024;
+
+#[derive(Parser, Debug)]
+#[command(name = "tokens")]
+#[command(version)]
+#[command(about = "Count the input tokens of a prompt and media file before paying for a request")]
+#[command(after_help = "EXAMPLES:
+    tokens --input talk.mp3 --prompt-file prompt.txt
+    tokens --input lecture.mp4 -m pro
+    tokens --prompt \"Summarize this\" --input scan.pdf --json")]
+#[command(group(
+    clap::ArgGroup::new("content")
+        .required(true)
+        .multiple(true)
+        .args(["input", "prompt_file", "prompt"])
+))]
+pub struct Args {
+    /// Audio, video, image or PDF file
+    #[arg(short, long, value_name = "FILE")]
+    input: Option<PathBuf>,
+
+    /// File with the prompt text
+    #[arg(long, value_name = "FILE", conflicts_with = "prompt")]
+    prompt_file: Option<PathBuf>,
+
+    /// Prompt text
+    #[arg(short, long)]
+    prompt: Option<String>,
+
+    /// Print the counts as JSON
+    #[arg(long)]
+    json: bool,
+
+    /// Upload through the File API even if the file is under 20MB
+    #[arg(long)]
+    force_file_api: bool,
+
+    /// Model: flash (default), flash-lite, pro
+    #[arg(
+        short = 'm',
+        long,
+        env = "FLASHECHO_TEXT_MODEL",
+        default_value = "flash"
+    )]
+    model: String,
+
+    /// Timeout of the API request in seconds
+    #[arg(short, long, default_value = "120")]
+    timeout: u64,
+
+    #[command(flatten)]
+    common: CommonArgs,
+}
+
+/// What `--json` prints
+#[derive(Debug, Serialize)]
+struct TokenReport {
+    model: &'static str,
+    #[serde(flatten)]
+    count: TokenCount,
+    context_window: u64,
+    context_used_percent: f64,
+}
+
+impl TokenReport {
+    fn new(model: TextModel, count: TokenCount) -> Self {
+        let context_window = model.input_token_limit();
+        Self {
+            model: model.api_model_name(),
+            context_used_percent: count.total_tokens as f64 * 100.0 / context_window as f64,
+            count,
+            context_window,
+        }
+    }
+
+    fn to_text(&self) -> String {
+        let mut text = format!("Model: {}\n", self.model);
+        for modality in &self.count.by_modality {
+            text.push_str(&format!(
+                "  {:<10} {:>10}\n",
+                modality.modality, modality.tokens
+            ));
+        }
+        text.push_str(&format!(
+            "Total: {} tokens ({:.1}% of the {}-token context window)\n",
+            self.count.total_tokens, self.context_used_percent, self.context_window
+        ));
+        text
+    }
+}
+
+/// MIME type of a media file the model accepts, by extension
+fn media_mime_type(path: &Path) -> Option<&'static str> {
+    match path.extension()?.to_str()?.to_lowercase().as_str() {
+        "mp3" => Some("audio/mpeg"),
+        "wav" => Some("audio/wav"),
+        "ogg" => Some("audio/ogg"),
+        "flac" => Some("audio/flac"),
+        "m4a" => Some("audio/mp4"),
+        "aac" => Some("audio/aac"),
+        "aiff" => Some("audio/aiff"),
+        "mp4" => Some("video/mp4"),
+        "mov" => Some("video/quicktime"),
+        "webm" => Some("video/webm"),
+        "mkv" => Some("video/x-matroska"),
+        "avi" => Some("video/x-msvideo"),
+        "mpeg" | "mpg" => Some("video/mpeg"),
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "webp" => Some("image/webp"),
+        "gif" => Some("image/gif"),
+        "heic" => Some("image/heic"),
+        "heif" => Some("image/heif"),
+        "pdf" => Some("application/pdf"),
+        _ => None,
+    }
+}
+
+/// Run with parsed arguments; shared by this binary and its `flashecho` subcommand
+pub async fn run(args: Args) -> Result<()> {
+    init_logging(args.common.verbose, args.common.log_format);
+    args.common.install_usage_ledger("tokens");
+
+    let file = args
+        .input
+        .as_ref()
+        .map(|p| p.display().to_string())
+        .unwrap_or_default();
+    let span = info_span!("file", file = %file);
+    count(args).instrument(span).await
+}
+
+async fn count(args: Args) -> Result<()> {
+    let prompt = match (&args.prompt_file, &args.prompt) {
+        (Some(path), _) => fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Failed to read prompt file {:?}", path))?,
+        (None, Some(prompt)) => prompt.clone(),
+        (None, None) => String::new(),
+    };
+
+    let model: TextModel = args.model.parse().map_err(|e: String| anyhow::anyhow!(e))?;
+    let profile = args.common.load_profile()?;
+    let clients =
+        Clients::new(cli::gemini_api_key(&profile)?).context("Failed to create HTTP client")?;
+    let budget = args
+        .common
+        .budget()
+        .context("Failed to read usage ledger for --budget-since")?;
+    let client = clients.text(TextClientConfig {
+        timeout_secs: args.timeout,
+        max_retries: args.common.max_retries,
+        model,
+        budget,
+        ..Default::default()
+    });
+
+    // Large files go through the File API (uploads are free) and are
+    // referenced by URI, just as the paid request would send them
+    let mut uploaded = None;
+    let mut media = Vec::new();
+    if let Some(input) = &args.input {
+        let mime_type = media_mime_type(input).with_context(|| {
+            format!(
+                "Unsupported input format: {:?} (use audio, video, an image or a PDF)",
+                input
+            )
+        })?;
+        let data = fs::read(input)
+            .await
+            .with_context(|| format!("Failed to read {:?}", input))?;
+        let file_size = data.len() as u64;
+
+        if args.force_file_api || file_size > MAX_INLINE_SIZE {
+            info!(
+                "Using File API for {} bytes ({:.1}MB)",
+                file_size,
+                file_size as f64 / (1024.0 * 1024.0)
+            );
+            let file_api = clients.file_api();
+            let display_name = input
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("media");
+            let pb = cli::spinner(args.common.quiet, "Uploading to Gemini File API...".into());
+            let result = file_api
+                .upload_file(data, mime_type, display_name)
+                .instrument(info_span!("stage", stage = "upload"))
+                .await;
+            if let Some(pb) = pb {
+                pb.finish_and_clear();
+            }
+            let file_info = result.map_err(|e| anyhow::anyhow!("File upload failed: {}", e))?;
+            debug!("File uploaded: {} -> {}", file_info.name, file_info.uri);
+            media.push(MediaPart::FileUri {
+                mime_type: mime_type.to_string(),
+                uri: file_info.uri,
+            });
+            uploaded = Some((file_api, file_info.name));
+        } else {
+            media.push(MediaPart::Inline {
+                mime_type: mime_type.to_string(),
+                data,
+            });
+        }
+    }
+
+    let pb = cli::spinner(args.common.quiet, "Counting tokens...".into());
+    let result = client
+        .count_tokens(&prompt, &media)
+        .instrument(info_span!("stage", stage = "count_tokens"))
+        .await;
+    if let Some(pb) = pb {
+        pb.finish_and_clear();
+    }
+
+    if let Some((file_api, file_name)) = uploaded {
+        match file_api.delete_file(&file_name).await {
+            Ok(()) => debug!("Remote file deleted: {}", file_name),
+            Err(e) => warn!("Failed to delete remote file {}: {}", file_name, e),
+        }
+    }
+    let count = result.map_err(|e| anyhow::anyhow!("Token counting failed: {}", e))?;
+
+    let report = TokenReport::new(model, count);
+    if report.context_used_percent > 100.0 {
+        warn!(
+            "{} tokens don't fit the {}-token context window of {}",
+            report.count.total_tokens, report.context_window, report.model
+        );
+    }
+    if args.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).context("Failed to serialize token counts")?
+        );
+    } else {
+        print!("{}", report.to_text());
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    run(Args::parse()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use transcript_tool::ModalityTokens;
+
+    #[test]
+    fn test_media_mime_type() {
+        assert_eq!(media_mime_type(Path::new("talk.MP3")), Some("audio/mpeg"));
+        assert_eq!(
+            media_mime_type(Path::new("a/clip.mov")),
+            Some("video/quicktime")
+        );
+        assert_eq!(
+            media_mime_type(Path::new("scan.pdf")),
+            Some("application/pdf")
+        );
+        assert_eq!(media_mime_type(Path::new("notes.txt")), None);
+    }
+
+    #[test]
+    fn test_token_report() {
+        let report = TokenReport::new(
+            TextModel::Flash,
+            TokenCount {
+                total_tokens: 104_858,
+                by_modality: vec![
+                    ModalityTokens {
+                        modality: "AUDIO".to_string(),
+                        tokens: 104_800,
+                    },
+                    ModalityTokens {
+                        modality: "TEXT".to_string(),
+                        tokens: 58,
+                    },
+                ],
+            },
+        );
+        assert!((report.context_used_percent - 10.0).abs() < 0.01);
+        let text = report.to_text();
+        assert!(text.starts_with("Model: gemini-2.5-flash\n"));
+        assert!(text.contains("  AUDIO          104800\n"));
+        assert!(
+            text.ends_with("Total: 104858 tokens (10.0% of the 1048576-token context window)\n")
+        );
+    }
+}
@@ -0,0 +1,80 @@
+//! A text-model pass over a finished transcript: punctuation,
+//! capitalization, filler words and obvious misrecognitions are fixed one
+//! segment at a time, while speakers, timestamps and the segments themselves
+//! stay as they are. Much cheaper than transcribing the audio again.
+
+use crate::gemini_api::TranscriptResponse;
+use crate::text_api::{Result, TextClient, TextGenConfig};
+use crate::translation::{DEFAULT_BATCH_CHARS, batch_ranges, rewrite_batch};
+
+const CLEANUP_INSTRUCTION: &str = "You proofread speech-recognition transcripts. Each string of \
+the JSON array is what one speaker said, in order. Fix punctuation and capitalization, remove \
+filler words such as um, uh and er, stutters and repeated words, and correct words that were \
+clearly misheard when the context makes the intended word obvious. Keep each string in its own \
+language, keep the speaker's wording otherwise, and never summarize, translate, merge or split \
+strings. Answer with a JSON array of strings of the same length and order, one cleaned string \
+per input string.";
+
+/// What a cleanup pass changed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CleanupStats {
+    pub segments: usize,
+    /// Segments whose text was rewritten
+    pub changed: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct TranscriptCleanup {
+    client: TextClient,
+    batch_chars: usize,
+}
+
+impl TranscriptCleanup {
+    pub fn new(client: TextClient) -> Self {
+        Self {
+            client,
+            batch_chars: DEFAULT_BATCH_CHARS,
+        }
+    }
+
+    pub fn with_batch_chars(mut self, batch_chars: usize) -> Self {
+        self.batch_chars = batch_chars.max(1);
+        self
+    }
+
+    /// Clean one batch of segment texts with a single request
+    pub async fn clean_batch(&self, texts: &[&str]) -> Result<Vec<String>> {
+        let config = TextGenConfig::new()
+            .with_system_instruction(CLEANUP_INSTRUCTION)
+            .with_json_output();
+        rewrite_batch(&self.client, &config, texts, "cleaned segments").await
+    }
+
+    /// Clean the text of every segment, one request per batch. A segment the
+    /// model empties (one that was all filler) keeps its text, so no cue
+    /// goes blank.
+    pub async fn clean(&self, transcript: &mut TranscriptResponse) -> Result<CleanupStats> {
+        let texts: Vec<&str> = transcript
+            .segments
+            .iter()
+            .map(|segment| segment.content.as_str())
+            .collect();
+        let mut cleaned = Vec::with_capacity(texts.len());
+        for batch in batch_ranges(&texts, self.batch_chars) {
+            cleaned.extend(self.clean_batch(&texts[batch]).await?);
+        }
+
+        let mut stats = CleanupStats {
+            segments: transcript.segments.len(),
+            changed: 0,
+        };
+        for (segment, text) in transcript.segments.iter_mut().zip(cleaned) {
+            let text = text.trim();
+            if !text.is_empty() && text != segment.content.trim() {
+                segment.content = text.to_string();
+                stats.changed += 1;
+            }
+        }
+        Ok(stats)
+    }
+}
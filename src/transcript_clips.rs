@@ -0,0 +1,149 @@
+//! One audio clip per transcript segment, for quote extraction and speech
+//! datasets: where each segment starts and ends in the media, and a file
+//! name that sorts in timeline order and says who is speaking.
+
+use serde::Serialize;
+
+use crate::gemini_api::TranscriptResponse;
+
+/// Length of the last clip when the media duration is unknown, matching the
+/// last SRT/VTT cue
+const LAST_CLIP_SECS: f64 = 5.0;
+
+/// A segment's place in the media; one line of the clip manifest
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SegmentClip {
+    /// Index of the segment in the transcript
+    pub segment: usize,
+    pub start_secs: f64,
+    pub end_secs: f64,
+    pub speaker: String,
+    pub text: String,
+    /// `003_01-25_ann`: segment number, start time and speaker, without an
+    /// extension
+    pub file_stem: String,
+}
+
+impl SegmentClip {
+    pub fn duration_secs(&self) -> f64 {
+        self.end_secs - self.start_secs
+    }
+}
+
+/// Clips of the segments of `transcript`. Each ends at the segment's end
+/// time when it has one, else where the next segment starts; the last one at
+/// `media_secs` when known, else five seconds in.
+/// Segments that start no earlier than the next one are left out.
+pub fn segment_clips(transcript: &TranscriptResponse, media_secs: Option<f64>) -> Vec<SegmentClip> {
+    let mut clips = Vec::new();
+    for (i, segment) in transcript.segments.iter().enumerate() {
+        let start = segment.timestamp.secs() as f64;
+        let own_end = segment
+            .end_timestamp
+            .map(|end| end.secs() as f64)
+            .filter(|&end| end > start);
+        let end = match (own_end, transcript.segments.get(i + 1)) {
+            (Some(end), _) => end,
+            (None, Some(next)) => next.timestamp.secs() as f64,
+            (None, None) => media_secs
+                .filter(|&end| end > start)
+                .unwrap_or(start + LAST_CLIP_SECS),
+        };
+        if end <= start {
+            continue;
+        }
+        clips.push(SegmentClip {
+            segment: i,
+            start_secs: start,
+            end_secs: end,
+            speaker: segment.speaker.clone(),
+            text: segment.content.clone(),
+            file_stem: clip_stem(i, start as u64, &segment.speaker),
+        });
+    }
+    clips
+}
+
+fn clip_stem(index: usize, start_secs: u64, speaker: &str) -> String {
+    let (hours, minutes, secs) = (start_secs / 3600, start_secs / 60 % 60, start_secs % 60);
+    let time = if hours > 0 {
+        format!("{}-{:02}-{:02}", hours, minutes, secs)
+    } else {
+        format!("{:02}-{:02}", minutes, secs)
+    };
+    format!("{:03}_{}_{}", index + 1, time, speaker_slug(speaker))
+}
+
+/// Lowercase letters and digits of `speaker` joined by `-`; other scripts
+/// such as Chinese are kept
+fn speaker_slug(speaker: &str) -> String {
+    let slug = speaker
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if slug.is_empty() {
+        "speaker".to_string()
+    } else {
+        slug
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timestamp::Timestamp;
+    use serde_json::json;
+
+    fn transcript(segments: &[(&str, &str)]) -> TranscriptResponse {
+        let segments: Vec<_> = segments
+            .iter()
+            .map(|(timestamp, speaker)| {
+                json!({
+                    "speaker": speaker, "timestamp": timestamp, "content": "Hi",
+                    "language": "English", "language_code": "en", "emotion": "neutral"
+                })
+            })
+            .collect();
+        serde_json::from_value(json!({ "summary": "", "segments": segments })).unwrap()
+    }
+
+    #[test]
+    fn test_segment_clips() {
+        let transcript = transcript(&[
+            ("00:00", "Speaker 1"),
+            ("00:07", "Dr. Ann Lee"),
+            ("01:02:03", "张三"),
+        ]);
+        let clips = segment_clips(&transcript, Some(3730.0));
+        let stems: Vec<_> = clips.iter().map(|clip| clip.file_stem.as_str()).collect();
+        assert_eq!(
+            stems,
+            [
+                "001_00-00_speaker-1",
+                "002_00-07_dr-ann-lee",
+                "003_1-02-03_张三"
+            ]
+        );
+        assert_eq!((clips[1].start_secs, clips[1].end_secs), (7.0, 3723.0));
+        assert_eq!(clips[2].duration_secs(), 7.0);
+
+        let clips = segment_clips(&transcript, None);
+        assert_eq!(clips[2].end_secs, 3728.0);
+
+        let mut transcript = transcript;
+        transcript.segments[0].end_timestamp = Timestamp::parse("00:04");
+        let clips = segment_clips(&transcript, None);
+        assert_eq!((clips[0].start_secs, clips[0].end_secs), (0.0, 4.0));
+    }
+
+    #[test]
+    fn test_out_of_order_segments_are_skipped() {
+        let transcript = transcript(&[("00:10", "A"), ("00:10", "B"), ("00:05", "?")]);
+        let clips = segment_clips(&transcript, None);
+        assert_eq!(clips.len(), 1);
+        assert_eq!(clips[0].segment, 2);
+        assert_eq!(speaker_slug("???"), "speaker");
+    }
+}
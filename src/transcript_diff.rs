@@ -0,0 +1,715 @@
+//! Side-by-side comparison of two transcripts of the same audio, e.g. from
+//! different models or settings: segments are aligned on the timeline, then
+//! compared word by word, by speaker and by timestamp.
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::gemini_api::{TranscriptResponse, TranscriptSegment};
+
+/// Default largest timestamp difference of two segments that can line up
+pub const DEFAULT_MAX_DRIFT_SECS: u64 = 10;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TranscriptDiff {
+    pub max_drift_secs: u64,
+    pub segments_a: usize,
+    pub segments_b: usize,
+    /// Segments of A that line up with a segment of B
+    pub matched: usize,
+    /// Matched segments with the same words
+    pub identical: usize,
+    /// Mean word similarity of the matched segments, 0 to 1
+    pub text_similarity: f64,
+    /// Speakers of A, in order of first appearance, with the B label they
+    /// line up with most
+    pub speakers: Vec<SpeakerPair>,
+    /// Matched segments whose B speaker isn't the one paired with the A speaker
+    pub speaker_disagreements: usize,
+    pub drift: DriftStats,
+    /// Every segment of both transcripts, in timeline order
+    pub segments: Vec<SegmentDiff>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SpeakerPair {
+    pub a: String,
+    pub b: Option<String>,
+    /// Matched segments of `a`
+    pub segments: usize,
+    /// Of those, how many B also gives to `b`
+    pub agreed: usize,
+}
+
+/// Timestamp of B minus timestamp of A over the matched segments
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct DriftStats {
+    pub mean_secs: f64,
+    pub mean_abs_secs: f64,
+    pub max_abs_secs: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SegmentDiff {
+    pub a: Option<SegmentRef>,
+    pub b: Option<SegmentRef>,
+    /// Word similarity, 0 to 1, when both sides are present
+    pub similarity: Option<f64>,
+    pub drift_secs: Option<i64>,
+    pub speaker_agrees: Option<bool>,
+    /// Word diff: `[-only in A-]` and `{+only in B+}` around the shared words
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub diff: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SegmentRef {
+    /// Position in its transcript's `segments`
+    pub index: usize,
+    pub timestamp: String,
+    pub speaker: String,
+    pub content: String,
+}
+
+impl SegmentRef {
+    fn new(index: usize, segment: &TranscriptSegment) -> Self {
+        Self {
+            index,
+            timestamp: segment.timestamp.to_string(),
+            speaker: segment.speaker.clone(),
+            content: segment.content.trim().to_string(),
+        }
+    }
+}
+
+impl SegmentDiff {
+    /// Both sides present, with the same words and matching speakers
+    pub fn is_identical(&self) -> bool {
+        self.similarity == Some(1.0) && self.speaker_agrees == Some(true)
+    }
+}
+
+/// One step of turning one word sequence into another
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WordEdit<'a> {
+    Same(&'a str),
+    Substitute(&'a str, &'a str),
+    Delete(&'a str),
+    Insert(&'a str),
+}
+
+/// Han, kana and hangul are written without spaces, so each character counts
+/// as a word
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF | 0xAC00..=0xD7AF
+    )
+}
+
+/// Lowercased words of `text` without punctuation; CJK characters are words
+/// of their own
+pub fn words(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let flush = |word: &mut String, words: &mut Vec<String>| {
+        let trimmed = word.trim_matches('\'');
+        if !trimmed.is_empty() {
+            words.push(trimmed.to_string());
+        }
+        word.clear();
+    };
+    for c in text.chars() {
+        if is_cjk(c) {
+            flush(&mut word, &mut words);
+            words.push(c.to_string());
+        } else if c.is_alphanumeric() || c == '\'' || c == '’' {
+            word.extend(if c == '’' { '\'' } else { c }.to_lowercase());
+        } else {
+            flush(&mut word, &mut words);
+        }
+    }
+    flush(&mut word, &mut words);
+    words
+}
+
+/// Alignments with more cells than this only search a band around the
+/// diagonal, so an hour of speech doesn't need gigabytes
+const FULL_ALIGNMENT_CELLS: usize = 4_000_000;
+
+/// Half-width in words of that band, on top of the length difference
+const ALIGNMENT_BAND: usize = 1_000;
+
+/// Backpointers of the word alignment
+const DIAGONAL: u8 = 0;
+const UP: u8 = 1;
+const LEFT: u8 = 2;
+
+/// Fewest substitutions, deletions and insertions turning `a` into `b`.
+///
+/// Of equally short alignments, shared words are kept lined up, so a dropped
+/// word reads as a deletion rather than a chain of substitutions. Very long
+/// inputs are aligned within [`ALIGNMENT_BAND`] words of the diagonal
+pub fn word_edits<'a>(a: &'a [String], b: &'a [String]) -> Vec<WordEdit<'a>> {
+    let (n, m) = (a.len(), b.len());
+    let band = ((n + 1).saturating_mul(m + 1) > FULL_ALIGNMENT_CELLS)
+        .then(|| ALIGNMENT_BAND + n.abs_diff(m));
+    // Columns searched in row `i`
+    let columns = |i: usize| match band {
+        Some(band) => {
+            let center = i * m / n.max(1);
+            (center.saturating_sub(band), (center + band).min(m))
+        }
+        None => (0, m),
+    };
+
+    const FAR: u32 = u32::MAX / 2;
+    let mut previous: Vec<u32> = (0..=m as u32).collect();
+    let (_, first_hi) = columns(0);
+    previous[first_hi + 1..].fill(FAR);
+    let mut rows: Vec<(usize, Vec<u8>)> = Vec::with_capacity(n + 1);
+    rows.push((0, vec![LEFT; first_hi + 1]));
+    let mut current = vec![FAR; m + 1];
+    for i in 1..=n {
+        let (lo, hi) = columns(i);
+        current.fill(FAR);
+        let mut from = Vec::with_capacity(hi - lo + 1);
+        for j in lo..=hi {
+            let up = previous[j] + 1;
+            let cell = if j == 0 {
+                (up, UP)
+            } else {
+                let diagonal = previous[j - 1] + u32::from(a[i - 1] != b[j - 1]);
+                let left = current[j - 1] + 1;
+                if a[i - 1] == b[j - 1] && diagonal <= up.min(left) {
+                    (diagonal, DIAGONAL)
+                } else if up <= left && up <= diagonal {
+                    (up, UP)
+                } else if left <= diagonal {
+                    (left, LEFT)
+                } else {
+                    (diagonal, DIAGONAL)
+                }
+            };
+            current[j] = cell.0;
+            from.push(cell.1);
+        }
+        rows.push((lo, from));
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    let mut edits = Vec::new();
+    let (mut i, mut j) = (n, m);
+    while i > 0 || j > 0 {
+        let (lo, from) = &rows[i];
+        match from[j - lo] {
+            DIAGONAL if a[i - 1] == b[j - 1] => edits.push(WordEdit::Same(&a[i - 1])),
+            DIAGONAL => edits.push(WordEdit::Substitute(&a[i - 1], &b[j - 1])),
+            UP => edits.push(WordEdit::Delete(&a[i - 1])),
+            _ => edits.push(WordEdit::Insert(&b[j - 1])),
+        }
+        match from[j - lo] {
+            DIAGONAL => {
+                i -= 1;
+                j -= 1;
+            }
+            UP => i -= 1,
+            _ => j -= 1,
+        }
+    }
+    edits.reverse();
+    edits
+}
+
+/// 1 minus the edits per word of the longer side; two empty texts are equal
+fn similarity(edits: &[WordEdit<'_>]) -> f64 {
+    let mut changed = 0;
+    let (mut len_a, mut len_b) = (0, 0);
+    for edit in edits {
+        match edit {
+            WordEdit::Same(_) => {
+                len_a += 1;
+                len_b += 1;
+            }
+            WordEdit::Substitute(..) => {
+                changed += 1;
+                len_a += 1;
+                len_b += 1;
+            }
+            WordEdit::Delete(_) => {
+                changed += 1;
+                len_a += 1;
+            }
+            WordEdit::Insert(_) => {
+                changed += 1;
+                len_b += 1;
+            }
+        }
+    }
+    match len_a.max(len_b) {
+        0 => 1.0,
+        len => 1.0 - changed as f64 / len as f64,
+    }
+}
+
+/// `the [-quick-] {+quack+} fox`; runs of changed words are grouped
+pub(crate) fn render_diff(edits: &[WordEdit<'_>]) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    let mut deleted: Vec<&str> = Vec::new();
+    let mut inserted: Vec<&str> = Vec::new();
+    let flush = |parts: &mut Vec<String>, deleted: &mut Vec<&str>, inserted: &mut Vec<&str>| {
+        if !deleted.is_empty() {
+            parts.push(format!("[-{}-]", deleted.join(" ")));
+            deleted.clear();
+        }
+        if !inserted.is_empty() {
+            parts.push(format!("{{+{}+}}", inserted.join(" ")));
+            inserted.clear();
+        }
+    };
+    for edit in edits {
+        match *edit {
+            WordEdit::Same(word) => {
+                flush(&mut parts, &mut deleted, &mut inserted);
+                parts.push(word.to_string());
+            }
+            WordEdit::Substitute(a, b) => {
+                deleted.push(a);
+                inserted.push(b);
+            }
+            WordEdit::Delete(a) => deleted.push(a),
+            WordEdit::Insert(b) => inserted.push(b),
+        }
+    }
+    flush(&mut parts, &mut deleted, &mut inserted);
+    parts.join(" ")
+}
+
+/// Signed seconds as `+3s` / `-1s` / `0s`
+fn format_drift(secs: i64) -> String {
+    if secs > 0 {
+        format!("+{}s", secs)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+/// Backpointers of the segment alignment
+const MATCH: u8 = 0;
+const ONLY_A: u8 = 1;
+const ONLY_B: u8 = 2;
+
+/// Pair the speakers of A (`order`: by first appearance) with those of B,
+/// most shared `labels` first, so each label is used at most once on either
+/// side
+pub(crate) fn pair_speakers<'a>(
+    order: &[String],
+    labels: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> HashMap<String, String> {
+    let mut counts: HashMap<(&str, &str), usize> = HashMap::new();
+    for label in labels {
+        *counts.entry(label).or_insert(0) += 1;
+    }
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    let rank = |speaker: &str| order.iter().position(|s| s == speaker);
+    counts.sort_by(|(x, x_count), (y, y_count)| {
+        y_count
+            .cmp(x_count)
+            .then_with(|| rank(x.0).cmp(&rank(y.0)))
+            .then_with(|| x.1.cmp(y.1))
+    });
+
+    let mut pairs = HashMap::new();
+    let mut taken = Vec::new();
+    for ((a, b), _) in counts {
+        if !pairs.contains_key(a) && !taken.contains(&b) {
+            pairs.insert(a.to_string(), b.to_string());
+            taken.push(b);
+        }
+    }
+    pairs
+}
+
+impl TranscriptDiff {
+    /// Align the segments of `a` and `b` and compare the pairs.
+    ///
+    /// Segments line up when their timestamps are at most `max_drift_secs`
+    /// apart, preferring pairs with similar words and little drift. Speaker
+    /// labels are compared through the best one-to-one pairing of A and B
+    /// labels, since each transcript numbers its speakers on its own.
+    pub fn compare(a: &TranscriptResponse, b: &TranscriptResponse, max_drift_secs: u64) -> Self {
+        let words_a: Vec<Vec<String>> = a.segments.iter().map(|s| words(&s.content)).collect();
+        let words_b: Vec<Vec<String>> = b.segments.iter().map(|s| words(&s.content)).collect();
+        let secs_a: Vec<_> = a.segments.iter().map(|s| s.timestamp.secs()).collect();
+        let secs_b: Vec<_> = b.segments.iter().map(|s| s.timestamp.secs()).collect();
+        let (n, m) = (a.segments.len(), b.segments.len());
+
+        // Edit distance over segments: leaving a segment unmatched costs 1,
+        // a pair costs its word dissimilarity plus up to 0.5 for drift
+        let pair_cost = |i: usize, j: usize| -> Option<f64> {
+            let drift = secs_a[i].abs_diff(secs_b[j]);
+            if drift > max_drift_secs {
+                return None;
+            }
+            let drift_cost = if max_drift_secs == 0 {
+                0.0
+            } else {
+                0.5 * drift as f64 / max_drift_secs as f64
+            };
+            let sim = similarity(&word_edits(&words_a[i], &words_b[j]));
+            Some(1.0 - sim + drift_cost)
+        };
+        let width = m + 1;
+        let mut from = vec![ONLY_B; (n + 1) * width];
+        let mut previous: Vec<f64> = (0..=m).map(|j| j as f64).collect();
+        for i in 1..=n {
+            let mut current = vec![0.0; width];
+            current[0] = i as f64;
+            from[i * width] = ONLY_A;
+            for j in 1..=m {
+                let mut best = (previous[j] + 1.0, ONLY_A);
+                if current[j - 1] + 1.0 < best.0 {
+                    best = (current[j - 1] + 1.0, ONLY_B);
+                }
+                if let Some(cost) = pair_cost(i - 1, j - 1)
+                    && previous[j - 1] + cost <= best.0
+                {
+                    best = (previous[j - 1] + cost, MATCH);
+                }
+                current[j] = best.0;
+                from[i * width + j] = best.1;
+            }
+            previous = current;
+        }
+
+        let mut steps = Vec::new();
+        let (mut i, mut j) = (n, m);
+        while i > 0 || j > 0 {
+            let step = if i == 0 { ONLY_B } else { from[i * width + j] };
+            match step {
+                MATCH => {
+                    i -= 1;
+                    j -= 1;
+                    steps.push((Some(i), Some(j)));
+                }
+                ONLY_A => {
+                    i -= 1;
+                    steps.push((Some(i), None));
+                }
+                _ => {
+                    j -= 1;
+                    steps.push((None, Some(j)));
+                }
+            }
+        }
+        steps.reverse();
+
+        let mut order: Vec<String> = Vec::new();
+        for segment in &a.segments {
+            if !order.contains(&segment.speaker) {
+                order.push(segment.speaker.clone());
+            }
+        }
+        let matched: Vec<_> = steps
+            .iter()
+            .filter_map(|&(i, j)| Some((&a.segments[i?], &b.segments[j?])))
+            .collect();
+        let pairs = pair_speakers(
+            &order,
+            matched
+                .iter()
+                .map(|(a, b)| (a.speaker.as_str(), b.speaker.as_str())),
+        );
+
+        let mut segments = Vec::with_capacity(steps.len());
+        let mut similarity_sum = 0.0;
+        let mut identical = 0;
+        let mut drifts = Vec::new();
+        for (i, j) in steps {
+            let mut diff = SegmentDiff {
+                a: i.map(|i| SegmentRef::new(i, &a.segments[i])),
+                b: j.map(|j| SegmentRef::new(j, &b.segments[j])),
+                similarity: None,
+                drift_secs: None,
+                speaker_agrees: None,
+                diff: String::new(),
+            };
+            if let (Some(i), Some(j)) = (i, j) {
+                let edits = word_edits(&words_a[i], &words_b[j]);
+                let sim = similarity(&edits);
+                similarity_sum += sim;
+                if sim == 1.0 {
+                    identical += 1;
+                } else {
+                    diff.diff = render_diff(&edits);
+                }
+                diff.similarity = Some(sim);
+                let drift = secs_b[j] as i64 - secs_a[i] as i64;
+                diff.drift_secs = Some(drift);
+                drifts.push(drift);
+                diff.speaker_agrees =
+                    Some(pairs.get(&a.segments[i].speaker) == Some(&b.segments[j].speaker));
+            }
+            segments.push(diff);
+        }
+
+        let speakers: Vec<SpeakerPair> = order
+            .into_iter()
+            .map(|speaker| {
+                let of_speaker = segments.iter().filter(|s| {
+                    s.b.is_some() && s.a.as_ref().is_some_and(|a| a.speaker == speaker)
+                });
+                SpeakerPair {
+                    b: pairs.get(&speaker).cloned(),
+                    segments: of_speaker.clone().count(),
+                    agreed: of_speaker
+                        .filter(|s| s.speaker_agrees == Some(true))
+                        .count(),
+                    a: speaker,
+                }
+            })
+            .collect();
+        let speaker_disagreements = speakers.iter().map(|s| s.segments - s.agreed).sum();
+
+        let drift = if drifts.is_empty() {
+            DriftStats {
+                mean_secs: 0.0,
+                mean_abs_secs: 0.0,
+                max_abs_secs: 0,
+            }
+        } else {
+            let count = drifts.len() as f64;
+            DriftStats {
+                mean_secs: drifts.iter().sum::<i64>() as f64 / count,
+                mean_abs_secs: drifts.iter().map(|d| d.unsigned_abs()).sum::<u64>() as f64 / count,
+                max_abs_secs: drifts.iter().map(|d| d.unsigned_abs()).max().unwrap_or(0),
+            }
+        };
+
+        Self {
+            max_drift_secs,
+            segments_a: n,
+            segments_b: m,
+            matched: matched.len(),
+            identical,
+            text_similarity: if matched.is_empty() {
+                0.0
+            } else {
+                similarity_sum / matched.len() as f64
+            },
+            speakers,
+            speaker_disagreements,
+            drift,
+            segments,
+        }
+    }
+
+    /// Summary followed by the segments that differ (all segments with `all`)
+    pub fn to_text(&self, all: bool) -> String {
+        let mut text = format!(
+            "Segments: {} in A, {} in B, {} matched ({} with the same words)\n",
+            self.segments_a, self.segments_b, self.matched, self.identical
+        );
+        text.push_str(&format!(
+            "Text similarity: {:.1}%\n",
+            self.text_similarity * 100.0
+        ));
+        let speakers: Vec<String> = self
+            .speakers
+            .iter()
+            .map(|s| match &s.b {
+                Some(b) => format!("{} = {} ({}/{})", s.a, b, s.agreed, s.segments),
+                None => format!("{} unpaired (0/{})", s.a, s.segments),
+            })
+            .collect();
+        text.push_str(&format!("Speakers: {}\n", speakers.join(", ")));
+        text.push_str(&format!(
+            "Speaker disagreements: {}\n",
+            self.speaker_disagreements
+        ));
+        text.push_str(&format!(
+            "Timing drift: mean {:+.1}s, mean absolute {:.1}s, max {}s\n",
+            self.drift.mean_secs, self.drift.mean_abs_secs, self.drift.max_abs_secs
+        ));
+
+        let speaker_b = |a: &str| {
+            self.speakers
+                .iter()
+                .find(|s| s.a == a)
+                .and_then(|s| s.b.as_deref())
+        };
+        for segment in &self.segments {
+            if !all && segment.is_identical() {
+                continue;
+            }
+            let clock = |side: &Option<SegmentRef>| {
+                side.as_ref()
+                    .map_or("--:--".to_string(), |s| s.timestamp.clone())
+            };
+            let mut notes = Vec::new();
+            match (&segment.a, &segment.b) {
+                (Some(_), None) => notes.push("only in A".to_string()),
+                (None, Some(_)) => notes.push("only in B".to_string()),
+                _ => {
+                    if let Some(sim) = segment.similarity.filter(|&sim| sim < 1.0) {
+                        notes.push(format!("text differs ({:.0}%)", sim * 100.0));
+                    }
+                    if segment.speaker_agrees == Some(false)
+                        && let Some(a) = &segment.a
+                    {
+                        notes.push(match speaker_b(&a.speaker) {
+                            Some(b) => format!("speaker (expected {})", b),
+                            None => format!("speaker ({} is unpaired)", a.speaker),
+                        });
+                    }
+                    if let Some(drift) = segment.drift_secs.filter(|&d| d != 0) {
+                        notes.push(format!("drift {}", format_drift(drift)));
+                    }
+                }
+            }
+            text.push_str(&format!(
+                "\n[{} | {}] {}\n",
+                clock(&segment.a),
+                clock(&segment.b),
+                if notes.is_empty() {
+                    "same".to_string()
+                } else {
+                    notes.join(", ")
+                }
+            ));
+            for (label, side) in [("A", &segment.a), ("B", &segment.b)] {
+                if let Some(side) = side {
+                    text.push_str(&format!("  {} {}: {}\n", label, side.speaker, side.content));
+                }
+            }
+            if !segment.diff.is_empty() {
+                text.push_str(&format!("  ~ {}\n", segment.diff));
+            }
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn transcript(segments: &[(&str, &str, &str)]) -> TranscriptResponse {
+        let segments: Vec<_> = segments
+            .iter()
+            .map(|(timestamp, speaker, content)| {
+                json!({
+                    "speaker": speaker,
+                    "timestamp": timestamp,
+                    "content": content,
+                    "language": "English",
+                    "language_code": "en",
+                    "emotion": "neutral"
+                })
+            })
+            .collect();
+        serde_json::from_value(json!({"summary": "", "segments": segments})).unwrap()
+    }
+
+    #[test]
+    fn test_words() {
+        assert_eq!(
+            words("Don't stop, 'Bob' — it’s FINE!"),
+            vec!["don't", "stop", "bob", "it's", "fine"]
+        );
+        assert_eq!(words("你好 world"), vec!["你", "好", "world"]);
+        assert!(words(" ... ").is_empty());
+    }
+
+    #[test]
+    fn test_word_edits_and_diff() {
+        let a = words("the quick brown fox");
+        let b = words("the quack fox jumps");
+        let edits = word_edits(&a, &b);
+        assert_eq!(
+            edits,
+            vec![
+                WordEdit::Same("the"),
+                WordEdit::Substitute("quick", "quack"),
+                WordEdit::Delete("brown"),
+                WordEdit::Same("fox"),
+                WordEdit::Insert("jumps"),
+            ]
+        );
+        assert_eq!(similarity(&edits), 0.25);
+        assert_eq!(
+            render_diff(&edits),
+            "the [-quick brown-] {+quack+} fox {+jumps+}"
+        );
+        assert_eq!(similarity(&word_edits(&[], &[])), 1.0);
+    }
+
+    #[test]
+    fn test_word_edits_long_input() {
+        // Over FULL_ALIGNMENT_CELLS, so only the band is searched
+        let a: Vec<String> = (0..2500).map(|i| format!("w{}", i)).collect();
+        let mut b = a.clone();
+        b.remove(100);
+        b[2000] = "other".to_string();
+        let edits = word_edits(&a, &b);
+        let changed: Vec<_> = edits
+            .iter()
+            .filter(|e| !matches!(e, WordEdit::Same(_)))
+            .collect();
+        assert_eq!(
+            changed,
+            vec![
+                &WordEdit::Delete("w100"),
+                &WordEdit::Substitute("w2001", "other")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compare() {
+        let a = transcript(&[
+            ("00:00", "Speaker 1", "Hello and welcome."),
+            ("00:04", "Speaker 2", "Thanks for having me."),
+            ("00:09", "Speaker 1", "Let's start with pricing."),
+            ("00:15", "Speaker 1", "Prices go up."),
+            ("00:35", "Speaker 2", "Sure."),
+        ]);
+        let b = transcript(&[
+            ("00:01", "Speaker 2", "Hello and welcome!"),
+            ("00:05", "Speaker 1", "Thanks for having me."),
+            ("00:11", "Speaker 1", "Let's start with the pricing."),
+            ("00:15", "Speaker 2", "Prices go up."),
+            ("00:20", "Speaker 1", "Okay."),
+        ]);
+        let diff = TranscriptDiff::compare(&a, &b, DEFAULT_MAX_DRIFT_SECS);
+
+        assert_eq!(diff.matched, 4);
+        assert_eq!(diff.identical, 3);
+        assert_eq!(diff.segments.len(), 6);
+        assert_eq!(diff.speakers[0].b.as_deref(), Some("Speaker 2"));
+        assert_eq!(diff.speakers[1].b.as_deref(), Some("Speaker 1"));
+        // "Let's start" is paired with B's Speaker 1, which belongs to A's Speaker 2
+        assert_eq!(diff.speaker_disagreements, 1);
+        assert_eq!(diff.drift.max_abs_secs, 2);
+        assert_eq!(diff.drift.mean_secs, 1.0);
+
+        let pricing = &diff.segments[2];
+        assert_eq!(pricing.speaker_agrees, Some(false));
+        assert_eq!(pricing.diff, "let's start with {+the+} pricing");
+        assert!(diff.segments[4].a.is_none());
+        assert!(diff.segments[5].b.is_none());
+
+        let text = diff.to_text(false);
+        assert!(text.starts_with("Segments: 5 in A, 5 in B, 4 matched (3 with the same words)\n"));
+        assert!(
+            text.contains("Speakers: Speaker 1 = Speaker 2 (2/3), Speaker 2 = Speaker 1 (1/1)\n")
+        );
+        assert!(text.contains(
+            "\n[00:09 | 00:11] text differs (80%), speaker (expected Speaker 2), drift +2s\n"
+        ));
+        assert!(text.contains("\n[--:-- | 00:20] only in B\n  B Speaker 1: Okay.\n"));
+        assert!(!text.contains("[00:00 | 00:01]"));
+        assert!(diff.to_text(true).contains("[00:00 | 00:01] drift +1s\n"));
+    }
+}
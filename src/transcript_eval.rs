@@ -0,0 +1,480 @@
+//! Accuracy of a transcript against a reference: word error rate over the
+//! whole text, how many words went to the right speaker, and the errors of
+//! each reference segment, for regression tests across models and releases.
+
+use serde::Serialize;
+
+use crate::gemini_api::TranscriptResponse;
+use crate::transcript_diff::{WordEdit, pair_speakers, render_diff, word_edits, words};
+use crate::transcript_format::segment_timestamp_secs;
+
+/// Longest `Name:` prefix read as a speaker label in a text reference
+const MAX_LABEL_CHARS: usize = 40;
+
+/// What the transcript should have said
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    pub segments: Vec<ReferenceSegment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReferenceSegment {
+    pub timestamp: Option<String>,
+    pub speaker: Option<String>,
+    pub text: String,
+}
+
+/// `Name` of a `Name: text` line, if it looks like a label rather than a
+/// sentence that happens to contain a colon (or a `10:30` time)
+fn speaker_label(line: &str) -> Option<(&str, &str)> {
+    let (label, text) = line.split_once(':')?;
+    let label = label.trim();
+    let valid = !label.is_empty()
+        && (text.is_empty() || text.starts_with(char::is_whitespace))
+        && label.chars().count() <= MAX_LABEL_CHARS
+        && !label.contains(['.', '?', '!', '"'])
+        && !label.chars().all(|c| c.is_ascii_digit());
+    valid.then(|| (label, text.trim()))
+}
+
+/// `[MM:SS]` (or `[HH:MM:SS]`) at the start of a line
+fn leading_timestamp(line: &str) -> Option<(&str, &str)> {
+    let rest = line.strip_prefix('[')?;
+    let (timestamp, text) = rest.split_once(']')?;
+    segment_timestamp_secs(timestamp).map(|_| (timestamp.trim(), text.trim()))
+}
+
+impl Reference {
+    /// Segments of a reviewed transcript JSON, with speakers and timestamps
+    pub fn from_transcript(transcript: &TranscriptResponse) -> Self {
+        Self {
+            segments: transcript
+                .segments
+                .iter()
+                .map(|segment| ReferenceSegment {
+                    timestamp: Some(segment.timestamp.to_string()),
+                    speaker: Some(segment.speaker.clone()),
+                    text: segment.content.trim().to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    /// One segment per non-empty line. Lines may start with `[MM:SS]`; when
+    /// every line also has a `Name:` label, the labels are the speakers,
+    /// otherwise the lines are taken as plain text
+    pub fn parse_text(text: &str) -> Self {
+        let lines: Vec<(Option<&str>, &str)> = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| match leading_timestamp(line) {
+                Some((timestamp, rest)) => (Some(timestamp), rest),
+                None => (None, line),
+            })
+            .collect();
+        let labeled =
+            !lines.is_empty() && lines.iter().all(|(_, line)| speaker_label(line).is_some());
+
+        let segments = lines
+            .into_iter()
+            .map(|(timestamp, line)| {
+                let (speaker, text) = match speaker_label(line).filter(|_| labeled) {
+                    Some((speaker, text)) => (Some(speaker.to_string()), text),
+                    None => (None, line),
+                };
+                ReferenceSegment {
+                    timestamp: timestamp.map(str::to_string),
+                    speaker,
+                    text: text.to_string(),
+                }
+            })
+            .collect();
+        Self { segments }
+    }
+
+    /// Whether speaker attribution can be scored
+    pub fn has_speakers(&self) -> bool {
+        self.segments
+            .iter()
+            .any(|segment| segment.speaker.is_some())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Evaluation {
+    pub reference_words: usize,
+    pub hypothesis_words: usize,
+    pub substitutions: usize,
+    pub deletions: usize,
+    pub insertions: usize,
+    /// (substitutions + deletions + insertions) / reference words
+    pub wer: f64,
+    /// Share of the aligned words given to the right speaker; `None` when
+    /// the reference has no speakers
+    pub speaker_accuracy: Option<f64>,
+    /// Reference speakers, in order of first appearance, with the
+    /// hypothesis label paired with them
+    pub speakers: Vec<SpeakerScore>,
+    pub segments: Vec<SegmentScore>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SpeakerScore {
+    pub reference: String,
+    pub hypothesis: Option<String>,
+    /// Reference words of this speaker aligned to a hypothesis word
+    pub words: usize,
+    /// Of those, how many the hypothesis gives to `hypothesis`
+    pub correct: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SegmentScore {
+    /// Position in the reference
+    pub index: usize,
+    pub timestamp: Option<String>,
+    pub speaker: Option<String>,
+    pub reference: String,
+    /// Hypothesis words aligned to this segment
+    pub hypothesis: String,
+    /// Hypothesis segments those words came from
+    pub hypothesis_segments: Vec<usize>,
+    pub reference_words: usize,
+    pub errors: usize,
+    pub wer: f64,
+    /// Word diff: `[-missed-]` and `{+wrong or extra+}`
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub diff: String,
+}
+
+/// Errors per reference word; an empty reference is only right when nothing
+/// was heard
+fn error_rate(errors: usize, reference_words: usize) -> f64 {
+    match reference_words {
+        0 if errors == 0 => 0.0,
+        0 => 1.0,
+        words => errors as f64 / words as f64,
+    }
+}
+
+impl Evaluation {
+    /// Align all words of `hypothesis` to those of `reference` at once, so
+    /// segment boundaries don't need to agree, then score the alignment.
+    ///
+    /// Words are compared lowercased and without punctuation (see
+    /// [`words`]). Speakers are scored through the best one-to-one pairing
+    /// of reference and hypothesis labels, since the hypothesis numbers its
+    /// speakers on its own.
+    pub fn evaluate(reference: &Reference, hypothesis: &TranscriptResponse) -> Self {
+        let mut ref_words = Vec::new();
+        let mut ref_owner = Vec::new();
+        for (index, segment) in reference.segments.iter().enumerate() {
+            for word in words(&segment.text) {
+                ref_words.push(word);
+                ref_owner.push(index);
+            }
+        }
+        let mut hyp_words = Vec::new();
+        let mut hyp_owner = Vec::new();
+        for (index, segment) in hypothesis.segments.iter().enumerate() {
+            for word in words(&segment.content) {
+                hyp_words.push(word);
+                hyp_owner.push(index);
+            }
+        }
+        let edits = word_edits(&ref_words, &hyp_words);
+
+        // Walk the alignment, attributing each edit to a reference segment;
+        // insertions go to the segment of the last reference word
+        let mut per_segment: Vec<Vec<WordEdit<'_>>> = vec![Vec::new(); reference.segments.len()];
+        let mut hyp_segments: Vec<Vec<usize>> = vec![Vec::new(); reference.segments.len()];
+        let mut aligned: Vec<(usize, usize)> = Vec::new();
+        let (mut substitutions, mut deletions, mut insertions) = (0, 0, 0);
+        let (mut i, mut j) = (0, 0);
+        let mut current = 0;
+        for edit in &edits {
+            let (consumes_ref, consumes_hyp) = match edit {
+                WordEdit::Same(_) => (true, true),
+                WordEdit::Substitute(..) => {
+                    substitutions += 1;
+                    (true, true)
+                }
+                WordEdit::Delete(_) => {
+                    deletions += 1;
+                    (true, false)
+                }
+                WordEdit::Insert(_) => {
+                    insertions += 1;
+                    (false, true)
+                }
+            };
+            if consumes_ref {
+                current = ref_owner[i];
+            }
+            if consumes_hyp && !per_segment.is_empty() {
+                let owner = hyp_owner[j];
+                if !hyp_segments[current].contains(&owner) {
+                    hyp_segments[current].push(owner);
+                }
+            }
+            if consumes_ref && consumes_hyp {
+                aligned.push((i, j));
+            }
+            if let Some(segment) = per_segment.get_mut(current) {
+                segment.push(*edit);
+            }
+            i += usize::from(consumes_ref);
+            j += usize::from(consumes_hyp);
+        }
+
+        let mut order: Vec<String> = Vec::new();
+        for segment in &reference.segments {
+            if let Some(speaker) = &segment.speaker
+                && !order.contains(speaker)
+            {
+                order.push(speaker.clone());
+            }
+        }
+        let labels: Vec<(&str, &str)> = aligned
+            .iter()
+            .filter_map(|&(i, j)| {
+                let speaker = reference.segments[ref_owner[i]].speaker.as_deref()?;
+                Some((speaker, hypothesis.segments[hyp_owner[j]].speaker.as_str()))
+            })
+            .collect();
+        let pairs = pair_speakers(&order, labels.iter().copied());
+        let speakers: Vec<SpeakerScore> = order
+            .into_iter()
+            .map(|speaker| {
+                let hypothesis = pairs.get(&speaker).cloned();
+                let of_speaker = labels.iter().filter(|(r, _)| *r == speaker);
+                SpeakerScore {
+                    words: of_speaker.clone().count(),
+                    correct: of_speaker
+                        .filter(|(_, h)| Some(*h) == hypothesis.as_deref())
+                        .count(),
+                    reference: speaker,
+                    hypothesis,
+                }
+            })
+            .collect();
+        let speaker_accuracy = reference.has_speakers().then(|| {
+            let correct: usize = speakers.iter().map(|s| s.correct).sum();
+            if labels.is_empty() {
+                0.0
+            } else {
+                correct as f64 / labels.len() as f64
+            }
+        });
+
+        let segments = reference
+            .segments
+            .iter()
+            .zip(per_segment)
+            .zip(hyp_segments)
+            .enumerate()
+            .map(|(index, ((segment, edits), hypothesis_segments))| {
+                let reference_words = edits
+                    .iter()
+                    .filter(|e| !matches!(e, WordEdit::Insert(_)))
+                    .count();
+                let errors = edits
+                    .iter()
+                    .filter(|e| !matches!(e, WordEdit::Same(_)))
+                    .count();
+                let hypothesis: Vec<&str> = edits
+                    .iter()
+                    .filter_map(|edit| match *edit {
+                        WordEdit::Same(word) | WordEdit::Insert(word) => Some(word),
+                        WordEdit::Substitute(_, word) => Some(word),
+                        WordEdit::Delete(_) => None,
+                    })
+                    .collect();
+                SegmentScore {
+                    index,
+                    timestamp: segment.timestamp.clone(),
+                    speaker: segment.speaker.clone(),
+                    reference: segment.text.clone(),
+                    hypothesis: hypothesis.join(" "),
+                    hypothesis_segments,
+                    reference_words,
+                    errors,
+                    wer: error_rate(errors, reference_words),
+                    diff: if errors == 0 {
+                        String::new()
+                    } else {
+                        render_diff(&edits)
+                    },
+                }
+            })
+            .collect();
+
+        Self {
+            reference_words: ref_words.len(),
+            hypothesis_words: hyp_words.len(),
+            substitutions,
+            deletions,
+            insertions,
+            wer: error_rate(substitutions + deletions + insertions, ref_words.len()),
+            speaker_accuracy,
+            speakers,
+            segments,
+        }
+    }
+
+    /// Scores followed by the reference segments with errors (all segments
+    /// with `all`)
+    pub fn to_text(&self, all: bool) -> String {
+        let mut text = format!(
+            "WER: {:.1}% ({} substitutions, {} deletions, {} insertions over {} reference words)\n",
+            self.wer * 100.0,
+            self.substitutions,
+            self.deletions,
+            self.insertions,
+            self.reference_words
+        );
+        if let Some(accuracy) = self.speaker_accuracy {
+            let speakers: Vec<String> = self
+                .speakers
+                .iter()
+                .map(|s| match &s.hypothesis {
+                    Some(h) => format!("{} = {} ({}/{})", s.reference, h, s.correct, s.words),
+                    None => format!("{} unpaired (0/{})", s.reference, s.words),
+                })
+                .collect();
+            text.push_str(&format!(
+                "Speaker accuracy: {:.1}% ({})\n",
+                accuracy * 100.0,
+                speakers.join(", ")
+            ));
+        }
+
+        for segment in &self.segments {
+            if !all && segment.errors == 0 {
+                continue;
+            }
+            let mut heading = format!("\n#{}", segment.index + 1);
+            if let Some(timestamp) = &segment.timestamp {
+                heading.push_str(&format!(" [{}]", timestamp));
+            }
+            if let Some(speaker) = &segment.speaker {
+                heading.push_str(&format!(" {}", speaker));
+            }
+            heading.push_str(&format!(
+                ": WER {:.0}% ({}/{})",
+                segment.wer * 100.0,
+                segment.errors,
+                segment.reference_words
+            ));
+            text.push_str(&heading);
+            text.push('\n');
+            text.push_str(&format!("  ref: {}\n", segment.reference));
+            if !segment.diff.is_empty() {
+                text.push_str(&format!("  ~ {}\n", segment.diff));
+            }
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn hypothesis(segments: &[(&str, &str, &str)]) -> TranscriptResponse {
+        let segments: Vec<_> = segments
+            .iter()
+            .map(|(timestamp, speaker, content)| {
+                json!({
+                    "speaker": speaker,
+                    "timestamp": timestamp,
+                    "content": content,
+                    "language": "English",
+                    "language_code": "en",
+                    "emotion": "neutral"
+                })
+            })
+            .collect();
+        serde_json::from_value(json!({"summary": "", "segments": segments})).unwrap()
+    }
+
+    #[test]
+    fn test_parse_text() {
+        let reference = Reference::parse_text("[00:01] Ann: Hello there.\n\nBo, PM: Hi Ann\n");
+        assert!(reference.has_speakers());
+        assert_eq!(reference.segments[0].timestamp.as_deref(), Some("00:01"));
+        assert_eq!(reference.segments[0].speaker.as_deref(), Some("Ann"));
+        assert_eq!(reference.segments[0].text, "Hello there.");
+        assert_eq!(reference.segments[1].speaker.as_deref(), Some("Bo, PM"));
+
+        // One line without a label: all lines are plain text
+        let reference = Reference::parse_text("Ann: Hello.\nThe meeting starts at 10:30.");
+        assert!(!reference.has_speakers());
+        assert_eq!(reference.segments[0].text, "Ann: Hello.");
+        assert_eq!(
+            Reference::parse_text("Note. Then: more").segments[0].speaker,
+            None
+        );
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let reference = Reference::parse_text(
+            "Ann: Welcome to the show.\nBo: Glad to be here.\nAnn: Tell us about Rust.",
+        );
+        let hypothesis = hypothesis(&[
+            ("00:00", "Speaker 1", "Welcome to the show. Glad to"),
+            ("00:03", "Speaker 2", "be here, really."),
+            ("00:06", "Speaker 1", "Tell us about rest."),
+        ]);
+        let evaluation = Evaluation::evaluate(&reference, &hypothesis);
+
+        assert_eq!(evaluation.reference_words, 12);
+        assert_eq!(evaluation.substitutions, 1);
+        assert_eq!(evaluation.deletions, 0);
+        assert_eq!(evaluation.insertions, 1);
+        assert!((evaluation.wer - 2.0 / 12.0).abs() < 1e-9);
+
+        assert_eq!(
+            evaluation.speakers[0].hypothesis.as_deref(),
+            Some("Speaker 1")
+        );
+        assert_eq!(
+            evaluation.speakers[1].hypothesis.as_deref(),
+            Some("Speaker 2")
+        );
+        // "Glad to" went to Speaker 1
+        assert_eq!(evaluation.speakers[1].correct, 2);
+        assert_eq!(evaluation.speakers[1].words, 4);
+        assert!((evaluation.speaker_accuracy.unwrap() - 10.0 / 12.0).abs() < 1e-9);
+
+        let bo = &evaluation.segments[1];
+        assert_eq!(bo.hypothesis, "glad to be here really");
+        assert_eq!(bo.hypothesis_segments, vec![0, 1]);
+        assert_eq!(bo.errors, 1);
+        assert_eq!(bo.diff, "glad to be here {+really+}");
+        assert_eq!(evaluation.segments[0].errors, 0);
+
+        let text = evaluation.to_text(false);
+        assert!(text.starts_with(
+            "WER: 16.7% (1 substitutions, 0 deletions, 1 insertions over 12 reference words)\n\
+             Speaker accuracy: 83.3% (Ann = Speaker 1 (8/8), Bo = Speaker 2 (2/4))\n"
+        ));
+        assert!(text.contains("\n#3 Ann: WER 25% (1/4)\n  ref: Tell us about Rust.\n"));
+        assert!(!text.contains("#1 "));
+    }
+
+    #[test]
+    fn test_evaluate_without_speakers_or_words() {
+        let reference = Reference::parse_text("");
+        let evaluation = Evaluation::evaluate(&reference, &hypothesis(&[]));
+        assert_eq!(evaluation.wer, 0.0);
+        assert_eq!(evaluation.speaker_accuracy, None);
+
+        let evaluation = Evaluation::evaluate(&reference, &hypothesis(&[("00:00", "A", "Hi")]));
+        assert_eq!(evaluation.wer, 1.0);
+        assert_eq!(evaluation.insertions, 1);
+    }
+}
@@ -0,0 +1,1390 @@
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+
+use crate::gemini_api::{TRANSLATION_LANGUAGE_CODE, Topic, TranscriptResponse, TranscriptSegment};
+use crate::timestamp::Timestamp;
+use crate::transcript_clips::segment_clips;
+use crate::transcript_query::language_tag;
+
+/// Output formats for transcripts
+#[derive(Debug, Clone, Copy, ValueEnum, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Json,
+    /// JSON Lines: a header record with the summary, then a record per
+    /// segment, for `jq` and other line-by-line processing
+    Jsonl,
+    Srt,
+    Vtt,
+    Txt,
+    /// Tab-separated Anki deck: original on the front, translation on the back
+    Anki,
+    /// Audacity label track (File > Import > Labels), a label per segment
+    Audacity,
+    /// Marker list CSV in the columns of Premiere Pro's marker export, for
+    /// jumping to segments in a video editor
+    Markers,
+    /// Standalone HTML page with click-to-seek timestamps; `convert` and
+    /// `batch_convert` point its player at the input file
+    Html,
+}
+
+impl OutputFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Json => "json",
+            OutputFormat::Jsonl => "jsonl",
+            OutputFormat::Srt => "srt",
+            OutputFormat::Vtt => "vtt",
+            OutputFormat::Txt => "txt",
+            OutputFormat::Anki => "tsv",
+            OutputFormat::Audacity => "labels.txt",
+            OutputFormat::Markers => "markers.csv",
+            OutputFormat::Html => "html",
+        }
+    }
+}
+
+/// Seconds of a segment timestamp (`MM:SS` or `HH:MM:SS`)
+pub fn segment_timestamp_secs(timestamp: &str) -> Option<u64> {
+    Timestamp::parse(timestamp).map(|t| t.secs())
+}
+
+/// SRT time (`HH:MM:SS,mmm`) of a segment timestamp; one that doesn't
+/// parse is the start of the recording
+pub fn format_timestamp_srt(timestamp: &str) -> String {
+    Timestamp::parse(timestamp).unwrap_or_default().to_srt()
+}
+
+/// WebVTT time (`HH:MM:SS.mmm`) of a segment timestamp; one that doesn't
+/// parse is the start of the recording
+pub fn format_timestamp_vtt(timestamp: &str) -> String {
+    Timestamp::parse(timestamp).unwrap_or_default().to_vtt()
+}
+
+/// Readability limits for SRT and VTT cues; a limit left `None` is off.
+///
+/// A segment that breaks a limit is split into several cues, at sentence
+/// ends where possible, with its time shared out by the length of each cue.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CueLimits {
+    /// Wrap cue text at this many characters per line, counting the SRT
+    /// `[Speaker] ` label on the first line
+    pub max_line_chars: Option<usize>,
+    /// Lines of text in one cue
+    pub max_lines: Option<usize>,
+    /// Seconds one cue stays on screen
+    pub max_duration_secs: Option<u64>,
+}
+
+/// Text alignment of WebVTT cues (the `align:` cue setting)
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
+pub enum VttAlign {
+    Start,
+    Center,
+    End,
+    Left,
+    Right,
+}
+
+impl VttAlign {
+    fn as_str(&self) -> &'static str {
+        match self {
+            VttAlign::Start => "start",
+            VttAlign::Center => "center",
+            VttAlign::End => "end",
+            VttAlign::Left => "left",
+            VttAlign::Right => "right",
+        }
+    }
+}
+
+/// Placement written after the timing of every WebVTT cue
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VttCueSettings {
+    /// `line:` setting: a line number, negative from the bottom, or a
+    /// percentage of the video height; see [`VttCueSettings::parse_line`]
+    pub line: Option<String>,
+    /// `position:` setting: a percentage of the video width
+    pub position: Option<String>,
+    pub align: Option<VttAlign>,
+}
+
+/// `value` if it is a percentage from 0 to 100, like `90%` or `12.5%`
+fn vtt_percentage(value: &str) -> Option<&str> {
+    let number: f64 = value.strip_suffix('%')?.parse().ok()?;
+    (value.starts_with(|c: char| c.is_ascii_digit()) && (0.0..=100.0).contains(&number))
+        .then_some(value)
+}
+
+impl VttCueSettings {
+    /// Check a `line:` value: an integer line number such as `-2`, or a percentage
+    pub fn parse_line(value: &str) -> Result<String, String> {
+        let value = value.trim();
+        if vtt_percentage(value).is_some() || value.parse::<i32>().is_ok() {
+            Ok(value.to_string())
+        } else {
+            Err(format!(
+                "invalid line `{}`: expected a line number like -2 or a percentage like 90%",
+                value
+            ))
+        }
+    }
+
+    /// Check a `position:` value: a percentage
+    pub fn parse_position(value: &str) -> Result<String, String> {
+        let value = value.trim();
+        vtt_percentage(value).map(str::to_string).ok_or_else(|| {
+            format!(
+                "invalid position `{}`: expected a percentage like 50%",
+                value
+            )
+        })
+    }
+
+    /// The settings as written after a cue's timing, with a leading space;
+    /// empty without any
+    fn suffix(&self) -> String {
+        let mut suffix = String::new();
+        if let Some(line) = &self.line {
+            suffix.push_str(&format!(" line:{}", line));
+        }
+        if let Some(position) = &self.position {
+            suffix.push_str(&format!(" position:{}", position));
+        }
+        if let Some(align) = self.align {
+            suffix.push_str(&format!(" align:{}", align.as_str()));
+        }
+        suffix
+    }
+}
+
+/// How SRT and VTT cues and marker timecodes are written; the default
+/// matches [`format_transcript`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubtitleOptions {
+    pub limits: CueLimits,
+    /// Cue settings for VTT; SRT has none
+    pub vtt_settings: VttCueSettings,
+    /// Frame rate of `markers` timecodes; `None` is [`DEFAULT_MARKER_FPS`]
+    pub marker_fps: Option<u32>,
+}
+
+/// A subtitle cue: formatted start and end, and its lines of text
+struct Cue {
+    start: String,
+    end: String,
+    lines: Vec<String>,
+}
+
+/// A word of cue text; a `glued` word follows the previous one without a space
+struct Word<'a> {
+    text: &'a str,
+    glued: bool,
+    sentence_end: bool,
+}
+
+const SENTENCE_ENDS: [char; 6] = ['.', '!', '?', '。', '！', '？'];
+
+/// The words of `content`. Text without spaces is cut after CJK sentence
+/// punctuation, and words longer than `width` are broken into pieces that fit
+fn cue_words(content: &str, width: Option<usize>) -> Vec<Word<'_>> {
+    let width = width.unwrap_or(usize::MAX);
+    let mut words = Vec::new();
+    for token in content.split_whitespace() {
+        let mut glued = false;
+        for piece in token.split_inclusive(['。', '！', '？']) {
+            let mut rest = piece;
+            while let Some((split, _)) = rest.char_indices().nth(width) {
+                words.push(Word {
+                    text: &rest[..split],
+                    glued,
+                    sentence_end: false,
+                });
+                rest = &rest[split..];
+                glued = true;
+            }
+            words.push(Word {
+                text: rest,
+                glued,
+                sentence_end: rest.ends_with(SENTENCE_ENDS),
+            });
+            glued = true;
+        }
+    }
+    words
+}
+
+/// Characters `words` take up on one line
+fn text_chars(words: &[Word]) -> usize {
+    words
+        .iter()
+        .enumerate()
+        .map(|(i, word)| word.text.chars().count() + usize::from(i > 0 && !word.glued))
+        .sum()
+}
+
+/// `words` wrapped greedily at `width` characters, the first line starting
+/// `indent` characters in
+fn wrap(words: &[Word], indent: usize, width: Option<usize>) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut used = indent;
+    for word in words {
+        let len = word.text.chars().count();
+        let space = usize::from(!word.glued);
+        if !line.is_empty() {
+            if width.is_some_and(|width| used + space + len > width) {
+                lines.push(std::mem::take(&mut line));
+                used = 0;
+            } else if space == 1 {
+                line.push(' ');
+                used += 1;
+            }
+        }
+        line.push_str(word.text);
+        used += len;
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Ranges of `words` for each cue: whole sentences while the cue `fits`,
+/// and a sentence that doesn't fit on its own word by word
+fn cue_ranges(words: &[Word], fits: impl Fn(&[Word]) -> bool) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    let mut end = 0;
+    while end < words.len() {
+        let next = words[end..]
+            .iter()
+            .position(|word| word.sentence_end)
+            .map_or(words.len(), |i| end + i + 1);
+        if end > start && !fits(&words[start..next]) {
+            ranges.push(start..end);
+            start = end;
+        }
+        if !fits(&words[start..next]) {
+            for i in start + 1..next {
+                if !fits(&words[start..=i]) {
+                    ranges.push(start..i);
+                    start = i;
+                }
+            }
+        }
+        end = next;
+    }
+    if end > start {
+        ranges.push(start..end);
+    }
+    ranges
+}
+
+/// When segment `i` ends: its own end time if the model gave one after its
+/// start, else the next segment's start if that is later, else five seconds
+/// after its start. Every output format times segments with this.
+fn segment_end(transcript: &TranscriptResponse, i: usize) -> Timestamp {
+    let segment = &transcript.segments[i];
+    let after_start = |end: &Timestamp| *end > segment.timestamp;
+    segment
+        .end_timestamp
+        .filter(after_start)
+        .or_else(|| {
+            transcript
+                .segments
+                .get(i + 1)
+                .map(|next| next.timestamp)
+                .filter(after_start)
+        })
+        .unwrap_or(segment.timestamp + 5000)
+}
+
+/// Start and end of segment `i` as cue times
+fn segment_bounds(transcript: &TranscriptResponse, i: usize, separator: char) -> (String, String) {
+    (
+        transcript.segments[i].timestamp.to_cue_time(separator),
+        segment_end(transcript, i).to_cue_time(separator),
+    )
+}
+
+/// Start and end of segment `i` in milliseconds
+fn segment_millis(transcript: &TranscriptResponse, i: usize) -> (u64, u64) {
+    (
+        transcript.segments[i].timestamp.millis(),
+        segment_end(transcript, i).millis(),
+    )
+}
+
+/// The cues of segment `i`; one cue timed as before unless `limits` split it
+fn segment_cues(
+    transcript: &TranscriptResponse,
+    i: usize,
+    indent: usize,
+    limits: &CueLimits,
+    separator: char,
+) -> Vec<Cue> {
+    let content = &transcript.segments[i].content;
+    let (start, end) = segment_bounds(transcript, i, separator);
+    let width = limits.max_line_chars.filter(|&width| width > 0);
+    let words = cue_words(content, width);
+    if *limits == CueLimits::default() || words.is_empty() {
+        return vec![Cue {
+            start,
+            end,
+            lines: vec![content.clone()],
+        }];
+    }
+
+    let (from, to) = segment_millis(transcript, i);
+    let max_millis = limits
+        .max_duration_secs
+        .filter(|&secs| secs > 0)
+        .map(|secs| secs * 1000);
+    let total = text_chars(&words);
+    // Cues are timed by their share of the text, so a duration limit is a
+    // limit on characters
+    let max_chars = max_millis.map(|max| ((max * total as u64 / (to - from)) as usize).max(1));
+    let max_lines = limits.max_lines.filter(|&lines| lines > 0);
+    let ranges = cue_ranges(&words, |words| {
+        max_lines.is_none_or(|max| wrap(words, indent, width).len() <= max)
+            && max_chars.is_none_or(|max| text_chars(words) <= max)
+    });
+
+    let clipped = max_millis.is_some_and(|max| to - from > max);
+    if ranges.len() == 1 && !clipped {
+        return vec![Cue {
+            start,
+            end,
+            lines: wrap(&words, indent, width),
+        }];
+    }
+
+    let duration = to - from;
+    let total: usize = ranges
+        .iter()
+        .map(|range| text_chars(&words[range.clone()]))
+        .sum();
+    let mut before = 0;
+    ranges
+        .into_iter()
+        .map(|range| {
+            let words = &words[range];
+            let cue_start = from + duration * before as u64 / total as u64;
+            before += text_chars(words);
+            let mut cue_end = from + duration * before as u64 / total as u64;
+            if let Some(max) = max_millis {
+                cue_end = cue_end.min(cue_start + max);
+            }
+            Cue {
+                start: Timestamp::from_millis(cue_start).to_cue_time(separator),
+                end: Timestamp::from_millis(cue_end).to_cue_time(separator),
+                lines: wrap(words, indent, width),
+            }
+        })
+        .collect()
+}
+
+pub fn transcript_to_srt(transcript: &TranscriptResponse) -> String {
+    transcript_to_srt_with(transcript, &SubtitleOptions::default())
+}
+
+/// SRT with long segments split into cues that keep within the limits of `options`
+pub fn transcript_to_srt_with(
+    transcript: &TranscriptResponse,
+    options: &SubtitleOptions,
+) -> String {
+    let limits = &options.limits;
+    let mut output = String::new();
+    let mut index = 0;
+
+    for (i, segment) in transcript.segments.iter().enumerate() {
+        let label = format!("[{}] ", segment.speaker);
+        let indent = label.chars().count();
+        for cue in segment_cues(transcript, i, indent, limits, ',') {
+            index += 1;
+            output.push_str(&format!("{}\n", index));
+            output.push_str(&format!("{} --> {}\n", cue.start, cue.end));
+            output.push_str(&format!("{}{}\n\n", label, cue.lines.join("\n")));
+        }
+    }
+
+    output
+}
+
+pub fn transcript_to_vtt(transcript: &TranscriptResponse) -> String {
+    transcript_to_vtt_with(transcript, &SubtitleOptions::default())
+}
+
+/// The language code most segments have, the earliest on a tie
+fn main_language(transcript: &TranscriptResponse) -> Option<&str> {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for segment in &transcript.segments {
+        let code = segment.language_code.trim();
+        if code.is_empty() {
+            continue;
+        }
+        match counts.iter_mut().find(|(c, _)| *c == code) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((code, 1)),
+        }
+    }
+    counts
+        .into_iter()
+        .rev()
+        .max_by_key(|&(_, count)| count)
+        .map(|(code, _)| code)
+}
+
+/// Text safe inside a WebVTT cue: `&`, `<` and `>` would otherwise start an
+/// entity or a tag, or end the `<v>` annotation
+fn vtt_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// WebVTT: a `Language:` header with the main language code, the summary
+/// in a `NOTE` block, and cues voiced as `<v.speaker-N Name>`, `N` counting
+/// speakers in order of appearance so pages can style them with
+/// `::cue(v.speaker-N)`. Long segments are split into cues that keep
+/// within the limits of `options`, and every cue carries its cue settings.
+pub fn transcript_to_vtt_with(
+    transcript: &TranscriptResponse,
+    options: &SubtitleOptions,
+) -> String {
+    let mut output = String::from("WEBVTT\n");
+    if let Some(language) = main_language(transcript) {
+        output.push_str(&format!("Language: {}\n", language));
+    }
+    output.push('\n');
+
+    // A NOTE block ends at a blank line and must not contain `-->`
+    let summary: Vec<String> = transcript
+        .summary
+        .lines()
+        .map(|line| line.trim().replace("-->", "->"))
+        .filter(|line| !line.is_empty())
+        .collect();
+    if !summary.is_empty() {
+        output.push_str(&format!("NOTE\n{}\n\n", summary.join("\n")));
+    }
+
+    let settings = options.vtt_settings.suffix();
+    let mut classes: HashMap<&str, usize> = HashMap::new();
+    for (i, segment) in transcript.segments.iter().enumerate() {
+        let count = classes.len();
+        let class = *classes.entry(segment.speaker.as_str()).or_insert(count + 1);
+        for cue in segment_cues(transcript, i, 0, &options.limits, '.') {
+            output.push_str(&format!("{} --> {}{}\n", cue.start, cue.end, settings));
+            output.push_str(&format!(
+                "<v.speaker-{} {}>{}\n\n",
+                class,
+                vtt_escape(&segment.speaker),
+                vtt_escape(&cue.lines.join("\n"))
+            ));
+        }
+    }
+
+    output
+}
+
+pub fn transcript_to_anki(transcript: &TranscriptResponse) -> String {
+    transcript_to_anki_with(transcript, None)
+}
+
+/// A deck Anki imports as is (File > Import): a card per segment with the
+/// original text on the front and its translation on the back, then the
+/// audio, speaker and timestamp. With `clip_extension`, the audio field
+/// names the segment's `--export-clips` file as `[sound:...]`, for clips
+/// copied into Anki's media folder. Tabs and line breaks in the text become
+/// spaces.
+pub fn transcript_to_anki_with(
+    transcript: &TranscriptResponse,
+    clip_extension: Option<&str>,
+) -> String {
+    let field = |text: &str| text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let clips = clip_extension
+        .map(|_| segment_clips(transcript, None))
+        .unwrap_or_default();
+    let mut output = String::from(
+        "#separator:tab\n#html:false\n#columns:Front\tBack\tAudio\tSpeaker\tTimestamp\n",
+    );
+    for (i, segment) in transcript.segments.iter().enumerate() {
+        let front = field(&segment.content);
+        if front.is_empty() {
+            continue;
+        }
+        let back = field(segment.translation.as_deref().unwrap_or_default());
+        let audio = clip_extension
+            .zip(clips.iter().find(|clip| clip.segment == i))
+            .map(|(extension, clip)| format!("[sound:{}.{}]", clip.file_stem, extension))
+            .unwrap_or_default();
+        output.push_str(&format!(
+            "{}\t{}\t{}\t{}\t{}\n",
+            front,
+            back,
+            audio,
+            field(&segment.speaker),
+            segment.timestamp
+        ));
+    }
+    output
+}
+
+/// Text on one line: tabs and line breaks become single spaces
+fn one_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// An Audacity label track: `start<TAB>end<TAB>label` in seconds, one label
+/// per segment reading `Speaker: text`
+pub fn transcript_to_audacity(transcript: &TranscriptResponse) -> String {
+    let mut output = String::new();
+    for (i, segment) in transcript.segments.iter().enumerate() {
+        let (start, end) = segment_millis(transcript, i);
+        output.push_str(&format!(
+            "{:.6}\t{:.6}\t{}: {}\n",
+            start as f64 / 1000.0,
+            end as f64 / 1000.0,
+            one_line(&segment.speaker),
+            one_line(&segment.content)
+        ));
+    }
+    output
+}
+
+/// Frame rate of marker timecodes unless one is given
+pub const DEFAULT_MARKER_FPS: u32 = 30;
+
+/// Non-drop-frame `HH:MM:SS:FF` timecode at `fps` frames per second, the
+/// frame rounded down
+fn format_timecode(millis: u64, fps: u32) -> String {
+    let secs = millis / 1000;
+    format!(
+        "{:02}:{:02}:{:02}:{:02}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        millis % 1000 * u64::from(fps) / 1000
+    )
+}
+
+/// A CSV field, quoted when it holds a comma, quote or line break
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// A marker per segment in the columns of Premiere Pro's marker list: the
+/// speaker as the name, the text as the description, and `HH:MM:SS:FF`
+/// timecodes at [`DEFAULT_MARKER_FPS`]
+pub fn transcript_to_markers(transcript: &TranscriptResponse) -> String {
+    transcript_to_markers_with(transcript, DEFAULT_MARKER_FPS)
+}
+
+/// [`transcript_to_markers`] with timecodes at `fps` frames per second, to
+/// match the frame rate of the editor's sequence
+pub fn transcript_to_markers_with(transcript: &TranscriptResponse, fps: u32) -> String {
+    let fps = fps.max(1);
+    let mut output = String::from("Marker Name,Description,In,Out,Duration,Marker Type\n");
+    for (i, segment) in transcript.segments.iter().enumerate() {
+        let (start, end) = segment_millis(transcript, i);
+        output.push_str(&format!(
+            "{},{},{},{},{},Comment\n",
+            csv_field(&one_line(&segment.speaker)),
+            csv_field(&one_line(&segment.content)),
+            format_timecode(start, fps),
+            format_timecode(end, fps),
+            format_timecode(end - start, fps)
+        ));
+    }
+    output
+}
+
+/// A line of [`transcript_to_jsonl`], told apart by its `type`
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum JsonlRecord<'a> {
+    Header {
+        schema_version: u32,
+        summary: &'a str,
+        #[serde(skip_serializing_if = "<[_]>::is_empty")]
+        topics: &'a [Topic],
+        #[serde(skip_serializing_if = "<[_]>::is_empty")]
+        keywords: &'a [Topic],
+    },
+    Segment {
+        index: usize,
+        #[serde(flatten)]
+        segment: &'a TranscriptSegment,
+    },
+}
+
+/// JSON Lines: a `"type": "header"` record with the schema version,
+/// summary, topics and keywords, then a `"type": "segment"` record per
+/// segment with its index and fields, one compact object per line
+pub fn transcript_to_jsonl(transcript: &TranscriptResponse) -> serde_json::Result<String> {
+    let header = JsonlRecord::Header {
+        schema_version: transcript.schema_version,
+        summary: &transcript.summary,
+        topics: &transcript.topics,
+        keywords: &transcript.keywords,
+    };
+    let mut output = serde_json::to_string(&header)?;
+    output.push('\n');
+    for (index, segment) in transcript.segments.iter().enumerate() {
+        output.push_str(&serde_json::to_string(&JsonlRecord::Segment {
+            index,
+            segment,
+        })?);
+        output.push('\n');
+    }
+    Ok(output)
+}
+
+/// Text safe in HTML content and attribute values
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Seconds for a `data-start`/`data-end` attribute: `5`, `12.25`
+fn html_secs(millis: u64) -> String {
+    (millis as f64 / 1000.0).to_string()
+}
+
+const HTML_STYLE: &str = "body { font-family: system-ui, sans-serif; max-width: 48rem; margin: 0 auto; padding: 1rem; line-height: 1.5; }
+audio { position: sticky; top: 0; width: 100%; background: #fff; padding: 0.5rem 0; }
+.segment { padding: 0.25rem 0.5rem; border-left: 3px solid transparent; }
+.segment.current { background: #fff8dc; border-left-color: #e0a800; }
+.segment p { margin: 0.25rem 0; }
+a[data-start] { font-family: monospace; color: #0645ad; text-decoration: none; }
+.speaker { font-weight: bold; }
+.emotion, .translation { color: #666; }
+.translation { font-style: italic; }";
+
+const HTML_SCRIPT: &str = "const player = document.getElementById('player');
+const segments = [...document.querySelectorAll('.segment[data-start]')];
+document.addEventListener('click', (event) => {
+  const link = event.target.closest('a[data-start]');
+  if (!link || !player) return;
+  event.preventDefault();
+  player.currentTime = Number(link.dataset.start);
+  player.play();
+});
+if (player) {
+  player.addEventListener('timeupdate', () => {
+    const time = player.currentTime;
+    for (const segment of segments) {
+      const current = time >= Number(segment.dataset.start) && time < Number(segment.dataset.end);
+      segment.classList.toggle('current', current);
+    }
+  });
+}";
+
+/// `<h2>` and a list of topics or keywords, each with a link to its first
+/// mention; empty without entries
+fn html_mentions(title: &str, mentions: &[Topic]) -> String {
+    if mentions.is_empty() {
+        return String::new();
+    }
+    let mut section = format!("<h2>{}</h2>\n<ul>\n", title);
+    for mention in mentions {
+        let name = html_escape(mention.name.trim());
+        match mention.timestamp {
+            Some(t) => section.push_str(&format!(
+                "<li><a href=\"#\" data-start=\"{}\">{}</a> {}</li>\n",
+                html_secs(t.millis()),
+                t,
+                name
+            )),
+            None => section.push_str(&format!("<li>{}</li>\n", name)),
+        }
+    }
+    section.push_str("</ul>\n");
+    section
+}
+
+/// A standalone HTML page without a player; see [`transcript_to_html_with`]
+pub fn transcript_to_html(transcript: &TranscriptResponse) -> String {
+    transcript_to_html_with(transcript, None)
+}
+
+/// A standalone HTML page of the transcript: summary, topics and keywords,
+/// then every segment with its speaker, emotion and translations. With
+/// `audio_src` (a URL or a path relative to the page) the page plays the
+/// recording: clicking a timestamp seeks to it, and the segment being played
+/// is highlighted.
+pub fn transcript_to_html_with(transcript: &TranscriptResponse, audio_src: Option<&str>) -> String {
+    let language = transcript
+        .segments
+        .iter()
+        .map(|segment| segment.language_code.trim())
+        .find(|code| !code.is_empty())
+        .unwrap_or("en");
+    let mut output = format!(
+        "<!DOCTYPE html>\n<html lang=\"{}\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>Transcript</title>\n<style>\n{}\n</style>\n</head>\n<body>\n",
+        html_escape(language),
+        HTML_STYLE
+    );
+    if let Some(src) = audio_src {
+        output.push_str(&format!(
+            "<audio id=\"player\" controls preload=\"metadata\" src=\"{}\"></audio>\n",
+            html_escape(src)
+        ));
+    }
+    output.push_str("<h1>Transcript</h1>\n");
+    if !transcript.summary.trim().is_empty() {
+        output.push_str(&format!(
+            "<p class=\"summary\">{}</p>\n",
+            html_escape(transcript.summary.trim())
+        ));
+    }
+    output.push_str(&html_mentions("Topics", &transcript.topics));
+    output.push_str(&html_mentions("Keywords", &transcript.keywords));
+
+    output.push_str("<h2>Segments</h2>\n");
+    for (i, segment) in transcript.segments.iter().enumerate() {
+        let (start, end) = segment_millis(transcript, i);
+        output.push_str(&format!(
+            "<div class=\"segment\" data-start=\"{start}\" data-end=\"{end}\">\n\
+             <a href=\"#\" data-start=\"{start}\">{time}</a>",
+            start = html_secs(start),
+            end = html_secs(end),
+            time = segment.timestamp
+        ));
+        output.push_str(&format!(
+            " <span class=\"speaker\">{}</span>",
+            html_escape(segment.speaker.trim())
+        ));
+        if !segment.emotion.trim().is_empty() {
+            output.push_str(&format!(
+                " <span class=\"emotion\">({})</span>",
+                html_escape(segment.emotion.trim())
+            ));
+        }
+        output.push_str(&format!(
+            "\n<p lang=\"{}\">{}</p>\n",
+            html_escape(segment.language_code.trim()),
+            html_escape(segment.content.trim())
+        ));
+        if let Some(translation) = segment
+            .translation
+            .as_deref()
+            .filter(|t| !t.trim().is_empty())
+        {
+            output.push_str(&format!(
+                "<p class=\"translation\" lang=\"{}\">{}</p>\n",
+                TRANSLATION_LANGUAGE_CODE,
+                html_escape(translation.trim())
+            ));
+        }
+        for (language, translation) in &segment.translations {
+            if translation.trim().is_empty() {
+                continue;
+            }
+            // `lang` only takes a code; a language name goes in the title
+            let lang = match language_tag(language) {
+                Some(code) => format!(" lang=\"{}\"", html_escape(code)),
+                None => format!(" title=\"{}\"", html_escape(language.trim())),
+            };
+            output.push_str(&format!(
+                "<p class=\"translation\"{}>{}</p>\n",
+                lang,
+                html_escape(translation.trim())
+            ));
+        }
+        output.push_str("</div>\n");
+    }
+    output.push_str(&format!(
+        "<script>\n{}\n</script>\n</body>\n</html>\n",
+        HTML_SCRIPT
+    ));
+    output
+}
+
+/// Path from an HTML page at `page` to `media`, for
+/// [`transcript_to_html_with`]: relative to the page's directory, with `/`
+/// separators, and percent-encoded where a URL needs it
+pub fn media_src(media: &Path, page: &Path) -> String {
+    let absolute = |path: &Path| std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let media = absolute(media);
+    let page = absolute(page);
+    let page_dir = page.parent().unwrap_or(Path::new(""));
+    let mut media_parts = media.components().peekable();
+    let mut dir_parts = page_dir.components().peekable();
+    while let (Some(a), Some(b)) = (media_parts.peek(), dir_parts.peek())
+        && a == b
+    {
+        media_parts.next();
+        dir_parts.next();
+    }
+    let parts: Vec<String> = dir_parts
+        .map(|_| "..".to_string())
+        .chain(media_parts.map(|part| part.as_os_str().to_string_lossy().into_owned()))
+        .collect();
+    let mut src = String::new();
+    for c in parts.join("/").chars() {
+        match c {
+            '%' | ' ' | '#' | '?' | '"' | '\\' => src.push_str(&format!("%{:02X}", c as u32)),
+            _ => src.push(c),
+        }
+    }
+    src
+}
+
+pub fn transcript_to_txt(transcript: &TranscriptResponse) -> String {
+    let mut output = String::new();
+
+    output.push_str(&format!("Summary:\n{}\n\n", transcript.summary));
+    output.push_str(&mentions_section("Topics", &transcript.topics));
+    output.push_str(&mentions_section("Keywords", &transcript.keywords));
+    output.push_str("---\n\n");
+
+    for segment in &transcript.segments {
+        output.push_str(&format!(
+            "[{}] {} ({})\n{}\n",
+            segment.timestamp, segment.speaker, segment.emotion, segment.content
+        ));
+        if let Some(ref translation) = segment.translation
+            && !translation.is_empty()
+        {
+            output.push_str(&format!("  Translation: {}\n", translation));
+        }
+        output.push('\n');
+    }
+
+    output
+}
+
+/// `Title:` followed by one `- name (MM:SS)` line per entry (`HH:MM:SS` from
+/// an hour on); empty without entries
+fn mentions_section(title: &str, mentions: &[Topic]) -> String {
+    if mentions.is_empty() {
+        return String::new();
+    }
+    let mut section = format!("{}:\n", title);
+    for mention in mentions {
+        match mention.timestamp {
+            Some(t) => section.push_str(&format!("- {} ({})\n", mention.name.trim(), t)),
+            None => section.push_str(&format!("- {}\n", mention.name.trim())),
+        }
+    }
+    section.push('\n');
+    section
+}
+
+/// One `[MM:SS] Speaker: text` line per segment, with the English
+/// translation appended when there is one; the form transcripts are given to
+/// text models in
+pub fn transcript_to_lines(transcript: &TranscriptResponse) -> String {
+    transcript
+        .segments
+        .iter()
+        .map(|segment| {
+            let mut line = format!(
+                "[{}] {}: {}",
+                segment.timestamp,
+                segment.speaker,
+                segment.content.trim()
+            );
+            if let Some(translation) = segment
+                .translation
+                .as_deref()
+                .filter(|t| !t.trim().is_empty())
+            {
+                line.push_str(&format!(" (English: {})", translation.trim()));
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Render a transcript in the given output format
+pub fn format_transcript(
+    transcript: &TranscriptResponse,
+    format: OutputFormat,
+) -> serde_json::Result<String> {
+    format_transcript_with(transcript, format, &SubtitleOptions::default())
+}
+
+/// Render a transcript, writing SRT and VTT cues and marker timecodes as
+/// `options` say
+pub fn format_transcript_with(
+    transcript: &TranscriptResponse,
+    format: OutputFormat,
+    options: &SubtitleOptions,
+) -> serde_json::Result<String> {
+    match format {
+        OutputFormat::Json => serde_json::to_string_pretty(transcript),
+        OutputFormat::Jsonl => transcript_to_jsonl(transcript),
+        OutputFormat::Srt => Ok(transcript_to_srt_with(transcript, options)),
+        OutputFormat::Vtt => Ok(transcript_to_vtt_with(transcript, options)),
+        OutputFormat::Txt => Ok(transcript_to_txt(transcript)),
+        OutputFormat::Anki => Ok(transcript_to_anki(transcript)),
+        OutputFormat::Audacity => Ok(transcript_to_audacity(transcript)),
+        OutputFormat::Markers => Ok(transcript_to_markers_with(
+            transcript,
+            options.marker_fps.unwrap_or(DEFAULT_MARKER_FPS),
+        )),
+        OutputFormat::Html => Ok(transcript_to_html(transcript)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gemini_api::TranscriptSegment;
+    use crate::transcript_schema::TRANSCRIPT_SCHEMA_VERSION;
+
+    fn ts(timestamp: &str) -> Timestamp {
+        Timestamp::parse(timestamp).unwrap()
+    }
+
+    fn create_test_transcript() -> TranscriptResponse {
+        TranscriptResponse {
+            schema_version: TRANSCRIPT_SCHEMA_VERSION,
+            summary: "Test summary".to_string(),
+            segments: vec![
+                TranscriptSegment {
+                    speaker: "Speaker 1".to_string(),
+                    timestamp: ts("00:05"),
+                    end_timestamp: None,
+                    content: "Hello world".to_string(),
+                    language: "English".to_string(),
+                    language_code: "en".to_string(),
+                    translation: None,
+                    translations: Default::default(),
+                    emotion: "neutral".to_string(),
+                },
+                TranscriptSegment {
+                    speaker: "Speaker 2".to_string(),
+                    timestamp: ts("00:10"),
+                    end_timestamp: None,
+                    content: "Hi there".to_string(),
+                    language: "English".to_string(),
+                    language_code: "en".to_string(),
+                    translation: Some("Bonjour".to_string()),
+                    translations: Default::default(),
+                    emotion: "happy".to_string(),
+                },
+            ],
+            topics: Vec::new(),
+            keywords: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_segment_timestamp_secs() {
+        assert_eq!(segment_timestamp_secs("05:30"), Some(330));
+        assert_eq!(segment_timestamp_secs("75:00"), Some(4500));
+        assert_eq!(segment_timestamp_secs("01:02:03"), Some(3723));
+        assert_eq!(segment_timestamp_secs("00:61"), None);
+        assert_eq!(segment_timestamp_secs("soon"), None);
+    }
+
+    #[test]
+    fn test_format_timestamp_srt() {
+        assert_eq!(format_timestamp_srt("05:30"), "00:05:30,000");
+        assert_eq!(format_timestamp_srt("00:05"), "00:00:05,000");
+        assert_eq!(format_timestamp_srt("75:30"), "01:15:30,000");
+        assert_eq!(format_timestamp_srt("01:02:03"), "01:02:03,000");
+        assert_eq!(format_timestamp_srt("soon"), "00:00:00,000");
+    }
+
+    #[test]
+    fn test_format_timestamp_vtt() {
+        assert_eq!(format_timestamp_vtt("05:30"), "00:05:30.000");
+        assert_eq!(format_timestamp_vtt("00:05"), "00:00:05.000");
+        assert_eq!(format_timestamp_vtt("1:59:59"), "01:59:59.000");
+    }
+
+    #[test]
+    fn test_end_timestamps_time_cues() {
+        let mut transcript = create_test_transcript();
+        transcript.segments[0].end_timestamp = Timestamp::parse("00:08");
+        transcript.segments[1].end_timestamp = Timestamp::parse("00:21");
+        let srt = transcript_to_srt(&transcript);
+        assert!(srt.contains("00:00:05,000 --> 00:00:08,000\n"), "{}", srt);
+        assert!(srt.contains("00:00:10,000 --> 00:00:21,000\n"), "{}", srt);
+        let vtt = transcript_to_vtt(&transcript);
+        assert!(vtt.contains("00:00:10.000 --> 00:00:21.000"), "{}", vtt);
+        assert!(transcript_to_audacity(&transcript).starts_with("5.000000\t8.000000\t"));
+
+        // An end that comes before the start is ignored
+        transcript.segments[0].end_timestamp = Timestamp::parse("00:04");
+        transcript.segments[1].end_timestamp = None;
+        let srt = transcript_to_srt(&transcript);
+        assert!(srt.contains("00:00:05,000 --> 00:00:10,000\n"), "{}", srt);
+        assert!(srt.contains("00:00:10,000 --> 00:00:15,000\n"), "{}", srt);
+
+        // So is a next segment starting no later, the same in every format
+        transcript.segments[0].end_timestamp = None;
+        transcript.segments[1].timestamp = ts("00:05");
+        let srt = transcript_to_srt(&transcript);
+        assert!(
+            srt.starts_with("1\n00:00:05,000 --> 00:00:10,000\n"),
+            "{}",
+            srt
+        );
+        assert!(transcript_to_audacity(&transcript).starts_with("5.000000\t10.000000\t"));
+        assert!(transcript_to_markers(&transcript).contains(",00:00:05:00,00:00:10:00,"));
+    }
+
+    #[test]
+    fn test_timestamps_past_an_hour() {
+        let mut transcript = create_test_transcript();
+        transcript.segments[0].timestamp = ts("59:58");
+        transcript.segments[1].timestamp = ts("60:03");
+        let srt = transcript_to_srt(&transcript);
+        assert!(srt.contains("00:59:58,000 --> 01:00:03,000\n"), "{}", srt);
+        assert!(srt.contains("01:00:03,000 --> 01:00:08,000\n"), "{}", srt);
+        let vtt = transcript_to_vtt(&transcript);
+        assert!(vtt.contains("01:00:03.000 --> 01:00:08.000\n"), "{}", vtt);
+        let txt = transcript_to_txt(&transcript);
+        assert!(
+            txt.contains("[59:58] ") && txt.contains("[01:00:03] "),
+            "{}",
+            txt
+        );
+    }
+
+    #[test]
+    fn test_transcript_to_srt() {
+        let transcript = create_test_transcript();
+        let srt = transcript_to_srt(&transcript);
+
+        assert!(srt.contains("1\n"));
+        assert!(srt.contains("00:00:05,000 --> 00:00:10,000"));
+        assert!(srt.contains("[Speaker 1] Hello world"));
+        // Last segment ends 5 seconds after it starts
+        assert!(srt.contains("00:00:10,000 --> 00:00:15,000"));
+    }
+
+    #[test]
+    fn test_transcript_to_vtt() {
+        let transcript = create_test_transcript();
+        let vtt = transcript_to_vtt(&transcript);
+
+        assert!(vtt.starts_with("WEBVTT\nLanguage: en\n\nNOTE\nTest summary\n\n"));
+        assert!(vtt.contains("00:00:05.000 --> 00:00:10.000"));
+        assert!(vtt.contains("<v.speaker-1 Speaker 1>Hello world"));
+        assert!(vtt.contains("<v.speaker-2 Speaker 2>Hi there"));
+
+        // Speakers and text can't open tags or entities
+        let mut transcript = transcript;
+        transcript.segments[0].speaker = "Tom & <Jerry>".to_string();
+        transcript.segments[0].content = "1 < 2 --> 3 & <b>".to_string();
+        assert!(
+            transcript_to_vtt(&transcript).contains(
+                "<v.speaker-1 Tom &amp; &lt;Jerry&gt;>1 &lt; 2 --&gt; 3 &amp; &lt;b&gt;\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_vtt_metadata_and_settings() {
+        let mut transcript = create_test_transcript();
+        transcript.summary = "Greetings.\n\nA --> B".to_string();
+        transcript.segments[0].language_code = "fr".to_string();
+        transcript.segments[1].speaker = "Speaker 1".to_string();
+        let options = SubtitleOptions {
+            vtt_settings: VttCueSettings {
+                line: Some("-2".to_string()),
+                position: Some("50%".to_string()),
+                align: Some(VttAlign::Center),
+            },
+            ..Default::default()
+        };
+        let vtt = transcript_to_vtt_with(&transcript, &options);
+        // Language ties go to the first one seen
+        assert!(vtt.starts_with("WEBVTT\nLanguage: fr\n\nNOTE\nGreetings.\nA -> B\n\n"));
+        assert!(vtt.contains(
+            "00:00:10.000 --> 00:00:15.000 line:-2 position:50% align:center\n\
+             <v.speaker-1 Speaker 1>Hi there\n"
+        ));
+
+        transcript.summary.clear();
+        for segment in &mut transcript.segments {
+            segment.language_code.clear();
+        }
+        assert!(transcript_to_vtt(&transcript).starts_with("WEBVTT\n\n00:00:05.000"));
+
+        assert_eq!(VttCueSettings::parse_line("90%").unwrap(), "90%");
+        assert_eq!(VttCueSettings::parse_line(" -1 ").unwrap(), "-1");
+        assert!(VttCueSettings::parse_line("bottom").is_err());
+        assert_eq!(VttCueSettings::parse_position("12.5%").unwrap(), "12.5%");
+        assert!(VttCueSettings::parse_position("50").is_err());
+        assert!(VttCueSettings::parse_position("120%").is_err());
+        assert!(VttCueSettings::parse_position("-5%").is_err());
+    }
+
+    fn long_transcript(content: &str) -> TranscriptResponse {
+        let mut transcript = create_test_transcript();
+        transcript.segments[0].timestamp = ts("00:00");
+        transcript.segments[0].content = content.to_string();
+        transcript.segments[1].timestamp = ts("00:12");
+        transcript
+    }
+
+    #[test]
+    fn test_srt_cue_limits() {
+        let transcript = long_transcript(
+            "This is the first sentence of a long answer. Here is another one. Short end.",
+        );
+        let options = SubtitleOptions {
+            limits: CueLimits {
+                max_line_chars: Some(32),
+                max_lines: Some(2),
+                max_duration_secs: None,
+            },
+            ..Default::default()
+        };
+        let srt = transcript_to_srt_with(&transcript, &options);
+        assert!(srt.starts_with(
+            "1\n00:00:00,000 --> 00:00:07,040\n\
+             [Speaker 1] This is the first\nsentence of a long answer.\n\n\
+             2\n00:00:07,040 --> 00:00:12,000\n\
+             [Speaker 1] Here is another one.\nShort end.\n\n\
+             3\n00:00:12,000 --> 00:00:17,000\n"
+        ));
+
+        assert_eq!(
+            transcript_to_srt_with(&transcript, &SubtitleOptions::default()),
+            transcript_to_srt(&transcript)
+        );
+    }
+
+    #[test]
+    fn test_vtt_cue_limits() {
+        let transcript =
+            long_transcript("你好，欢迎收听我们的节目。今天我们聊一聊人工智能的发展历史和未来。");
+        let options = SubtitleOptions {
+            limits: CueLimits {
+                max_line_chars: Some(10),
+                max_lines: None,
+                max_duration_secs: Some(5),
+            },
+            ..Default::default()
+        };
+        let vtt = transcript_to_vtt_with(&transcript, &options);
+        assert!(vtt.contains(
+            "\n\n00:00:00.000 --> 00:00:04.727\n<v.speaker-1 Speaker 1>你好，欢迎收听我们的\n节目。\n\n\
+             00:00:04.727 --> 00:00:08.363\n<v.speaker-1 Speaker 1>今天我们聊一聊人工智\n\n\
+             00:00:08.363 --> 00:00:12.000\n<v.speaker-1 Speaker 1>能的发展历史和未来。\n\n"
+        ));
+
+        // A cue that can't be split is cut short instead
+        let transcript = long_transcript("Yes.");
+        let vtt = transcript_to_vtt_with(&transcript, &options);
+        assert!(vtt.contains("00:00:00.000 --> 00:00:05.000\n<v.speaker-1 Speaker 1>Yes.\n"));
+    }
+
+    #[test]
+    fn test_transcript_to_anki() {
+        let mut transcript = create_test_transcript();
+        transcript.segments[0].content = "Hello\tworld,\nagain".to_string();
+        let deck = transcript_to_anki(&transcript);
+        assert_eq!(
+            deck,
+            "#separator:tab\n#html:false\n#columns:Front\tBack\tAudio\tSpeaker\tTimestamp\n\
+             Hello world, again\t\t\tSpeaker 1\t00:05\n\
+             Hi there\tBonjour\t\tSpeaker 2\t00:10\n"
+        );
+
+        // Audio fields name the clips --export-clips writes
+        let deck = transcript_to_anki_with(&transcript, Some("mp3"));
+        assert!(deck.contains("Hi there\tBonjour\t[sound:002_00-10_speaker-2.mp3]\tSpeaker 2"));
+    }
+
+    #[test]
+    fn test_transcript_to_audacity() {
+        let mut transcript = create_test_transcript();
+        transcript.segments[0].content = "Hello\tworld".to_string();
+        assert_eq!(
+            transcript_to_audacity(&transcript),
+            "5.000000\t10.000000\tSpeaker 1: Hello world\n\
+             10.000000\t15.000000\tSpeaker 2: Hi there\n"
+        );
+    }
+
+    #[test]
+    fn test_transcript_to_markers() {
+        let mut transcript = create_test_transcript();
+        transcript.segments[1].content = "Hi, \"you\"".to_string();
+        transcript.segments[1].timestamp = ts("1:02:03");
+        assert_eq!(
+            transcript_to_markers(&transcript),
+            "Marker Name,Description,In,Out,Duration,Marker Type\n\
+             Speaker 1,Hello world,00:00:05:00,01:02:03:00,01:01:58:00,Comment\n\
+             Speaker 2,\"Hi, \"\"you\"\"\",01:02:03:00,01:02:08:00,00:00:05:00,Comment\n"
+        );
+        assert_eq!(format_timecode(3_723_500, 30), "01:02:03:15");
+        assert_eq!(format_timecode(999, 30), "00:00:00:29");
+    }
+
+    #[test]
+    fn test_markers_at_other_frame_rates() {
+        let mut transcript = create_test_transcript();
+        transcript.segments[0].timestamp = ts("00:00:05.500");
+        let options = SubtitleOptions {
+            marker_fps: Some(25),
+            ..Default::default()
+        };
+        assert_eq!(
+            format_transcript_with(&transcript, OutputFormat::Markers, &options).unwrap(),
+            "Marker Name,Description,In,Out,Duration,Marker Type\n\
+             Speaker 1,Hello world,00:00:05:12,00:00:10:00,00:00:04:12,Comment\n\
+             Speaker 2,Hi there,00:00:10:00,00:00:15:00,00:00:05:00,Comment\n"
+        );
+        assert_eq!(format_timecode(999, 25), "00:00:00:24");
+        assert_eq!(format_timecode(999, 24), "00:00:00:23");
+        assert_eq!(format_timecode(1_000, 60), "00:00:01:00");
+    }
+
+    #[test]
+    fn test_transcript_to_txt() {
+        let transcript = create_test_transcript();
+        let txt = transcript_to_txt(&transcript);
+
+        assert!(txt.contains("Summary:"));
+        assert!(txt.contains("Test summary"));
+        assert!(txt.contains("[00:05] Speaker 1 (neutral)"));
+        assert!(txt.contains("Hello world"));
+        assert!(txt.contains("  Translation: Bonjour"));
+        assert!(!txt.contains("Topics:"));
+    }
+
+    #[test]
+    fn test_transcript_to_txt_with_topics() {
+        let mut transcript = create_test_transcript();
+        transcript.topics = vec![Topic {
+            name: "Greetings".to_string(),
+            timestamp: Some(ts("00:05")),
+        }];
+        transcript.keywords = vec![Topic {
+            name: "Bonjour".to_string(),
+            timestamp: None,
+        }];
+        let txt = transcript_to_txt(&transcript);
+        assert!(txt.starts_with(
+            "Summary:\nTest summary\n\nTopics:\n- Greetings (00:05)\n\nKeywords:\n- Bonjour\n\n---\n\n"
+        ));
+
+        let json = format_transcript(&transcript, OutputFormat::Json).unwrap();
+        assert!(json.contains("\"topics\""));
+        assert!(
+            !format_transcript(&create_test_transcript(), OutputFormat::Json)
+                .unwrap()
+                .contains("\"topics\"")
+        );
+    }
+
+    #[test]
+    fn test_transcript_to_lines() {
+        let transcript = create_test_transcript();
+        assert_eq!(
+            transcript_to_lines(&transcript),
+            "[00:05] Speaker 1: Hello world\n[00:10] Speaker 2: Hi there (English: Bonjour)"
+        );
+    }
+
+    #[test]
+    fn test_format_transcript_json() {
+        let transcript = create_test_transcript();
+        let json = format_transcript(&transcript, OutputFormat::Json).unwrap();
+        let parsed: TranscriptResponse = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.segments.len(), 2);
+    }
+
+    #[test]
+    fn test_transcript_to_html() {
+        let mut transcript = create_test_transcript();
+        transcript.segments[0].content = "Fish & <chips>".to_string();
+        let html = transcript_to_html_with(&transcript, Some("../media/talk \"1\".mp3"));
+        assert!(html.starts_with("<!DOCTYPE html>\n<html lang=\"en\">"));
+        assert!(html.contains(r#"<audio id="player" controls preload="metadata" src="../media/talk &quot;1&quot;.mp3"></audio>"#));
+        assert!(html.contains(
+            "<div class=\"segment\" data-start=\"5\" data-end=\"10\">\n\
+             <a href=\"#\" data-start=\"5\">00:05</a> <span class=\"speaker\">Speaker 1</span>"
+        ));
+        assert!(html.contains("Fish &amp; &lt;chips&gt;"));
+        assert!(html.contains(
+            "<div class=\"segment\" data-start=\"10\" data-end=\"15\">\n\
+             <a href=\"#\" data-start=\"10\">00:10</a>"
+        ));
+        assert!(html.contains("<p class=\"translation\" lang=\"en\">Bonjour</p>"));
+        transcript.segments[1]
+            .translations
+            .insert("de".to_string(), "Hallo".to_string());
+        transcript.segments[1]
+            .translations
+            .insert("Italian".to_string(), "Ciao".to_string());
+        let html = transcript_to_html(&transcript);
+        assert!(html.contains("<p class=\"translation\" lang=\"de\">Hallo</p>"));
+        assert!(html.contains("<p class=\"translation\" title=\"Italian\">Ciao</p>"));
+        assert!(!transcript_to_html(&transcript).contains("<audio"));
+    }
+
+    #[test]
+    fn test_media_src() {
+        assert_eq!(
+            media_src(Path::new("talks/a b#1.mp4"), Path::new("talks/a b#1.html")),
+            "a%20b%231.mp4"
+        );
+        assert_eq!(
+            media_src(
+                Path::new("talks/day 1/a.mp3"),
+                Path::new("out/day 1/a.html")
+            ),
+            "../../talks/day%201/a.mp3"
+        );
+    }
+
+    #[test]
+    fn test_format_transcript_jsonl() {
+        let transcript = create_test_transcript();
+        let jsonl = format_transcript(&transcript, OutputFormat::Jsonl).unwrap();
+        let records: Vec<serde_json::Value> = jsonl
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0],
+            serde_json::json!({
+                "type": "header",
+                "schema_version": transcript.schema_version,
+                "summary": "Test summary"
+            })
+        );
+        assert_eq!(records[2]["type"], "segment");
+        assert_eq!(records[2]["index"], 1);
+        assert_eq!(records[2]["speaker"], "Speaker 2");
+        assert_eq!(records[2]["translation"], "Bonjour");
+        assert!(jsonl.ends_with("}\n"));
+    }
+
+    #[test]
+    fn test_output_format_extension() {
+        assert_eq!(OutputFormat::Json.extension(), "json");
+        assert_eq!(OutputFormat::Jsonl.extension(), "jsonl");
+        assert_eq!(OutputFormat::Html.extension(), "html");
+        assert_eq!(OutputFormat::Srt.extension(), "srt");
+        assert_eq!(OutputFormat::Vtt.extension(), "vtt");
+        assert_eq!(OutputFormat::Txt.extension(), "txt");
+    }
+}
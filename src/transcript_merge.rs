@@ -0,0 +1,273 @@
+//! Transcripts of a recording split across several files, merged into one:
+//! timestamps moved to the combined timeline, generic speaker labels
+//! numbered consistently, and (with the `text` feature) a summary written
+//! for the whole recording by [`TextClient`].
+
+use std::collections::HashMap;
+
+use crate::gemini_api::{Topic, TranscriptResponse, TranscriptSegment};
+#[cfg(feature = "text")]
+use crate::text_api::{Result, TextClient, TextError, TextGenConfig};
+#[cfg(feature = "text")]
+use crate::transcript_format::transcript_to_lines;
+use crate::transcript_schema::TRANSCRIPT_SCHEMA_VERSION;
+
+#[cfg(feature = "text")]
+const SUMMARY_INSTRUCTION: &str = "You write the summary of a recorded conversation from its \
+transcript, given as `[MM:SS] Speaker: text` lines. The recording was split into parts that \
+were transcribed separately, so cover the whole conversation rather than its first or last \
+part. Write two to four sentences in the language of the conversation and answer with the \
+summary only.";
+
+/// One transcript to merge and where it starts in the combined recording
+#[derive(Debug, Clone, Copy)]
+pub struct MergePart<'a> {
+    pub transcript: &'a TranscriptResponse,
+    /// Seconds from the start of the combined recording; `None` continues
+    /// right after the last segment of the previous part
+    pub offset_secs: Option<u64>,
+}
+
+impl<'a> MergePart<'a> {
+    pub fn new(transcript: &'a TranscriptResponse) -> Self {
+        Self {
+            transcript,
+            offset_secs: None,
+        }
+    }
+
+    pub fn with_offset(mut self, offset_secs: u64) -> Self {
+        self.offset_secs = Some(offset_secs);
+        self
+    }
+}
+
+/// Whether `label` is a numbered placeholder like `Speaker 2` rather than a name
+fn is_generic_speaker(label: &str) -> bool {
+    let label = label.trim();
+    label
+        .get(..7)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("speaker"))
+        .map(|_| label[7..].trim())
+        .is_some_and(|number| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()))
+}
+
+impl TranscriptSegment {
+    /// Move the start and end of the segment `offset_secs` later
+    pub fn shift(&mut self, offset_secs: u64) {
+        let offset_millis = offset_secs.saturating_mul(1000);
+        self.timestamp = self.timestamp + offset_millis;
+        self.end_timestamp = self.end_timestamp.map(|end| end + offset_millis);
+    }
+}
+
+impl TranscriptResponse {
+    /// Move segment, topic and keyword timestamps `offset_secs` later, e.g.
+    /// for the transcript of a clip that starts that far into a recording
+    pub fn shift_timestamps(&mut self, offset_secs: u64) {
+        for segment in &mut self.segments {
+            segment.shift(offset_secs);
+        }
+        for mention in self.topics.iter_mut().chain(&mut self.keywords) {
+            mention.timestamp = mention
+                .timestamp
+                .map(|timestamp| timestamp + offset_secs.saturating_mul(1000));
+        }
+    }
+}
+
+/// Timestamp of the last segment
+fn last_timestamp_secs(transcript: &TranscriptResponse) -> u64 {
+    transcript
+        .segments
+        .last()
+        .map_or(0, |segment| segment.timestamp.secs())
+}
+
+/// Add the `mentions` not already listed (by name, ignoring case) to `merged`
+fn merge_mentions(merged: &mut Vec<Topic>, mentions: &[Topic], offset_secs: u64) {
+    for mention in mentions {
+        let name = mention.name.trim();
+        if merged
+            .iter()
+            .any(|m| m.name.trim().eq_ignore_ascii_case(name))
+        {
+            continue;
+        }
+        merged.push(Topic {
+            name: mention.name.clone(),
+            timestamp: mention
+                .timestamp
+                .map(|timestamp| timestamp + offset_secs.saturating_mul(1000)),
+        });
+    }
+}
+
+/// Concatenate `parts` into one transcript.
+///
+/// Generic labels (`Speaker 1`, `Speaker 2`, ...) are renumbered in order of
+/// first appearance across the whole recording. Each part numbers its
+/// speakers on its own, so by default its labels are kept apart from those
+/// of other parts; with `shared_speakers` the same label means the same
+/// person in every part. Named speakers are always matched by name. The
+/// summary is the part summaries joined; see [`summarize`] for a new one.
+pub fn merge_transcripts(parts: &[MergePart<'_>], shared_speakers: bool) -> TranscriptResponse {
+    let mut merged = TranscriptResponse {
+        schema_version: TRANSCRIPT_SCHEMA_VERSION,
+        summary: String::new(),
+        segments: Vec::new(),
+        topics: Vec::new(),
+        keywords: Vec::new(),
+    };
+    let mut summaries = Vec::new();
+    let mut speakers: HashMap<(Option<usize>, String), String> = HashMap::new();
+    let mut next_start = 0;
+
+    for (index, part) in parts.iter().enumerate() {
+        let offset_secs = part.offset_secs.unwrap_or(next_start);
+        next_start = offset_secs + last_timestamp_secs(part.transcript);
+
+        for segment in &part.transcript.segments {
+            let mut segment = segment.clone();
+            segment.shift(offset_secs);
+            if is_generic_speaker(&segment.speaker) {
+                let scope = (!shared_speakers).then_some(index);
+                let label = segment.speaker.trim().to_lowercase();
+                let count = speakers.len();
+                segment.speaker = speakers
+                    .entry((scope, label))
+                    .or_insert_with(|| format!("Speaker {}", count + 1))
+                    .clone();
+            }
+            merged.segments.push(segment);
+        }
+
+        merge_mentions(&mut merged.topics, &part.transcript.topics, offset_secs);
+        merge_mentions(&mut merged.keywords, &part.transcript.keywords, offset_secs);
+        let summary = part.transcript.summary.trim();
+        if !summary.is_empty() {
+            summaries.push(summary);
+        }
+    }
+
+    merged.summary = summaries.join("\n\n");
+    merged
+}
+
+/// Write a summary covering the whole of a merged `transcript`
+#[cfg(feature = "text")]
+pub async fn summarize(client: &TextClient, transcript: &TranscriptResponse) -> Result<String> {
+    let config = TextGenConfig::new().with_system_instruction(SUMMARY_INSTRUCTION);
+    let summary = client
+        .generate_with_config(&transcript_to_lines(transcript), Some(&config))
+        .await?;
+    let summary = summary.trim();
+    if summary.is_empty() {
+        return Err(TextError::InvalidResponse("Empty summary".to_string()));
+    }
+    Ok(summary.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timestamp::Timestamp;
+    use serde_json::json;
+
+    fn transcript(summary: &str, segments: &[(&str, &str)]) -> TranscriptResponse {
+        let segments: Vec<_> = segments
+            .iter()
+            .map(|(timestamp, speaker)| {
+                json!({
+                    "speaker": speaker,
+                    "timestamp": timestamp,
+                    "content": "Hi",
+                    "language": "English",
+                    "language_code": "en",
+                    "emotion": "Neutral"
+                })
+            })
+            .collect();
+        serde_json::from_value(json!({
+            "summary": summary,
+            "segments": segments,
+            "topics": [{"name": "Budget", "timestamp": "00:10"}]
+        }))
+        .unwrap()
+    }
+
+    /// `timestamp speaker` per segment
+    fn labels(transcript: &TranscriptResponse) -> Vec<String> {
+        transcript
+            .segments
+            .iter()
+            .map(|s| format!("{} {}", s.timestamp, s.speaker))
+            .collect()
+    }
+
+    #[test]
+    fn test_shift_timestamps() {
+        let mut shifted = transcript("", &[("00:05", "A"), ("00:20", "B")]);
+        shifted.segments[0].end_timestamp = Timestamp::parse("00:09");
+        shifted.shift_timestamps(3600);
+        assert_eq!(labels(&shifted), ["01:00:05 A", "01:00:20 B"]);
+        assert_eq!(
+            shifted.segments[0].end_timestamp,
+            Timestamp::parse("01:00:09")
+        );
+        assert_eq!(shifted.segments[1].end_timestamp, None);
+        assert_eq!(shifted.topics[0].timestamp, Timestamp::parse("01:00:10"));
+    }
+
+    #[test]
+    fn test_is_generic_speaker() {
+        assert!(is_generic_speaker("Speaker 1"));
+        assert!(is_generic_speaker(" speaker 12 "));
+        assert!(!is_generic_speaker("Speaker"));
+        assert!(!is_generic_speaker("Speaker A"));
+        assert!(!is_generic_speaker("Ann"));
+    }
+
+    #[test]
+    fn test_merge_offsets_and_speakers() {
+        let a = transcript("First half.", &[("00:00", "Speaker 2"), ("29:50", "Ann")]);
+        let b = transcript(
+            "Second half.",
+            &[
+                ("00:05", "Speaker 1"),
+                ("01:00:10", "Speaker 2"),
+                ("01:00:20", "Ann"),
+            ],
+        );
+        let parts = [MergePart::new(&a), MergePart::new(&b).with_offset(30 * 60)];
+
+        let merged = merge_transcripts(&parts, false);
+        assert_eq!(
+            labels(&merged),
+            [
+                "00:00 Speaker 1",
+                "29:50 Ann",
+                "30:05 Speaker 2",
+                "01:30:10 Speaker 3",
+                "01:30:20 Ann",
+            ]
+        );
+        assert_eq!(merged.summary, "First half.\n\nSecond half.");
+        assert_eq!(merged.topics.len(), 1);
+        assert_eq!(merged.topics[0].timestamp, Timestamp::parse("00:10"));
+
+        let merged = merge_transcripts(&parts, true);
+        assert_eq!(merged.segments[0].speaker, "Speaker 1");
+        assert_eq!(merged.segments[2].speaker, "Speaker 2");
+        assert_eq!(merged.segments[3].speaker, "Speaker 1");
+    }
+
+    #[test]
+    fn test_merge_continues_without_offset() {
+        let a = transcript("", &[("00:00", "Ann"), ("10:00", "Ann")]);
+        let b = transcript("", &[("00:30", "Ann")]);
+        let merged = merge_transcripts(&[MergePart::new(&a), MergePart::new(&b)], false);
+        assert_eq!(merged.segments[2].timestamp.to_string(), "10:30");
+        assert_eq!(merged.summary, "");
+    }
+}
@@ -0,0 +1,129 @@
+//! A text-model pass that writes spoken numbers, dates, times and amounts
+//! the way documentation does ("twenty five dollars" becomes "$25"), using
+//! the conventions of a locale picked per language: segments in German
+//! follow `de-DE` ("25 €", "4. März"), those in English `en-US`. Segments in
+//! a language without a locale are left alone, as are speakers, timestamps
+//! and the wording around the numbers.
+
+use crate::gemini_api::TranscriptResponse;
+use crate::text_api::{Result, TextClient, TextGenConfig};
+use crate::transcript_cleanup::CleanupStats;
+use crate::translation::{DEFAULT_BATCH_CHARS, batch_ranges, rewrite_batch};
+
+fn instruction(locale: &str) -> String {
+    format!(
+        "You prepare speech-recognition transcripts for written documentation, following \
+         {locale} conventions. Each string of the JSON array is what one speaker said, in \
+         order. Write spoken numbers as digits, and dates, times, percentages, measurements \
+         and currency amounts in the usual written form for {locale}; in en-US, for example, \
+         \"twenty five dollars\" becomes \"$25\" and \"march fourth at three thirty pm\" \
+         becomes \"March 4 at 3:30 PM\". Leave numbers that are part of a name or an idiom as \
+         they are. Change nothing else: keep the wording, punctuation and language, and never \
+         summarize, translate, merge or split strings. Answer with a JSON array of strings of \
+         the same length and order, one rewritten string per input string."
+    )
+}
+
+/// First subtag of a language code or locale, lowercased: `pt` of `pt_BR`
+fn primary_language(code: &str) -> String {
+    code.trim()
+        .split(['-', '_'])
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+}
+
+#[derive(Debug, Clone)]
+pub struct TranscriptNormalizer {
+    client: TextClient,
+    locales: Vec<String>,
+    batch_chars: usize,
+}
+
+impl TranscriptNormalizer {
+    /// Normalize segments whose language matches one of `locales` (`en-US`,
+    /// `de-DE`, or a bare `fr`), each with that locale's conventions
+    pub fn new<S: Into<String>>(client: TextClient, locales: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            client,
+            locales: locales.into_iter().map(Into::into).collect(),
+            batch_chars: DEFAULT_BATCH_CHARS,
+        }
+    }
+
+    pub fn with_batch_chars(mut self, batch_chars: usize) -> Self {
+        self.batch_chars = batch_chars.max(1);
+        self
+    }
+
+    /// The locale for segments with `language_code`; the first match wins
+    pub fn locale_for(&self, language_code: &str) -> Option<&str> {
+        let language = primary_language(language_code);
+        self.locales
+            .iter()
+            .find(|locale| !language.is_empty() && primary_language(locale) == language)
+            .map(String::as_str)
+    }
+
+    /// Normalize one batch of segment texts in `locale` with a single request
+    pub async fn normalize_batch(&self, locale: &str, texts: &[&str]) -> Result<Vec<String>> {
+        let config = TextGenConfig::new()
+            .with_system_instruction(instruction(locale))
+            .with_json_output();
+        rewrite_batch(&self.client, &config, texts, "normalized segments").await
+    }
+
+    /// Normalize the text of every segment with a locale, one request per
+    /// batch of segments sharing it. A segment the model empties keeps its
+    /// text.
+    pub async fn normalize(&self, transcript: &mut TranscriptResponse) -> Result<CleanupStats> {
+        let mut stats = CleanupStats {
+            segments: transcript.segments.len(),
+            changed: 0,
+        };
+        for locale in &self.locales {
+            let indices: Vec<usize> = transcript
+                .segments
+                .iter()
+                .enumerate()
+                .filter(|(_, segment)| self.locale_for(&segment.language_code) == Some(locale))
+                .map(|(i, _)| i)
+                .collect();
+            let texts: Vec<&str> = indices
+                .iter()
+                .map(|&i| transcript.segments[i].content.as_str())
+                .collect();
+            let mut normalized = Vec::with_capacity(texts.len());
+            for batch in batch_ranges(&texts, self.batch_chars) {
+                normalized.extend(self.normalize_batch(locale, &texts[batch]).await?);
+            }
+
+            for (i, text) in indices.into_iter().zip(normalized) {
+                let segment = &mut transcript.segments[i];
+                let text = text.trim();
+                if !text.is_empty() && text != segment.content.trim() {
+                    segment.content = text.to_string();
+                    stats.changed += 1;
+                }
+            }
+        }
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locale_for() {
+        let client = TextClient::new("key".to_string()).unwrap();
+        let normalizer = TranscriptNormalizer::new(client, ["en-US", "de_DE", "en-GB"]);
+        assert_eq!(normalizer.locale_for("en"), Some("en-US"));
+        assert_eq!(normalizer.locale_for("EN-gb"), Some("en-US"));
+        assert_eq!(normalizer.locale_for("de"), Some("de_DE"));
+        assert_eq!(normalizer.locale_for("fr"), None);
+        assert_eq!(normalizer.locale_for(""), None);
+        assert!(instruction("de-DE").contains("for de-DE"));
+    }
+}
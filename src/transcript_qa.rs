@@ -0,0 +1,169 @@
+//! Questions answered from a transcript with [`TextClient`], citing the
+//! segments the answer is based on. The transcript can be stored once with
+//! Gemini context caching so follow-up questions don't resend it.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::gemini_api::TranscriptResponse;
+use crate::text_api::{CachedContext, Result, TextClient, TextError, TextGenConfig};
+use crate::transcript_format::transcript_to_lines;
+
+/// How long a cached transcript is kept by default
+pub const DEFAULT_CONTEXT_TTL_SECS: u64 = 60 * 60;
+
+const INSTRUCTION: &str = "You answer questions about a recorded conversation using only its \
+transcript, given as `[MM:SS] Speaker: text` lines. Cite every line your answer relies on with \
+its timestamp, speaker and a short quote. If the transcript doesn't answer the question, say so \
+and cite nothing. Answer in the language of the question.";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Answer {
+    pub answer: String,
+    #[serde(default)]
+    pub citations: Vec<Citation>,
+}
+
+/// Transcript line an answer is based on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Citation {
+    pub timestamp: String,
+    #[serde(default)]
+    pub speaker: String,
+    #[serde(default)]
+    pub quote: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct TranscriptQa {
+    client: TextClient,
+    context: String,
+    cached: Option<CachedContext>,
+}
+
+impl TranscriptQa {
+    pub fn new(client: TextClient, transcript: &TranscriptResponse) -> Self {
+        let mut context = String::new();
+        if !transcript.summary.trim().is_empty() {
+            context.push_str(&format!("Summary: {}\n\n", transcript.summary.trim()));
+        }
+        context.push_str("Transcript:\n");
+        context.push_str(&transcript_to_lines(transcript));
+        Self {
+            client,
+            context,
+            cached: None,
+        }
+    }
+
+    /// Store the transcript with Gemini context caching (reusing one stored
+    /// by an earlier run if the client has a response cache), so each
+    /// question only sends itself. Fails for transcripts below the model's
+    /// minimum cache size; questions then keep sending the transcript
+    pub async fn cache_context(&mut self, ttl_secs: u64) -> Result<&CachedContext> {
+        let cached = self
+            .client
+            .cached_context(&self.context, Some(INSTRUCTION), ttl_secs)
+            .await?;
+        Ok(self.cached.insert(cached))
+    }
+
+    pub fn cached_context(&self) -> Option<&CachedContext> {
+        self.cached.as_ref()
+    }
+
+    fn request(&self, question: &str) -> (String, TextGenConfig) {
+        let config = TextGenConfig::new().with_response_schema(response_schema());
+        match &self.cached {
+            Some(cached) => (
+                format!("Question: {}", question.trim()),
+                config.with_cached_content(&cached.name),
+            ),
+            None => (
+                format!("{}\n\nQuestion: {}", self.context, question.trim()),
+                config.with_system_instruction(INSTRUCTION),
+            ),
+        }
+    }
+
+    /// Answer one question with a single request
+    pub async fn ask(&self, question: &str) -> Result<Answer> {
+        if question.trim().is_empty() {
+            return Err(TextError::EmptyPrompt);
+        }
+        let (prompt, config) = self.request(question);
+        let answer = self
+            .client
+            .generate_with_config(&prompt, Some(&config))
+            .await?;
+        serde_json::from_str(answer.trim()).map_err(|e| {
+            TextError::InvalidResponse(format!("Answer doesn't match the schema: {}", e))
+        })
+    }
+}
+
+fn response_schema() -> Value {
+    json!({
+        "type": "OBJECT",
+        "properties": {
+            "answer": { "type": "STRING" },
+            "citations": {
+                "type": "ARRAY",
+                "items": {
+                    "type": "OBJECT",
+                    "properties": {
+                        "timestamp": { "type": "STRING", "description": "MM:SS of the cited line" },
+                        "speaker": { "type": "STRING" },
+                        "quote": { "type": "STRING", "description": "Short verbatim excerpt" }
+                    },
+                    "required": ["timestamp", "speaker", "quote"]
+                }
+            }
+        },
+        "required": ["answer", "citations"]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn qa() -> TranscriptQa {
+        let transcript: TranscriptResponse = serde_json::from_value(json!({
+            "summary": "Planning call.",
+            "segments": [
+                {"speaker": "CTO", "timestamp": "01:05", "content": "I'll hire two engineers.",
+                 "language": "English", "language_code": "en", "emotion": "neutral"}
+            ]
+        }))
+        .unwrap();
+        TranscriptQa::new(TextClient::new("key".to_string()).unwrap(), &transcript)
+    }
+
+    #[test]
+    fn test_request_without_cache_sends_transcript() {
+        let (prompt, config) = qa().request(" What did the CTO commit to? ");
+        assert_eq!(
+            prompt,
+            "Summary: Planning call.\n\nTranscript:\n[01:05] CTO: I'll hire two engineers.\n\n\
+             Question: What did the CTO commit to?"
+        );
+        assert_eq!(config.system_instruction.as_deref(), Some(INSTRUCTION));
+        assert!(config.cached_content.is_none());
+    }
+
+    #[test]
+    fn test_request_with_cache_sends_only_question() {
+        let mut qa = qa();
+        qa.cached = Some(CachedContext {
+            name: "cachedContents/abc".to_string(),
+            expire_time: None,
+            token_count: None,
+        });
+        let (prompt, config) = qa.request("Who spoke?");
+        assert_eq!(prompt, "Question: Who spoke?");
+        assert_eq!(config.cached_content.as_deref(), Some("cachedContents/abc"));
+        assert!(config.system_instruction.is_none());
+        assert!(config.response_schema.is_some());
+    }
+}
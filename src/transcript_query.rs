@@ -0,0 +1,209 @@
+//! Queries over a transcript's segments: who speaks, what is said in a time
+//! window or a language, and the spoken text on its own. Methods that narrow
+//! the transcript return a copy that keeps the summary, topics and keywords.
+
+use crate::gemini_api::{TranscriptResponse, TranscriptSegment};
+
+impl TranscriptResponse {
+    /// Speakers in order of first appearance
+    pub fn speakers(&self) -> Vec<&str> {
+        let mut speakers: Vec<&str> = Vec::new();
+        for segment in &self.segments {
+            let speaker = segment.speaker.trim();
+            if !speakers.contains(&speaker) {
+                speakers.push(speaker);
+            }
+        }
+        speakers
+    }
+
+    /// Segments of `speaker`, ignoring case and surrounding whitespace
+    pub fn segments_by_speaker<'a>(
+        &'a self,
+        speaker: &'a str,
+    ) -> impl Iterator<Item = &'a TranscriptSegment> {
+        let speaker = speaker.trim();
+        self.segments
+            .iter()
+            .filter(move |segment| segment.speaker.trim().eq_ignore_ascii_case(speaker))
+    }
+
+    /// Segments that start at or after `start_secs` and before `end_secs`
+    pub fn segments_between(
+        &self,
+        start_secs: u64,
+        end_secs: u64,
+    ) -> impl Iterator<Item = &TranscriptSegment> {
+        self.segments.iter().filter(move |segment| {
+            let secs = segment.timestamp.secs();
+            secs >= start_secs && secs < end_secs
+        })
+    }
+
+    /// A copy with only the segments `keep` accepts
+    pub fn filter_segments(&self, keep: impl FnMut(&TranscriptSegment) -> bool) -> Self {
+        let mut filtered = self.clone();
+        filtered.segments.retain(keep);
+        filtered
+    }
+
+    /// A copy with only the segments in `language`: a code such as `fr`,
+    /// which also matches regional codes like `fr-CA`, or a name such as
+    /// `French`, ignoring case
+    pub fn filter_language(&self, language: &str) -> Self {
+        let language = language.trim();
+        self.filter_segments(|segment| {
+            let code = segment.language_code.trim();
+            let primary = code.split(['-', '_']).next().unwrap_or(code);
+            code.eq_ignore_ascii_case(language)
+                || primary.eq_ignore_ascii_case(language)
+                || segment.language.trim().eq_ignore_ascii_case(language)
+        })
+    }
+
+    /// A copy in `language`, one of `GeminiClientConfig::translate_to`: each
+    /// segment's text is its translation into `language` (the original where
+    /// it has none) and its language is `language`, with no other
+    /// translations, so every writer renders it as it renders an original.
+    /// Its language code is `language` when that is a code (see
+    /// [`language_tag`]) and empty for a name such as `French`.
+    pub fn in_translation(&self, language: &str) -> Self {
+        let code = language_tag(language).unwrap_or_default();
+        let mut translated = self.clone();
+        for segment in &mut translated.segments {
+            if let Some(text) = segment
+                .translations
+                .remove(language)
+                .filter(|text| !text.trim().is_empty())
+            {
+                segment.content = text;
+                segment.language = language.to_string();
+                segment.language_code = code.to_string();
+            }
+            segment.translation = None;
+            segment.translations.clear();
+        }
+        translated
+    }
+
+    /// What was said, one segment per line, without timestamps, speakers or
+    /// translations
+    pub fn to_plain_text(&self) -> String {
+        self.segments
+            .iter()
+            .map(|segment| segment.content.trim())
+            .filter(|content| !content.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// `language` when it reads as a BCP 47 tag (`es`, `pt-BR`, `zh-Hant`): a
+/// two- or three-letter lowercase primary subtag, then alphanumeric subtags.
+/// Language names (`French`, `español`) are `None`.
+pub fn language_tag(language: &str) -> Option<&str> {
+    let language = language.trim();
+    let mut subtags = language.split('-');
+    let primary = subtags.next()?;
+    let primary_ok =
+        (2..=3).contains(&primary.len()) && primary.bytes().all(|b| b.is_ascii_lowercase());
+    let rest_ok = subtags.all(|subtag| {
+        (1..=8).contains(&subtag.len()) && subtag.bytes().all(|b| b.is_ascii_alphanumeric())
+    });
+    (primary_ok && rest_ok).then_some(language)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn transcript() -> TranscriptResponse {
+        let segment =
+            |timestamp: &str, speaker: &str, content: &str, language: &str, code: &str| {
+                json!({
+                    "speaker": speaker, "timestamp": timestamp, "content": content,
+                    "language": language, "language_code": code, "emotion": "neutral"
+                })
+            };
+        serde_json::from_value(json!({
+            "summary": "A chat",
+            "segments": [
+                segment("00:00", "Speaker 1", "Hello.", "English", "en"),
+                segment("00:04", "speaker 2 ", "Bonjour.", "French", "fr-CA"),
+                segment("02:00", "Speaker 1", "Hm.", "English", "en"),
+                segment("01:00", "Speaker 1", " Salut. ", "French", "fr"),
+            ],
+            "topics": [{"name": "Greetings", "timestamp": "00:00"}]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_speakers() {
+        let transcript = transcript();
+        assert_eq!(transcript.speakers(), ["Speaker 1", "speaker 2"]);
+        let contents: Vec<_> = transcript
+            .segments_by_speaker("Speaker 2")
+            .map(|segment| segment.content.as_str())
+            .collect();
+        assert_eq!(contents, ["Bonjour."]);
+        assert_eq!(transcript.segments_by_speaker("Speaker 1").count(), 3);
+    }
+
+    #[test]
+    fn test_segments_between() {
+        let transcript = transcript();
+        let timestamps: Vec<_> = transcript
+            .segments_between(0, 60)
+            .map(|segment| segment.timestamp.to_string())
+            .collect();
+        assert_eq!(timestamps, ["00:00", "00:04"]);
+        assert_eq!(transcript.segments_between(60, 61).count(), 1);
+    }
+
+    #[test]
+    fn test_in_translation() {
+        let mut transcript = transcript();
+        transcript.segments[0]
+            .translations
+            .insert("es".to_string(), "Hola.".to_string());
+        let spanish = transcript.in_translation("es");
+        assert_eq!(spanish.segments[0].content, "Hola.");
+        assert_eq!(spanish.segments[0].language_code, "es");
+        assert!(spanish.segments[0].translations.is_empty());
+        // Untranslated segments keep their text
+        assert_eq!(spanish.segments[1].content, "Bonjour.");
+        assert_eq!(spanish.segments[1].language_code, "fr-CA");
+        // A language name is not a code
+        transcript.segments[0]
+            .translations
+            .insert("French".to_string(), "Salut.".to_string());
+        let french = transcript.in_translation("French");
+        assert_eq!(french.segments[0].language, "French");
+        assert_eq!(french.segments[0].language_code, "");
+    }
+
+    #[test]
+    fn test_language_tag() {
+        assert_eq!(language_tag("es"), Some("es"));
+        assert_eq!(language_tag(" pt-BR "), Some("pt-BR"));
+        assert_eq!(language_tag("zh-Hant"), Some("zh-Hant"));
+        assert_eq!(language_tag("French"), None);
+        assert_eq!(language_tag("español"), None);
+        assert_eq!(language_tag("en-"), None);
+        assert_eq!(language_tag(""), None);
+    }
+
+    #[test]
+    fn test_filter_language() {
+        let transcript = transcript();
+        let french = transcript.filter_language("fr");
+        assert_eq!(french.to_plain_text(), "Bonjour.\nSalut.");
+        assert_eq!(french.summary, "A chat");
+        assert_eq!(french.topics.len(), 1);
+        assert_eq!(transcript.filter_language("ENGLISH").segments.len(), 2);
+        assert_eq!(transcript.filter_language("fr-ca").segments.len(), 1);
+        assert!(transcript.filter_language("de").segments.is_empty());
+    }
+}
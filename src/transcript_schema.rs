@@ -0,0 +1,173 @@
+//! Versioning of the transcript JSON that `convert` writes and every other
+//! tool reads back. [`TRANSCRIPT_SCHEMA_VERSION`] names its shape:
+//!
+//! - Adding an optional field (chapters, word timings, confidence, ...)
+//!   keeps the version. Readers ignore fields they don't know, and new
+//!   fields are left out when empty, so older builds still read newer files.
+//! - Renaming, removing or retyping a field bumps the version, together with
+//!   a step in [`TranscriptResponse::from_json_any_version`] that upgrades
+//!   files of the previous version.
+//!
+//! Versions:
+//! - 0: no `schema_version` field, as written before it existed. Files
+//!   written by hand or by other tools may also leave out `summary` and the
+//!   `language`, `language_code` and `emotion` of a segment.
+//! - 1: `schema_version` is written first; every field of version 0.
+
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+use crate::error::ErrorKind;
+use crate::gemini_api::TranscriptResponse;
+
+/// Schema version of the transcript JSON this build writes
+pub const TRANSCRIPT_SCHEMA_VERSION: u32 = 1;
+
+/// Version given to JSON without a `schema_version` field when it is
+/// deserialized directly, such as a Gemini answer
+pub(crate) fn current_schema_version() -> u32 {
+    TRANSCRIPT_SCHEMA_VERSION
+}
+
+#[derive(Debug, Error)]
+pub enum SchemaError {
+    #[error("Invalid transcript JSON: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error(
+        "Transcript schema version {version} is newer than this build reads (up to {supported}); upgrade flashecho"
+    )]
+    Unsupported { version: u64, supported: u32 },
+
+    #[error("Invalid transcript: {0}")]
+    Invalid(String),
+}
+
+impl SchemaError {
+    pub fn kind(&self) -> ErrorKind {
+        ErrorKind::InvalidInput
+    }
+
+    /// Always `None`: parsing makes no requests
+    pub fn status(&self) -> Option<u16> {
+        None
+    }
+}
+
+/// Version 0 to 1: fill in the fields version 0 files may leave out
+fn upgrade_from_v0(transcript: &mut Map<String, Value>) {
+    transcript
+        .entry("summary")
+        .or_insert_with(|| Value::String(String::new()));
+    if let Some(Value::Array(segments)) = transcript.get_mut("segments") {
+        for segment in segments.iter_mut().filter_map(Value::as_object_mut) {
+            for field in ["language", "language_code", "emotion"] {
+                segment
+                    .entry(field)
+                    .or_insert_with(|| Value::String(String::new()));
+            }
+        }
+    }
+}
+
+impl TranscriptResponse {
+    /// Parse transcript JSON of any schema version up to
+    /// [`TRANSCRIPT_SCHEMA_VERSION`], upgrading older versions step by step
+    pub fn from_json_any_version(json: &str) -> Result<Self, SchemaError> {
+        Self::from_value_any_version(serde_json::from_str(json)?)
+    }
+
+    /// [`TranscriptResponse::from_json_any_version`] for parsed JSON
+    pub fn from_value_any_version(mut value: Value) -> Result<Self, SchemaError> {
+        let transcript = value
+            .as_object_mut()
+            .ok_or_else(|| SchemaError::Invalid("expected a JSON object".to_string()))?;
+        let version = match transcript.get("schema_version") {
+            None => 0,
+            Some(version) => version.as_u64().ok_or_else(|| {
+                SchemaError::Invalid(format!(
+                    "schema_version must be a whole number, not {}",
+                    version
+                ))
+            })?,
+        };
+        if version > u64::from(TRANSCRIPT_SCHEMA_VERSION) {
+            return Err(SchemaError::Unsupported {
+                version,
+                supported: TRANSCRIPT_SCHEMA_VERSION,
+            });
+        }
+
+        if version < 1 {
+            upgrade_from_v0(transcript);
+        }
+        transcript.insert(
+            "schema_version".to_string(),
+            Value::from(TRANSCRIPT_SCHEMA_VERSION),
+        );
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_upgrade_unversioned() {
+        let transcript = TranscriptResponse::from_json_any_version(
+            r#"{"segments": [{"speaker": "Ann", "timestamp": "00:01", "content": "Hi"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(transcript.schema_version, TRANSCRIPT_SCHEMA_VERSION);
+        assert_eq!(transcript.summary, "");
+        assert_eq!(transcript.segments[0].language_code, "");
+
+        let json = serde_json::to_string(&transcript).unwrap();
+        assert!(json.starts_with(r#"{"schema_version":1,"#));
+    }
+
+    #[test]
+    fn test_current_version_and_unknown_fields() {
+        let transcript = TranscriptResponse::from_value_any_version(json!({
+            "schema_version": 1,
+            "summary": "Talk",
+            "chapters": [{"title": "Intro"}],
+            "segments": [{
+                "speaker": "Ann", "timestamp": "00:01", "content": "Hi",
+                "language": "English", "language_code": "en", "emotion": "neutral",
+                "confidence": 0.9
+            }]
+        }))
+        .unwrap();
+        assert_eq!(transcript.summary, "Talk");
+        assert_eq!(transcript.segments.len(), 1);
+    }
+
+    #[test]
+    fn test_rejected_versions() {
+        let newer = TranscriptResponse::from_json_any_version(
+            r#"{"schema_version": 2, "summary": "", "segments": []}"#,
+        );
+        assert!(matches!(
+            newer,
+            Err(SchemaError::Unsupported {
+                version: 2,
+                supported: 1
+            })
+        ));
+        assert!(matches!(
+            TranscriptResponse::from_json_any_version(r#"{"schema_version": "one"}"#),
+            Err(SchemaError::Invalid(_))
+        ));
+        assert!(matches!(
+            TranscriptResponse::from_json_any_version("[]"),
+            Err(SchemaError::Invalid(_))
+        ));
+        assert!(matches!(
+            TranscriptResponse::from_json_any_version(r#"{"summary": ""}"#),
+            Err(SchemaError::Json(_))
+        ));
+    }
+}
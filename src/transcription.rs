@@ -0,0 +1,160 @@
+use clap::ValueEnum;
+use std::fmt;
+use std::future::Future;
+use thiserror::Error;
+
+use crate::gemini_api::{GeminiClient, GeminiError, TranscriptResponse};
+use crate::openai_api::{DEFAULT_OPENAI_MODEL, OpenAiClient, OpenAiError};
+
+#[derive(Debug, Error)]
+pub enum TranscriptionError {
+    #[error(transparent)]
+    Gemini(#[from] GeminiError),
+
+    #[error(transparent)]
+    OpenAi(#[from] OpenAiError),
+}
+
+pub type Result<T> = std::result::Result<T, TranscriptionError>;
+
+/// A speech-to-text service that produces the shared transcript structure
+pub trait TranscriptionBackend {
+    /// Transcribe audio bytes sent inline with the request
+    fn transcribe(
+        &self,
+        audio_data: &[u8],
+        mime_type: &str,
+    ) -> impl Future<Output = Result<TranscriptResponse>> + Send;
+
+    /// Largest audio payload accepted by `transcribe`
+    fn max_audio_size(&self) -> u64;
+}
+
+impl TranscriptionBackend for GeminiClient {
+    async fn transcribe(&self, audio_data: &[u8], mime_type: &str) -> Result<TranscriptResponse> {
+        Ok(self.transcribe_audio(audio_data, mime_type).await?)
+    }
+
+    fn max_audio_size(&self) -> u64 {
+        crate::gemini_api::MAX_INLINE_FILE_SIZE
+    }
+}
+
+impl TranscriptionBackend for OpenAiClient {
+    async fn transcribe(&self, audio_data: &[u8], mime_type: &str) -> Result<TranscriptResponse> {
+        Ok(self.transcribe_audio(audio_data, mime_type).await?)
+    }
+
+    fn max_audio_size(&self) -> u64 {
+        crate::openai_api::MAX_OPENAI_FILE_SIZE
+    }
+}
+
+/// Shortest request timeout [`scaled_timeout_secs`] picks
+pub const MIN_TIMEOUT_SECS: u64 = 120;
+
+/// Longest request timeout [`scaled_timeout_secs`] picks
+pub const MAX_TIMEOUT_SECS: u64 = 3600;
+
+/// Timeout added per second of audio
+const TIMEOUT_PER_AUDIO_SEC: f64 = 0.5;
+
+/// About what the MP3s `convert` extracts take per second (190 kbit/s), to
+/// guess a length from the size when ffprobe can't tell
+const AUDIO_BYTES_PER_SEC: u64 = 24_000;
+
+/// Request timeout for transcribing `duration_secs` of audio, or audio of
+/// `size_bytes` when the duration is unknown: [`MIN_TIMEOUT_SECS`] plus half
+/// a minute per minute of audio, at most [`MAX_TIMEOUT_SECS`]. A 10-minute
+/// clip gets 7 minutes, recordings from about 2 hours the full hour.
+pub fn scaled_timeout_secs(duration_secs: Option<f64>, size_bytes: u64) -> u64 {
+    let audio_secs =
+        duration_secs.unwrap_or_else(|| size_bytes as f64 / AUDIO_BYTES_PER_SEC as f64);
+    let timeout = MIN_TIMEOUT_SECS as f64 + audio_secs.max(0.0) * TIMEOUT_PER_AUDIO_SEC;
+    (timeout.ceil() as u64).min(MAX_TIMEOUT_SECS)
+}
+
+/// Transcription providers selectable from the CLI
+#[derive(Debug, Clone, Copy, ValueEnum, Default, PartialEq, Eq)]
+pub enum Provider {
+    #[default]
+    Gemini,
+    #[value(name = "openai")]
+    OpenAi,
+}
+
+impl Provider {
+    pub fn default_model(&self) -> &'static str {
+        match self {
+            Provider::Gemini => "gemini-2.5-flash",
+            Provider::OpenAi => DEFAULT_OPENAI_MODEL,
+        }
+    }
+
+    /// Environment variables checked (in order) for this provider's API key
+    pub fn api_key_vars(&self) -> &'static [&'static str] {
+        match self {
+            Provider::Gemini => &["GEMINI_API_KEY", "GOOGLE_AI_KEY"],
+            Provider::OpenAi => &["OPENAI_API_KEY"],
+        }
+    }
+
+    /// OS keyring account holding this provider's API key
+    pub fn keyring_account(&self) -> &'static str {
+        match self {
+            Provider::Gemini => "gemini",
+            Provider::OpenAi => "openai",
+        }
+    }
+
+    /// Read this provider's API key from the environment
+    pub fn api_key_from_env(&self) -> Option<String> {
+        self.api_key_vars()
+            .iter()
+            .find_map(|var| std::env::var(var).ok())
+    }
+}
+
+impl fmt::Display for Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Provider::Gemini => write!(f, "Gemini"),
+            Provider::OpenAi => write!(f, "OpenAI"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provider_defaults() {
+        assert_eq!(Provider::default(), Provider::Gemini);
+        assert_eq!(Provider::Gemini.default_model(), "gemini-2.5-flash");
+        assert_eq!(Provider::OpenAi.default_model(), "whisper-1");
+        assert_eq!(Provider::OpenAi.api_key_vars(), &["OPENAI_API_KEY"]);
+    }
+
+    #[test]
+    fn test_provider_from_cli_value() {
+        assert_eq!(
+            Provider::from_str("openai", true).unwrap(),
+            Provider::OpenAi
+        );
+        assert_eq!(
+            Provider::from_str("gemini", true).unwrap(),
+            Provider::Gemini
+        );
+        assert!(Provider::from_str("azure", true).is_err());
+    }
+
+    #[test]
+    fn test_scaled_timeout() {
+        assert_eq!(scaled_timeout_secs(Some(600.0), 0), 420);
+        assert_eq!(scaled_timeout_secs(Some(4.0 * 3600.0), 0), MAX_TIMEOUT_SECS);
+        assert_eq!(scaled_timeout_secs(Some(0.0), 0), MIN_TIMEOUT_SECS);
+        // 14.4 MB of MP3 is about 10 minutes
+        assert_eq!(scaled_timeout_secs(None, 14_400_000), 420);
+    }
+}
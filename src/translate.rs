@@ -0,0 +1,373 @@
+use anyhow::{Context, Result};
+use clap::Parser;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tracing::{Instrument, debug, info, info_span};
+
+use transcript_tool::cli::{self, CommonArgs, init_logging};
+use transcript_tool::storage::{OutputStorage, Storage};
+use transcript_tool::translation::{DEFAULT_BATCH_CHARS, Translator};
+use transcript_tool::{
+    Clients, SubtitleFormat, Subtitles, TextClientConfig, TextModel, TranscriptResponse,
+};
+
+#[derive(Parser, Debug)]
+#[command(name = "translate")]
+#[command(version)]
+#[command(about = "Translate SRT, VTT or transcript JSON files using Gemini, keeping their timing")]
+#[command(after_help = "EXAMPLES:
+    translate -i talk.srt --to French
+    translate -i talk.vtt --to ja -o talk.ja.vtt
+    translate -i talk.json --to Spanish -m pro
+    translate -i talk.srt --to German -o s3://my-bucket/subtitles/
+    cat talk.srt | translate -i - --to Korean -o -")]
+pub struct Args {
+    /// SRT, VTT or transcript JSON file (`-` for stdin)
+    #[arg(short, long)]
+    input: PathBuf,
+
+    /// Target language, e.g. French, zh-TW or "Brazilian Portuguese"
+    #[arg(short = 'l', long = "to", value_name = "LANGUAGE")]
+    target_language: String,
+
+    /// Output file path (`-` for stdout), or an s3:// / gs:// URI
+    /// [default: <input>.<language>.<ext>]
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Text model: flash (default), flash-lite, pro
+    #[arg(
+        short = 'm',
+        long,
+        env = "FLASHECHO_TEXT_MODEL",
+        default_value = "flash"
+    )]
+    model: String,
+
+    /// Characters of source text sent per request
+    #[arg(long, default_value_t = DEFAULT_BATCH_CHARS)]
+    batch_chars: usize,
+
+    /// Timeout of each API request in seconds
+    #[arg(short, long, default_value = "120")]
+    timeout: u64,
+
+    #[command(flatten)]
+    common: CommonArgs,
+}
+
+/// File being translated
+#[derive(Debug)]
+enum Document {
+    Subtitles(Subtitles),
+    Transcript(TranscriptResponse),
+}
+
+impl Document {
+    fn extension(&self) -> &'static str {
+        match self {
+            Document::Subtitles(subtitles) => subtitles.format().extension(),
+            Document::Transcript(_) => "json",
+        }
+    }
+}
+
+/// Parse by extension, or by content for stdin and unknown extensions
+fn parse_document(path: &Path, content: &str) -> Result<Document> {
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_lowercase);
+    let trimmed = content.trim_start_matches('\u{feff}').trim_start();
+    let format = match SubtitleFormat::from_path(path) {
+        Some(format) => Some(format),
+        None if ext.as_deref() == Some("json") || trimmed.starts_with('{') => None,
+        None if trimmed.starts_with("WEBVTT") => Some(SubtitleFormat::Vtt),
+        None => Some(SubtitleFormat::Srt),
+    };
+
+    match format {
+        Some(format) => Ok(Document::Subtitles(
+            Subtitles::parse(format, content)
+                .with_context(|| format!("Failed to parse {} file", format.extension()))?,
+        )),
+        None => Ok(Document::Transcript(
+            TranscriptResponse::from_json_any_version(content)
+                .context("Failed to parse transcript JSON")?,
+        )),
+    }
+}
+
+/// Split off a speaker label written by `convert` (`[Speaker 1] ` in SRT,
+/// `<v.speaker-1 Speaker 1>` in VTT), which must not be translated
+fn split_speaker(text: &str) -> (&str, &str) {
+    let end = if text.starts_with('[') {
+        text.find("] ").map(|i| i + 2)
+    } else if text.starts_with("<v ") || text.starts_with("<v.") {
+        text.find('>').map(|i| i + 1)
+    } else {
+        None
+    };
+    match end {
+        Some(end) => text.split_at(end),
+        None => ("", text),
+    }
+}
+
+/// Text of every segment to translate, in order
+fn source_texts(document: &Document) -> Vec<&str> {
+    match document {
+        Document::Subtitles(subtitles) => subtitles
+            .cues()
+            .map(|cue| split_speaker(&cue.text).1)
+            .collect(),
+        Document::Transcript(transcript) => transcript
+            .segments
+            .iter()
+            .map(|segment| segment.content.as_str())
+            .collect(),
+    }
+}
+
+/// Put translations back; transcript JSON keeps the original text and gets
+/// the translation in each segment's `translation` field
+fn apply_translations(document: &mut Document, translations: Vec<String>) {
+    match document {
+        Document::Subtitles(subtitles) => {
+            for (cue, translation) in subtitles.cues_mut().zip(translations) {
+                let (speaker, _) = split_speaker(&cue.text);
+                cue.text = format!("{}{}", speaker, translation);
+            }
+        }
+        Document::Transcript(transcript) => {
+            for (segment, translation) in transcript.segments.iter_mut().zip(translations) {
+                segment.translation = Some(translation);
+            }
+        }
+    }
+}
+
+fn render_document(document: &Document) -> Result<String> {
+    match document {
+        Document::Subtitles(subtitles) => Ok(subtitles.render()),
+        Document::Transcript(transcript) => {
+            serde_json::to_string_pretty(transcript).context("Failed to serialize transcript")
+        }
+    }
+}
+
+/// `talk.srt` + `zh-TW` -> `talk.zh-tw.srt`
+fn default_output(input: &Path, language: &str, extension: &str) -> PathBuf {
+    let slug: String = language
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect();
+    let slug = slug.trim_matches('-');
+    if input == Path::new("-") {
+        return PathBuf::from(format!("translated.{}.{}", slug, extension));
+    }
+    input.with_extension(format!("{}.{}", slug, extension))
+}
+
+async fn read_input(path: &Path) -> Result<String> {
+    if path == Path::new("-") {
+        let mut content = String::new();
+        tokio::io::stdin()
+            .read_to_string(&mut content)
+            .await
+            .context("Failed to read stdin")?;
+        return Ok(content);
+    }
+    fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Failed to read input file {:?}", path))
+}
+
+/// Run with parsed arguments; shared by this binary and its `flashecho` subcommand
+pub async fn run(args: Args) -> Result<()> {
+    init_logging(args.common.verbose, args.common.log_format);
+    args.common.install_usage_ledger("translate");
+
+    let span = info_span!("file", file = %args.input.display());
+    translate(args).instrument(span).await
+}
+
+async fn translate(args: Args) -> Result<()> {
+    if args.target_language.trim().is_empty() {
+        anyhow::bail!("--to must name a language");
+    }
+    let model: TextModel = args.model.parse().map_err(|e: String| anyhow::anyhow!(e))?;
+
+    let content = read_input(&args.input).await?;
+    let mut document = parse_document(&args.input, &content)?;
+    let to_stdout = args.output.as_deref() == Some("-");
+
+    let profile = args.common.load_profile()?;
+    let clients =
+        Clients::new(cli::gemini_api_key(&profile)?).context("Failed to create HTTP client")?;
+    let budget = args
+        .common
+        .budget()
+        .context("Failed to read usage ledger for --budget-since")?;
+    let client = clients.text(TextClientConfig {
+        timeout_secs: args.timeout,
+        max_retries: args.common.max_retries,
+        model,
+        cache: args.common.response_cache(),
+        budget,
+        ..Default::default()
+    });
+    let translator =
+        Translator::new(client, args.target_language.trim()).with_batch_chars(args.batch_chars);
+
+    let texts = source_texts(&document);
+    let batches = translator.batches(&texts);
+    debug!(
+        "Translating {} segments in {} requests",
+        texts.len(),
+        batches.len()
+    );
+
+    let pb = if !(args.common.quiet || to_stdout) {
+        let pb = ProgressBar::new(texts.len() as u64);
+        pb.set_style(
+            ProgressStyle::default_bar()
+                .template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} segments ({elapsed})")
+                .unwrap()
+                .progress_chars("#>-"),
+        );
+        Some(pb)
+    } else {
+        None
+    };
+
+    let mut translations = Vec::with_capacity(texts.len());
+    for batch in batches {
+        let count = batch.len();
+        let translated = translator
+            .translate_batch(&texts[batch])
+            .instrument(info_span!("stage", stage = "translate"))
+            .await
+            .map_err(|e| anyhow::anyhow!("Translation failed: {}", e))?;
+        translations.extend(translated);
+        if let Some(pb) = &pb {
+            pb.inc(count as u64);
+        }
+    }
+    if let Some(pb) = pb {
+        pb.finish_and_clear();
+    }
+
+    apply_translations(&mut document, translations);
+    let output = render_document(&document)?;
+
+    if to_stdout {
+        let mut stdout = tokio::io::stdout();
+        stdout
+            .write_all(output.as_bytes())
+            .await
+            .context("Failed to write to stdout")?;
+        stdout.flush().await.context("Failed to flush stdout")?;
+        return Ok(());
+    }
+
+    let default_path = default_output(
+        &args.input,
+        translator.target_language(),
+        document.extension(),
+    );
+    let default_name = default_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let target = args
+        .output
+        .clone()
+        .unwrap_or_else(|| default_path.to_string_lossy().to_string());
+    let (storage, key) = OutputStorage::open_file(&target, &default_name)
+        .with_context(|| format!("Invalid output target: {}", target))?;
+
+    storage
+        .put(&key, output.as_bytes())
+        .instrument(info_span!("stage", stage = "write"))
+        .await
+        .context("Failed to write translated file")?;
+    let location = storage.location(&key);
+    info!("Translation written to {}", location);
+    if !args.common.quiet {
+        println!("Saved to: {}", location);
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    run(Args::parse()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_document_detection() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\nHi\n";
+        let vtt = "WEBVTT\n\n00:01.000 --> 00:02.000\nHi\n";
+        let json = r#"{"summary": "", "segments": []}"#;
+        assert!(matches!(
+            parse_document(Path::new("a.srt"), srt).unwrap(),
+            Document::Subtitles(s) if s.format() == SubtitleFormat::Srt
+        ));
+        assert!(matches!(
+            parse_document(Path::new("-"), vtt).unwrap(),
+            Document::Subtitles(s) if s.format() == SubtitleFormat::Vtt
+        ));
+        assert!(matches!(
+            parse_document(Path::new("-"), json).unwrap(),
+            Document::Transcript(_)
+        ));
+        assert!(parse_document(Path::new("a.json"), srt).is_err());
+    }
+
+    #[test]
+    fn test_speaker_labels_are_kept() {
+        assert_eq!(
+            split_speaker("[Speaker 1] Hello"),
+            ("[Speaker 1] ", "Hello")
+        );
+        assert_eq!(split_speaker("<v Ann>Hello"), ("<v Ann>", "Hello"));
+        assert_eq!(
+            split_speaker("<v.speaker-2 Bo>Hello"),
+            ("<v.speaker-2 Bo>", "Hello")
+        );
+        assert_eq!(split_speaker("Hello [laughs]"), ("", "Hello [laughs]"));
+
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\n[Speaker 1] Hello\n\n2\n00:00:02,000 --> 00:00:03,000\nBye\n";
+        let mut document = parse_document(Path::new("a.srt"), srt).unwrap();
+        assert_eq!(source_texts(&document), ["Hello", "Bye"]);
+        apply_translations(
+            &mut document,
+            vec!["Bonjour".to_string(), "Salut".to_string()],
+        );
+        assert_eq!(
+            render_document(&document).unwrap(),
+            "1\n00:00:01,000 --> 00:00:02,000\n[Speaker 1] Bonjour\n\n2\n00:00:02,000 --> 00:00:03,000\nSalut\n\n"
+        );
+    }
+
+    #[test]
+    fn test_default_output() {
+        assert_eq!(
+            default_output(Path::new("talks/a.srt"), "zh-TW", "srt"),
+            PathBuf::from("talks/a.zh-tw.srt")
+        );
+        assert_eq!(
+            default_output(Path::new("-"), "Brazilian Portuguese", "vtt"),
+            PathBuf::from("translated.brazilian-portuguese.vtt")
+        );
+    }
+}
@@ -0,0 +1,145 @@
+//! Translation of many short texts (subtitle cues, transcript segments) with
+//! [`TextClient`]. Each batch goes out as a JSON array and must come back as an
+//! array of the same length, so translations map back to their inputs.
+
+use std::ops::Range;
+
+use crate::text_api::{Result, TextClient, TextError, TextGenConfig};
+
+/// Characters of source text per request; keeps the answer well under the
+/// model's output token limit
+pub const DEFAULT_BATCH_CHARS: usize = 6000;
+
+#[derive(Debug, Clone)]
+pub struct Translator {
+    client: TextClient,
+    target_language: String,
+    batch_chars: usize,
+}
+
+impl Translator {
+    pub fn new(client: TextClient, target_language: impl Into<String>) -> Self {
+        Self {
+            client,
+            target_language: target_language.into(),
+            batch_chars: DEFAULT_BATCH_CHARS,
+        }
+    }
+
+    pub fn with_batch_chars(mut self, batch_chars: usize) -> Self {
+        self.batch_chars = batch_chars.max(1);
+        self
+    }
+
+    pub fn target_language(&self) -> &str {
+        &self.target_language
+    }
+
+    /// Consecutive ranges of `texts` of at most `batch_chars` characters each;
+    /// a longer text gets a batch of its own
+    pub fn batches(&self, texts: &[&str]) -> Vec<Range<usize>> {
+        batch_ranges(texts, self.batch_chars)
+    }
+
+    fn gen_config(&self) -> TextGenConfig {
+        TextGenConfig::new()
+            .with_system_instruction(format!(
+                "Translate each string of the JSON array into {}. Answer with a JSON array of \
+                 strings of the same length and order, one translation per input string. Keep \
+                 line breaks and markup such as <i> tags, leave empty strings empty, and add no \
+                 notes.",
+                self.target_language
+            ))
+            .with_json_output()
+    }
+
+    /// Translate one batch of texts with a single request
+    pub async fn translate_batch(&self, texts: &[&str]) -> Result<Vec<String>> {
+        rewrite_batch(&self.client, &self.gen_config(), texts, "translations").await
+    }
+
+    /// Translate all texts, one request per batch
+    pub async fn translate(&self, texts: &[&str]) -> Result<Vec<String>> {
+        let mut translated = Vec::with_capacity(texts.len());
+        for batch in self.batches(texts) {
+            translated.extend(self.translate_batch(&texts[batch]).await?);
+        }
+        Ok(translated)
+    }
+}
+
+/// Send `texts` as one JSON array under `config` and read back an array of
+/// the same length, one rewritten text (named `what` in errors) per input
+pub(crate) async fn rewrite_batch(
+    client: &TextClient,
+    config: &TextGenConfig,
+    texts: &[&str],
+    what: &str,
+) -> Result<Vec<String>> {
+    // Empty texts don't need a round trip, and would only confuse the model
+    let pending: Vec<&str> = texts
+        .iter()
+        .copied()
+        .filter(|t| !t.trim().is_empty())
+        .collect();
+    if pending.is_empty() {
+        return Ok(texts.iter().map(|t| t.to_string()).collect());
+    }
+
+    let prompt = serde_json::to_string(&pending)?;
+    let answer = client.generate_with_config(&prompt, Some(config)).await?;
+    let rewritten: Vec<String> = serde_json::from_str(answer.trim()).map_err(|e| {
+        TextError::InvalidResponse(format!("Expected a JSON string array of {}: {}", what, e))
+    })?;
+    if rewritten.len() != pending.len() {
+        return Err(TextError::InvalidResponse(format!(
+            "Expected {} {}, got {}",
+            pending.len(),
+            what,
+            rewritten.len()
+        )));
+    }
+
+    let mut rewritten = rewritten.into_iter();
+    Ok(texts
+        .iter()
+        .map(|text| {
+            if text.trim().is_empty() {
+                text.to_string()
+            } else {
+                rewritten.next().unwrap_or_default()
+            }
+        })
+        .collect())
+}
+
+pub(crate) fn batch_ranges(texts: &[&str], batch_chars: usize) -> Vec<Range<usize>> {
+    let mut batches = Vec::new();
+    let mut start = 0;
+    let mut chars = 0;
+    for (i, text) in texts.iter().enumerate() {
+        let len = text.chars().count();
+        if i > start && chars + len > batch_chars {
+            batches.push(start..i);
+            start = i;
+            chars = 0;
+        }
+        chars += len;
+    }
+    if start < texts.len() {
+        batches.push(start..texts.len());
+    }
+    batches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batches_respect_char_limit() {
+        let texts = ["abcd", "efgh", "ij", "a very long cue", "k", ""];
+        assert_eq!(batch_ranges(&texts, 10), [0..3, 3..4, 4..6]);
+        assert!(batch_ranges(&[], 10).is_empty());
+    }
+}
@@ -0,0 +1,127 @@
+//! Connection settings for the HTTP clients built by this crate: how many
+//! idle connections the pool keeps and for how long, TCP and HTTP/2
+//! keep-alive, HTTP/2 flow control and response compression.
+//!
+//! The defaults suit long transcription and image requests: connections stay
+//! open between batch tasks, HTTP/2's adaptive window lets large responses
+//! use the link's full bandwidth instead of the fixed 64KB window, and JSON
+//! responses arrive gzip or brotli compressed.
+
+use reqwest::ClientBuilder;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransportConfig {
+    /// Idle connections kept per host; `None` keeps all of them
+    pub pool_max_idle_per_host: Option<usize>,
+    /// Close pooled connections idle for this long; `None` never does
+    pub pool_idle_timeout_secs: Option<u64>,
+    /// TCP keep-alive probe interval; `None` disables probes
+    pub tcp_keepalive_secs: Option<u64>,
+    /// Size HTTP/2 flow-control windows from measured bandwidth and latency
+    pub http2_adaptive_window: bool,
+    /// Ping HTTP/2 connections this often, even while idle; `None` disables pings
+    pub http2_keep_alive_interval_secs: Option<u64>,
+    /// Close an HTTP/2 connection whose ping isn't answered within this
+    pub http2_keep_alive_timeout_secs: u64,
+    /// Ask for gzip/brotli responses and decompress them
+    pub compression: bool,
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        Self {
+            pool_max_idle_per_host: None,
+            pool_idle_timeout_secs: Some(600),
+            tcp_keepalive_secs: Some(60),
+            http2_adaptive_window: true,
+            http2_keep_alive_interval_secs: Some(30),
+            http2_keep_alive_timeout_secs: 20,
+            compression: true,
+        }
+    }
+}
+
+impl TransportConfig {
+    pub fn with_pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.pool_max_idle_per_host = Some(max);
+        self
+    }
+
+    pub fn with_pool_idle_timeout_secs(mut self, secs: Option<u64>) -> Self {
+        self.pool_idle_timeout_secs = secs;
+        self
+    }
+
+    pub fn with_tcp_keepalive_secs(mut self, secs: Option<u64>) -> Self {
+        self.tcp_keepalive_secs = secs;
+        self
+    }
+
+    pub fn with_http2_adaptive_window(mut self, enabled: bool) -> Self {
+        self.http2_adaptive_window = enabled;
+        self
+    }
+
+    pub fn with_http2_keep_alive(mut self, interval_secs: Option<u64>, timeout_secs: u64) -> Self {
+        self.http2_keep_alive_interval_secs = interval_secs;
+        self.http2_keep_alive_timeout_secs = timeout_secs;
+        self
+    }
+
+    pub fn with_compression(mut self, enabled: bool) -> Self {
+        self.compression = enabled;
+        self
+    }
+}
+
+/// Apply `transport` to a client builder
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn apply(builder: ClientBuilder, transport: &TransportConfig) -> ClientBuilder {
+    let mut builder = builder
+        .pool_idle_timeout(transport.pool_idle_timeout_secs.map(Duration::from_secs))
+        .tcp_keepalive(transport.tcp_keepalive_secs.map(Duration::from_secs))
+        .http2_adaptive_window(transport.http2_adaptive_window)
+        .gzip(transport.compression)
+        .brotli(transport.compression);
+    if let Some(max) = transport.pool_max_idle_per_host {
+        builder = builder.pool_max_idle_per_host(max);
+    }
+    if let Some(interval) = transport.http2_keep_alive_interval_secs {
+        builder = builder
+            .http2_keep_alive_interval(Duration::from_secs(interval))
+            .http2_keep_alive_timeout(Duration::from_secs(transport.http2_keep_alive_timeout_secs))
+            .http2_keep_alive_while_idle(true);
+    }
+    builder
+}
+
+/// The browser/runtime owns connections on wasm32
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn apply(builder: ClientBuilder, _transport: &TransportConfig) -> ClientBuilder {
+    builder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transport_config_builders() {
+        let transport = TransportConfig::default()
+            .with_pool_max_idle_per_host(2)
+            .with_pool_idle_timeout_secs(None)
+            .with_http2_adaptive_window(false)
+            .with_http2_keep_alive(None, 5)
+            .with_compression(false);
+
+        assert_eq!(transport.pool_max_idle_per_host, Some(2));
+        assert_eq!(transport.pool_idle_timeout_secs, None);
+        assert_eq!(transport.tcp_keepalive_secs, Some(60));
+        assert!(!transport.http2_adaptive_window);
+        assert_eq!(transport.http2_keep_alive_interval_secs, None);
+        assert_eq!(transport.http2_keep_alive_timeout_secs, 5);
+        assert!(!transport.compression);
+    }
+}
@@ -0,0 +1,924 @@
+//! Gemini speech generation. Text is read by one prebuilt voice, or a
+//! dialogue by one voice per speaker; the model answers with raw 16-bit PCM,
+//! which [`SpeechAudio::to_wav`] wraps in a WAV header.
+//!
+//! A single request takes at most two speakers, so dialogues with more are
+//! sent in consecutive chunks of two and the audio is joined.
+
+use base64::Engine;
+use reqwest::{Client, Method};
+use serde_json::{Value, json};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{Instrument, debug, field, info, info_span, warn};
+
+use crate::auth::{AuthMode, GEMINI_BASE_URL};
+use crate::budget::{Budget, BudgetExceeded};
+use crate::cache::ResponseCache;
+use crate::circuit_breaker::{CircuitBreaker, CircuitOpen};
+use crate::error::ErrorKind;
+use crate::proxy::ProxyConfig;
+use crate::redact::ApiKey;
+use crate::rt::Stopwatch;
+use crate::tls::TlsConfig;
+use crate::transport::TransportConfig;
+
+const DEFAULT_TIMEOUT_SECS: u64 = 120;
+const DEFAULT_MAX_RETRIES: u32 = 3;
+/// Sample rate of the PCM returned by the TTS models
+const DEFAULT_SAMPLE_RATE: u32 = 24_000;
+
+/// Voice used when none is given
+pub const DEFAULT_VOICE: &str = "Kore";
+
+/// Speakers a single request can voice
+pub const MAX_SPEAKERS_PER_REQUEST: usize = 2;
+
+/// Prebuilt voices of the Gemini TTS models
+pub const VOICES: &[&str] = &[
+    "Achernar",
+    "Achird",
+    "Algenib",
+    "Algieba",
+    "Alnilam",
+    "Aoede",
+    "Autonoe",
+    "Callirrhoe",
+    "Charon",
+    "Despina",
+    "Enceladus",
+    "Erinome",
+    "Fenrir",
+    "Gacrux",
+    "Iapetus",
+    "Kore",
+    "Laomedeia",
+    "Leda",
+    "Orus",
+    "Puck",
+    "Pulcherrima",
+    "Rasalgethi",
+    "Sadachbia",
+    "Sadaltager",
+    "Schedar",
+    "Sulafat",
+    "Umbriel",
+    "Vindemiatrix",
+    "Zephyr",
+    "Zubenelgenubi",
+];
+
+/// Voices handed out, in order, to dialogue speakers without one
+const DIALOGUE_VOICES: &[&str] = &["Kore", "Puck", "Charon", "Aoede", "Fenrir", "Leda"];
+
+/// Canonical spelling of a prebuilt voice name, ignoring case
+pub fn canonical_voice(name: &str) -> Option<&'static str> {
+    VOICES
+        .iter()
+        .copied()
+        .find(|voice| voice.eq_ignore_ascii_case(name.trim()))
+}
+
+/// Supported speech generation models
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SpeechModel {
+    /// Gemini 2.5 Flash TTS - fast and cheap
+    #[default]
+    Flash,
+    /// Gemini 2.5 Pro TTS - more natural delivery
+    Pro,
+}
+
+impl SpeechModel {
+    pub fn api_model_name(&self) -> &'static str {
+        match self {
+            SpeechModel::Flash => "gemini-2.5-flash-preview-tts",
+            SpeechModel::Pro => "gemini-2.5-pro-preview-tts",
+        }
+    }
+}
+
+impl fmt::Display for SpeechModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpeechModel::Flash => write!(f, "flash"),
+            SpeechModel::Pro => write!(f, "pro"),
+        }
+    }
+}
+
+impl FromStr for SpeechModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "flash" | "2.5-flash" | "gemini-2.5-flash-preview-tts" => Ok(SpeechModel::Flash),
+            "pro" | "2.5-pro" | "gemini-2.5-pro-preview-tts" => Ok(SpeechModel::Pro),
+            _ => Err(format!("Unknown speech model: {}. Use 'flash' or 'pro'", s)),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum TtsError {
+    #[error("API key not found in environment (set GEMINI_API_KEY or GOOGLE_AI_KEY)")]
+    MissingApiKey,
+
+    #[error("Unknown voice: {0}. Run `speak --list-voices` for the available voices")]
+    UnknownVoice(String),
+
+    #[error("Nothing to speak: the text is empty")]
+    EmptyText,
+
+    #[error("Gemini API error ({status}, request {request_id}): {message}")]
+    ApiError {
+        status: u16,
+        message: String,
+        request_id: String,
+    },
+
+    #[error("Rate limited by API (request {request_id}). Retry after some time.")]
+    RateLimited { request_id: String },
+
+    #[error("Invalid response from Gemini API: {0}")]
+    InvalidResponse(String),
+
+    #[error("No audio data in response")]
+    NoAudioData,
+
+    #[error("Network error: {0}")]
+    NetworkError(#[source] reqwest::Error),
+
+    #[error("JSON parsing error: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("Max retries ({0}) exceeded")]
+    MaxRetriesExceeded(u32),
+
+    #[error(transparent)]
+    CircuitOpen(#[from] CircuitOpen),
+
+    #[error(transparent)]
+    BudgetExceeded(#[from] BudgetExceeded),
+
+    #[error("Base64 decode error: {0}")]
+    Base64Error(#[from] base64::DecodeError),
+}
+
+pub type Result<T> = std::result::Result<T, TtsError>;
+
+impl From<reqwest::Error> for TtsError {
+    fn from(err: reqwest::Error) -> Self {
+        TtsError::NetworkError(crate::redact::reqwest_error(err))
+    }
+}
+
+impl TtsError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            TtsError::MissingApiKey | TtsError::UnknownVoice(_) => ErrorKind::Config,
+            TtsError::EmptyText => ErrorKind::InvalidInput,
+            TtsError::ApiError { .. } => ErrorKind::Api,
+            TtsError::RateLimited { .. } => ErrorKind::RateLimited,
+            TtsError::InvalidResponse(_)
+            | TtsError::NoAudioData
+            | TtsError::JsonError(_)
+            | TtsError::Base64Error(_) => ErrorKind::InvalidResponse,
+            TtsError::NetworkError(_) => ErrorKind::Network,
+            TtsError::MaxRetriesExceeded(_) => ErrorKind::RetriesExhausted,
+            TtsError::CircuitOpen(_) => ErrorKind::CircuitOpen,
+            TtsError::BudgetExceeded(_) => ErrorKind::BudgetExceeded,
+        }
+    }
+
+    /// HTTP status returned by the API, if any
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            TtsError::ApiError { status, .. } => Some(*status),
+            TtsError::RateLimited { .. } => Some(429),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TtsClientConfig {
+    pub timeout_secs: u64,
+    pub max_retries: u32,
+    pub model: SpeechModel,
+    /// API host, e.g. a local mock server in tests
+    pub base_url: String,
+    /// Used by `with_config`; `with_http_client` keeps the given client's proxy
+    pub proxy: ProxyConfig,
+    /// Extra root certificates and builder hook, applied like `proxy`
+    pub tls: TlsConfig,
+    /// Connection pool, keep-alive and HTTP/2 settings, applied like `proxy`
+    pub transport: TransportConfig,
+    pub auth_mode: AuthMode,
+    /// Serve identical speech requests from disk; `None` disables
+    pub cache: Option<ResponseCache>,
+    /// Shared with other clients of a batch to stop retrying when the API is down
+    pub circuit_breaker: Option<CircuitBreaker>,
+    /// Spend limit shared with other clients; requests fail once it is reached
+    pub budget: Option<Budget>,
+}
+
+impl Default for TtsClientConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: DEFAULT_TIMEOUT_SECS,
+            max_retries: DEFAULT_MAX_RETRIES,
+            model: SpeechModel::default(),
+            base_url: GEMINI_BASE_URL.to_string(),
+            proxy: ProxyConfig::default(),
+            tls: TlsConfig::default(),
+            transport: TransportConfig::default(),
+            auth_mode: AuthMode::default(),
+            cache: None,
+            circuit_breaker: None,
+            budget: None,
+        }
+    }
+}
+
+/// Voice for one named speaker of a dialogue
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpeakerVoice {
+    pub speaker: String,
+    pub voice: String,
+}
+
+/// Voices and delivery for a request
+#[derive(Debug, Clone, Default)]
+pub struct SpeechConfig {
+    /// Voice for single-speaker text ([`DEFAULT_VOICE`] when unset)
+    pub voice: Option<String>,
+    /// Voices of dialogue speakers; others get distinct defaults in order of appearance
+    pub speaker_voices: Vec<SpeakerVoice>,
+    /// Delivery instruction put before the text, e.g. "Say cheerfully"
+    pub style: Option<String>,
+}
+
+impl SpeechConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_voice(mut self, voice: impl Into<String>) -> Self {
+        self.voice = Some(voice.into());
+        self
+    }
+
+    pub fn with_speaker_voice(
+        mut self,
+        speaker: impl Into<String>,
+        voice: impl Into<String>,
+    ) -> Self {
+        self.speaker_voices.push(SpeakerVoice {
+            speaker: speaker.into(),
+            voice: voice.into(),
+        });
+        self
+    }
+
+    pub fn with_style(mut self, style: impl Into<String>) -> Self {
+        self.style = Some(style.into());
+        self
+    }
+
+    fn single_voice(&self) -> Result<&'static str> {
+        let voice = self.voice.as_deref().unwrap_or(DEFAULT_VOICE);
+        canonical_voice(voice).ok_or_else(|| TtsError::UnknownVoice(voice.to_string()))
+    }
+
+    /// Voice for each speaker, in order of first appearance
+    fn dialogue_voices(&self, speakers: &[&str]) -> Result<Vec<&'static str>> {
+        let taken = |voice: &str| {
+            self.speaker_voices
+                .iter()
+                .any(|sv| sv.voice.eq_ignore_ascii_case(voice))
+        };
+        let mut free: Vec<&'static str> = DIALOGUE_VOICES
+            .iter()
+            .copied()
+            .filter(|voice| !taken(voice))
+            .collect();
+        if free.is_empty() {
+            free = DIALOGUE_VOICES.to_vec();
+        }
+        let mut defaults = free.into_iter().cycle();
+        speakers
+            .iter()
+            .map(
+                |speaker| match self.speaker_voices.iter().find(|sv| sv.speaker == *speaker) {
+                    Some(sv) => canonical_voice(&sv.voice)
+                        .ok_or_else(|| TtsError::UnknownVoice(sv.voice.clone())),
+                    None => Ok(defaults.next().unwrap_or(DEFAULT_VOICE)),
+                },
+            )
+            .collect()
+    }
+}
+
+/// One turn of a dialogue
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DialogueLine {
+    pub speaker: String,
+    pub text: String,
+}
+
+impl DialogueLine {
+    pub fn new(speaker: impl Into<String>, text: impl Into<String>) -> Self {
+        Self {
+            speaker: speaker.into(),
+            text: text.into(),
+        }
+    }
+}
+
+/// Split a `Speaker: text` script into turns. Lines without a speaker
+/// prefix continue the previous turn; `None` if the script doesn't start
+/// with one, i.e. it is plain text.
+pub fn parse_script(script: &str) -> Option<Vec<DialogueLine>> {
+    let mut lines: Vec<DialogueLine> = Vec::new();
+    for line in script.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        match speaker_prefix(line) {
+            Some((speaker, text)) => lines.push(DialogueLine::new(speaker, text)),
+            None => {
+                let last = lines.last_mut()?;
+                last.text.push(' ');
+                last.text.push_str(line);
+            }
+        }
+    }
+    (!lines.is_empty()).then_some(lines)
+}
+
+/// `("Name", "text")` for a line like `Name: text`
+fn speaker_prefix(line: &str) -> Option<(&str, &str)> {
+    let (speaker, text) = line.split_once(':')?;
+    let speaker = speaker.trim();
+    let is_name = !speaker.is_empty()
+        && speaker.chars().count() <= 32
+        && speaker
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, ' ' | '_' | '-' | '.' | '\''));
+    is_name.then(|| (speaker, text.trim()))
+}
+
+/// 16-bit little-endian PCM as returned by the API
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpeechAudio {
+    pub pcm: Vec<u8>,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+impl SpeechAudio {
+    /// Parse the sample rate from a MIME type like `audio/L16;codec=pcm;rate=24000`
+    pub fn from_pcm(mime_type: &str, pcm: Vec<u8>) -> Self {
+        let sample_rate = mime_type
+            .split(';')
+            .filter_map(|param| param.trim().strip_prefix("rate="))
+            .find_map(|rate| rate.parse().ok())
+            .unwrap_or(DEFAULT_SAMPLE_RATE);
+        Self {
+            pcm,
+            sample_rate,
+            channels: 1,
+        }
+    }
+
+    pub fn duration_secs(&self) -> f64 {
+        let bytes_per_second = self.sample_rate as usize * self.channels as usize * 2;
+        self.pcm.len() as f64 / bytes_per_second.max(1) as f64
+    }
+
+    /// Append `other`, which must have the same sample rate and channels
+    pub fn append(&mut self, other: SpeechAudio) -> Result<()> {
+        if other.sample_rate != self.sample_rate || other.channels != self.channels {
+            return Err(TtsError::InvalidResponse(format!(
+                "audio chunks differ in format ({} Hz vs {} Hz)",
+                self.sample_rate, other.sample_rate
+            )));
+        }
+        self.pcm.extend(other.pcm);
+        Ok(())
+    }
+
+    /// The audio as a WAV file
+    pub fn to_wav(&self) -> Vec<u8> {
+        let data_len = self.pcm.len() as u32;
+        let block_align = self.channels * 2;
+        let byte_rate = self.sample_rate * u32::from(block_align);
+
+        let mut wav = Vec::with_capacity(44 + self.pcm.len());
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        wav.extend_from_slice(&self.channels.to_le_bytes());
+        wav.extend_from_slice(&self.sample_rate.to_le_bytes());
+        wav.extend_from_slice(&byte_rate.to_le_bytes());
+        wav.extend_from_slice(&block_align.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+        wav.extend_from_slice(&self.pcm);
+        wav
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TtsClient {
+    client: Client,
+    api_key: ApiKey,
+    config: TtsClientConfig,
+}
+
+impl TtsClient {
+    pub fn new(api_key: String) -> Result<Self> {
+        Self::with_config(api_key, TtsClientConfig::default())
+    }
+
+    pub fn with_config(api_key: String, config: TtsClientConfig) -> Result<Self> {
+        let builder = crate::proxy::apply(
+            crate::rt::client_builder(config.timeout_secs),
+            &config.proxy,
+        )?;
+        let builder = crate::transport::apply(builder, &config.transport);
+        let client = crate::tls::apply(builder, &config.tls)?.build()?;
+
+        Ok(Self::with_http_client(client, api_key, config))
+    }
+
+    /// Build on an existing HTTP client, sharing its connection pool
+    pub fn with_http_client(client: Client, api_key: String, config: TtsClientConfig) -> Self {
+        Self {
+            client,
+            api_key: api_key.into(),
+            config,
+        }
+    }
+
+    fn voice_config(voice: &str) -> Value {
+        json!({"prebuiltVoiceConfig": {"voiceName": voice}})
+    }
+
+    /// Request body; `speakers` pairs each dialogue speaker with its voice,
+    /// and a single pair (or none) means a single voice
+    fn build_payload(text: &str, voice: &str, speakers: &[(&str, &str)]) -> Value {
+        let speech_config = if speakers.len() > 1 {
+            let configs: Vec<Value> = speakers
+                .iter()
+                .map(|(speaker, voice)| {
+                    json!({"speaker": speaker, "voiceConfig": Self::voice_config(voice)})
+                })
+                .collect();
+            json!({"multiSpeakerVoiceConfig": {"speakerVoiceConfigs": configs}})
+        } else {
+            json!({"voiceConfig": Self::voice_config(voice)})
+        };
+
+        json!({
+            "contents": [{"parts": [{"text": text}]}],
+            "generationConfig": {
+                "responseModalities": ["AUDIO"],
+                "speechConfig": speech_config
+            }
+        })
+    }
+
+    /// Prompt text with the style instruction, if any, in front
+    fn styled(text: &str, style: Option<&str>) -> String {
+        match style.map(str::trim).filter(|s| !s.is_empty()) {
+            Some(style) => format!("{}: {}", style.trim_end_matches(':'), text),
+            None => text.to_string(),
+        }
+    }
+
+    async fn send_request(&self, payload: &Value, request_id: &str) -> Result<SpeechAudio> {
+        let url = format!(
+            "{}/v1beta/models/{}:generateContent",
+            self.config.base_url,
+            self.config.model.api_model_name()
+        );
+
+        debug!(
+            "Sending speech request to Gemini API (model: {})",
+            self.config.model
+        );
+
+        let response = self
+            .config
+            .auth_mode
+            .request(&self.client, Method::POST, &url, &self.api_key)
+            .timeout(Duration::from_secs(self.config.timeout_secs))
+            .header("Content-Type", "application/json")
+            .json(payload)
+            .send()
+            .await?;
+
+        let status = response.status();
+        debug!("Received response with status: {}", status);
+
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            crate::metrics::record_rate_limited("tts");
+            return Err(TtsError::RateLimited {
+                request_id: request_id.to_string(),
+            });
+        }
+
+        if !status.is_success() {
+            let error_text = self
+                .api_key
+                .scrub(response.text().await.unwrap_or_default());
+            return Err(TtsError::ApiError {
+                status: status.as_u16(),
+                message: error_text,
+                request_id: request_id.to_string(),
+            });
+        }
+
+        let data: Value = response.json().await?;
+        crate::metrics::record_gemini_usage("tts", &data);
+        crate::usage::record_gemini(
+            "tts",
+            self.config.model.api_model_name(),
+            &data,
+            self.config.budget.as_ref(),
+        );
+
+        let parts = data["candidates"][0]["content"]["parts"]
+            .as_array()
+            .ok_or_else(|| TtsError::InvalidResponse("Missing parts in response".to_string()))?;
+
+        for part in parts {
+            if let Some(inline_data) = part.get("inlineData") {
+                let mime_type = inline_data["mimeType"].as_str().unwrap_or("audio/L16");
+                let base64_data = inline_data["data"]
+                    .as_str()
+                    .ok_or_else(|| TtsError::InvalidResponse("Missing audio data".to_string()))?;
+                let pcm = base64::engine::general_purpose::STANDARD.decode(base64_data)?;
+                return Ok(SpeechAudio::from_pcm(mime_type, pcm));
+            }
+        }
+
+        Err(TtsError::NoAudioData)
+    }
+
+    fn is_retryable_error(err: &TtsError) -> bool {
+        crate::error::is_retryable(err.kind(), err.status())
+    }
+
+    /// Read `text` with the default voice
+    pub async fn synthesize(&self, text: &str) -> Result<SpeechAudio> {
+        self.synthesize_with_config(text, &SpeechConfig::default())
+            .await
+    }
+
+    /// Read `text` with the voice and style from `speech`
+    pub async fn synthesize_with_config(
+        &self,
+        text: &str,
+        speech: &SpeechConfig,
+    ) -> Result<SpeechAudio> {
+        if text.trim().is_empty() {
+            return Err(TtsError::EmptyText);
+        }
+        let voice = speech.single_voice()?;
+        let prompt = Self::styled(text.trim(), speech.style.as_deref());
+        let payload = Self::build_payload(&prompt, voice, &[]);
+        self.generate_cached(&payload, prompt.len()).await
+    }
+
+    /// Read a dialogue with one voice per speaker. Dialogues with more than
+    /// [`MAX_SPEAKERS_PER_REQUEST`] speakers are sent in consecutive chunks.
+    pub async fn synthesize_dialogue(
+        &self,
+        lines: &[DialogueLine],
+        speech: &SpeechConfig,
+    ) -> Result<SpeechAudio> {
+        let lines: Vec<&DialogueLine> = lines.iter().filter(|l| !l.text.is_empty()).collect();
+        if lines.is_empty() {
+            return Err(TtsError::EmptyText);
+        }
+
+        let mut speakers: Vec<&str> = Vec::new();
+        for line in &lines {
+            if !speakers.contains(&line.speaker.as_str()) {
+                speakers.push(&line.speaker);
+            }
+        }
+        let voices = speech.dialogue_voices(&speakers)?;
+        let voice_of = |speaker: &str| {
+            let index = speakers.iter().position(|s| *s == speaker).unwrap_or(0);
+            voices[index]
+        };
+
+        let mut audio: Option<SpeechAudio> = None;
+        for chunk in Self::chunk_dialogue(&lines) {
+            let mut chunk_speakers: Vec<(&str, &str)> = Vec::new();
+            for line in &chunk {
+                if !chunk_speakers.iter().any(|(s, _)| *s == line.speaker) {
+                    chunk_speakers.push((&line.speaker, voice_of(&line.speaker)));
+                }
+            }
+
+            // A lone speaker is read as plain text, without the name prefix
+            let text = if chunk_speakers.len() > 1 {
+                chunk
+                    .iter()
+                    .map(|line| format!("{}: {}", line.speaker, line.text))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            } else {
+                chunk
+                    .iter()
+                    .map(|line| line.text.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            };
+            let prompt = Self::styled(&text, speech.style.as_deref());
+            let payload = Self::build_payload(&prompt, chunk_speakers[0].1, &chunk_speakers);
+            let part = self.generate_cached(&payload, prompt.len()).await?;
+
+            match &mut audio {
+                Some(audio) => audio.append(part)?,
+                None => audio = Some(part),
+            }
+        }
+
+        audio.ok_or(TtsError::NoAudioData)
+    }
+
+    /// Consecutive runs of lines with at most [`MAX_SPEAKERS_PER_REQUEST`] speakers each
+    fn chunk_dialogue<'a>(lines: &[&'a DialogueLine]) -> Vec<Vec<&'a DialogueLine>> {
+        let mut chunks: Vec<Vec<&DialogueLine>> = Vec::new();
+        let mut speakers: Vec<&str> = Vec::new();
+        for line in lines {
+            if !speakers.contains(&line.speaker.as_str()) {
+                if speakers.len() == MAX_SPEAKERS_PER_REQUEST {
+                    speakers.clear();
+                    chunks.push(Vec::new());
+                }
+                speakers.push(&line.speaker);
+            }
+            match chunks.last_mut() {
+                Some(chunk) => chunk.push(line),
+                None => chunks.push(vec![line]),
+            }
+        }
+        chunks
+    }
+
+    async fn generate_cached(&self, payload: &Value, prompt_bytes: usize) -> Result<SpeechAudio> {
+        let Some(cache) = &self.config.cache else {
+            return self.generate_with_retry(payload, prompt_bytes).await;
+        };
+
+        let key = ResponseCache::key(&[
+            self.config.model.api_model_name().as_bytes(),
+            payload.to_string().as_bytes(),
+        ]);
+        if let Some(audio) = cache.get_speech(&key).await {
+            info!("Using cached speech");
+            return Ok(audio);
+        }
+
+        let audio = self.generate_with_retry(payload, prompt_bytes).await?;
+        cache.put_speech(&key, &audio).await;
+        Ok(audio)
+    }
+
+    async fn generate_with_retry(
+        &self,
+        payload: &Value,
+        prompt_bytes: usize,
+    ) -> Result<SpeechAudio> {
+        let request_id = crate::request_id::next();
+        let span = info_span!(
+            "tts_request",
+            request_id = %request_id,
+            model = %self.config.model,
+            payload_bytes = prompt_bytes,
+            attempt = field::Empty,
+            latency_ms = field::Empty,
+        );
+
+        async {
+            let mut last_error = None;
+            let mut retry_count = 0;
+
+            while retry_count < self.config.max_retries {
+                if let Some(breaker) = &self.config.circuit_breaker {
+                    breaker.check()?;
+                }
+                if let Some(budget) = &self.config.budget {
+                    budget.check()?;
+                }
+                span.record("attempt", retry_count + 1);
+                let stopwatch = Stopwatch::start();
+                let result = self.send_request(payload, &request_id).await;
+                span.record("latency_ms", stopwatch.elapsed_ms());
+                if let Some(breaker) = &self.config.circuit_breaker {
+                    breaker.record(&result, Self::is_retryable_error);
+                }
+                crate::metrics::record_request("tts", result.is_ok(), stopwatch.elapsed_ms());
+                debug!(
+                    "Attempt {} finished in {}ms",
+                    retry_count + 1,
+                    stopwatch.elapsed_ms()
+                );
+
+                match result {
+                    Ok(audio) => {
+                        info!("Speech generation successful");
+                        return Ok(audio);
+                    }
+                    Err(e) => {
+                        if Self::is_retryable_error(&e)
+                            && retry_count + 1 < self.config.max_retries
+                            && self
+                                .config
+                                .circuit_breaker
+                                .as_ref()
+                                .is_none_or(CircuitBreaker::try_retry)
+                        {
+                            let delay = if matches!(e, TtsError::RateLimited { .. }) {
+                                Duration::from_secs(30 * (retry_count as u64 + 1))
+                            } else {
+                                Duration::from_secs(2u64.pow(retry_count))
+                            };
+                            warn!(
+                                "Request failed (attempt {}/{}): {}. Retrying in {:?}...",
+                                retry_count + 1,
+                                self.config.max_retries,
+                                e,
+                                delay
+                            );
+                            crate::metrics::record_retry("tts");
+                            crate::rt::sleep(delay).await;
+                            retry_count += 1;
+                            last_error = Some(e);
+                        } else {
+                            return Err(e);
+                        }
+                    }
+                }
+            }
+
+            Err(last_error.unwrap_or(TtsError::MaxRetriesExceeded(self.config.max_retries)))
+        }
+        .instrument(span.clone())
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_speech_model_from_str() {
+        assert_eq!(SpeechModel::from_str("flash").unwrap(), SpeechModel::Flash);
+        assert_eq!(SpeechModel::from_str("PRO").unwrap(), SpeechModel::Pro);
+        assert_eq!(
+            SpeechModel::from_str("gemini-2.5-pro-preview-tts").unwrap(),
+            SpeechModel::Pro
+        );
+        assert!(SpeechModel::from_str("lite").is_err());
+        assert_eq!(SpeechModel::Flash.to_string(), "flash");
+    }
+
+    #[test]
+    fn test_canonical_voice() {
+        assert_eq!(canonical_voice("kore"), Some("Kore"));
+        assert_eq!(canonical_voice(" PUCK "), Some("Puck"));
+        assert_eq!(canonical_voice("Nobody"), None);
+        assert!(DIALOGUE_VOICES.iter().all(|v| VOICES.contains(v)));
+    }
+
+    #[test]
+    fn test_build_payload_single_voice() {
+        let payload = TtsClient::build_payload("Hello", "Kore", &[]);
+        assert_eq!(payload["contents"][0]["parts"][0]["text"], "Hello");
+        let generation = &payload["generationConfig"];
+        assert_eq!(generation["responseModalities"], json!(["AUDIO"]));
+        assert_eq!(
+            generation["speechConfig"]["voiceConfig"]["prebuiltVoiceConfig"]["voiceName"],
+            "Kore"
+        );
+    }
+
+    #[test]
+    fn test_build_payload_multi_speaker() {
+        let payload =
+            TtsClient::build_payload("A: hi\nB: hey", "Kore", &[("A", "Kore"), ("B", "Puck")]);
+        let configs = &payload["generationConfig"]["speechConfig"]["multiSpeakerVoiceConfig"]["speakerVoiceConfigs"];
+        assert_eq!(configs[0]["speaker"], "A");
+        assert_eq!(
+            configs[1]["voiceConfig"]["prebuiltVoiceConfig"]["voiceName"],
+            "Puck"
+        );
+        assert!(payload["generationConfig"]["speechConfig"]["voiceConfig"].is_null());
+    }
+
+    #[test]
+    fn test_styled_prompt() {
+        assert_eq!(TtsClient::styled("Hi", None), "Hi");
+        assert_eq!(
+            TtsClient::styled("Hi", Some("Say cheerfully:")),
+            "Say cheerfully: Hi"
+        );
+        assert_eq!(TtsClient::styled("Hi", Some("  ")), "Hi");
+    }
+
+    #[test]
+    fn test_dialogue_voices() {
+        let speech = SpeechConfig::new().with_speaker_voice("Bob", "kore");
+        let voices = speech.dialogue_voices(&["Alice", "Bob", "Carol"]).unwrap();
+        // Kore is taken by Bob, so the defaults skip it
+        assert_eq!(voices, vec!["Puck", "Kore", "Charon"]);
+
+        let bad = SpeechConfig::new().with_speaker_voice("Bob", "Nobody");
+        assert!(matches!(
+            bad.dialogue_voices(&["Bob"]),
+            Err(TtsError::UnknownVoice(_))
+        ));
+        assert!(matches!(
+            SpeechConfig::new().with_voice("Nobody").single_voice(),
+            Err(TtsError::UnknownVoice(_))
+        ));
+    }
+
+    #[test]
+    fn test_chunk_dialogue() {
+        let lines = [
+            DialogueLine::new("A", "1"),
+            DialogueLine::new("B", "2"),
+            DialogueLine::new("A", "3"),
+            DialogueLine::new("C", "4"),
+            DialogueLine::new("A", "5"),
+        ];
+        let refs: Vec<&DialogueLine> = lines.iter().collect();
+        let chunks = TtsClient::chunk_dialogue(&refs);
+        let texts: Vec<Vec<&str>> = chunks
+            .iter()
+            .map(|c| c.iter().map(|l| l.text.as_str()).collect())
+            .collect();
+        assert_eq!(texts, vec![vec!["1", "2", "3"], vec!["4", "5"]]);
+    }
+
+    #[test]
+    fn test_parse_script() {
+        let script = "Alice: Hi there.\nBob: Hello!\nHow are you?\n\nAlice: Fine.";
+        let lines = parse_script(script).unwrap();
+        assert_eq!(
+            lines,
+            vec![
+                DialogueLine::new("Alice", "Hi there."),
+                DialogueLine::new("Bob", "Hello! How are you?"),
+                DialogueLine::new("Alice", "Fine."),
+            ]
+        );
+
+        assert!(parse_script("Just some text. Nothing else.").is_none());
+        assert!(parse_script("Note (important): read this").is_none());
+        assert!(parse_script("").is_none());
+    }
+
+    #[test]
+    fn test_speech_audio_wav() {
+        let audio = SpeechAudio::from_pcm("audio/L16;codec=pcm;rate=16000", vec![0; 32_000]);
+        assert_eq!(audio.sample_rate, 16_000);
+        assert_eq!(audio.duration_secs(), 1.0);
+
+        let wav = audio.to_wav();
+        assert_eq!(wav.len(), 44 + 32_000);
+        assert_eq!(&wav[0..4], b"RIFF");
+        assert_eq!(&wav[8..16], b"WAVEfmt ");
+        assert_eq!(u32::from_le_bytes(wav[24..28].try_into().unwrap()), 16_000);
+        assert_eq!(u32::from_le_bytes(wav[40..44].try_into().unwrap()), 32_000);
+
+        let default_rate = SpeechAudio::from_pcm("audio/L16", vec![]);
+        assert_eq!(default_rate.sample_rate, DEFAULT_SAMPLE_RATE);
+    }
+
+    #[test]
+    fn test_speech_audio_append() {
+        let mut audio = SpeechAudio::from_pcm("audio/L16;rate=24000", vec![1, 2]);
+        audio
+            .append(SpeechAudio::from_pcm("audio/L16;rate=24000", vec![3, 4]))
+            .unwrap();
+        assert_eq!(audio.pcm, vec![1, 2, 3, 4]);
+        assert!(
+            audio
+                .append(SpeechAudio::from_pcm("audio/L16;rate=16000", vec![5]))
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,732 @@
+//! Local ledger of API spend. Every successful API call appends one JSON line
+//! with its model, token counts, image count, video length and estimated cost, so spend can
+//! be reported per tool, model and project later (`flashecho usage`).
+//!
+//! Nothing is recorded until a CLI calls [`install`], so library users don't
+//! get a ledger they didn't ask for. Write failures are logged and ignored.
+//! Costs are estimated from list prices in [`PRICES`] and left out for models
+//! that aren't in the table.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+#[cfg(feature = "__retry")]
+use crate::budget::Budget;
+
+/// Overrides the ledger location
+pub const LEDGER_ENV: &str = "FLASHECHO_USAGE_LEDGER";
+
+/// One API call
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageRecord {
+    /// Unix time in seconds
+    pub time: u64,
+    /// Tool that made the call (`convert`, `imagen`, ...)
+    pub binary: String,
+    /// `--project` tag
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    /// Client that made the call (`embeddings`, `gemini`, `imagen`, `image_edit`, `music`, `openai`, `text`, `tts`, `veo`)
+    pub client: String,
+    pub model: String,
+    #[serde(default)]
+    pub input_tokens: u64,
+    /// Input tokens that were audio, billed at a higher rate by some models
+    #[serde(default)]
+    pub audio_tokens: u64,
+    #[serde(default)]
+    pub output_tokens: u64,
+    #[serde(default)]
+    pub images: u64,
+    /// Audio length, for models billed per minute (Whisper, Lyria)
+    #[serde(default)]
+    pub audio_seconds: f64,
+    /// Generated video length, for models billed per second (Veo)
+    #[serde(default)]
+    pub video_seconds: f64,
+    /// Estimated cost in USD, if the model's price is known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
+}
+
+/// List prices in USD used for cost estimates
+#[derive(Debug, Clone, Copy)]
+pub struct Price {
+    /// Matches model names starting with this
+    pub model_prefix: &'static str,
+    pub input_per_million: f64,
+    pub audio_input_per_million: f64,
+    /// Output tokens, including generated image and audio tokens
+    pub output_per_million: f64,
+    pub per_audio_minute: f64,
+    pub per_video_second: f64,
+}
+
+const fn tokens(prefix: &'static str, input: f64, audio: f64, output: f64) -> Price {
+    Price {
+        model_prefix: prefix,
+        input_per_million: input,
+        audio_input_per_million: audio,
+        output_per_million: output,
+        per_audio_minute: 0.0,
+        per_video_second: 0.0,
+    }
+}
+
+const fn per_minute(prefix: &'static str, price: f64) -> Price {
+    Price {
+        model_prefix: prefix,
+        input_per_million: 0.0,
+        audio_input_per_million: 0.0,
+        output_per_million: 0.0,
+        per_audio_minute: price,
+        per_video_second: 0.0,
+    }
+}
+
+const fn per_second(prefix: &'static str, price: f64) -> Price {
+    Price {
+        model_prefix: prefix,
+        input_per_million: 0.0,
+        audio_input_per_million: 0.0,
+        output_per_million: 0.0,
+        per_audio_minute: 0.0,
+        per_video_second: price,
+    }
+}
+
+/// Longer prefixes first, so `gemini-2.5-flash-image` isn't priced as
+/// `gemini-2.5-flash`
+pub const PRICES: &[Price] = &[
+    tokens("gemini-3-pro-image", 2.0, 2.0, 120.0),
+    tokens("gemini-2.5-flash-image", 0.30, 0.30, 30.0),
+    tokens("gemini-2.5-flash-preview-tts", 0.50, 0.50, 10.0),
+    tokens("gemini-2.5-flash-lite", 0.10, 0.30, 0.40),
+    tokens("gemini-2.5-flash", 0.30, 1.00, 2.50),
+    tokens("gemini-2.5-pro-preview-tts", 1.0, 1.0, 20.0),
+    tokens("gemini-2.5-pro", 1.25, 1.25, 10.0),
+    tokens("gemini-embedding-001", 0.15, 0.15, 0.0),
+    tokens("text-embedding-004", 0.0, 0.0, 0.0),
+    per_minute("gpt-4o-mini-transcribe", 0.003),
+    per_minute("gpt-4o-transcribe", 0.006),
+    per_minute("whisper-1", 0.006),
+    per_minute("lyria-002", 0.12),
+    per_second("veo-3.0-fast-generate", 0.15),
+    per_second("veo-3.0-generate", 0.40),
+    per_second("veo-2.0-generate", 0.35),
+];
+
+impl UsageRecord {
+    /// Cost from [`PRICES`], `None` for unknown models
+    pub fn estimate_cost(&self) -> Option<f64> {
+        let price = PRICES
+            .iter()
+            .find(|p| self.model.starts_with(p.model_prefix))?;
+        let text_tokens = self.input_tokens.saturating_sub(self.audio_tokens);
+        Some(
+            (text_tokens as f64 * price.input_per_million
+                + self.audio_tokens as f64 * price.audio_input_per_million
+                + self.output_tokens as f64 * price.output_per_million)
+                / 1_000_000.0
+                + self.audio_seconds / 60.0 * price.per_audio_minute
+                + self.video_seconds * price.per_video_second,
+        )
+    }
+}
+
+/// Token counts a Gemini response reports in its `usageMetadata`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    /// Prompt tokens, media included
+    pub input_tokens: u64,
+    /// Prompt tokens that were audio
+    pub audio_tokens: u64,
+    /// Answer tokens, including generated image and audio tokens
+    pub output_tokens: u64,
+}
+
+impl TokenUsage {
+    /// The counts of a `generateContent` response; zero where it reports none
+    pub fn from_response(response: &serde_json::Value) -> Self {
+        let usage = &response["usageMetadata"];
+        let audio_tokens = usage["promptTokensDetails"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|detail| detail["modality"] == "AUDIO")
+            .filter_map(|detail| detail["tokenCount"].as_u64())
+            .sum();
+        Self {
+            input_tokens: usage["promptTokenCount"].as_u64().unwrap_or_default(),
+            audio_tokens,
+            output_tokens: usage["candidatesTokenCount"].as_u64().unwrap_or_default(),
+        }
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens
+    }
+
+    /// Cost at `model`'s list price, `None` for unknown models
+    pub fn estimate_cost(&self, model: &str) -> Option<f64> {
+        UsageRecord {
+            model: model.to_string(),
+            input_tokens: self.input_tokens,
+            audio_tokens: self.audio_tokens,
+            output_tokens: self.output_tokens,
+            ..Default::default()
+        }
+        .estimate_cost()
+    }
+}
+
+impl std::ops::Add for TokenUsage {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            input_tokens: self.input_tokens + other.input_tokens,
+            audio_tokens: self.audio_tokens + other.audio_tokens,
+            output_tokens: self.output_tokens + other.output_tokens,
+        }
+    }
+}
+
+impl std::ops::AddAssign for TokenUsage {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+/// A client result with the tokens it took. Answers served from the
+/// response cache took none.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WithUsage<T> {
+    pub value: T,
+    pub usage: TokenUsage,
+}
+
+impl<T> WithUsage<T> {
+    pub fn new(value: T, usage: TokenUsage) -> Self {
+        Self { value, usage }
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> WithUsage<U> {
+        WithUsage {
+            value: f(self.value),
+            usage: self.usage,
+        }
+    }
+}
+
+/// Audio tokens Gemini bills per second of audio
+pub const AUDIO_TOKENS_PER_SECOND: f64 = 32.0;
+
+/// Rough transcript JSON tokens per second of speech, for estimates made
+/// before anything is sent
+pub const TRANSCRIPT_TOKENS_PER_SECOND: f64 = 8.0;
+
+/// Expected cost of transcribing `seconds` of audio with `model`: audio
+/// tokens in, a typical transcript out (or minutes for per-minute models).
+/// `None` for unknown models.
+pub fn estimate_transcription_cost(model: &str, seconds: f64) -> Option<f64> {
+    let audio_tokens = (seconds * AUDIO_TOKENS_PER_SECOND).ceil() as u64;
+    UsageRecord {
+        model: model.to_string(),
+        input_tokens: audio_tokens,
+        audio_tokens,
+        output_tokens: (seconds * TRANSCRIPT_TOKENS_PER_SECOND).ceil() as u64,
+        audio_seconds: seconds,
+        ..Default::default()
+    }
+    .estimate_cost()
+}
+
+/// Append-only JSONL file of [`UsageRecord`]s
+#[derive(Debug)]
+pub struct UsageLedger {
+    path: PathBuf,
+    binary: String,
+    project: Option<String>,
+    /// Serializes appends from concurrent tasks
+    lock: Mutex<()>,
+}
+
+impl UsageLedger {
+    /// Ledger at `path`; records are attributed to `binary`
+    pub fn new(path: impl Into<PathBuf>, binary: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            binary: binary.into(),
+            project: None,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Tag every record with `project`
+    pub fn with_project(mut self, project: Option<String>) -> Self {
+        self.project = project;
+        self
+    }
+
+    /// `$FLASHECHO_USAGE_LEDGER`, else `$XDG_DATA_HOME/flashecho/usage.jsonl`,
+    /// else `~/.local/share/flashecho/usage.jsonl`
+    pub fn default_path() -> PathBuf {
+        if let Some(path) = std::env::var_os(LEDGER_ENV) {
+            return PathBuf::from(path);
+        }
+        let base = std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| Path::new(&home).join(".local").join("share"))
+            })
+            .unwrap_or_else(std::env::temp_dir);
+        base.join("flashecho").join("usage.jsonl")
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Fill in time, binary, project and cost, then append `record`
+    pub fn append(&self, mut record: UsageRecord) -> std::io::Result<()> {
+        record.time = unix_time();
+        record.binary = self.binary.clone();
+        record.project = self.project.clone();
+        record.cost_usd = record.estimate_cost();
+
+        let mut line = serde_json::to_vec(&record)?;
+        line.push(b'\n');
+        let _guard = self.lock.lock().unwrap();
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(&line)
+    }
+
+    /// Every record in the file at `path`; unparsable lines are skipped
+    pub fn read(path: &Path) -> std::io::Result<Vec<UsageRecord>> {
+        let file = match std::fs::File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut records = Vec::new();
+        for line in std::io::BufReader::new(file).lines() {
+            match serde_json::from_str(&line?) {
+                Ok(record) => records.push(record),
+                Err(e) => tracing::warn!("Skipping bad usage ledger line: {}", e),
+            }
+        }
+        Ok(records)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn unix_time() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+// std::time::SystemTime::now panics on wasm32, which has no filesystem anyway
+#[cfg(target_arch = "wasm32")]
+fn unix_time() -> u64 {
+    0
+}
+
+static LEDGER: OnceLock<UsageLedger> = OnceLock::new();
+
+/// Record every later API call of this process in `ledger`. Only the first
+/// call has an effect.
+pub fn install(ledger: UsageLedger) {
+    let _ = LEDGER.set(ledger);
+}
+
+/// Charge `record` to `budget` and append it to the installed ledger
+#[cfg(feature = "__retry")]
+fn record(record: UsageRecord, budget: Option<&Budget>) {
+    if let Some(budget) = budget {
+        budget.charge(&record);
+    }
+    if let Some(ledger) = LEDGER.get()
+        && let Err(e) = ledger.append(record)
+    {
+        tracing::warn!("Failed to write usage ledger {:?}: {}", ledger.path(), e);
+    }
+}
+
+/// A successful Gemini `generateContent` response from `client`
+#[cfg(any(
+    feature = "transcription",
+    feature = "imagen",
+    feature = "tts",
+    feature = "text"
+))]
+pub(crate) fn record_gemini(
+    client: &str,
+    model: &str,
+    response: &serde_json::Value,
+    budget: Option<&Budget>,
+) {
+    if LEDGER.get().is_none() && budget.is_none() {
+        return;
+    }
+    let usage = TokenUsage::from_response(response);
+    let images = response["candidates"][0]["content"]["parts"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|part| part.get("inlineData").is_some() || part.get("inline_data").is_some())
+        .count() as u64;
+
+    record(
+        UsageRecord {
+            client: client.to_string(),
+            model: model.to_string(),
+            input_tokens: usage.input_tokens,
+            audio_tokens: usage.audio_tokens,
+            output_tokens: usage.output_tokens,
+            images,
+            ..Default::default()
+        },
+        budget,
+    );
+}
+
+/// A successful OpenAI transcription of `audio_seconds` of audio
+#[cfg(feature = "transcription")]
+pub(crate) fn record_openai(model: &str, audio_seconds: f64, budget: Option<&Budget>) {
+    if LEDGER.get().is_none() && budget.is_none() {
+        return;
+    }
+    record(
+        UsageRecord {
+            client: "openai".to_string(),
+            model: model.to_string(),
+            audio_seconds,
+            ..Default::default()
+        },
+        budget,
+    );
+}
+
+/// A successful embedding request over `input_chars` characters of text.
+/// The endpoint reports no token counts, so input tokens are estimated at
+/// four characters each.
+#[cfg(feature = "embeddings")]
+pub(crate) fn record_embeddings(model: &str, input_chars: usize, budget: Option<&Budget>) {
+    if LEDGER.get().is_none() && budget.is_none() {
+        return;
+    }
+    record(
+        UsageRecord {
+            client: "embeddings".to_string(),
+            model: model.to_string(),
+            input_tokens: input_chars.div_ceil(4) as u64,
+            ..Default::default()
+        },
+        budget,
+    );
+}
+
+/// A successful Lyria generation of an `audio_seconds` long clip
+#[cfg(feature = "music")]
+pub(crate) fn record_music(model: &str, audio_seconds: f64, budget: Option<&Budget>) {
+    if LEDGER.get().is_none() && budget.is_none() {
+        return;
+    }
+    record(
+        UsageRecord {
+            client: "music".to_string(),
+            model: model.to_string(),
+            audio_seconds,
+            ..Default::default()
+        },
+        budget,
+    );
+}
+
+/// A successful Veo generation of `video_seconds` of video
+#[cfg(feature = "veo")]
+pub(crate) fn record_veo(model: &str, video_seconds: f64, budget: Option<&Budget>) {
+    if LEDGER.get().is_none() && budget.is_none() {
+        return;
+    }
+    record(
+        UsageRecord {
+            client: "veo".to_string(),
+            model: model.to_string(),
+            video_seconds,
+            ..Default::default()
+        },
+        budget,
+    );
+}
+
+/// Totals of one (binary, model, project) group
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct UsageSummary {
+    pub binary: String,
+    pub model: String,
+    pub project: Option<String>,
+    pub calls: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub images: u64,
+    pub audio_seconds: f64,
+    pub video_seconds: f64,
+    /// Sum of the known estimates
+    pub cost_usd: f64,
+    /// Calls whose cost could not be estimated
+    pub unpriced_calls: u64,
+}
+
+/// Group `records` at or after `since` (unix seconds) by binary, model and
+/// project, sorted by those keys
+pub fn summarize(records: &[UsageRecord], since: u64) -> Vec<UsageSummary> {
+    let mut groups: BTreeMap<(&str, &str, Option<&str>), UsageSummary> = BTreeMap::new();
+    for record in records.iter().filter(|r| r.time >= since) {
+        let key = (
+            record.binary.as_str(),
+            record.model.as_str(),
+            record.project.as_deref(),
+        );
+        let summary = groups.entry(key).or_insert_with(|| UsageSummary {
+            binary: record.binary.clone(),
+            model: record.model.clone(),
+            project: record.project.clone(),
+            ..Default::default()
+        });
+        summary.calls += 1;
+        summary.input_tokens += record.input_tokens;
+        summary.output_tokens += record.output_tokens;
+        summary.images += record.images;
+        summary.audio_seconds += record.audio_seconds;
+        summary.video_seconds += record.video_seconds;
+        match record.cost_usd {
+            Some(cost) => summary.cost_usd += cost,
+            None => summary.unpriced_calls += 1,
+        }
+    }
+    groups.into_values().collect()
+}
+
+/// Unix time of midnight UTC at the start of `date` (`YYYY-MM-DD`, or
+/// `YYYY-MM` for the first of the month)
+pub fn parse_date(date: &str) -> Option<u64> {
+    let mut parts = date.splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: i64 = parts.next()?.parse().ok()?;
+    let day: i64 = match parts.next() {
+        Some(day) => day.parse().ok()?,
+        None => 1,
+    };
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // Days from civil date, Howard Hinnant's algorithm
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+    u64::try_from(days * 86400).ok()
+}
+
+/// `YYYY-MM-DD` (UTC) of a unix time; the inverse of [`parse_date`]
+pub fn format_date(secs: u64) -> String {
+    // Civil date from days, Howard Hinnant's algorithm
+    let days = (secs / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(binary: &str, model: &str, time: u64, cost: Option<f64>) -> UsageRecord {
+        UsageRecord {
+            time,
+            binary: binary.to_string(),
+            model: model.to_string(),
+            output_tokens: 10,
+            cost_usd: cost,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_estimate_cost() {
+        let image = UsageRecord {
+            model: "gemini-2.5-flash-image".to_string(),
+            input_tokens: 1_000,
+            output_tokens: 1_290,
+            images: 1,
+            ..Default::default()
+        };
+        let cost = image.estimate_cost().unwrap();
+        assert!((cost - (0.0003 + 0.0387)).abs() < 1e-9);
+
+        let audio = UsageRecord {
+            model: "gemini-2.5-flash".to_string(),
+            input_tokens: 1_000_000,
+            audio_tokens: 1_000_000,
+            ..Default::default()
+        };
+        assert!((audio.estimate_cost().unwrap() - 1.0).abs() < 1e-9);
+
+        let whisper = UsageRecord {
+            model: "whisper-1".to_string(),
+            audio_seconds: 90.0,
+            ..Default::default()
+        };
+        assert!((whisper.estimate_cost().unwrap() - 0.009).abs() < 1e-9);
+
+        // An hour: 115,200 audio tokens and 28,800 output tokens
+        let hour = estimate_transcription_cost("gemini-2.5-flash", 3600.0).unwrap();
+        assert!((hour - (0.1152 + 0.072)).abs() < 1e-9);
+        let hour = estimate_transcription_cost("whisper-1", 3600.0).unwrap();
+        assert!((hour - 0.36).abs() < 1e-9);
+        assert_eq!(estimate_transcription_cost("unknown-model", 60.0), None);
+
+        let video = UsageRecord {
+            model: "veo-3.0-fast-generate-001".to_string(),
+            video_seconds: 8.0,
+            ..Default::default()
+        };
+        assert!((video.estimate_cost().unwrap() - 1.2).abs() < 1e-9);
+
+        let speech = UsageRecord {
+            model: "gemini-2.5-flash-preview-tts".to_string(),
+            input_tokens: 1_000,
+            output_tokens: 100_000,
+            ..Default::default()
+        };
+        assert!((speech.estimate_cost().unwrap() - (0.0005 + 1.0)).abs() < 1e-9);
+
+        let music = UsageRecord {
+            model: "lyria-002".to_string(),
+            audio_seconds: 30.0,
+            ..Default::default()
+        };
+        assert!((music.estimate_cost().unwrap() - 0.06).abs() < 1e-9);
+
+        let embedding = UsageRecord {
+            model: "gemini-embedding-001".to_string(),
+            input_tokens: 1_000_000,
+            ..Default::default()
+        };
+        assert!((embedding.estimate_cost().unwrap() - 0.15).abs() < 1e-9);
+
+        let unknown = UsageRecord {
+            model: "some-future-model".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(unknown.estimate_cost(), None);
+    }
+
+    #[test]
+    fn test_token_usage() {
+        let response = serde_json::json!({
+            "usageMetadata": {
+                "promptTokenCount": 3300,
+                "candidatesTokenCount": 700,
+                "promptTokensDetails": [
+                    {"modality": "TEXT", "tokenCount": 100},
+                    {"modality": "AUDIO", "tokenCount": 3200}
+                ]
+            }
+        });
+        let usage = TokenUsage::from_response(&response);
+        assert_eq!(usage.audio_tokens, 3200);
+        assert_eq!(usage.total_tokens(), 4000);
+        // 100 text tokens at $0.30, 3200 audio at $1.00, 700 out at $2.50 per million
+        let cost = usage.estimate_cost("gemini-2.5-flash").unwrap();
+        assert!((cost - 0.00498).abs() < 1e-9);
+        assert_eq!((usage + usage).output_tokens, 1400);
+        assert_eq!(
+            TokenUsage::from_response(&serde_json::json!({})),
+            TokenUsage::default()
+        );
+        assert_eq!(usage.estimate_cost("unknown-model"), None);
+    }
+
+    #[test]
+    fn test_summarize_groups_and_filters() {
+        let records = vec![
+            record("imagen", "gemini-3-pro-image-preview", 100, Some(0.1)),
+            record("imagen", "gemini-3-pro-image-preview", 200, Some(0.2)),
+            record("convert", "gemini-2.5-flash", 200, None),
+            record("convert", "gemini-2.5-flash", 50, Some(1.0)),
+        ];
+
+        let summary = summarize(&records, 100);
+        assert_eq!(summary.len(), 2);
+        assert_eq!(summary[0].binary, "convert");
+        assert_eq!(summary[0].calls, 1);
+        assert_eq!(summary[0].unpriced_calls, 1);
+        assert_eq!(summary[1].calls, 2);
+        assert_eq!(summary[1].output_tokens, 20);
+        assert!((summary[1].cost_usd - 0.3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("1970-01-01"), Some(0));
+        assert_eq!(parse_date("2024-01-01"), Some(1_704_067_200));
+        assert_eq!(parse_date("2024-03-01"), Some(1_709_251_200));
+        assert_eq!(parse_date("2024-03"), Some(1_709_251_200));
+        assert_eq!(parse_date("2024-13-01"), None);
+        assert_eq!(parse_date("yesterday"), None);
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(1_709_251_200 + 86399), "2024-03-01");
+        assert_eq!(format_date(1_709_164_800), "2024-02-29");
+    }
+
+    #[test]
+    fn test_append_and_read() {
+        let path = std::env::temp_dir().join(format!(
+            "flashecho_usage_test_{}/usage.jsonl",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let ledger = UsageLedger::new(&path, "imagen").with_project(Some("blog".to_string()));
+        ledger
+            .append(UsageRecord {
+                client: "imagen".to_string(),
+                model: "gemini-2.5-flash-image".to_string(),
+                output_tokens: 1_290,
+                images: 1,
+                ..Default::default()
+            })
+            .unwrap();
+
+        let records = UsageLedger::read(&path).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].binary, "imagen");
+        assert_eq!(records[0].project.as_deref(), Some("blog"));
+        assert!(records[0].time > 0);
+        assert!(records[0].cost_usd.is_some());
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+}
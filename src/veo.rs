@@ -0,0 +1,755 @@
+use anyhow::{Context, Result};
+use clap::Parser;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Semaphore;
+use tracing::{Instrument, debug, info_span};
+
+use transcript_tool::cli::{self, BatchLimitArgs, CommonArgs, init_logging};
+use transcript_tool::storage::{OutputStorage, Storage};
+use transcript_tool::{
+    Budget, CircuitBreaker, Clients, GeneratedVideo, Resolution, VeoClient, VeoClientConfig,
+    VideoAspectRatio, VideoGenConfig, VideoModel,
+};
+
+#[derive(Parser, Debug)]
+#[command(name = "veo")]
+#[command(version)]
+#[command(about = "Generate videos using the Veo models of the Gemini API")]
+#[command(after_help = "EXAMPLES:
+    veo \"A drone shot over a misty forest at dawn\"
+    veo -m 3-fast --aspect 9:16 \"A cat chasing a laser pointer\"
+    veo --resolution 1080p --duration 6 \"Waves crashing on a rocky shore\"
+    veo --negative-prompt \"text, watermark\" \"A city street in the rain\"
+    veo --yaml videos.yaml
+    veo --yaml videos.yaml --name intro -o s3://my-bucket/videos/
+    veo \"A campfire\" -o - | some-player")]
+pub struct Args {
+    /// Text prompt for video generation (positional argument)
+    #[arg(value_name = "PROMPT")]
+    prompt: Option<String>,
+
+    /// YAML file containing prompts
+    #[arg(short = 'y', long)]
+    yaml: Option<PathBuf>,
+
+    /// Generate only specific prompt by name from YAML
+    #[arg(short = 'n', long)]
+    name: Option<String>,
+
+    /// Output file path (for single prompt, `-` for stdout) or directory (for YAML batch);
+    /// s3:// and gs:// URIs write to object storage
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Veo model to use: 3 (default), 3-fast, 2
+    #[arg(short = 'm', long, env = "FLASHECHO_VIDEO_MODEL")]
+    model: Option<String>,
+
+    /// Aspect ratio: 16:9 (default), 9:16
+    #[arg(short = 'a', long)]
+    aspect: Option<String>,
+
+    /// Resolution (Veo 3 only): 720p, 1080p
+    #[arg(short = 'r', long)]
+    resolution: Option<String>,
+
+    /// Video length in seconds (4-8)
+    #[arg(short = 'd', long)]
+    duration: Option<u32>,
+
+    /// What the video should not contain
+    #[arg(long)]
+    negative_prompt: Option<String>,
+
+    /// Timeout of each API request in seconds
+    #[arg(short, long, default_value = "120")]
+    timeout: u64,
+
+    /// Give up on a video that isn't ready after this many seconds
+    #[arg(long, default_value = "600")]
+    max_wait: u64,
+
+    /// Seconds between checks on a rendering video
+    #[arg(long, default_value = "10")]
+    poll_interval: u64,
+
+    /// Number of parallel jobs for YAML batch mode
+    #[arg(short = 'j', long, default_value = "2")]
+    jobs: usize,
+
+    /// Print results as a JSON array to stdout (for scripts and CI)
+    #[arg(long)]
+    json: bool,
+
+    #[command(flatten)]
+    common: CommonArgs,
+
+    #[command(flatten)]
+    limits: BatchLimitArgs,
+}
+
+/// YAML file structure for batch prompts
+#[derive(Debug, Deserialize)]
+struct PromptsFile {
+    prompts: Vec<PromptEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PromptEntry {
+    name: String,
+    prompt: String,
+    output: Option<String>,
+    /// Model override for this prompt
+    model: Option<String>,
+    /// Aspect ratio: 16:9 or 9:16
+    aspect: Option<String>,
+    /// Resolution (Veo 3 only)
+    resolution: Option<String>,
+    /// Length in seconds
+    duration: Option<u32>,
+    negative_prompt: Option<String>,
+}
+
+/// Per-request settings, from flags or a YAML entry
+#[derive(Debug, Clone, Default)]
+struct GenSettings {
+    aspect: Option<String>,
+    resolution: Option<String>,
+    duration: Option<u32>,
+    negative_prompt: Option<String>,
+}
+
+impl GenSettings {
+    /// `self` with unset fields taken from `defaults`
+    fn or(self, defaults: &GenSettings) -> GenSettings {
+        GenSettings {
+            aspect: self.aspect.or_else(|| defaults.aspect.clone()),
+            resolution: self.resolution.or_else(|| defaults.resolution.clone()),
+            duration: self.duration.or(defaults.duration),
+            negative_prompt: self
+                .negative_prompt
+                .or_else(|| defaults.negative_prompt.clone()),
+        }
+    }
+}
+
+fn parse_model(model_str: &str) -> Result<VideoModel> {
+    VideoModel::from_str(model_str).map_err(|e| anyhow::anyhow!("{}", e))
+}
+
+fn parse_aspect(aspect_str: &str) -> Result<VideoAspectRatio> {
+    VideoAspectRatio::from_str(aspect_str).map_err(|e| anyhow::anyhow!("{}", e))
+}
+
+fn parse_resolution(resolution_str: &str) -> Result<Resolution> {
+    Resolution::from_str(resolution_str).map_err(|e| anyhow::anyhow!("{}", e))
+}
+
+fn build_gen_config(settings: &GenSettings) -> Result<Option<VideoGenConfig>> {
+    if settings.aspect.is_none()
+        && settings.resolution.is_none()
+        && settings.duration.is_none()
+        && settings.negative_prompt.is_none()
+    {
+        return Ok(None);
+    }
+
+    let mut config = VideoGenConfig::new();
+
+    if let Some(a) = &settings.aspect {
+        config.aspect_ratio = Some(parse_aspect(a)?);
+    }
+    if let Some(r) = &settings.resolution {
+        config.resolution = Some(parse_resolution(r)?);
+    }
+    config.duration_secs = settings.duration;
+    config.negative_prompt = settings.negative_prompt.clone();
+
+    Ok(Some(config))
+}
+
+/// Convert a string to a URL-friendly slug
+fn slugify(s: &str) -> String {
+    let slug: String = s
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect::<String>()
+        .split('-')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+
+    // If slug is empty (e.g., emoji-only names), use "video"
+    if slug.is_empty() {
+        "video".to_string()
+    } else {
+        slug
+    }
+}
+
+/// Generate output filename: slug(name)-hash(name+prompt).mp4
+fn generate_output_filename(name: &str, prompt: &str) -> String {
+    let slug = slugify(name);
+    let hash_input = format!("{}{}", name, prompt);
+    let hash = blake3::hash(hash_input.as_bytes());
+    let hash_prefix = &hash.to_hex()[..6];
+    format!("{}-{}.mp4", slug, hash_prefix)
+}
+
+/// Write the video to `key`, returning its path or URI
+async fn save_video(video: &GeneratedVideo, storage: &OutputStorage, key: &str) -> Result<String> {
+    storage
+        .put(key, &video.data)
+        .await
+        .context("Failed to write video file")?;
+    Ok(storage.location(key))
+}
+
+/// Check whether an output path means "write to stdout"
+fn is_stdout_path(path: &str) -> bool {
+    path == "-"
+}
+
+/// Write raw video bytes to stdout (for piping into other tools)
+async fn write_video_to_stdout(video: &GeneratedVideo) -> Result<()> {
+    let mut stdout = tokio::io::stdout();
+    stdout
+        .write_all(&video.data)
+        .await
+        .context("Failed to write video to stdout")?;
+    stdout.flush().await.context("Failed to flush stdout")?;
+    Ok(())
+}
+
+/// Where a single generated video goes
+enum SingleOutput {
+    Stdout,
+    Storage(OutputStorage, String),
+}
+
+async fn generate_single(
+    client: &VeoClient,
+    prompt: &str,
+    output: SingleOutput,
+    gen_config: Option<&VideoGenConfig>,
+    quiet: bool,
+) -> Result<String> {
+    let pb = cli::spinner(quiet, "Generating video...".into());
+
+    let video = client
+        .generate_video_with_config(prompt, gen_config)
+        .await
+        .map_err(|e| anyhow::anyhow!("Video generation failed: {}", e))?;
+
+    if let Some(pb) = pb {
+        pb.finish_with_message("Video generated!");
+    }
+
+    let (storage, key) = match output {
+        SingleOutput::Stdout => {
+            write_video_to_stdout(&video).await?;
+            return Ok("-".to_string());
+        }
+        SingleOutput::Storage(storage, key) => (storage, key),
+    };
+
+    // Determine final output path with correct extension
+    let key = if Path::new(&key).extension().is_none() {
+        format!("{}.{}", key, video.extension())
+    } else {
+        key
+    };
+
+    save_video(&video, &storage, &key).await
+}
+
+struct YamlGenOptions {
+    clients: Clients,
+    yaml_path: PathBuf,
+    name_filter: Option<String>,
+    output_dir: OutputStorage,
+    default_model: VideoModel,
+    defaults: GenSettings,
+    client_config: VeoClientConfig,
+    circuit_breaker: Option<CircuitBreaker>,
+    budget: Option<Budget>,
+    jobs: usize,
+    quiet: bool,
+    json: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum GenStatus {
+    Success,
+    Skipped,
+    Failed,
+}
+
+/// Result of a single video generation task
+#[derive(Debug, Serialize)]
+struct GenResult {
+    name: String,
+    prompt: String,
+    output_path: Option<String>,
+    status: GenStatus,
+    error: Option<String>,
+}
+
+impl GenResult {
+    fn success(name: &str, prompt: &str, output_path: String) -> Self {
+        Self {
+            name: name.to_string(),
+            prompt: prompt.to_string(),
+            output_path: Some(output_path),
+            status: GenStatus::Success,
+            error: None,
+        }
+    }
+
+    fn skipped(name: &str, prompt: &str, output_path: String) -> Self {
+        Self {
+            status: GenStatus::Skipped,
+            ..Self::success(name, prompt, output_path)
+        }
+    }
+
+    fn failed(name: &str, prompt: &str, error: String) -> Self {
+        Self {
+            name: name.to_string(),
+            prompt: prompt.to_string(),
+            output_path: None,
+            status: GenStatus::Failed,
+            error: Some(error),
+        }
+    }
+}
+
+/// Print generation results as a JSON array to stdout
+fn print_json_results(results: &[GenResult]) -> Result<()> {
+    let json = serde_json::to_string_pretty(results).context("Failed to serialize results")?;
+    println!("{}", json);
+    Ok(())
+}
+
+/// Generate one YAML entry, skipping it when its output already exists
+async fn generate_entry(entry: &PromptEntry, opts: &YamlGenOptions) -> Result<GenResult> {
+    let filename = entry
+        .output
+        .clone()
+        .unwrap_or_else(|| generate_output_filename(&entry.name, &entry.prompt));
+    if opts
+        .output_dir
+        .exists(&filename)
+        .await
+        .context("Failed to check for an existing video")?
+    {
+        let location = opts.output_dir.location(&filename);
+        return Ok(GenResult::skipped(&entry.name, &entry.prompt, location));
+    }
+
+    let model = match &entry.model {
+        Some(model_str) => parse_model(model_str)?,
+        None => opts.default_model,
+    };
+    let settings = GenSettings {
+        aspect: entry.aspect.clone(),
+        resolution: entry.resolution.clone(),
+        duration: entry.duration,
+        negative_prompt: entry.negative_prompt.clone(),
+    }
+    .or(&opts.defaults);
+    let gen_config = build_gen_config(&settings)?;
+
+    // Build client on the shared connection pool
+    let client = opts.clients.veo(VeoClientConfig {
+        model,
+        circuit_breaker: opts.circuit_breaker.clone(),
+        budget: opts.budget.clone(),
+        ..opts.client_config.clone()
+    });
+    let video = client
+        .generate_video_with_config(&entry.prompt, gen_config.as_ref())
+        .await?;
+    let output_path = save_video(&video, &opts.output_dir, &filename).await?;
+    Ok(GenResult::success(&entry.name, &entry.prompt, output_path))
+}
+
+async fn generate_from_yaml(opts: YamlGenOptions) -> Result<()> {
+    let yaml_content = fs::read_to_string(&opts.yaml_path)
+        .await
+        .context("Failed to read YAML file")?;
+
+    let prompts_file: PromptsFile =
+        serde_yaml::from_str(&yaml_content).context("Failed to parse YAML file")?;
+
+    let prompts: Vec<PromptEntry> = if let Some(ref name) = opts.name_filter {
+        prompts_file
+            .prompts
+            .into_iter()
+            .filter(|p| &p.name == name)
+            .collect()
+    } else {
+        prompts_file.prompts
+    };
+
+    if prompts.is_empty() {
+        if let Some(name) = opts.name_filter {
+            anyhow::bail!("No prompt found with name: {}", name);
+        } else {
+            anyhow::bail!("No prompts found in YAML file");
+        }
+    }
+
+    let total = prompts.len();
+    let jobs = opts.jobs.max(1);
+
+    if !opts.quiet && !opts.json {
+        println!(
+            "Generating {} videos with {} parallel jobs...\n",
+            total, jobs
+        );
+    }
+
+    // Create semaphore for concurrency control
+    let semaphore = Arc::new(Semaphore::new(jobs));
+    let opts = Arc::new(opts);
+
+    // Create multi-progress bar for parallel display
+    let multi_progress = Arc::new(MultiProgress::new());
+
+    // Spawn all tasks
+    let mut handles = Vec::new();
+
+    for (i, entry) in prompts.into_iter().enumerate() {
+        let sem = Arc::clone(&semaphore);
+        let opts = Arc::clone(&opts);
+        let mp = Arc::clone(&multi_progress);
+
+        let span = info_span!("entry", entry = %entry.name);
+        let handle = tokio::spawn(
+            async move {
+                // Acquire semaphore permit
+                let _permit = sem.acquire().await.unwrap();
+
+                // Create progress bar for this task
+                let pb = if !opts.quiet {
+                    let pb = mp.add(ProgressBar::new_spinner());
+                    pb.set_style(
+                        ProgressStyle::default_spinner()
+                            .template("{spinner:.green} [{pos}] {msg} ({elapsed})")
+                            .unwrap(),
+                    );
+                    pb.set_position((i + 1) as u64);
+                    pb.set_message(format!("Generating {}...", entry.name));
+                    pb.enable_steady_tick(Duration::from_millis(100));
+                    Some(pb)
+                } else {
+                    None
+                };
+
+                let result = generate_entry(&entry, &opts).await.unwrap_or_else(|e| {
+                    GenResult::failed(&entry.name, &entry.prompt, e.to_string())
+                });
+
+                if let Some(pb) = pb {
+                    let message = match result.status {
+                        GenStatus::Success => format!(
+                            "{} -> {}",
+                            entry.name,
+                            result.output_path.as_deref().unwrap_or_default()
+                        ),
+                        GenStatus::Skipped => format!("{} skipped (already exists)", entry.name),
+                        GenStatus::Failed => format!("{} failed!", entry.name),
+                    };
+                    pb.finish_with_message(message);
+                }
+                result
+            }
+            .instrument(span),
+        );
+
+        handles.push(handle);
+    }
+
+    // Wait for all tasks to complete
+    let results: Vec<GenResult> = futures::future::join_all(handles)
+        .await
+        .into_iter()
+        .filter_map(|r| r.ok())
+        .collect();
+
+    // Collect results
+    let count = |status| results.iter().filter(|r| r.status == status).count();
+    let success_count = count(GenStatus::Success);
+    let skipped_count = count(GenStatus::Skipped);
+    let errors: Vec<_> = results
+        .iter()
+        .filter(|r| r.status == GenStatus::Failed)
+        .map(|r| (r.name.clone(), r.error.clone().unwrap_or_default()))
+        .collect();
+
+    if opts.json {
+        print_json_results(&results)?;
+    }
+
+    // Summary
+    if !opts.quiet && !opts.json {
+        println!("\n--- Summary ---");
+        println!(
+            "Total: {}, Success: {}, Skipped: {}, Failed: {}",
+            total,
+            success_count,
+            skipped_count,
+            errors.len()
+        );
+        if !errors.is_empty() {
+            println!("\nFailed prompts:");
+            for (name, error) in &errors {
+                println!("  - {}: {}", name, error);
+            }
+        }
+    }
+
+    if success_count + skipped_count == 0 && !errors.is_empty() {
+        anyhow::bail!("All video generations failed");
+    }
+
+    Ok(())
+}
+
+/// Run with parsed arguments; shared by this binary and its `flashecho` subcommand
+pub async fn run(args: Args) -> Result<()> {
+    init_logging(args.common.verbose, args.common.log_format);
+    args.common.install_usage_ledger("veo");
+
+    // Validate arguments
+    if args.prompt.is_none() && args.yaml.is_none() {
+        anyhow::bail!(
+            "Either a prompt or --yaml file must be provided\n\nUsage:\n  veo \"your prompt here\"\n  veo --yaml videos.yaml"
+        );
+    }
+
+    if args.prompt.is_some() && args.yaml.is_some() {
+        anyhow::bail!("Cannot use both prompt and --yaml at the same time");
+    }
+
+    if args.name.is_some() && args.yaml.is_none() {
+        anyhow::bail!("--name can only be used with --yaml");
+    }
+
+    let to_stdout = args.output.as_deref().is_some_and(is_stdout_path);
+    if to_stdout && args.yaml.is_some() {
+        anyhow::bail!("-o - (stdout) can only be used with a single prompt, not --yaml");
+    }
+    if to_stdout && args.json {
+        anyhow::bail!("Cannot use --json when writing the video to stdout (-o -)");
+    }
+
+    let profile = args.common.load_profile()?;
+    let clients =
+        Clients::new(cli::gemini_api_key(&profile)?).context("Failed to create HTTP client")?;
+    let model_name = args.model.as_deref().or(profile.video_model.as_deref());
+    let model = parse_model(model_name.unwrap_or("3"))?;
+    let breaker = args.limits.circuit_breaker();
+    let budget = args
+        .common
+        .budget()
+        .context("Failed to read usage ledger for --budget-since")?;
+
+    if args.resolution.is_some() && !model.supports_resolution() {
+        eprintln!("Warning: --resolution is only supported with Veo 3 models (-m 3, -m 3-fast)");
+    }
+
+    let client_config = VeoClientConfig {
+        timeout_secs: args.timeout,
+        max_retries: args.common.max_retries,
+        model,
+        poll_interval_secs: args.poll_interval,
+        max_wait_secs: args.max_wait,
+        ..Default::default()
+    };
+    let defaults = GenSettings {
+        aspect: args.aspect,
+        resolution: args.resolution,
+        duration: args.duration,
+        negative_prompt: args.negative_prompt,
+    };
+
+    if let Some(yaml_path) = args.yaml {
+        // YAML batch mode
+        if !yaml_path.exists() {
+            anyhow::bail!("YAML file does not exist: {:?}", yaml_path);
+        }
+
+        let output_dir = args.output.as_deref().unwrap_or("./output");
+        debug!("Output directory: {:?}", output_dir);
+        let output_dir = OutputStorage::open(output_dir).context("Invalid output directory")?;
+
+        generate_from_yaml(YamlGenOptions {
+            clients,
+            yaml_path,
+            name_filter: args.name,
+            output_dir,
+            default_model: model,
+            defaults,
+            client_config,
+            circuit_breaker: breaker,
+            budget,
+            jobs: args.jobs,
+            quiet: args.common.quiet,
+            json: args.json,
+        })
+        .await?;
+    } else if let Some(prompt) = args.prompt {
+        // Single prompt mode
+        let client = clients.veo(VeoClientConfig {
+            budget,
+            ..client_config
+        });
+
+        let gen_config = build_gen_config(&defaults)?;
+        // Generate filename: video-hash(prompt).mp4
+        let default_name = generate_output_filename("video", &prompt);
+        let output_path = args.output.unwrap_or_else(|| default_name.clone());
+        debug!("Output path: {:?}", output_path);
+        let output = if to_stdout {
+            SingleOutput::Stdout
+        } else {
+            let (storage, key) = OutputStorage::open_file(&output_path, &default_name)
+                .context("Invalid output path")?;
+            SingleOutput::Storage(storage, key)
+        };
+
+        let result = generate_single(
+            &client,
+            &prompt,
+            output,
+            gen_config.as_ref(),
+            args.common.quiet || to_stdout,
+        )
+        .await;
+
+        if args.json {
+            let gen_result = match &result {
+                Ok(path) => GenResult::success("video", &prompt, path.clone()),
+                Err(e) => GenResult::failed("video", &prompt, e.to_string()),
+            };
+            print_json_results(&[gen_result])?;
+        }
+
+        result?;
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    run(Args::parse()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_yaml_parsing() {
+        let yaml = r#"
+prompts:
+  - name: intro
+    prompt: A drone shot over a forest
+    output: intro.mp4
+  - name: vertical
+    prompt: A cat chasing a laser
+    model: 3-fast
+    aspect: "9:16"
+    resolution: 1080p
+    duration: 6
+    negative_prompt: text overlays
+"#;
+        let parsed: PromptsFile = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(parsed.prompts.len(), 2);
+        assert_eq!(parsed.prompts[0].name, "intro");
+        assert_eq!(parsed.prompts[0].output, Some("intro.mp4".to_string()));
+        assert_eq!(parsed.prompts[0].model, None);
+        assert_eq!(parsed.prompts[1].model, Some("3-fast".to_string()));
+        assert_eq!(parsed.prompts[1].aspect, Some("9:16".to_string()));
+        assert_eq!(parsed.prompts[1].resolution, Some("1080p".to_string()));
+        assert_eq!(parsed.prompts[1].duration, Some(6));
+        assert_eq!(
+            parsed.prompts[1].negative_prompt,
+            Some("text overlays".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_model() {
+        assert!(parse_model("3").is_ok());
+        assert!(parse_model("3-fast").is_ok());
+        assert!(parse_model("2").is_ok());
+        assert!(parse_model("3pro").is_err());
+    }
+
+    #[test]
+    fn test_build_gen_config() {
+        assert!(build_gen_config(&GenSettings::default()).unwrap().is_none());
+
+        let entry = GenSettings {
+            aspect: Some("9:16".to_string()),
+            ..Default::default()
+        };
+        let defaults = GenSettings {
+            aspect: Some("16:9".to_string()),
+            resolution: Some("1080p".to_string()),
+            duration: Some(6),
+            negative_prompt: None,
+        };
+        let config = build_gen_config(&entry.or(&defaults)).unwrap().unwrap();
+        assert_eq!(config.aspect_ratio, Some(VideoAspectRatio::Tall));
+        assert_eq!(config.resolution, Some(Resolution::P1080));
+        assert_eq!(config.duration_secs, Some(6));
+        assert!(config.negative_prompt.is_none());
+
+        let invalid = GenSettings {
+            aspect: Some("1:1".to_string()),
+            ..Default::default()
+        };
+        assert!(build_gen_config(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_gen_result_json() {
+        let results = vec![
+            GenResult::success("intro", "A forest", "output/intro.mp4".to_string()),
+            GenResult::skipped("outro", "A sunset", "output/outro.mp4".to_string()),
+            GenResult::failed("broken", "A prompt", "Rate limited".to_string()),
+        ];
+        let json: serde_json::Value = serde_json::to_value(&results).unwrap();
+        assert_eq!(json[0]["status"], "success");
+        assert_eq!(json[0]["output_path"], "output/intro.mp4");
+        assert_eq!(json[1]["status"], "skipped");
+        assert_eq!(json[1]["output_path"], "output/outro.mp4");
+        assert_eq!(json[2]["status"], "failed");
+        assert!(json[2]["output_path"].is_null());
+        assert_eq!(json[2]["error"], "Rate limited");
+    }
+
+    #[test]
+    fn test_generate_output_filename() {
+        let filename = generate_output_filename("Intro Shot", "A forest");
+        assert!(filename.starts_with("intro-shot-"));
+        assert!(filename.ends_with(".mp4"));
+        assert_eq!(filename, generate_output_filename("Intro Shot", "A forest"));
+        assert_ne!(filename, generate_output_filename("Intro Shot", "A desert"));
+        assert!(generate_output_filename("🎬", "A film").starts_with("video-"));
+    }
+}
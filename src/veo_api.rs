@@ -0,0 +1,889 @@
+//! Veo video generation. A prompt is submitted with `predictLongRunning`,
+//! which returns an operation name; the operation is polled until it is
+//! done, then the generated MP4 is downloaded from the URI in its response.
+//!
+//! Responses aren't cached: videos are large, and a rerun of the CLI skips
+//! outputs that already exist instead.
+
+use base64::Engine;
+use reqwest::{Client, Method};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::fmt;
+use std::future::Future;
+use std::str::FromStr;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{Instrument, Span, debug, field, info, info_span, warn};
+
+use crate::auth::{AuthMode, GEMINI_BASE_URL};
+use crate::budget::{Budget, BudgetExceeded};
+use crate::circuit_breaker::{CircuitBreaker, CircuitOpen};
+use crate::error::ErrorKind;
+use crate::proxy::ProxyConfig;
+use crate::redact::ApiKey;
+use crate::rt::Stopwatch;
+use crate::tls::TlsConfig;
+use crate::transport::TransportConfig;
+
+const DEFAULT_TIMEOUT_SECS: u64 = 120;
+const DEFAULT_MAX_RETRIES: u32 = 3;
+const DEFAULT_POLL_INTERVAL_SECS: u64 = 10;
+const DEFAULT_MAX_WAIT_SECS: u64 = 600;
+/// Length of a video when `duration_secs` is unset
+pub const DEFAULT_VIDEO_SECONDS: u32 = 8;
+
+/// Supported video generation models
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VideoModel {
+    /// Veo 3 - video with generated audio
+    #[default]
+    Veo3,
+    /// Veo 3 Fast - cheaper and quicker, lower quality
+    Veo3Fast,
+    /// Veo 2 - silent video
+    Veo2,
+}
+
+impl VideoModel {
+    pub fn api_model_name(&self) -> &'static str {
+        match self {
+            VideoModel::Veo3 => "veo-3.0-generate-001",
+            VideoModel::Veo3Fast => "veo-3.0-fast-generate-001",
+            VideoModel::Veo2 => "veo-2.0-generate-001",
+        }
+    }
+
+    /// Check if this model accepts a resolution (720p/1080p)
+    pub fn supports_resolution(&self) -> bool {
+        !matches!(self, VideoModel::Veo2)
+    }
+}
+
+impl fmt::Display for VideoModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VideoModel::Veo3 => write!(f, "3"),
+            VideoModel::Veo3Fast => write!(f, "3-fast"),
+            VideoModel::Veo2 => write!(f, "2"),
+        }
+    }
+}
+
+impl FromStr for VideoModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "3" | "veo3" | "veo-3" | "veo-3.0-generate-001" => Ok(VideoModel::Veo3),
+            "3-fast" | "fast" | "veo3-fast" | "veo-3.0-fast-generate-001" => {
+                Ok(VideoModel::Veo3Fast)
+            }
+            "2" | "veo2" | "veo-2" | "veo-2.0-generate-001" => Ok(VideoModel::Veo2),
+            _ => Err(format!("Unknown model: {}. Use '3', '3-fast' or '2'", s)),
+        }
+    }
+}
+
+/// Video aspect ratio
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VideoAspectRatio {
+    #[default]
+    Wide, // 16:9
+    Tall, // 9:16
+}
+
+impl VideoAspectRatio {
+    pub fn api_value(&self) -> &'static str {
+        match self {
+            VideoAspectRatio::Wide => "16:9",
+            VideoAspectRatio::Tall => "9:16",
+        }
+    }
+}
+
+impl fmt::Display for VideoAspectRatio {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.api_value())
+    }
+}
+
+impl FromStr for VideoAspectRatio {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "16:9" | "wide" => Ok(VideoAspectRatio::Wide),
+            "9:16" | "tall" => Ok(VideoAspectRatio::Tall),
+            _ => Err(format!("Invalid aspect ratio: {}. Use 16:9 or 9:16", s)),
+        }
+    }
+}
+
+/// Video resolution (Veo 3 only)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Resolution {
+    #[default]
+    P720,
+    P1080,
+}
+
+impl Resolution {
+    pub fn api_value(&self) -> &'static str {
+        match self {
+            Resolution::P720 => "720p",
+            Resolution::P1080 => "1080p",
+        }
+    }
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.api_value())
+    }
+}
+
+impl FromStr for Resolution {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "720p" | "720" => Ok(Resolution::P720),
+            "1080p" | "1080" => Ok(Resolution::P1080),
+            _ => Err(format!("Invalid resolution: {}. Use 720p or 1080p", s)),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum VeoError {
+    #[error("API key not found in environment (set GEMINI_API_KEY or GOOGLE_AI_KEY)")]
+    MissingApiKey,
+
+    #[error("Invalid video config: {0}")]
+    InvalidConfig(String),
+
+    #[error("Gemini API error ({status}, request {request_id}): {message}")]
+    ApiError {
+        status: u16,
+        message: String,
+        request_id: String,
+    },
+
+    #[error("Rate limited by API (request {request_id}). Retry after some time.")]
+    RateLimited { request_id: String },
+
+    #[error("Video generation failed (request {request_id}): {message}")]
+    OperationFailed { message: String, request_id: String },
+
+    #[error("Video blocked by safety filters (request {request_id}): {reasons}")]
+    Filtered { reasons: String, request_id: String },
+
+    #[error("Video generation did not finish within {0} seconds")]
+    OperationTimeout(u64),
+
+    #[error("Invalid response from Gemini API: {0}")]
+    InvalidResponse(String),
+
+    #[error("No video in response")]
+    NoVideoData,
+
+    #[error("Network error: {0}")]
+    NetworkError(#[source] reqwest::Error),
+
+    #[error("JSON parsing error: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("Max retries ({0}) exceeded")]
+    MaxRetriesExceeded(u32),
+
+    #[error(transparent)]
+    CircuitOpen(#[from] CircuitOpen),
+
+    #[error(transparent)]
+    BudgetExceeded(#[from] BudgetExceeded),
+
+    #[error("Base64 decode error: {0}")]
+    Base64Error(#[from] base64::DecodeError),
+}
+
+pub type Result<T> = std::result::Result<T, VeoError>;
+
+impl From<reqwest::Error> for VeoError {
+    fn from(err: reqwest::Error) -> Self {
+        VeoError::NetworkError(crate::redact::reqwest_error(err))
+    }
+}
+
+impl VeoError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            VeoError::MissingApiKey | VeoError::InvalidConfig(_) => ErrorKind::Config,
+            VeoError::ApiError { .. }
+            | VeoError::OperationFailed { .. }
+            | VeoError::Filtered { .. }
+            | VeoError::OperationTimeout(_) => ErrorKind::Api,
+            VeoError::RateLimited { .. } => ErrorKind::RateLimited,
+            VeoError::InvalidResponse(_)
+            | VeoError::NoVideoData
+            | VeoError::JsonError(_)
+            | VeoError::Base64Error(_) => ErrorKind::InvalidResponse,
+            VeoError::NetworkError(_) => ErrorKind::Network,
+            VeoError::MaxRetriesExceeded(_) => ErrorKind::RetriesExhausted,
+            VeoError::CircuitOpen(_) => ErrorKind::CircuitOpen,
+            VeoError::BudgetExceeded(_) => ErrorKind::BudgetExceeded,
+        }
+    }
+
+    /// HTTP status returned by the API, if any
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            VeoError::ApiError { status, .. } => Some(*status),
+            VeoError::RateLimited { .. } => Some(429),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VeoClientConfig {
+    /// Timeout of each submit, poll and download request
+    pub timeout_secs: u64,
+    pub max_retries: u32,
+    pub model: VideoModel,
+    /// How often the operation is polled while the video renders
+    pub poll_interval_secs: u64,
+    /// Give up on an operation that isn't done after this long
+    pub max_wait_secs: u64,
+    /// API host, e.g. a local mock server in tests
+    pub base_url: String,
+    /// Used by `with_config`; `with_http_client` keeps the given client's proxy
+    pub proxy: ProxyConfig,
+    /// Extra root certificates and builder hook, applied like `proxy`
+    pub tls: TlsConfig,
+    /// Connection pool, keep-alive and HTTP/2 settings, applied like `proxy`
+    pub transport: TransportConfig,
+    pub auth_mode: AuthMode,
+    /// Shared with other clients of a batch to stop retrying when the API is down
+    pub circuit_breaker: Option<CircuitBreaker>,
+    /// Spend limit shared with other clients; requests fail once it is reached
+    pub budget: Option<Budget>,
+}
+
+impl Default for VeoClientConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: DEFAULT_TIMEOUT_SECS,
+            max_retries: DEFAULT_MAX_RETRIES,
+            model: VideoModel::default(),
+            poll_interval_secs: DEFAULT_POLL_INTERVAL_SECS,
+            max_wait_secs: DEFAULT_MAX_WAIT_SECS,
+            base_url: GEMINI_BASE_URL.to_string(),
+            proxy: ProxyConfig::default(),
+            tls: TlsConfig::default(),
+            transport: TransportConfig::default(),
+            auth_mode: AuthMode::default(),
+            circuit_breaker: None,
+            budget: None,
+        }
+    }
+}
+
+/// Video generation configuration for a request
+#[derive(Debug, Clone, Default)]
+pub struct VideoGenConfig {
+    pub aspect_ratio: Option<VideoAspectRatio>,
+    pub resolution: Option<Resolution>,
+    /// Length in seconds, 4 to 8
+    pub duration_secs: Option<u32>,
+    /// What the video should not contain
+    pub negative_prompt: Option<String>,
+}
+
+impl VideoGenConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_aspect_ratio(mut self, ratio: VideoAspectRatio) -> Self {
+        self.aspect_ratio = Some(ratio);
+        self
+    }
+
+    pub fn with_resolution(mut self, resolution: Resolution) -> Self {
+        self.resolution = Some(resolution);
+        self
+    }
+
+    pub fn with_duration_secs(mut self, secs: u32) -> Self {
+        self.duration_secs = Some(secs);
+        self
+    }
+
+    pub fn with_negative_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.negative_prompt = Some(prompt.into());
+        self
+    }
+}
+
+/// Generated video data
+#[derive(Debug, Clone)]
+pub struct GeneratedVideo {
+    pub data: Vec<u8>,
+    pub mime_type: String,
+}
+
+impl GeneratedVideo {
+    /// Get the appropriate file extension for this video
+    pub fn extension(&self) -> &'static str {
+        match self.mime_type.as_str() {
+            "video/webm" => "webm",
+            "video/quicktime" => "mov",
+            _ => "mp4",
+        }
+    }
+}
+
+/// Long-running operation returned by `predictLongRunning`
+#[derive(Debug, Clone, Deserialize)]
+pub struct Operation {
+    pub name: String,
+    #[serde(default)]
+    pub done: bool,
+    pub error: Option<OperationError>,
+    pub response: Option<Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OperationError {
+    #[serde(default)]
+    pub code: i64,
+    #[serde(default)]
+    pub message: String,
+}
+
+/// Where a finished operation put the video
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum VideoSource {
+    Uri(String),
+    Inline { data: String, mime_type: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct VeoClient {
+    client: Client,
+    api_key: ApiKey,
+    config: VeoClientConfig,
+}
+
+impl VeoClient {
+    pub fn new(api_key: String) -> Result<Self> {
+        Self::with_config(api_key, VeoClientConfig::default())
+    }
+
+    pub fn with_config(api_key: String, config: VeoClientConfig) -> Result<Self> {
+        let builder = crate::proxy::apply(
+            crate::rt::client_builder(config.timeout_secs),
+            &config.proxy,
+        )?;
+        let builder = crate::transport::apply(builder, &config.transport);
+        let client = crate::tls::apply(builder, &config.tls)?.build()?;
+
+        Ok(Self::with_http_client(client, api_key, config))
+    }
+
+    /// Build on an existing HTTP client, sharing its connection pool
+    pub fn with_http_client(client: Client, api_key: String, config: VeoClientConfig) -> Self {
+        Self {
+            client,
+            api_key: api_key.into(),
+            config,
+        }
+    }
+
+    fn validate(model: VideoModel, gen_config: Option<&VideoGenConfig>) -> Result<()> {
+        let Some(cfg) = gen_config else {
+            return Ok(());
+        };
+        if cfg.resolution.is_some() && !model.supports_resolution() {
+            return Err(VeoError::InvalidConfig(
+                "resolution is only supported with Veo 3 models".to_string(),
+            ));
+        }
+        if let Some(secs) = cfg.duration_secs
+            && !(4..=8).contains(&secs)
+        {
+            return Err(VeoError::InvalidConfig(format!(
+                "duration must be 4 to 8 seconds, got {}",
+                secs
+            )));
+        }
+        Ok(())
+    }
+
+    fn build_payload(prompt: &str, gen_config: Option<&VideoGenConfig>) -> Value {
+        let mut parameters = json!({});
+        if let Some(cfg) = gen_config {
+            if let Some(ratio) = &cfg.aspect_ratio {
+                parameters["aspectRatio"] = json!(ratio.api_value());
+            }
+            if let Some(resolution) = &cfg.resolution {
+                parameters["resolution"] = json!(resolution.api_value());
+            }
+            if let Some(secs) = cfg.duration_secs {
+                parameters["durationSeconds"] = json!(secs);
+            }
+            if let Some(negative) = &cfg.negative_prompt {
+                parameters["negativePrompt"] = json!(negative);
+            }
+        }
+
+        json!({
+            "instances": [{"prompt": prompt}],
+            "parameters": parameters
+        })
+    }
+
+    /// Turn an error response into `RateLimited` or `ApiError`
+    async fn check_status(
+        &self,
+        response: reqwest::Response,
+        request_id: &str,
+    ) -> Result<reqwest::Response> {
+        let status = response.status();
+        debug!("Received response with status: {}", status);
+
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            crate::metrics::record_rate_limited("veo");
+            return Err(VeoError::RateLimited {
+                request_id: request_id.to_string(),
+            });
+        }
+
+        if !status.is_success() {
+            let error_text = self
+                .api_key
+                .scrub(response.text().await.unwrap_or_default());
+            return Err(VeoError::ApiError {
+                status: status.as_u16(),
+                message: error_text,
+                request_id: request_id.to_string(),
+            });
+        }
+
+        Ok(response)
+    }
+
+    async fn send_submit(&self, payload: &Value, request_id: &str) -> Result<String> {
+        let url = format!(
+            "{}/v1beta/models/{}:predictLongRunning",
+            self.config.base_url,
+            self.config.model.api_model_name()
+        );
+
+        debug!(
+            "Submitting video generation request to Gemini API (model: {})",
+            self.config.model
+        );
+
+        let response = self
+            .config
+            .auth_mode
+            .request(&self.client, Method::POST, &url, &self.api_key)
+            .timeout(Duration::from_secs(self.config.timeout_secs))
+            .header("Content-Type", "application/json")
+            .json(payload)
+            .send()
+            .await?;
+        let operation: Operation = self
+            .check_status(response, request_id)
+            .await?
+            .json()
+            .await?;
+        Ok(operation.name)
+    }
+
+    async fn send_poll(&self, operation: &str, request_id: &str) -> Result<Operation> {
+        let url = format!("{}/v1beta/{}", self.config.base_url, operation);
+        let response = self
+            .config
+            .auth_mode
+            .request(&self.client, Method::GET, &url, &self.api_key)
+            .timeout(Duration::from_secs(self.config.timeout_secs))
+            .send()
+            .await?;
+        Ok(self
+            .check_status(response, request_id)
+            .await?
+            .json()
+            .await?)
+    }
+
+    async fn send_download(&self, uri: &str, request_id: &str) -> Result<GeneratedVideo> {
+        debug!("Downloading video from {}", crate::redact::url(uri));
+        let response = self
+            .config
+            .auth_mode
+            .request(&self.client, Method::GET, uri, &self.api_key)
+            .timeout(Duration::from_secs(self.config.timeout_secs))
+            .send()
+            .await?;
+        let response = self.check_status(response, request_id).await?;
+        let mime_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .filter(|v| v.starts_with("video/"))
+            .unwrap_or("video/mp4")
+            .to_string();
+        let data = response.bytes().await?.to_vec();
+        Ok(GeneratedVideo { data, mime_type })
+    }
+
+    /// Video of a finished operation, or why there is none
+    fn video_source(operation: &Operation, request_id: &str) -> Result<VideoSource> {
+        if let Some(error) = &operation.error {
+            return Err(VeoError::OperationFailed {
+                message: format!("{} (code {})", error.message, error.code),
+                request_id: request_id.to_string(),
+            });
+        }
+
+        let response = operation
+            .response
+            .as_ref()
+            .ok_or_else(|| VeoError::InvalidResponse("Missing response in operation".into()))?;
+        let generated = &response["generateVideoResponse"];
+
+        if let Some(video) = generated["generatedSamples"]
+            .as_array()
+            .and_then(|samples| samples.first())
+            .map(|sample| &sample["video"])
+        {
+            if let Some(uri) = video["uri"].as_str() {
+                return Ok(VideoSource::Uri(uri.to_string()));
+            }
+            if let Some(data) = video["bytesBase64Encoded"].as_str() {
+                return Ok(VideoSource::Inline {
+                    data: data.to_string(),
+                    mime_type: video["mimeType"]
+                        .as_str()
+                        .unwrap_or("video/mp4")
+                        .to_string(),
+                });
+            }
+        }
+
+        let reasons: Vec<&str> = generated["raiMediaFilteredReasons"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .collect();
+        if !reasons.is_empty() {
+            return Err(VeoError::Filtered {
+                reasons: reasons.join("; "),
+                request_id: request_id.to_string(),
+            });
+        }
+
+        Err(VeoError::NoVideoData)
+    }
+
+    fn is_retryable_error(err: &VeoError) -> bool {
+        crate::error::is_retryable(err.kind(), err.status())
+    }
+
+    /// Run one request with the shared retry policy, recording each attempt
+    /// on `span`
+    async fn with_retry<T, F, Fut>(&self, span: &Span, mut attempt: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut last_error = None;
+        let mut retry_count = 0;
+
+        while retry_count < self.config.max_retries {
+            if let Some(breaker) = &self.config.circuit_breaker {
+                breaker.check()?;
+            }
+            span.record("attempt", retry_count + 1);
+            let stopwatch = Stopwatch::start();
+            let result = attempt().await;
+            span.record("latency_ms", stopwatch.elapsed_ms());
+            if let Some(breaker) = &self.config.circuit_breaker {
+                breaker.record(&result, Self::is_retryable_error);
+            }
+            crate::metrics::record_request("veo", result.is_ok(), stopwatch.elapsed_ms());
+
+            match result {
+                Ok(value) => return Ok(value),
+                Err(e) => {
+                    if Self::is_retryable_error(&e)
+                        && retry_count + 1 < self.config.max_retries
+                        && self
+                            .config
+                            .circuit_breaker
+                            .as_ref()
+                            .is_none_or(CircuitBreaker::try_retry)
+                    {
+                        let delay = if matches!(e, VeoError::RateLimited { .. }) {
+                            Duration::from_secs(30 * (retry_count as u64 + 1))
+                        } else {
+                            Duration::from_secs(2u64.pow(retry_count))
+                        };
+                        warn!(
+                            "Request failed (attempt {}/{}): {}. Retrying in {:?}...",
+                            retry_count + 1,
+                            self.config.max_retries,
+                            e,
+                            delay
+                        );
+                        crate::metrics::record_retry("veo");
+                        crate::rt::sleep(delay).await;
+                        retry_count += 1;
+                        last_error = Some(e);
+                    } else {
+                        return Err(e);
+                    }
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or(VeoError::MaxRetriesExceeded(self.config.max_retries)))
+    }
+
+    /// Submit a prompt and return the name of its long-running operation
+    pub async fn submit(
+        &self,
+        prompt: &str,
+        gen_config: Option<&VideoGenConfig>,
+    ) -> Result<String> {
+        Self::validate(self.config.model, gen_config)?;
+        if let Some(budget) = &self.config.budget {
+            budget.check()?;
+        }
+        let payload = Self::build_payload(prompt, gen_config);
+        let request_id = crate::request_id::next();
+        let span = self.span(&request_id, prompt.len());
+        self.with_retry(&span, || self.send_submit(&payload, &request_id))
+            .instrument(span.clone())
+            .await
+    }
+
+    /// Fetch the current state of an operation
+    pub async fn get_operation(&self, operation: &str) -> Result<Operation> {
+        let request_id = crate::request_id::next();
+        let span = self.span(&request_id, 0);
+        self.with_retry(&span, || self.send_poll(operation, &request_id))
+            .instrument(span.clone())
+            .await
+    }
+
+    fn span(&self, request_id: &str, payload_bytes: usize) -> Span {
+        info_span!(
+            "veo_request",
+            request_id = %request_id,
+            model = %self.config.model,
+            payload_bytes,
+            attempt = field::Empty,
+            latency_ms = field::Empty,
+        )
+    }
+
+    /// Generate a video from a text prompt: submit, wait for the operation
+    /// and download the result
+    pub async fn generate_video(&self, prompt: &str) -> Result<GeneratedVideo> {
+        self.generate_video_with_config(prompt, None).await
+    }
+
+    /// Generate a video with specific configuration (aspect ratio,
+    /// resolution, duration, negative prompt)
+    pub async fn generate_video_with_config(
+        &self,
+        prompt: &str,
+        gen_config: Option<&VideoGenConfig>,
+    ) -> Result<GeneratedVideo> {
+        Self::validate(self.config.model, gen_config)?;
+        if let Some(budget) = &self.config.budget {
+            budget.check()?;
+        }
+
+        let payload = Self::build_payload(prompt, gen_config);
+        let request_id = crate::request_id::next();
+        let span = self.span(&request_id, prompt.len());
+
+        async {
+            let operation = self
+                .with_retry(&span, || self.send_submit(&payload, &request_id))
+                .await?;
+            info!("Video generation started: {}", operation);
+
+            // Count polls rather than reading a clock: std::time::Instant is unavailable on wasm32
+            let interval = Duration::from_secs(self.config.poll_interval_secs);
+            let max_polls = self.config.max_wait_secs / self.config.poll_interval_secs.max(1);
+            let mut polls = 0;
+            let finished = loop {
+                let state = self
+                    .with_retry(&span, || self.send_poll(&operation, &request_id))
+                    .await?;
+                if state.done {
+                    break state;
+                }
+                if polls >= max_polls {
+                    return Err(VeoError::OperationTimeout(self.config.max_wait_secs));
+                }
+                debug!("Operation {} is still running, waiting...", operation);
+                crate::rt::sleep(interval).await;
+                polls += 1;
+            };
+
+            let video = match Self::video_source(&finished, &request_id)? {
+                VideoSource::Uri(uri) => {
+                    self.with_retry(&span, || self.send_download(&uri, &request_id))
+                        .await?
+                }
+                VideoSource::Inline { data, mime_type } => GeneratedVideo {
+                    data: base64::engine::general_purpose::STANDARD.decode(data)?,
+                    mime_type,
+                },
+            };
+
+            let seconds = gen_config
+                .and_then(|cfg| cfg.duration_secs)
+                .unwrap_or(DEFAULT_VIDEO_SECONDS);
+            crate::usage::record_veo(
+                self.config.model.api_model_name(),
+                f64::from(seconds),
+                self.config.budget.as_ref(),
+            );
+            info!("Video generation successful");
+            Ok(video)
+        }
+        .instrument(span.clone())
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_payload() {
+        let payload = VeoClient::build_payload("A cat", None);
+        assert_eq!(payload["instances"][0]["prompt"], "A cat");
+        assert_eq!(payload["parameters"], json!({}));
+
+        let gen_config = VideoGenConfig::new()
+            .with_aspect_ratio(VideoAspectRatio::Tall)
+            .with_resolution(Resolution::P1080)
+            .with_duration_secs(6)
+            .with_negative_prompt("text overlays");
+        let payload = VeoClient::build_payload("A cat", Some(&gen_config));
+        assert_eq!(
+            payload["parameters"],
+            json!({
+                "aspectRatio": "9:16",
+                "resolution": "1080p",
+                "durationSeconds": 6,
+                "negativePrompt": "text overlays"
+            })
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let resolution = VideoGenConfig::new().with_resolution(Resolution::P1080);
+        assert!(VeoClient::validate(VideoModel::Veo3, Some(&resolution)).is_ok());
+        assert!(matches!(
+            VeoClient::validate(VideoModel::Veo2, Some(&resolution)),
+            Err(VeoError::InvalidConfig(_))
+        ));
+
+        let too_long = VideoGenConfig::new().with_duration_secs(12);
+        let err = VeoClient::validate(VideoModel::Veo3, Some(&too_long)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Config);
+        assert!(VeoClient::validate(VideoModel::Veo2, None).is_ok());
+    }
+
+    #[test]
+    fn test_video_source() {
+        let operation: Operation = serde_json::from_value(json!({
+            "name": "models/veo-3.0-generate-001/operations/abc",
+            "done": true,
+            "response": {
+                "generateVideoResponse": {
+                    "generatedSamples": [{"video": {"uri": "https://example.com/v.mp4"}}]
+                }
+            }
+        }))
+        .unwrap();
+        assert_eq!(
+            VeoClient::video_source(&operation, "req").unwrap(),
+            VideoSource::Uri("https://example.com/v.mp4".to_string())
+        );
+
+        let filtered: Operation = serde_json::from_value(json!({
+            "name": "operations/abc",
+            "done": true,
+            "response": {
+                "generateVideoResponse": {"raiMediaFilteredReasons": ["Unsafe content"]}
+            }
+        }))
+        .unwrap();
+        let err = VeoClient::video_source(&filtered, "req").unwrap_err();
+        assert!(
+            matches!(err, VeoError::Filtered { ref reasons, .. } if reasons == "Unsafe content")
+        );
+
+        let failed: Operation = serde_json::from_value(json!({
+            "name": "operations/abc",
+            "done": true,
+            "error": {"code": 13, "message": "Internal error"}
+        }))
+        .unwrap();
+        let err = VeoClient::video_source(&failed, "req").unwrap_err();
+        assert!(err.to_string().contains("Internal error (code 13)"));
+        assert!(!VeoClient::is_retryable_error(&err));
+    }
+
+    #[test]
+    fn test_video_model_from_str() {
+        assert_eq!(VideoModel::from_str("3").unwrap(), VideoModel::Veo3);
+        assert_eq!(VideoModel::from_str("veo3").unwrap(), VideoModel::Veo3);
+        assert_eq!(VideoModel::from_str("fast").unwrap(), VideoModel::Veo3Fast);
+        assert_eq!(VideoModel::from_str("2").unwrap(), VideoModel::Veo2);
+        assert!(VideoModel::from_str("4").is_err());
+        assert_eq!(VideoModel::Veo3Fast.to_string(), "3-fast");
+        assert_eq!(VideoModel::Veo3.api_model_name(), "veo-3.0-generate-001");
+    }
+
+    #[test]
+    fn test_aspect_and_resolution_from_str() {
+        assert_eq!(
+            VideoAspectRatio::from_str("9:16").unwrap(),
+            VideoAspectRatio::Tall
+        );
+        assert_eq!(
+            VideoAspectRatio::from_str("wide").unwrap(),
+            VideoAspectRatio::Wide
+        );
+        assert!(VideoAspectRatio::from_str("1:1").is_err());
+        assert_eq!(Resolution::from_str("1080p").unwrap(), Resolution::P1080);
+        assert_eq!(Resolution::from_str("720").unwrap(), Resolution::P720);
+        assert!(Resolution::from_str("4k").is_err());
+    }
+
+    #[test]
+    fn test_generated_video_extension() {
+        let video = |mime_type: &str| GeneratedVideo {
+            data: vec![],
+            mime_type: mime_type.to_string(),
+        };
+        assert_eq!(video("video/mp4").extension(), "mp4");
+        assert_eq!(video("video/webm").extension(), "webm");
+        assert_eq!(video("application/octet-stream").extension(), "mp4");
+    }
+}
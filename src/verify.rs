@@ -0,0 +1,201 @@
+use anyhow::Result;
+use clap::Parser;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tracing::debug;
+use walkdir::WalkDir;
+
+use transcript_tool::cli::{LogFormat, init_logging};
+use transcript_tool::integrity::{CHECKSUM_EXTENSION, checksum_key, is_image_path};
+use transcript_tool::{check_image, verify_checksum};
+
+#[derive(Parser, Debug)]
+#[command(name = "verify")]
+#[command(version)]
+#[command(
+    about = "Check outputs against their .blake3 checksum sidecars, and images for truncation"
+)]
+#[command(after_help = "EXAMPLES:
+    verify talk.json
+    verify ./transcripts ./images
+    verify -q ./out || echo \"Some outputs are damaged\"")]
+pub struct Args {
+    /// Output files, or folders searched for checksum sidecars and images
+    #[arg(value_name = "PATH", required = true)]
+    paths: Vec<PathBuf>,
+
+    /// Only print files that fail
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// Verbosity level (-v, -vv, -vvv)
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Log output format: human-readable text, or one JSON object per line
+    #[arg(long, value_enum, default_value = "text", env = "FLASHECHO_LOG_FORMAT")]
+    log_format: LogFormat,
+}
+
+fn is_sidecar(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(CHECKSUM_EXTENSION))
+}
+
+/// Files to check: those given, and in folders every file with a sidecar
+/// and every image. A sidecar stands for the file it describes, so one whose
+/// file is gone is reported.
+fn find_outputs(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut seen = HashSet::new();
+    let mut add = |file: &Path| {
+        let file = if is_sidecar(file) {
+            file.with_extension("")
+        } else {
+            file.to_path_buf()
+        };
+        if seen.insert(file.clone()) {
+            files.push(file);
+        }
+    };
+    for path in paths {
+        if path.is_file() {
+            add(path);
+            continue;
+        }
+        if !path.exists() {
+            anyhow::bail!("Path does not exist: {:?}", path);
+        }
+        for entry in WalkDir::new(path)
+            .follow_links(true)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(|e| e.ok())
+        {
+            let file = entry.path();
+            if file.is_file() && (is_sidecar(file) || is_image_path(file)) {
+                add(file);
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Check `file` against its sidecar and, for an image, its structure;
+/// returns what was checked, or what is wrong
+async fn verify_file(file: &Path) -> Result<Vec<&'static str>, String> {
+    let data = fs::read(file)
+        .await
+        .map_err(|e| format!("cannot read file: {}", e))?;
+    let mut checked = Vec::new();
+
+    let sidecar = PathBuf::from(checksum_key(&file.to_string_lossy()));
+    match fs::read_to_string(&sidecar).await {
+        Ok(sidecar) => {
+            verify_checksum(&data, &sidecar).map_err(|e| e.to_string())?;
+            checked.push("checksum");
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(format!("cannot read checksum sidecar: {}", e)),
+    }
+    if is_image_path(file) {
+        check_image(&data).map_err(|e| e.to_string())?;
+        checked.push("image");
+    }
+
+    if checked.is_empty() {
+        return Err("no checksum sidecar".to_string());
+    }
+    Ok(checked)
+}
+
+/// Run with parsed arguments; shared by this binary and its `flashecho` subcommand
+pub async fn run(args: Args) -> Result<()> {
+    init_logging(args.verbose, args.log_format);
+
+    let files = find_outputs(&args.paths)?;
+    if files.is_empty() {
+        anyhow::bail!("No checksum sidecars or images found");
+    }
+    debug!("Checking {} files", files.len());
+
+    let mut failed = 0;
+    for file in &files {
+        match verify_file(file).await {
+            Ok(checked) => {
+                if !args.quiet {
+                    println!("OK      {} ({})", file.display(), checked.join(", "));
+                }
+            }
+            Err(problem) => {
+                failed += 1;
+                println!("FAILED  {}: {}", file.display(), problem);
+            }
+        }
+    }
+
+    if !args.quiet {
+        println!("\n{} files checked, {} failed", files.len(), failed);
+    }
+    if failed > 0 {
+        anyhow::bail!("{} of {} files failed verification", failed, files.len());
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    run(Args::parse()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use transcript_tool::checksum_line;
+
+    #[tokio::test]
+    async fn test_verify_outputs() {
+        let dir = std::env::temp_dir().join(format!("flashecho_verify_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, data: &[u8]| std::fs::write(dir.join(name), data).unwrap();
+        write("good.json", b"{}");
+        write(
+            "good.json.blake3",
+            checksum_line(b"{}", "good.json").as_bytes(),
+        );
+        write("edited.json", b"{ }");
+        write(
+            "edited.json.blake3",
+            checksum_line(b"{}", "edited.json").as_bytes(),
+        );
+        write(
+            "gone.srt.blake3",
+            checksum_line(b"1", "gone.srt").as_bytes(),
+        );
+        write("cut.jpg", &[0xFF, 0xD8, 0xFF, 0xE0]);
+        write("notes.txt", b"not an output");
+
+        let files = find_outputs(std::slice::from_ref(&dir)).unwrap();
+        let names: Vec<_> = files
+            .iter()
+            .map(|f| f.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, ["cut.jpg", "edited.json", "gone.srt", "good.json"]);
+
+        assert_eq!(
+            verify_file(&dir.join("good.json")).await,
+            Ok(vec!["checksum"])
+        );
+        let edited = verify_file(&dir.join("edited.json")).await.unwrap_err();
+        assert!(edited.starts_with("Checksum mismatch"), "{}", edited);
+        assert!(verify_file(&dir.join("gone.srt")).await.is_err());
+        assert!(verify_file(&dir.join("cut.jpg")).await.is_err());
+        assert_eq!(
+            verify_file(&dir.join("notes.txt")).await,
+            Err("no checksum sidecar".to_string())
+        );
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
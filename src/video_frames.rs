@@ -0,0 +1,242 @@
+//! Keyframes of a video pulled out with ffmpeg at scene changes (or at a
+//! given time), so a model can look at what a video shows without receiving
+//! the whole file.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use thiserror::Error;
+use tokio::fs;
+use tokio::process::Command;
+use tracing::debug;
+
+use crate::error::ErrorKind;
+use crate::text_api::MediaPart;
+use crate::vision_api::Frame;
+
+/// Fraction of a frame that must change for ffmpeg to report a new scene
+pub const DEFAULT_SCENE_THRESHOLD: f64 = 0.3;
+
+/// Width keyframes are scaled down to for a model to look at; Gemini tiles
+/// images at 768px
+pub const MAX_FRAME_WIDTH: u32 = 768;
+
+#[derive(Debug, Error)]
+pub enum FrameError {
+    #[error("Failed to execute ffmpeg. Is ffmpeg installed? ({0})")]
+    FfmpegNotFound(std::io::Error),
+
+    #[error("ffmpeg frame extraction failed: {0}")]
+    FfmpegFailed(String),
+
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Invalid scene threshold: {0}. Must be between 0 and 1")]
+    InvalidThreshold(f64),
+}
+
+pub type Result<T> = std::result::Result<T, FrameError>;
+
+impl FrameError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            FrameError::InvalidThreshold(_) => ErrorKind::InvalidInput,
+            FrameError::FfmpegNotFound(_)
+            | FrameError::FfmpegFailed(_)
+            | FrameError::IoError(_) => ErrorKind::Io,
+        }
+    }
+}
+
+/// Keyframes of one video in time order
+#[derive(Debug, Clone)]
+pub struct SceneFrames {
+    pub frames: Vec<Frame>,
+    /// Length of the video as reported by ffmpeg
+    pub duration_secs: Option<f64>,
+}
+
+/// Extract the first frame and one JPEG per scene change (a change of more
+/// than `threshold` of the picture), scaled down to at most `max_width` and
+/// keeping at most `max_frames` spread evenly over the list
+pub async fn extract_scene_frames(
+    video: &Path,
+    threshold: f64,
+    max_frames: usize,
+    max_width: u32,
+) -> Result<SceneFrames> {
+    if !(0.0..=1.0).contains(&threshold) {
+        return Err(FrameError::InvalidThreshold(threshold));
+    }
+
+    let select = format!("select='eq(n\\,0)+gt(scene\\,{})'", threshold);
+    let (mut frames, duration_secs) = in_scratch_dir(video, &[], &select, &[], max_width).await?;
+
+    if frames.len() > max_frames {
+        let keep = spread_indices(frames.len(), max_frames);
+        debug!("Keeping {} of {} keyframes", keep.len(), frames.len());
+        frames = keep.into_iter().map(|i| frames[i].clone()).collect();
+    }
+    Ok(SceneFrames {
+        frames,
+        duration_secs,
+    })
+}
+
+/// A fresh directory for one extraction, unique within and across processes
+fn scratch_dir() -> PathBuf {
+    static NEXT: AtomicU32 = AtomicU32::new(0);
+    std::env::temp_dir().join(format!(
+        "flashecho_frames_{}_{}",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+/// Extract the frame shown at `time_secs`, scaled down to at most `max_width`
+pub async fn extract_frame_at(video: &Path, time_secs: f64, max_width: u32) -> Result<Frame> {
+    let seek = format!("{:.3}", time_secs.max(0.0));
+    let input_args = ["-ss", seek.as_str()];
+    let select = "select='eq(n\\,0)'";
+    let (frames, _) =
+        in_scratch_dir(video, &input_args, select, &["-frames:v", "1"], max_width).await?;
+    let mut frame = frames.into_iter().next().ok_or_else(|| {
+        FrameError::FfmpegFailed(format!("No frame at {}s (past the end?)", seek))
+    })?;
+    // Timestamps restart at zero after an input seek
+    frame.time_secs += time_secs.max(0.0);
+    Ok(frame)
+}
+
+/// Run one extraction in a fresh directory that is removed afterwards
+async fn in_scratch_dir(
+    video: &Path,
+    input_args: &[&str],
+    select: &str,
+    output_args: &[&str],
+    max_width: u32,
+) -> Result<(Vec<Frame>, Option<f64>)> {
+    let dir = scratch_dir();
+    fs::create_dir_all(&dir).await?;
+    let result = run_extraction(video, input_args, select, output_args, max_width, &dir).await;
+    if let Err(e) = fs::remove_dir_all(&dir).await {
+        debug!("Failed to remove {:?}: {}", dir, e);
+    }
+    result
+}
+
+async fn run_extraction(
+    video: &Path,
+    input_args: &[&str],
+    select: &str,
+    output_args: &[&str],
+    max_width: u32,
+    dir: &Path,
+) -> Result<(Vec<Frame>, Option<f64>)> {
+    let filter = format!("{},showinfo,scale='min({}\\,iw)':-2", select, max_width);
+    debug!("Extracting keyframes of {:?} with {}", video, filter);
+
+    // showinfo reports at info level; that's also where ffmpeg prints the duration
+    let output = Command::new("ffmpeg")
+        .args(["-hide_banner", "-nostdin", "-loglevel", "info"])
+        .args(input_args)
+        .arg("-i")
+        .arg(video)
+        .args(["-vf", &filter, "-fps_mode", "vfr", "-q:v", "3"])
+        .args(output_args)
+        .arg(dir.join("frame_%05d.jpg"))
+        .output()
+        .await
+        .map_err(FrameError::FfmpegNotFound)?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        let last_lines: Vec<&str> = stderr.lines().rev().take(5).collect();
+        let message: Vec<&str> = last_lines.into_iter().rev().collect();
+        return Err(FrameError::FfmpegFailed(message.join("\n")));
+    }
+
+    let times = parse_frame_times(&stderr);
+    let mut frames = Vec::with_capacity(times.len());
+    for (i, time_secs) in times.into_iter().enumerate() {
+        let path = dir.join(format!("frame_{:05}.jpg", i + 1));
+        if !fs::try_exists(&path).await? {
+            break;
+        }
+        frames.push(Frame {
+            time_secs,
+            image: MediaPart::Inline {
+                mime_type: "image/jpeg".to_string(),
+                data: fs::read(&path).await?,
+            },
+        });
+    }
+    Ok((frames, parse_duration(&stderr)))
+}
+
+/// `pts_time` of every frame that showinfo reported
+fn parse_frame_times(stderr: &str) -> Vec<f64> {
+    stderr
+        .lines()
+        .filter(|line| line.contains("Parsed_showinfo"))
+        .filter_map(|line| {
+            let rest = &line[line.find("pts_time:")? + "pts_time:".len()..];
+            rest.split_whitespace().next()?.parse().ok()
+        })
+        .collect()
+}
+
+/// `Duration: 00:01:23.45` of the input, if ffmpeg knows it
+fn parse_duration(stderr: &str) -> Option<f64> {
+    let line = stderr
+        .lines()
+        .find(|line| line.trim_start().starts_with("Duration:"))?;
+    let value = line.trim_start()["Duration:".len()..]
+        .split(',')
+        .next()?
+        .trim();
+    let mut parts = value.split(':');
+    let hours: f64 = parts.next()?.parse().ok()?;
+    let minutes: f64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    Some(hours * 3600.0 + minutes * 60.0 + seconds)
+}
+
+/// `count` indices out of `0..len`, evenly spread and always including the first
+fn spread_indices(len: usize, count: usize) -> Vec<usize> {
+    if count >= len {
+        return (0..len).collect();
+    }
+    (0..count).map(|i| i * len / count).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STDERR: &str = "\
+Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'talk.mp4':
+  Duration: 00:01:23.45, start: 0.000000, bitrate: 1205 kb/s
+[Parsed_showinfo_1 @ 0x600] n:   0 pts:      0 pts_time:0       duration:512 fmt:yuv420p
+[Parsed_showinfo_1 @ 0x600] n:   1 pts: 630784 pts_time:41.067  duration:512 fmt:yuv420p
+[Parsed_showinfo_1 @ 0x600] color_range:tv color_space:bt709
+frame=    2 fps=0.0 q=3.0 Lsize=N/A time=00:01:23.40";
+
+    #[test]
+    fn test_parse_frame_times() {
+        assert_eq!(parse_frame_times(STDERR), [0.0, 41.067]);
+        assert!(parse_frame_times("").is_empty());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration(STDERR), Some(83.45));
+        assert_eq!(parse_duration("  Duration: N/A, bitrate: N/A"), None);
+    }
+
+    #[test]
+    fn test_spread_indices() {
+        assert_eq!(spread_indices(3, 5), [0, 1, 2]);
+        assert_eq!(spread_indices(10, 4), [0, 2, 5, 7]);
+    }
+}
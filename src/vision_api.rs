@@ -0,0 +1,781 @@
+//! Image understanding, the inverse of `imagen`: images go to a Gemini model
+//! through [`TextClient`] and come back as a caption, the objects in view,
+//! dominant colors and any visible text, following a response schema. The
+//! same client extracts the text of images and scanned PDFs in reading order,
+//! turns keyframes of a video into a list of scenes and picks the frame that
+//! makes the best thumbnail.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::path::Path;
+
+use crate::text_api::{MediaPart, Result, TextClient, TextError, TextGenConfig};
+
+/// Largest image sent inline; the request limit is 20MB including the prompt
+pub const MAX_INLINE_IMAGE_SIZE: u64 = 20 * 1024 * 1024;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImageDescription {
+    /// One or two sentences
+    pub caption: String,
+    #[serde(default)]
+    pub objects: Vec<String>,
+    /// Most prominent first
+    #[serde(default)]
+    pub dominant_colors: Vec<DominantColor>,
+    /// Text visible in the image, one entry per line or sign; empty if none
+    #[serde(default)]
+    pub text: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DominantColor {
+    pub name: String,
+    /// `#RRGGBB`
+    #[serde(default)]
+    pub hex: String,
+}
+
+/// Text of an image or document in reading order
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OcrResult {
+    pub blocks: Vec<TextBlock>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextBlock {
+    /// 1-based; always 1 for images
+    #[serde(default = "first_page")]
+    pub page: u32,
+    #[serde(default)]
+    pub kind: BlockKind,
+    /// Tables are given as Markdown table rows
+    pub text: String,
+    /// `[ymin, xmin, ymax, xmax]` scaled to 0-1000, when regions were requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bounding_box: Option<Vec<u32>>,
+}
+
+fn first_page() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockKind {
+    Heading,
+    #[default]
+    Paragraph,
+    ListItem,
+    Table,
+    Caption,
+    #[serde(other)]
+    Other,
+}
+
+/// Still image taken from a video
+#[derive(Debug, Clone)]
+pub struct Frame {
+    /// Position in the video
+    pub time_secs: f64,
+    pub image: MediaPart,
+}
+
+/// Scenes of a video in time order
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SceneSummary {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_secs: Option<f64>,
+    pub scenes: Vec<Scene>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scene {
+    pub start_secs: f64,
+    pub end_secs: f64,
+    pub description: String,
+    /// Text shown on screen, verbatim; empty if none
+    #[serde(default)]
+    pub on_screen_text: Vec<String>,
+}
+
+/// Scene as the model reports it, by the number of its first frame
+#[derive(Debug, Deserialize)]
+struct SceneAnswer {
+    first_frame: usize,
+    description: String,
+    #[serde(default)]
+    on_screen_text: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScenesAnswer {
+    scenes: Vec<SceneAnswer>,
+}
+
+/// Frame chosen as a video thumbnail
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThumbnailPick {
+    /// Index into the frames that were offered
+    pub frame: usize,
+    pub reason: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ThumbnailAnswer {
+    best_frame: usize,
+    #[serde(default)]
+    reason: String,
+}
+
+/// MIME type of an image file by extension, for the formats Gemini accepts
+pub fn image_mime_type(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    match ext.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "webp" => Some("image/webp"),
+        "gif" => Some("image/gif"),
+        "heic" => Some("image/heic"),
+        "heif" => Some("image/heif"),
+        _ => None,
+    }
+}
+
+/// MIME type of an image or PDF file by extension, the inputs of OCR
+pub fn document_mime_type(path: &Path) -> Option<&'static str> {
+    match path.extension()?.to_str()?.to_lowercase().as_str() {
+        "pdf" => Some("application/pdf"),
+        _ => image_mime_type(path),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VisionClient {
+    client: TextClient,
+    hint: Option<String>,
+}
+
+impl VisionClient {
+    pub fn new(client: TextClient) -> Self {
+        Self { client, hint: None }
+    }
+
+    /// Context for the model, e.g. what the image is for or what to focus on
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        let hint = hint.into();
+        self.hint = (!hint.trim().is_empty()).then(|| hint.trim().to_string());
+        self
+    }
+
+    fn prompt(&self) -> String {
+        let mut prompt = String::from(
+            "Describe this image. Write a caption of one or two sentences, list the \
+             distinct objects in view, give up to five dominant colors with a common \
+             name and a #RRGGBB value, and transcribe any text that appears in it \
+             exactly as written, one entry per line or sign. Leave the text list \
+             empty if there is none.",
+        );
+        if let Some(hint) = &self.hint {
+            prompt.push_str(&format!("\n\nContext: {}", hint));
+        }
+        prompt
+    }
+
+    /// Describe one image with a single request
+    pub async fn describe(&self, image: &MediaPart) -> Result<ImageDescription> {
+        let config = TextGenConfig::new().with_response_schema(response_schema());
+        let answer = self
+            .client
+            .generate_with_media(&self.prompt(), std::slice::from_ref(image), Some(&config))
+            .await?;
+        let description: ImageDescription = serde_json::from_str(answer.trim()).map_err(|e| {
+            TextError::InvalidResponse(format!("Description doesn't match the schema: {}", e))
+        })?;
+        Ok(description.normalized())
+    }
+
+    fn ocr_prompt(&self, regions: bool) -> String {
+        let mut prompt = String::from(
+            "Extract all text from this document exactly as written, in natural reading \
+             order: follow columns top to bottom before moving right, and skip nothing. \
+             Split the text into blocks and give each block's kind and 1-based page \
+             number. Write tables as Markdown table rows. Don't translate or correct \
+             the text.",
+        );
+        if regions {
+            prompt.push_str(
+                " Give each block's bounding box as [ymin, xmin, ymax, xmax] scaled to 0-1000.",
+            );
+        }
+        if let Some(hint) = &self.hint {
+            prompt.push_str(&format!("\n\nContext: {}", hint));
+        }
+        prompt
+    }
+
+    /// Extract the text of an image or PDF with a single request, optionally
+    /// with the bounding box of each block
+    pub async fn extract_text(&self, document: &MediaPart, regions: bool) -> Result<OcrResult> {
+        let config = TextGenConfig::new().with_response_schema(ocr_response_schema(regions));
+        let answer = self
+            .client
+            .generate_with_media(
+                &self.ocr_prompt(regions),
+                std::slice::from_ref(document),
+                Some(&config),
+            )
+            .await?;
+        let result: OcrResult = serde_json::from_str(answer.trim()).map_err(|e| {
+            TextError::InvalidResponse(format!("OCR result doesn't match the schema: {}", e))
+        })?;
+        Ok(result.normalized())
+    }
+
+    fn scenes_prompt(
+        &self,
+        frames: &[Frame],
+        duration_secs: Option<f64>,
+        transcript: Option<&str>,
+    ) -> String {
+        let mut prompt = format!(
+            "These {} images are frames of one video, in order, taken where the picture \
+             changes. Group consecutive frames that show the same scene. For each scene \
+             give the number of its first frame, describe what it shows in one or two \
+             sentences, and transcribe any text on screen exactly as written.\n\nFrames:\n",
+            frames.len()
+        );
+        for (i, frame) in frames.iter().enumerate() {
+            prompt.push_str(&format!("{}: {}\n", i + 1, format_clock(frame.time_secs)));
+        }
+        if let Some(duration) = duration_secs {
+            prompt.push_str(&format!(
+                "\nThe video is {} long.\n",
+                format_clock(duration)
+            ));
+        }
+        if let Some(transcript) = transcript.filter(|t| !t.trim().is_empty()) {
+            prompt.push_str(&format!(
+                "\nWhat is said in the video, as `[MM:SS] Speaker: text` lines, for \
+                 context:\n{}\n",
+                transcript.trim_end()
+            ));
+        }
+        if let Some(hint) = &self.hint {
+            prompt.push_str(&format!("\nContext: {}", hint));
+        }
+        prompt
+    }
+
+    /// Turn keyframes of a video into scenes with a single request. Scene
+    /// times come from the frames, so they don't depend on the model reading
+    /// timestamps; a transcript of the video gives the model more context
+    pub async fn summarize_scenes(
+        &self,
+        frames: &[Frame],
+        duration_secs: Option<f64>,
+        transcript: Option<&str>,
+    ) -> Result<SceneSummary> {
+        if frames.is_empty() {
+            return Err(TextError::EmptyPrompt);
+        }
+        let config = TextGenConfig::new().with_response_schema(scenes_response_schema());
+        let media: Vec<MediaPart> = frames.iter().map(|frame| frame.image.clone()).collect();
+        let answer = self
+            .client
+            .generate_with_media(
+                &self.scenes_prompt(frames, duration_secs, transcript),
+                &media,
+                Some(&config),
+            )
+            .await?;
+        let answer: ScenesAnswer = serde_json::from_str(answer.trim()).map_err(|e| {
+            TextError::InvalidResponse(format!("Scene list doesn't match the schema: {}", e))
+        })?;
+        Ok(SceneSummary {
+            duration_secs,
+            scenes: scenes_from_answer(answer.scenes, frames, duration_secs),
+        })
+    }
+
+    fn thumbnail_prompt(&self, count: usize, title: Option<&str>) -> String {
+        let mut prompt = format!(
+            "These {} images are frames of one video. Pick the frame that makes the best \
+             thumbnail: sharp and well lit, with a clear subject (an expressive face if \
+             there are people) and room for a title. Avoid blurred frames, transitions, \
+             closed eyes and frames that are mostly text. Answer with the 1-based number \
+             of the frame and a short reason.",
+            count
+        );
+        if let Some(title) = title.filter(|t| !t.trim().is_empty()) {
+            prompt.push_str(&format!(
+                "\n\nThe thumbnail's title will be: {}",
+                title.trim()
+            ));
+        }
+        if let Some(hint) = &self.hint {
+            prompt.push_str(&format!("\n\nContext: {}", hint));
+        }
+        prompt
+    }
+
+    /// Pick the frame that makes the best thumbnail with a single request
+    pub async fn pick_thumbnail(
+        &self,
+        frames: &[Frame],
+        title: Option<&str>,
+    ) -> Result<ThumbnailPick> {
+        if frames.is_empty() {
+            return Err(TextError::EmptyPrompt);
+        }
+        let schema = json!({
+            "type": "OBJECT",
+            "properties": {
+                "best_frame": { "type": "INTEGER", "description": "1-based frame number" },
+                "reason": { "type": "STRING" }
+            },
+            "required": ["best_frame", "reason"]
+        });
+        let config = TextGenConfig::new().with_response_schema(schema);
+        let media: Vec<MediaPart> = frames.iter().map(|frame| frame.image.clone()).collect();
+        let answer = self
+            .client
+            .generate_with_media(
+                &self.thumbnail_prompt(frames.len(), title),
+                &media,
+                Some(&config),
+            )
+            .await?;
+        let answer: ThumbnailAnswer = serde_json::from_str(answer.trim()).map_err(|e| {
+            TextError::InvalidResponse(format!("Thumbnail pick doesn't match the schema: {}", e))
+        })?;
+        if !(1..=frames.len()).contains(&answer.best_frame) {
+            return Err(TextError::InvalidResponse(format!(
+                "Picked frame {} of {}",
+                answer.best_frame,
+                frames.len()
+            )));
+        }
+        Ok(ThumbnailPick {
+            frame: answer.best_frame - 1,
+            reason: answer.reason.trim().to_string(),
+        })
+    }
+}
+
+/// Place the model's scenes on the frame times: each scene runs from its
+/// first frame to the next scene, and the first one from the first frame
+fn scenes_from_answer(
+    mut answers: Vec<SceneAnswer>,
+    frames: &[Frame],
+    duration_secs: Option<f64>,
+) -> Vec<Scene> {
+    answers.retain(|scene| (1..=frames.len()).contains(&scene.first_frame));
+    answers.sort_by_key(|scene| scene.first_frame);
+    answers.dedup_by_key(|scene| scene.first_frame);
+    if let Some(first) = answers.first_mut() {
+        first.first_frame = 1;
+    }
+    let last_time = frames
+        .last()
+        .map(|frame| frame.time_secs)
+        .unwrap_or_default();
+    let video_end = duration_secs.unwrap_or(last_time).max(last_time);
+
+    let starts: Vec<f64> = answers
+        .iter()
+        .map(|scene| frames[scene.first_frame - 1].time_secs)
+        .collect();
+    answers
+        .into_iter()
+        .enumerate()
+        .map(|(i, scene)| Scene {
+            start_secs: starts[i],
+            end_secs: starts.get(i + 1).copied().unwrap_or(video_end),
+            description: scene.description.trim().to_string(),
+            on_screen_text: scene
+                .on_screen_text
+                .into_iter()
+                .map(|text| text.trim().to_string())
+                .filter(|text| !text.is_empty())
+                .collect(),
+        })
+        .collect()
+}
+
+fn scenes_response_schema() -> Value {
+    json!({
+        "type": "OBJECT",
+        "properties": {
+            "scenes": {
+                "type": "ARRAY",
+                "items": {
+                    "type": "OBJECT",
+                    "properties": {
+                        "first_frame": { "type": "INTEGER", "description": "1-based frame number" },
+                        "description": { "type": "STRING" },
+                        "on_screen_text": {
+                            "type": "ARRAY",
+                            "description": "Text on screen, verbatim; empty if none",
+                            "items": { "type": "STRING" }
+                        }
+                    },
+                    "required": ["first_frame", "description", "on_screen_text"]
+                }
+            }
+        },
+        "required": ["scenes"]
+    })
+}
+
+/// `MM:SS`, or `HH:MM:SS` from an hour on
+fn format_clock(secs: f64) -> String {
+    let secs = secs.max(0.0) as u64;
+    if secs >= 3600 {
+        format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+impl SceneSummary {
+    /// One `[start - end] description` line per scene, with an indented
+    /// `Text:` line for on-screen text
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for scene in &self.scenes {
+            out.push_str(&format!(
+                "[{} - {}] {}\n",
+                format_clock(scene.start_secs),
+                format_clock(scene.end_secs),
+                scene.description
+            ));
+            if !scene.on_screen_text.is_empty() {
+                let lines: Vec<String> = scene
+                    .on_screen_text
+                    .iter()
+                    .map(|line| format!("\"{}\"", line))
+                    .collect();
+                out.push_str(&format!("    Text: {}\n", lines.join(", ")));
+            }
+        }
+        out
+    }
+}
+
+fn response_schema() -> Value {
+    json!({
+        "type": "OBJECT",
+        "properties": {
+            "caption": { "type": "STRING" },
+            "objects": { "type": "ARRAY", "items": { "type": "STRING" } },
+            "dominant_colors": {
+                "type": "ARRAY",
+                "items": {
+                    "type": "OBJECT",
+                    "properties": {
+                        "name": { "type": "STRING" },
+                        "hex": { "type": "STRING", "description": "#RRGGBB" }
+                    },
+                    "required": ["name", "hex"]
+                }
+            },
+            "text": {
+                "type": "ARRAY",
+                "description": "Visible text, verbatim; empty if none",
+                "items": { "type": "STRING" }
+            }
+        },
+        "required": ["caption", "objects", "dominant_colors", "text"]
+    })
+}
+
+fn ocr_response_schema(regions: bool) -> Value {
+    let mut block = json!({
+        "type": "OBJECT",
+        "properties": {
+            "page": { "type": "INTEGER" },
+            "kind": {
+                "type": "STRING",
+                "enum": ["heading", "paragraph", "list_item", "table", "caption", "other"]
+            },
+            "text": { "type": "STRING" }
+        },
+        "required": ["page", "kind", "text"]
+    });
+    if regions {
+        block["properties"]["bounding_box"] = json!({
+            "type": "ARRAY",
+            "description": "[ymin, xmin, ymax, xmax] scaled to 0-1000",
+            "items": { "type": "INTEGER" }
+        });
+        block["required"] = json!(["page", "kind", "text", "bounding_box"]);
+    }
+    json!({
+        "type": "OBJECT",
+        "properties": {
+            "blocks": { "type": "ARRAY", "items": block }
+        },
+        "required": ["blocks"]
+    })
+}
+
+/// `#RRGGBB` in upper case, or empty if `hex` isn't a 6-digit color
+fn normalize_hex(hex: &str) -> String {
+    let digits = hex.trim().trim_start_matches('#');
+    if digits.len() == 6 && digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        format!("#{}", digits.to_uppercase())
+    } else {
+        String::new()
+    }
+}
+
+impl ImageDescription {
+    /// Trim entries and drop empty ones and malformed color values
+    fn normalized(mut self) -> Self {
+        self.caption = self.caption.trim().to_string();
+        let trimmed = |items: Vec<String>| -> Vec<String> {
+            items
+                .into_iter()
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect()
+        };
+        self.objects = trimmed(self.objects);
+        self.text = trimmed(self.text);
+        self.dominant_colors.retain(|c| !c.name.trim().is_empty());
+        for color in &mut self.dominant_colors {
+            color.name = color.name.trim().to_string();
+            color.hex = normalize_hex(&color.hex);
+        }
+        self
+    }
+}
+
+/// A box of four 0-1000 coordinates with the minimum before the maximum
+fn valid_box(bounding_box: &[u32]) -> bool {
+    matches!(bounding_box, [ymin, xmin, ymax, xmax]
+        if ymin <= ymax && xmin <= xmax && *ymax <= 1000 && *xmax <= 1000)
+}
+
+impl OcrResult {
+    /// Trim blocks, drop empty ones and malformed boxes, and keep pages in
+    /// order without reordering blocks within a page
+    fn normalized(mut self) -> Self {
+        self.blocks.retain(|block| !block.text.trim().is_empty());
+        for block in &mut self.blocks {
+            block.text = block.text.trim().to_string();
+            block.page = block.page.max(1);
+            if block.bounding_box.as_deref().is_some_and(|b| !valid_box(b)) {
+                block.bounding_box = None;
+            }
+        }
+        self.blocks.sort_by_key(|block| block.page);
+        self
+    }
+
+    fn pages(&self) -> Vec<(u32, Vec<&TextBlock>)> {
+        let mut pages: Vec<(u32, Vec<&TextBlock>)> = Vec::new();
+        for block in &self.blocks {
+            match pages.last_mut() {
+                Some((page, blocks)) if *page == block.page => blocks.push(block),
+                _ => pages.push((block.page, vec![block])),
+            }
+        }
+        pages
+    }
+
+    /// Plain text with a blank line between blocks, and a `--- Page N ---`
+    /// line before each page when there is more than one
+    pub fn to_text(&self) -> String {
+        let pages = self.pages();
+        let mut out = String::new();
+        for (page, blocks) in &pages {
+            if pages.len() > 1 {
+                out.push_str(&format!("--- Page {} ---\n\n", page));
+            }
+            for block in blocks {
+                out.push_str(&block.text);
+                out.push_str("\n\n");
+            }
+        }
+        out.truncate(out.trim_end().len());
+        out.push('\n');
+        out
+    }
+
+    /// Markdown with headings, list items and italic captions, and a rule
+    /// between pages
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        for (i, (_, blocks)) in self.pages().iter().enumerate() {
+            if i > 0 {
+                out.push_str("---\n\n");
+            }
+            for block in blocks {
+                let text = match block.kind {
+                    BlockKind::Heading => {
+                        format!("## {}", block.text.trim_start_matches('#').trim())
+                    }
+                    BlockKind::ListItem if !starts_with_list_marker(&block.text) => {
+                        format!("- {}", block.text)
+                    }
+                    BlockKind::Caption => format!("*{}*", block.text),
+                    _ => block.text.clone(),
+                };
+                out.push_str(&text);
+                out.push_str("\n\n");
+            }
+        }
+        out.truncate(out.trim_end().len());
+        out.push('\n');
+        out
+    }
+}
+
+/// `- `, `* `, `• ` or `1.` / `1)` at the start of a list item
+fn starts_with_list_marker(text: &str) -> bool {
+    if ["- ", "* ", "• "]
+        .iter()
+        .any(|marker| text.starts_with(marker))
+    {
+        return true;
+    }
+    let digits = text.chars().take_while(char::is_ascii_digit).count();
+    digits > 0 && matches!(text[digits..].chars().next(), Some('.' | ')'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_mime_type() {
+        assert_eq!(
+            image_mime_type(Path::new("a/photo.JPG")),
+            Some("image/jpeg")
+        );
+        assert_eq!(image_mime_type(Path::new("scan.webp")), Some("image/webp"));
+        assert_eq!(image_mime_type(Path::new("notes.txt")), None);
+        assert_eq!(image_mime_type(Path::new("README")), None);
+        assert_eq!(
+            document_mime_type(Path::new("scan.PDF")),
+            Some("application/pdf")
+        );
+        assert_eq!(document_mime_type(Path::new("page.png")), Some("image/png"));
+    }
+
+    fn frames(times: &[f64]) -> Vec<Frame> {
+        times
+            .iter()
+            .map(|&time_secs| Frame {
+                time_secs,
+                image: MediaPart::Inline {
+                    mime_type: "image/jpeg".to_string(),
+                    data: Vec::new(),
+                },
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_scenes_from_answer() {
+        let answer: ScenesAnswer = serde_json::from_value(json!({
+            "scenes": [
+                {"first_frame": 4, "description": " Slides. ", "on_screen_text": ["Q3 plan", " "]},
+                {"first_frame": 2, "description": "Speaker at a desk.", "on_screen_text": []},
+                {"first_frame": 9, "description": "Out of range.", "on_screen_text": []}
+            ]
+        }))
+        .unwrap();
+        let frames = frames(&[0.0, 12.5, 30.0, 41.0, 55.0]);
+        let scenes = scenes_from_answer(answer.scenes, &frames, Some(83.4));
+        assert_eq!(scenes.len(), 2);
+        assert_eq!((scenes[0].start_secs, scenes[0].end_secs), (0.0, 41.0));
+        assert_eq!((scenes[1].start_secs, scenes[1].end_secs), (41.0, 83.4));
+        assert_eq!(scenes[1].description, "Slides.");
+        assert_eq!(scenes[1].on_screen_text, ["Q3 plan"]);
+
+        let summary = SceneSummary {
+            duration_secs: Some(83.4),
+            scenes,
+        };
+        assert_eq!(
+            summary.to_text(),
+            "[00:00 - 00:41] Speaker at a desk.\n[00:41 - 01:23] Slides.\n    Text: \"Q3 plan\"\n"
+        );
+    }
+
+    #[test]
+    fn test_scenes_prompt_lists_frame_times() {
+        let vision = VisionClient::new(TextClient::new("key".to_string()).unwrap());
+        let prompt = vision.scenes_prompt(
+            &frames(&[0.0, 3725.0]),
+            None,
+            Some("[00:01] Host: Welcome.\n"),
+        );
+        assert!(prompt.contains("Frames:\n1: 00:00\n2: 01:02:05\n"));
+        assert!(prompt.ends_with("context:\n[00:01] Host: Welcome.\n"));
+    }
+
+    fn ocr_result() -> OcrResult {
+        serde_json::from_value::<OcrResult>(json!({
+            "blocks": [
+                {"page": 2, "kind": "paragraph", "text": "Second page."},
+                {"page": 1, "kind": "heading", "text": "# Report ", "bounding_box": [10, 20, 40, 980]},
+                {"page": 1, "kind": "list_item", "text": "Apples", "bounding_box": [50, 900, 60, 100]},
+                {"page": 1, "kind": "list_item", "text": "2) Pears"},
+                {"page": 1, "kind": "caption", "text": "Figure 1"},
+                {"page": 1, "kind": "footnote", "text": "  "}
+            ]
+        }))
+        .unwrap()
+        .normalized()
+    }
+
+    #[test]
+    fn test_ocr_normalized() {
+        let result = ocr_result();
+        assert_eq!(result.blocks.len(), 5);
+        assert_eq!(result.blocks[0].text, "# Report");
+        assert_eq!(result.blocks[0].bounding_box, Some(vec![10, 20, 40, 980]));
+        assert_eq!(result.blocks[1].bounding_box, None);
+        assert_eq!(result.blocks[4].page, 2);
+    }
+
+    #[test]
+    fn test_ocr_to_text_and_markdown() {
+        let result = ocr_result();
+        assert_eq!(
+            result.to_text(),
+            "--- Page 1 ---\n\n# Report\n\nApples\n\n2) Pears\n\nFigure 1\n\n\
+             --- Page 2 ---\n\nSecond page.\n"
+        );
+        assert_eq!(
+            result.to_markdown(),
+            "## Report\n\n- Apples\n\n2) Pears\n\n*Figure 1*\n\n---\n\nSecond page.\n"
+        );
+    }
+
+    #[test]
+    fn test_normalized() {
+        let description: ImageDescription = serde_json::from_value(json!({
+            "caption": " A shop front at night. ",
+            "objects": ["door", " ", "neon sign "],
+            "dominant_colors": [
+                {"name": "navy", "hex": "1a2b3c"},
+                {"name": "pink", "hex": "hot"},
+                {"name": " ", "hex": "#FFFFFF"}
+            ],
+            "text": ["OPEN", ""]
+        }))
+        .unwrap();
+        let description = description.normalized();
+        assert_eq!(description.caption, "A shop front at night.");
+        assert_eq!(description.objects, ["door", "neon sign"]);
+        assert_eq!(description.dominant_colors.len(), 2);
+        assert_eq!(description.dominant_colors[0].hex, "#1A2B3C");
+        assert_eq!(description.dominant_colors[1].hex, "");
+        assert_eq!(description.text, ["OPEN"]);
+    }
+}
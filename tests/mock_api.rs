@@ -0,0 +1,2493 @@
+//! Client tests against the in-process mock server.
+//! Run with `cargo test --features testing`.
+#![cfg(feature = "testing")]
+
+use std::time::Duration;
+
+use transcript_tool::gemini_api::AudioSource;
+use transcript_tool::storage::{GcsAuth, GcsStorage, S3Config, S3Storage, Storage};
+use transcript_tool::testing::vcr::Vcr;
+use transcript_tool::testing::{MockResponse, MockServer, fixtures};
+use transcript_tool::{
+    AuthMode, Budget, BudgetExceeded, CircuitBreaker, Clients, ErrorKind, FileApiClient,
+    FileApiError, GeminiClient, GeminiClientConfig, GeminiError, ImageEditClient,
+    ImageEditClientConfig, ImageModel, ImagenClient, ImagenClientConfig, InputImage, MediaRange,
+    OpenAiClient, OpenAiClientConfig, ProxyConfig, ResponseCache, TlsConfig, TokenUsage,
+    TranscriptResponse, TransportConfig, VeoClient, VeoClientConfig, VeoError, VideoAspectRatio,
+    VideoGenConfig, VideoModel,
+};
+use transcript_tool::{DialogueLine, SpeechAudio, SpeechConfig, TtsClient, TtsClientConfig};
+use transcript_tool::{
+    EmbedConfig, EmbeddingsClient, EmbeddingsClientConfig, SearchIndex, TaskType,
+};
+use transcript_tool::{Frame, MediaPart, VisionClient};
+use transcript_tool::{
+    MergePart, MinutesExtractor, TextClient, TextClientConfig, TextError, TextGenConfig, TextModel,
+    TranscriptCleanup, TranscriptNormalizer, TranscriptQa, Translator, merge_transcripts,
+};
+use transcript_tool::{MusicClient, MusicClientConfig, MusicGenConfig};
+
+const API_KEY: &str = "test-key";
+const TRANSCRIBE_PATH: &str = "/v1beta/models/gemini-2.5-flash:generateContent";
+
+fn gemini_client(server: &MockServer, max_retries: u32) -> GeminiClient {
+    let config = GeminiClientConfig {
+        timeout_secs: 10,
+        max_retries,
+        base_url: server.uri(),
+        ..Default::default()
+    };
+    GeminiClient::with_config(API_KEY.to_string(), config).unwrap()
+}
+
+fn file_api_client(server: &MockServer) -> FileApiClient {
+    let gemini = gemini_client(server, 1);
+    FileApiClient::new(gemini.http_client().clone(), API_KEY.to_string())
+        .with_base_url(gemini.base_url())
+}
+
+#[tokio::test]
+async fn test_transcribe_audio_parses_structured_output() {
+    let server = MockServer::start().await;
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(
+            200,
+            &fixtures::gemini_transcript_response(&fixtures::transcript()),
+        ),
+    );
+
+    let transcript = gemini_client(&server, 3)
+        .transcribe_audio(b"fake audio", "audio/mpeg")
+        .await
+        .unwrap();
+
+    assert_eq!(transcript.summary, "Two people greet each other.");
+    assert_eq!(transcript.segments.len(), 2);
+    assert_eq!(transcript.segments[1].language_code, "fr");
+    assert_eq!(
+        transcript.segments[1].translation.as_deref(),
+        Some("Hello!")
+    );
+
+    let requests = server.received_requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].header("x-goog-api-key"), Some("test-key"));
+    assert_eq!(requests[0].query, None);
+    let body = requests[0].json().unwrap();
+    let inline = &body["contents"][0]["parts"][1]["inline_data"];
+    assert_eq!(inline["mime_type"], "audio/mpeg");
+    assert_eq!(inline["data"], "ZmFrZSBhdWRpbw==");
+}
+
+#[tokio::test]
+async fn test_transcribe_retries_server_errors() {
+    let server = MockServer::start().await;
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(500, &fixtures::api_error(500, "internal")),
+    );
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(
+            200,
+            &fixtures::gemini_transcript_response(&fixtures::transcript()),
+        ),
+    );
+
+    let transcript = gemini_client(&server, 3)
+        .transcribe_audio(b"audio", "audio/mpeg")
+        .await
+        .unwrap();
+
+    assert_eq!(transcript.segments.len(), 2);
+    assert_eq!(server.received_requests().len(), 2);
+}
+
+#[tokio::test]
+async fn test_transcribe_does_not_retry_client_errors() {
+    let server = MockServer::start().await;
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(400, &fixtures::api_error(400, "bad request")),
+    );
+
+    let err = gemini_client(&server, 3)
+        .transcribe_audio(b"audio", "audio/mpeg")
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, GeminiError::ApiError { status: 400, .. }));
+    assert_eq!(server.received_requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_transcribe_rate_limited_without_retries_left() {
+    let server = MockServer::start().await;
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(429, &fixtures::api_error(429, "quota")),
+    );
+
+    let err = gemini_client(&server, 1)
+        .transcribe_audio(b"audio", "audio/mpeg")
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, GeminiError::RateLimited { .. }));
+}
+
+#[tokio::test]
+async fn test_identical_transcription_served_from_cache() {
+    let server = MockServer::start().await;
+    for _ in 0..2 {
+        server.mock(
+            "POST",
+            TRANSCRIBE_PATH,
+            MockResponse::json(
+                200,
+                &fixtures::gemini_transcript_response(&fixtures::transcript()),
+            ),
+        );
+    }
+
+    let cache_dir =
+        std::env::temp_dir().join(format!("flashecho_mock_cache_{}", std::process::id()));
+    let config = GeminiClientConfig {
+        timeout_secs: 10,
+        base_url: server.uri(),
+        cache: Some(ResponseCache::new(&cache_dir, Duration::from_secs(60))),
+        ..Default::default()
+    };
+    let client = GeminiClient::with_config(API_KEY.to_string(), config).unwrap();
+
+    let first = client
+        .transcribe_audio_with_usage(b"audio", "audio/mpeg")
+        .await
+        .unwrap();
+    assert_eq!(first.usage.input_tokens, 1200);
+    assert_eq!(first.usage.output_tokens, 150);
+    // A cached answer costs nothing
+    let second = client
+        .transcribe_audio_with_usage(b"audio", "audio/mpeg")
+        .await
+        .unwrap();
+    assert_eq!(second.usage, TokenUsage::default());
+    assert_eq!(second.value.summary, first.value.summary);
+    assert_eq!(second.value.segments.len(), 2);
+    assert_eq!(server.received_requests().len(), 1);
+
+    client
+        .transcribe_audio(b"other audio", "audio/mpeg")
+        .await
+        .unwrap();
+    assert_eq!(server.received_requests().len(), 2);
+
+    std::fs::remove_dir_all(&cache_dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_circuit_breaker_shared_across_clients() {
+    let server = MockServer::start().await;
+    for _ in 0..3 {
+        server.mock(
+            "POST",
+            TRANSCRIBE_PATH,
+            MockResponse::json(503, &fixtures::api_error(503, "unavailable")),
+        );
+    }
+
+    let breaker = CircuitBreaker::new(2);
+    let client = |breaker: &CircuitBreaker| {
+        let config = GeminiClientConfig {
+            timeout_secs: 10,
+            max_retries: 3,
+            base_url: server.uri(),
+            circuit_breaker: Some(breaker.clone()),
+            ..Default::default()
+        };
+        GeminiClient::with_config(API_KEY.to_string(), config).unwrap()
+    };
+
+    let err = client(&breaker)
+        .transcribe_audio(b"audio", "audio/mpeg")
+        .await
+        .unwrap_err();
+    assert_eq!(err.status(), Some(503));
+    assert!(breaker.is_open());
+
+    let err = client(&breaker)
+        .transcribe_audio(b"audio", "audio/mpeg")
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::CircuitOpen);
+    assert!(matches!(err, GeminiError::CircuitOpen(_)));
+    assert_eq!(server.received_requests().len(), 2);
+}
+
+#[tokio::test]
+async fn test_budget_stops_requests_once_spent() {
+    let server = MockServer::start().await;
+    for _ in 0..2 {
+        server.mock(
+            "POST",
+            TRANSCRIBE_PATH,
+            MockResponse::json(
+                200,
+                &fixtures::gemini_transcript_response(&fixtures::transcript()),
+            ),
+        );
+    }
+
+    // One response uses 1350 tokens
+    let budget = Budget::new().with_max_tokens(1000);
+    let config = GeminiClientConfig {
+        timeout_secs: 10,
+        max_retries: 1,
+        base_url: server.uri(),
+        budget: Some(budget.clone()),
+        ..Default::default()
+    };
+    let client = GeminiClient::with_config(API_KEY.to_string(), config).unwrap();
+
+    client
+        .transcribe_audio(b"audio", "audio/mpeg")
+        .await
+        .unwrap();
+    assert_eq!(budget.spent_tokens(), 1350);
+    assert!(budget.spent_usd() > 0.0);
+
+    let err = client
+        .transcribe_audio(b"other audio", "audio/mpeg")
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::BudgetExceeded);
+    assert!(matches!(
+        err,
+        GeminiError::BudgetExceeded(BudgetExceeded::Tokens { .. })
+    ));
+    assert_eq!(server.received_requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_query_string_auth_mode() {
+    let server = MockServer::start().await;
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(
+            200,
+            &fixtures::gemini_transcript_response(&fixtures::transcript()),
+        ),
+    );
+    server.mock(
+        "DELETE",
+        "/v1beta/files/abc",
+        MockResponse::json(200, &serde_json::json!({})),
+    );
+
+    let client = GeminiClient::with_config(
+        API_KEY.to_string(),
+        GeminiClientConfig {
+            base_url: server.uri(),
+            auth_mode: AuthMode::QueryString,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    client
+        .transcribe_audio(b"audio", "audio/mpeg")
+        .await
+        .unwrap();
+    FileApiClient::new(client.http_client().clone(), API_KEY.to_string())
+        .with_base_url(client.base_url())
+        .with_auth_mode(client.auth_mode())
+        .delete_file("files/abc")
+        .await
+        .unwrap();
+
+    for request in server.received_requests() {
+        assert_eq!(request.query.as_deref(), Some("key=test-key"));
+        assert_eq!(request.header("x-goog-api-key"), None);
+    }
+}
+
+#[tokio::test]
+async fn test_requests_go_through_custom_proxy() {
+    // The mock server plays the proxy: plain-HTTP proxying sends the
+    // absolute URL as the request target
+    let proxy = MockServer::start().await;
+    proxy.mock(
+        "POST",
+        &format!("http://gemini.invalid{}", TRANSCRIBE_PATH),
+        MockResponse::json(
+            200,
+            &fixtures::gemini_transcript_response(&fixtures::transcript()),
+        ),
+    );
+
+    let client = GeminiClient::with_config(
+        API_KEY.to_string(),
+        GeminiClientConfig {
+            base_url: "http://gemini.invalid".to_string(),
+            proxy: ProxyConfig::custom(proxy.uri()),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let transcript = client
+        .transcribe_audio(b"audio", "audio/mpeg")
+        .await
+        .unwrap();
+
+    assert_eq!(transcript.segments.len(), 2);
+    assert_eq!(proxy.received_requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_tls_customize_hook_applied() {
+    let server = MockServer::start().await;
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(
+            200,
+            &fixtures::gemini_transcript_response(&fixtures::transcript()),
+        ),
+    );
+
+    let client = GeminiClient::with_config(
+        API_KEY.to_string(),
+        GeminiClientConfig {
+            base_url: server.uri(),
+            tls: TlsConfig::default().with_customize(|builder| builder.user_agent("pinned/1.0")),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    client
+        .transcribe_audio(b"audio", "audio/mpeg")
+        .await
+        .unwrap();
+
+    assert_eq!(
+        server.received_requests()[0].header("user-agent"),
+        Some("pinned/1.0")
+    );
+}
+
+#[tokio::test]
+async fn test_custom_transport_config_applied() {
+    let server = MockServer::start().await;
+    for _ in 0..2 {
+        server.mock(
+            "POST",
+            TRANSCRIBE_PATH,
+            MockResponse::json(
+                200,
+                &fixtures::gemini_transcript_response(&fixtures::transcript()),
+            ),
+        );
+    }
+
+    let transport = TransportConfig::default()
+        .with_pool_max_idle_per_host(1)
+        .with_tcp_keepalive_secs(None)
+        .with_http2_keep_alive(None, 5);
+    let client = GeminiClient::with_config(
+        API_KEY.to_string(),
+        GeminiClientConfig {
+            base_url: server.uri(),
+            transport,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    for _ in 0..2 {
+        client
+            .transcribe_audio(b"audio", "audio/mpeg")
+            .await
+            .unwrap();
+    }
+
+    assert_eq!(server.received_requests().len(), 2);
+}
+
+#[tokio::test]
+async fn test_gzip_response_decompressed() {
+    use flate2::{Compression, write::GzEncoder};
+    use std::io::Write;
+
+    let body = fixtures::gemini_transcript_response(&fixtures::transcript()).to_string();
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(body.as_bytes()).unwrap();
+    let server = MockServer::start().await;
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::new(200)
+            .with_header("Content-Type", "application/json")
+            .with_header("Content-Encoding", "gzip")
+            .with_body(encoder.finish().unwrap()),
+    );
+
+    let transcript = gemini_client(&server, 1)
+        .transcribe_audio(b"audio", "audio/mpeg")
+        .await
+        .unwrap();
+    assert_eq!(transcript.segments[0].content, "Hello there.");
+    let accept = server.received_requests()[0]
+        .header("accept-encoding")
+        .unwrap()
+        .to_string();
+    assert!(accept.contains("gzip") && accept.contains("br"));
+}
+
+#[tokio::test]
+async fn test_api_key_redacted_from_errors_and_debug() {
+    // Stop the server so the request fails with a connection error
+    let server = MockServer::start().await;
+    let base_url = server.uri();
+    drop(server);
+    tokio::task::yield_now().await;
+    let client = GeminiClient::with_config(
+        API_KEY.to_string(),
+        GeminiClientConfig {
+            max_retries: 1,
+            base_url,
+            // Only query-string auth puts the key in the URL reqwest reports
+            auth_mode: AuthMode::QueryString,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let err = client
+        .transcribe_audio(b"audio", "audio/mpeg")
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, GeminiError::NetworkError(_)));
+    let output = format!("{} {:?} {:?}", err, err, client);
+    assert!(output.contains("key=REDACTED"), "{output}");
+    assert!(!output.contains(API_KEY), "{output}");
+}
+
+#[tokio::test]
+async fn test_transcribe_rejects_response_without_text() {
+    let server = MockServer::start().await;
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(200, &serde_json::json!({"candidates": []})),
+    );
+
+    let err = gemini_client(&server, 1)
+        .transcribe_audio(b"audio", "audio/mpeg")
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, GeminiError::InvalidResponse(_)));
+}
+
+#[tokio::test]
+async fn test_transcribe_repairs_truncated_answer() {
+    let server = MockServer::start().await;
+    // Cut off inside the second segment
+    let cut = r#"{"summary": "Hi", "segments": [{"speaker": "Speaker 1", "timestamp": "00:00",
+        "content": "Hello.", "language": "English", "language_code": "en", "emotion": "happy"},
+        {"speaker": "Speaker 2", "timestamp": "00:03", "content": "Bonj"#;
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(200, &fixtures::text_response(&format!("```json\n{}", cut))),
+    );
+
+    let transcript = gemini_client(&server, 1)
+        .transcribe_audio(b"audio", "audio/mpeg")
+        .await
+        .unwrap();
+
+    assert_eq!(transcript.segments.len(), 1);
+    assert_eq!(server.received_requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_transcribe_reasks_once_after_unparsable_answer() {
+    let server = MockServer::start().await;
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(200, &fixtures::text_response(r#"{"summary": 42}"#)),
+    );
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(
+            200,
+            &fixtures::gemini_transcript_response(&fixtures::transcript()),
+        ),
+    );
+
+    let response = gemini_client(&server, 1)
+        .transcribe_audio_with_usage(b"audio", "audio/mpeg")
+        .await
+        .unwrap();
+
+    assert_eq!(response.value.segments.len(), 2);
+    // Both answers were paid for
+    assert_eq!(response.usage.input_tokens, 8 + 1200);
+    assert_eq!(response.usage.output_tokens, 5 + 150);
+    let requests = server.received_requests();
+    assert_eq!(requests.len(), 2);
+    let contents = &requests[1].json().unwrap()["contents"];
+    assert_eq!(contents[1]["role"], "model");
+    assert_eq!(contents[1]["parts"][0]["text"], r#"{"summary": 42}"#);
+    let follow_up = contents[2]["parts"][0]["text"].as_str().unwrap();
+    assert!(follow_up.contains("not valid JSON"), "{follow_up}");
+}
+
+#[tokio::test]
+async fn test_transcribe_gives_up_after_one_reask() {
+    let server = MockServer::start().await;
+    for _ in 0..2 {
+        server.mock(
+            "POST",
+            TRANSCRIBE_PATH,
+            MockResponse::json(200, &fixtures::text_response("Sorry, no.")),
+        );
+    }
+
+    let err = gemini_client(&server, 3)
+        .transcribe_audio(b"audio", "audio/mpeg")
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, GeminiError::JsonError(_)));
+    assert_eq!(server.received_requests().len(), 2);
+}
+
+#[tokio::test]
+async fn test_transcribe_file_uri_sends_file_data() {
+    let server = MockServer::start().await;
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(
+            200,
+            &fixtures::gemini_transcript_response(&fixtures::transcript()),
+        ),
+    );
+
+    gemini_client(&server, 1)
+        .transcribe_file_uri("https://example.com/files/abc", "audio/mpeg")
+        .await
+        .unwrap();
+
+    let body = server.received_requests()[0].json().unwrap();
+    assert_eq!(
+        body["contents"][0]["parts"][1]["file_data"]["file_uri"],
+        "https://example.com/files/abc"
+    );
+}
+
+#[tokio::test]
+async fn test_transcribe_sends_system_instruction() {
+    let server = MockServer::start().await;
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(
+            200,
+            &fixtures::gemini_transcript_response(&fixtures::transcript()),
+        ),
+    );
+
+    let config = GeminiClientConfig {
+        timeout_secs: 10,
+        base_url: server.uri(),
+        system_instruction: Some("Never summarize profanity.".to_string()),
+        ..Default::default()
+    };
+    let client = GeminiClient::with_config(API_KEY.to_string(), config).unwrap();
+    client
+        .transcribe_audio(b"fake audio", "audio/mpeg")
+        .await
+        .unwrap();
+    client
+        .transcribe_file_uri("https://example.com/files/abc", "audio/mpeg")
+        .await
+        .unwrap();
+
+    for request in server.received_requests() {
+        let body = request.json().unwrap();
+        assert_eq!(
+            body["systemInstruction"]["parts"][0]["text"],
+            "Never summarize profanity."
+        );
+        assert!(body["generation_config"]["response_schema"].is_object());
+    }
+}
+
+#[tokio::test]
+async fn test_transcription_token_count_retries_and_parses() {
+    let server = MockServer::start().await;
+    let path = "/v1beta/models/gemini-2.5-flash:countTokens";
+    server.mock(
+        "POST",
+        path,
+        MockResponse::json(500, &fixtures::api_error(500, "internal")),
+    );
+    server.mock(
+        "POST",
+        path,
+        MockResponse::json(
+            200,
+            &serde_json::json!({
+                "totalTokens": 1930,
+                "promptTokensDetails": [
+                    {"modality": "TEXT", "tokenCount": 250},
+                    {"modality": "AUDIO", "tokenCount": 1680}
+                ]
+            }),
+        ),
+    );
+
+    let source = AudioSource::FileUri {
+        mime_type: "audio/mpeg".to_string(),
+        uri: "https://example.com/files/abc".to_string(),
+    };
+    let count = gemini_client(&server, 3)
+        .count_tokens(&source)
+        .await
+        .unwrap();
+
+    assert_eq!(count.total_tokens, 1930);
+    assert_eq!(count.modality("audio"), 1680);
+    let requests = server.received_requests();
+    assert_eq!(requests.len(), 2);
+    let body = requests[1].json().unwrap();
+    let parts = &body["contents"][0]["parts"];
+    assert!(
+        parts[0]["text"]
+            .as_str()
+            .unwrap()
+            .contains("detailed transcription")
+    );
+    assert_eq!(
+        parts[1]["file_data"]["file_uri"],
+        "https://example.com/files/abc"
+    );
+    assert!(body.get("generation_config").is_none());
+}
+
+#[tokio::test]
+async fn test_transcribe_with_schema_returns_raw_answer() {
+    let server = MockServer::start().await;
+    let answer = serde_json::json!({"topic": "Quarterly results", "speakers": 2});
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(200, &fixtures::gemini_transcript_response(&answer)),
+    );
+
+    let schema = serde_json::json!({
+        "type": "OBJECT",
+        "properties": {
+            "topic": { "type": "STRING" },
+            "speakers": { "type": "INTEGER" }
+        }
+    });
+    let config = GeminiClientConfig {
+        timeout_secs: 10,
+        base_url: server.uri(),
+        response_schema: Some(schema.clone()),
+        ..Default::default()
+    };
+    let source = AudioSource::Inline {
+        mime_type: "audio/mpeg".to_string(),
+        data: b"fake audio".to_vec(),
+    };
+    let value = GeminiClient::with_config(API_KEY.to_string(), config)
+        .unwrap()
+        .transcribe_with_schema(&source)
+        .await
+        .unwrap();
+
+    assert_eq!(value, answer);
+    let body = server.received_requests()[0].json().unwrap();
+    assert_eq!(body["generation_config"]["response_schema"], schema);
+}
+
+#[tokio::test]
+async fn test_transcribe_file_uri_range_sends_offsets_and_shifts_timestamps() {
+    let server = MockServer::start().await;
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(
+            200,
+            &fixtures::gemini_transcript_response(&fixtures::transcript()),
+        ),
+    );
+
+    let range = MediaRange {
+        start_secs: Some(90),
+        end_secs: Some(150),
+    };
+    let transcript = gemini_client(&server, 1)
+        .transcribe_file_uri_range("https://example.com/files/abc", "audio/mpeg", range)
+        .await
+        .unwrap();
+
+    let body = server.received_requests()[0].json().unwrap();
+    let metadata = &body["contents"][0]["parts"][1]["video_metadata"];
+    assert_eq!(metadata["start_offset"], "90s");
+    assert_eq!(metadata["end_offset"], "150s");
+    // Timestamps count from the start of the file, not of the range
+    assert_eq!(transcript.segments[0].timestamp.to_string(), "01:30");
+    assert_eq!(transcript.segments[1].timestamp.to_string(), "01:33");
+}
+
+#[tokio::test]
+async fn test_transcribe_with_topics_requests_and_parses_them() {
+    let server = MockServer::start().await;
+    let mut answer = fixtures::transcript();
+    answer["topics"] = serde_json::json!([{"name": "Greetings", "timestamp": "00:00"}]);
+    answer["keywords"] = serde_json::json!([{"name": "Bonjour", "timestamp": "00:03"}]);
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(200, &fixtures::gemini_transcript_response(&answer)),
+    );
+
+    let config = GeminiClientConfig {
+        timeout_secs: 10,
+        base_url: server.uri(),
+        topics: true,
+        ..Default::default()
+    };
+    let transcript = GeminiClient::with_config(API_KEY.to_string(), config)
+        .unwrap()
+        .transcribe_audio(b"fake audio", "audio/mpeg")
+        .await
+        .unwrap();
+
+    assert_eq!(transcript.topics[0].name, "Greetings");
+    assert_eq!(
+        transcript.keywords[0]
+            .timestamp
+            .map(|t| t.to_string())
+            .as_deref(),
+        Some("00:03")
+    );
+    let body = server.received_requests()[0].json().unwrap();
+    let schema = &body["generation_config"]["response_schema"];
+    assert_eq!(schema["properties"]["topics"]["type"], "ARRAY");
+}
+
+#[tokio::test]
+async fn test_transcribe_sends_custom_prompt() {
+    let server = MockServer::start().await;
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(
+            200,
+            &fixtures::gemini_transcript_response(&fixtures::transcript()),
+        ),
+    );
+
+    let config = GeminiClientConfig {
+        timeout_secs: 10,
+        base_url: server.uri(),
+        prompt: Some("Transcribe this cardiology consult.".to_string()),
+        ..Default::default()
+    };
+    GeminiClient::with_config(API_KEY.to_string(), config)
+        .unwrap()
+        .transcribe_audio(b"fake audio", "audio/mpeg")
+        .await
+        .unwrap();
+
+    let body = server.received_requests()[0].json().unwrap();
+    assert_eq!(
+        body["contents"][0]["parts"][0]["text"],
+        "Transcribe this cardiology consult."
+    );
+    assert!(body["generation_config"]["response_schema"].is_object());
+}
+
+#[tokio::test]
+async fn test_rediarize_relabels_speakers() {
+    let server = MockServer::start().await;
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(
+            200,
+            &fixtures::gemini_transcript_response(&serde_json::json!({
+                "speakers": ["Speaker 1", "Speaker 1"]
+            })),
+        ),
+    );
+
+    let transcript: TranscriptResponse = serde_json::from_value(fixtures::transcript()).unwrap();
+    let source = AudioSource::Inline {
+        mime_type: "audio/mpeg".to_string(),
+        data: b"fake audio".to_vec(),
+    };
+    let updated = gemini_client(&server, 1)
+        .rediarize(&source, &transcript, Some("One person"))
+        .await
+        .unwrap();
+
+    assert!(updated.segments.iter().all(|s| s.speaker == "Speaker 1"));
+    assert_eq!(updated.segments[1].content, "Bonjour !");
+    assert_eq!(updated.summary, transcript.summary);
+
+    let body = server.received_requests()[0].json().unwrap();
+    let prompt = body["contents"][0]["parts"][0]["text"].as_str().unwrap();
+    assert!(prompt.contains("Context about the speakers: One person"));
+    assert_eq!(
+        body["contents"][0]["parts"][1]["inline_data"]["mime_type"],
+        "audio/mpeg"
+    );
+}
+
+#[tokio::test]
+async fn test_file_upload_flow() {
+    let server = MockServer::start().await;
+    let upload_url = format!("{}/upload-session/1", server.uri());
+    server.mock(
+        "POST",
+        "/upload/v1beta/files",
+        MockResponse::new(200).with_header("X-Goog-Upload-URL", &upload_url),
+    );
+    server.mock(
+        "POST",
+        "/upload-session/1",
+        MockResponse::json(200, &fixtures::upload_response("files/abc", "PROCESSING")),
+    );
+    server.mock(
+        "GET",
+        "/v1beta/files/abc",
+        MockResponse::json(200, &fixtures::file_info("files/abc", "ACTIVE")),
+    );
+
+    let info = file_api_client(&server)
+        .upload_file(b"audio bytes".as_slice(), "audio/mpeg", "audio.mp3")
+        .await
+        .unwrap();
+
+    assert_eq!(info.name, "files/abc");
+    assert_eq!(info.state, "ACTIVE");
+
+    let requests = server.received_requests();
+    assert_eq!(requests.len(), 3);
+    assert_eq!(requests[0].header("x-goog-upload-command"), Some("start"));
+    assert_eq!(
+        requests[0].header("x-goog-upload-header-content-length"),
+        Some("11")
+    );
+    assert_eq!(
+        requests[0].json().unwrap()["file"]["display_name"],
+        "audio.mp3"
+    );
+    assert_eq!(
+        requests[1].header("x-goog-upload-command"),
+        Some("upload, finalize")
+    );
+    assert_eq!(requests[1].body, b"audio bytes");
+    assert_eq!(requests[2].method, "GET");
+}
+
+#[tokio::test]
+async fn test_file_upload_missing_upload_url() {
+    let server = MockServer::start().await;
+    server.mock("POST", "/upload/v1beta/files", MockResponse::new(200));
+
+    let err = file_api_client(&server)
+        .upload_file(b"audio".as_slice(), "audio/mpeg", "audio.mp3")
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, FileApiError::MissingUploadUrl));
+}
+
+#[tokio::test]
+async fn test_delete_file() {
+    let server = MockServer::start().await;
+    server.mock(
+        "DELETE",
+        "/v1beta/files/abc",
+        MockResponse::json(200, &serde_json::json!({})),
+    );
+
+    file_api_client(&server)
+        .delete_file("files/abc")
+        .await
+        .unwrap();
+
+    let requests = server.received_requests();
+    assert_eq!(requests[0].method, "DELETE");
+    assert_eq!(requests[0].header("x-goog-api-key"), Some("test-key"));
+}
+
+#[tokio::test]
+async fn test_list_files_follows_pagination() {
+    let server = MockServer::start().await;
+    server.mock(
+        "GET",
+        "/v1beta/files",
+        MockResponse::json(
+            200,
+            &serde_json::json!({
+                "files": [fixtures::file_info("files/a", "ACTIVE")],
+                "nextPageToken": "page2"
+            }),
+        ),
+    );
+    server.mock(
+        "GET",
+        "/v1beta/files",
+        MockResponse::json(
+            200,
+            &serde_json::json!({ "files": [fixtures::file_info("files/b", "PROCESSING")] }),
+        ),
+    );
+
+    let files = file_api_client(&server).list_files().await.unwrap();
+    let names: Vec<_> = files.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(names, ["files/a", "files/b"]);
+
+    let requests = server.received_requests();
+    assert_eq!(requests.len(), 2);
+    assert!(
+        requests[1]
+            .query
+            .as_deref()
+            .unwrap()
+            .contains("pageToken=page2")
+    );
+}
+
+#[tokio::test]
+async fn test_generate_image() {
+    let server = MockServer::start().await;
+    let model = ImageModel::Gemini25Flash;
+    server.mock(
+        "POST",
+        &format!("/v1beta/models/{}:generateContent", model.api_model_name()),
+        MockResponse::json(200, &fixtures::image_response(b"PNGDATA", "image/png")),
+    );
+
+    let config = ImagenClientConfig {
+        model,
+        base_url: server.uri(),
+        ..Default::default()
+    };
+    let image = ImagenClient::with_config(API_KEY.to_string(), config)
+        .unwrap()
+        .generate_image("A sunset")
+        .await
+        .unwrap();
+
+    assert_eq!(image.data, b"PNGDATA");
+    assert_eq!(image.mime_type, "image/png");
+    let provenance = image.provenance.unwrap();
+    assert_eq!(
+        provenance.model_version.as_deref(),
+        Some("gemini-2.5-flash-image")
+    );
+    assert_eq!(
+        provenance.response_id.as_deref(),
+        Some("mock-image-response")
+    );
+    assert_eq!(provenance.watermark.as_deref(), Some("SynthID"));
+
+    let body = server.received_requests()[0].json().unwrap();
+    assert_eq!(body["contents"][0]["parts"][0]["text"], "A sunset");
+}
+
+#[tokio::test]
+async fn test_generate_video_polls_and_downloads() {
+    let server = MockServer::start().await;
+    let operation = "models/veo-3.0-fast-generate-001/operations/op1";
+    server.mock(
+        "POST",
+        "/v1beta/models/veo-3.0-fast-generate-001:predictLongRunning",
+        MockResponse::json(200, &fixtures::video_operation(operation)),
+    );
+    let poll_path = format!("/v1beta/{}", operation);
+    server.mock(
+        "GET",
+        &poll_path,
+        MockResponse::json(200, &fixtures::video_operation(operation)),
+    );
+    let video_uri = format!("{}/v1beta/files/vid1:download?alt=media", server.uri());
+    server.mock(
+        "GET",
+        &poll_path,
+        MockResponse::json(200, &fixtures::video_operation_done(operation, &video_uri)),
+    );
+    server.mock(
+        "GET",
+        "/v1beta/files/vid1:download",
+        MockResponse::new(200)
+            .with_header("Content-Type", "video/mp4")
+            .with_body(b"MP4DATA".to_vec()),
+    );
+
+    let config = VeoClientConfig {
+        model: VideoModel::Veo3Fast,
+        poll_interval_secs: 0,
+        base_url: server.uri(),
+        ..Default::default()
+    };
+    let gen_config = VideoGenConfig::new()
+        .with_aspect_ratio(VideoAspectRatio::Tall)
+        .with_duration_secs(6);
+    let video = VeoClient::with_config(API_KEY.to_string(), config)
+        .unwrap()
+        .generate_video_with_config("A forest", Some(&gen_config))
+        .await
+        .unwrap();
+
+    assert_eq!(video.data, b"MP4DATA");
+    assert_eq!(video.extension(), "mp4");
+
+    let requests = server.received_requests();
+    assert_eq!(requests.len(), 4);
+    let body = requests[0].json().unwrap();
+    assert_eq!(body["instances"][0]["prompt"], "A forest");
+    assert_eq!(body["parameters"]["aspectRatio"], "9:16");
+    assert_eq!(body["parameters"]["durationSeconds"], 6);
+    assert_eq!(requests[3].query.as_deref(), Some("alt=media"));
+    assert!(
+        requests
+            .iter()
+            .all(|r| r.header("x-goog-api-key") == Some(API_KEY))
+    );
+}
+
+#[tokio::test]
+async fn test_generate_video_operation_error() {
+    let server = MockServer::start().await;
+    let operation = "models/veo-3.0-generate-001/operations/op2";
+    server.mock(
+        "POST",
+        "/v1beta/models/veo-3.0-generate-001:predictLongRunning",
+        MockResponse::json(200, &fixtures::video_operation(operation)),
+    );
+    server.mock(
+        "GET",
+        &format!("/v1beta/{}", operation),
+        MockResponse::json(
+            200,
+            &serde_json::json!({
+                "name": operation,
+                "done": true,
+                "error": {"code": 3, "message": "Prompt was blocked"}
+            }),
+        ),
+    );
+
+    let config = VeoClientConfig {
+        poll_interval_secs: 0,
+        base_url: server.uri(),
+        ..Default::default()
+    };
+    let err = VeoClient::with_config(API_KEY.to_string(), config)
+        .unwrap()
+        .generate_video("A forest")
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, VeoError::OperationFailed { .. }));
+    assert!(err.to_string().contains("Prompt was blocked"));
+    assert_eq!(server.received_requests().len(), 2);
+}
+
+const TTS_PATH: &str = "/v1beta/models/gemini-2.5-flash-preview-tts:generateContent";
+
+fn tts_client(server: &MockServer) -> TtsClient {
+    let config = TtsClientConfig {
+        base_url: server.uri(),
+        ..Default::default()
+    };
+    TtsClient::with_config(API_KEY.to_string(), config).unwrap()
+}
+
+#[tokio::test]
+async fn test_synthesize_single_voice() {
+    let server = MockServer::start().await;
+    server.mock(
+        "POST",
+        TTS_PATH,
+        MockResponse::json(200, &fixtures::speech_response(&[0; 48_000])),
+    );
+
+    let speech = SpeechConfig::new()
+        .with_voice("puck")
+        .with_style("Say cheerfully");
+    let audio = tts_client(&server)
+        .synthesize_with_config("Hello there", &speech)
+        .await
+        .unwrap();
+
+    assert_eq!(audio.sample_rate, 24_000);
+    assert_eq!(audio.duration_secs(), 1.0);
+    assert_eq!(&audio.to_wav()[..4], b"RIFF");
+
+    let body = server.received_requests()[0].json().unwrap();
+    assert_eq!(
+        body["contents"][0]["parts"][0]["text"],
+        "Say cheerfully: Hello there"
+    );
+    assert_eq!(
+        body["generationConfig"]["speechConfig"]["voiceConfig"]["prebuiltVoiceConfig"]["voiceName"],
+        "Puck"
+    );
+}
+
+#[tokio::test]
+async fn test_synthesize_dialogue_splits_speakers() {
+    let server = MockServer::start().await;
+    for _ in 0..2 {
+        server.mock(
+            "POST",
+            TTS_PATH,
+            MockResponse::json(200, &fixtures::speech_response(&[1; 100])),
+        );
+    }
+
+    let lines = [
+        DialogueLine::new("Alice", "Hi Bob."),
+        DialogueLine::new("Bob", "Hi Alice."),
+        DialogueLine::new("Carol", "Hello both."),
+    ];
+    let speech = SpeechConfig::new().with_speaker_voice("Bob", "Charon");
+    let audio = tts_client(&server)
+        .synthesize_dialogue(&lines, &speech)
+        .await
+        .unwrap();
+    assert_eq!(audio.pcm.len(), 200);
+
+    let requests = server.received_requests();
+    assert_eq!(requests.len(), 2);
+
+    let first = requests[0].json().unwrap();
+    assert_eq!(
+        first["contents"][0]["parts"][0]["text"],
+        "Alice: Hi Bob.\nBob: Hi Alice."
+    );
+    let speakers = &first["generationConfig"]["speechConfig"]["multiSpeakerVoiceConfig"]["speakerVoiceConfigs"];
+    assert_eq!(speakers[0]["speaker"], "Alice");
+    assert_eq!(
+        speakers[1]["voiceConfig"]["prebuiltVoiceConfig"]["voiceName"],
+        "Charon"
+    );
+
+    // Carol alone in the second chunk is read without her name
+    let second = requests[1].json().unwrap();
+    assert_eq!(second["contents"][0]["parts"][0]["text"], "Hello both.");
+    assert!(second["generationConfig"]["speechConfig"]["voiceConfig"].is_object());
+}
+
+const MUSIC_PATH: &str = "/v1beta/models/lyria-002:predict";
+
+#[tokio::test]
+async fn test_generate_music_trims_clip() {
+    let server = MockServer::start().await;
+    let clip = SpeechAudio {
+        pcm: vec![0; 8_000 * 2 * 3],
+        sample_rate: 8_000,
+        channels: 1,
+    }
+    .to_wav();
+    server.mock("POST", MUSIC_PATH, MockResponse::new(500));
+    server.mock(
+        "POST",
+        MUSIC_PATH,
+        MockResponse::json(200, &fixtures::music_response(&clip)),
+    );
+
+    let config = MusicClientConfig {
+        base_url: server.uri(),
+        ..Default::default()
+    };
+    let gen_config = MusicGenConfig::new()
+        .with_genre("synthwave")
+        .with_bpm(100)
+        .with_negative_prompt("vocals")
+        .with_duration_secs(1);
+    let music = MusicClient::with_config(API_KEY.to_string(), config)
+        .unwrap()
+        .generate_music_with_config("Night drive", Some(&gen_config))
+        .await
+        .unwrap();
+
+    assert_eq!(music.extension(), "wav");
+    assert_eq!(music.duration_secs(), Some(1.0));
+
+    let requests = server.received_requests();
+    assert_eq!(requests.len(), 2);
+    let body = requests[1].json().unwrap();
+    assert_eq!(
+        body["instances"][0]["prompt"],
+        "Night drive. Genre: synthwave. Tempo: 100 BPM"
+    );
+    assert_eq!(body["instances"][0]["negative_prompt"], "vocals");
+}
+
+const BATCH_EMBED_PATH: &str = "/v1beta/models/gemini-embedding-001:batchEmbedContents";
+
+#[tokio::test]
+async fn test_embed_batch_splits_requests() {
+    let server = MockServer::start().await;
+    let first: Vec<&[f32]> = vec![&[1.0, 0.0]; 100];
+    server.mock(
+        "POST",
+        BATCH_EMBED_PATH,
+        MockResponse::json(200, &fixtures::embeddings_response(&first)),
+    );
+    server.mock(
+        "POST",
+        BATCH_EMBED_PATH,
+        MockResponse::json(200, &fixtures::embeddings_response(&[&[0.0, 1.0]])),
+    );
+
+    let config = EmbeddingsClientConfig {
+        base_url: server.uri(),
+        ..Default::default()
+    };
+    let texts: Vec<String> = (0..101).map(|i| format!("segment {}", i)).collect();
+    let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+    let embed_config = EmbedConfig::new()
+        .with_task_type(TaskType::RetrievalDocument)
+        .with_output_dimensionality(2);
+    let embeddings = EmbeddingsClient::with_config(API_KEY.to_string(), config)
+        .unwrap()
+        .embed_batch(&texts, Some(&embed_config))
+        .await
+        .unwrap();
+
+    assert_eq!(embeddings.len(), 101);
+    assert_eq!(embeddings[100].values, [0.0, 1.0]);
+
+    let requests = server.received_requests();
+    assert_eq!(requests.len(), 2);
+    let second = requests[1].json().unwrap();
+    let entry = &second["requests"][0];
+    assert_eq!(entry["model"], "models/gemini-embedding-001");
+    assert_eq!(entry["content"]["parts"][0]["text"], "segment 100");
+    assert_eq!(entry["taskType"], "RETRIEVAL_DOCUMENT");
+    assert_eq!(entry["outputDimensionality"], 2);
+}
+
+#[tokio::test]
+async fn test_text_generate_retries_server_error() {
+    let server = MockServer::start().await;
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(503, &serde_json::json!({"error": "unavailable"})),
+    );
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(200, &fixtures::text_response("Bonjour")),
+    );
+
+    let config = TextClientConfig {
+        base_url: server.uri(),
+        ..Default::default()
+    };
+    let client = TextClient::with_config(API_KEY.to_string(), config).unwrap();
+    let gen_config = TextGenConfig::new()
+        .with_system_instruction("Translate to French")
+        .with_temperature(0.0);
+    let text = client
+        .generate_with_config("Hello", Some(&gen_config))
+        .await
+        .unwrap();
+
+    assert_eq!(text, "Bonjour");
+    let requests = server.received_requests();
+    assert_eq!(requests.len(), 2);
+    let body = requests[1].json().unwrap();
+    assert_eq!(body["contents"][0]["parts"][0]["text"], "Hello");
+    assert_eq!(
+        body["systemInstruction"]["parts"][0]["text"],
+        "Translate to French"
+    );
+    assert!(matches!(
+        client.generate("  ").await,
+        Err(TextError::EmptyPrompt)
+    ));
+}
+
+#[tokio::test]
+async fn test_translator_batches_and_skips_empty_texts() {
+    let server = MockServer::start().await;
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(200, &fixtures::text_response(r#"["Bonjour", "Salut"]"#)),
+    );
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(200, &fixtures::text_response(r#"["Au revoir"]"#)),
+    );
+
+    let config = TextClientConfig {
+        base_url: server.uri(),
+        ..Default::default()
+    };
+    let client = TextClient::with_config(API_KEY.to_string(), config).unwrap();
+    let translator = Translator::new(client, "French").with_batch_chars(8);
+    let translated = translator
+        .translate(&["Hello", "", "Hi", "Goodbye"])
+        .await
+        .unwrap();
+
+    assert_eq!(translated, ["Bonjour", "", "Salut", "Au revoir"]);
+    let requests = server.received_requests();
+    assert_eq!(requests.len(), 2);
+    let body = requests[0].json().unwrap();
+    assert_eq!(body["contents"][0]["parts"][0]["text"], r#"["Hello","Hi"]"#);
+    assert_eq!(
+        body["generationConfig"]["responseMimeType"],
+        "application/json"
+    );
+    assert!(
+        body["systemInstruction"]["parts"][0]["text"]
+            .as_str()
+            .unwrap()
+            .contains("French")
+    );
+}
+
+#[tokio::test]
+async fn test_normalize_rewrites_segments_of_listed_languages() {
+    let server = MockServer::start().await;
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(200, &fixtures::text_response(r#"["Ça coûte 25 €."]"#)),
+    );
+
+    let config = TextClientConfig {
+        base_url: server.uri(),
+        ..Default::default()
+    };
+    let client = TextClient::with_config(API_KEY.to_string(), config).unwrap();
+    let mut transcript: TranscriptResponse =
+        serde_json::from_value(fixtures::transcript()).unwrap();
+    transcript.segments[1].content = "Ça coûte vingt-cinq euros.".to_string();
+    let stats = TranscriptNormalizer::new(client, ["fr-FR"])
+        .normalize(&mut transcript)
+        .await
+        .unwrap();
+
+    assert_eq!((stats.segments, stats.changed), (2, 1));
+    assert_eq!(transcript.segments[1].content, "Ça coûte 25 €.");
+    // English has no locale, so it is neither sent nor changed
+    assert_eq!(transcript.segments[0].content, "Hello there.");
+
+    let requests = server.received_requests();
+    assert_eq!(requests.len(), 1);
+    let body = requests[0].json().unwrap();
+    assert_eq!(
+        body["contents"][0]["parts"][0]["text"],
+        r#"["Ça coûte vingt-cinq euros."]"#
+    );
+    assert!(
+        body["systemInstruction"]["parts"][0]["text"]
+            .as_str()
+            .unwrap()
+            .contains("fr-FR conventions")
+    );
+}
+
+#[tokio::test]
+async fn test_cleanup_rewrites_text_and_keeps_segments() {
+    let server = MockServer::start().await;
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(200, &fixtures::text_response(r#"["Hello there.", ""]"#)),
+    );
+
+    let config = TextClientConfig {
+        base_url: server.uri(),
+        ..Default::default()
+    };
+    let client = TextClient::with_config(API_KEY.to_string(), config).unwrap();
+    let mut transcript: TranscriptResponse =
+        serde_json::from_value(fixtures::transcript()).unwrap();
+    transcript.segments[0].content = "um hello there there".to_string();
+    let stats = TranscriptCleanup::new(client)
+        .clean(&mut transcript)
+        .await
+        .unwrap();
+
+    assert_eq!((stats.segments, stats.changed), (2, 1));
+    assert_eq!(transcript.segments[0].content, "Hello there.");
+    assert_eq!(transcript.segments[0].timestamp.to_string(), "00:00");
+    // An emptied segment keeps its text
+    assert_eq!(transcript.segments[1].content, "Bonjour !");
+    assert_eq!(transcript.segments[1].speaker, "Speaker 2");
+
+    let body = server.received_requests()[0].json().unwrap();
+    assert_eq!(
+        body["contents"][0]["parts"][0]["text"],
+        r#"["um hello there there","Bonjour !"]"#
+    );
+    assert!(
+        body["systemInstruction"]["parts"][0]["text"]
+            .as_str()
+            .unwrap()
+            .contains("filler words")
+    );
+}
+
+#[tokio::test]
+async fn test_merged_transcript_summary_covers_all_parts() {
+    let server = MockServer::start().await;
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(200, &fixtures::text_response(" Two greetings. \n")),
+    );
+
+    let config = TextClientConfig {
+        base_url: server.uri(),
+        ..Default::default()
+    };
+    let client = TextClient::with_config(API_KEY.to_string(), config).unwrap();
+    let transcript: TranscriptResponse = serde_json::from_value(fixtures::transcript()).unwrap();
+    let parts = [
+        MergePart::new(&transcript),
+        MergePart::new(&transcript).with_offset(60),
+    ];
+    let merged = merge_transcripts(&parts, false);
+    let summary = transcript_tool::transcript_merge::summarize(&client, &merged)
+        .await
+        .unwrap();
+    assert_eq!(summary, "Two greetings.");
+
+    let body = server.received_requests()[0].json().unwrap();
+    let lines = body["contents"][0]["parts"][0]["text"].as_str().unwrap();
+    assert!(lines.starts_with("[00:00] Speaker 1: Hello there.\n"));
+    assert!(lines.ends_with("[01:03] Speaker 4: Bonjour ! (English: Hello!)"));
+}
+
+#[tokio::test]
+async fn test_minutes_extractor_sends_schema_and_transcript_lines() {
+    let server = MockServer::start().await;
+    let answer = serde_json::json!({
+        "summary": "A short greeting.",
+        "decisions": [],
+        "action_items": [
+            {"task": "Send the agenda", "owner": "Speaker 2", "due": "", "timestamp": "00:03"}
+        ],
+        "open_questions": [{"question": "Who books the room?", "timestamp": "00:00"}]
+    });
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(200, &fixtures::text_response(&answer.to_string())),
+    );
+
+    let config = TextClientConfig {
+        base_url: server.uri(),
+        ..Default::default()
+    };
+    let client = TextClient::with_config(API_KEY.to_string(), config).unwrap();
+    let transcript: TranscriptResponse = serde_json::from_value(fixtures::transcript()).unwrap();
+    let minutes = MinutesExtractor::new(client)
+        .with_hint("Speaker 2 is Bo")
+        .extract(&transcript)
+        .await
+        .unwrap();
+
+    assert_eq!(minutes.action_items[0].owner.as_deref(), Some("Speaker 2"));
+    assert_eq!(minutes.action_items[0].due, None);
+    assert_eq!(minutes.open_questions.len(), 1);
+
+    let body = server.received_requests()[0].json().unwrap();
+    assert_eq!(
+        body["contents"][0]["parts"][0]["text"],
+        "[00:00] Speaker 1: Hello there.\n[00:03] Speaker 2: Bonjour ! (English: Hello!)"
+    );
+    assert_eq!(
+        body["generationConfig"]["responseSchema"]["required"][2],
+        "action_items"
+    );
+    assert!(
+        body["systemInstruction"]["parts"][0]["text"]
+            .as_str()
+            .unwrap()
+            .ends_with("Context about the meeting: Speaker 2 is Bo")
+    );
+}
+
+#[tokio::test]
+async fn test_vision_client_sends_image_before_prompt() {
+    let server = MockServer::start().await;
+    let answer = serde_json::json!({
+        "caption": "A red door.",
+        "objects": ["door"],
+        "dominant_colors": [{"name": "red", "hex": "c0392b"}],
+        "text": ["No entry"]
+    });
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(200, &fixtures::text_response(&answer.to_string())),
+    );
+
+    let config = TextClientConfig {
+        base_url: server.uri(),
+        ..Default::default()
+    };
+    let client = TextClient::with_config(API_KEY.to_string(), config).unwrap();
+    let image = MediaPart::Inline {
+        mime_type: "image/png".to_string(),
+        data: b"fake png".to_vec(),
+    };
+    let description = VisionClient::new(client).describe(&image).await.unwrap();
+
+    assert_eq!(description.caption, "A red door.");
+    assert_eq!(description.dominant_colors[0].hex, "#C0392B");
+    assert_eq!(description.text, ["No entry"]);
+
+    let body = server.received_requests()[0].json().unwrap();
+    let parts = &body["contents"][0]["parts"];
+    assert_eq!(parts[0]["inline_data"]["mime_type"], "image/png");
+    assert_eq!(parts[0]["inline_data"]["data"], "ZmFrZSBwbmc=");
+    assert!(
+        parts[1]["text"]
+            .as_str()
+            .unwrap()
+            .starts_with("Describe this image")
+    );
+    assert_eq!(
+        body["generationConfig"]["responseSchema"]["required"][2],
+        "dominant_colors"
+    );
+}
+
+#[tokio::test]
+async fn test_vision_client_extracts_text_from_uploaded_pdf() {
+    let server = MockServer::start().await;
+    let answer = serde_json::json!({
+        "blocks": [
+            {"page": 2, "kind": "paragraph", "text": "Total due: 40 EUR", "bounding_box": [700, 80, 740, 600]},
+            {"page": 1, "kind": "heading", "text": "Invoice", "bounding_box": [40, 80, 90, 400]}
+        ]
+    });
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(200, &fixtures::text_response(&answer.to_string())),
+    );
+
+    let config = TextClientConfig {
+        base_url: server.uri(),
+        ..Default::default()
+    };
+    let client = TextClient::with_config(API_KEY.to_string(), config).unwrap();
+    let document = MediaPart::FileUri {
+        mime_type: "application/pdf".to_string(),
+        uri: "https://example.com/files/scan".to_string(),
+    };
+    let result = VisionClient::new(client)
+        .extract_text(&document, true)
+        .await
+        .unwrap();
+
+    assert_eq!(result.blocks[0].text, "Invoice");
+    assert_eq!(result.blocks[1].bounding_box, Some(vec![700, 80, 740, 600]));
+    assert_eq!(
+        result.to_text(),
+        "--- Page 1 ---\n\nInvoice\n\n--- Page 2 ---\n\nTotal due: 40 EUR\n"
+    );
+
+    let body = server.received_requests()[0].json().unwrap();
+    let parts = &body["contents"][0]["parts"];
+    assert_eq!(parts[0]["file_data"]["mime_type"], "application/pdf");
+    assert_eq!(
+        parts[0]["file_data"]["file_uri"],
+        "https://example.com/files/scan"
+    );
+    let block = &body["generationConfig"]["responseSchema"]["properties"]["blocks"]["items"];
+    assert!(block["properties"]["bounding_box"].is_object());
+}
+
+#[tokio::test]
+async fn test_vision_client_summarizes_scenes_from_keyframes() {
+    let server = MockServer::start().await;
+    let answer = serde_json::json!({
+        "scenes": [
+            {"first_frame": 1, "description": "A host in a studio.", "on_screen_text": []},
+            {"first_frame": 3, "description": "A chart of sales.", "on_screen_text": ["Sales 2024"]}
+        ]
+    });
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(200, &fixtures::text_response(&answer.to_string())),
+    );
+
+    let config = TextClientConfig {
+        base_url: server.uri(),
+        ..Default::default()
+    };
+    let client = TextClient::with_config(API_KEY.to_string(), config).unwrap();
+    let frames: Vec<Frame> = [0.0, 8.0, 20.0]
+        .into_iter()
+        .map(|time_secs| Frame {
+            time_secs,
+            image: MediaPart::Inline {
+                mime_type: "image/jpeg".to_string(),
+                data: b"jpeg".to_vec(),
+            },
+        })
+        .collect();
+    let summary = VisionClient::new(client)
+        .summarize_scenes(&frames, Some(45.0), Some("[00:02] Host: Hi.\n"))
+        .await
+        .unwrap();
+
+    assert_eq!(summary.scenes.len(), 2);
+    assert_eq!(summary.scenes[0].end_secs, 20.0);
+    assert_eq!(summary.scenes[1].end_secs, 45.0);
+    assert_eq!(summary.scenes[1].on_screen_text, ["Sales 2024"]);
+
+    let body = server.received_requests()[0].json().unwrap();
+    let parts = body["contents"][0]["parts"].as_array().unwrap();
+    assert_eq!(parts.len(), 4);
+    let prompt = parts[3]["text"].as_str().unwrap();
+    assert!(prompt.contains("3: 00:20"));
+    assert!(prompt.contains("[00:02] Host: Hi."));
+}
+
+#[tokio::test]
+async fn test_vision_client_picks_thumbnail_frame() {
+    let server = MockServer::start().await;
+    for best_frame in [2, 7] {
+        let answer = serde_json::json!({"best_frame": best_frame, "reason": "Clear face."});
+        server.mock(
+            "POST",
+            TRANSCRIBE_PATH,
+            MockResponse::json(200, &fixtures::text_response(&answer.to_string())),
+        );
+    }
+
+    let config = TextClientConfig {
+        base_url: server.uri(),
+        ..Default::default()
+    };
+    let client = TextClient::with_config(API_KEY.to_string(), config).unwrap();
+    let vision = VisionClient::new(client);
+    let frames: Vec<Frame> = (0..3)
+        .map(|i| Frame {
+            time_secs: f64::from(i) * 10.0,
+            image: MediaPart::Inline {
+                mime_type: "image/jpeg".to_string(),
+                data: vec![i as u8],
+            },
+        })
+        .collect();
+
+    let pick = vision
+        .pick_thumbnail(&frames, Some("Why Rust?"))
+        .await
+        .unwrap();
+    assert_eq!(pick.frame, 1);
+    assert_eq!(pick.reason, "Clear face.");
+    let body = server.received_requests()[0].json().unwrap();
+    let prompt = body["contents"][0]["parts"][3]["text"].as_str().unwrap();
+    assert!(prompt.ends_with("The thumbnail's title will be: Why Rust?"));
+
+    let err = vision.pick_thumbnail(&frames, None).await.unwrap_err();
+    assert!(matches!(err, TextError::InvalidResponse(_)));
+}
+
+#[tokio::test]
+async fn test_text_client_counts_tokens_of_prompt_and_media() {
+    let server = MockServer::start().await;
+    server.mock(
+        "POST",
+        "/v1beta/models/gemini-2.5-pro:countTokens",
+        MockResponse::json(
+            200,
+            &serde_json::json!({
+                "totalTokens": 1610,
+                "promptTokensDetails": [
+                    {"modality": "AUDIO", "tokenCount": 1600},
+                    {"modality": "TEXT", "tokenCount": 10}
+                ]
+            }),
+        ),
+    );
+
+    let config = TextClientConfig {
+        base_url: server.uri(),
+        model: TextModel::Pro,
+        ..Default::default()
+    };
+    let client = TextClient::with_config(API_KEY.to_string(), config).unwrap();
+    let audio = MediaPart::Inline {
+        mime_type: "audio/mpeg".to_string(),
+        data: b"mp3".to_vec(),
+    };
+    let count = client
+        .count_tokens("Transcribe this", std::slice::from_ref(&audio))
+        .await
+        .unwrap();
+    assert_eq!(count.total_tokens, 1610);
+    assert_eq!(count.by_modality[0].modality, "AUDIO");
+    assert_eq!(count.by_modality[0].tokens, 1600);
+
+    let body = server.received_requests()[0].json().unwrap();
+    let parts = &body["contents"][0]["parts"];
+    assert_eq!(parts[0]["inline_data"]["mime_type"], "audio/mpeg");
+    assert_eq!(parts[1]["text"], "Transcribe this");
+    assert!(body.get("generationConfig").is_none());
+
+    let err = client.count_tokens("  ", &[]).await.unwrap_err();
+    assert!(matches!(err, TextError::EmptyPrompt));
+}
+
+#[tokio::test]
+async fn test_transcript_qa_reuses_cached_context() {
+    let server = MockServer::start().await;
+    let answer = serde_json::json!({
+        "answer": "They greeted each other.",
+        "citations": [{"timestamp": "00:00", "speaker": "Speaker 1", "quote": "Hello there."}]
+    });
+    server.mock(
+        "POST",
+        "/v1beta/cachedContents",
+        MockResponse::json(200, &fixtures::cached_content("cachedContents/abc")),
+    );
+    server.mock(
+        "GET",
+        "/v1beta/cachedContents/abc",
+        MockResponse::json(200, &fixtures::cached_content("cachedContents/abc")),
+    );
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(200, &fixtures::text_response(&answer.to_string())),
+    );
+
+    let cache_dir =
+        std::env::temp_dir().join(format!("flashecho_mock_qa_cache_{}", std::process::id()));
+    let config = TextClientConfig {
+        base_url: server.uri(),
+        cache: Some(ResponseCache::new(&cache_dir, Duration::from_secs(60))),
+        ..Default::default()
+    };
+    let client = TextClient::with_config(API_KEY.to_string(), config).unwrap();
+    let transcript: TranscriptResponse = serde_json::from_value(fixtures::transcript()).unwrap();
+
+    let mut qa = TranscriptQa::new(client.clone(), &transcript);
+    let cached = qa.cache_context(600).await.unwrap();
+    assert_eq!(cached.token_count, Some(2048));
+    let answered = qa.ask("What happened?").await.unwrap();
+    assert_eq!(answered.citations[0].timestamp, "00:00");
+
+    // A second session on the same transcript looks the context up instead
+    let mut again = TranscriptQa::new(client, &transcript);
+    again.cache_context(600).await.unwrap();
+
+    let requests = server.received_requests();
+    let paths: Vec<_> = requests
+        .iter()
+        .map(|r| format!("{} {}", r.method, r.path))
+        .collect();
+    assert_eq!(
+        paths,
+        [
+            "POST /v1beta/cachedContents",
+            format!("POST {}", TRANSCRIBE_PATH).as_str(),
+            "GET /v1beta/cachedContents/abc"
+        ]
+    );
+    let create = requests[0].json().unwrap();
+    assert_eq!(create["ttl"], "600s");
+    assert!(
+        create["contents"][0]["parts"][0]["text"]
+            .as_str()
+            .unwrap()
+            .contains("[00:03] Speaker 2: Bonjour !")
+    );
+    let question = requests[1].json().unwrap();
+    assert_eq!(question["cachedContent"], "cachedContents/abc");
+    assert_eq!(
+        question["contents"][0]["parts"][0]["text"],
+        "Question: What happened?"
+    );
+    assert!(question.get("systemInstruction").is_none());
+
+    std::fs::remove_dir_all(&cache_dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_search_index_embeds_changed_transcripts_once() {
+    let server = MockServer::start().await;
+    server.mock(
+        "POST",
+        BATCH_EMBED_PATH,
+        MockResponse::json(
+            200,
+            &fixtures::embeddings_response(&[&[1.0, 0.0], &[0.0, 1.0]]),
+        ),
+    );
+    server.mock(
+        "POST",
+        "/v1beta/models/gemini-embedding-001:embedContent",
+        MockResponse::json(
+            200,
+            &serde_json::json!({"embedding": {"values": [0.1, 0.9]}}),
+        ),
+    );
+
+    let dir = std::env::temp_dir().join(format!("flashecho_search_mock_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let transcript = dir.join("talk.json");
+    std::fs::write(&transcript, fixtures::transcript().to_string()).unwrap();
+    let other = dir.join("settings.json");
+    std::fs::write(&other, r#"{"theme": "dark"}"#).unwrap();
+
+    let config = EmbeddingsClientConfig {
+        base_url: server.uri(),
+        ..Default::default()
+    };
+    let client = EmbeddingsClient::with_config(API_KEY.to_string(), config).unwrap();
+    let mut index = SearchIndex::default();
+    let files = [transcript.clone(), other];
+    let stats = index.update(&client, &files).await.unwrap();
+    assert_eq!((stats.indexed, stats.skipped), (1, 1));
+    assert_eq!(index.segment_count(), 2);
+
+    // Unchanged files are not embedded again
+    let stats = index.update(&client, &files).await.unwrap();
+    assert_eq!(stats.unchanged, 1);
+    assert_eq!(server.received_requests().len(), 1);
+
+    let hits = index
+        .query(&client, "a greeting in French", &[], 1)
+        .await
+        .unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].timestamp, "00:03");
+    assert_eq!(hits[0].snippet, "Bonjour !");
+    assert_eq!(hits[0].file, std::fs::canonicalize(&transcript).unwrap());
+
+    let requests = server.received_requests();
+    let batch = requests[0].json().unwrap();
+    assert_eq!(
+        batch["requests"][1]["content"]["parts"][0]["text"],
+        "Bonjour !\nHello!"
+    );
+    let query = requests[1].json().unwrap();
+    assert_eq!(query["taskType"], "RETRIEVAL_QUERY");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_edit_images() {
+    let server = MockServer::start().await;
+    let model = ImageModel::Gemini3Pro;
+    server.mock(
+        "POST",
+        &format!("/v1beta/models/{}:generateContent", model.api_model_name()),
+        MockResponse::json(200, &fixtures::image_response(b"EDITED", "image/jpeg")),
+    );
+
+    let config = ImageEditClientConfig {
+        model,
+        base_url: server.uri(),
+        ..Default::default()
+    };
+    let input = InputImage::from_bytes(b"ORIGINAL".to_vec(), "image/png".to_string());
+    let image = ImageEditClient::with_config(API_KEY.to_string(), config)
+        .unwrap()
+        .edit_images("Make it watercolor", &[input])
+        .await
+        .unwrap();
+
+    assert_eq!(image.data, b"EDITED");
+    assert_eq!(image.mime_type, "image/jpeg");
+
+    let body = server.received_requests()[0].json().unwrap();
+    let parts = body["contents"][0]["parts"].as_array().unwrap();
+    assert!(
+        parts
+            .iter()
+            .any(|p| p["inline_data"]["mime_type"] == "image/png")
+    );
+}
+
+#[tokio::test]
+async fn test_openai_transcription() {
+    let server = MockServer::start().await;
+    server.mock(
+        "POST",
+        "/v1/audio/transcriptions",
+        MockResponse::json(200, &fixtures::openai_transcription()),
+    );
+
+    let config = OpenAiClientConfig {
+        base_url: server.uri(),
+        ..Default::default()
+    };
+    let transcript = OpenAiClient::with_config(API_KEY.to_string(), config)
+        .unwrap()
+        .transcribe_audio(b"audio", "audio/mpeg")
+        .await
+        .unwrap();
+
+    assert_eq!(transcript.segments.len(), 2);
+    assert_eq!(transcript.segments[1].timestamp.to_string(), "00:02");
+    assert_eq!(transcript.segments[1].content, "How are you?");
+
+    let request = &server.received_requests()[0];
+    assert_eq!(request.header("authorization"), Some("Bearer test-key"));
+    assert!(
+        request
+            .header("content-type")
+            .unwrap()
+            .starts_with("multipart/form-data; boundary=")
+    );
+    let body = String::from_utf8_lossy(&request.body);
+    assert!(body.contains("name=\"model\"\r\n\r\nwhisper-1"));
+    assert!(body.contains("filename=\"audio.mp3\""));
+}
+
+#[tokio::test]
+async fn test_clients_share_one_http_client() {
+    let server = MockServer::start().await;
+    let model = ImageModel::Gemini3Pro;
+    server.mock(
+        "POST",
+        &format!("/v1beta/models/{}:generateContent", model.api_model_name()),
+        MockResponse::json(200, &fixtures::image_response(b"IMAGE", "image/png")),
+    );
+    server.mock(
+        "DELETE",
+        "/v1beta/files/abc",
+        MockResponse::json(200, &serde_json::json!({})),
+    );
+
+    let clients = Clients::builder(API_KEY).timeout_secs(10).build().unwrap();
+
+    let imagen = clients.imagen(ImagenClientConfig {
+        model,
+        base_url: server.uri(),
+        ..Default::default()
+    });
+    let editor = clients.image_edit(ImageEditClientConfig {
+        model,
+        base_url: server.uri(),
+        ..Default::default()
+    });
+    let input = InputImage::from_bytes(b"ORIGINAL".to_vec(), "image/png".to_string());
+
+    imagen.generate_image("A sunset").await.unwrap();
+    editor.edit_images("Watercolor", &[input]).await.unwrap();
+    clients
+        .file_api()
+        .with_base_url(server.uri())
+        .delete_file("files/abc")
+        .await
+        .unwrap();
+
+    let requests = server.received_requests();
+    assert_eq!(requests.len(), 3);
+    assert!(
+        requests
+            .iter()
+            .all(|r| r.header("x-goog-api-key") == Some("test-key"))
+    );
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn test_blocking_clients() {
+    use transcript_tool::blocking::{GeminiClientBlocking, ImagenClientBlocking};
+
+    // The mock server needs its own runtime; the blocking clients bring theirs
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let server = rt.block_on(MockServer::start());
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(
+            200,
+            &fixtures::gemini_transcript_response(&fixtures::transcript()),
+        ),
+    );
+    let model = ImageModel::Gemini25Flash;
+    server.mock(
+        "POST",
+        &format!("/v1beta/models/{}:generateContent", model.api_model_name()),
+        MockResponse::json(200, &fixtures::image_response(b"PNGDATA", "image/png")),
+    );
+    server.mock(
+        "DELETE",
+        "/v1beta/files/abc",
+        MockResponse::json(200, &serde_json::json!({})),
+    );
+
+    let gemini = GeminiClientBlocking::with_config(
+        API_KEY.to_string(),
+        GeminiClientConfig {
+            base_url: server.uri(),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let transcript = gemini.transcribe_audio(b"audio", "audio/mpeg").unwrap();
+    assert_eq!(transcript.segments.len(), 2);
+    gemini.file_api().delete_file("files/abc").unwrap();
+
+    let imagen = ImagenClientBlocking::with_config(
+        API_KEY.to_string(),
+        ImagenClientConfig {
+            model,
+            base_url: server.uri(),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(imagen.generate_image("A sunset").unwrap().data, b"PNGDATA");
+
+    assert_eq!(server.received_requests().len(), 3);
+}
+
+#[cfg(feature = "metrics")]
+#[tokio::test]
+async fn test_metrics_recorded_and_served() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let server = MockServer::start().await;
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(503, &fixtures::api_error(503, "unavailable")),
+    );
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(
+            200,
+            &fixtures::gemini_transcript_response(&fixtures::transcript()),
+        ),
+    );
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(429, &fixtures::api_error(429, "quota")),
+    );
+
+    // 503 then success, then a 429 with no retries left
+    gemini_client(&server, 2)
+        .transcribe_audio(b"fake audio", "audio/mpeg")
+        .await
+        .unwrap();
+    gemini_client(&server, 1)
+        .transcribe_audio(b"fake audio", "audio/mpeg")
+        .await
+        .unwrap_err();
+
+    // Other tests share the process-wide registry, so only check presence
+    let text = transcript_tool::metrics::render();
+    for series in [
+        "flashecho_requests_total{client=\"gemini\",outcome=\"success\"}",
+        "flashecho_requests_total{client=\"gemini\",outcome=\"error\"}",
+        "flashecho_retries_total{client=\"gemini\"}",
+        "flashecho_rate_limited_total{client=\"gemini\"}",
+        "flashecho_upload_bytes_total{client=\"gemini\"}",
+        "flashecho_tokens_total{client=\"gemini\",kind=\"prompt\"}",
+        "flashecho_request_duration_seconds_count{client=\"gemini\"}",
+    ] {
+        assert!(text.contains(series), "missing {series} in:\n{text}");
+    }
+
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    tokio::spawn(transcript_tool::metrics::serve(addr));
+    let mut stream = loop {
+        match tokio::net::TcpStream::connect(addr).await {
+            Ok(stream) => break stream,
+            Err(_) => tokio::time::sleep(std::time::Duration::from_millis(10)).await,
+        }
+    };
+    stream
+        .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+    assert!(response.contains("# TYPE flashecho_request_duration_seconds histogram"));
+}
+
+#[tokio::test]
+async fn test_vcr_records_and_replays_interactions() {
+    let upstream = MockServer::start().await;
+    let image_path = format!(
+        "/v1beta/models/{}:generateContent",
+        ImageModel::Gemini25Flash.api_model_name()
+    );
+    upstream.mock(
+        "POST",
+        "/upload/v1beta/files",
+        MockResponse::new(200).with_header(
+            "X-Goog-Upload-URL",
+            &format!("{}/upload/v1beta/files?upload_id=1", upstream.uri()),
+        ),
+    );
+    upstream.mock(
+        "POST",
+        "/upload/v1beta/files",
+        MockResponse::json(200, &fixtures::upload_response("files/abc", "ACTIVE")),
+    );
+    upstream.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(
+            200,
+            &fixtures::gemini_transcript_response(&fixtures::transcript()),
+        ),
+    );
+    upstream.mock(
+        "POST",
+        &image_path,
+        MockResponse::json(200, &fixtures::image_response(b"PNGDATA", "image/png")),
+    );
+
+    async fn run(base_url: String) -> (String, usize, Vec<u8>) {
+        let gemini = GeminiClient::with_config(
+            API_KEY.to_string(),
+            GeminiClientConfig {
+                timeout_secs: 10,
+                base_url: base_url.clone(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let file = FileApiClient::new(gemini.http_client().clone(), API_KEY.to_string())
+            .with_base_url(&base_url)
+            .upload_file(b"audio bytes".as_slice(), "audio/mpeg", "audio.mp3")
+            .await
+            .unwrap();
+        let transcript = gemini
+            .transcribe_audio(b"audio", "audio/mpeg")
+            .await
+            .unwrap();
+        let image = ImagenClient::with_config(
+            API_KEY.to_string(),
+            ImagenClientConfig {
+                model: ImageModel::Gemini25Flash,
+                base_url,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .generate_image("A sunset")
+        .await
+        .unwrap();
+        (file.name, transcript.segments.len(), image.data)
+    }
+
+    let cassette = std::env::temp_dir().join(format!("flashecho_vcr_{}.json", std::process::id()));
+    let recorder = Vcr::record(&cassette, &upstream.uri()).await;
+    let recorded = run(recorder.uri()).await;
+    recorder.finish().unwrap();
+    assert_eq!(upstream.received_requests().len(), 4);
+
+    let saved = std::fs::read_to_string(&cassette).unwrap();
+    assert!(!saved.contains(API_KEY));
+    assert!(saved.contains("{base_url}/upload/v1beta/files?upload_id=1"));
+
+    let player = Vcr::replay(&cassette).await.unwrap();
+    let replayed = run(player.uri()).await;
+    player.finish().unwrap();
+    assert_eq!(replayed, recorded);
+    assert_eq!(replayed.0, "files/abc");
+    assert_eq!(upstream.received_requests().len(), 4);
+
+    std::fs::remove_file(&cassette).unwrap();
+}
+
+/// An HTTP client whose requests are recognizable by a default header
+fn shared_http_client() -> reqwest::Client {
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert("x-shared-client", "1".parse().unwrap());
+    reqwest::Client::builder()
+        .default_headers(headers)
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_s3_storage_signs_put_and_head() {
+    let server = MockServer::start().await;
+    server.mock("PUT", "/bucket/out/talk.srt", MockResponse::new(200));
+    server.mock("HEAD", "/bucket/out/talk.srt", MockResponse::new(404));
+
+    let config = S3Config::new("eu-west-1", "AKIDEXAMPLE", "s3-secret-key")
+        .with_endpoint(server.uri())
+        .with_session_token("s3-session-token");
+    let storage = S3Storage::new("bucket", "out/", config).unwrap();
+    assert!(!format!("{:?}", storage).contains("s3-secret-key"));
+
+    storage.put("talk.srt", b"1\n00:00:00,000").await.unwrap();
+    assert!(!storage.exists("talk.srt").await.unwrap());
+
+    let requests = server.received_requests();
+    let put = &requests[0];
+    assert_eq!(put.body, b"1\n00:00:00,000");
+    assert_eq!(put.header("content-type"), Some("application/x-subrip"));
+    assert_eq!(put.header("x-amz-security-token"), Some("s3-session-token"));
+    let authorization = put.header("authorization").unwrap();
+    assert!(authorization.starts_with("AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/"));
+    assert!(authorization.contains("/eu-west-1/s3/aws4_request"));
+    assert!(
+        authorization
+            .contains("SignedHeaders=host;x-amz-content-sha256;x-amz-date;x-amz-security-token")
+    );
+    assert_eq!(put.header("x-shared-client"), None);
+    assert_eq!(requests[1].method, "HEAD");
+
+    // A shared client, with its proxy, TLS and timeout settings, replaces the
+    // storage's own
+    let storage = storage.with_http_client(shared_http_client());
+    storage.put("talk.srt", b"1\n00:00:00,000").await.unwrap();
+    assert_eq!(
+        server.received_requests()[2].header("x-shared-client"),
+        Some("1")
+    );
+}
+
+#[tokio::test]
+async fn test_gcs_storage_uploads_with_bearer_token() {
+    let server = MockServer::start().await;
+    server.mock(
+        "POST",
+        "/upload/storage/v1/b/bucket/o",
+        MockResponse::json(200, &serde_json::json!({"name": "out/a.png"})),
+    );
+    server.mock(
+        "GET",
+        "/storage/v1/b/bucket/o/out%2Fa.png",
+        MockResponse::json(200, &serde_json::json!({"name": "out/a.png"})),
+    );
+    server.mock(
+        "POST",
+        "/upload/storage/v1/b/bucket/o",
+        MockResponse::json(403, &fixtures::api_error(403, "denied")),
+    );
+
+    let storage = GcsStorage::new("bucket", "out/", GcsAuth::AccessToken("gcs-token".into()))
+        .unwrap()
+        .with_base_url(server.uri())
+        .with_http_client(shared_http_client());
+    assert!(!format!("{:?}", storage).contains("gcs-token"));
+
+    storage.put("a.png", b"png").await.unwrap();
+    assert!(storage.exists("a.png").await.unwrap());
+    let err = storage.put("a.png", b"png").await.unwrap_err();
+    assert!(err.to_string().contains("gs://bucket/out/a.png"));
+
+    let requests = server.received_requests();
+    assert_eq!(
+        requests[0].query.as_deref(),
+        Some("uploadType=media&name=out%2Fa.png")
+    );
+    assert_eq!(
+        requests[0].header("authorization"),
+        Some("Bearer gcs-token")
+    );
+    assert_eq!(requests[0].header("content-type"), Some("image/png"));
+    assert_eq!(requests[0].body, b"png");
+    assert!(
+        requests
+            .iter()
+            .all(|request| request.header("x-shared-client") == Some("1"))
+    );
+}
+
+#[cfg(feature = "server")]
+#[tokio::test]
+async fn test_server_runs_transcription_and_image_jobs() {
+    use transcript_tool::{Backends, Server, ServerConfig};
+
+    let server = MockServer::start().await;
+    server.mock(
+        "POST",
+        TRANSCRIBE_PATH,
+        MockResponse::json(
+            200,
+            &fixtures::gemini_transcript_response(&fixtures::transcript()),
+        ),
+    );
+    for data in [b"PNGDATA", b"PNGMORE"] {
+        server.mock(
+            "POST",
+            "/v1beta/models/gemini-2.5-flash-image:generateContent",
+            MockResponse::json(200, &fixtures::image_response(data, "image/png")),
+        );
+    }
+
+    let cache_dir =
+        std::env::temp_dir().join(format!("flashecho_server_cache_{}", std::process::id()));
+    let backends = Backends {
+        gemini: gemini_client(&server, 1),
+        file_api: file_api_client(&server),
+        imagen: ImagenClient::with_config(
+            API_KEY.to_string(),
+            ImagenClientConfig {
+                base_url: server.uri(),
+                cache: Some(ResponseCache::new(&cache_dir, Duration::from_secs(60))),
+                ..Default::default()
+            },
+        )
+        .unwrap(),
+    };
+    let api = Server::bind(
+        "127.0.0.1:0".parse().unwrap(),
+        backends,
+        ServerConfig::default(),
+    )
+    .await
+    .unwrap();
+    let base = format!("http://{}", api.local_addr().unwrap());
+    let handle = tokio::spawn(api.run());
+    let http = reqwest::Client::new();
+
+    let wait_for = |id: String| {
+        let http = http.clone();
+        let url = format!("{}/jobs/{}", base, id);
+        async move {
+            for _ in 0..100 {
+                let job: serde_json::Value =
+                    http.get(&url).send().await.unwrap().json().await.unwrap();
+                if job["status"] == "done" || job["status"] == "failed" {
+                    return job;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+            panic!("job {} didn't finish", id);
+        }
+    };
+
+    let body = b"--b0undary\r\n\
+Content-Disposition: form-data; name=\"format\"\r\n\r\n\
+srt\r\n\
+--b0undary\r\n\
+Content-Disposition: form-data; name=\"file\"; filename=\"talk.mp3\"\r\n\
+Content-Type: application/octet-stream\r\n\r\n\
+ID3audio\r\n\
+--b0undary--\r\n";
+    let response = http
+        .post(format!("{}/transcribe", base))
+        .header("Content-Type", "multipart/form-data; boundary=b0undary")
+        .body(body.to_vec())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 202);
+    let submitted: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(submitted["status"], "queued");
+    let job = wait_for(submitted["id"].as_str().unwrap().to_string()).await;
+    assert_eq!(job["kind"], "transcribe");
+    assert_eq!(job["status"], "done");
+    assert_eq!(job["result"]["format"], "srt");
+    assert!(
+        job["result"]["content"]
+            .as_str()
+            .unwrap()
+            .contains("Hello there.")
+    );
+    assert_eq!(
+        job["result"]["transcript"]["segments"][0]["speaker"],
+        "Speaker 1"
+    );
+    let request = server.received_requests()[0].json().unwrap();
+    assert_eq!(
+        request["contents"][0]["parts"][1]["inline_data"]["mime_type"],
+        "audio/mpeg"
+    );
+
+    let response = http
+        .post(format!("{}/images", base))
+        .json(&serde_json::json!({"prompt": "A sunset", "count": 2}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 202);
+    let location = response.headers()["location"].to_str().unwrap().to_string();
+    let submitted: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(
+        location,
+        format!("/jobs/{}", submitted["id"].as_str().unwrap())
+    );
+    let job = wait_for(submitted["id"].as_str().unwrap().to_string()).await;
+    assert_eq!(job["kind"], "images");
+    assert_eq!(job["status"], "done");
+    let images = job["result"]["images"].as_array().unwrap();
+    assert_eq!(images.len(), 2);
+    assert_eq!(images[0]["mime_type"], "image/png");
+    assert_eq!(images[0]["data"], "UE5HREFUQQ==");
+    assert_eq!(images[0]["provenance"]["watermark"], "SynthID");
+    // Each image is its own generation, not a cached copy of the first
+    assert_eq!(images[1]["data"], "UE5HTU9SRQ==");
+    assert!(!cache_dir.exists());
+
+    let response = http
+        .post(format!("{}/images", base))
+        .json(&serde_json::json!({"prompt": "A sunset", "aspect_ratio": "7:3"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+    let response = http
+        .get(format!("{}/jobs/unknown", base))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+
+    #[cfg(feature = "metrics")]
+    {
+        let response = http.get(format!("{}/metrics", base)).send().await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert!(
+            response
+                .text()
+                .await
+                .unwrap()
+                .contains("flashecho_requests_total{client=\"gemini\"")
+        );
+    }
+
+    handle.abort();
+}